@@ -94,6 +94,7 @@ impl Clay {
             &mut encoder,
             &device,
             true,
+            true,
             "../resources/models/dragon.obj",
             None,
         )?
@@ -317,14 +318,10 @@ fn main() {
         if input_helper.update(&event) {
             match cone.redraw(&input_helper, &window, control_flow) {
                 Ok(_) => (),
-                Err(e) => {
-                    if let Some(e) = e.downcast_ref::<gpu::Error>() {
-                        if e.can_continue() {
-                            return;
-                        }
-                    }
-                    panic!("{}", e);
-                }
+                Err(e) => match e.downcast_ref::<gpu::Error>().map(gpu::Error::recovery) {
+                    Some(gpu::Recovery::RecreateSwapchain) => return,
+                    _ => panic!("{}", e),
+                },
             }
 
             if input_helper.quit() {
@@ -22,6 +22,11 @@ use winit_input_helper::WinitInputHelper;
 const WIDTH: u32 = 850;
 const HEIGHT: u32 = 850;
 
+/// use temporal anti-aliasing instead of smaa
+const TAA: bool = true;
+/// the number of frames before the camera jitter repeats
+const TAA_SAMPLES: u32 = 8;
+
 #[allow(dead_code)]
 pub struct Cone {
     _instance: gpu::Instance,
@@ -39,6 +44,7 @@ pub struct Cone {
     point_renderer: cone::PointLightRenderer,
     ao_renderer: cone::AORenderer,
     smaa_renderer: ddd::utils::SMAARenderer,
+    taa_renderer: cone::TAARenderer,
     display_renderer: ddd::utils::CopyRenderer,
     solid_renderer: clay::SolidRenderer,
     bloom_renderer: cone::BloomRenderer,
@@ -73,6 +79,7 @@ pub struct Cone {
     env: cone::EnvironmentMap,
 
     update_mouse: bool,
+    frame: u32,
     prev_time: std::time::Instant,
     start_time: std::time::Instant,
 
@@ -110,6 +117,7 @@ impl Cone {
             &mut encoder,
             &device,
             false,
+            true,
             "../resources/models/dragon_small.obj",
             if debug {
                 Some("mesh_small")
@@ -123,6 +131,7 @@ impl Cone {
             &mut encoder,
             &device,
             true,
+            true,
             "../resources/models/dragon.obj",
             if debug {
                 Some("mesh")
@@ -175,6 +184,10 @@ impl Cone {
         let smaa_renderer =
             ddd::utils::SMAARenderer::new(&mut encoder, &device, ddd::utils::SMAAState::LOW, None, if debug { Some("smaa") } else { None })?;
 
+        let taa_renderer = cone::TAARenderer::new(&device, None, if debug { Some("taa") } else { None })?;
+        // create the history now so that clearing it isn't recorded into the re-used offscreen commands
+        taa_renderer.targets(&mut encoder, &device, WIDTH, HEIGHT)?;
+
         let env_renderer = cone::EnvironmentRenderer::new(
             &mut encoder,
             &device,
@@ -286,24 +299,24 @@ impl Cone {
         let leather_albedo_image = image::open("../resources/images/leather/color.jpg")
             .unwrap()
             .to_rgba8();
-        let leather_albedo = gfx::Texture2D::from_image_buffer(
+        let leather_albedo = gfx::Texture2D::from_image_buffer_mipmapped(
             &mut encoder,
             &device,
             &leather_albedo_image,
             gpu::TextureUsage::SAMPLED,
-            3,
+            gfx::ColorSpace::Srgb,
             if debug { Some("leather albedo") } else { None },
         )?;
 
         let leather_roughness_image = image::open("../resources/images/leather/roughness.jpg")
             .unwrap()
             .to_luma8();
-        let leather_roughness = gfx::Texture2D::from_image_buffer(
+        let leather_roughness = gfx::Texture2D::from_image_buffer_mipmapped(
             &mut encoder,
             &device,
             &leather_roughness_image,
             gpu::TextureUsage::SAMPLED,
-            1,
+            gfx::ColorSpace::Linear,
             if debug {
                 Some("leather roughness")
             } else {
@@ -338,12 +351,12 @@ impl Cone {
         let metal_albedo_image = image::open("../resources/images/metal/color.jpg")
             .unwrap()
             .to_rgba8();
-        let metal_albedo = gfx::Texture2D::from_image_buffer(
+        let metal_albedo = gfx::Texture2D::from_image_buffer_mipmapped(
             &mut encoder,
             &device,
             &metal_albedo_image,
             gpu::TextureUsage::SAMPLED,
-            3,
+            gfx::ColorSpace::Srgb,
             if debug {
                 Some("metal albedo")
             } else {
@@ -354,12 +367,12 @@ impl Cone {
         let metal_roughness_image = image::open("../resources/images/metal/roughness.jpg")
             .unwrap()
             .to_luma8();
-        let metal_roughness = gfx::Texture2D::from_image_buffer(
+        let metal_roughness = gfx::Texture2D::from_image_buffer_mipmapped(
             &mut encoder,
             &device,
             &metal_roughness_image,
             gpu::TextureUsage::SAMPLED,
-            1,
+            gfx::ColorSpace::Linear,
             if debug {
                 Some("metal roughness")
             } else {
@@ -370,12 +383,12 @@ impl Cone {
         let metal_metallic_image = image::open("../resources/images/metal/metallic.jpg")
             .unwrap()
             .to_luma8();
-        let metal_metallic = gfx::Texture2D::from_image_buffer(
+        let metal_metallic = gfx::Texture2D::from_image_buffer_mipmapped(
             &mut encoder,
             &device,
             &metal_metallic_image,
             gpu::TextureUsage::SAMPLED,
-            1,
+            gfx::ColorSpace::Linear,
             if debug {
                 Some("metal metallic")
             } else {
@@ -420,12 +433,12 @@ impl Cone {
         let wood_albedo_image = image::open("../resources/images/wood/color.jpg")
             .unwrap()
             .to_rgba8();
-        let wood_albedo = gfx::Texture2D::from_image_buffer(
+        let wood_albedo = gfx::Texture2D::from_image_buffer_mipmapped(
             &mut encoder,
             &device,
             &wood_albedo_image,
             gpu::TextureUsage::SAMPLED,
-            3,
+            gfx::ColorSpace::Srgb,
             if debug {
                 Some("wood albedo")
             } else {
@@ -436,12 +449,12 @@ impl Cone {
         let wood_roughness_image = image::open("../resources/images/wood/roughness.jpg")
             .unwrap()
             .to_luma8();
-        let wood_roughness = gfx::Texture2D::from_image_buffer(
+        let wood_roughness = gfx::Texture2D::from_image_buffer_mipmapped(
             &mut encoder,
             &device,
             &wood_roughness_image,
             gpu::TextureUsage::SAMPLED,
-            1,
+            gfx::ColorSpace::Linear,
             if debug {
                 Some("wood roughness")
             } else {
@@ -452,12 +465,12 @@ impl Cone {
         let wood_normal_image = image::open("../resources/images/wood/normal.jpg")
             .unwrap()
             .to_rgba8();
-        let wood_normal = gfx::Texture2D::from_image_buffer(
+        let wood_normal = gfx::Texture2D::from_image_buffer_mipmapped(
             &mut encoder,
             &device,
             &wood_normal_image,
             gpu::TextureUsage::SAMPLED,
-            1,
+            gfx::ColorSpace::Linear,
             if debug {
                 Some("wood normal")
             } else {
@@ -562,6 +575,7 @@ impl Cone {
             buffer,
             env_renderer,
             smaa_renderer,
+            taa_renderer,
             point_renderer,
             ao_renderer,
             display_renderer,
@@ -599,6 +613,7 @@ impl Cone {
             shadow_renderer,
 
             update_mouse: true,
+            frame: 0,
             prev_time: std::time::Instant::now(),
             start_time: std::time::Instant::now(),
 
@@ -795,20 +810,33 @@ impl Cone {
         encoder.write_timestamp_ref(&self.query1, 13, gpu::PipelineStage::BottomOfPipe);
         encoder.write_timestamp_ref(&self.query1, 14, gpu::PipelineStage::TopOfPipe);
 
-        self.smaa_renderer.pass(
-            &mut encoder,
-            &self.device,
-            &self.buffer.get("output").unwrap().view,
-            None,
-            gfx::Attachment {
-                raw: gpu::Attachment::View(
-                    Cow::Borrowed(&self.antialiased.view),
-                    gpu::ClearValue::ColorFloat([0.0; 4]),
-                ),
-                load: gpu::LoadOp::Clear,
-                store: gpu::StoreOp::Store,
-            },
-        )?;
+        let antialiased = gfx::Attachment {
+            raw: gpu::Attachment::View(
+                Cow::Borrowed(&self.antialiased.view),
+                gpu::ClearValue::ColorFloat([0.0; 4]),
+            ),
+            load: gpu::LoadOp::Clear,
+            store: gpu::StoreOp::Store,
+        };
+
+        if TAA {
+            self.taa_renderer.pass(
+                &mut encoder,
+                &self.device,
+                &self.buffer,
+                &self.buffer.get("output").unwrap().view,
+                antialiased,
+                0.9,
+            )?;
+        } else {
+            self.smaa_renderer.pass(
+                &mut encoder,
+                &self.device,
+                &self.buffer.get("output").unwrap().view,
+                None,
+                antialiased,
+            )?;
+        }
 
         encoder.write_timestamp_ref(&self.query1, 15, gpu::PipelineStage::BottomOfPipe);
 
@@ -918,8 +946,24 @@ impl Cone {
         self.light_instance
             .update_gpu(&mut encoder, &light_instances);
 
-        self.controller
-            .update_cam_owned(&mut encoder, &mut self.camera);
+        if TAA {
+            // jitter the projection a different amount each frame and keep the last frame's camera for velocity
+            let data = self
+                .controller
+                .cam_data()
+                .jittered(
+                    ddd::utils::CameraData::halton_jitter(self.frame, TAA_SAMPLES),
+                    self.buffer.width(),
+                    self.buffer.height(),
+                )
+                .with_previous(&self.camera.data);
+            self.camera.data = data;
+            self.camera.update_gpu_owned(&mut encoder);
+            self.frame += 1;
+        } else {
+            self.controller
+                .update_cam_owned(&mut encoder, &mut self.camera);
+        }
 
         encoder.write_timestamp_ref(&self.query2, 0, gpu::PipelineStage::TopOfPipe);
 
@@ -967,7 +1011,7 @@ impl Cone {
             "light   : ",
             "sky+fwd : ",
             "bloom   : ",
-            "smaa    : ",
+            "aa      : ",
         ];
         for (duration, name) in durations.iter().zip(names) {
             println!("{}{:?}", name, duration);
@@ -1004,14 +1048,10 @@ fn main() {
         if input_helper.update(&event) {
             match cone.redraw(&input_helper, &window, control_flow) {
                 Ok(_) => (),
-                Err(e) => {
-                    if let Some(e) = e.downcast_ref::<gpu::Error>() {
-                        if e.can_continue() {
-                            return;
-                        }
-                    }
-                    panic!("{}", e);
-                }
+                Err(e) => match e.downcast_ref::<gpu::Error>().map(gpu::Error::recovery) {
+                    Some(gpu::Recovery::RecreateSwapchain) => return,
+                    _ => panic!("{}", e),
+                },
             }
 
             if input_helper.quit() {
@@ -1,13 +1,17 @@
 files='
+depth.frag
+depth.vert
 flat.frag
 flat.vert
 smooth.frag
 smooth.vert
 solid.frag
 solid.vert
+vectors.frag
+vectors.vert
 '
 
 for file in $files
 do
     glslc $file -o $file.spv
-done
\ No newline at end of file
+done
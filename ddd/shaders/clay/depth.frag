@@ -0,0 +1,19 @@
+#version 450
+
+layout(location = 0) out vec4 out_color;
+
+layout(location = 0) in float in_depth;
+
+layout(push_constant) uniform PushColor {
+    vec4 u_color;
+};
+
+// blue when near through green to red when far
+vec3 heat(float t) {
+    t = clamp(t, 0.0, 1.0);
+    return clamp(vec3(2.0 * t - 1.0, 1.0 - abs(2.0 * t - 1.0), 1.0 - 2.0 * t), 0.0, 1.0);
+}
+
+void main() {
+    out_color = vec4(heat(in_depth), u_color.a);
+}
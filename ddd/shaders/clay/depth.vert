@@ -0,0 +1,23 @@
+#version 450
+
+layout(location = 0) in vec3 in_pos;
+
+layout(location = 0) out float out_depth;
+
+layout(set = 0, binding = 0) uniform Camera {
+    mat4 projection;
+    mat4 view;
+    vec4 position;
+    float z_far;
+} u_camera;
+
+layout(set = 1, binding = 0) buffer Instance {
+    mat4 models[];
+} u_instances;
+
+void main() {
+    mat4 model = u_instances.models[gl_InstanceIndex];
+    vec4 view_pos = u_camera.view * model * vec4(in_pos, 1.0);
+    gl_Position = u_camera.projection * view_pos;
+    out_depth = -view_pos.z / u_camera.z_far;
+}
@@ -0,0 +1,51 @@
+#version 450
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform Camera {
+    mat4 projection;
+    mat4 view;
+    vec4 position;
+    float z_far;
+} u_camera;
+
+layout(set = 1, binding = 0) buffer Instance {
+    mat4 models[];
+} u_instances;
+
+// the vertex buffer of the mesh read as floats
+layout(set = 1, binding = 1) readonly buffer Vertices {
+    float data[];
+} u_vertices;
+
+layout(push_constant) uniform PushData {
+    vec4 u_color;
+    // size of the vertex in floats
+    uint stride;
+    // offset of the position in floats
+    uint pos_offset;
+    // offset of the vector to draw in floats
+    uint vec_offset;
+    // length of the drawn vectors
+    float len;
+};
+
+vec3 read_vec3(uint idx) {
+    return vec3(u_vertices.data[idx], u_vertices.data[idx + 1], u_vertices.data[idx + 2]);
+}
+
+void main() {
+    uint vertex = uint(gl_VertexIndex) / 2;
+    uint end = uint(gl_VertexIndex) % 2;
+
+    mat4 model = u_instances.models[gl_InstanceIndex];
+    vec3 pos = read_vec3(vertex * stride + pos_offset);
+    vec3 dir = normalize(mat3(model) * read_vec3(vertex * stride + vec_offset));
+
+    vec4 world_pos = model * vec4(pos, 1.0);
+    world_pos.xyz += dir * len * float(end);
+    gl_Position = u_camera.projection * u_camera.view * world_pos;
+
+    // fade the color towards the tip of the arrow
+    out_color = vec4(u_color.rgb * (1.0 - 0.5 * float(end)), u_color.a);
+}
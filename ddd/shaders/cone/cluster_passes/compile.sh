@@ -0,0 +1,9 @@
+files='
+cluster_assign.comp
+cluster_base.comp
+'
+
+for file in $files
+do
+    glslc $file -o $file.spv
+done
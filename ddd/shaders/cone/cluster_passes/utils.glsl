@@ -0,0 +1,21 @@
+#include "../point_light_passes/light.glsl"
+
+struct ClusterGrid {
+    uint width;
+    uint height;
+    uint slices;
+    uint tile_size;
+    uint max_lights;
+    float z_near;
+};
+
+// index of the depth slice containing the view space depth, slices are spaced exponentially
+uint cluster_slice(float depth, float z_near, float z_far, uint slices) {
+    float slice = log(max(depth, z_near) / z_near) / log(z_far / z_near) * float(slices);
+    return min(uint(max(slice, 0.0)), slices - 1u);
+}
+
+// view space depth of the near side of a slice
+float slice_depth(uint slice, float z_near, float z_far, uint slices) {
+    return z_near * pow(z_far / z_near, float(slice) / float(slices));
+}
@@ -0,0 +1,9 @@
+files='
+cull.comp
+depth_pyramid.frag
+'
+
+for file in $files
+do
+    glslc $file -o $file.spv
+done
@@ -0,0 +1,41 @@
+#version 450
+
+layout(location = 0) in vec2 in_uv;
+
+layout(location = 0) out float out_depth;
+
+layout(set = 0, binding = 0) uniform texture2D u_depth;
+layout(set = 0, binding = 1) uniform sampler u_sampler;
+
+layout(push_constant) uniform Data {
+    // if the source is the depth buffer at the same size rather than the mip above
+    int first;
+};
+
+void main() {
+    ivec2 pixel = ivec2(gl_FragCoord.xy);
+
+    if (first == 1) {
+        out_depth = texelFetch(sampler2D(u_depth, u_sampler), pixel, 0).x;
+        return;
+    }
+
+    ivec2 size = textureSize(sampler2D(u_depth, u_sampler), 0);
+    ivec2 src = pixel * 2;
+
+    // furthest depth of the texels covered, including the extra row / column when the source is odd
+    float depth = 0.0;
+    for (int x = 0; x < 3; x++) {
+        for (int y = 0; y < 3; y++) {
+            ivec2 coord = src + ivec2(x, y);
+            bool inside = (x < 2 || (x == 2 && size.x % 2 == 1 && pixel.x == size.x / 2 - 1))
+                && (y < 2 || (y == 2 && size.y % 2 == 1 && pixel.y == size.y / 2 - 1));
+            if (inside) {
+                coord = min(coord, size - 1);
+                depth = max(depth, texelFetch(sampler2D(u_depth, u_sampler), coord, 0).x);
+            }
+        }
+    }
+
+    out_depth = depth;
+}
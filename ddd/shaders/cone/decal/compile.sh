@@ -0,0 +1,9 @@
+files='
+decal.vert
+decal.frag
+'
+
+for file in $files
+do
+    glslc $file -o $file.spv
+done
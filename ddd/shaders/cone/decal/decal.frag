@@ -0,0 +1,85 @@
+#version 450
+
+layout(location = 0) flat in vec4 in_inv_view_projection_0;
+layout(location = 1) flat in vec4 in_inv_view_projection_1;
+layout(location = 2) flat in vec4 in_inv_view_projection_2;
+layout(location = 3) flat in vec4 in_inv_view_projection_3;
+
+layout(location = 0) out vec4 out_albedo;
+layout(location = 1) out vec4 out_normal;
+layout(location = 2) out vec4 out_roughness;
+
+layout(set = 1, binding = 0) uniform DecalData {
+    mat4 model;
+    mat4 inv_model;
+    vec4 albedo;
+    float roughness;
+    float normal_strength;
+    float roughness_strength;
+    float angle_fade;
+} u_decal;
+
+layout(set = 2, binding = 0) uniform texture2D u_depth;
+layout(set = 2, binding = 1) uniform sampler u_sampler;
+
+layout(set = 3, binding = 0) uniform texture2D u_albedo;
+layout(set = 3, binding = 1) uniform texture2D u_normal;
+layout(set = 3, binding = 2) uniform texture2D u_roughness;
+
+layout(push_constant) uniform Data {
+    float width;
+    float height;
+    int has_normal;
+    int has_roughness;
+};
+
+void main() {
+    mat4 inv_view_projection = mat4(
+        in_inv_view_projection_0,
+        in_inv_view_projection_1,
+        in_inv_view_projection_2,
+        in_inv_view_projection_3
+    );
+
+    // reconstruct the world position of the geometry under this pixel from depth
+    vec2 screen_uv = gl_FragCoord.xy / vec2(width, height);
+    float depth = texelFetch(sampler2D(u_depth, u_sampler), ivec2(gl_FragCoord.xy), 0).x;
+    vec4 world = inv_view_projection * vec4(screen_uv * 2.0 - 1.0, depth, 1.0);
+    vec3 world_pos = world.xyz / world.w;
+
+    // only geometry inside the decal's box is affected
+    vec3 local = (u_decal.inv_model * vec4(world_pos, 1.0)).xyz;
+    if (abs(local.x) > 0.5 || abs(local.y) > 0.5 || abs(local.z) > 0.5) {
+        discard;
+    }
+    vec2 uv = vec2(local.x + 0.5, 0.5 - local.y);
+
+    // the decal projects along its local z axis, fade out on surfaces that face away from it
+    vec3 tangent = normalize(u_decal.model[0].xyz);
+    vec3 bitangent = normalize(u_decal.model[1].xyz);
+    vec3 axis = normalize(u_decal.model[2].xyz);
+    vec3 surface_normal = normalize(cross(dFdy(world_pos), dFdx(world_pos)));
+    float facing = abs(dot(surface_normal, axis));
+    float fade = smoothstep(u_decal.angle_fade, 1.0, facing);
+
+    vec4 albedo = texture(sampler2D(u_albedo, u_sampler), uv) * u_decal.albedo;
+    float alpha = albedo.a * fade;
+    if (alpha <= 0.0) {
+        discard;
+    }
+    out_albedo = vec4(albedo.rgb, alpha);
+
+    if (has_normal == 1) {
+        vec3 n = texture(sampler2D(u_normal, u_sampler), uv).xyz * 2.0 - 1.0;
+        vec3 normal = normalize(tangent * n.x + bitangent * n.y + axis * n.z);
+        out_normal = vec4(normal, alpha * u_decal.normal_strength);
+    } else {
+        out_normal = vec4(0.0);
+    }
+
+    float roughness = u_decal.roughness;
+    if (has_roughness == 1) {
+        roughness *= texture(sampler2D(u_roughness, u_sampler), uv).x;
+    }
+    out_roughness = vec4(vec3(roughness), alpha * u_decal.roughness_strength);
+}
@@ -0,0 +1,50 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform CameraData {
+    mat4 projection;
+    mat4 view;
+    vec4 position;
+    float z_far;
+} u_camera;
+
+layout(set = 1, binding = 0) uniform DecalData {
+    mat4 model;
+    mat4 inv_model;
+    vec4 albedo;
+    float roughness;
+    float normal_strength;
+    float roughness_strength;
+    float angle_fade;
+} u_decal;
+
+// columns of the inverse view projection matrix
+layout(location = 0) flat out vec4 out_inv_view_projection_0;
+layout(location = 1) flat out vec4 out_inv_view_projection_1;
+layout(location = 2) flat out vec4 out_inv_view_projection_2;
+layout(location = 3) flat out vec4 out_inv_view_projection_3;
+
+// the 36 vertices of the triangles of a unit cube
+const int INDICES[36] = int[36](
+    0, 2, 1, 1, 2, 3,
+    4, 5, 6, 5, 7, 6,
+    0, 1, 4, 1, 5, 4,
+    2, 6, 3, 3, 6, 7,
+    0, 4, 2, 2, 4, 6,
+    1, 3, 5, 3, 7, 5
+);
+
+void main() {
+    int corner = INDICES[gl_VertexIndex];
+    vec3 pos = vec3(
+        (corner & 1) == 0 ? -0.5 : 0.5,
+        (corner & 2) == 0 ? -0.5 : 0.5,
+        (corner & 4) == 0 ? -0.5 : 0.5
+    );
+    gl_Position = u_camera.projection * u_camera.view * u_decal.model * vec4(pos, 1.0);
+
+    mat4 inv_view_projection = inverse(u_camera.projection * u_camera.view);
+    out_inv_view_projection_0 = inv_view_projection[0];
+    out_inv_view_projection_1 = inv_view_projection[1];
+    out_inv_view_projection_2 = inv_view_projection[2];
+    out_inv_view_projection_3 = inv_view_projection[3];
+}
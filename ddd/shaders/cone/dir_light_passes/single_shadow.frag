@@ -0,0 +1,118 @@
+#version 450
+
+#include "utils.glsl"
+
+layout(location = 0) in vec2 in_uv;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform texture2D u_position;
+layout(set = 0, binding = 1) uniform texture2D u_normal;
+layout(set = 0, binding = 2) uniform texture2D u_albedo;
+layout(set = 0, binding = 3) uniform texture2D u_roughness;
+layout(set = 0, binding = 4) uniform texture2D u_metallic;
+layout(set = 0, binding = 5) uniform sampler u_sampler;
+
+layout(set = 1, binding = 0) uniform CameraData {
+    mat4 projection;
+    mat4 view;
+    vec4 position;
+    float z_far;
+} u_camera;
+
+layout(set = 2, binding = 0) uniform LightData {
+    DirLightData light;
+} u_light_data;
+
+layout(set = 3, binding = 0) uniform ShadowData {
+    CascadedDepthData depth;
+} u_shadow_data;
+
+layout(set = 3, binding = 1) uniform texture2DArray u_shadow_map;
+layout(set = 3, binding = 2) uniform sampler u_shadow_sampler;
+
+layout(push_constant) uniform PushData {
+    float strength;
+    uint samples;
+    float width;
+    float height;
+};
+
+// fraction of samples in the cascade that are occluded
+float cascade_shadow_calc(uint cascade, vec3 world_pos, float bias) {
+    vec4 light_pos = u_shadow_data.depth.matrices[cascade] * vec4(world_pos, 1.0);
+    vec3 proj = light_pos.xyz / light_pos.w;
+    if (proj.z >= 1.0) {
+        return 0.0;
+    }
+    vec2 uv = proj.xy * 0.5 + vec2(0.5);
+
+    vec2 texel = 1.0 / vec2(textureSize(sampler2DArray(u_shadow_map, u_shadow_sampler), 0).xy);
+    float radius = u_shadow_data.depth.strength;
+    float shadow = 0.0;
+    for (uint i = 0u; i < samples; i++) {
+        vec2 offset = poisson_disk[i] * radius * texel;
+        float closest = texture(sampler2DArray(u_shadow_map, u_shadow_sampler), vec3(uv + offset, float(cascade))).r;
+        if (proj.z - bias > closest) {
+            shadow += 1.0;
+        }
+    }
+
+    return shadow / float(samples);
+}
+
+void main() {
+    vec3 world_pos = texture(sampler2D(u_position, u_sampler), in_uv).xyz;
+    vec3 normal = texture(sampler2D(u_normal, u_sampler), in_uv).xyz;
+    vec4 albedo = texture(sampler2D(u_albedo, u_sampler), in_uv);
+    float roughness = texture(sampler2D(u_roughness, u_sampler), in_uv).x;
+    float metallic = texture(sampler2D(u_metallic, u_sampler), in_uv).x;
+
+    DirLightData light = u_light_data.light;
+    vec3 to_light = -normalize(vec3(light.direction_x, light.direction_y, light.direction_z));
+    float bias = max(u_shadow_data.depth.bias * (1.0 - dot(normal, to_light)), u_shadow_data.depth.bias);
+
+    // pick the first cascade that reaches past the pixel
+    float view_depth = -(u_camera.view * vec4(world_pos, 1.0)).z;
+    uint count = u_shadow_data.depth.count;
+    uint cascade = count;
+    for (uint i = 0u; i < count; i++) {
+        if (view_depth < cascade_split(u_shadow_data.depth, i)) {
+            cascade = i;
+            break;
+        }
+    }
+
+    float shadow = 0.0;
+    if (cascade < count) {
+        shadow = cascade_shadow_calc(cascade, world_pos, bias);
+
+        // blend into the next cascade over the end of this one to hide the seam
+        float start = 0.0;
+        if (cascade > 0u) {
+            start = cascade_split(u_shadow_data.depth, cascade - 1u);
+        }
+        float end = cascade_split(u_shadow_data.depth, cascade);
+        float fade = (end - view_depth) / max((end - start) * u_shadow_data.depth.blend, 0.0001);
+        if (fade < 1.0 && cascade + 1u < count) {
+            float next = cascade_shadow_calc(cascade + 1u, world_pos, bias);
+            shadow = mix(next, shadow, fade);
+        }
+    }
+
+    if (shadow == 1.0) {
+        out_color = vec4(vec3(0.0), albedo.a);
+        return;
+    }
+
+    vec3 lighting = dir_light_calc(
+        light,
+        u_camera.position.xyz,
+        world_pos,
+        normal,
+        albedo.rgb,
+        roughness,
+        metallic
+    );
+    out_color = vec4((1.0 - shadow) * strength * lighting, albedo.a);
+}
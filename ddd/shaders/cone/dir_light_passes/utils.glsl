@@ -0,0 +1,64 @@
+#include "../utils.glsl"
+
+#define MAX_CASCADES 8
+
+struct DirLightData {
+    float direction_x;
+    float direction_y;
+    float direction_z;
+
+    float color_r;
+    float color_g;
+    float color_b;
+};
+
+vec3 dir_light_calc(
+    DirLightData light,
+    vec3 view_pos,
+    vec3 world_pos,
+    vec3 normal,
+    vec3 albedo,
+    float roughness,
+    float metallic
+) {
+    vec3 to_light = -normalize(vec3(light.direction_x, light.direction_y, light.direction_z));
+
+    vec3 view = normalize(view_pos - world_pos);
+    vec3 half_way = normalize(view + to_light);
+
+    vec3 radiance = vec3(light.color_r, light.color_g, light.color_b);
+
+    vec3 f0 = vec3(0.04);
+    f0 = mix(f0, albedo, metallic);
+    vec3 f = fresnelSchlick(max(dot(half_way, view), 0.0), f0);
+
+    float ndf = distributionGGX(normal, half_way, roughness);
+    float g = geometry_smith(normal, view, to_light, roughness);
+
+    vec3 numerator = ndf * g * f;
+    float denominator = 4.0 * max(dot(normal, view), 0.0) * max(dot(normal, to_light), 0.0);
+    vec3 specular = numerator / max(denominator, 0.001);
+
+    // specular component weight
+    vec3 ks = f;
+    // diffuse component weight
+    vec3 kd = vec3(1.0) - ks;
+    kd *= 1.0 - metallic;
+
+    float n_dot_l = max(dot(normal, to_light), 0.0);
+
+    return (kd * albedo / PI + specular) * radiance * n_dot_l;
+}
+
+struct CascadedDepthData {
+    mat4 matrices[MAX_CASCADES];
+    vec4 splits[MAX_CASCADES / 4];
+    uint count;
+    float strength;
+    float bias;
+    float blend;
+};
+
+float cascade_split(CascadedDepthData depth, uint cascade) {
+    return depth.splits[cascade / 4u][cascade % 4u];
+}
@@ -1,6 +1,7 @@
 files='
 ambient.frag
 environment.frag
+probes.frag
 '
 
 for file in $files
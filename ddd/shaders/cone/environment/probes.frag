@@ -0,0 +1,116 @@
+#version 450
+
+#include "utils.glsl"
+
+layout(location = 0) in vec2 in_uv;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform texture2D u_position;
+layout(set = 0, binding = 1) uniform texture2D u_normal;
+layout(set = 0, binding = 2) uniform texture2D u_albedo;
+layout(set = 0, binding = 3) uniform texture2D u_roughness;
+layout(set = 0, binding = 4) uniform texture2D u_metallic;
+layout(set = 0, binding = 5) uniform texture2D u_subsurface;
+layout(set = 0, binding = 6) uniform texture2D u_ao;
+layout(set = 0, binding = 7) uniform sampler u_sampler;
+
+layout(set = 1, binding = 0) uniform CameraData {
+    mat4 projection;
+    mat4 view;
+    vec4 position;
+    float z_far;
+} u_camera;
+
+layout(set = 2, binding = 0) uniform textureCube u_diffuse;
+layout(set = 2, binding = 1) uniform textureCube u_specular;
+layout(set = 2, binding = 2) uniform texture2D u_brdf_lut;
+
+struct Probe {
+    // xyz position the probe was captured from, w distance inside the box over which the probe fades in
+    vec4 position;
+    vec4 box_min;
+    vec4 box_max;
+};
+
+layout(set = 3, binding = 0) readonly buffer Probes {
+    Probe probes[];
+} u_probes;
+layout(set = 3, binding = 1) uniform textureCubeArray u_probe_diffuse;
+layout(set = 3, binding = 2) uniform textureCubeArray u_probe_specular;
+
+layout(push_constant) uniform Data {
+    float max_reflection_lod;
+    float max_probe_lod;
+    float strength;
+    float width;
+    float height;
+    int probe_count;
+};
+
+void main() {
+    vec3 position = texture(sampler2D(u_position, u_sampler), in_uv).xyz;
+    vec3 normal = texture(sampler2D(u_normal, u_sampler), in_uv).xyz;
+    vec4 albedo = texture(sampler2D(u_albedo, u_sampler), in_uv);
+    float metallic = texture(sampler2D(u_metallic, u_sampler), in_uv).x;
+    float roughness = texture(sampler2D(u_roughness, u_sampler), in_uv).x;
+    float ao = texture(sampler2D(u_ao, u_sampler), in_uv).x;
+
+    vec3 view = normalize(u_camera.position.xyz - position);
+    vec3 ref = reflect(-view, normal);
+
+    vec3 irradiance_sample = normal;
+    irradiance_sample.y *= -1.0;
+
+    vec3 irradiance = vec3(0.0);
+    vec3 prefiltered = vec3(0.0);
+    float total = 0.0;
+
+    for (int i = 0; i < probe_count; i++) {
+        Probe probe = u_probes.probes[i];
+
+        // distance to the closest face of the box, negative outside of it
+        vec3 inside = min(position - probe.box_min.xyz, probe.box_max.xyz - position);
+        float edge = min(inside.x, min(inside.y, inside.z));
+        if (edge <= 0.0) {
+            continue;
+        }
+        float weight = clamp(edge / max(probe.position.w, 0.0001), 0.0, 1.0);
+
+        // intersect the reflection with the box so that reflections line up with the captured geometry
+        vec3 first = (probe.box_max.xyz - position) / ref;
+        vec3 second = (probe.box_min.xyz - position) / ref;
+        vec3 furthest = max(first, second);
+        float dist = min(furthest.x, min(furthest.y, furthest.z));
+        vec3 dir = position + ref * dist - probe.position.xyz;
+        dir.y *= -1.0; // correct for vulkan coordinates
+
+        irradiance += weight * texture(samplerCubeArray(u_probe_diffuse, u_sampler), vec4(irradiance_sample, float(i))).rgb;
+        prefiltered += weight * textureLod(samplerCubeArray(u_probe_specular, u_sampler), vec4(dir, float(i)), roughness * max_probe_lod).rgb;
+        total += weight;
+    }
+
+    // normalize overlapping probes and fill the rest with the environment map
+    if (total > 1.0) {
+        irradiance /= total;
+        prefiltered /= total;
+        total = 1.0;
+    }
+    vec3 global_ref = ref;
+    global_ref.y *= -1.0; // correct for vulkan coordinates
+    irradiance += (1.0 - total) * texture(samplerCube(u_diffuse, u_sampler), irradiance_sample).rgb;
+    prefiltered += (1.0 - total) * textureLod(samplerCube(u_specular, u_sampler), global_ref, roughness * max_reflection_lod).rgb;
+
+    vec3 f0 = vec3(0.04);
+    f0 = mix(f0, albedo.rgb, metallic);
+
+    vec3 kS = fresnelSchlickRoughness(max(dot(normal, view), 0.0), f0, roughness);
+    vec3 kD = 1.0 - kS;
+    kD *= 1.0 - metallic;
+    vec3 diffuse = irradiance * albedo.rgb;
+
+    vec2 envBRDF = texture(sampler2D(u_brdf_lut, u_sampler), vec2(max(dot(normal, view), 0.0), roughness)).rg;
+    vec3 specular = prefiltered * (kS * envBRDF.x + envBRDF.y);
+
+    out_color = vec4(ao * strength * (kD * diffuse + specular), albedo.a);
+}
@@ -0,0 +1,9 @@
+files='
+object_id.frag
+object_id.vert
+'
+
+for file in $files
+do
+    glslc $file -o $file.spv
+done
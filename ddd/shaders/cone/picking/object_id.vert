@@ -0,0 +1,28 @@
+#version 450
+
+layout(location = 0) in vec3 in_pos;
+
+layout(location = 0) flat out uint out_id;
+
+layout(set = 0, binding = 0) uniform Camera {
+    mat4 projection;
+    mat4 view;
+    vec4 position;
+    float z_far;
+} u_camera;
+
+layout(set = 1, binding = 0) buffer Instances {
+    mat4 models[];
+} u_instances;
+
+layout(push_constant) uniform PushData {
+    // id of the first instance
+    uint first_id;
+};
+
+void main() {
+    mat4 model = u_instances.models[gl_InstanceIndex];
+    gl_Position = u_camera.projection * u_camera.view * model * vec4(in_pos, 1.0);
+    // 0 is reserved for no object
+    out_id = first_id + uint(gl_InstanceIndex) + 1;
+}
@@ -1,65 +1,4 @@
-#include "../utils.glsl"
-
-struct PointLightData {
-    float falloff;
-    
-    float position_x;
-    float position_y;
-    float position_z;
-    
-    float color_r;
-    float color_g;
-    float color_b;
-
-    float radius;
-};
-
-vec3 point_light_calc(
-    PointLightData light, 
-    vec3 view_pos,
-    vec3 world_pos,
-    vec3 normal,
-    vec3 albedo,
-    float roughness,
-    float metallic
-) {
-    vec3 light_pos = vec3(light.position_x, light.position_y, light.position_z);
-
-    vec3 view = normalize(view_pos - world_pos);
-    vec3 to_light = light_pos - world_pos;
-
-    if (length(to_light) > light.radius) {
-        return vec3(0.0);
-    }
-
-    vec3 to_light_unit = normalize(to_light);
-    vec3 half_way = normalize(view + to_light);
-
-    float distance2 = dot(to_light, to_light);
-    float attenuation = 1.0 / (0.001 + light.falloff * distance2);
-    vec3 radiance = vec3(light.color_r, light.color_g, light.color_b) * attenuation;
-
-    vec3 f0 = vec3(0.04);
-    f0 = mix(f0, albedo, metallic);
-    vec3 f = fresnelSchlick(max(dot(half_way, view), 0.0), f0);
-
-    float ndf = distributionGGX(normal, half_way, roughness);
-    float g = geometry_smith(normal, view, to_light_unit, roughness);
-
-    vec3 numerator = ndf * g * f;
-    float denominator = 4.0 * max(dot(normal, view), 0.0) * max(dot(normal, to_light_unit), 0.0);
-    vec3 specular = numerator / max(denominator, 0.001);
-    
-    // specular component weight
-    vec3 ks = f;
-    // diffuse component weight
-    vec3 kd = vec3(1.0) - ks;
-    kd *= 1.0 - metallic;
-
-    float n_dot_l = max(dot(normal, to_light_unit), 0.0);
-
-    return (kd * albedo / PI + specular) * radiance * n_dot_l;
-}
+#include "light.glsl"
 
 struct PointDepthData {
     mat4 views[6];
@@ -7,6 +7,9 @@ bloom_prefilter.frag
 skybox.frag
 tonemap_global.frag
 tonemap_local.frag
+ssr.frag
+taa.frag
+exposure_histogram.comp
 '
 
 for file in $files
@@ -0,0 +1,139 @@
+#version 450
+
+#include "../utils.glsl"
+
+layout(location = 0) in vec2 in_uv;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform texture2D u_position;
+layout(set = 0, binding = 1) uniform texture2D u_view_pos;
+layout(set = 0, binding = 2) uniform texture2D u_normal;
+layout(set = 0, binding = 3) uniform texture2D u_albedo;
+layout(set = 0, binding = 4) uniform texture2D u_roughness;
+layout(set = 0, binding = 5) uniform texture2D u_metallic;
+layout(set = 0, binding = 6) uniform texture2D u_ao;
+layout(set = 0, binding = 7) uniform sampler u_sampler;
+
+layout(set = 1, binding = 0) uniform CameraData {
+    mat4 projection;
+    mat4 view;
+    vec4 position;
+    float z_far;
+} u_camera;
+
+layout(set = 2, binding = 0) uniform textureCube u_specular;
+layout(set = 2, binding = 1) uniform texture2D u_brdf_lut;
+
+layout(set = 3, binding = 0) uniform texture2D u_scene;
+layout(set = 3, binding = 1) uniform SSRData {
+    int max_steps;
+    int refine_steps;
+    float max_distance;
+    float thickness;
+    float max_roughness;
+    float edge_fade;
+} u_data;
+
+layout(push_constant) uniform Data {
+    float max_reflection_lod;
+    float max_scene_lod;
+    float strength;
+    float width;
+    float height;
+};
+
+vec2 project(vec3 view_pos) {
+    vec4 clip = u_camera.projection * vec4(view_pos, 1.0);
+    return clip.xy / clip.w * 0.5 + 0.5;
+}
+
+float scene_depth(vec2 uv) {
+    return textureLod(sampler2D(u_view_pos, u_sampler), uv, 0.0).z;
+}
+
+// march from origin along dir in view space
+// returns (uv, distance travelled, 1.0) on a hit or 0.0 on a miss
+vec4 trace(vec3 origin, vec3 dir) {
+    float step_length = u_data.max_distance / float(max(u_data.max_steps, 1));
+    vec3 prev = origin;
+    for (int i = 1; i <= u_data.max_steps; i++) {
+        vec3 pos = origin + dir * (step_length * float(i));
+        // behind the camera
+        if (pos.z > 0.0) {
+            break;
+        }
+        vec2 uv = project(pos);
+        if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+            break;
+        }
+        float diff = scene_depth(uv) - pos.z;
+        if (diff > 0.0 && diff < u_data.thickness) {
+            // binary search between the last two steps for the intersection
+            vec3 lo = prev;
+            vec3 hi = pos;
+            for (int j = 0; j < u_data.refine_steps; j++) {
+                vec3 mid = 0.5 * (lo + hi);
+                if (scene_depth(project(mid)) > mid.z) {
+                    hi = mid;
+                } else {
+                    lo = mid;
+                }
+            }
+            return vec4(project(hi), length(hi - origin), 1.0);
+        }
+        prev = pos;
+    }
+    return vec4(0.0);
+}
+
+void main() {
+    vec3 position = texture(sampler2D(u_position, u_sampler), in_uv).xyz;
+    vec3 view_pos = texture(sampler2D(u_view_pos, u_sampler), in_uv).xyz;
+    vec3 normal = normalize(texture(sampler2D(u_normal, u_sampler), in_uv).xyz);
+    vec4 albedo = texture(sampler2D(u_albedo, u_sampler), in_uv);
+    float metallic = texture(sampler2D(u_metallic, u_sampler), in_uv).x;
+    float roughness = texture(sampler2D(u_roughness, u_sampler), in_uv).x;
+    float ao = texture(sampler2D(u_ao, u_sampler), in_uv).x;
+
+    vec3 view = normalize(u_camera.position.xyz - position);
+    vec3 ref = reflect(-view, normal);
+
+    // the environment pass has already added this
+    vec3 env_ref = ref;
+    env_ref.y *= -1.0; // correct for vulkan coordinates
+    vec3 environment = textureLod(samplerCube(u_specular, u_sampler), env_ref, roughness * max_reflection_lod).rgb;
+
+    vec3 traced = environment;
+    float confidence = 0.0;
+    if (roughness < u_data.max_roughness) {
+        vec3 view_ref = normalize(mat3(u_camera.view) * ref);
+        vec4 hit = trace(view_pos, view_ref);
+        if (hit.w > 0.0) {
+            // widen the sample with distance travelled and roughness by reading lower mips of the scene
+            float radius = hit.z * roughness * roughness;
+            float hit_depth = max(-scene_depth(hit.xy), 0.001);
+            float pixels = radius * u_camera.projection[1][1] * 0.5 * height / hit_depth;
+            float lod = clamp(log2(max(pixels, 1.0)), 0.0, max_scene_lod);
+            traced = textureLod(sampler2D(u_scene, u_sampler), hit.xy, lod).rgb;
+
+            // fade out where the trace is unreliable
+            vec2 edge = smoothstep(vec2(0.0), vec2(u_data.edge_fade), hit.xy)
+                * smoothstep(vec2(0.0), vec2(u_data.edge_fade), 1.0 - hit.xy);
+            float distance_fade = 1.0 - clamp(hit.z / u_data.max_distance, 0.0, 1.0);
+            float roughness_fade = 1.0 - smoothstep(0.5 * u_data.max_roughness, u_data.max_roughness, roughness);
+            float facing_fade = 1.0 - clamp(view_ref.z, 0.0, 1.0);
+            confidence = edge.x * edge.y * distance_fade * roughness_fade * facing_fade;
+        }
+    }
+
+    vec3 f0 = vec3(0.04);
+    f0 = mix(f0, albedo.rgb, metallic);
+
+    vec3 kS = fresnelSchlickRoughness(max(dot(normal, view), 0.0), f0, roughness);
+    vec2 envBRDF = texture(sampler2D(u_brdf_lut, u_sampler), vec2(max(dot(normal, view), 0.0), roughness)).rg;
+
+    // replace the environment reflection with the traced reflection where the trace hit
+    vec3 reflection = confidence * (traced - environment);
+    out_color = vec4(ao * strength * reflection * (kS * envBRDF.x + envBRDF.y), albedo.a);
+}
@@ -0,0 +1,75 @@
+#version 450
+
+layout(location = 0) in vec2 in_uv;
+
+layout(location = 0) out vec4 out_color;
+layout(location = 1) out vec4 out_history;
+
+layout(set = 0, binding = 0) uniform texture2D u_current;
+layout(set = 0, binding = 1) uniform texture2D u_history;
+layout(set = 0, binding = 2) uniform texture2D u_velocity;
+layout(set = 0, binding = 3) uniform sampler u_sampler;
+
+layout(push_constant) uniform Data {
+    float blend;
+    float width;
+    float height;
+};
+
+vec3 rgb_to_ycocg(vec3 c) {
+    return vec3(
+        0.25 * c.r + 0.5 * c.g + 0.25 * c.b,
+        0.5 * c.r - 0.5 * c.b,
+        -0.25 * c.r + 0.5 * c.g - 0.25 * c.b
+    );
+}
+
+vec3 ycocg_to_rgb(vec3 c) {
+    return vec3(
+        c.x + c.y - c.z,
+        c.x + c.z,
+        c.x - c.y - c.z
+    );
+}
+
+// weight hdr colors by inverse luminance so that bright pixels don't dominate the blend
+float weight(vec3 c) {
+    return 1.0 / (1.0 + c.x);
+}
+
+void main() {
+    vec2 texel = 1.0 / vec2(width, height);
+
+    vec4 current = texture(sampler2D(u_current, u_sampler), in_uv);
+    vec3 current_ycocg = rgb_to_ycocg(current.rgb);
+
+    // bounding box of the neighbourhood in ycocg space
+    vec3 neighbourhood_min = current_ycocg;
+    vec3 neighbourhood_max = current_ycocg;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            vec2 uv = in_uv + vec2(float(x), float(y)) * texel;
+            vec3 c = rgb_to_ycocg(texture(sampler2D(u_current, u_sampler), uv).rgb);
+            neighbourhood_min = min(neighbourhood_min, c);
+            neighbourhood_max = max(neighbourhood_max, c);
+        }
+    }
+
+    vec2 velocity = texture(sampler2D(u_velocity, u_sampler), in_uv).xy;
+    vec2 prev_uv = in_uv - velocity;
+
+    vec3 result = current_ycocg;
+    if (prev_uv.x >= 0.0 && prev_uv.x <= 1.0 && prev_uv.y >= 0.0 && prev_uv.y <= 1.0) {
+        vec3 history = rgb_to_ycocg(texture(sampler2D(u_history, u_sampler), prev_uv).rgb);
+        // reject history that isn't similar to the current neighbourhood
+        history = clamp(history, neighbourhood_min, neighbourhood_max);
+
+        float current_weight = (1.0 - blend) * weight(current_ycocg);
+        float history_weight = blend * weight(history);
+        result = (current_ycocg * current_weight + history * history_weight) / (current_weight + history_weight);
+    }
+
+    vec4 color = vec4(ycocg_to_rgb(result), current.a);
+    out_color = color;
+    out_history = color;
+}
@@ -17,12 +17,18 @@ layout(set = 1, binding = 0) uniform Params {
     float w;
 } u;
 
+layout(set = 1, binding = 1) readonly buffer Exposure {
+    float luminance;
+    float exposure;
+} u_exposure;
+
 vec4 f(vec4 x) {
     return ((x * (u.a * x + u.c * u.b) + u.d * u.e) / (x * (u.a * x + u.b) + u.d * u.f)) - u.e / u.f;
 }
 
 void main() {
     vec4 t = texture(sampler2D(u_texture, u_sampler), in_uv);
+    t.rgb *= u_exposure.exposure;
     vec4 w = vec4(u.w);
     out_color = f(t) / f(w);
 }
\ No newline at end of file
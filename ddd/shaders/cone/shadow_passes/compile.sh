@@ -1,5 +1,7 @@
 files='
 point.vert
+dir.vert
+spot.vert
 shadow.frag
 '
 
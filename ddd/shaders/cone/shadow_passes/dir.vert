@@ -0,0 +1,25 @@
+#version 450
+
+layout(location = 0) in vec3 in_pos;
+
+layout(set = 0, binding = 0) uniform Cascades {
+    mat4 matrices[8];
+    vec4 splits[2];
+    uint count;
+    float strength;
+    float bias;
+    float blend;
+} u_shadow;
+
+layout(set = 1, binding = 0) buffer Instances {
+    mat4 models[];
+} u_instance;
+
+layout(push_constant) uniform Cascade {
+    uint cascade;
+};
+
+void main() {
+    vec4 world_pos = u_instance.models[gl_InstanceIndex] * vec4(in_pos, 1.0);
+    gl_Position = u_shadow.matrices[cascade] * world_pos;
+}
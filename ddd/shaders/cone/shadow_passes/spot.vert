@@ -0,0 +1,27 @@
+#version 450
+
+layout(location = 0) in vec3 in_pos;
+
+layout(location = 0) out float out_depth;
+
+layout(set = 0, binding = 0) uniform Light {
+    mat4 view;
+    mat4 projection;
+    float pos_x;
+    float pos_y;
+    float pos_z;
+    float z_far;
+    float strength;
+    float bias;
+} u_shadow;
+
+layout(set = 1, binding = 0) buffer Instances {
+    mat4 models[];
+} u_instance;
+
+void main() {
+    vec4 world_pos = u_instance.models[gl_InstanceIndex] * vec4(in_pos, 1.0);
+    vec3 light_pos = vec3(u_shadow.pos_x, u_shadow.pos_y, u_shadow.pos_z);
+    out_depth = length(world_pos.xyz - light_pos) / u_shadow.z_far;
+    gl_Position = u_shadow.projection * u_shadow.view * world_pos;
+}
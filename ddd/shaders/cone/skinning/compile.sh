@@ -0,0 +1,8 @@
+files='
+skin.comp
+'
+
+for file in $files
+do
+    glslc $file -o $file.spv
+done
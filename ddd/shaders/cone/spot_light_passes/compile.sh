@@ -0,0 +1,9 @@
+files='
+single_base.frag
+single_shadow.frag
+'
+
+for file in $files
+do
+    glslc $file -o $file.spv
+done
@@ -0,0 +1,96 @@
+#version 450
+
+#include "utils.glsl"
+
+layout(location = 0) in vec2 in_uv;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform texture2D u_position;
+layout(set = 0, binding = 1) uniform texture2D u_normal;
+layout(set = 0, binding = 2) uniform texture2D u_albedo;
+layout(set = 0, binding = 3) uniform texture2D u_roughness;
+layout(set = 0, binding = 4) uniform texture2D u_metallic;
+layout(set = 0, binding = 5) uniform sampler u_sampler;
+
+layout(set = 1, binding = 0) uniform CameraData {
+    mat4 projection;
+    mat4 view;
+    vec4 position;
+    float z_far;
+} u_camera;
+
+layout(set = 2, binding = 0) uniform LightData {
+    SpotLightData light;
+} u_light_data;
+
+layout(set = 3, binding = 0) uniform ShadowData {
+    SpotDepthData depth;
+} u_shadow_data;
+
+layout(set = 3, binding = 1) uniform texture2D u_shadow_map;
+layout(set = 3, binding = 2) uniform sampler u_shadow_sampler;
+
+layout(push_constant) uniform PushData {
+    float strength;
+    uint samples;
+    float width;
+    float height;
+};
+
+// fraction of samples that are occluded
+float spot_shadow_calc(vec3 world_pos, vec3 normal) {
+    SpotDepthData depth = u_shadow_data.depth;
+    vec4 light_pos = depth.projection * depth.view * vec4(world_pos, 1.0);
+    if (light_pos.w <= 0.0) {
+        return 0.0;
+    }
+    vec2 uv = (light_pos.xy / light_pos.w) * 0.5 + vec2(0.5);
+
+    // the shadow map stores distance to the light over z_far
+    vec3 shadow_pos = vec3(depth.pos_x, depth.pos_y, depth.pos_z);
+    vec3 to_shadow = world_pos - shadow_pos;
+    float current_depth = length(to_shadow) / depth.z_far;
+    if (current_depth >= 1.0) {
+        return 0.0;
+    }
+
+    float bias = max(depth.bias * (1.0 - dot(normal, -normalize(to_shadow))), depth.bias);
+    vec2 texel = 1.0 / vec2(textureSize(sampler2D(u_shadow_map, u_shadow_sampler), 0));
+    float shadow = 0.0;
+    for (uint i = 0u; i < samples; i++) {
+        vec2 offset = poisson_disk[i] * depth.strength * texel;
+        float closest = texture(sampler2D(u_shadow_map, u_shadow_sampler), uv + offset).r;
+        if (current_depth - bias > closest) {
+            shadow += 1.0;
+        }
+    }
+
+    return shadow / float(samples);
+}
+
+void main() {
+    vec3 world_pos = texture(sampler2D(u_position, u_sampler), in_uv).xyz;
+    vec3 normal = texture(sampler2D(u_normal, u_sampler), in_uv).xyz;
+    vec4 albedo = texture(sampler2D(u_albedo, u_sampler), in_uv);
+    float roughness = texture(sampler2D(u_roughness, u_sampler), in_uv).x;
+    float metallic = texture(sampler2D(u_metallic, u_sampler), in_uv).x;
+
+    float shadow = spot_shadow_calc(world_pos, normal);
+
+    if (shadow == 1.0) {
+        out_color = vec4(vec3(0.0), albedo.a);
+        return;
+    }
+
+    vec3 lighting = spot_light_calc(
+        u_light_data.light,
+        u_camera.position.xyz,
+        world_pos,
+        normal,
+        albedo.rgb,
+        roughness,
+        metallic
+    );
+    out_color = vec4((1.0 - shadow) * strength * lighting, albedo.a);
+}
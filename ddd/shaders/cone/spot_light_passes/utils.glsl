@@ -0,0 +1,88 @@
+#include "../utils.glsl"
+
+struct SpotLightData {
+    float falloff;
+
+    float position_x;
+    float position_y;
+    float position_z;
+
+    float direction_x;
+    float direction_y;
+    float direction_z;
+
+    float color_r;
+    float color_g;
+    float color_b;
+
+    float radius;
+    float inner_cos;
+    float outer_cos;
+};
+
+vec3 spot_light_calc(
+    SpotLightData light,
+    vec3 view_pos,
+    vec3 world_pos,
+    vec3 normal,
+    vec3 albedo,
+    float roughness,
+    float metallic
+) {
+    vec3 light_pos = vec3(light.position_x, light.position_y, light.position_z);
+    vec3 light_dir = normalize(vec3(light.direction_x, light.direction_y, light.direction_z));
+
+    vec3 view = normalize(view_pos - world_pos);
+    vec3 to_light = light_pos - world_pos;
+
+    if (length(to_light) > light.radius) {
+        return vec3(0.0);
+    }
+
+    vec3 to_light_unit = normalize(to_light);
+
+    // smooth fall off between the inner and outer cone
+    float theta = dot(to_light_unit, -light_dir);
+    float cone = clamp((theta - light.outer_cos) / max(light.inner_cos - light.outer_cos, 0.0001), 0.0, 1.0);
+    if (cone == 0.0) {
+        return vec3(0.0);
+    }
+
+    vec3 half_way = normalize(view + to_light_unit);
+
+    float distance2 = dot(to_light, to_light);
+    float attenuation = 1.0 / (0.001 + light.falloff * distance2);
+    vec3 radiance = vec3(light.color_r, light.color_g, light.color_b) * attenuation * cone;
+
+    vec3 f0 = vec3(0.04);
+    f0 = mix(f0, albedo, metallic);
+    vec3 f = fresnelSchlick(max(dot(half_way, view), 0.0), f0);
+
+    float ndf = distributionGGX(normal, half_way, roughness);
+    float g = geometry_smith(normal, view, to_light_unit, roughness);
+
+    vec3 numerator = ndf * g * f;
+    float denominator = 4.0 * max(dot(normal, view), 0.0) * max(dot(normal, to_light_unit), 0.0);
+    vec3 specular = numerator / max(denominator, 0.001);
+
+    // specular component weight
+    vec3 ks = f;
+    // diffuse component weight
+    vec3 kd = vec3(1.0) - ks;
+    kd *= 1.0 - metallic;
+
+    float n_dot_l = max(dot(normal, to_light_unit), 0.0);
+
+    return (kd * albedo / PI + specular) * radiance * n_dot_l;
+}
+
+struct SpotDepthData {
+    mat4 view;
+    mat4 projection;
+    float pos_x;
+    float pos_y;
+    float pos_z;
+    float z_far;
+    float strength;
+    float bias;
+};
@@ -0,0 +1,8 @@
+files='
+composite.frag
+'
+
+for file in $files
+do
+    glslc $file -o $file.spv
+done
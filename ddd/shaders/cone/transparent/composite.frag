@@ -0,0 +1,22 @@
+#version 450
+
+layout(location = 0) in vec2 in_uv;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform texture2D u_accum;
+layout(set = 0, binding = 1) uniform texture2D u_revealage;
+layout(set = 0, binding = 2) uniform sampler u_sampler;
+
+void main() {
+    float revealage = texture(sampler2D(u_revealage, u_sampler), in_uv).r;
+    // nothing transparent covers this pixel
+    if (revealage >= 0.9999) {
+        discard;
+    }
+
+    vec4 accum = texture(sampler2D(u_accum, u_sampler), in_uv);
+    vec3 color = accum.rgb / max(accum.a, 0.00001);
+
+    out_color = vec4(color, 1.0 - revealage);
+}
@@ -0,0 +1,10 @@
+files='
+scatter.comp
+integrate.comp
+composite.frag
+'
+
+for file in $files
+do
+    glslc $file -o $file.spv
+done
@@ -0,0 +1,34 @@
+#version 450
+
+#include "utils.glsl"
+
+layout(location = 0) in vec2 in_uv;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform CameraData {
+    mat4 projection;
+    mat4 view;
+    vec4 position;
+    float z_far;
+} u_camera;
+
+layout(set = 0, binding = 1) uniform Volumetrics {
+    VolumetricsData data;
+} u_volumetrics;
+
+layout(set = 0, binding = 2) uniform texture3D u_integrated;
+layout(set = 0, binding = 3) uniform texture2D u_depth;
+layout(set = 0, binding = 4) uniform sampler u_sampler;
+
+void main() {
+    float depth = textureLod(sampler2D(u_depth, u_sampler), in_uv, 0.0).r;
+    vec4 view_pos = inverse(u_camera.projection) * vec4(in_uv * 2.0 - 1.0, depth, 1.0);
+    float view_depth = -view_pos.z / view_pos.w;
+
+    float s = depth_slice(u_volumetrics.data, view_depth);
+    vec4 fog = textureLod(sampler3D(u_integrated, u_sampler), vec3(in_uv, s), 0.0);
+
+    // blended as output * transmittance + scattering
+    out_color = fog;
+}
@@ -0,0 +1,22 @@
+struct VolumetricsData {
+    vec3 albedo;
+    float density;
+    vec3 ambient;
+    float anisotropy;
+    float height_falloff;
+    float base_height;
+    float near;
+    float max_distance;
+    float light_strength;
+};
+
+// view depth of a froxel from its slice coordinate in [0, 1]
+// slices are distributed exponentially so that there is more detail close to the camera
+float slice_depth(VolumetricsData data, float s) {
+    return data.near * pow(data.max_distance / data.near, s);
+}
+
+// slice coordinate in [0, 1] from view depth
+float depth_slice(VolumetricsData data, float depth) {
+    return log(max(depth, data.near) / data.near) / log(data.max_distance / data.near);
+}
@@ -46,6 +46,10 @@ impl mesh::Vertex for Vertex {
         println!("Call to set tangents of ddd::clay::Vertex, no tangent fields so no action taken")
     }
 
+    fn set_normal(&mut self, normal: glam::Vec3) {
+        self.normal = normal;
+    }
+
     fn pos(&self) -> glam::Vec3 {
         self.pos
     }
@@ -67,6 +71,8 @@ impl mesh::Vertex for Vertex {
     }
 }
 
+use gfx::prelude::*;
+
 use std::collections::HashMap;
 
 use crate::utils::{Camera, Instances};
@@ -225,3 +231,296 @@ impl_renderer!(
     "../shaders/clay/smooth.vert.spv",
     "../shaders/clay/smooth.frag.spv"
 );
+
+/// How a mesh is drawn by a [`DebugRenderer`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugMode {
+    /// The edges of triangles are drawn as lines in the color
+    ///
+    /// Using this requires enabling gpu::DeviceFeatures::NON_SOLID
+    Wireframe,
+    /// A line of the given length is drawn along the normal of each vertex
+    Normals(f32),
+    /// Lines of the given length are drawn along both tangents of each vertex
+    Tangents(f32),
+    /// Color by view depth from blue when near to red at the cameras z_far, only the alpha of the color is used
+    Depth,
+    /// The color is added ignoring depth so that areas drawn over many times are brighter
+    Overdraw,
+}
+
+impl DebugMode {
+    fn index(&self) -> usize {
+        match self {
+            Self::Wireframe => 0,
+            Self::Normals(_) | Self::Tangents(_) => 1,
+            Self::Depth => 2,
+            Self::Overdraw => 3,
+        }
+    }
+}
+
+/// A forward renderer for debugging imported meshes where the [`DebugMode`] is selected per draw
+///
+/// Meshes drawn with [`DebugMode::Normals`] or [`DebugMode::Tangents`] read their vertex buffer in the shader
+/// so must be created with gpu::BufferUsage::STORAGE vertex usage, see [`gfx::Mesh::from_usage_indexed`]
+pub struct DebugRenderer {
+    pub wireframe: gfx::ReflectedGraphics,
+    pub vectors: gfx::ReflectedGraphics,
+    pub depth: gfx::ReflectedGraphics,
+    pub overdraw: gfx::ReflectedGraphics,
+    /// map from (mode, camera, instances, vertex buffer) to bundle
+    pub bundles: Arc<Mutex<HashMap<(usize, u64, u64, u64), gfx::Bundle>>>,
+}
+
+impl DebugRenderer {
+    pub fn new(
+        device: &gpu::Device,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let depth_test = gpu::DepthStencilState {
+            depth: Some(gpu::DepthState {
+                test_enable: true,
+                write_enable: true,
+                compare_op: gpu::CompareOp::LessEqual,
+            }),
+            stencil_front: None,
+            stencil_back: None,
+        };
+
+        let solid_vert = gpu::include_spirv!("../shaders/clay/solid.vert.spv");
+        let solid_frag = gpu::include_spirv!("../shaders/clay/solid.frag.spv");
+
+        let n = name.map(|n| format!("{}_wireframe", n));
+        let wireframe = Self::pipeline(
+            device,
+            &solid_vert,
+            &solid_frag,
+            gpu::Rasterizer {
+                polygon_mode: gpu::PolygonMode::Line,
+                ..Default::default()
+            },
+            gpu::BlendState::ALPHA,
+            depth_test,
+            cache.clone(),
+            n.as_deref(),
+        )?;
+
+        let n = name.map(|n| format!("{}_vectors", n));
+        let vectors = Self::pipeline(
+            device,
+            &gpu::include_spirv!("../shaders/clay/vectors.vert.spv"),
+            &gpu::include_spirv!("../shaders/clay/vectors.frag.spv"),
+            gpu::Rasterizer {
+                primitive_topology: gpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            gpu::BlendState::ALPHA,
+            depth_test,
+            cache.clone(),
+            n.as_deref(),
+        )?;
+
+        let n = name.map(|n| format!("{}_depth", n));
+        let depth = Self::pipeline(
+            device,
+            &gpu::include_spirv!("../shaders/clay/depth.vert.spv"),
+            &gpu::include_spirv!("../shaders/clay/depth.frag.spv"),
+            gpu::Rasterizer::default(),
+            gpu::BlendState::ALPHA,
+            depth_test,
+            cache.clone(),
+            n.as_deref(),
+        )?;
+
+        let n = name.map(|n| format!("{}_overdraw", n));
+        let overdraw = Self::pipeline(
+            device,
+            &solid_vert,
+            &solid_frag,
+            gpu::Rasterizer::default(),
+            gpu::BlendState::ADD,
+            gpu::DepthStencilState {
+                depth: Some(gpu::DepthState {
+                    test_enable: false,
+                    write_enable: false,
+                    compare_op: gpu::CompareOp::Always,
+                }),
+                stencil_front: None,
+                stencil_back: None,
+            },
+            cache,
+            n.as_deref(),
+        )?;
+
+        Ok(Self {
+            wireframe,
+            vectors,
+            depth,
+            overdraw,
+            bundles: Arc::default(),
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn pipeline(
+        device: &gpu::Device,
+        vert_spv: &[u32],
+        frag_spv: &[u32],
+        rasterizer: gpu::Rasterizer,
+        blend: gpu::BlendState,
+        depth_stencil: gpu::DepthStencilState,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<gfx::ReflectedGraphics, gpu::Error> {
+        match gfx::ReflectedGraphics::from_spirv(
+            device,
+            vert_spv,
+            None,
+            Some(frag_spv),
+            rasterizer,
+            &[blend],
+            Some(depth_stencil),
+            cache,
+            name,
+        ) {
+            Ok(g) => Ok(g),
+            Err(e) => match e {
+                gfx::error::ReflectedError::Gpu(e) => Err(e),
+                e => unreachable!("{}", e),
+            },
+        }
+    }
+
+    fn graphics(&self, mode: DebugMode) -> &gfx::ReflectedGraphics {
+        match mode.index() {
+            0 => &self.wireframe,
+            1 => &self.vectors,
+            2 => &self.depth,
+            _ => &self.overdraw,
+        }
+    }
+
+    /// vertex_buffer is only used by [`DebugMode::Normals`] and [`DebugMode::Tangents`]
+    pub fn bundle(
+        &self,
+        device: &gpu::Device,
+        mode: DebugMode,
+        camera: &Camera,
+        instance: &Instances,
+        vertex_buffer: &gpu::Buffer,
+    ) -> Result<gfx::Bundle, gpu::Error> {
+        let vectors = matches!(mode, DebugMode::Normals(_) | DebugMode::Tangents(_));
+        let key = (
+            mode.index(),
+            camera.buffer.id(),
+            instance.buffer.id(),
+            if vectors { vertex_buffer.id() } else { 0 },
+        );
+        let mut bundles = self.bundles.lock().unwrap();
+        if let Some(b) = bundles.get(&key) {
+            return Ok(b.clone());
+        }
+
+        let mut builder = self
+            .graphics(mode)
+            .bundle()
+            .unwrap()
+            .set_resource("u_camera", camera)
+            .unwrap()
+            .set_resource("u_instances", instance)
+            .unwrap();
+        if vectors {
+            builder = builder.set_resource("u_vertices", vertex_buffer).unwrap();
+        }
+        let b = match builder.build(device) {
+            Ok(b) => b,
+            Err(e) => match e {
+                gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+
+        bundles.insert(key, b.clone());
+        Ok(b)
+    }
+
+    /// Draw each mesh with it's own mode and color
+    ///
+    /// Consecutive meshes with the same pipeline are drawn in the same pass so meshes should be ordered by mode where possible
+    pub fn pass<'a, V: gfx::Vertex>(
+        &'a self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        mut target: gfx::Attachment<'a>,
+        mut depth: gfx::Attachment<'a>,
+        meshes: impl IntoIterator<Item = (&'a gfx::Mesh<V>, &'a Instances, [f32; 4], DebugMode)>,
+        camera: &Camera,
+    ) -> Result<(), gpu::Error> {
+        let meshes = meshes.into_iter().collect::<Vec<_>>();
+
+        for group in meshes.chunk_by(|a, b| a.3.index() == b.3.index()) {
+            let mut pass = encoder.graphics_pass_reflected(
+                device,
+                &[target.clone()],
+                &[],
+                Some(depth.clone()),
+                self.graphics(group[0].3),
+            )?;
+
+            for &(mesh, instance, color, mode) in group {
+                let bundle = self.bundle(device, mode, camera, instance, &mesh.vertex_buffer)?;
+                pass.set_bundle_owned(bundle);
+                pass.push_vec4("u_color", color);
+
+                match mode {
+                    DebugMode::Normals(len) => {
+                        Self::draw_vectors(&mut pass, mesh, instance, "normal", len)
+                    }
+                    DebugMode::Tangents(len) => {
+                        Self::draw_vectors(&mut pass, mesh, instance, "tangent_u", len);
+                        Self::draw_vectors(&mut pass, mesh, instance, "tangent_v", len);
+                    }
+                    _ => pass.draw_instanced_mesh_ref(mesh, 0, instance.length as _),
+                }
+            }
+
+            pass.finish();
+
+            target.load = gpu::LoadOp::Load;
+            depth.load = gpu::LoadOp::Load;
+        }
+
+        Ok(())
+    }
+
+    fn draw_vectors<V: gfx::Vertex>(
+        pass: &mut gfx::pass::ReflectedGraphicsPass<'_, '_, V>,
+        mesh: &gfx::Mesh<V>,
+        instance: &Instances,
+        attribute: &str,
+        len: f32,
+    ) {
+        let offset = |name: &str| match V::get(name) {
+            Some((offset, _)) => offset / 4,
+            None => panic!(
+                "ERROR: Attempt to draw {} of vertices without {} attribute",
+                attribute, name
+            ),
+        };
+
+        pass.push_u32("stride", (std::mem::size_of::<V>() / 4) as u32);
+        pass.push_u32("pos_offset", offset("pos"));
+        pass.push_u32("vec_offset", offset(attribute));
+        pass.push_f32("len", len);
+        pass.draw(0, mesh.vertex_count * 2, 0, instance.length as _);
+    }
+
+    /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
+    /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
+    /// This function drops Descriptor sets cached by self
+    pub fn clean(&mut self) {
+        self.bundles.lock().unwrap().clear();
+    }
+}
@@ -0,0 +1,500 @@
+//! Gpu frustum and occlusion culling of instances
+//!
+//! [`CulledInstances`] keeps the transform and [`Bounds`] of every instance of a mesh in a storage buffer
+//! [`CullingRenderer`] tests each instance on the gpu and writes the ones that might be seen to a compacted instance list
+//! and the instance count of an indirect draw, then [`Material::culled_pass`] draws them without reading anything back.
+//!
+//! Instances are culled against the frustum of the camera and optionally against a depth pyramid built from the depth of the
+//! previous frame's [`GeometryBuffer`] with [`CullingRenderer::depth_pyramid_pass`]. The occlusion test projects instances
+//! with the previous frame's camera, see [`crate::utils::CameraData::with_previous`], so instances that become visible
+//! from behind something can appear a frame late when the camera moves quickly.
+
+use gfx::prelude::*;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::cone::GeometryBuffer;
+use crate::utils::{Camera, InstanceData, Instances};
+
+/// A bounding sphere in the local space of a mesh
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Bounds {
+    pub center: glam::Vec3,
+    pub radius: f32,
+}
+
+unsafe impl bytemuck::Pod for Bounds {}
+unsafe impl bytemuck::Zeroable for Bounds {}
+
+impl Bounds {
+    pub fn new(center: glam::Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Create a sphere around the centre of the bounding box of the points that contains all of them
+    pub fn from_points(points: impl IntoIterator<Item = glam::Vec3> + Clone) -> Self {
+        let (min, max) = points.clone().into_iter().fold(
+            (glam::Vec3::splat(f32::MAX), glam::Vec3::splat(f32::MIN)),
+            |(min, max), p| (min.min(p), max.max(p)),
+        );
+        if min.x > max.x {
+            return Self::default();
+        }
+        let center = (min + max) * 0.5;
+        let radius = points
+            .into_iter()
+            .fold(0.0f32, |r, p| r.max(p.distance(center)));
+        Self { center, radius }
+    }
+
+    /// Create a sphere containing all the vertices
+    pub fn from_vertices<V: mesh::Vertex>(vertices: &[V]) -> Self {
+        Self::from_points(vertices.iter().map(|v| v.pos()))
+    }
+}
+
+/// The transform and local bounds of an instance to be culled
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CullInstanceData {
+    pub model: glam::Mat4,
+    pub bounds: Bounds,
+}
+
+unsafe impl bytemuck::Pod for CullInstanceData {}
+unsafe impl bytemuck::Zeroable for CullInstanceData {}
+
+impl CullInstanceData {
+    pub fn new(model: glam::Mat4, bounds: Bounds) -> Self {
+        Self { model, bounds }
+    }
+}
+
+/// Instances of one mesh that are culled on the gpu before being drawn
+///
+/// The indirect arguments are created for the mesh passed to [`CulledInstances::new`] so
+/// self should only be drawn with that mesh
+#[derive(Debug, Clone)]
+pub struct CulledInstances {
+    /// transforms and bounds of every instance
+    pub instances: gfx::Storage<CullInstanceData>,
+    /// the instances that passed culling compacted to the start of the buffer
+    pub visible: Instances,
+    /// a single [`gpu::DrawIndexedIndirectCommand`] if the mesh is indexed or [`gpu::DrawIndirectCommand`] otherwise
+    /// the instance count is written by culling, usage: COPY_DST STORAGE INDIRECT
+    pub indirect: gpu::Buffer,
+}
+
+impl CulledInstances {
+    /// The stride of the indirect arguments
+    pub const STRIDE: u32 = std::mem::size_of::<gpu::DrawIndexedIndirectCommand>() as u32;
+
+    /// Create new instances of the mesh to be culled
+    ///
+    /// No instances will be drawn until they have been culled
+    pub fn new<V: gfx::Vertex>(
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        mesh: &gfx::Mesh<V>,
+        data: Vec<CullInstanceData>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let length = data.len();
+        let instances = gfx::Storage::from_vec(
+            encoder,
+            device,
+            data,
+            name.map(|n| format!("{}_instances", n)).as_deref(),
+        )?;
+        // TODO uninitialized Storage
+        let visible = gfx::Storage::from_vec(
+            encoder,
+            device,
+            vec![InstanceData::default(); length.max(1)],
+            name.map(|n| format!("{}_visible", n)).as_deref(),
+        )?;
+
+        let indirect = device.create_buffer(&gpu::BufferDesc {
+            size: Self::STRIDE as u64,
+            usage: gpu::BufferUsage::COPY_DST
+                | gpu::BufferUsage::STORAGE
+                | gpu::BufferUsage::INDIRECT,
+            memory: gpu::MemoryType::Device,
+            name: name.map(|n| format!("{}_indirect", n)),
+        })?;
+
+        let command = if let Some((_, index_count)) = &mesh.indices {
+            bytemuck::bytes_of(&gpu::DrawIndexedIndirectCommand {
+                index_count: *index_count,
+                instance_count: 0,
+                first_index: 0,
+                vertex_offset: 0,
+                first_instance: 0,
+            })
+            .to_vec()
+        } else {
+            let mut bytes = bytemuck::bytes_of(&gpu::DrawIndirectCommand {
+                vertex_count: mesh.vertex_count,
+                instance_count: 0,
+                first_vertex: 0,
+                first_instance: 0,
+            })
+            .to_vec();
+            bytes.resize(Self::STRIDE as usize, 0);
+            bytes
+        };
+        encoder.update_buffer_owned(indirect.clone(), 0, command);
+
+        Ok(Self {
+            instances,
+            visible,
+            indirect,
+        })
+    }
+
+    /// The number of instances before culling
+    pub fn length(&self) -> usize {
+        self.instances.length
+    }
+
+    /// Update the transform and bounds of the instances
+    ///
+    /// data should have the same length as self
+    pub fn update_gpu<'a>(
+        &'a self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        data: &'a [CullInstanceData],
+    ) {
+        self.instances.update_gpu(encoder, data)
+    }
+}
+
+/// Culls [`CulledInstances`] on the gpu
+///
+/// Usage overview:
+///  - each frame call [`CullingRenderer::cull_pass`] for all the instances before drawing them
+///  - draw with [`Material::culled_pass`]
+///  - for occlusion culling call [`CullingRenderer::depth_pyramid_pass`] with the geometry buffer after drawing,
+///    the next frame's culling will test against it
+///
+/// Each set of instances is compacted by one workgroup so the order of instances is kept
+pub struct CullingRenderer {
+    pub cull: gfx::ReflectedCompute,
+    /// map from (camera, instances, depth pyramid) to bundle
+    pub cull_bundles: Arc<Mutex<HashMap<(u64, u64, u64), gfx::Bundle>>>,
+    pub pyramid: gfx::ReflectedGraphics,
+    /// map from (width, height) to (depth pyramid, single mip views into the pyramid)
+    pub pyramids: Arc<Mutex<HashMap<(u32, u32), (gfx::GTexture2D, Vec<gpu::TextureView>)>>>,
+    /// map from source view to bundle
+    pub pyramid_bundles: Arc<Mutex<HashMap<u64, gfx::Bundle>>>,
+    /// bound in place of a depth pyramid when not occlusion culling
+    pub empty: gfx::GTexture2D,
+    pub sampler: gpu::Sampler,
+    name: Option<String>,
+}
+
+impl CullingRenderer {
+    pub fn new(
+        device: &gpu::Device,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let cull_spv = gpu::include_spirv!("../../shaders/cone/culling/cull.comp.spv");
+        let cull = match gfx::ReflectedCompute::from_spirv(
+            device,
+            &cull_spv,
+            cache.clone(),
+            name.map(|n| format!("{}_cull", n)).as_deref(),
+        ) {
+            Ok(p) => p,
+            Err(e) => match e {
+                gfx::ReflectedError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+
+        let screen_spv = gpu::include_spirv!("../../shaders/screen.vert.spv");
+        let pyramid_spv = gpu::include_spirv!("../../shaders/cone/culling/depth_pyramid.frag.spv");
+        let n = name.map(|n| format!("{}_depth_pyramid", n));
+        let pyramid = match gfx::ReflectedGraphics::from_spirv(
+            device,
+            &screen_spv,
+            None,
+            Some(&pyramid_spv),
+            gpu::Rasterizer::default(),
+            &[gpu::BlendState::REPLACE],
+            None,
+            cache,
+            n.as_deref(),
+        ) {
+            Ok(g) => g,
+            Err(e) => match e {
+                gfx::error::ReflectedError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+
+        let empty = gfx::GTexture2D::new(
+            device,
+            1,
+            1,
+            gpu::Samples::S1,
+            gpu::TextureUsage::SAMPLED,
+            1,
+            gpu::Format::R32Float,
+            name.map(|n| format!("{}_empty", n)).as_deref(),
+        )?;
+
+        let sampler = device.create_sampler(&gpu::SamplerDesc::new(
+            gpu::FilterMode::Nearest,
+            gpu::WrapMode::ClampToEdge,
+            name.map(|n| format!("{}_sampler", n)),
+        ))?;
+
+        Ok(Self {
+            cull,
+            cull_bundles: Arc::default(),
+            pyramid,
+            pyramids: Arc::default(),
+            pyramid_bundles: Arc::default(),
+            empty,
+            sampler,
+            name: name.map(|n| n.to_string()),
+        })
+    }
+
+    /// Get or create the depth pyramid for a geometry buffer of width and height
+    ///
+    /// Each mip of the pyramid holds the furthest depth of the texels it covers in the mip above,
+    /// returns (pyramid, single mip views into the pyramid)
+    pub fn depth_pyramid(
+        &self,
+        device: &gpu::Device,
+        width: u32,
+        height: u32,
+    ) -> Result<(gfx::GTexture2D, Vec<gpu::TextureView>), gpu::Error> {
+        let mut pyramids = self.pyramids.lock().unwrap();
+        if pyramids.get(&(width, height)).is_none() {
+            let pyramid = gfx::GTexture2D::new(
+                device,
+                width,
+                height,
+                gpu::Samples::S1,
+                gpu::TextureUsage::COLOR_OUTPUT
+                    | gpu::TextureUsage::SAMPLED
+                    | gpu::TextureUsage::COPY_DST,
+                gfx::full_mip_levels(width, height),
+                gpu::Format::R32Float,
+                self.name
+                    .as_ref()
+                    .map(|n| format!("{}_depth_pyramid_width_{}_height_{}", n, width, height))
+                    .as_deref(),
+            )?;
+            let views = (0..pyramid.texture.mip_levels())
+                .map(|mip| {
+                    pyramid.create_view(&gpu::TextureViewDesc {
+                        dimension: gpu::TextureDimension::D2(
+                            (width >> mip).max(1),
+                            (height >> mip).max(1),
+                            gpu::Samples::S1,
+                        ),
+                        base_mip_level: mip,
+                        mip_levels: 1,
+                        base_array_layer: 0,
+                        name: None,
+                        format_change: None,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            pyramids.insert((width, height), (pyramid, views));
+        }
+        Ok(pyramids.get(&(width, height)).unwrap().clone())
+    }
+
+    fn pyramid_bundle(
+        &self,
+        device: &gpu::Device,
+        src: &gpu::TextureView,
+    ) -> Result<gfx::Bundle, gpu::Error> {
+        let mut bundles = self.pyramid_bundles.lock().unwrap();
+        if bundles.get(&src.id()).is_none() {
+            let b = match self
+                .pyramid
+                .bundle()
+                .unwrap()
+                .set_resource("u_depth", src)
+                .unwrap()
+                .set_resource("u_sampler", &self.sampler)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+            bundles.insert(src.id(), b);
+        }
+        Ok(bundles.get(&src.id()).unwrap().clone())
+    }
+
+    /// Build the depth pyramid of the geometry buffer used for occlusion culling
+    ///
+    /// Should be called after drawing to the geometry buffer (and resolving it if it is multisampled)
+    /// the next call to [`CullingRenderer::cull_pass`] with the buffer will test instances against this depth
+    pub fn depth_pyramid_pass(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        buffer: &GeometryBuffer,
+    ) -> Result<(), gpu::Error> {
+        let (_, views) = self.depth_pyramid(device, buffer.width, buffer.height)?;
+
+        for (mip, view) in views.iter().enumerate() {
+            let src = if mip == 0 {
+                &buffer.depth.view
+            } else {
+                &views[mip - 1]
+            };
+            let bundle = self.pyramid_bundle(device, src)?;
+
+            let mut pass = encoder.graphics_pass_reflected::<()>(
+                device,
+                &[gfx::Attachment {
+                    raw: gpu::Attachment::View(
+                        Cow::Owned(view.clone()),
+                        gpu::ClearValue::ColorFloat([0.0; 4]),
+                    ),
+                    load: gpu::LoadOp::DontCare,
+                    store: gpu::StoreOp::Store,
+                }],
+                &[],
+                None,
+                &self.pyramid,
+            )?;
+            pass.set_bundle_owned(bundle);
+            pass.push_i32("first", (mip == 0) as i32);
+            pass.draw(0, 3, 0, 1);
+            pass.finish();
+        }
+
+        Ok(())
+    }
+
+    fn cull_bundle(
+        &self,
+        device: &gpu::Device,
+        camera: &Camera,
+        instances: &CulledInstances,
+        pyramid: &gfx::GTexture2D,
+    ) -> Result<gfx::Bundle, gpu::Error> {
+        let mut bundles = self.cull_bundles.lock().unwrap();
+        let pyramid_id: u64 = unsafe { std::mem::transmute(pyramid.raw_image()) };
+        let key = (camera.buffer.id(), instances.instances.id(), pyramid_id);
+        if bundles.get(&key).is_none() {
+            let b = match self
+                .cull
+                .bundle()
+                .unwrap()
+                .set_resource("u_camera", camera)
+                .unwrap()
+                .set_resource("u_instances", &instances.instances)
+                .unwrap()
+                .set_resource("u_visible", &instances.visible)
+                .unwrap()
+                .set_resource("u_args", &instances.indirect)
+                .unwrap()
+                .set_resource("u_pyramid", pyramid)
+                .unwrap()
+                .set_resource("u_sampler", &self.sampler)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+            bundles.insert(key, b);
+        }
+        Ok(bundles.get(&key).unwrap().clone())
+    }
+
+    /// Cull each of the instances against the camera's frustum
+    ///
+    /// If occlusion is some then instances hidden behind the depth pyramid of the geometry buffer are culled too,
+    /// the first frame with a new geometry buffer will test against an empty pyramid so no instances are occluded
+    pub fn cull_pass<'a>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        camera: &Camera,
+        instances: impl IntoIterator<Item = &'a CulledInstances>,
+        occlusion: Option<&GeometryBuffer>,
+    ) -> Result<(), gpu::Error> {
+        let pyramid = match occlusion {
+            Some(buffer) => {
+                let new = self
+                    .pyramids
+                    .lock()
+                    .unwrap()
+                    .get(&(buffer.width, buffer.height))
+                    .is_none();
+                let (pyramid, _) = self.depth_pyramid(device, buffer.width, buffer.height)?;
+                if new {
+                    encoder.clear_texture(
+                        pyramid.whole_slice_owned(),
+                        gpu::ClearValue::ColorFloat([1.0; 4]),
+                    );
+                }
+                Some(pyramid)
+            }
+            None => None,
+        };
+
+        for instances in instances {
+            let bundle = self.cull_bundle(
+                device,
+                camera,
+                instances,
+                pyramid.as_ref().unwrap_or(&self.empty),
+            )?;
+
+            let mut pass = encoder.compute_pass_reflected(device, &self.cull)?;
+            pass.set_bundle_owned(bundle);
+            pass.push_u32("count", instances.length() as u32);
+            if let Some(pyramid) = &pyramid {
+                pass.push_i32("occlusion", 1);
+                pass.push_f32("pyramid_width", pyramid.width() as f32);
+                pass.push_f32("pyramid_height", pyramid.height() as f32);
+                pass.push_f32("max_lod", (pyramid.texture.mip_levels() - 1) as f32);
+            } else {
+                pass.push_i32("occlusion", 0);
+                pass.push_f32("pyramid_width", 1.0);
+                pass.push_f32("pyramid_height", 1.0);
+                pass.push_f32("max_lod", 0.0);
+            }
+            pass.dispatch(1, 1, 1);
+            pass.finish();
+        }
+
+        Ok(())
+    }
+
+    /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
+    /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
+    /// This function drops Descriptor sets cached by self
+    pub fn clear(&mut self) {
+        self.cull_bundles.lock().unwrap().clear();
+        self.pyramid_bundles.lock().unwrap().clear();
+        self.pyramids.lock().unwrap().clear();
+        self.cull.clear();
+        self.pyramid.clear();
+    }
+}
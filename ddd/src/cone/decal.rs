@@ -0,0 +1,300 @@
+//! Decals projected onto the geometry buffer
+//!
+//! [`DecalData`] attributes of a decal
+//! [`Decal`] a decal with its own textures
+//! [`DecalRenderer`] for blending [`Decal`]s into a [`GeometryBuffer`]
+//!
+//! A decal is a box, the unit cube transformed by its model matrix. The world position of the geometry under each pixel
+//! of the box is reconstructed from the depth of the geometry buffer and if it is inside the box the decal's textures are
+//! sampled with the box's local x and y and blended into the albedo, normal and roughness maps. Decals should be drawn
+//! after materials and before lighting.
+
+use gfx::prelude::*;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::cone::GeometryBuffer;
+use crate::utils::Camera;
+
+/// Describes parameters sent to the gpu for decals
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecalData {
+    /// transforms the unit cube centered on the origin to the box of the decal, the decal projects along the local z axis
+    pub model: glam::Mat4,
+    /// the inverse of model
+    pub inv_model: glam::Mat4,
+    /// multiplies the albedo texture, the alpha channel multiplies the opacity of the decal
+    pub albedo: glam::Vec4,
+    /// the roughness of the decal, multiplies the roughness texture if there is one
+    pub roughness: f32,
+    /// how much of the decal's normal is blended into the normal map
+    pub normal_strength: f32,
+    /// how much of the decal's roughness is blended into the roughness map
+    pub roughness_strength: f32,
+    /// the cosine of the angle between the surface and the decal's z axis at which the decal has faded out completely
+    pub angle_fade: f32,
+}
+
+unsafe impl bytemuck::Pod for DecalData {}
+unsafe impl bytemuck::Zeroable for DecalData {}
+
+impl Default for DecalData {
+    fn default() -> Self {
+        Self::new(glam::Mat4::IDENTITY)
+    }
+}
+
+impl DecalData {
+    /// Create a new decal from its model matrix
+    pub fn new(model: glam::Mat4) -> Self {
+        Self {
+            model,
+            inv_model: model.inverse(),
+            albedo: glam::Vec4::ONE,
+            roughness: 1.0,
+            normal_strength: 1.0,
+            roughness_strength: 0.0,
+            angle_fade: 0.2,
+        }
+    }
+
+    /// Set the model matrix and its inverse
+    pub fn set_model(&mut self, model: glam::Mat4) {
+        self.model = model;
+        self.inv_model = model.inverse();
+    }
+}
+
+/// A decal with its own textures
+///
+/// The alpha channel of the albedo texture is the opacity of the decal
+/// If there is no normal texture then the normal map isn't changed and if there is no roughness texture then the
+/// roughness is taken from [`DecalData::roughness`]
+#[derive(Debug, Clone)]
+pub struct Decal {
+    pub uniform: gfx::Uniform<DecalData>,
+    pub albedo: gfx::GTexture2D,
+    pub normal: Option<gfx::GTexture2D>,
+    pub roughness: Option<gfx::GTexture2D>,
+}
+
+impl Decal {
+    pub fn new(
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        data: DecalData,
+        albedo: &gfx::GTexture2D,
+        normal: Option<&gfx::GTexture2D>,
+        roughness: Option<&gfx::GTexture2D>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let uniform = gfx::Uniform::new(encoder, device, data, name)?;
+        Ok(Self {
+            uniform,
+            albedo: albedo.clone(),
+            normal: normal.cloned(),
+            roughness: roughness.cloned(),
+        })
+    }
+
+    /// Update the data of the decal on the gpu
+    ///
+    /// The update will only be complete when the command encoder is submitted
+    pub fn update_gpu(&mut self, encoder: &mut gfx::CommandEncoder<'_>, data: DecalData) {
+        self.uniform.data = data;
+        self.uniform.update_gpu_owned(encoder);
+    }
+}
+
+/// Blends [`Decal`]s into the albedo, normal and roughness maps of a [`GeometryBuffer`]
+///
+/// The geometry buffer must not be multisampled, or must be resolved before decals are drawn
+pub struct DecalRenderer {
+    pub pipeline: gfx::ReflectedGraphics,
+    /// map from Camera to descriptor set
+    pub camera_sets: Arc<Mutex<HashMap<u64, gpu::DescriptorSet>>>,
+    /// map from Decal to descriptor set of the decal's data
+    pub decal_sets: Arc<Mutex<HashMap<u64, gpu::DescriptorSet>>>,
+    /// map from GeometryBuffer to descriptor set of the depth
+    pub buffer_sets: Arc<Mutex<HashMap<u64, gpu::DescriptorSet>>>,
+    /// map from Decal to descriptor set of the decal's textures
+    pub texture_sets: Arc<Mutex<HashMap<u64, gpu::DescriptorSet>>>,
+    pub sampler: gpu::Sampler,
+}
+
+impl DecalRenderer {
+    pub fn new(
+        device: &gpu::Device,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let sampler = device.create_sampler(&gpu::SamplerDesc {
+            name: name.map(|n| format!("{}_sampler", n)),
+            ..gpu::SamplerDesc::LINEAR
+        })?;
+
+        let vert = gpu::include_spirv!("../../shaders/cone/decal/decal.vert.spv");
+        let frag = gpu::include_spirv!("../../shaders/cone/decal/decal.frag.spv");
+
+        // blend the color but keep the destination alpha
+        let blend = gpu::BlendState {
+            src_alpha_blend: gpu::BlendFactor::Zero,
+            dst_alpha_blend: gpu::BlendFactor::One,
+            ..gpu::BlendState::ALPHA
+        };
+
+        let pipeline = match gfx::ReflectedGraphics::from_spirv(
+            device,
+            &vert,
+            None,
+            Some(&frag),
+            gpu::Rasterizer {
+                // draw the back faces so that the decal is still drawn when the camera is inside its box
+                cull_face: gpu::CullFace::Front,
+                ..Default::default()
+            },
+            &[blend; 3],
+            None,
+            cache,
+            name,
+        ) {
+            Ok(g) => g,
+            Err(e) => match e {
+                gfx::error::ReflectedError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+
+        Ok(Self {
+            pipeline,
+            camera_sets: Arc::default(),
+            decal_sets: Arc::default(),
+            buffer_sets: Arc::default(),
+            texture_sets: Arc::default(),
+            sampler,
+        })
+    }
+
+    fn set(
+        &self,
+        device: &gpu::Device,
+        map: &Mutex<HashMap<u64, gpu::DescriptorSet>>,
+        key: u64,
+        set: u32,
+        resources: &[(&str, &dyn gfx::Resource)],
+    ) -> Result<gpu::DescriptorSet, gpu::Error> {
+        let mut map = map.lock().unwrap();
+        if let Some(s) = map.get(&key) {
+            return Ok(s.clone());
+        }
+        let mut bundle = self.pipeline.bundle().unwrap();
+        for (name, resource) in resources {
+            bundle = bundle.set_resource(name, *resource).unwrap();
+        }
+        let s = match bundle.build_set(device, set) {
+            Ok(s) => s,
+            Err(e) => match e {
+                gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+        map.insert(key, s.clone());
+        Ok(s)
+    }
+
+    /// Blend the decals into the albedo, normal and roughness maps of the geometry buffer
+    pub fn pass<'a>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &'a GeometryBuffer,
+        camera: &Camera,
+        decals: impl IntoIterator<Item = &'a Decal>,
+    ) -> Result<(), gpu::Error> {
+        let attachments = ["albedo", "normal", "roughness"]
+            .iter()
+            .map(|n| gfx::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Borrowed(&buffer.get(n).unwrap().view),
+                    gpu::ClearValue::ColorFloat([0.0; 4]),
+                ),
+                load: gpu::LoadOp::Load,
+                store: gpu::StoreOp::Store,
+            })
+            .collect::<Vec<_>>();
+
+        let camera_set = self.set(
+            device,
+            &self.camera_sets,
+            camera.buffer.id(),
+            0,
+            &[("u_camera", camera)],
+        )?;
+        let buffer_set = self.set(
+            device,
+            &self.buffer_sets,
+            buffer.id,
+            2,
+            &[("u_depth", &buffer.depth), ("u_sampler", &self.sampler)],
+        )?;
+
+        let mut pass = encoder.graphics_pass_reflected::<()>(
+            device,
+            &attachments,
+            &[],
+            None,
+            &self.pipeline,
+        )?;
+        pass.bind_descriptor_owned(0, camera_set);
+        pass.bind_descriptor_owned(2, buffer_set);
+        pass.push_f32("width", buffer.width as _);
+        pass.push_f32("height", buffer.height as _);
+
+        for decal in decals {
+            let key = decal.uniform.buffer.id();
+            let decal_set = self.set(
+                device,
+                &self.decal_sets,
+                key,
+                1,
+                &[("u_decal", &decal.uniform)],
+            )?;
+            let texture_set = self.set(
+                device,
+                &self.texture_sets,
+                key,
+                3,
+                &[
+                    ("u_albedo", &decal.albedo),
+                    ("u_normal", decal.normal.as_ref().unwrap_or(&decal.albedo)),
+                    (
+                        "u_roughness",
+                        decal.roughness.as_ref().unwrap_or(&decal.albedo),
+                    ),
+                ],
+            )?;
+            pass.bind_descriptor_owned(1, decal_set);
+            pass.bind_descriptor_owned(3, texture_set);
+            pass.push_i32("has_normal", decal.normal.is_some() as i32);
+            pass.push_i32("has_roughness", decal.roughness.is_some() as i32);
+            pass.draw(0, 36, 0, 1);
+        }
+
+        Ok(())
+    }
+
+    /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
+    /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
+    /// This function drops Descriptor sets cached by self
+    pub fn clear(&mut self) {
+        self.camera_sets.lock().unwrap().clear();
+        self.decal_sets.lock().unwrap().clear();
+        self.buffer_sets.lock().unwrap().clear();
+        self.texture_sets.lock().unwrap().clear();
+        self.pipeline.clear();
+    }
+}
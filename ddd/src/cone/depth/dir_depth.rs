@@ -1,21 +1,448 @@
-/// projection + view matrices and shadow strength for DirLights
+//! Cascaded shadow maps to be used with [`crate::cone::DirLight`] as well as the pipeline for drawing to them
+//!
+//! # Data and Map types
+//! [`CascadedDepthData`] information about the cascades sent to the gpu
+//! [`CascadedDepthMap`] depth of each cascade stored as a [`gfx::GTexture2DArray`] and a [`gfx::Uniform<CascadedDepthData>`]
+//!
+//! # Renderer types
+//! [`DirDepthMapRenderer`] used for rendering to [`CascadedDepthMap`]
+//!
+//! The view frustum of the camera is split into cascades along its depth, each cascade gets its own layer in the
+//! depth map covering only its slice of the frustum so that shadows close to the camera get more resolution.
+//! The cascade matrices have to be recalculated whenever the camera or light moves see [`CascadedDepthMap::update`]
+
+use crate::cone::*;
+use crate::utils::*;
+
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::{borrow::Cow, collections::HashMap};
+
+/// The maximum number of cascades a [`CascadedDepthMap`] can have
+pub const MAX_CASCADES: usize = 8;
+
+/// view projection matrices and split distances of each cascade
 #[repr(C)]
-#[derive(Debug, Copy, Clone)]
-pub struct DirShadowData {
-    /// projection matrix, transforms from view space to screen space
-    pub projection: glam::Mat4,
-    /// view matrix, transforms from world space to view space
-    pub view: glam::Mat4,
-    /// position of the dir shadow caster
-    pub position: glam::Vec3,
-    /// glsl interprets position as a vec3 which has the same memory layout as vec4
-    pub _padding1: u32,
-    /// strength of shadow, how sharp the shadow should be
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CascadedDepthData {
+    /// projection * view matrix for each cascade, transforms from world space to the cascades screen space
+    pub matrices: [glam::Mat4; MAX_CASCADES],
+    /// the view space depth where each cascade ends, packed into vec4s to match glsl array alignment
+    pub splits: [glam::Vec4; MAX_CASCADES / 4],
+    /// the number of cascades in use
+    pub count: u32,
+    /// strength of the shadow, the radius in texels of pcf filtering
     pub strength: f32,
-    /// match alignment
-    pub _padding2: u32,
-    pub _padding3: u64,
+    /// bias of the shadow map, subtracted from test depths to avoid shadow acne
+    pub bias: f32,
+    /// fraction of each cascade at its far end that is blended into the next cascade
+    pub blend: f32,
+}
+
+unsafe impl bytemuck::Pod for CascadedDepthData {}
+unsafe impl bytemuck::Zeroable for CascadedDepthData {}
+
+impl CascadedDepthData {
+    /// Calculate the view space depth that each cascade ends at
+    ///
+    /// lambda blends between uniform splits at 0.0 and logarithmic splits at 1.0
+    pub fn split_distances(z_near: f32, z_far: f32, cascades: u32, lambda: f32) -> Vec<f32> {
+        (1..=cascades)
+            .map(|i| {
+                let f = i as f32 / cascades as f32;
+                let log = z_near * (z_far / z_near).powf(f);
+                let uniform = z_near + (z_far - z_near) * f;
+                lambda * log + (1.0 - lambda) * uniform
+            })
+            .collect()
+    }
+
+    /// Create the cascade data for a light seen from a camera
+    ///
+    /// z_near is the near plane of the camera's projection
+    /// cascades is the number of cascades (max [`MAX_CASCADES`])
+    /// lambda blends between uniform splits at 0.0 and logarithmic splits at 1.0
+    /// size is the width and height of each cascade in texels, used to stop shadows shimmering as the camera moves
+    pub fn new(
+        light: &DirLightData,
+        camera: &CameraData,
+        z_near: f32,
+        cascades: u32,
+        lambda: f32,
+        size: u32,
+        strength: f32,
+        bias: f32,
+        blend: f32,
+    ) -> Self {
+        let count = cascades.min(MAX_CASCADES as u32);
+        let direction = light.direction.normalize();
+        let up = if direction.cross(glam::Vec3::Y).length_squared() < 1e-6 {
+            glam::Vec3::Z
+        } else {
+            glam::Vec3::Y
+        };
+        let rotation = glam::Mat4::look_at_rh(glam::Vec3::ZERO, direction, up);
+        let inv_rotation = rotation.inverse();
+
+        // corners of the camera frustum on the near and far plane in world space
+        let inv_camera = (camera.projection * camera.view).inverse();
+        let mut near_corners = [glam::Vec3::ZERO; 4];
+        let mut far_corners = [glam::Vec3::ZERO; 4];
+        for (i, (x, y)) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+            .into_iter()
+            .enumerate()
+        {
+            near_corners[i] = inv_camera.project_point3(glam::vec3(x, y, 0.0));
+            far_corners[i] = inv_camera.project_point3(glam::vec3(x, y, 1.0));
+        }
+
+        let distances = Self::split_distances(z_near, camera.z_far, count, lambda);
+        let mut matrices = [glam::Mat4::IDENTITY; MAX_CASCADES];
+        let mut splits = [0.0; MAX_CASCADES];
+        let mut start = z_near;
+        for (i, &end) in distances.iter().enumerate() {
+            let t0 = (start - z_near) / (camera.z_far - z_near);
+            let t1 = (end - z_near) / (camera.z_far - z_near);
+            let corners = near_corners
+                .iter()
+                .zip(&far_corners)
+                .flat_map(|(&n, &f)| [n.lerp(f, t0), n.lerp(f, t1)])
+                .collect::<Vec<_>>();
+
+            // fit a sphere around the slice so the projection doesn't change size as the camera rotates
+            let mut center = corners.iter().fold(glam::Vec3::ZERO, |a, &c| a + c) / 8.0;
+            let radius = corners
+                .iter()
+                .map(|&c| c.distance(center))
+                .fold(0.0f32, f32::max);
+            let radius = (radius * 16.0).ceil() / 16.0;
+
+            // snap the center to whole texels
+            let texel = 2.0 * radius / size as f32;
+            let light_center = rotation.transform_point3(center);
+            let snapped = glam::vec3(
+                (light_center.x / texel).floor() * texel,
+                (light_center.y / texel).floor() * texel,
+                light_center.z,
+            );
+            center = inv_rotation.transform_point3(snapped);
+
+            // pull the eye back so that casters outside of the slice still cast shadows into it
+            let eye = center - direction * 3.0 * radius;
+            let view = glam::Mat4::look_at_rh(eye, center, up);
+            let projection =
+                glam::Mat4::orthographic_rh(-radius, radius, -radius, radius, 0.0, 4.0 * radius);
+
+            matrices[i] = projection * view;
+            splits[i] = end;
+            start = end;
+        }
+
+        Self {
+            matrices,
+            splits: [
+                glam::Vec4::from_slice(&splits[0..4]),
+                glam::Vec4::from_slice(&splits[4..8]),
+            ],
+            count,
+            strength,
+            bias,
+            blend,
+        }
+    }
+}
+
+/// Depth information to be used with a [`crate::cone::DirLight`]
+///
+/// Depth of each cascade is stored as a layer of a [`gfx::GTexture2DArray`] and how to interpret it as a [`gfx::Uniform<CascadedDepthData>`]
+#[derive(Debug, Clone)]
+pub struct CascadedDepthMap {
+    pub(crate) id: u64,
+    pub texture: gfx::GTexture2DArray,
+    pub layers: Vec<gpu::TextureView>,
+    pub uniform: gfx::Uniform<CascadedDepthData>,
+    pub sampler: gpu::Sampler,
+    /// width and height of each cascade
+    pub size: u32,
+    /// blends between uniform (0.0) and logarithmic (1.0) splits
+    pub lambda: f32,
+}
+
+impl std::hash::Hash for CascadedDepthMap {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl std::cmp::PartialEq for CascadedDepthMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl std::cmp::Eq for CascadedDepthMap {}
+
+impl CascadedDepthMap {
+    /// Create a new [`CascadedDepthMap`]
+    ///
+    /// cascades is the number of cascades (max [`MAX_CASCADES`])
+    /// size is the width and height of each cascade
+    /// lambda blends between uniform splits at 0.0 and logarithmic splits at 1.0
+    /// blend is the fraction of each cascade at its far end that is blended into the next
+    ///
+    /// The cascades are empty until [`CascadedDepthMap::update`] is called
+    pub fn new(
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        cascades: u32,
+        size: u32,
+        lambda: f32,
+        strength: f32,
+        bias: f32,
+        blend: f32,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let count = cascades.min(MAX_CASCADES as u32).max(1);
+        let uniform = gfx::Uniform::new(
+            encoder,
+            device,
+            CascadedDepthData {
+                matrices: [glam::Mat4::IDENTITY; MAX_CASCADES],
+                splits: [glam::Vec4::ZERO; MAX_CASCADES / 4],
+                count: 0,
+                strength,
+                bias,
+                blend,
+            },
+            name.as_ref()
+                .map(|n| format!("{}_uniform", n))
+                .as_ref()
+                .map(|n| &**n),
+        )?;
+        let texture = gfx::GTexture2DArray::from_formats(
+            device,
+            size,
+            size,
+            gpu::Samples::S1,
+            count,
+            gpu::TextureUsage::SAMPLED | gpu::TextureUsage::DEPTH_OUTPUT,
+            1,
+            gfx::alt_formats(gpu::Format::Depth32Float),
+            name.as_ref()
+                .map(|n| format!("{}_texture", n))
+                .as_ref()
+                .map(|n| &**n),
+        )?
+        .unwrap();
+        let layers = (0..count)
+            .map(|i| texture.layer_view(i))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let sampler = device.create_sampler(&gpu::SamplerDesc::new(
+            gpu::FilterMode::Linear,
+            gpu::WrapMode::ClampToEdge,
+            name.as_ref().map(|n| format!("{}_sampler", n)),
+        ))?;
+
+        Ok(Self {
+            id: unsafe { std::mem::transmute(texture.raw_image()) },
+            texture,
+            layers,
+            uniform,
+            sampler,
+            size,
+            lambda,
+        })
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The number of cascades in the map
+    pub fn cascades(&self) -> u32 {
+        self.layers.len() as u32
+    }
+
+    /// Recalculate the cascades to cover the view of the camera and update them on the gpu
+    ///
+    /// z_near is the near plane of the camera's projection
+    /// Should be called whenever the camera or light moves before rendering to the map
+    pub fn update<'a>(
+        &mut self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        light: &DirLightData,
+        camera: &CameraData,
+        z_near: f32,
+    ) {
+        let data = &self.uniform.data;
+        self.uniform.data = CascadedDepthData::new(
+            light,
+            camera,
+            z_near,
+            self.cascades(),
+            self.lambda,
+            self.size,
+            data.strength,
+            data.bias,
+            data.blend,
+        );
+        self.uniform.update_gpu_owned(encoder);
+    }
 }
 
-unsafe impl bytemuck::Pod for DirShadowData {}
-unsafe impl bytemuck::Zeroable for DirShadowData {}
+impl std::ops::Deref for CascadedDepthMap {
+    type Target = gfx::Uniform<CascadedDepthData>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.uniform
+    }
+}
+
+impl std::ops::DerefMut for CascadedDepthMap {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.uniform
+    }
+}
+
+/// Used for rendering cascaded depth maps that correspond to directional lights
+pub struct DirDepthMapRenderer {
+    pub pipeline: gfx::ReflectedGraphics,
+    /// map from (instances, shadow) to bundle
+    pub bundles: Arc<Mutex<HashMap<(u64, u64), gfx::Bundle>>>,
+}
+
+impl DirDepthMapRenderer {
+    /// Create a new [`DirDepthMapRenderer`]
+    ///
+    /// Used for rendering depth maps that correspond to directional lights
+    ///
+    /// cull_face determins if to cull a face or not
+    /// front_face determins what the front face is
+    pub fn new(
+        device: &gpu::Device,
+        cull_face: gpu::CullFace,
+        front_face: gpu::FrontFace,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let pipeline = Self::pipeline(device, cull_face, front_face, cache, name)?;
+        Ok(Self {
+            pipeline,
+            bundles: Arc::default(),
+        })
+    }
+
+    /// Create the pipeline used for rendering instanced meshes shadows
+    pub fn pipeline(
+        device: &gpu::Device,
+        cull_face: gpu::CullFace,
+        front_face: gpu::FrontFace,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<gfx::ReflectedGraphics, gpu::Error> {
+        let vertex_spv = gpu::include_spirv!("../../../shaders/cone/shadow_passes/dir.vert.spv");
+
+        match gfx::ReflectedGraphics::from_spirv(
+            device,
+            &vertex_spv,
+            None,
+            None,
+            gpu::Rasterizer {
+                cull_face,
+                front_face,
+                depth_clamp: false,
+                polygon_mode: gpu::PolygonMode::Fill,
+                primitive_topology: gpu::PrimitiveTopology::TriangleList,
+                line_width: 1.0,
+                depth_bias: false,
+                depth_bias_constant: 0.01,
+                depth_bias_slope: 1.0,
+            },
+            &[],
+            Some(gpu::DepthStencilState::default_depth()),
+            cache,
+            name.map(|n| format!("{}_renderer", n))
+                .as_ref()
+                .map(|n| &**n),
+        ) {
+            Ok(p) => Ok(p),
+            Err(e) => match e {
+                gfx::error::ReflectedError::Gpu(e) => Err(e)?,
+                _ => unreachable!(),
+            },
+        }
+    }
+
+    /// Draw each of the meshes shadow into every cascade of the [`CascadedDepthMap`] supplied
+    ///
+    /// The map should have been updated with [`CascadedDepthMap::update`] first
+    pub fn pass<'a, V: gfx::Vertex>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        shadow: &'a CascadedDepthMap,
+        meshes: impl IntoIterator<Item = (&'a gfx::Mesh<V>, &'a Instances)>,
+        clear: bool,
+    ) -> Result<(), gpu::Error> {
+        let meshes = meshes.into_iter().collect::<Vec<_>>();
+        for (cascade, layer) in shadow.layers.iter().enumerate() {
+            let mut pass = encoder.graphics_pass_reflected(
+                device,
+                &[],
+                &[],
+                Some(gfx::Attachment {
+                    raw: gpu::Attachment::View(Cow::Borrowed(layer), gpu::ClearValue::Depth(1.0)),
+                    load: if clear {
+                        gpu::LoadOp::Clear
+                    } else {
+                        gpu::LoadOp::Load
+                    },
+                    store: gpu::StoreOp::Store,
+                }),
+                &self.pipeline,
+            )?;
+
+            let mut bundles = self.bundles.lock().unwrap();
+            for (mesh, instance) in &meshes {
+                let key = (instance.buffer.id(), shadow.uniform.buffer.id());
+
+                if bundles.get(&key).is_none() {
+                    let b = match self
+                        .pipeline
+                        .bundle()
+                        .unwrap()
+                        .set_resource("u_instance", *instance)
+                        .unwrap()
+                        .set_resource("u_shadow", &shadow.uniform)
+                        .unwrap()
+                        .build(device)
+                    {
+                        Ok(b) => b,
+                        Err(e) => match e {
+                            gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                            e => unreachable!("{}", e),
+                        },
+                    };
+                    bundles.insert(key, b);
+                }
+
+                let bundle = bundles.get(&key).unwrap().clone();
+
+                pass.push_u32("cascade", cascade as u32);
+                pass.set_bundle_owned(bundle);
+                pass.draw_instanced_mesh_ref(mesh, 0, instance.length as _);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
+    /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
+    /// This function drops Descriptor sets cached by self
+    pub fn clear(&mut self) {
+        self.bundles.lock().unwrap().clear();
+        self.pipeline.clear();
+    }
+}
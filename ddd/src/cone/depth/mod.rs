@@ -2,6 +2,8 @@
 
 pub mod dir_depth;
 pub mod point_depth;
+pub mod spot_depth;
 
 pub use dir_depth::*;
 pub use point_depth::*;
+pub use spot_depth::*;
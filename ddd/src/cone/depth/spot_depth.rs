@@ -0,0 +1,330 @@
+//! Shadow maps to be used with [`crate::cone::SpotLight`] as well as the pipeline for drawing to them
+//!
+//! # Data and Map types
+//! [`SpotDepthData`] information about the shadow map sent to the gpu
+//! [`SpotDepthMap`] a depth map stored as a [`gfx::GTexture2D`] and a [`gfx::Uniform<SpotDepthData>`]
+//!
+//! # Renderer types
+//! [`SpotDepthMapRenderer`] used for rendering to [`SpotDepthMap`]
+
+use crate::cone::*;
+use crate::utils::*;
+
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::{borrow::Cow, collections::HashMap};
+
+/// view + projection matrices and strength for spot shadow
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpotDepthData {
+    /// view matrix looking down the spot light
+    pub view: glam::Mat4,
+    /// projection matrix covering the cone of the spot light
+    pub projection: glam::Mat4,
+    /// position of the shadow caster
+    pub position: glam::Vec3,
+    /// the distance to the far plane of the projection matrix
+    pub z_far: f32,
+    /// strength of the shadow, the radius in texels of pcf filtering
+    pub strength: f32,
+    /// bias of the shadow map, subtracted from test depths to avoid shadow acne
+    pub bias: f32,
+}
+
+unsafe impl bytemuck::Pod for SpotDepthData {}
+unsafe impl bytemuck::Zeroable for SpotDepthData {}
+
+impl SpotDepthData {
+    /// create SpotDepthData from raw matrices
+    pub fn from_raw(
+        view: glam::Mat4,
+        projection: glam::Mat4,
+        position: glam::Vec3,
+        z_far: f32,
+        strength: f32,
+        bias: f32,
+    ) -> Self {
+        Self {
+            view,
+            projection,
+            position,
+            z_far,
+            strength,
+            bias,
+        }
+    }
+
+    /// create SpotDepthData looking from position in direction with a perspective projection
+    pub fn new(
+        position: glam::Vec3,
+        direction: glam::Vec3,
+        fovy: f32,
+        z_near: f32,
+        z_far: f32,
+        strength: f32,
+        bias: f32,
+    ) -> Self {
+        let direction = direction.normalize();
+        let up = if direction.cross(glam::Vec3::Y).length_squared() < 1e-6 {
+            glam::Vec3::Z
+        } else {
+            glam::Vec3::Y
+        };
+        let view = glam::Mat4::look_at_rh(position, position + direction, up);
+        let projection = glam::Mat4::perspective_rh(fovy, 1.0, z_near, z_far);
+        Self::from_raw(view, projection, position, z_far, strength, bias)
+    }
+
+    /// Create the shadow data from a light, the projection covers the outer cone of the light
+    pub fn from_light(
+        light: &SpotLightData,
+        z_near: f32,
+        z_far: f32,
+        strength: f32,
+        bias: f32,
+    ) -> Self {
+        let fovy = 2.0 * light.outer_cos.clamp(-1.0, 1.0).acos();
+        Self::new(
+            light.position,
+            light.direction,
+            fovy.min(std::f32::consts::PI - 0.01),
+            z_near,
+            z_far,
+            strength,
+            bias,
+        )
+    }
+}
+
+/// Depth information to be used with a [`crate::cone::SpotLight`]
+///
+/// Depth is stored as a [`gfx::GTexture2D`] and how to interprate it as a [`gfx::Uniform<SpotDepthData>`]
+#[derive(Debug, Clone)]
+pub struct SpotDepthMap {
+    pub(crate) id: u64,
+    pub texture: gfx::GTexture2D,
+    pub uniform: gfx::Uniform<SpotDepthData>,
+    pub sampler: gpu::Sampler,
+}
+
+impl std::hash::Hash for SpotDepthMap {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl std::cmp::PartialEq for SpotDepthMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl std::cmp::Eq for SpotDepthMap {}
+
+impl SpotDepthMap {
+    pub fn new(
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        data: SpotDepthData,
+        size: u32,
+        name: Option<&str>,
+    ) -> Result<SpotDepthMap, gpu::Error> {
+        let uniform = gfx::Uniform::new(
+            encoder,
+            device,
+            data,
+            name.as_ref()
+                .map(|n| format!("{}_uniform", n))
+                .as_ref()
+                .map(|n| &**n),
+        )?;
+        let texture = gfx::GTexture2D::from_formats(
+            device,
+            size,
+            size,
+            gpu::Samples::S1,
+            gpu::TextureUsage::SAMPLED | gpu::TextureUsage::DEPTH_OUTPUT,
+            1,
+            gfx::alt_formats(gpu::Format::Depth32Float),
+            name.as_ref()
+                .map(|n| format!("{}_texture", n))
+                .as_ref()
+                .map(|n| &**n),
+        )?
+        .unwrap();
+
+        let sampler = device.create_sampler(&gpu::SamplerDesc::new(
+            gpu::FilterMode::Linear,
+            gpu::WrapMode::ClampToEdge,
+            name.as_ref().map(|n| format!("{}_sampler", n)),
+        ))?;
+
+        Ok(SpotDepthMap {
+            id: unsafe { std::mem::transmute(texture.raw_image()) },
+            texture,
+            uniform,
+            sampler,
+        })
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl std::ops::Deref for SpotDepthMap {
+    type Target = gfx::Uniform<SpotDepthData>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.uniform
+    }
+}
+
+impl std::ops::DerefMut for SpotDepthMap {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.uniform
+    }
+}
+
+/// Used for rendering depth maps that correspond to spot lights
+pub struct SpotDepthMapRenderer {
+    pub pipeline: gfx::ReflectedGraphics,
+    /// map from (instances, shadow) to bundle
+    pub bundles: Arc<Mutex<HashMap<(u64, u64), gfx::Bundle>>>,
+}
+
+impl SpotDepthMapRenderer {
+    /// Create a new [`SpotDepthMapRenderer`]
+    ///
+    /// Used for rendering depth maps that correspond to spot lights
+    ///
+    /// cull_face determins if to cull a face or not
+    /// front_face determins what the front face is
+    pub fn new(
+        device: &gpu::Device,
+        cull_face: gpu::CullFace,
+        front_face: gpu::FrontFace,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let pipeline = Self::pipeline(device, cull_face, front_face, cache, name)?;
+        Ok(Self {
+            pipeline,
+            bundles: Arc::default(),
+        })
+    }
+
+    /// Create the pipeline used for rendering instanced meshes shadows
+    pub fn pipeline(
+        device: &gpu::Device,
+        cull_face: gpu::CullFace,
+        front_face: gpu::FrontFace,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<gfx::ReflectedGraphics, gpu::Error> {
+        let vertex_spv = gpu::include_spirv!("../../../shaders/cone/shadow_passes/spot.vert.spv");
+
+        let fragment_spv =
+            gpu::include_spirv!("../../../shaders/cone/shadow_passes/shadow.frag.spv");
+
+        match gfx::ReflectedGraphics::from_spirv(
+            device,
+            &vertex_spv,
+            None,
+            Some(&fragment_spv),
+            gpu::Rasterizer {
+                cull_face,
+                front_face,
+                depth_clamp: false,
+                polygon_mode: gpu::PolygonMode::Fill,
+                primitive_topology: gpu::PrimitiveTopology::TriangleList,
+                line_width: 1.0,
+                depth_bias: false,
+                depth_bias_constant: 0.01,
+                depth_bias_slope: 1.0,
+            },
+            &[],
+            Some(gpu::DepthStencilState::default_depth()),
+            cache,
+            name.map(|n| format!("{}_renderer", n))
+                .as_ref()
+                .map(|n| &**n),
+        ) {
+            Ok(p) => Ok(p),
+            Err(e) => match e {
+                gfx::error::ReflectedError::Gpu(e) => Err(e)?,
+                _ => unreachable!(),
+            },
+        }
+    }
+
+    /// Draw each of the meshes shadow into the [`SpotDepthMap`] supplied
+    pub fn pass<'a, V: gfx::Vertex>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        shadow: &'a SpotDepthMap,
+        meshes: impl IntoIterator<Item = (&'a gfx::Mesh<V>, &'a Instances)>,
+        clear: bool,
+    ) -> Result<(), gpu::Error> {
+        let mut pass = encoder.graphics_pass_reflected(
+            device,
+            &[],
+            &[],
+            Some(gfx::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Borrowed(&shadow.texture.view),
+                    gpu::ClearValue::Depth(1.0),
+                ),
+                load: if clear {
+                    gpu::LoadOp::Clear
+                } else {
+                    gpu::LoadOp::Load
+                },
+                store: gpu::StoreOp::Store,
+            }),
+            &self.pipeline,
+        )?;
+
+        let mut bundles = self.bundles.lock().unwrap();
+        for (mesh, instance) in meshes {
+            let key = (instance.buffer.id(), shadow.uniform.buffer.id());
+
+            if bundles.get(&key).is_none() {
+                let b = match self
+                    .pipeline
+                    .bundle()
+                    .unwrap()
+                    .set_resource("u_instance", instance)
+                    .unwrap()
+                    .set_resource("u_shadow", &shadow.uniform)
+                    .unwrap()
+                    .build(device)
+                {
+                    Ok(b) => b,
+                    Err(e) => match e {
+                        gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                        e => unreachable!("{}", e),
+                    },
+                };
+                bundles.insert(key, b);
+            }
+
+            let bundle = bundles.get(&key).unwrap().clone();
+
+            pass.set_bundle_owned(bundle);
+            pass.draw_instanced_mesh_ref(mesh, 0, instance.length as _);
+        }
+
+        Ok(())
+    }
+
+    /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
+    /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
+    /// This function drops Descriptor sets cached by self
+    pub fn clear(&mut self) {
+        self.bundles.lock().unwrap().clear();
+        self.pipeline.clear();
+    }
+}
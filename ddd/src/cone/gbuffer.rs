@@ -29,6 +29,9 @@ pub struct GeometryBufferDesc<'a, F: Fn(&str) -> (Option<gpu::TextureUsage>, Opt
     /// The precisiion of components of maps in the geometry buffer
     pub precision: GeometryBufferPrecision,
     /// The maps the geometry buffer contiains (name, components, shift)
+    ///
+    /// A map named "object_id" is always created as a single sampled [`gpu::Format::R32Uint`] texture
+    /// regardless of precision, see [`super::ObjectIdRenderer`] and [`GeometryBuffer::pick`]
     pub maps: &'a [(&'a str, u32)],
     /// Extra infomation specific to one map
     pub map_features: F,
@@ -137,6 +140,7 @@ impl<'a> GeometryBufferDesc<'a, fn(&str) -> (Option<gpu::TextureUsage>, Option<f
         ("output", 4),
         ("subsurface", 4),
         ("ao", 1),
+        ("velocity", 2),
     ];
 
     /// Has all maps
@@ -214,6 +218,7 @@ impl GeometryBuffer {
             let usage = usage.unwrap_or(gpu::TextureUsage::empty());
 
             let format = match *num_components {
+                _ if *n == "object_id" => R32Uint,
                 1 => r,
                 2 => rg,
                 3 => rgb,
@@ -242,6 +247,8 @@ impl GeometryBuffer {
 
             match desc.samples {
                 gpu::Samples::S1 => (),
+                // ids are drawn after resolving so don't need a multisampled map
+                _ if *n == "object_id" => (),
                 _ => {
                     let tn = desc.name.as_ref().map(|n0| format!("{}_{}_ms", n0, n));
                     let t = gfx::GTexture2D::from_formats(
@@ -346,6 +353,62 @@ impl GeometryBuffer {
         self.ms_maps.get(name)
     }
 
+    /// Read back the id of the object drawn at (x, y) by [`super::ObjectIdRenderer`]
+    ///
+    /// The readback resolves to None if no object was drawn at that pixel
+    /// Will panic if the geometry buffer doesn't have an "object_id" map or (x, y) is outside of it
+    pub fn pick(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        x: u32,
+        y: u32,
+    ) -> Result<gfx::ReadbackFuture<Option<u32>>, gpu::Error> {
+        let ids = match self.get("object_id") {
+            Some(t) => t,
+            None => panic!("ERROR: Attempt to pick from geometry buffer without object_id map"),
+        };
+        if x >= self.width || y >= self.height {
+            panic!(
+                "ERROR: Attempt to pick at ({}, {}) outside of geometry buffer of size ({}, {})",
+                x, y, self.width, self.height
+            );
+        }
+        let slice = ids.texture.slice_owned(&gpu::TextureSliceDesc {
+            offset: gpu::Offset3D {
+                x: x as _,
+                y: y as _,
+                z: 0,
+            },
+            extent: gpu::Extent3D {
+                width: 1,
+                height: 1,
+                depth: 1,
+            },
+            base_array_layer: 0,
+            array_layers: 1,
+            base_mip_level: 0,
+            mip_levels: 1,
+        });
+        Ok(encoder.read_texture_raw(device, slice)?.map(|data| {
+            // 0 is cleared to when no object is drawn
+            match u32::from_ne_bytes([data[0], data[1], data[2], data[3]]) {
+                0 => None,
+                id => Some(id - 1),
+            }
+        }))
+    }
+
+    /// Capture the "output" map as an srgb encoded image, blocking until the copy has completed
+    ///
+    /// Should be called after tonemapping and submitting the frame, will panic if there is no "output" map
+    pub fn capture(&self, device: &gpu::Device) -> Result<gfx::image::RgbaImage, gpu::Error> {
+        match self.get("output") {
+            Some(t) => gfx::capture_frame_srgb(device, &t.texture),
+            None => panic!("ERROR: Attempt to capture geometry buffer without output map"),
+        }
+    }
+
     pub fn resolve_ref<'a>(&'a self, encoder: &mut gfx::CommandEncoder<'a>) {
         for (n, src) in self.ms_maps.iter() {
             let dst = self.maps.get(n).unwrap();
@@ -0,0 +1,340 @@
+//! Clustered light culling for rendering many [`PointLights`]
+//!
+//! [`ClusterGrid`] describes how the view frustum is split into clusters
+//! [`ClusteredPointLightsRenderer`] assigns lights to clusters then renders them
+//!
+//! The view frustum is split into tiles on the screen and each tile is split into slices along its depth.
+//! A compute pass tests each light against the bounding box of every cluster and builds a list of lights for
+//! each cluster, then the lighting pass only evaluates the lights in the cluster containing each pixel.
+//! Unlike the tiles of [`PointLightsRenderer`] the clusters don't depend on the contents of the geometry buffer
+//! so no pass over the depth of the geometry buffer is needed before assigning lights.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::cone::*;
+use crate::prelude::*;
+use crate::utils::*;
+
+/// Describes how the view frustum is split into clusters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClusterGrid {
+    /// width and height in pixels of the tile covered by each cluster on the screen
+    pub tile_size: u32,
+    /// the number of slices along the depth of the view frustum, slices are spaced exponentially
+    pub slices: u32,
+    /// the maximum number of lights that can be assigned to one cluster, any extra are ignored
+    pub max_lights: u32,
+    /// the distance to the near plane of the camera's projection
+    pub z_near: f32,
+}
+
+impl Default for ClusterGrid {
+    fn default() -> Self {
+        Self {
+            tile_size: 64,
+            slices: 24,
+            max_lights: 128,
+            z_near: 0.1,
+        }
+    }
+}
+
+impl ClusterGrid {
+    /// The number of clusters across, up and along the depth of a target with width and height
+    pub fn dimensions(&self, width: u32, height: u32) -> (u32, u32, u32) {
+        (
+            width.div_ceil(self.tile_size),
+            height.div_ceil(self.tile_size),
+            self.slices,
+        )
+    }
+}
+
+/// Renders [`PointLights`] to the output of a [`GeometryBuffer`] with clustered light culling
+///
+/// Before lighting each [`PointLights`] is assigned to the clusters that its radius reaches, then each pixel only
+/// evaluates the lights in its cluster so scenes with hundreds of lights that each affect a small area are feasible.
+/// Like [`PointLightsRenderer`] the output map of the geometry buffer must have been created with storage usage
+///
+/// TODO shadow and subsurface passes
+pub struct ClusteredPointLightsRenderer {
+    /// map from (grid_width, grid_height, slices, max_lights) to (counts, indices)
+    pub grid_map: Arc<Mutex<HashMap<(u32, u32, u32, u32), (gfx::Storage<u32>, gfx::Storage<u32>)>>>,
+
+    /// compute pipeline for assigning lights to clusters
+    pub assign: gfx::ReflectedCompute,
+    /// map from (camera, counts, lights) to bundle
+    pub assign_bundles: Arc<Mutex<HashMap<(u64, u64, u64), gfx::Bundle>>>,
+
+    /// compute pipeline for adding lighting contributions
+    pub base: gfx::ReflectedCompute,
+    /// map from (geometry_buffer, camera, lights, counts) to bundle
+    pub base_bundles: Arc<Mutex<HashMap<(u64, u64, u64, u64), gfx::Bundle>>>,
+
+    pub name: Option<String>,
+}
+
+impl ClusteredPointLightsRenderer {
+    /// Create a new [`ClusteredPointLightsRenderer`]
+    pub fn new(
+        device: &gpu::Device,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let assign_spv =
+            gpu::include_spirv!("../../../shaders/cone/cluster_passes/cluster_assign.comp.spv");
+
+        let assign = match gfx::ReflectedCompute::from_spirv(
+            device,
+            &assign_spv,
+            cache.clone(),
+            name.map(|n| format!("{}_assign", n)).as_ref().map(|n| &**n),
+        ) {
+            Ok(p) => p,
+            Err(e) => match e {
+                gfx::ReflectedError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+
+        let base_spv =
+            gpu::include_spirv!("../../../shaders/cone/cluster_passes/cluster_base.comp.spv");
+
+        let base = match gfx::ReflectedCompute::from_spirv(
+            device,
+            &base_spv,
+            cache,
+            name.map(|n| format!("{}_base_pass", n))
+                .as_ref()
+                .map(|n| &**n),
+        ) {
+            Ok(p) => p,
+            Err(e) => match e {
+                gfx::ReflectedError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+
+        Ok(Self {
+            grid_map: Arc::default(),
+            assign,
+            assign_bundles: Arc::default(),
+            base,
+            base_bundles: Arc::default(),
+            name: name.map(|n| n.to_string()),
+        })
+    }
+}
+
+impl ClusteredPointLightsRenderer {
+    /// append instructions for assigning lights to clusters to the encoder
+    ///
+    /// returns (counts, indices) where counts is the number of lights in each cluster and
+    /// indices is grid.max_lights indices into lights for each cluster
+    pub fn assign_pass<'a>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        width: u32,
+        height: u32,
+        camera: &'a Camera,
+        lights: &'a PointLights,
+        grid: ClusterGrid,
+    ) -> Result<(gfx::Storage<u32>, gfx::Storage<u32>), gpu::Error> {
+        let (grid_width, grid_height, slices) = grid.dimensions(width, height);
+        let clusters = (grid_width * grid_height * slices) as usize;
+        let grid_key = (grid_width, grid_height, slices, grid.max_lights);
+
+        // get / create cluster storage
+        let mut grid_map = self.grid_map.lock().unwrap();
+        if grid_map.get(&grid_key).is_none() {
+            let name = self.name.as_ref().map(|n| {
+                format!(
+                    "{}_clusters_width_{}_height_{}_slices_{}",
+                    n, grid_width, grid_height, slices
+                )
+            });
+            // TODO uninitialized Storage
+            let counts = gfx::Storage::from_vec(
+                encoder,
+                device,
+                vec![0u32; clusters],
+                name.as_ref()
+                    .map(|n| format!("{}_counts", n))
+                    .as_ref()
+                    .map(|n| &**n),
+            )?;
+            let indices = gfx::Storage::from_vec(
+                encoder,
+                device,
+                vec![0u32; clusters * grid.max_lights as usize],
+                name.as_ref()
+                    .map(|n| format!("{}_indices", n))
+                    .as_ref()
+                    .map(|n| &**n),
+            )?;
+            grid_map.insert(grid_key, (counts, indices));
+        }
+        let (counts, indices) = grid_map.get(&grid_key).unwrap().clone();
+
+        // get / create bundle for assign pipeline
+        let key = (camera.buffer.id(), counts.id(), lights.id());
+        let mut assign_bundles = self.assign_bundles.lock().unwrap();
+        if assign_bundles.get(&key).is_none() {
+            let b = match self
+                .assign
+                .bundle()
+                .unwrap()
+                .set_resource("u_camera", camera)
+                .unwrap()
+                .set_resource("u_counts", &counts)
+                .unwrap()
+                .set_resource("u_indices", &indices)
+                .unwrap()
+                .set_resource("u_lights", lights)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+            assign_bundles.insert(key, b);
+        }
+        let bundle = assign_bundles.get(&key).unwrap();
+
+        // compute pass to assign lights to clusters
+        let mut pass = encoder.compute_pass_reflected(device, &self.assign)?;
+        pass.set_bundle_owned(bundle.clone());
+        pass.push_u32("width", width);
+        pass.push_u32("height", height);
+        pass.push_u32("grid_width", grid_width);
+        pass.push_u32("grid_height", grid_height);
+        pass.push_u32("slices", slices);
+        pass.push_u32("tile_size", grid.tile_size);
+        pass.push_u32("max_lights", grid.max_lights);
+        pass.push_f32("z_near", grid.z_near);
+        pass.push_u32("num_lights", lights.length as u32);
+        pass.dispatch(
+            grid_width.div_ceil(4),
+            grid_height.div_ceil(4),
+            slices.div_ceil(4),
+        );
+        pass.finish();
+
+        Ok((counts, indices))
+    }
+
+    /// Add the lights contributions to the output map of the geometry buffer
+    ///
+    /// For each lights in the iterator:
+    ///     each light is assigned to the clusters that it reaches
+    ///     for each pixel add the contributions of the lights in its cluster to the output map of the geometry buffer
+    ///
+    /// strength multiplies the lights contributions per pixel
+    /// clear specifies if to clear the geometry buffers output map or not
+    pub fn base_pass<'a>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &'a GeometryBuffer,
+        camera: &'a Camera,
+        lights: impl IntoIterator<Item = &'a PointLights>,
+        strength: f32,
+        mut clear: bool,
+        grid: ClusterGrid,
+    ) -> Result<(), gpu::Error> {
+        let (grid_width, grid_height, slices) = grid.dimensions(buffer.width, buffer.height);
+
+        for light in lights {
+            let (counts, indices) = self.assign_pass(
+                encoder,
+                device,
+                buffer.width,
+                buffer.height,
+                camera,
+                light,
+                grid,
+            )?;
+
+            // get / create bundle for base pipeline
+            let key = (buffer.id, camera.buffer.id(), light.id(), counts.id());
+            let mut base_bundles = self.base_bundles.lock().unwrap();
+            if base_bundles.get(&key).is_none() {
+                let b = match self
+                    .base
+                    .bundle()
+                    .unwrap()
+                    .set_resource("u_counts", &counts)
+                    .unwrap()
+                    .set_resource("u_indices", &indices)
+                    .unwrap()
+                    .set_resource("u_lights", light)
+                    .unwrap()
+                    .set_resource("u_position", buffer.get("world_pos").unwrap())
+                    .unwrap()
+                    .set_resource("u_normal", buffer.get("normal").unwrap())
+                    .unwrap()
+                    .set_resource("u_albedo", buffer.get("albedo").unwrap())
+                    .unwrap()
+                    .set_resource("u_roughness", buffer.get("roughness").unwrap())
+                    .unwrap()
+                    .set_resource("u_metallic", buffer.get("metallic").unwrap())
+                    .unwrap()
+                    .set_resource("u_output", buffer.get("output").unwrap())
+                    .unwrap()
+                    .set_resource("u_camera", camera)
+                    .unwrap()
+                    .build(device)
+                {
+                    Ok(b) => b,
+                    Err(e) => match e {
+                        gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                        e => unreachable!("{}", e),
+                    },
+                };
+                base_bundles.insert(key, b);
+            }
+            let bundle = base_bundles.get(&key).unwrap();
+
+            // compute pass to render light contributions
+            let mut pass = encoder.compute_pass_reflected(device, &self.base)?;
+            pass.set_bundle_owned(bundle.clone());
+            pass.push_f32("strength", strength);
+            pass.push_u32("width", buffer.width);
+            pass.push_u32("height", buffer.height);
+            pass.push_u32("grid_width", grid_width);
+            pass.push_u32("grid_height", grid_height);
+            pass.push_u32("slices", slices);
+            pass.push_u32("tile_size", grid.tile_size);
+            pass.push_u32("max_lights", grid.max_lights);
+            pass.push_f32("z_near", grid.z_near);
+            if clear {
+                pass.push_i32("clear", 1);
+                // only clear on the first pass
+                clear = false;
+            } else {
+                pass.push_i32("clear", 0);
+            }
+            pass.dispatch(buffer.width.div_ceil(8), buffer.height.div_ceil(8), 1);
+            pass.finish();
+        }
+
+        Ok(())
+    }
+
+    /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
+    /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
+    /// This function drops Descriptor sets cached by self
+    pub fn clear(&mut self) {
+        self.grid_map.lock().unwrap().clear();
+        self.assign_bundles.lock().unwrap().clear();
+        self.base_bundles.lock().unwrap().clear();
+        self.assign.clear();
+        self.base.clear();
+    }
+}
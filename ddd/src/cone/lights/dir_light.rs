@@ -1,10 +1,363 @@
+//! Directional light types and renderers for lights
+//!
+//! [`DirLightData`] attributes about a directional light
+//! [`DirLight`] alias for [`gfx::Uniform<DirLightData>`]
+//! [`DirLights`] alias for [`gfx::Storage<DirLightData>`]
+//! [`DirLightRenderer`] for rendering [`DirLight`] with optional cascaded shadows from a [`CascadedDepthMap`]
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::cone::*;
+use crate::prelude::*;
+use crate::utils::*;
+
 pub type DirLight = gfx::Uniform<DirLightData>;
 pub type DirLights = gfx::Storage<DirLightData>;
 
-/// TODO
+/// Describes parameters sent to the gpu for directional lights
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub struct DirLightData {}
+pub struct DirLightData {
+    /// direction that the light travels in
+    pub direction: glam::Vec3,
+
+    /// color of the light
+    pub color: glam::Vec3,
+}
+
+impl DirLightData {
+    pub fn new(direction: glam::Vec3, color: glam::Vec3) -> Self {
+        Self {
+            direction: direction.normalize(),
+            color,
+        }
+    }
+}
 
 unsafe impl bytemuck::Pod for DirLightData {}
 unsafe impl bytemuck::Zeroable for DirLightData {}
+
+bitflags::bitflags!(
+    pub struct DirLightRendererFlags: u32 {
+        const BASE                = 0b0000001;
+        const SHADOW              = 0b0000010;
+    }
+);
+
+/// Renders [`DirLight`] to the output of [`GeometryBuffer`] with optional
+/// cascaded shadows via [`CascadedDepthMap`]
+///
+/// ## Types of passes
+/// - Base pass just performs lighting calculations for the geometry so no shadows
+/// - Shadow pass performs lighting calculations with pcf shadow mapping, picking the cascade
+/// from the pixel's view depth and blending between neighbouring cascades
+#[derive(Clone)]
+pub struct DirLightRenderer {
+    /// Pure directional light calculation, acts on all pixels
+    pub base: Option<gfx::ReflectedGraphics>,
+    /// map from (geometry_buffer, camera, light) to bundle
+    pub base_bundles: Arc<Mutex<HashMap<(u64, u64, u64), gfx::Bundle>>>,
+
+    /// directional light calculation with shadows, acts on all pixels
+    pub shadow: Option<gfx::ReflectedGraphics>,
+    /// map from (geometry_buffer, camera, light, shadow) to bundle
+    pub shadow_bundles: Arc<Mutex<HashMap<(u64, u64, u64, u64), gfx::Bundle>>>,
+}
+
+impl DirLightRenderer {
+    /// Create a new [`DirLightRenderer`]
+    ///
+    /// The renderer can only make use of passes declared by the flags
+    pub fn new(
+        device: &gpu::Device,
+        flags: DirLightRendererFlags,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let bfn = name.as_ref().map(|n| format!("{}_base_pipeline", n));
+        let sfn = name.as_ref().map(|n| format!("{}_shadow_pipeline", n));
+
+        Ok(Self {
+            base: if flags.contains(DirLightRendererFlags::BASE) {
+                Some(Self::create_base(
+                    device,
+                    cache.clone(),
+                    bfn.as_ref().map(|n| &**n),
+                )?)
+            } else {
+                None
+            },
+            base_bundles: Arc::default(),
+            shadow: if flags.contains(DirLightRendererFlags::SHADOW) {
+                Some(Self::create_shadow(
+                    device,
+                    cache.clone(),
+                    sfn.as_ref().map(|n| &**n),
+                )?)
+            } else {
+                None
+            },
+            shadow_bundles: Arc::default(),
+        })
+    }
+
+    pub fn create_base(
+        device: &gpu::Device,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<gfx::ReflectedGraphics, gpu::Error> {
+        let vert = gpu::include_spirv!("../../../shaders/screen.vert.spv");
+        let frag =
+            gpu::include_spirv!("../../../shaders/cone/dir_light_passes/single_base.frag.spv");
+        PointLightRenderer::create_pipeline(device, &vert, &frag, cache, name)
+    }
+
+    pub fn create_shadow(
+        device: &gpu::Device,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<gfx::ReflectedGraphics, gpu::Error> {
+        let vert = gpu::include_spirv!("../../../shaders/screen.vert.spv");
+        let frag =
+            gpu::include_spirv!("../../../shaders/cone/dir_light_passes/single_shadow.frag.spv");
+        PointLightRenderer::create_pipeline(device, &vert, &frag, cache, name)
+    }
+
+    /// Begin a pass adding to the output map of the geometry buffer
+    fn output_pass<'a, 'b>(
+        encoder: &'b mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &'a GeometryBuffer,
+        pipeline: &gfx::ReflectedGraphics,
+        clear: bool,
+    ) -> Result<gfx::pass::ReflectedGraphicsPass<'a, 'b, ()>, gpu::Error> {
+        encoder.graphics_pass_reflected::<()>(
+            device,
+            &[gfx::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Borrowed(&buffer.get("output").unwrap().view),
+                    gpu::ClearValue::ColorFloat([0.0; 4]),
+                ),
+                load: if clear {
+                    gpu::LoadOp::Clear
+                } else {
+                    gpu::LoadOp::Load
+                },
+                store: gpu::StoreOp::Store,
+            }],
+            &[],
+            Some(gfx::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Borrowed(&buffer.depth.view),
+                    gpu::ClearValue::Depth(1.0),
+                ),
+                load: gpu::LoadOp::Load,
+                store: gpu::StoreOp::Store,
+            }),
+            pipeline,
+        )
+    }
+}
+
+impl DirLightRenderer {
+    pub fn base_bundle(
+        &self,
+        device: &gpu::Device,
+        buffer: &GeometryBuffer,
+        camera: &Camera,
+        light: &DirLight,
+    ) -> Result<gfx::Bundle, gpu::Error> {
+        let mut bundles = self.base_bundles.lock().unwrap();
+        let key = (buffer.id, camera.buffer.id(), light.buffer.id());
+        if bundles.get(&key).is_none() {
+            let b = match self
+                .base
+                .as_ref()
+                .expect("ERROR: DirLightRenderer missing flags")
+                .bundle()
+                .unwrap()
+                .set_resource("u_position", buffer.get("world_pos").unwrap())
+                .unwrap()
+                .set_resource("u_normal", buffer.get("normal").unwrap())
+                .unwrap()
+                .set_resource("u_albedo", buffer.get("albedo").unwrap())
+                .unwrap()
+                .set_resource("u_roughness", buffer.get("roughness").unwrap())
+                .unwrap()
+                .set_resource("u_metallic", buffer.get("metallic").unwrap())
+                .unwrap()
+                .set_resource("u_sampler", &buffer.sampler)
+                .unwrap()
+                .set_resource("u_light_data", light)
+                .unwrap()
+                .set_resource("u_camera", camera)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+            bundles.insert(key, b);
+        }
+
+        Ok(bundles.get(&key).unwrap().clone())
+    }
+
+    /// Add the lights contributions to the output map of the geometry buffer
+    ///
+    /// Each light in the iterator will be drawn as a fullscreen pass under a separate draw call
+    ///
+    /// strength multiplies the lights contibution per pixel
+    /// clear specifies if to clear the geometry buffers output map or not
+    pub fn base_pass<'a>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &'a GeometryBuffer,
+        camera: &'a Camera,
+        lights: impl IntoIterator<Item = &'a DirLight>,
+        strength: f32,
+        clear: bool,
+    ) -> Result<(), gpu::Error> {
+        let mut pass = Self::output_pass(
+            encoder,
+            device,
+            buffer,
+            self.base
+                .as_ref()
+                .expect("ERROR: DirLightRenderer missing flags"),
+            clear,
+        )?;
+
+        pass.push_f32("strength", strength);
+        pass.push_f32("width", buffer.width as _);
+        pass.push_f32("height", buffer.height as _);
+
+        for light in lights {
+            let bundle = self.base_bundle(device, buffer, camera, light)?;
+            pass.set_bundle_owned(bundle);
+            pass.draw(0, 3, 0, 1);
+        }
+
+        Ok(())
+    }
+}
+
+// shadow passes
+impl DirLightRenderer {
+    pub fn shadow_bundle(
+        &self,
+        device: &gpu::Device,
+        buffer: &GeometryBuffer,
+        camera: &Camera,
+        light: &DirLight,
+        shadow: &CascadedDepthMap,
+    ) -> Result<gfx::Bundle, gpu::Error> {
+        let mut bundles = self.shadow_bundles.lock().unwrap();
+        let key = (buffer.id, camera.buffer.id(), light.buffer.id(), shadow.id);
+        if bundles.get(&key).is_none() {
+            let b = match self
+                .shadow
+                .as_ref()
+                .expect("ERROR: DirLightRenderer missing flags")
+                .bundle()
+                .unwrap()
+                .set_resource("u_position", buffer.get("world_pos").unwrap())
+                .unwrap()
+                .set_resource("u_normal", buffer.get("normal").unwrap())
+                .unwrap()
+                .set_resource("u_albedo", buffer.get("albedo").unwrap())
+                .unwrap()
+                .set_resource("u_roughness", buffer.get("roughness").unwrap())
+                .unwrap()
+                .set_resource("u_metallic", buffer.get("metallic").unwrap())
+                .unwrap()
+                .set_resource("u_sampler", &buffer.sampler)
+                .unwrap()
+                .set_resource("u_light_data", light)
+                .unwrap()
+                .set_resource("u_camera", camera)
+                .unwrap()
+                .set_resource("u_shadow_data", &shadow.uniform)
+                .unwrap()
+                .set_resource("u_shadow_map", &shadow.texture)
+                .unwrap()
+                .set_resource("u_shadow_sampler", &shadow.sampler)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+            bundles.insert(key, b);
+        }
+
+        Ok(bundles.get(&key).unwrap().clone())
+    }
+
+    /// Add the lights contributions to the output map of the geometry buffer including cascaded shadows
+    ///
+    /// Each light in the iterator will be drawn as a fullscreen pass under a separate draw call
+    /// The depth maps should have been updated for the camera with [`CascadedDepthMap::update`]
+    ///
+    /// strength multiplies the lights contibution per pixel
+    /// samples is the number of shadow map reads per cascade for calculating shadow contribution (max 16)
+    /// clear specifies if to clear the geometry buffers output map or not
+    pub fn shadow_pass<'a>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &'a GeometryBuffer,
+        camera: &'a Camera,
+        lights: impl IntoIterator<Item = (&'a DirLight, &'a CascadedDepthMap)>,
+        strength: f32,
+        samples: u32,
+        clear: bool,
+    ) -> Result<(), gpu::Error> {
+        let mut pass = Self::output_pass(
+            encoder,
+            device,
+            buffer,
+            self.shadow
+                .as_ref()
+                .expect("ERROR: DirLightRenderer missing flags"),
+            clear,
+        )?;
+
+        pass.push_f32("strength", strength);
+        pass.push_u32("samples", samples.clamp(1, 16));
+        pass.push_f32("width", buffer.width as _);
+        pass.push_f32("height", buffer.height as _);
+
+        for (light, shadow) in lights {
+            let bundle = self.shadow_bundle(device, buffer, camera, light, shadow)?;
+            pass.set_bundle_owned(bundle);
+            pass.draw(0, 3, 0, 1);
+        }
+
+        Ok(())
+    }
+
+    /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
+    /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
+    /// This function drops Descriptor sets cached by self
+    pub fn clear(&mut self) {
+        self.base_bundles.lock().unwrap().clear();
+        self.shadow_bundles.lock().unwrap().clear();
+        if let Some(base) = self.base.as_ref() {
+            base.clear();
+        }
+        if let Some(shadow) = self.shadow.as_ref() {
+            shadow.clear();
+        }
+    }
+}
@@ -8,6 +8,8 @@
 //!  - convert equirectangular image to cubemap texture (see [`new_skybox`])
 //!  - convert cubemap texture to environment map (see [`new_env_map`])
 //!  - use the environment map to render lighting (see [`EnvironmentRenderer::environment_pass`])
+//!
+//! For local reflections the environment map can be blended with [`ReflectionProbes`] (see [`EnvironmentRenderer::probe_pass`])
 
 use crate::cone::*;
 use crate::prelude::*;
@@ -464,7 +466,7 @@ impl<'a> EnvironmentMapGenerator<'a> {
         Ok([diffuse, specular, brdf])
     }
 
-    /// Generate an environment map from
+    /// Generate an environment map from a skybox
     pub fn generate(
         &self,
         encoder: &mut gfx::CommandEncoder<'a>,
@@ -477,10 +479,39 @@ impl<'a> EnvironmentMapGenerator<'a> {
         brdf_height: u32,
         sample_count: u32,
     ) -> Result<EnvironmentMap, gpu::Error> {
+        let diffuse = self.generate_diffuse(encoder, device, skybox, diffuse_size)?;
+        let specular = self.generate_specular(
+            encoder,
+            device,
+            skybox,
+            specular_size,
+            specular_mip_levels,
+            sample_count,
+        )?;
+        let brdf_lut = self.generate_brdf(encoder, device, brdf_width, brdf_height, sample_count)?;
+
+        Ok(EnvironmentMap::new(diffuse, specular, brdf_lut))
+    }
+
+    /// The views of each face of a cube in the order of [`gfx::CubeFace`]
+    pub fn face_views() -> [glam::Mat4; 6] {
+        gfx::cube_face_views(glam::Vec3::ZERO)
+    }
+
+    /// Generate the irradiance of a skybox for diffuse lighting
+    pub fn generate_diffuse(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        skybox: &SkyBox,
+        diffuse_size: u32,
+    ) -> Result<gfx::GTextureCube, gpu::Error> {
         let diffuse = gfx::GTextureCube::new(
             device,
             diffuse_size,
-            gpu::TextureUsage::COLOR_OUTPUT | gpu::TextureUsage::SAMPLED,
+            gpu::TextureUsage::COLOR_OUTPUT
+                | gpu::TextureUsage::SAMPLED
+                | gpu::TextureUsage::COPY_SRC,
             1,
             gpu::Format::Rgba32Float,
             None,
@@ -506,14 +537,7 @@ impl<'a> EnvironmentMapGenerator<'a> {
         let z_far = 10.0;
         let projection = glam::Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1, z_far);
 
-        let views = [
-            glam::Mat4::look_at_rh(glam::Vec3::ZERO, -glam::Vec3::X, glam::Vec3::Y),
-            glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::X, glam::Vec3::Y),
-            glam::Mat4::look_at_rh(glam::Vec3::ZERO, -glam::Vec3::Y, glam::Vec3::Z),
-            glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::Y, -glam::Vec3::Z),
-            glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::Z, glam::Vec3::Y),
-            glam::Mat4::look_at_rh(glam::Vec3::ZERO, -glam::Vec3::Z, glam::Vec3::Y),
-        ];
+        let views = Self::face_views();
 
         for face in gfx::CubeFace::iter() {
             let view = diffuse.face_view(face)?;
@@ -544,10 +568,25 @@ impl<'a> EnvironmentMapGenerator<'a> {
             }
         }
 
+        Ok(diffuse)
+    }
+
+    /// Generate the prefiltered radiance of a skybox for specular lighting, rougher reflections in lower mip levels
+    pub fn generate_specular(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        skybox: &SkyBox,
+        specular_size: u32,
+        specular_mip_levels: u32,
+        sample_count: u32,
+    ) -> Result<gfx::GTextureCube, gpu::Error> {
         let specular = gfx::GTextureCube::new(
             device,
             specular_size,
-            gpu::TextureUsage::COLOR_OUTPUT | gpu::TextureUsage::SAMPLED,
+            gpu::TextureUsage::COLOR_OUTPUT
+                | gpu::TextureUsage::SAMPLED
+                | gpu::TextureUsage::COPY_SRC,
             specular_mip_levels,
             gpu::Format::Rgba32Float,
             None,
@@ -564,15 +603,15 @@ impl<'a> EnvironmentMapGenerator<'a> {
             None,
         )?;
 
+        let z_far = 10.0;
+        let projection = glam::Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1, z_far);
+
+        let views = Self::face_views();
+
         let mut camera = gfx::Uniform::new(
             encoder,
             device,
-            CameraData {
-                projection,
-                view: views[0],
-                z_far,
-                position: glam::vec4(0.0, 0.0, 0.0, 1.0),
-            },
+            CameraData::new(projection, views[0], glam::Vec3::ZERO, z_far),
             None,
         )?;
 
@@ -640,6 +679,18 @@ impl<'a> EnvironmentMapGenerator<'a> {
             }
         }
 
+        Ok(specular)
+    }
+
+    /// Generate the lookup table of the split sum brdf approximation
+    pub fn generate_brdf(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        brdf_width: u32,
+        brdf_height: u32,
+        sample_count: u32,
+    ) -> Result<gfx::GTexture2D, gpu::Error> {
         let brdf_lut = gfx::GTexture2D::new(
             device,
             brdf_width,
@@ -669,7 +720,7 @@ impl<'a> EnvironmentMapGenerator<'a> {
         pass.draw(0, 3, 0, 1);
         pass.finish();
 
-        Ok(EnvironmentMap::new(diffuse, specular, brdf_lut))
+        Ok(brdf_lut)
     }
 }
 
@@ -707,6 +758,7 @@ bitflags::bitflags!(
         const AMBIENT          = 0b0001;
         const SKYBOX           = 0b0010;
         const ENVIRONMENT      = 0b0100;
+        const PROBES           = 0b1000;
     }
 );
 
@@ -724,6 +776,9 @@ pub struct EnvironmentRenderer {
     /// Environment map lighting
     pub environment: Option<gfx::ReflectedGraphics>,
     pub environment_bundles: Arc<Mutex<HashMap<(u64, u64, u64), gfx::Bundle>>>,
+    /// Environment map lighting blended with reflection probes
+    pub probes: Option<gfx::ReflectedGraphics>,
+    pub probe_bundles: Arc<Mutex<HashMap<(u64, u64, u64, u64), gfx::Bundle>>>,
     pub sampler: gpu::Sampler,
 }
 
@@ -744,6 +799,7 @@ impl EnvironmentRenderer {
         let an = name.as_ref().map(|n| format!("{}_ambient", n));
         let sn = name.as_ref().map(|n| format!("{}_skybox", n));
         let en = name.as_ref().map(|n| format!("{}_environment", n));
+        let pn = name.as_ref().map(|n| format!("{}_probes", n));
 
         Ok(Self {
             cube: mesh::cube(encoder, device, cn.as_ref().map(|n| &**n))?,
@@ -760,11 +816,17 @@ impl EnvironmentRenderer {
             },
             skybox_bundles: Arc::default(),
             environment: if flags.contains(EnvironmentRendererFlags::ENVIRONMENT) {
-                Some(Self::create_environment(device, cache, en.as_ref().map(|n| &**n))?)
+                Some(Self::create_environment(device, cache.clone(), en.as_ref().map(|n| &**n))?)
             } else {
                 None
             },
             environment_bundles: Arc::default(),
+            probes: if flags.contains(EnvironmentRendererFlags::PROBES) {
+                Some(Self::create_probes(device, cache, pn.as_ref().map(|n| &**n))?)
+            } else {
+                None
+            },
+            probe_bundles: Arc::default(),
             sampler,
         })
     }
@@ -857,6 +919,16 @@ impl EnvironmentRenderer {
         Self::create_light_pipeline(device, &vert, &frag, cache, name)
     }
 
+    pub fn create_probes(
+        device: &gpu::Device,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<gfx::ReflectedGraphics, gpu::Error> {
+        let vert = gpu::include_spirv!("../../../shaders/screen.vert.spv");
+        let frag = gpu::include_spirv!("../../../shaders/cone/environment/probes.frag.spv");
+        Self::create_light_pipeline(device, &vert, &frag, cache, name)
+    }
+
     pub fn create_skybox(
         device: &gpu::Device,
         cache: Option<gpu::PipelineCache>,
@@ -1016,6 +1088,68 @@ impl EnvironmentRenderer {
         }
         Ok(bundles.get(&key).unwrap().clone())
     }
+
+    /// Create and insert or get a bundle referencing the geometry buffer camera environment map and probes and return it
+    pub fn probe_bundle(
+        &self,
+        device: &gpu::Device,
+        buffer: &GeometryBuffer,
+        camera: &Camera,
+        environment: &EnvironmentMap,
+        probes: &ReflectionProbes,
+    ) -> Result<gfx::Bundle, gpu::Error> {
+        let mut bundles = self.probe_bundles.lock().unwrap();
+        let key = (buffer.id, camera.buffer.id(), environment.id, probes.id);
+        if bundles.get(&key).is_none() {
+            let b = match self
+                .probes
+                .as_ref()
+                .expect("ERROR: EnvironmentRenderer missing flags")
+                .bundle()
+                .unwrap()
+                .set_resource("u_position", buffer.get("world_pos").unwrap())
+                .unwrap()
+                .set_resource("u_normal", buffer.get("normal").unwrap())
+                .unwrap()
+                .set_resource("u_albedo", buffer.get("albedo").unwrap())
+                .unwrap()
+                .set_resource("u_roughness", buffer.get("roughness").unwrap())
+                .unwrap()
+                .set_resource("u_metallic", buffer.get("metallic").unwrap())
+                .unwrap()
+                .set_resource("u_subsurface", buffer.get("subsurface").unwrap())
+                .unwrap()
+                .set_resource("u_ao", buffer.get("ao").unwrap())
+                .unwrap()
+                .set_resource("u_sampler", &self.sampler)
+                .unwrap()
+                .set_resource("u_camera", camera)
+                .unwrap()
+                .set_resource("u_diffuse", &environment.diffuse)
+                .unwrap()
+                .set_resource("u_specular", &environment.specular)
+                .unwrap()
+                .set_resource("u_brdf_lut", &environment.brdf_lut)
+                .unwrap()
+                .set_resource("u_probes", &probes.probes)
+                .unwrap()
+                .set_resource("u_probe_diffuse", &probes.diffuse)
+                .unwrap()
+                .set_resource("u_probe_specular", &probes.specular)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+
+            bundles.insert(key, b.clone());
+        }
+        Ok(bundles.get(&key).unwrap().clone())
+    }
 }
 
 impl EnvironmentRenderer {
@@ -1169,6 +1303,66 @@ impl EnvironmentRenderer {
         Ok(())
     }
 
+    /// Same as [`EnvironmentRenderer::environment_pass`] but inside the boxes of probes the lighting is taken from the
+    /// probes instead of the environment map
+    ///
+    /// Where probes overlap they are blended by how far inside each box the pixel is
+    pub fn probe_pass(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        buffer: &GeometryBuffer,
+        camera: &Camera,
+        environment: &EnvironmentMap,
+        probes: &ReflectionProbes,
+        strength: f32,
+        clear: bool,
+    ) -> Result<(), gpu::Error> {
+        let mut pass = encoder.graphics_pass_reflected::<()>(
+            device,
+            &[gfx::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Owned(buffer.get("output").unwrap().view.clone()),
+                    gpu::ClearValue::ColorFloat([0.0; 4]),
+                ),
+                load: if clear {
+                    gpu::LoadOp::Clear
+                } else {
+                    gpu::LoadOp::Load
+                },
+                store: gpu::StoreOp::Store,
+            }],
+            &[],
+            Some(gfx::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Owned(buffer.depth.view.clone()),
+                    gpu::ClearValue::Depth(1.0),
+                ),
+                load: gpu::LoadOp::Load,
+                store: gpu::StoreOp::Store,
+            }),
+            self.probes
+                .as_ref()
+                .expect("ERROR: EnvironmentRenderer missing flags"),
+        )?;
+
+        let bundle = self.probe_bundle(&device, buffer, camera, environment, probes)?;
+
+        pass.push_f32(
+            "max_reflection_lod",
+            environment.specular.texture.mip_levels() as f32,
+        );
+        pass.push_f32("max_probe_lod", probes.specular.texture.mip_levels() as f32);
+        pass.push_f32("strength", strength);
+        pass.push_f32("width", buffer.width as _);
+        pass.push_f32("height", buffer.height as _);
+        pass.push_i32("probe_count", probes.len() as _);
+        pass.set_bundle_owned(bundle);
+        pass.draw(0, 3, 0, 1);
+
+        Ok(())
+    }
+
     /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
     /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
     /// This function drops Descriptor sets cached by self
@@ -1176,6 +1370,7 @@ impl EnvironmentRenderer {
         self.ambient_bundles.lock().unwrap().clear();
         self.environment_bundles.lock().unwrap().clear();
         self.skybox_bundles.lock().unwrap().clear();
+        self.probe_bundles.lock().unwrap().clear();
         if let Some(ambient) = self.ambient.as_ref() {
             ambient.clear();
         }
@@ -1185,5 +1380,8 @@ impl EnvironmentRenderer {
         if let Some(skybox) = self.skybox.as_ref() {
             skybox.clear();
         }
+        if let Some(probes) = self.probes.as_ref() {
+            probes.clear();
+        }
     }
 }
@@ -1,7 +1,13 @@
+pub mod clustered;
 pub mod dir_light;
 pub mod environment_light;
 pub mod point_light;
+pub mod reflection_probe;
+pub mod spot_light;
 
+pub use clustered::*;
 pub use dir_light::*;
 pub use environment_light::*;
 pub use point_light::*;
+pub use reflection_probe::*;
+pub use spot_light::*;
@@ -0,0 +1,235 @@
+//! Local reflection probes
+//!
+//! [`ReflectionProbeData`] where a probe is captured from and the box it affects
+//! [`ReflectionProbes`] the baked lighting of a group of probes
+//!
+//! A single [`EnvironmentMap`] can only reflect what is infinitely far away. Reflection probes capture the scene
+//! around author placed positions and are blended per pixel by how far inside each probe's box it is. Reflections are
+//! projected onto the box so that they line up with the geometry captured.
+//!
+//! The basic steps to use reflection probes are:
+//!  - render the scene into the output of a geometry buffer from each of [`ReflectionProbeData::face_cameras`]
+//!  - copy each output into the capture cube with [`ReflectionProbes::capture_face`]
+//!  - bake the capture into the probe with [`ReflectionProbes::bake`]
+//!  - use the probes to render lighting (see [`EnvironmentRenderer::probe_pass`])
+
+use crate::cone::*;
+use crate::utils::*;
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+
+/// Describes a reflection probe on the gpu
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReflectionProbeData {
+    /// xyz the position the probe is captured from, w the distance inside the box over which the probe fades in
+    pub position: glam::Vec4,
+    /// the minimum corner of the box the probe affects in world space
+    pub box_min: glam::Vec4,
+    /// the maximum corner of the box the probe affects in world space
+    pub box_max: glam::Vec4,
+}
+
+unsafe impl bytemuck::Pod for ReflectionProbeData {}
+unsafe impl bytemuck::Zeroable for ReflectionProbeData {}
+
+impl ReflectionProbeData {
+    /// Create a new probe captured from position affecting the box between box_min and box_max
+    pub fn new(position: glam::Vec3, box_min: glam::Vec3, box_max: glam::Vec3, fade: f32) -> Self {
+        Self {
+            position: glam::vec4(position.x, position.y, position.z, fade),
+            box_min: glam::vec4(box_min.x, box_min.y, box_min.z, 0.0),
+            box_max: glam::vec4(box_max.x, box_max.y, box_max.z, 0.0),
+        }
+    }
+
+    /// The cameras to render the scene from for each face of the capture in the order of [`gfx::CubeFace`]
+    pub fn face_cameras(&self, z_near: f32, z_far: f32) -> [CameraData; 6] {
+        let position = self.position.truncate();
+        let projection = gfx::cube_face_projection(z_near, z_far);
+        gfx::cube_face_views(position)
+            .map(|view| CameraData::new(projection, view, position, z_far))
+    }
+}
+
+/// The baked lighting of a group of reflection probes
+///
+/// Each probe has one cube in the diffuse and specular cube arrays
+#[derive(Debug, Clone)]
+pub struct ReflectionProbes {
+    pub(crate) id: u64,
+    pub probes: gfx::Storage<ReflectionProbeData>,
+    pub diffuse: gfx::GTextureCubeArray,
+    pub specular: gfx::GTextureCubeArray,
+    /// the scene is copied into this before being baked into a probe
+    pub capture: gfx::GTextureCube,
+}
+
+impl ReflectionProbes {
+    /// Create new reflection probes, the probes will be black until they are baked
+    pub fn new(
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        probes: Vec<ReflectionProbeData>,
+        capture_size: u32,
+        diffuse_size: u32,
+        specular_size: u32,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        if probes.is_empty() {
+            panic!("ERROR: Attempt to create ReflectionProbes with no probes");
+        }
+        let cubes = probes.len() as u32;
+
+        let n = name.map(|n| format!("{}_probes", n));
+        let probes = gfx::Storage::from_vec(encoder, device, probes, n.as_ref().map(|n| &**n))?;
+
+        let create = |suffix: &str, size: u32, mip_levels: u32| {
+            let n = name.map(|n| format!("{}_{}", n, suffix));
+            gfx::GTextureCubeArray::new(
+                device,
+                size,
+                cubes,
+                gpu::TextureUsage::SAMPLED | gpu::TextureUsage::COPY_DST,
+                mip_levels,
+                gpu::Format::Rgba32Float,
+                n.as_ref().map(|n| &**n),
+            )
+        };
+        let diffuse = create("diffuse", diffuse_size, 1)?;
+        let specular = create(
+            "specular",
+            specular_size,
+            gfx::max_mip_levels(gfx::texture::D1(specular_size)),
+        )?;
+        encoder.clear_texture(
+            diffuse.whole_slice_owned(),
+            gpu::ClearValue::ColorFloat([0.0; 4]),
+        );
+        encoder.clear_texture(
+            specular.whole_slice_owned(),
+            gpu::ClearValue::ColorFloat([0.0; 4]),
+        );
+
+        let n = name.map(|n| format!("{}_capture", n));
+        let capture = gfx::GTextureCube::new(
+            device,
+            capture_size,
+            gpu::TextureUsage::SAMPLED
+                | gpu::TextureUsage::COLOR_OUTPUT
+                | gpu::TextureUsage::COPY_DST,
+            1,
+            gpu::Format::Rgba32Float,
+            n.as_ref().map(|n| &**n),
+        )?;
+
+        let mut hasher = DefaultHasher::new();
+        probes.buffer.id().hash(&mut hasher);
+        diffuse.hash(&mut hasher);
+        specular.hash(&mut hasher);
+
+        Ok(Self {
+            id: hasher.finish(),
+            probes,
+            diffuse,
+            specular,
+            capture,
+        })
+    }
+
+    /// The number of probes
+    pub fn len(&self) -> usize {
+        self.probes.length
+    }
+
+    /// Update the position and box of the probe at index
+    ///
+    /// The probe should be baked again if it has moved
+    pub fn set_probe(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        index: usize,
+        data: ReflectionProbeData,
+    ) -> Result<(), gpu::Error> {
+        self.probes.update_range(encoder, device, index, &[data])
+    }
+
+    /// Copy src into a face of the capture cube, scaling and converting the format if needed
+    ///
+    /// src will typically be the output map of a geometry buffer rendered with the camera of the same face
+    /// from [`ReflectionProbeData::face_cameras`]
+    pub fn capture_face(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'_>,
+        src: &gfx::GTexture2D,
+        face: gfx::CubeFace,
+    ) {
+        encoder.blit_textures(
+            src.whole_slice_owned(),
+            self.capture.face_slice_owned(face),
+            gpu::FilterMode::Linear,
+        );
+    }
+
+    /// Bake the capture cube into the probe at index using the environment map pipelines of generator
+    pub fn bake<'a>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        generator: &EnvironmentMapGenerator<'a>,
+        index: u32,
+        sample_count: u32,
+    ) -> Result<(), gpu::Error> {
+        if index as usize >= self.len() {
+            panic!(
+                "ERROR: Attempt to bake probe {} of ReflectionProbes with {} probes",
+                index,
+                self.len()
+            );
+        }
+        let diffuse_size = self.diffuse.width();
+        let specular_size = self.specular.width();
+        let mip_levels = self.specular.mip_levels();
+
+        let diffuse = generator.generate_diffuse(encoder, device, &self.capture, diffuse_size)?;
+        let specular = generator.generate_specular(
+            encoder,
+            device,
+            &self.capture,
+            specular_size,
+            mip_levels,
+            sample_count,
+        )?;
+
+        let slice = |texture: &gpu::Texture, size: u32, layer: u32, mip: u32| {
+            let s = (size >> mip).max(1);
+            texture.slice_owned(&gpu::TextureSliceDesc {
+                offset: gpu::Offset3D::ZERO,
+                extent: gpu::Extent3D {
+                    width: s,
+                    height: s,
+                    depth: 1,
+                },
+                base_array_layer: layer,
+                array_layers: 6,
+                base_mip_level: mip,
+                mip_levels: 1,
+            })
+        };
+        encoder.copy_texture_to_texture(
+            slice(&diffuse.texture, diffuse_size, 0, 0),
+            slice(&self.diffuse.texture, diffuse_size, index * 6, 0),
+        );
+        for mip in 0..mip_levels {
+            encoder.copy_texture_to_texture(
+                slice(&specular.texture, specular_size, 0, mip),
+                slice(&self.specular.texture, specular_size, index * 6, mip),
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,396 @@
+//! Spot light types and renderers for lights
+//!
+//! [`SpotLightData`] attributes about a spot light
+//! [`SpotLight`] alias for [`gfx::Uniform<SpotLightData>`]
+//! [`SpotLights`] alias for [`gfx::Storage<SpotLightData>`]
+//! [`SpotLightRenderer`] for rendering [`SpotLight`] with optional shadows from a [`SpotDepthMap`]
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::cone::*;
+use crate::prelude::*;
+use crate::utils::*;
+
+pub type SpotLight = gfx::Uniform<SpotLightData>;
+pub type SpotLights = gfx::Storage<SpotLightData>;
+
+/// Describes parameters sent to the gpu for spot lights
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpotLightData {
+    /// Affects the strength of light fall off, higher numbers mean gets dark faster
+    pub falloff: f32,
+
+    /// position of the light
+    pub position: glam::Vec3,
+
+    /// direction that the light points in
+    pub direction: glam::Vec3,
+
+    /// color of the light
+    pub color: glam::Vec3,
+
+    /// effective radius of the light
+    pub radius: f32,
+
+    /// cosine of the inner cone angle, pixels inside the inner cone get the full light
+    pub inner_cos: f32,
+
+    /// cosine of the outer cone angle, pixels outside the outer cone get no light
+    pub outer_cos: f32,
+}
+
+impl SpotLightData {
+    /// Create a new spot light
+    ///
+    /// inner and outer are the angles (radians) from the direction to the edges of the cones
+    /// between the inner and outer cone the light fades out smoothly
+    pub fn new(
+        falloff: f32,
+        position: glam::Vec3,
+        direction: glam::Vec3,
+        color: glam::Vec3,
+        inner: f32,
+        outer: f32,
+        cutoff: f32,
+    ) -> Self {
+        let point = PointLightData::new(falloff, position, color, cutoff);
+        Self {
+            falloff,
+            position,
+            direction: direction.normalize(),
+            color,
+            radius: point.radius,
+            inner_cos: inner.min(outer).cos(),
+            outer_cos: outer.cos(),
+        }
+    }
+}
+
+unsafe impl bytemuck::Pod for SpotLightData {}
+unsafe impl bytemuck::Zeroable for SpotLightData {}
+
+bitflags::bitflags!(
+    pub struct SpotLightRendererFlags: u32 {
+        const BASE                = 0b0000001;
+        const SHADOW              = 0b0000010;
+    }
+);
+
+/// Renders [`SpotLight`] to the output of [`GeometryBuffer`] with optional
+/// shadows via [`SpotDepthMap`]
+///
+/// Works the same as [`PointLightRenderer`] except that light only reaches pixels within the cone of the light
+///
+/// ## Types of passes
+/// - Base pass just performs lighting calculations for the geometry so no shadows
+/// - Shadow pass performs lighting caclulations with pcf shadow mapping
+#[derive(Clone)]
+pub struct SpotLightRenderer {
+    /// Pure spot light calculation, acts on all pixels
+    pub base: Option<gfx::ReflectedGraphics>,
+    /// map from (geometry_buffer, camera, light) to bundle
+    pub base_bundles: Arc<Mutex<HashMap<(u64, u64, u64), gfx::Bundle>>>,
+
+    /// spot light calculation with shadows, acts on all pixels
+    pub shadow: Option<gfx::ReflectedGraphics>,
+    /// map from (geometry_buffer, camera, light, shadow) to bundle
+    pub shadow_bundles: Arc<Mutex<HashMap<(u64, u64, u64, u64), gfx::Bundle>>>,
+}
+
+impl SpotLightRenderer {
+    /// Create a new [`SpotLightRenderer`]
+    ///
+    /// The renderer can only make use of passes declared by the flags
+    pub fn new(
+        device: &gpu::Device,
+        flags: SpotLightRendererFlags,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let bfn = name.as_ref().map(|n| format!("{}_base_pipeline", n));
+        let sfn = name.as_ref().map(|n| format!("{}_shadow_pipeline", n));
+
+        Ok(Self {
+            base: if flags.contains(SpotLightRendererFlags::BASE) {
+                Some(Self::create_base(
+                    device,
+                    cache.clone(),
+                    bfn.as_ref().map(|n| &**n),
+                )?)
+            } else {
+                None
+            },
+            base_bundles: Arc::default(),
+            shadow: if flags.contains(SpotLightRendererFlags::SHADOW) {
+                Some(Self::create_shadow(
+                    device,
+                    cache.clone(),
+                    sfn.as_ref().map(|n| &**n),
+                )?)
+            } else {
+                None
+            },
+            shadow_bundles: Arc::default(),
+        })
+    }
+
+    pub fn create_base(
+        device: &gpu::Device,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<gfx::ReflectedGraphics, gpu::Error> {
+        let vert = gpu::include_spirv!("../../../shaders/screen.vert.spv");
+        let frag =
+            gpu::include_spirv!("../../../shaders/cone/spot_light_passes/single_base.frag.spv");
+        PointLightRenderer::create_pipeline(device, &vert, &frag, cache, name)
+    }
+
+    pub fn create_shadow(
+        device: &gpu::Device,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<gfx::ReflectedGraphics, gpu::Error> {
+        let vert = gpu::include_spirv!("../../../shaders/screen.vert.spv");
+        let frag =
+            gpu::include_spirv!("../../../shaders/cone/spot_light_passes/single_shadow.frag.spv");
+        PointLightRenderer::create_pipeline(device, &vert, &frag, cache, name)
+    }
+
+    /// Begin a pass adding to the output map of the geometry buffer
+    fn output_pass<'a, 'b>(
+        encoder: &'b mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &'a GeometryBuffer,
+        pipeline: &gfx::ReflectedGraphics,
+        clear: bool,
+    ) -> Result<gfx::pass::ReflectedGraphicsPass<'a, 'b, ()>, gpu::Error> {
+        encoder.graphics_pass_reflected::<()>(
+            device,
+            &[gfx::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Borrowed(&buffer.get("output").unwrap().view),
+                    gpu::ClearValue::ColorFloat([0.0; 4]),
+                ),
+                load: if clear {
+                    gpu::LoadOp::Clear
+                } else {
+                    gpu::LoadOp::Load
+                },
+                store: gpu::StoreOp::Store,
+            }],
+            &[],
+            Some(gfx::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Borrowed(&buffer.depth.view),
+                    gpu::ClearValue::Depth(1.0),
+                ),
+                load: gpu::LoadOp::Load,
+                store: gpu::StoreOp::Store,
+            }),
+            pipeline,
+        )
+    }
+}
+
+impl SpotLightRenderer {
+    pub fn base_bundle(
+        &self,
+        device: &gpu::Device,
+        buffer: &GeometryBuffer,
+        camera: &Camera,
+        light: &SpotLight,
+    ) -> Result<gfx::Bundle, gpu::Error> {
+        let mut bundles = self.base_bundles.lock().unwrap();
+        let key = (buffer.id, camera.buffer.id(), light.buffer.id());
+        if bundles.get(&key).is_none() {
+            let b = match self
+                .base
+                .as_ref()
+                .expect("ERROR: SpotLightRenderer missing flags")
+                .bundle()
+                .unwrap()
+                .set_resource("u_position", buffer.get("world_pos").unwrap())
+                .unwrap()
+                .set_resource("u_normal", buffer.get("normal").unwrap())
+                .unwrap()
+                .set_resource("u_albedo", buffer.get("albedo").unwrap())
+                .unwrap()
+                .set_resource("u_roughness", buffer.get("roughness").unwrap())
+                .unwrap()
+                .set_resource("u_metallic", buffer.get("metallic").unwrap())
+                .unwrap()
+                .set_resource("u_sampler", &buffer.sampler)
+                .unwrap()
+                .set_resource("u_light_data", light)
+                .unwrap()
+                .set_resource("u_camera", camera)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+            bundles.insert(key, b);
+        }
+
+        Ok(bundles.get(&key).unwrap().clone())
+    }
+
+    /// Add the lights contributions to the output map of the geometry buffer
+    ///
+    /// Each light in the iterator will be drawn as a fullscreen pass under a separate draw call
+    ///
+    /// strength multiplies the lights contibution per pixel
+    /// clear specifies if to clear the geometry buffers output map or not
+    pub fn base_pass<'a>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &'a GeometryBuffer,
+        camera: &'a Camera,
+        lights: impl IntoIterator<Item = &'a SpotLight>,
+        strength: f32,
+        clear: bool,
+    ) -> Result<(), gpu::Error> {
+        let mut pass = Self::output_pass(
+            encoder,
+            device,
+            buffer,
+            self.base
+                .as_ref()
+                .expect("ERROR: SpotLightRenderer missing flags"),
+            clear,
+        )?;
+
+        pass.push_f32("strength", strength);
+        pass.push_f32("width", buffer.width as _);
+        pass.push_f32("height", buffer.height as _);
+
+        for light in lights {
+            let bundle = self.base_bundle(device, buffer, camera, light)?;
+            pass.set_bundle_owned(bundle);
+            pass.draw(0, 3, 0, 1);
+        }
+
+        Ok(())
+    }
+}
+
+// shadow passes
+impl SpotLightRenderer {
+    pub fn shadow_bundle(
+        &self,
+        device: &gpu::Device,
+        buffer: &GeometryBuffer,
+        camera: &Camera,
+        light: &SpotLight,
+        shadow: &SpotDepthMap,
+    ) -> Result<gfx::Bundle, gpu::Error> {
+        let mut bundles = self.shadow_bundles.lock().unwrap();
+        let key = (buffer.id, camera.buffer.id(), light.buffer.id(), shadow.id);
+        if bundles.get(&key).is_none() {
+            let b = match self
+                .shadow
+                .as_ref()
+                .expect("ERROR: SpotLightRenderer missing flags")
+                .bundle()
+                .unwrap()
+                .set_resource("u_position", buffer.get("world_pos").unwrap())
+                .unwrap()
+                .set_resource("u_normal", buffer.get("normal").unwrap())
+                .unwrap()
+                .set_resource("u_albedo", buffer.get("albedo").unwrap())
+                .unwrap()
+                .set_resource("u_roughness", buffer.get("roughness").unwrap())
+                .unwrap()
+                .set_resource("u_metallic", buffer.get("metallic").unwrap())
+                .unwrap()
+                .set_resource("u_sampler", &buffer.sampler)
+                .unwrap()
+                .set_resource("u_light_data", light)
+                .unwrap()
+                .set_resource("u_camera", camera)
+                .unwrap()
+                .set_resource("u_shadow_data", &shadow.uniform)
+                .unwrap()
+                .set_resource("u_shadow_map", &shadow.texture)
+                .unwrap()
+                .set_resource("u_shadow_sampler", &shadow.sampler)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+            bundles.insert(key, b);
+        }
+
+        Ok(bundles.get(&key).unwrap().clone())
+    }
+
+    /// Add the lights contributions to the output map of the geometry buffer including shadows
+    ///
+    /// Each light in the iterator will be drawn as a fullscreen pass under a separate draw call
+    ///
+    /// strength multiplies the lights contibution per pixel
+    /// samples is the number of shadow map reads for calculating shadow contribution (max 16)
+    /// clear specifies if to clear the geometry buffers output map or not
+    pub fn shadow_pass<'a>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &'a GeometryBuffer,
+        camera: &'a Camera,
+        lights: impl IntoIterator<Item = (&'a SpotLight, &'a SpotDepthMap)>,
+        strength: f32,
+        samples: u32,
+        clear: bool,
+    ) -> Result<(), gpu::Error> {
+        let mut pass = Self::output_pass(
+            encoder,
+            device,
+            buffer,
+            self.shadow
+                .as_ref()
+                .expect("ERROR: SpotLightRenderer missing flags"),
+            clear,
+        )?;
+
+        pass.push_f32("strength", strength);
+        pass.push_u32("samples", samples.clamp(1, 16));
+        pass.push_f32("width", buffer.width as _);
+        pass.push_f32("height", buffer.height as _);
+
+        for (light, shadow) in lights {
+            let bundle = self.shadow_bundle(device, buffer, camera, light, shadow)?;
+            pass.set_bundle_owned(bundle);
+            pass.draw(0, 3, 0, 1);
+        }
+
+        Ok(())
+    }
+
+    /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
+    /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
+    /// This function drops Descriptor sets cached by self
+    pub fn clear(&mut self) {
+        self.base_bundles.lock().unwrap().clear();
+        self.shadow_bundles.lock().unwrap().clear();
+        if let Some(base) = self.base.as_ref() {
+            base.clear();
+        }
+        if let Some(shadow) = self.shadow.as_ref() {
+            shadow.clear();
+        }
+    }
+}
@@ -36,6 +36,64 @@ impl Default for MaterialData {
 unsafe impl bytemuck::Pod for MaterialData {}
 unsafe impl bytemuck::Zeroable for MaterialData {}
 
+/// The maximum number of layers of a layered material, the first layer and one layer for each channel of the mask
+pub const MAX_MATERIAL_LAYERS: usize = 5;
+
+/// One layer of a layered material, see [`MaterialBuilder::layered_fragment`]
+#[derive(Clone, Copy)]
+pub struct MaterialLayer<'a> {
+    /// sampled for the albedo of the layer, defaults.albedo is used if None
+    pub albedo: Option<&'a gfx::Texture2D>,
+    /// sampled for the roughness of the layer, defaults.roughness is used if None
+    pub roughness: Option<&'a gfx::Texture2D>,
+    /// sampled for the metallic of the layer, defaults.metallic is used if None
+    pub metallic: Option<&'a gfx::Texture2D>,
+    /// values used for maps without textures
+    pub defaults: MaterialData,
+    /// multiplies the uv that the layer's textures are sampled at so that they can be tiled
+    pub uv_scale: f32,
+}
+
+impl<'a> MaterialLayer<'a> {
+    /// A layer sampling albedo and roughness from textures
+    pub fn textured(
+        albedo: &'a gfx::Texture2D,
+        roughness: &'a gfx::Texture2D,
+        metallic: Option<&'a gfx::Texture2D>,
+        uv_scale: f32,
+    ) -> Self {
+        Self {
+            albedo: Some(albedo),
+            roughness: Some(roughness),
+            metallic,
+            defaults: MaterialData::default(),
+            uv_scale,
+        }
+    }
+
+    /// A layer with constant values
+    pub fn constant(defaults: MaterialData) -> Self {
+        Self {
+            albedo: None,
+            roughness: None,
+            metallic: None,
+            defaults,
+            uv_scale: 1.0,
+        }
+    }
+}
+
+/// Where the weights of the layers of a layered material come from
+///
+/// Each channel r, g, b, a weights layers 1, 2, 3, 4 in order over the layers before them
+#[derive(Clone, Copy)]
+pub enum LayerMask<'a> {
+    /// the vertex color, the mesh must have a vec4 color attribute see [`super::ColorVertex`]
+    VertexColor,
+    /// a texture sampled at the uv of the mesh, for example a terrain splat map
+    Texture(&'a gfx::Texture2D),
+}
+
 /// Builds a Materials shader modules as well a bundle
 pub struct MaterialBuilder<'a> {
     /// builds the vertex module
@@ -63,6 +121,18 @@ pub struct MaterialBuilder<'a> {
     pub subsurface: spv::Output<spv::IOVec4>,
     /// the uv coordinate at that point
     pub uv: spv::Output<spv::IOVec2>,
+    /// the change in uv coordinate since the previous frame, see [`MaterialBuilder::store_velocity`]
+    pub velocity: spv::Output<spv::IOVec2>,
+
+    /// outputs of a transparent material, see [`MaterialBuilder::new_transparent`]
+    transparent: Option<TransparentOutputs<'a>>,
+}
+
+/// The lighting and outputs written to by transparent materials
+struct TransparentOutputs<'a> {
+    lighting: &'a super::TransparentLighting,
+    accum: spv::Output<spv::IOVec4>,
+    revealage: spv::Output<spv::IOFloat>,
 }
 
 impl<'a> MaterialBuilder<'a> {
@@ -70,14 +140,46 @@ impl<'a> MaterialBuilder<'a> {
     pub fn new() -> Self {
         let vertex = spv::Builder::new();
         let fragment = spv::Builder::new();
-        let world_pos = fragment.output(0, false, Some("out_world_pos"));
-        let view_pos = fragment.output(1, false, Some("out_view_pos"));
-        let normal = fragment.output(2, false, Some("out_normal"));
-        let albedo = fragment.output(3, false, Some("out_albedo"));
-        let roughness = fragment.output(4, false, Some("out_roughness"));
-        let metallic = fragment.output(5, false, Some("out_metallic"));
-        let subsurface = fragment.output(6, false, Some("out_subsurface"));
-        let uv = fragment.output(7, false, Some("out_uv"));
+        Self::from_builders(vertex, fragment, 0, None)
+    }
+
+    /// Create a new MaterialBuilder for a transparent material
+    ///
+    /// Instead of writing to the geometry buffer the fragment is lit by lighting and written to the accumulation and
+    /// revealage targets of a [`super::TransparentRenderer`], see [`Material::transparent_pass`]
+    /// The outputs of the builder should still be stored to in the fragment shader as they are read back for lighting
+    pub fn new_transparent(lighting: &'a super::TransparentLighting) -> Self {
+        let vertex = spv::Builder::new();
+        let fragment = spv::Builder::new();
+        let accum = fragment.output(0, false, Some("out_accum"));
+        let revealage = fragment.output(1, false, Some("out_revealage"));
+        Self::from_builders(
+            vertex,
+            fragment,
+            2,
+            Some(TransparentOutputs {
+                lighting,
+                accum,
+                revealage,
+            }),
+        )
+    }
+
+    fn from_builders(
+        vertex: spv::Builder,
+        fragment: spv::Builder,
+        first: u32,
+        transparent: Option<TransparentOutputs<'a>>,
+    ) -> Self {
+        let world_pos = fragment.output(first, false, Some("out_world_pos"));
+        let view_pos = fragment.output(first + 1, false, Some("out_view_pos"));
+        let normal = fragment.output(first + 2, false, Some("out_normal"));
+        let albedo = fragment.output(first + 3, false, Some("out_albedo"));
+        let roughness = fragment.output(first + 4, false, Some("out_roughness"));
+        let metallic = fragment.output(first + 5, false, Some("out_metallic"));
+        let subsurface = fragment.output(first + 6, false, Some("out_subsurface"));
+        let uv = fragment.output(first + 7, false, Some("out_uv"));
+        let velocity = fragment.output(first + 8, false, Some("out_velocity"));
 
         Self {
             vertex,
@@ -92,14 +194,93 @@ impl<'a> MaterialBuilder<'a> {
             metallic,
             subsurface,
             uv,
+            velocity,
+
+            transparent,
         }
     }
 
+    /// Returns true if the builder was created with [`MaterialBuilder::new_transparent`]
+    pub fn is_transparent(&self) -> bool {
+        self.transparent.is_some()
+    }
+
+    /// Sets the lighting of a transparent material in the fragment shader and returns the spir-v uniform
+    ///
+    /// Returns None if the material isn't transparent
+    pub fn transparent_lighting(
+        &self,
+    ) -> Option<spv::Uniform<super::transparent::SpvTransparentData<'_>>> {
+        self.transparent
+            .as_ref()
+            .map(|t| self.set_fragment_uniform(t.lighting, Some("u_lighting")))
+    }
+
+    /// Light the albedo and normal outputs and write them to the accumulation and revealage outputs
+    ///
+    /// Must be called inside the fragment entry point after the albedo, normal and view_pos outputs have been stored
+    /// lighting should be from [`MaterialBuilder::transparent_lighting`], does nothing if the material isn't transparent
+    pub fn store_transparent(
+        &self,
+        lighting: &Option<spv::Uniform<super::transparent::SpvTransparentData<'_>>>,
+    ) {
+        let (transparent, lighting) = match (&self.transparent, lighting) {
+            (Some(t), Some(l)) => (t, l),
+            _ => return,
+        };
+        let b = &self.fragment;
+        let lighting = lighting.load();
+        let albedo = self.albedo.load();
+        let normal = self.normal.load().normalized();
+        let alpha = albedo.w();
+
+        // wrapped diffuse so that the back of the surface isn't black
+        let wrap = normal.dot(lighting.light_direction().xyz().normalized()) * 0.5 + 0.5;
+        let diffuse = lighting.ambient().xyz() + lighting.light_color().xyz() * (wrap * wrap);
+        let color = albedo.xyz() * diffuse * alpha;
+
+        // depth weight from McGuire and Bavoil 2013, the constant in the denominator caps the weight near the camera
+        let z = self.view_pos.load().z();
+        let near = z / 5.0;
+        let far = z / 200.0;
+        let far = far * far * far;
+        let weight = alpha * 10.0 / (near * near + far * far + 3e-3);
+
+        let accum = color * weight;
+        transparent
+            .accum
+            .store(b.vec4(accum.x(), accum.y(), accum.z(), alpha * weight));
+        transparent.revealage.store(alpha);
+    }
+
     /// Sets a camera in the vertex shader and returns the spir-v uniform
     pub fn camera(&self) -> spv::Uniform<crate::utils::camera::SpvCameraData> {
         self.vertex.uniform::<SpvCameraData>(0, 0, Some("u_camera"))
     }
 
+    /// Sets a camera in the fragment shader and returns the spir-v uniform
+    pub fn fragment_camera(&self) -> spv::Uniform<crate::utils::camera::SpvCameraData<'_>> {
+        self.fragment.uniform::<SpvCameraData>(0, 0, Some("u_camera"))
+    }
+
+    /// Store the change in screen position of world_pos since the previous frame to the velocity output
+    ///
+    /// Must be called inside the fragment entry point, camera should be from [`MaterialBuilder::fragment_camera`]
+    pub fn store_velocity(
+        &self,
+        camera: &spv::Uniform<crate::utils::camera::SpvCameraData>,
+        world_pos: spv::Vec3<'_>,
+    ) {
+        let b = &self.fragment;
+        let camera = camera.load();
+        let pos = b.vec4(world_pos.x(), world_pos.y(), world_pos.z(), 1.0);
+        let current = camera.projection() * (camera.view() * pos);
+        let previous = camera.prev_view_projection() * pos;
+        let velocity =
+            (current.xy() / current.w() - camera.jitter()) - previous.xy() / previous.w();
+        self.velocity.store(velocity * 0.5);
+    }
+
     pub fn instances(&self) -> spv::Storage<SpvInstanceData> {
         self.vertex.storage::<SpvInstanceData>(
             // spv::StorageAccessDesc {
@@ -254,6 +435,76 @@ impl<'a> MaterialBuilder<'a> {
         (in_world_pos, in_view_pos, in_uv, in_t, in_b, in_n)
     }
 
+    /// Creates a vertex state that also passes the vertex color to the fragment shader
+    ///
+    /// The vertex builder can't be used after this function
+    /// returns (in_world_pos, in_view_pos, in_normal, in_uv, in_color) for the fragment shader
+    pub fn color_vertex(
+        &self,
+    ) -> (
+        spv::Input<spv::IOVec3>,
+        spv::Input<spv::IOVec3>,
+        spv::Input<spv::IOVec3>,
+        spv::Input<spv::IOVec2>,
+        spv::Input<spv::IOVec4>,
+    ) {
+        let in_pos = self.vertex.in_vec3(0, "in_pos");
+        let in_normal = self.vertex.in_vec3(1, "in_normal");
+        let in_uv = self.vertex.in_vec2(2, "in_uv");
+        let in_color = self.vertex.in_vec4(3, "in_color");
+
+        let out_world_pos = self.vertex.out_vec3(0, "out_world_pos");
+        let out_view_pos = self.vertex.out_vec3(1, "out_view_pos");
+        let out_normal = self.vertex.out_vec3(2, "out_normal");
+        let out_uv = self.vertex.out_vec2(3, "out_uv");
+        let out_color = self.vertex.out_vec4(4, "out_color");
+
+        let camera = self.camera();
+        let instances = self.instances();
+
+        let instance_idx = self.vertex.instance_index();
+
+        let vk_pos = self.vertex.vk_position();
+
+        let b = &self.vertex;
+
+        self.vertex.entry(spv::Stage::Vertex, "main", || {
+            let camera = camera.load();
+            let projection = camera.projection();
+            let view = camera.view();
+
+            let idx = instance_idx.load();
+
+            let model = instances.load_element(idx).model();
+            let pos = in_pos.load();
+            let world_pos = model * b.vec4(pos.x(), pos.y(), pos.z(), 1.0);
+            out_world_pos.store(world_pos.xyz());
+            let view_pos = view * world_pos;
+            out_view_pos.store(view_pos.xyz());
+            let screen_pos = projection * view_pos;
+            vk_pos.store(screen_pos);
+
+            let normal = in_normal.load();
+            let model_x = model.col(0).xyz();
+            let model_y = model.col(1).xyz();
+            let model_z = model.col(2).xyz();
+            let model3 = b.mat3(model_x, model_y, model_z);
+            let normal = model3 * normal;
+            out_normal.store(normal.normalized());
+
+            out_uv.store(in_uv.load());
+            out_color.store(in_color.load());
+        });
+
+        let in_world_pos = self.fragment.in_vec3(0, "in_pos");
+        let in_view_pos = self.fragment.in_vec3(1, "in_view_pos");
+        let in_normal = self.fragment.in_vec3(2, "in_normal");
+        let in_uv = self.fragment.in_vec2(3, "in_uv");
+        let in_color = self.fragment.in_vec4(4, "in_color");
+
+        (in_world_pos, in_view_pos, in_normal, in_uv, in_color)
+    }
+
     /// Set the outputs to sample from the textures
     ///
     /// The fragment builder can't be used after this function
@@ -299,12 +550,15 @@ impl<'a> MaterialBuilder<'a> {
         _discard: bool,
     ) {
         let params = self.set_fragment_uniform(&uniform, Some("u_params"));
+        let camera = self.fragment_camera();
+        let lighting = self.transparent_lighting();
 
         let b = &self.fragment;
 
         b.entry(spv::Stage::Fragment, "main", || {
             let params = params.load();
             self.world_pos.store(world_pos.load());
+            self.store_velocity(&camera, world_pos.load());
             self.view_pos.store(view_pos.load());
             self.normal.store(normal.load());
             self.albedo.store(params.albedo());
@@ -314,6 +568,7 @@ impl<'a> MaterialBuilder<'a> {
             let tmp = (-1.0 / subsurface.xyz()).exp();
             self.subsurface.store(b.vec4(tmp.x(), tmp.y(), tmp.z(), subsurface.w()));
             self.uv.store(b.vec2(0.0, 0.0));
+            self.store_transparent(&lighting);
         });
     }
 
@@ -330,9 +585,12 @@ impl<'a> MaterialBuilder<'a> {
         let mut tmp = constants.subsurface.xyz();
         tmp = (-1.0 / tmp).exp();
         let subsurface = glam::vec4(tmp.x, tmp.y, tmp.z, constants.subsurface.w);
+        let camera = self.fragment_camera();
+        let lighting = self.transparent_lighting();
         let b = &self.fragment;
         b.entry(spv::Stage::Fragment, "main", || {
             self.world_pos.store(world_pos.load());
+            self.store_velocity(&camera, world_pos.load());
             self.view_pos.store(view_pos.load());
             self.normal.store(normal.load());
             self.albedo.store(b.const_vec4(constants.albedo));
@@ -340,6 +598,7 @@ impl<'a> MaterialBuilder<'a> {
             self.metallic.store(b.const_float(constants.metallic));
             self.subsurface.store(b.const_vec4(subsurface));
             self.uv.store(b.vec2(0.0, 0.0));
+            self.store_transparent(&lighting);
         });
     }
 
@@ -399,11 +658,14 @@ impl<'a> MaterialBuilder<'a> {
         };
 
         let sampler = self.set_fragment_sampler(sampler, Some("u_sampler"));
+        let camera = self.fragment_camera();
+        let lighting = self.transparent_lighting();
 
         let b = &self.fragment;
 
         b.entry(spv::Stage::Fragment, "main", || {
             self.world_pos.store(world_pos.load());
+            self.store_velocity(&camera, world_pos.load());
             self.view_pos.store(view_pos.load());
             let uv = uv.load();
             self.uv.store(uv);
@@ -459,7 +721,160 @@ impl<'a> MaterialBuilder<'a> {
                 let tmp = (-1.0 / subsurface.xyz()).exp();
                 self.subsurface.store(b.vec4(tmp.x(), tmp.y(), tmp.z(), subsurface.w()));
             };
-            
+
+            self.store_transparent(&lighting);
+        });
+    }
+
+    /// Set the outputs to blend between layers weighted by the mask
+    ///
+    /// The fragment builder can't be used after this function
+    /// Each layer after the first is blended over the layers before it by one channel of the mask so only the
+    /// shader for the number of layers and textures used is generated. The subsurface output is taken from the defaults
+    /// of the first layer. color must be some if the mask is [`LayerMask::VertexColor`], see [`MaterialBuilder::color_vertex`]
+    pub fn layered_fragment(
+        &mut self,
+        world_pos: spv::Input<spv::IOVec3>,
+        view_pos: spv::Input<spv::IOVec3>,
+        normal: spv::Input<spv::IOVec3>,
+        uv: spv::Input<spv::IOVec2>,
+        color: Option<spv::Input<spv::IOVec4>>,
+        layers: &[MaterialLayer<'a>],
+        mask: LayerMask<'a>,
+        sampler: &'a gpu::Sampler,
+    ) {
+        const ALBEDO: [&str; MAX_MATERIAL_LAYERS] =
+            ["u_albedo_0", "u_albedo_1", "u_albedo_2", "u_albedo_3", "u_albedo_4"];
+        const ROUGHNESS: [&str; MAX_MATERIAL_LAYERS] = [
+            "u_roughness_0",
+            "u_roughness_1",
+            "u_roughness_2",
+            "u_roughness_3",
+            "u_roughness_4",
+        ];
+        const METALLIC: [&str; MAX_MATERIAL_LAYERS] = [
+            "u_metallic_0",
+            "u_metallic_1",
+            "u_metallic_2",
+            "u_metallic_3",
+            "u_metallic_4",
+        ];
+
+        if layers.is_empty() || layers.len() > MAX_MATERIAL_LAYERS {
+            panic!(
+                "ERROR: Attempt to create layered material with {} layers, must have between 1 and {}",
+                layers.len(),
+                MAX_MATERIAL_LAYERS
+            );
+        }
+
+        let textures = layers
+            .iter()
+            .enumerate()
+            .map(|(i, layer)| {
+                (
+                    layer
+                        .albedo
+                        .map(|t| self.set_fragment_texture(t, Some(ALBEDO[i]))),
+                    layer
+                        .roughness
+                        .map(|t| self.set_fragment_texture(t, Some(ROUGHNESS[i]))),
+                    layer
+                        .metallic
+                        .map(|t| self.set_fragment_texture(t, Some(METALLIC[i]))),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mask = match mask {
+            LayerMask::VertexColor => Left(color.expect(
+                "ERROR: Attempt to create layered material masked by vertex color without color input",
+            )),
+            LayerMask::Texture(t) => Right(self.set_fragment_texture(t, Some("u_mask"))),
+        };
+
+        let sampler = self.set_fragment_sampler(sampler, Some("u_sampler"));
+        let camera = self.fragment_camera();
+        let lighting = self.transparent_lighting();
+
+        let b = &self.fragment;
+
+        b.entry(spv::Stage::Fragment, "main", || {
+            self.world_pos.store(world_pos.load());
+            self.store_velocity(&camera, world_pos.load());
+            self.view_pos.store(view_pos.load());
+            self.normal.store(normal.load());
+            let uv = uv.load();
+            self.uv.store(uv);
+
+            let combined_mask = match &mask {
+                Left(_) => None,
+                Right(mask) => Some(spv::combine(mask, sampler)),
+            };
+            let weights = match (&mask, &combined_mask) {
+                (Left(color), _) => color.load(),
+                (_, Some(combined)) => spv::sample(combined, uv),
+                _ => unreachable!(),
+            };
+
+            let combined = textures
+                .iter()
+                .map(|(albedo, roughness, metallic)| {
+                    (
+                        albedo.as_ref().map(|t| spv::combine(t, sampler)),
+                        roughness.as_ref().map(|t| spv::combine(t, sampler)),
+                        metallic.as_ref().map(|t| spv::combine(t, sampler)),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let mut blended = None;
+            for (i, (layer, (albedo, roughness, metallic))) in
+                layers.iter().zip(&combined).enumerate()
+            {
+                let layer_uv = uv * layer.uv_scale;
+                let albedo = match albedo {
+                    Some(t) => spv::sample(t, layer_uv),
+                    None => b.const_vec4(layer.defaults.albedo),
+                };
+                let roughness = match roughness {
+                    Some(t) => spv::sample(t, layer_uv).x(),
+                    None => b.const_float(layer.defaults.roughness),
+                };
+                let metallic = match metallic {
+                    Some(t) => spv::sample(t, layer_uv).x(),
+                    None => b.const_float(layer.defaults.metallic),
+                };
+
+                blended = Some(match blended {
+                    None => (albedo, roughness, metallic),
+                    Some((a, r, m)) => {
+                        let w = match i {
+                            1 => weights.x(),
+                            2 => weights.y(),
+                            3 => weights.z(),
+                            _ => weights.w(),
+                        };
+                        (
+                            a + (albedo - a) * w,
+                            r + (roughness - r) * w,
+                            m + (metallic - m) * w,
+                        )
+                    }
+                });
+            }
+
+            let (albedo, roughness, metallic) = blended.unwrap();
+            self.albedo.store(albedo);
+            self.roughness.store(roughness);
+            self.metallic.store(metallic);
+
+            let subsurface = b.const_vec4(layers[0].defaults.subsurface);
+            let tmp = (-1.0 / subsurface.xyz()).exp();
+            self.subsurface
+                .store(b.vec4(tmp.x(), tmp.y(), tmp.z(), subsurface.w()));
+
+            self.store_transparent(&lighting);
         });
     }
 
@@ -508,17 +923,6 @@ impl<'a> MaterialBuilder<'a> {
         self.vertex.texture(2, binding, name)
     }
 
-    pub fn set_vertex_d_texture<D: gfx::AsDimension>(
-        &mut self,
-        texture: &'a gfx::DTexture<D>,
-        name: Option<&'static str>,
-    ) -> spv::DTexture<D::Spirv> {
-        let mut resources = self.resources.borrow_mut();
-        let binding = resources.len() as u32;
-        resources.push(&texture.0);
-        self.vertex.dtexture(2, binding, name)
-    }
-
     pub fn set_vertex_i_texture<D: gfx::AsDimension>(
         &mut self,
         texture: &'a gfx::ITexture<D>,
@@ -598,18 +1002,6 @@ impl<'a> MaterialBuilder<'a> {
         self.fragment.texture(2, binding, name)
     }
 
-    /// Set a texture in the fragment shader
-    pub fn set_fragment_d_texture<D: gfx::AsDimension>(
-        &mut self,
-        texture: &'a gfx::DTexture<D>,
-        name: Option<&'static str>,
-    ) -> spv::DTexture<D::Spirv> {
-        let mut resources = self.resources.borrow_mut();
-        let binding = resources.len() as u32;
-        resources.push(&texture.0);
-        self.fragment.dtexture(2, binding, name)
-    }
-
     /// Set a texture in the fragment shader
     pub fn set_fragment_i_texture<D: gfx::AsDimension>(
         &mut self,
@@ -647,11 +1039,34 @@ impl<'a> MaterialBuilder<'a> {
     }
 
     /// Build a material from defalt graphics pipeline parameters
+    ///
+    /// Transparent materials blend into the accumulation and revealage targets and test against but don't write depth
     pub fn build(self, device: &gpu::Device, cache: Option<gpu::PipelineCache>) -> Result<Material, gfx::error::ReflectedError> {
+        if self.is_transparent() {
+            let mut blend_states = [gpu::BlendState::REPLACE; 11];
+            blend_states[0] = gpu::BlendState::ADD;
+            blend_states[1] = gpu::BlendState {
+                src_blend: gpu::BlendFactor::Zero,
+                dst_blend: gpu::BlendFactor::OneMinusSrcColor,
+                src_alpha_blend: gpu::BlendFactor::Zero,
+                dst_alpha_blend: gpu::BlendFactor::OneMinusSrcAlpha,
+                ..gpu::BlendState::ADD
+            };
+            return self.build_from_info(
+                device,
+                gpu::Rasterizer::default(),
+                &blend_states,
+                Some(gpu::DepthState {
+                    write_enable: false,
+                    ..Default::default()
+                }),
+                cache,
+            );
+        }
         self.build_from_info(
             device,
             gpu::Rasterizer::default(),
-            &[gpu::BlendState::REPLACE; 8],
+            &[gpu::BlendState::REPLACE; 9],
             Some(gpu::DepthState::default()),
             cache,
         )
@@ -669,6 +1084,7 @@ impl<'a> MaterialBuilder<'a> {
         if blend_states.len() < 7 {
             panic!("ERROR: Attempt to build material with less than 7 blend states\nOne state must be supplied for each output write")
         }
+        let transparent = self.is_transparent();
 
         let vertex_spv = self.vertex.compile();
         let fragment_spv = self.fragment.compile();
@@ -717,6 +1133,7 @@ impl<'a> MaterialBuilder<'a> {
             camera_set_map: Arc::new(Mutex::new(HashMap::new())),
             instance_set_map: Arc::new(Mutex::new(HashMap::new())),
             set,
+            transparent,
         })
     }
 }
@@ -727,6 +1144,8 @@ pub struct Material {
     pub instance_set_map: Arc<Mutex<HashMap<u64, gpu::DescriptorSet>>>,
     pub camera_set_map: Arc<Mutex<HashMap<u64, gpu::DescriptorSet>>>,
     pub set: Option<gpu::DescriptorSet>,
+    /// if the material was built from [`MaterialBuilder::new_transparent`]
+    pub transparent: bool,
 }
 
 impl Material {
@@ -798,6 +1217,41 @@ impl Material {
         builder.build(device, cache)
     }
 
+    /// Create a material blending between layers with a single instance
+    ///
+    /// If the mask is [`LayerMask::VertexColor`] the meshes drawn must have a vec4 color attribute, see [`super::ColorVertex`]
+    pub fn layered(
+        device: &gpu::Device,
+        layers: &[MaterialLayer<'_>],
+        mask: LayerMask<'_>,
+        sampler: &gpu::Sampler,
+        cache: Option<gpu::PipelineCache>,
+    ) -> Result<Self, gfx::error::ReflectedError> {
+        let mut builder = MaterialBuilder::new();
+        match mask {
+            LayerMask::VertexColor => {
+                let (world_pos, view_pos, normal, uv, color) = builder.color_vertex();
+                builder.layered_fragment(
+                    world_pos,
+                    view_pos,
+                    normal,
+                    uv,
+                    Some(color),
+                    layers,
+                    mask,
+                    sampler,
+                );
+            }
+            LayerMask::Texture(_) => {
+                let (world_pos, view_pos, normal, uv) = builder.default_vertex();
+                builder.layered_fragment(
+                    world_pos, view_pos, normal, uv, None, layers, mask, sampler,
+                );
+            }
+        }
+        builder.build(device, cache)
+    }
+
     /// Draw all the meshes with the material into self
     pub fn pass<'a, V: gfx::Vertex>(
         &'a self,
@@ -808,13 +1262,136 @@ impl Material {
         meshes: impl IntoIterator<Item = (&'a gfx::Mesh<V>, &'a Instances)>,
         clear: bool,
     ) -> Result<(), gpu::Error> {
+        let mut pass = self.begin_pass::<V>(encoder, device, buffer, camera, clear)?;
+
+        for (mesh, instances) in meshes {
+            pass.bind_descriptor_owned(1, self.instance_set(device, instances)?);
+            pass.draw_instanced_mesh_ref(mesh, 0, instances.length as _);
+        }
+
+        Ok(())
+    }
+
+    /// Draw the instances of each mesh that passed culling with the material into self
+    ///
+    /// The instances should have been culled by [`super::CullingRenderer::cull_pass`] earlier in the encoder,
+    /// the number of instances drawn is read from the indirect arguments written by culling
+    pub fn culled_pass<'a, V: gfx::Vertex>(
+        &'a self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &'a super::GeometryBuffer,
+        camera: &'a Camera,
+        meshes: impl IntoIterator<Item = (&'a gfx::Mesh<V>, &'a super::CulledInstances)>,
+        clear: bool,
+    ) -> Result<(), gpu::Error> {
+        let mut pass = self.begin_pass::<V>(encoder, device, buffer, camera, clear)?;
+
+        for (mesh, instances) in meshes {
+            pass.bind_descriptor_owned(1, self.instance_set(device, &instances.visible)?);
+            mesh.draw_indirect_ref(
+                &mut pass,
+                &instances.indirect,
+                0,
+                1,
+                super::CulledInstances::STRIDE,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Draw all the meshes with the transparent material into the accumulation and revealage targets of renderer
+    ///
+    /// The meshes are depth tested against the geometry buffer so should be drawn after all opaque materials
+    /// clear should be true for the first transparent material drawn each frame
+    /// The geometry buffer must not be multisampled, or must have had its depth resolved
+    /// Will panic if the material isn't transparent, see [`MaterialBuilder::new_transparent`]
+    pub fn transparent_pass<'a, V: gfx::Vertex>(
+        &'a self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &'a super::GeometryBuffer,
+        camera: &'a Camera,
+        renderer: &super::TransparentRenderer,
+        meshes: impl IntoIterator<Item = (&'a gfx::Mesh<V>, &'a Instances)>,
+        clear: bool,
+    ) -> Result<(), gpu::Error> {
+        if !self.transparent {
+            panic!("ERROR: Attempt to draw opaque material with Material::transparent_pass\nBuild the material from MaterialBuilder::new_transparent");
+        }
+        let load = if clear {
+            gpu::LoadOp::Clear
+        } else {
+            gpu::LoadOp::Load
+        };
+        let (accum, revealage) = renderer.targets(device, buffer.width, buffer.height)?;
+
+        let mut pass = encoder.graphics_pass_reflected::<V>(
+            device,
+            &[
+                gfx::Attachment {
+                    raw: gpu::Attachment::View(
+                        Cow::Owned(accum.view.clone()),
+                        gpu::ClearValue::ColorFloat([0.0; 4]),
+                    ),
+                    load,
+                    store: gpu::StoreOp::Store,
+                },
+                gfx::Attachment {
+                    raw: gpu::Attachment::View(
+                        Cow::Owned(revealage.view.clone()),
+                        gpu::ClearValue::ColorFloat([1.0; 4]),
+                    ),
+                    load,
+                    store: gpu::StoreOp::Store,
+                },
+            ],
+            &[],
+            Some(gfx::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Borrowed(&buffer.depth.view),
+                    gpu::ClearValue::Depth(1.0),
+                ),
+                load: gpu::LoadOp::Load,
+                store: gpu::StoreOp::Store,
+            }),
+            &self.graphics,
+        )?;
+
+        pass.bind_descriptor_owned(0, self.camera_set(device, camera)?);
+
+        if let Some(set) = &self.set {
+            pass.bind_descriptor_ref(2, set);
+        }
+
+        for (mesh, instances) in meshes {
+            pass.bind_descriptor_owned(1, self.instance_set(device, instances)?);
+            pass.draw_instanced_mesh_ref(mesh, 0, instances.length as _);
+        }
+
+        Ok(())
+    }
+
+    /// Begin a pass writing to the geometry buffer with the camera bound
+    fn begin_pass<'a, 'b, V: gfx::Vertex>(
+        &'a self,
+        encoder: &'b mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &'a super::GeometryBuffer,
+        camera: &'a Camera,
+        clear: bool,
+    ) -> Result<gfx::pass::ReflectedGraphicsPass<'a, 'b, V>, gpu::Error> {
+        if self.transparent {
+            panic!("ERROR: Attempt to draw transparent material into the geometry buffer\nUse Material::transparent_pass instead");
+        }
         let load = if clear {
             gpu::LoadOp::Clear
         } else {
             gpu::LoadOp::Load
         };
         let clear_color = gpu::ClearValue::ColorFloat([0.0; 4]);
-        let attachments = &[
+        let mut attachments = vec![
             "world_pos",
             "view_pos",
             "normal",
@@ -824,11 +1401,15 @@ impl Material {
             "subsurface",
             "uv",
         ];
+        // velocity is only written if the geometry buffer has somewhere to put it
+        if buffer.get("velocity").is_some() {
+            attachments.push("velocity");
+        }
 
         let (color_attachments, resolve_attachments) = if buffer.ms() {
             let mut colors = Vec::with_capacity(attachments.len());
             let mut resolves = Vec::with_capacity(attachments.len());
-            for attachment in attachments {
+            for attachment in &attachments {
                 colors.push(gfx::Attachment {
                     raw: gpu::Attachment::View(
                         Cow::Borrowed(&buffer.get_ms(attachment).unwrap().view),
@@ -850,7 +1431,7 @@ impl Material {
         } else {
             let mut colors = Vec::with_capacity(attachments.len());
 
-            for attachment in attachments {
+            for attachment in &attachments {
                 colors.push(gfx::Attachment {
                     raw: gpu::Attachment::View(
                         Cow::Borrowed(&buffer.get(attachment).unwrap().view),
@@ -887,62 +1468,70 @@ impl Material {
             &self.graphics,
         )?;
 
-        let mut camera_set_map = self.camera_set_map.lock().unwrap();
-        let camera_set = if let Some(s) = camera_set_map.get(&camera.buffer.id()) {
-            s.clone()
-        } else {
-            let s = match self
-                .graphics
-                .bundle()
-                .unwrap()
-                .set_resource("u_camera", camera)
-                .unwrap()
-                .build_set(device, 0)
-            {
-                Ok(s) => s,
-                Err(e) => match e {
-                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
-                    e => unreachable!("{}", e),
-                },
-            };
-            camera_set_map.insert(camera.buffer.id(), s.clone());
-            s
-        };
-
-        pass.bind_descriptor_owned(0, camera_set);
+        pass.bind_descriptor_owned(0, self.camera_set(device, camera)?);
 
         if let Some(set) = &self.set {
             // pass.set_bundle_ref(bundle);
             pass.bind_descriptor_ref(2, set);
         }
 
-        for (mesh, instances) in meshes {
-            let mut instance_set_map = self.instance_set_map.lock().unwrap();
-            let instance_set = if let Some(i) = instance_set_map.get(&instances.buffer.id()) {
-                i.clone()
-            } else {
-                let s = match self
-                    .graphics
-                    .bundle()
-                    .unwrap()
-                    .set_resource("u_instances", instances)
-                    .unwrap()
-                    .build_set(device, 1)
-                {
-                    Ok(s) => s,
-                    Err(e) => match e {
-                        gfx::BundleBuildError::Gpu(e) => Err(e)?,
-                        e => unreachable!("{}", e),
-                    },
-                };
-                instance_set_map.insert(instances.buffer.id(), s.clone());
-                s
-            };
-            pass.bind_descriptor_owned(1, instance_set);
-            pass.draw_instanced_mesh_ref(mesh, 0, instances.length as _);
+        Ok(pass)
+    }
+
+    /// Get or create the descriptor set referencing camera
+    fn camera_set(
+        &self,
+        device: &gpu::Device,
+        camera: &Camera,
+    ) -> Result<gpu::DescriptorSet, gpu::Error> {
+        let mut camera_set_map = self.camera_set_map.lock().unwrap();
+        if let Some(s) = camera_set_map.get(&camera.buffer.id()) {
+            return Ok(s.clone());
         }
+        let s = match self
+            .graphics
+            .bundle()
+            .unwrap()
+            .set_resource("u_camera", camera)
+            .unwrap()
+            .build_set(device, 0)
+        {
+            Ok(s) => s,
+            Err(e) => match e {
+                gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+        camera_set_map.insert(camera.buffer.id(), s.clone());
+        Ok(s)
+    }
 
-        Ok(())
+    /// Get or create the descriptor set referencing instances
+    fn instance_set(
+        &self,
+        device: &gpu::Device,
+        instances: &Instances,
+    ) -> Result<gpu::DescriptorSet, gpu::Error> {
+        let mut instance_set_map = self.instance_set_map.lock().unwrap();
+        if let Some(i) = instance_set_map.get(&instances.buffer.id()) {
+            return Ok(i.clone());
+        }
+        let s = match self
+            .graphics
+            .bundle()
+            .unwrap()
+            .set_resource("u_instances", instances)
+            .unwrap()
+            .build_set(device, 1)
+        {
+            Ok(s) => s,
+            Err(e) => match e {
+                gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+        instance_set_map.insert(instances.buffer.id(), s.clone());
+        Ok(s)
     }
 
     /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
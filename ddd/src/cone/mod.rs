@@ -1,16 +1,26 @@
 //! A defered, rasterized, physically based rendering library
 
+pub mod culling;
+pub mod decal;
 pub mod depth;
 pub mod gbuffer;
 pub mod lights;
 pub mod material;
+pub mod picking;
 pub mod postprocess;
+pub mod skinning;
+pub mod transparent;
 
+pub use culling::*;
+pub use decal::*;
 pub use depth::*;
 pub use gbuffer::*;
 pub use lights::*;
 pub use material::*;
+pub use picking::*;
 pub use postprocess::*;
+pub use skinning::*;
+pub use transparent::*;
 
 
 #[derive(Debug, Clone, Copy, gfx::Vertex)]
@@ -99,6 +109,74 @@ impl mesh::Vertex for Vertex {
         self.tangent_v = v;
     }
 
+    fn set_normal(&mut self, normal: glam::Vec3) {
+        self.normal = normal;
+    }
+
+    fn pos(&self) -> glam::Vec3 {
+        self.pos
+    }
+
+    fn uv(&self) -> Option<glam::Vec2> {
+        Some(self.uv)
+    }
+
+    fn normal(&self) -> Option<glam::Vec3> {
+        Some(self.normal)
+    }
+
+    fn tangent_u(&self) -> Option<glam::Vec3> {
+        Some(self.tangent_u)
+    }
+
+    fn tangent_v(&self) -> Option<glam::Vec3> {
+        Some(self.tangent_v)
+    }
+}
+
+/// A vertex with a color used to weight the layers of a layered material, see [`LayerMask::VertexColor`]
+#[derive(Debug, Clone, Copy, gfx::Vertex)]
+#[allow(dead_code)]
+#[repr(C)]
+pub struct ColorVertex {
+    pub pos: glam::Vec3,
+    pub normal: glam::Vec3,
+    pub tangent_u: glam::Vec3,
+    pub tangent_v: glam::Vec3,
+    pub uv: glam::Vec2,
+    pub color: glam::Vec4,
+}
+
+unsafe impl bytemuck::Pod for ColorVertex {}
+unsafe impl bytemuck::Zeroable for ColorVertex {}
+
+impl mesh::Vertex for ColorVertex {
+    fn new(
+        pos: glam::Vec3,
+        uv: glam::Vec2,
+        normal: glam::Vec3,
+        tangent_u: Option<glam::Vec3>,
+        tangent_v: Option<glam::Vec3>,
+    ) -> Self {
+        Self {
+            pos,
+            uv,
+            normal,
+            tangent_u: tangent_u.unwrap_or(glam::Vec3::ZERO),
+            tangent_v: tangent_v.unwrap_or(glam::Vec3::ZERO),
+            color: glam::Vec4::ZERO,
+        }
+    }
+
+    fn set_tangents(&mut self, u: glam::Vec3, v: glam::Vec3) {
+        self.tangent_u = u;
+        self.tangent_v = v;
+    }
+
+    fn set_normal(&mut self, normal: glam::Vec3) {
+        self.normal = normal;
+    }
+
     fn pos(&self) -> glam::Vec3 {
         self.pos
     }
@@ -119,3 +197,15 @@ impl mesh::Vertex for Vertex {
         Some(self.tangent_v)
     }
 }
+
+impl From<ColorVertex> for Vertex {
+    fn from(v: ColorVertex) -> Self {
+        Self {
+            pos: v.pos,
+            normal: v.normal,
+            tangent_u: v.tangent_u,
+            tangent_v: v.tangent_v,
+            uv: v.uv,
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Object ids for selecting objects under the cursor
+//!
+//! [`ObjectIdRenderer`] for drawing the ids of meshes into the "object_id" map of a [`GeometryBuffer`]
+//!
+//! Each instance of a mesh is given an id, the first id of the draw plus the instance index. After materials have been
+//! drawn the meshes are drawn again testing against the depth of the geometry buffer so that only the id of the visible
+//! object is kept. The id under a pixel can then be read back with [`GeometryBuffer::pick`] without stalling the gpu.
+
+use gfx::prelude::*;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::cone::GeometryBuffer;
+use crate::utils::{Camera, Instances};
+
+/// Draws object ids into the "object_id" map of a [`GeometryBuffer`]
+///
+/// Usage overview:
+///  - create the geometry buffer with an ("object_id", 1) map
+///  - draw materials as usual
+///  - call [`ObjectIdRenderer::pass`] with the same meshes
+///  - call [`GeometryBuffer::pick`] and poll the returned future
+///
+/// The geometry buffer must not be multisampled, or must have had its depth resolved
+pub struct ObjectIdRenderer {
+    pub pipeline: gfx::ReflectedGraphics,
+    /// map from Camera to descriptor set
+    pub camera_sets: Arc<Mutex<HashMap<u64, gpu::DescriptorSet>>>,
+    /// map from Instances to descriptor set
+    pub instance_sets: Arc<Mutex<HashMap<u64, gpu::DescriptorSet>>>,
+}
+
+impl ObjectIdRenderer {
+    pub fn new(
+        device: &gpu::Device,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let vert = gpu::include_spirv!("../../shaders/cone/picking/object_id.vert.spv");
+        let frag = gpu::include_spirv!("../../shaders/cone/picking/object_id.frag.spv");
+
+        let pipeline = match gfx::ReflectedGraphics::from_spirv(
+            device,
+            &vert,
+            None,
+            Some(&frag),
+            gpu::Rasterizer::default(),
+            // integer targets can't be blended
+            &[gpu::BlendState::REPLACE],
+            Some(gpu::DepthStencilState {
+                // only keep the id of the geometry that was drawn into the geometry buffer
+                depth: Some(gpu::DepthState {
+                    test_enable: true,
+                    write_enable: false,
+                    compare_op: gpu::CompareOp::LessEqual,
+                }),
+                stencil_front: None,
+                stencil_back: None,
+            }),
+            cache,
+            name,
+        ) {
+            Ok(g) => g,
+            Err(e) => match e {
+                gfx::error::ReflectedError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+
+        Ok(Self {
+            pipeline,
+            camera_sets: Arc::default(),
+            instance_sets: Arc::default(),
+        })
+    }
+
+    fn set(
+        &self,
+        device: &gpu::Device,
+        map: &Mutex<HashMap<u64, gpu::DescriptorSet>>,
+        key: u64,
+        set: u32,
+        resources: &[(&str, &dyn gfx::Resource)],
+    ) -> Result<gpu::DescriptorSet, gpu::Error> {
+        let mut map = map.lock().unwrap();
+        if let Some(s) = map.get(&key) {
+            return Ok(s.clone());
+        }
+        let mut bundle = self.pipeline.bundle().unwrap();
+        for (name, resource) in resources {
+            bundle = bundle.set_resource(name, *resource).unwrap();
+        }
+        let s = match bundle.build_set(device, set) {
+            Ok(s) => s,
+            Err(e) => match e {
+                gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+        map.insert(key, s.clone());
+        Ok(s)
+    }
+
+    /// Draw the ids of the meshes into the "object_id" map of the geometry buffer
+    ///
+    /// meshes are (mesh, instances, first id), instance i of the mesh has id first id + i
+    /// clear should be true for the first pass each frame, pixels without objects are picked as None
+    /// Will panic if the geometry buffer doesn't have an "object_id" map
+    pub fn pass<'a, V: gfx::Vertex>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &'a GeometryBuffer,
+        camera: &Camera,
+        meshes: impl IntoIterator<Item = (&'a gfx::Mesh<V>, &'a Instances, u32)>,
+        clear: bool,
+    ) -> Result<(), gpu::Error> {
+        let ids = match buffer.get("object_id") {
+            Some(t) => t,
+            None => panic!(
+                "ERROR: Attempt to draw object ids into geometry buffer without object_id map"
+            ),
+        };
+
+        let camera_set = self.set(
+            device,
+            &self.camera_sets,
+            camera.buffer.id(),
+            0,
+            &[("u_camera", camera)],
+        )?;
+
+        let mut pass = encoder.graphics_pass_reflected::<V>(
+            device,
+            &[gfx::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Borrowed(&ids.view),
+                    gpu::ClearValue::ColorUint([0; 4]),
+                ),
+                load: if clear {
+                    gpu::LoadOp::Clear
+                } else {
+                    gpu::LoadOp::Load
+                },
+                store: gpu::StoreOp::Store,
+            }],
+            &[],
+            Some(gfx::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Borrowed(&buffer.depth.view),
+                    gpu::ClearValue::Depth(1.0),
+                ),
+                load: gpu::LoadOp::Load,
+                store: gpu::StoreOp::Store,
+            }),
+            &self.pipeline,
+        )?;
+        pass.bind_descriptor_owned(0, camera_set);
+
+        for (mesh, instances, first_id) in meshes {
+            let instance_set = self.set(
+                device,
+                &self.instance_sets,
+                instances.buffer.id(),
+                1,
+                &[("u_instances", instances)],
+            )?;
+            pass.bind_descriptor_owned(1, instance_set);
+            pass.push_u32("first_id", first_id);
+            pass.draw_instanced_mesh_ref(mesh, 0, instances.length as _);
+        }
+
+        Ok(())
+    }
+
+    /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
+    /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
+    /// This function drops Descriptor sets cached by self
+    pub fn clear(&mut self) {
+        self.camera_sets.lock().unwrap().clear();
+        self.instance_sets.lock().unwrap().clear();
+        self.pipeline.clear();
+    }
+}
@@ -0,0 +1,174 @@
+//! Automatic exposure from a histogram of luminance
+//!
+//! [`AutoExposureParams`] how the exposure is calculated and adapts
+//! [`AutoExposureRenderer`] for calculating the exposure of a [`GlobalToneMapRenderer`]
+//!
+//! A grid of samples of the scene is sorted into a histogram of log luminance on the gpu. The average of the histogram,
+//! ignoring the darkest and brightest samples, is blended with the luminance of previous frames so that the exposure
+//! adapts over time like an eye and then written to [`GlobalToneMapRenderer::exposure`] without being read back.
+
+use gfx::prelude::*;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use super::GlobalToneMapRenderer;
+
+/// Parameters of automatic exposure
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutoExposureParams {
+    /// the log2 luminance of the first bin of the histogram, darker samples are counted in it
+    pub min_log_luminance: f32,
+    /// the log2 luminance of the end of the last bin of the histogram, brighter samples are counted in it
+    pub max_log_luminance: f32,
+    /// the fraction of the darkest samples to ignore
+    pub low_percent: f32,
+    /// the fraction of samples below which are averaged, brighter samples are ignored
+    pub high_percent: f32,
+    /// how quickly the exposure adapts to brighter scenes
+    pub speed_up: f32,
+    /// how quickly the exposure adapts to darker scenes
+    pub speed_down: f32,
+    /// added to the exposure value in stops, positive values brighten the image
+    pub compensation: f32,
+}
+
+impl std::default::Default for AutoExposureParams {
+    fn default() -> Self {
+        Self {
+            min_log_luminance: -10.0,
+            max_log_luminance: 16.0,
+            low_percent: 0.5,
+            high_percent: 0.95,
+            speed_up: 3.0,
+            speed_down: 1.0,
+            compensation: 0.0,
+        }
+    }
+}
+
+unsafe impl bytemuck::Pod for AutoExposureParams {}
+unsafe impl bytemuck::Zeroable for AutoExposureParams {}
+
+/// Calculates the exposure of a [`GlobalToneMapRenderer`] from the scene
+///
+/// Usage overview:
+///  - call [`AutoExposureRenderer::pass`] with the hdr output each frame before tonemapping
+///  - tonemap with [`GlobalToneMapRenderer::pass`] as usual
+#[derive(Debug, Clone)]
+pub struct AutoExposureRenderer {
+    pub pipeline: gfx::ReflectedCompute,
+    /// map from (source view, exposure buffer) to bundle
+    pub bundles: Arc<Mutex<HashMap<(u64, u64), gfx::Bundle>>>,
+    pub params: gfx::Uniform<AutoExposureParams>,
+    pub sampler: gpu::Sampler,
+    /// the width and height of the grid of samples taken from the source
+    pub samples: (u32, u32),
+}
+
+impl AutoExposureRenderer {
+    pub fn new(
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        params: AutoExposureParams,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let sampler = device.create_sampler(&gpu::SamplerDesc {
+            name: name.map(|n| format!("{}_sampler", n)),
+            ..gpu::SamplerDesc::LINEAR
+        })?;
+
+        let n = name.map(|n| format!("{}_params", n));
+        let params = gfx::Uniform::new(encoder, device, params, n.as_deref())?;
+
+        let spv =
+            gpu::include_spirv!("../../../shaders/cone/postprocess/exposure_histogram.comp.spv");
+        let pipeline = match gfx::ReflectedCompute::from_spirv(device, &spv, cache, name) {
+            Ok(p) => p,
+            Err(e) => match e {
+                gfx::ReflectedError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+
+        Ok(Self {
+            pipeline,
+            bundles: Arc::default(),
+            params,
+            sampler,
+            samples: (64, 64),
+        })
+    }
+
+    /// Calculate the exposure of src and write it to the exposure of the tonemap renderer
+    ///
+    /// dt is the time in seconds since the last pass, if reset then the exposure jumps to the target
+    /// instead of adapting, which should be used for the first frame or after a cut
+    pub fn pass(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        src: &gpu::TextureView,
+        tonemap: &GlobalToneMapRenderer,
+        dt: f32,
+        reset: bool,
+    ) -> Result<(), gpu::Error> {
+        let mut bundles = self.bundles.lock().unwrap();
+        let key = (src.id(), tonemap.exposure.buffer.id());
+        if bundles.get(&key).is_none() {
+            let b = match self
+                .pipeline
+                .bundle()
+                .unwrap()
+                .set_resource("u_texture", src)
+                .unwrap()
+                .set_resource("u_sampler", &self.sampler)
+                .unwrap()
+                .set_resource("u_params", &self.params)
+                .unwrap()
+                .set_resource("u_exposure", &tonemap.exposure)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+            bundles.insert(key, b);
+        }
+        let bundle = bundles.get(&key).unwrap().clone();
+
+        let mut pass = encoder.compute_pass_reflected(device, &self.pipeline)?;
+        pass.set_bundle_owned(bundle);
+        pass.push_f32("dt", dt);
+        pass.push_u32("samples_x", self.samples.0);
+        pass.push_u32("samples_y", self.samples.1);
+        pass.push_i32("reset", reset as i32);
+        pass.dispatch(1, 1, 1);
+        pass.finish();
+
+        Ok(())
+    }
+
+    pub fn update_params(
+        &mut self,
+        encoder: &mut gfx::CommandEncoder<'_>,
+        params: AutoExposureParams,
+    ) {
+        self.params.data = params;
+        self.params.update_gpu_owned(encoder)
+    }
+
+    /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
+    /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
+    /// This function drops Descriptor sets cached by self
+    pub fn clear(&mut self) {
+        self.bundles.lock().unwrap().clear();
+        self.pipeline.clear();
+    }
+}
@@ -1,9 +1,17 @@
 pub mod ao;
 pub mod bloom;
 pub mod blur;
+pub mod exposure;
+pub mod ssr;
+pub mod taa;
 pub mod tonemap;
+pub mod volumetrics;
 
 pub use ao::*;
 pub use bloom::*;
 pub use blur::*;
+pub use exposure::*;
+pub use ssr::*;
+pub use taa::*;
 pub use tonemap::*;
+pub use volumetrics::*;
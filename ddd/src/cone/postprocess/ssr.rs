@@ -0,0 +1,265 @@
+use gfx::prelude::*;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::cone::{EnvironmentMap, EnvironmentRenderer, GeometryBuffer};
+use crate::utils::Camera;
+
+/// Parameters to tweak how screen space reflections are traced
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+pub struct SSRParams {
+    /// The number of steps to march each ray
+    pub max_steps: i32,
+    /// The number of binary search steps to refine a hit with
+    pub refine_steps: i32,
+    /// The distance in view space that each ray travels
+    pub max_distance: f32,
+    /// How far behind the depth buffer a ray can be and still count as a hit
+    pub thickness: f32,
+    /// Surfaces rougher than this only get reflections from the environment map
+    pub max_roughness: f32,
+    /// The fraction of the screen near the edges over which reflections fade out
+    pub edge_fade: f32,
+}
+
+unsafe impl bytemuck::Pod for SSRParams {}
+unsafe impl bytemuck::Zeroable for SSRParams {}
+
+impl std::default::Default for SSRParams {
+    fn default() -> Self {
+        Self {
+            max_steps: 64,
+            refine_steps: 8,
+            max_distance: 20.0,
+            thickness: 0.5,
+            max_roughness: 0.6,
+            edge_fade: 0.1,
+        }
+    }
+}
+
+/// Renders screen space reflections to the output of a [`GeometryBuffer`]
+///
+/// Rays are marched through the view space positions of the geometry buffer, where they hit the
+/// reflection of the environment map added by [`EnvironmentRenderer::environment_pass`] is replaced with the color
+/// of the output at the hit. Rougher surfaces and longer rays read from lower mips of the output to approximate a cone
+/// so the pass should be run after lighting and before tonemapping, with the same strength as the environment pass.
+///
+/// The geometry buffer must have "world_pos", "view_pos", "normal", "albedo", "roughness", "metallic", "ao" and "output" maps
+#[derive(Debug, Clone)]
+pub struct SSRRenderer {
+    pub pipeline: gfx::ReflectedGraphics,
+    /// map from (width, height) to a mipmapped copy of the output
+    pub scene_textures: Arc<Mutex<HashMap<(u32, u32), gfx::GTexture2D>>>,
+    /// map from (GeometryBuffer, Camera, EnvironmentMap, scene) to Bundle
+    pub bundles: Arc<Mutex<HashMap<(u64, u64, u64, u64), gfx::Bundle>>>,
+    pub uniform: gfx::Uniform<SSRParams>,
+    pub sampler: gpu::Sampler,
+    name: Option<String>,
+}
+
+impl SSRRenderer {
+    pub fn new(
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        params: SSRParams,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let n = name.map(|n| format!("{}_uniform", n));
+        let uniform = gfx::Uniform::new(encoder, device, params, n.as_ref().map(|n| &**n))?;
+
+        let sampler = device.create_sampler(&gpu::SamplerDesc {
+            name: name.as_ref().map(|n| format!("{}_sampler", n)),
+            ..gpu::SamplerDesc::LINEAR
+        })?;
+
+        let screen_spv = gpu::include_spirv!("../../../shaders/screen.vert.spv");
+        let ssr_spv = gpu::include_spirv!("../../../shaders/cone/postprocess/ssr.frag.spv");
+
+        let n = name.map(|n| format!("{}_renderer", n));
+        let pipeline = EnvironmentRenderer::create_light_pipeline(
+            device,
+            &screen_spv,
+            &ssr_spv,
+            cache,
+            n.as_ref().map(|n| &**n),
+        )?;
+
+        Ok(Self {
+            pipeline,
+            scene_textures: Arc::default(),
+            bundles: Arc::default(),
+            uniform,
+            sampler,
+            name: name.map(|n| n.to_string()),
+        })
+    }
+
+    /// Get or create the mipmapped copy of the output for a geometry buffer of width and height
+    pub fn scene_texture(
+        &self,
+        device: &gpu::Device,
+        width: u32,
+        height: u32,
+    ) -> Result<gfx::GTexture2D, gpu::Error> {
+        let mut textures = self.scene_textures.lock().unwrap();
+        if textures.get(&(width, height)).is_none() {
+            let t = gfx::GTexture2D::from_formats(
+                device,
+                width,
+                height,
+                gpu::Samples::S1,
+                gpu::TextureUsage::SAMPLED
+                    | gpu::TextureUsage::COPY_SRC
+                    | gpu::TextureUsage::COPY_DST,
+                gfx::full_mip_levels(width, height),
+                gfx::alt_formats(gpu::Format::Rgba16Float),
+                self.name
+                    .as_ref()
+                    .map(|n| format!("{}_scene_width_{}_height_{}", n, width, height))
+                    .as_ref()
+                    .map(|n| &**n),
+            )?
+            .unwrap();
+            textures.insert((width, height), t);
+        }
+
+        Ok(textures.get(&(width, height)).unwrap().clone())
+    }
+
+    /// Create and insert or get a bundle referencing the geometry buffer, camera, environment map and scene and return it
+    pub fn bundle(
+        &self,
+        device: &gpu::Device,
+        buffer: &GeometryBuffer,
+        camera: &Camera,
+        environment: &EnvironmentMap,
+        scene: &gfx::GTexture2D,
+    ) -> Result<gfx::Bundle, gpu::Error> {
+        let mut bundles = self.bundles.lock().unwrap();
+        let scene_id: u64 = unsafe { std::mem::transmute(scene.raw_image()) };
+        let key = (buffer.id, camera.buffer.id(), environment.id, scene_id);
+        if bundles.get(&key).is_none() {
+            let b = match self
+                .pipeline
+                .bundle()
+                .unwrap()
+                .set_resource("u_position", buffer.get("world_pos").unwrap())
+                .unwrap()
+                .set_resource("u_view_pos", buffer.get("view_pos").unwrap())
+                .unwrap()
+                .set_resource("u_normal", buffer.get("normal").unwrap())
+                .unwrap()
+                .set_resource("u_albedo", buffer.get("albedo").unwrap())
+                .unwrap()
+                .set_resource("u_roughness", buffer.get("roughness").unwrap())
+                .unwrap()
+                .set_resource("u_metallic", buffer.get("metallic").unwrap())
+                .unwrap()
+                .set_resource("u_ao", buffer.get("ao").unwrap())
+                .unwrap()
+                .set_resource("u_sampler", &self.sampler)
+                .unwrap()
+                .set_resource("u_camera", camera)
+                .unwrap()
+                .set_resource("u_specular", &environment.specular)
+                .unwrap()
+                .set_resource("u_brdf_lut", &environment.brdf_lut)
+                .unwrap()
+                .set_resource("u_scene", scene)
+                .unwrap()
+                .set_resource("u_data", &self.uniform)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+            bundles.insert(key, b.clone());
+        }
+
+        Ok(bundles.get(&key).unwrap().clone())
+    }
+
+    /// Add screen space reflections to the output map of the geometry buffer
+    ///
+    /// The output is first copied and mipmapped so that it can be read while being written to
+    /// strength multiplies the reflections and should match the strength of the environment pass
+    pub fn pass(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        buffer: &GeometryBuffer,
+        camera: &Camera,
+        environment: &EnvironmentMap,
+        strength: f32,
+    ) -> Result<(), gpu::Error> {
+        let scene = self.scene_texture(device, buffer.width, buffer.height)?;
+
+        encoder.blit_textures(
+            buffer.get("output").unwrap().whole_slice_owned(),
+            scene.mip_slice_owned(0),
+            gpu::FilterMode::Nearest,
+        );
+        scene.gen_mipmaps_owned(encoder);
+
+        let mut pass = encoder.graphics_pass_reflected::<()>(
+            device,
+            &[gfx::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Owned(buffer.get("output").unwrap().view.clone()),
+                    gpu::ClearValue::ColorFloat([0.0; 4]),
+                ),
+                load: gpu::LoadOp::Load,
+                store: gpu::StoreOp::Store,
+            }],
+            &[],
+            Some(gfx::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Owned(buffer.depth.view.clone()),
+                    gpu::ClearValue::Depth(1.0),
+                ),
+                load: gpu::LoadOp::Load,
+                store: gpu::StoreOp::Store,
+            }),
+            &self.pipeline,
+        )?;
+
+        let bundle = self.bundle(device, buffer, camera, environment, &scene)?;
+
+        pass.push_f32(
+            "max_reflection_lod",
+            environment.specular.texture.mip_levels() as f32,
+        );
+        pass.push_f32("max_scene_lod", (scene.texture.mip_levels() - 1) as f32);
+        pass.push_f32("strength", strength);
+        pass.push_f32("width", buffer.width as _);
+        pass.push_f32("height", buffer.height as _);
+        pass.set_bundle_owned(bundle);
+        pass.draw(0, 3, 0, 1);
+
+        Ok(())
+    }
+
+    pub fn update_params(&mut self, encoder: &mut gfx::CommandEncoder<'_>, params: SSRParams) {
+        self.uniform.data = params;
+        self.uniform.update_gpu_owned(encoder)
+    }
+
+    /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
+    /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
+    /// This function drops Descriptor sets cached by self
+    pub fn clear(&mut self) {
+        self.bundles.lock().unwrap().clear();
+        self.scene_textures.lock().unwrap().clear();
+        self.pipeline.clear();
+    }
+}
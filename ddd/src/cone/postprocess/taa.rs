@@ -0,0 +1,223 @@
+//! TAA: Temporal anti-aliasing
+//!
+//! Each frame the camera's projection is jittered by a different fraction of a pixel, see [`crate::utils::CameraData::jittered`].
+//! [`TAARenderer`] blends the new frame with a history of previous frames, reprojected with the velocity map of the
+//! [`GeometryBuffer`], and clamps the history to the colors around each pixel to avoid ghosting.
+
+use gfx::prelude::*;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::cone::GeometryBuffer;
+
+/// Implementation of TAA (Temporal anti-aliasing)
+///
+/// Usage overview:
+///  - jitter the camera each frame with [`crate::utils::CameraData::jittered`] and set the previous frame's camera with
+///    [`crate::utils::CameraData::with_previous`]
+///  - render the geometry buffer with a "velocity" map, see [`crate::cone::GeometryBufferDesc::ALL_MAPS`]
+///  - resolve the output into the target with [`TAARenderer::pass`]
+///
+/// The history is kept per target size so one renderer should be used per view
+pub struct TAARenderer {
+    pub pipeline: gfx::ReflectedGraphics,
+    /// map from (width, height) to (history, resolve)
+    pub targets: Arc<Mutex<HashMap<(u32, u32), (gfx::GTexture2D, gfx::GTexture2D)>>>,
+    /// map from (src, GeometryBuffer) to bundle
+    pub bundles: Arc<Mutex<HashMap<(u64, u64), gfx::Bundle>>>,
+    pub sampler: gpu::Sampler,
+    name: Option<String>,
+}
+
+impl TAARenderer {
+    pub fn new(
+        device: &gpu::Device,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let sampler = device.create_sampler(&gpu::SamplerDesc::new(
+            gpu::FilterMode::Linear,
+            gpu::WrapMode::ClampToEdge,
+            name.as_ref().map(|n| format!("{}_sampler", n)),
+        ))?;
+
+        let screen_spv = gpu::include_spirv!("../../../shaders/screen.vert.spv");
+        let taa_spv = gpu::include_spirv!("../../../shaders/cone/postprocess/taa.frag.spv");
+
+        let n = name.map(|n| format!("{}_resolve", n));
+        let pipeline = match gfx::ReflectedGraphics::from_spirv(
+            device,
+            &screen_spv,
+            None,
+            Some(&taa_spv),
+            gpu::Rasterizer::default(),
+            &[gpu::BlendState::REPLACE; 2],
+            None,
+            cache,
+            n.as_ref().map(|n| &**n),
+        ) {
+            Ok(g) => g,
+            Err(e) => match e {
+                gfx::error::ReflectedError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+
+        Ok(Self {
+            pipeline,
+            targets: Arc::default(),
+            bundles: Arc::default(),
+            sampler,
+            name: name.map(|n| n.to_string()),
+        })
+    }
+
+    /// Get or create the (history, resolve) textures for a target of width and height
+    ///
+    /// The history is cleared when it is created
+    pub fn targets(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        width: u32,
+        height: u32,
+    ) -> Result<(gfx::GTexture2D, gfx::GTexture2D), gpu::Error> {
+        let mut targets = self.targets.lock().unwrap();
+        if targets.get(&(width, height)).is_none() {
+            let create = |suffix: &str| -> Result<gfx::GTexture2D, gpu::Error> {
+                Ok(gfx::GTexture2D::from_formats(
+                    device,
+                    width,
+                    height,
+                    gpu::Samples::S1,
+                    gpu::TextureUsage::COLOR_OUTPUT
+                        | gpu::TextureUsage::SAMPLED
+                        | gpu::TextureUsage::COPY_SRC
+                        | gpu::TextureUsage::COPY_DST,
+                    1,
+                    gfx::alt_formats(gpu::Format::Rgba16Float),
+                    self.name
+                        .as_ref()
+                        .map(|n| format!("{}_{}_width_{}_height_{}", n, suffix, width, height))
+                        .as_ref()
+                        .map(|n| &**n),
+                )?
+                .unwrap())
+            };
+            let history = create("history")?;
+            let resolve = create("resolve")?;
+            encoder.clear_texture(
+                history.whole_slice_owned(),
+                gpu::ClearValue::ColorFloat([0.0; 4]),
+            );
+            targets.insert((width, height), (history, resolve));
+        }
+        Ok(targets.get(&(width, height)).unwrap().clone())
+    }
+
+    /// Create and insert or get a bundle referencing src, the geometry buffer and history and return it
+    pub fn bundle(
+        &self,
+        device: &gpu::Device,
+        src: &gpu::TextureView,
+        buffer: &GeometryBuffer,
+        history: &gfx::GTexture2D,
+    ) -> Result<gfx::Bundle, gpu::Error> {
+        let mut bundles = self.bundles.lock().unwrap();
+        let key = (src.id(), buffer.id);
+        if bundles.get(&key).is_none() {
+            let b = match self
+                .pipeline
+                .bundle()
+                .unwrap()
+                .set_resource("u_current", src)
+                .unwrap()
+                .set_resource("u_history", history)
+                .unwrap()
+                .set_resource(
+                    "u_velocity",
+                    buffer
+                        .get("velocity")
+                        .expect("ERROR: TAARenderer requires a GeometryBuffer with a velocity map"),
+                )
+                .unwrap()
+                .set_resource("u_sampler", &self.sampler)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+            bundles.insert(key, b);
+        }
+        Ok(bundles.get(&key).unwrap().clone())
+    }
+
+    /// Blend src with the history of previous frames into the target and update the history
+    ///
+    /// src should be the same size as the geometry buffer, typically its output map
+    /// blend is the weight of the history, 0.9 is a reasonable default, higher values are smoother but blurrier
+    ///
+    /// If the commands are recorded once and submitted repeatedly call [`TAARenderer::targets`] beforehand
+    /// otherwise clearing the new history will be recorded too
+    pub fn pass<'a>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &GeometryBuffer,
+        src: &gpu::TextureView,
+        target: gfx::Attachment<'a>,
+        blend: f32,
+    ) -> Result<(), gpu::Error> {
+        let width = src.extent().width;
+        let height = src.extent().height;
+        let (history, resolve) = self.targets(encoder, device, width, height)?;
+
+        let mut pass = encoder.graphics_pass_reflected::<()>(
+            device,
+            &[
+                target,
+                gfx::Attachment {
+                    raw: gpu::Attachment::View(
+                        Cow::Owned(resolve.view.clone()),
+                        gpu::ClearValue::ColorFloat([0.0; 4]),
+                    ),
+                    load: gpu::LoadOp::DontCare,
+                    store: gpu::StoreOp::Store,
+                },
+            ],
+            &[],
+            None,
+            &self.pipeline,
+        )?;
+
+        let bundle = self.bundle(device, src, buffer, &history)?;
+        pass.set_bundle_owned(bundle);
+        pass.push_f32("blend", blend);
+        pass.push_f32("width", width as _);
+        pass.push_f32("height", height as _);
+        pass.draw(0, 3, 0, 1);
+        pass.finish();
+
+        encoder.copy_texture_to_texture(resolve.whole_slice_owned(), history.whole_slice_owned());
+
+        Ok(())
+    }
+
+    /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
+    /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
+    /// This function drops Descriptor sets cached by self
+    ///
+    /// This also drops the history so the next frame won't be blended with previous frames
+    pub fn clear(&mut self) {
+        self.bundles.lock().unwrap().clear();
+        self.targets.lock().unwrap().clear();
+        self.pipeline.clear();
+    }
+}
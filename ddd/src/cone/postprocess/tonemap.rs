@@ -50,11 +50,35 @@ impl std::default::Default for GlobalToneMapParams {
 unsafe impl bytemuck::Pod for GlobalToneMapParams {}
 unsafe impl bytemuck::Zeroable for GlobalToneMapParams {}
 
+/// The exposure that colors are multiplied by before being tonemapped
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExposureData {
+    /// the average luminance the exposure was calculated from, 0.0 if set manually
+    pub luminance: f32,
+    /// multiplies linear colors
+    pub exposure: f32,
+}
+
+impl std::default::Default for ExposureData {
+    fn default() -> Self {
+        Self {
+            luminance: 0.0,
+            exposure: 1.0,
+        }
+    }
+}
+
+unsafe impl bytemuck::Pod for ExposureData {}
+unsafe impl bytemuck::Zeroable for ExposureData {}
+
 #[derive(Debug, Clone)]
 pub struct GlobalToneMapRenderer {
     pub pipeline: gfx::ReflectedGraphics,
     pub bundles: Arc<Mutex<HashMap<u64, gfx::Bundle>>>,
     pub params: gfx::Uniform<GlobalToneMapParams>,
+    /// set manually with [`GlobalToneMapRenderer::set_exposure`] or written by [`crate::cone::AutoExposureRenderer`]
+    pub exposure: gfx::Storage<ExposureData>,
     pub sampler: gpu::Sampler,
 }
 
@@ -74,6 +98,14 @@ impl GlobalToneMapRenderer {
         let n = name.as_ref().map(|n| format!("{}_params", n));
         let params = gfx::Uniform::new(encoder, device, params, n.as_ref().map(|n| &**n))?;
 
+        let n = name.as_ref().map(|n| format!("{}_exposure", n));
+        let exposure = gfx::Storage::from_vec(
+            encoder,
+            device,
+            vec![ExposureData::default()],
+            n.as_ref().map(|n| &**n),
+        )?;
+
         let n = name.as_ref().map(|n| format!("{}_pipeline", n));
         let pipeline = Self::create_pipeline(device, cache, n.as_ref().map(|n| &**n))?;
 
@@ -81,10 +113,31 @@ impl GlobalToneMapRenderer {
             pipeline,
             bundles: Arc::default(),
             params,
+            exposure,
             sampler,
         })
     }
 
+    /// Set the exposure manually, for example from [`crate::utils::PhysicalCamera::exposure`]
+    ///
+    /// Will be overwritten by any following auto exposure pass
+    pub fn set_exposure(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        exposure: f32,
+    ) -> Result<(), gpu::Error> {
+        self.exposure.update_range(
+            encoder,
+            device,
+            0,
+            &[ExposureData {
+                luminance: 0.0,
+                exposure,
+            }],
+        )
+    }
+
     pub fn create_pipeline(
         device: &gpu::Device,
         cache: Option<gpu::PipelineCache>,
@@ -135,6 +188,8 @@ impl GlobalToneMapRenderer {
                 .unwrap()
                 .set_resource("u", &self.params)
                 .unwrap()
+                .set_resource("u_exposure", &self.exposure)
+                .unwrap()
                 .build(device)
             {
                 Ok(b) => b,
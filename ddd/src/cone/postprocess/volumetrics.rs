@@ -0,0 +1,421 @@
+//! Froxel based volumetric fog
+//!
+//! [`VolumetricsData`] parameters of the fog
+//! [`VolumetricsRenderer`] for adding fog lit by a [`crate::cone::DirLight`] to the output of a [`GeometryBuffer`]
+//!
+//! The view frustum is split into froxels, a 3D texture aligned with the screen with slices distributed exponentially
+//! away from the camera. Each froxel's density and the light scattered towards the camera is calculated with the shadow
+//! of a [`CascadedDepthMap`] so that shadowed fog stays dark and light shafts appear. The froxels are then integrated
+//! front to back and each pixel of the output is attenuated and has the scattered light added by reading the froxel at
+//! the depth of the pixel.
+
+use gfx::prelude::*;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::cone::{CascadedDepthMap, DirLight, GeometryBuffer};
+use crate::utils::Camera;
+
+/// Parameters of volumetric fog
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolumetricsData {
+    /// color of light scattered by the fog
+    pub albedo: glam::Vec3,
+    /// density of the fog at and below base_height
+    pub density: f32,
+    /// light scattered by the fog from all directions
+    pub ambient: glam::Vec3,
+    /// henyey greenstein anisotropy in (-1, 1), positive values scatter more light when looking towards the light
+    pub anisotropy: f32,
+    /// how quickly the density falls off above base_height, 0.0 for uniform fog
+    pub height_falloff: f32,
+    /// the height in world space above which the density starts to fall off
+    pub base_height: f32,
+    /// the view depth of the first froxel slice
+    pub near: f32,
+    /// the view depth of the last froxel slice, there is no fog past this
+    pub max_distance: f32,
+    /// multiplies the color of the light
+    pub light_strength: f32,
+    pub _padding: [f32; 3],
+}
+
+unsafe impl bytemuck::Pod for VolumetricsData {}
+unsafe impl bytemuck::Zeroable for VolumetricsData {}
+
+impl Default for VolumetricsData {
+    fn default() -> Self {
+        Self {
+            albedo: glam::Vec3::ONE,
+            density: 0.02,
+            ambient: glam::Vec3::splat(0.01),
+            anisotropy: 0.6,
+            height_falloff: 0.1,
+            base_height: 0.0,
+            near: 0.1,
+            max_distance: 100.0,
+            light_strength: 1.0,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+/// Renders volumetric fog into the output of a [`GeometryBuffer`]
+///
+/// Usage overview:
+///  - draw and light the geometry buffer as usual, updating the depth map of the light for the camera
+///  - call [`VolumetricsRenderer::pass`] with the light and its depth map before tonemapping
+///
+/// The froxel textures are kept per geometry buffer size, each froxel covers tile_size by tile_size pixels
+pub struct VolumetricsRenderer {
+    pub params: gfx::Uniform<VolumetricsData>,
+    pub scatter: gfx::ReflectedCompute,
+    pub integrate: gfx::ReflectedCompute,
+    pub composite: gfx::ReflectedGraphics,
+    /// map from (width, height) to (scattering, integrated) froxels
+    pub froxels: Arc<Mutex<HashMap<(u32, u32), (gfx::GTexture3D, gfx::GTexture3D)>>>,
+    /// map from (width, height, Camera, DirLight, CascadedDepthMap) to bundle
+    pub scatter_bundles: Arc<Mutex<HashMap<(u32, u32, u64, u64, u64), gfx::Bundle>>>,
+    /// map from (width, height) to bundle
+    pub integrate_bundles: Arc<Mutex<HashMap<(u32, u32), gfx::Bundle>>>,
+    /// map from (GeometryBuffer, Camera) to bundle
+    pub composite_bundles: Arc<Mutex<HashMap<(u64, u64), gfx::Bundle>>>,
+    pub sampler: gpu::Sampler,
+    /// the width and height in pixels of each froxel
+    pub tile_size: u32,
+    /// the number of froxels along the view direction
+    pub slices: u32,
+    name: Option<String>,
+}
+
+impl VolumetricsRenderer {
+    pub fn new(
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        params: VolumetricsData,
+        tile_size: u32,
+        slices: u32,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let params = gfx::Uniform::new(
+            encoder,
+            device,
+            params,
+            name.map(|n| format!("{}_params", n)).as_deref(),
+        )?;
+
+        let sampler = device.create_sampler(&gpu::SamplerDesc {
+            name: name.map(|n| format!("{}_sampler", n)),
+            ..gpu::SamplerDesc::LINEAR
+        })?;
+
+        let compute = |spv: &[u32], suffix: &str| match gfx::ReflectedCompute::from_spirv(
+            device,
+            spv,
+            cache.clone(),
+            name.map(|n| format!("{}_{}", n, suffix)).as_deref(),
+        ) {
+            Ok(p) => Ok(p),
+            Err(e) => match e {
+                gfx::ReflectedError::Gpu(e) => Err(e),
+                e => unreachable!("{}", e),
+            },
+        };
+        let scatter_spv = gpu::include_spirv!("../../../shaders/cone/volumetrics/scatter.comp.spv");
+        let integrate_spv =
+            gpu::include_spirv!("../../../shaders/cone/volumetrics/integrate.comp.spv");
+        let scatter = compute(&scatter_spv, "scatter")?;
+        let integrate = compute(&integrate_spv, "integrate")?;
+
+        let screen_spv = gpu::include_spirv!("../../../shaders/screen.vert.spv");
+        let composite_spv =
+            gpu::include_spirv!("../../../shaders/cone/volumetrics/composite.frag.spv");
+
+        // output * transmittance + scattering keeping the destination alpha
+        let blend = gpu::BlendState {
+            blend_enable: true,
+            src_blend: gpu::BlendFactor::One,
+            dst_blend: gpu::BlendFactor::SrcAlpha,
+            src_alpha_blend: gpu::BlendFactor::Zero,
+            dst_alpha_blend: gpu::BlendFactor::One,
+            ..gpu::BlendState::ALPHA
+        };
+
+        let n = name.map(|n| format!("{}_composite", n));
+        let composite = match gfx::ReflectedGraphics::from_spirv(
+            device,
+            &screen_spv,
+            None,
+            Some(&composite_spv),
+            gpu::Rasterizer::default(),
+            &[blend],
+            None,
+            cache,
+            n.as_deref(),
+        ) {
+            Ok(g) => g,
+            Err(e) => match e {
+                gfx::error::ReflectedError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+
+        Ok(Self {
+            params,
+            scatter,
+            integrate,
+            composite,
+            froxels: Arc::default(),
+            scatter_bundles: Arc::default(),
+            integrate_bundles: Arc::default(),
+            composite_bundles: Arc::default(),
+            sampler,
+            tile_size: tile_size.max(1),
+            slices: slices.max(1),
+            name: name.map(|n| n.to_string()),
+        })
+    }
+
+    /// The (width, height, depth) of the froxels for a geometry buffer of width and height
+    pub fn froxel_dimensions(&self, width: u32, height: u32) -> (u32, u32, u32) {
+        (
+            (width + self.tile_size - 1) / self.tile_size,
+            (height + self.tile_size - 1) / self.tile_size,
+            self.slices,
+        )
+    }
+
+    /// Get or create the (scattering, integrated) froxel textures for a geometry buffer of width and height
+    pub fn froxels(
+        &self,
+        device: &gpu::Device,
+        width: u32,
+        height: u32,
+    ) -> Result<(gfx::GTexture3D, gfx::GTexture3D), gpu::Error> {
+        let mut froxels = self.froxels.lock().unwrap();
+        if froxels.get(&(width, height)).is_none() {
+            let (w, h, d) = self.froxel_dimensions(width, height);
+            let create = |suffix: &str| {
+                gfx::GTexture3D::new(
+                    device,
+                    w,
+                    h,
+                    d,
+                    gpu::TextureUsage::STORAGE | gpu::TextureUsage::SAMPLED,
+                    gpu::Format::Rgba16Float,
+                    self.name
+                        .as_ref()
+                        .map(|n| format!("{}_{}_width_{}_height_{}", n, suffix, width, height))
+                        .as_deref(),
+                )
+            };
+            let scattering = create("scattering")?;
+            let integrated = create("integrated")?;
+            froxels.insert((width, height), (scattering, integrated));
+        }
+        Ok(froxels.get(&(width, height)).unwrap().clone())
+    }
+
+    fn scatter_bundle(
+        &self,
+        device: &gpu::Device,
+        width: u32,
+        height: u32,
+        camera: &Camera,
+        light: &DirLight,
+        shadow: &CascadedDepthMap,
+    ) -> Result<gfx::Bundle, gpu::Error> {
+        let (scattering, _) = self.froxels(device, width, height)?;
+        let mut bundles = self.scatter_bundles.lock().unwrap();
+        let key = (
+            width,
+            height,
+            camera.buffer.id(),
+            light.buffer.id(),
+            shadow.id,
+        );
+        if bundles.get(&key).is_none() {
+            let b = match self
+                .scatter
+                .bundle()
+                .unwrap()
+                .set_resource("u_camera", camera)
+                .unwrap()
+                .set_resource("u_volumetrics", &self.params)
+                .unwrap()
+                .set_resource("u_scatter", &scattering)
+                .unwrap()
+                .set_resource("u_light_data", light)
+                .unwrap()
+                .set_resource("u_shadow_data", &shadow.uniform)
+                .unwrap()
+                .set_resource("u_shadow_map", &shadow.texture)
+                .unwrap()
+                .set_resource("u_shadow_sampler", &shadow.sampler)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+            bundles.insert(key, b);
+        }
+        Ok(bundles.get(&key).unwrap().clone())
+    }
+
+    fn integrate_bundle(
+        &self,
+        device: &gpu::Device,
+        width: u32,
+        height: u32,
+    ) -> Result<gfx::Bundle, gpu::Error> {
+        let (scattering, integrated) = self.froxels(device, width, height)?;
+        let mut bundles = self.integrate_bundles.lock().unwrap();
+        let key = (width, height);
+        if bundles.get(&key).is_none() {
+            let b = match self
+                .integrate
+                .bundle()
+                .unwrap()
+                .set_resource("u_volumetrics", &self.params)
+                .unwrap()
+                .set_resource("u_scatter", &scattering)
+                .unwrap()
+                .set_resource("u_integrated", &integrated)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+            bundles.insert(key, b);
+        }
+        Ok(bundles.get(&key).unwrap().clone())
+    }
+
+    fn composite_bundle(
+        &self,
+        device: &gpu::Device,
+        buffer: &GeometryBuffer,
+        camera: &Camera,
+    ) -> Result<gfx::Bundle, gpu::Error> {
+        let (_, integrated) = self.froxels(device, buffer.width, buffer.height)?;
+        let mut bundles = self.composite_bundles.lock().unwrap();
+        let key = (buffer.id, camera.buffer.id());
+        if bundles.get(&key).is_none() {
+            let b = match self
+                .composite
+                .bundle()
+                .unwrap()
+                .set_resource("u_camera", camera)
+                .unwrap()
+                .set_resource("u_volumetrics", &self.params)
+                .unwrap()
+                .set_resource("u_integrated", &integrated)
+                .unwrap()
+                .set_resource("u_depth", &buffer.depth)
+                .unwrap()
+                .set_resource("u_sampler", &self.sampler)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+            bundles.insert(key, b);
+        }
+        Ok(bundles.get(&key).unwrap().clone())
+    }
+
+    /// Add fog lit by the light to the output map of the geometry buffer
+    ///
+    /// The depth map should have been updated for the camera with [`CascadedDepthMap::update`]
+    /// Should be called after lighting and before tonemapping, the geometry buffer must not be multisampled
+    /// or must be resolved first
+    pub fn pass<'a>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &'a GeometryBuffer,
+        camera: &Camera,
+        light: &DirLight,
+        shadow: &CascadedDepthMap,
+    ) -> Result<(), gpu::Error> {
+        let (w, h, d) = self.froxel_dimensions(buffer.width, buffer.height);
+
+        let bundle =
+            self.scatter_bundle(device, buffer.width, buffer.height, camera, light, shadow)?;
+        let mut pass = encoder.compute_pass_reflected(device, &self.scatter)?;
+        pass.set_bundle_owned(bundle);
+        pass.push_u32("width", w);
+        pass.push_u32("height", h);
+        pass.push_u32("depth", d);
+        pass.dispatch((w + 7) / 8, (h + 7) / 8, d);
+        pass.finish();
+
+        let bundle = self.integrate_bundle(device, buffer.width, buffer.height)?;
+        let mut pass = encoder.compute_pass_reflected(device, &self.integrate)?;
+        pass.set_bundle_owned(bundle);
+        pass.push_u32("width", w);
+        pass.push_u32("height", h);
+        pass.push_u32("depth", d);
+        pass.dispatch((w + 7) / 8, (h + 7) / 8, 1);
+        pass.finish();
+
+        let bundle = self.composite_bundle(device, buffer, camera)?;
+        let mut pass = encoder.graphics_pass_reflected::<()>(
+            device,
+            &[gfx::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Borrowed(&buffer.get("output").unwrap().view),
+                    gpu::ClearValue::ColorFloat([0.0; 4]),
+                ),
+                load: gpu::LoadOp::Load,
+                store: gpu::StoreOp::Store,
+            }],
+            &[],
+            None,
+            &self.composite,
+        )?;
+        pass.set_bundle_owned(bundle);
+        pass.draw(0, 3, 0, 1);
+
+        Ok(())
+    }
+
+    pub fn update_params(
+        &mut self,
+        encoder: &mut gfx::CommandEncoder<'_>,
+        params: VolumetricsData,
+    ) {
+        self.params.data = params;
+        self.params.update_gpu_owned(encoder)
+    }
+
+    /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
+    /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
+    /// This function drops Descriptor sets cached by self
+    pub fn clear(&mut self) {
+        self.scatter_bundles.lock().unwrap().clear();
+        self.integrate_bundles.lock().unwrap().clear();
+        self.composite_bundles.lock().unwrap().clear();
+        self.froxels.lock().unwrap().clear();
+        self.scatter.clear();
+        self.integrate.clear();
+        self.composite.clear();
+    }
+}
@@ -0,0 +1,430 @@
+//! Skinned meshes and animation playback
+//!
+//! [`SkinnedVertex`] a vertex influenced by up to four joints
+//! [`SkinnedMesh`] skinned vertices and the regular [`Vertex`] mesh they are skinned into
+//! [`AnimationPlayer`] plays [`mesh::AnimationClip`]s on a [`mesh::Skeleton`] and uploads the joint matrices
+//! [`SkinningRenderer`] skins meshes on the gpu
+//!
+//! Skinning is done by a compute pass before drawing which transforms the vertices of each [`SkinnedMesh`] by the
+//! joint matrices of a [`mesh::BoneBuffer`] into [`SkinnedMesh::mesh`]. The skinned mesh can then be drawn by any
+//! material, shadow or culling pass like any other mesh of [`Vertex`]s.
+
+use gfx::prelude::*;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::cone::Vertex;
+
+/// A vertex influenced by up to four joints
+///
+/// joints holds the index of each joint as a float as there are no integer vertex formats
+#[derive(Debug, Clone, Copy, gfx::Vertex)]
+#[repr(C)]
+pub struct SkinnedVertex {
+    pub pos: glam::Vec3,
+    pub normal: glam::Vec3,
+    pub tangent_u: glam::Vec3,
+    pub tangent_v: glam::Vec3,
+    pub uv: glam::Vec2,
+    pub joints: glam::Vec4,
+    pub weights: glam::Vec4,
+}
+
+unsafe impl bytemuck::Pod for SkinnedVertex {}
+unsafe impl bytemuck::Zeroable for SkinnedVertex {}
+
+impl mesh::Vertex for SkinnedVertex {
+    fn new(
+        pos: glam::Vec3,
+        uv: glam::Vec2,
+        normal: glam::Vec3,
+        tangent_u: Option<glam::Vec3>,
+        tangent_v: Option<glam::Vec3>,
+    ) -> Self {
+        Self {
+            pos,
+            normal,
+            tangent_u: tangent_u.unwrap_or(glam::Vec3::ZERO),
+            tangent_v: tangent_v.unwrap_or(glam::Vec3::ZERO),
+            uv,
+            joints: glam::Vec4::ZERO,
+            weights: glam::vec4(1.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    fn set_tangents(&mut self, u: glam::Vec3, v: glam::Vec3) {
+        self.tangent_u = u;
+        self.tangent_v = v;
+    }
+
+    fn set_normal(&mut self, normal: glam::Vec3) {
+        self.normal = normal;
+    }
+
+    fn pos(&self) -> glam::Vec3 {
+        self.pos
+    }
+
+    fn uv(&self) -> Option<glam::Vec2> {
+        Some(self.uv)
+    }
+
+    fn normal(&self) -> Option<glam::Vec3> {
+        Some(self.normal)
+    }
+
+    fn tangent_u(&self) -> Option<glam::Vec3> {
+        Some(self.tangent_u)
+    }
+
+    fn tangent_v(&self) -> Option<glam::Vec3> {
+        Some(self.tangent_v)
+    }
+
+    fn set_joints(&mut self, joints: [u32; 4], weights: glam::Vec4) {
+        self.joints = glam::UVec4::from(joints).as_vec4();
+        self.weights = weights;
+    }
+
+    fn joints(&self) -> Option<[u32; 4]> {
+        Some(self.joints.as_uvec4().into())
+    }
+
+    fn weights(&self) -> Option<glam::Vec4> {
+        Some(self.weights)
+    }
+}
+
+impl From<SkinnedVertex> for Vertex {
+    fn from(v: SkinnedVertex) -> Self {
+        Self {
+            pos: v.pos,
+            normal: v.normal,
+            tangent_u: v.tangent_u,
+            tangent_v: v.tangent_v,
+            uv: v.uv,
+        }
+    }
+}
+
+/// Skinned vertices and the mesh that they are skinned into
+///
+/// Until the mesh is skinned by [`SkinningRenderer::pass`] it holds the vertices at rest
+#[derive(Debug, Clone)]
+pub struct SkinnedMesh {
+    /// the vertices before skinning
+    pub source: gfx::Storage<SkinnedVertex>,
+    /// the skinned vertices, the vertex buffer has STORAGE usage
+    pub mesh: gfx::Mesh<Vertex>,
+}
+
+impl SkinnedMesh {
+    /// Create a new skinned mesh, indexed if indices is some
+    ///
+    /// The mesh won't be valid until the encoder is submitted
+    pub fn new(
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        vertices: &[SkinnedVertex],
+        indices: Option<&[u32]>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        if vertices.is_empty() {
+            panic!("ERROR: Attempt to create SkinnedMesh with no vertices");
+        }
+        let source = gfx::Storage::from_vec(
+            encoder,
+            device,
+            vertices.to_vec(),
+            name.map(|n| format!("{}_source", n)).as_deref(),
+        )?;
+        let rest = vertices
+            .iter()
+            .map(|v| Vertex::from(*v))
+            .collect::<Vec<_>>();
+        let mesh = if let Some(indices) = indices {
+            gfx::Mesh::from_usage_indexed(
+                encoder,
+                device,
+                &rest,
+                gpu::BufferUsage::STORAGE,
+                indices,
+                gpu::BufferUsage::empty(),
+                name,
+            )?
+        } else {
+            gfx::Mesh::from_usage_basic(encoder, device, &rest, gpu::BufferUsage::STORAGE, name)?
+        };
+        Ok(Self { source, mesh })
+    }
+
+    /// The number of vertices in the mesh
+    pub fn vertex_count(&self) -> u32 {
+        self.mesh.vertex_count
+    }
+}
+
+impl std::ops::Deref for SkinnedMesh {
+    type Target = gfx::Mesh<Vertex>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.mesh
+    }
+}
+
+/// Plays animation clips on a skeleton
+///
+/// Usage overview:
+///  - each frame call [`AnimationPlayer::advance`] with the time since the last frame
+///  - upload the pose with [`AnimationPlayer::update_gpu`]
+///  - skin the meshes using [`AnimationPlayer::bones`] with [`SkinningRenderer::pass`]
+#[derive(Debug, Clone)]
+pub struct AnimationPlayer {
+    pub skeleton: mesh::Skeleton,
+    pub clips: Vec<mesh::AnimationClip>,
+    /// the joint matrices of the current pose
+    pub bones: mesh::BoneBuffer,
+    /// multiplies the time passed to advance
+    pub speed: f32,
+    pose: mesh::Pose,
+    current: Option<Playing>,
+    /// (clip being faded out, time faded over, time since the fade started)
+    fading: Option<(Playing, f32, f32)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Playing {
+    clip: usize,
+    time: f32,
+    looping: bool,
+}
+
+impl AnimationPlayer {
+    /// Create a new player with the skeleton at rest
+    pub fn new(
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        skeleton: mesh::Skeleton,
+        clips: Vec<mesh::AnimationClip>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let bones = mesh::BoneBuffer::new(encoder, device, &skeleton, name)?;
+        let pose = skeleton.rest_pose();
+        Ok(Self {
+            skeleton,
+            clips,
+            bones,
+            speed: 1.0,
+            pose,
+            current: None,
+            fading: None,
+        })
+    }
+
+    fn check_clip(&self, clip: usize) {
+        if clip >= self.clips.len() {
+            panic!(
+                "ERROR: Attempt to play clip {} of AnimationPlayer with {} clips",
+                clip,
+                self.clips.len()
+            );
+        }
+    }
+
+    /// Find the index of the clip by name
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.clips
+            .iter()
+            .position(|c| c.name.as_deref() == Some(name))
+    }
+
+    /// Start playing the clip from the beginning, stopping any other clip immediately
+    pub fn play(&mut self, clip: usize, looping: bool) {
+        self.check_clip(clip);
+        self.current = Some(Playing {
+            clip,
+            time: 0.0,
+            looping,
+        });
+        self.fading = None;
+    }
+
+    /// Start playing the clip from the beginning, blending from the current pose over duration seconds
+    pub fn crossfade(&mut self, clip: usize, looping: bool, duration: f32) {
+        self.check_clip(clip);
+        self.fading = match (self.current, duration > 0.0) {
+            (Some(playing), true) => Some((playing, duration, 0.0)),
+            _ => None,
+        };
+        self.current = Some(Playing {
+            clip,
+            time: 0.0,
+            looping,
+        });
+    }
+
+    /// Stop playing and return the skeleton to rest
+    pub fn stop(&mut self) {
+        self.current = None;
+        self.fading = None;
+        self.pose = self.skeleton.rest_pose();
+    }
+
+    /// The index of the clip being played if any
+    pub fn playing(&self) -> Option<usize> {
+        self.current.map(|p| p.clip)
+    }
+
+    /// The time through the current clip in seconds
+    pub fn time(&self) -> f32 {
+        self.current.map(|p| p.time).unwrap_or(0.0)
+    }
+
+    /// Returns true if the current clip doesn't loop and has played to the end
+    pub fn finished(&self) -> bool {
+        match self.current {
+            Some(p) => !p.looping && p.time >= self.clips[p.clip].duration(),
+            None => true,
+        }
+    }
+
+    /// The pose as of the last advance
+    pub fn pose(&self) -> &mesh::Pose {
+        &self.pose
+    }
+
+    /// Move the clips forward by dt seconds and recalculate the pose
+    pub fn advance(&mut self, dt: f32) {
+        let dt = dt * self.speed;
+        let mut current = match self.current {
+            Some(c) => c,
+            None => return,
+        };
+        current.time += dt;
+        self.current = Some(current);
+
+        let mut pose = self.skeleton.rest_pose();
+        self.clips[current.clip].sample_into(current.time, current.looping, &mut pose);
+
+        if let Some((mut previous, duration, mut elapsed)) = self.fading {
+            previous.time += dt;
+            elapsed += dt;
+            if elapsed >= duration {
+                self.fading = None;
+            } else {
+                let mut from = self.skeleton.rest_pose();
+                self.clips[previous.clip].sample_into(previous.time, previous.looping, &mut from);
+                pose = from.blend(&pose, elapsed / duration);
+                self.fading = Some((previous, duration, elapsed));
+            }
+        }
+
+        self.pose = pose;
+    }
+
+    /// Upload the joint matrices of the pose
+    ///
+    /// The update will only be complete when the command encoder is submitted
+    pub fn update_gpu(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+    ) -> Result<(), gpu::Error> {
+        self.bones
+            .update_pose(encoder, device, &self.skeleton, &self.pose)
+    }
+}
+
+/// Skins [`SkinnedMesh`]es on the gpu
+pub struct SkinningRenderer {
+    pub pipeline: gfx::ReflectedCompute,
+    /// map from (SkinnedMesh, BoneBuffer) to bundle
+    pub bundles: Arc<Mutex<HashMap<(u64, u64), gfx::Bundle>>>,
+}
+
+impl SkinningRenderer {
+    pub fn new(
+        device: &gpu::Device,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let spv = gpu::include_spirv!("../../shaders/cone/skinning/skin.comp.spv");
+        let pipeline = match gfx::ReflectedCompute::from_spirv(device, &spv, cache, name) {
+            Ok(p) => p,
+            Err(e) => match e {
+                gfx::ReflectedError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+
+        Ok(Self {
+            pipeline,
+            bundles: Arc::default(),
+        })
+    }
+
+    fn bundle(
+        &self,
+        device: &gpu::Device,
+        mesh: &SkinnedMesh,
+        bones: &mesh::BoneBuffer,
+    ) -> Result<gfx::Bundle, gpu::Error> {
+        let mut bundles = self.bundles.lock().unwrap();
+        let key = (mesh.source.buffer.id(), bones.storage.buffer.id());
+        if bundles.get(&key).is_none() {
+            let b = match self
+                .pipeline
+                .bundle()
+                .unwrap()
+                .set_resource("u_source", &mesh.source)
+                .unwrap()
+                .set_resource("u_bones", &bones.storage)
+                .unwrap()
+                .set_resource("u_vertices", &mesh.mesh.vertex_buffer)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+            bundles.insert(key, b);
+        }
+        Ok(bundles.get(&key).unwrap().clone())
+    }
+
+    /// Transform the vertices of each mesh by the joint matrices of its bone buffer
+    ///
+    /// Should be called each frame after the bones are updated and before the meshes are drawn
+    pub fn pass<'a>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        meshes: impl IntoIterator<Item = (&'a SkinnedMesh, &'a mesh::BoneBuffer)>,
+    ) -> Result<(), gpu::Error> {
+        for (mesh, bones) in meshes {
+            let bundle = self.bundle(device, mesh, bones)?;
+            let count = mesh.vertex_count();
+
+            let mut pass = encoder.compute_pass_reflected(device, &self.pipeline)?;
+            pass.set_bundle_owned(bundle);
+            pass.push_u32("count", count);
+            pass.dispatch((count + 63) / 64, 1, 1);
+            pass.finish();
+        }
+
+        Ok(())
+    }
+
+    /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
+    /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
+    /// This function drops Descriptor sets cached by self
+    pub fn clear(&mut self) {
+        self.bundles.lock().unwrap().clear();
+        self.pipeline.clear();
+    }
+}
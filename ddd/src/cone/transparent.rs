@@ -0,0 +1,229 @@
+//! Weighted blended order independent transparency
+//!
+//! [`TransparentData`] lighting of transparent materials
+//! [`TransparentRenderer`] for compositing transparent materials over the output of a [`GeometryBuffer`]
+//!
+//! Transparent materials, see [`crate::cone::MaterialBuilder::new_transparent`], can't be lit in the geometry buffer
+//! as there can be many layers of them over each pixel. Instead each fragment is lit in the material and added to an
+//! accumulation target weighted by its alpha and depth, while the product of one minus the alpha of each fragment is
+//! written to a revealage target. The accumulated color is normalized and blended over the lit output by
+//! [`TransparentRenderer::composite_pass`] so the order materials are drawn in doesn't matter.
+
+use gfx::prelude::*;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::cone::GeometryBuffer;
+
+pub type TransparentLighting = gfx::Uniform<TransparentData>;
+
+/// Describes how transparent materials are lit
+///
+/// Only the xyz components of each field are used
+#[repr(C)]
+#[derive(Debug, Clone, Copy, spv::AsStructType)]
+pub struct TransparentData {
+    /// light added to all fragments
+    pub ambient: glam::Vec4,
+    /// direction from fragments towards the light
+    pub light_direction: glam::Vec4,
+    /// color and intensity of the light
+    pub light_color: glam::Vec4,
+}
+
+impl Default for TransparentData {
+    fn default() -> Self {
+        Self {
+            ambient: glam::vec4(0.1, 0.1, 0.1, 0.0),
+            light_direction: glam::vec4(0.0, 1.0, 0.0, 0.0),
+            light_color: glam::vec4(1.0, 1.0, 1.0, 0.0),
+        }
+    }
+}
+
+unsafe impl bytemuck::Pod for TransparentData {}
+unsafe impl bytemuck::Zeroable for TransparentData {}
+
+/// Implementation of weighted blended order independent transparency
+///
+/// Usage overview:
+///  - draw opaque materials and lights into the geometry buffer as usual
+///  - draw transparent materials with [`crate::cone::Material::transparent_pass`], clearing with the first
+///  - blend the transparent materials over the output map with [`TransparentRenderer::composite_pass`]
+///
+/// The accumulation and revealage targets are kept per geometry buffer size
+pub struct TransparentRenderer {
+    pub pipeline: gfx::ReflectedGraphics,
+    /// map from (width, height) to (accumulation, revealage)
+    pub targets: Arc<Mutex<HashMap<(u32, u32), (gfx::GTexture2D, gfx::GTexture2D)>>>,
+    /// map from (width, height) to bundle
+    pub bundles: Arc<Mutex<HashMap<(u32, u32), gfx::Bundle>>>,
+    pub sampler: gpu::Sampler,
+    name: Option<String>,
+}
+
+impl TransparentRenderer {
+    pub fn new(
+        device: &gpu::Device,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let sampler = device.create_sampler(&gpu::SamplerDesc::new(
+            gpu::FilterMode::Nearest,
+            gpu::WrapMode::ClampToEdge,
+            name.as_ref().map(|n| format!("{}_sampler", n)),
+        ))?;
+
+        let screen_spv = gpu::include_spirv!("../../shaders/screen.vert.spv");
+        let composite_spv =
+            gpu::include_spirv!("../../shaders/cone/transparent/composite.frag.spv");
+
+        // blend the color but keep the destination alpha
+        let blend = gpu::BlendState {
+            src_alpha_blend: gpu::BlendFactor::Zero,
+            dst_alpha_blend: gpu::BlendFactor::One,
+            ..gpu::BlendState::ALPHA
+        };
+
+        let n = name.map(|n| format!("{}_composite", n));
+        let pipeline = match gfx::ReflectedGraphics::from_spirv(
+            device,
+            &screen_spv,
+            None,
+            Some(&composite_spv),
+            gpu::Rasterizer::default(),
+            &[blend],
+            None,
+            cache,
+            n.as_ref().map(|n| &**n),
+        ) {
+            Ok(g) => g,
+            Err(e) => match e {
+                gfx::error::ReflectedError::Gpu(e) => Err(e)?,
+                e => unreachable!("{}", e),
+            },
+        };
+
+        Ok(Self {
+            pipeline,
+            targets: Arc::default(),
+            bundles: Arc::default(),
+            sampler,
+            name: name.map(|n| n.to_string()),
+        })
+    }
+
+    /// Get or create the (accumulation, revealage) textures for a geometry buffer of width and height
+    ///
+    /// The targets are cleared by the first [`crate::cone::Material::transparent_pass`] each frame
+    pub fn targets(
+        &self,
+        device: &gpu::Device,
+        width: u32,
+        height: u32,
+    ) -> Result<(gfx::GTexture2D, gfx::GTexture2D), gpu::Error> {
+        let mut targets = self.targets.lock().unwrap();
+        if targets.get(&(width, height)).is_none() {
+            let create =
+                |suffix: &str, format: gpu::Format| -> Result<gfx::GTexture2D, gpu::Error> {
+                    Ok(gfx::GTexture2D::from_formats(
+                        device,
+                        width,
+                        height,
+                        gpu::Samples::S1,
+                        gpu::TextureUsage::COLOR_OUTPUT | gpu::TextureUsage::SAMPLED,
+                        1,
+                        gfx::alt_formats(format),
+                        self.name
+                            .as_ref()
+                            .map(|n| format!("{}_{}_width_{}_height_{}", n, suffix, width, height))
+                            .as_ref()
+                            .map(|n| &**n),
+                    )?
+                    .unwrap())
+                };
+            let accum = create("accum", gpu::Format::Rgba16Float)?;
+            let revealage = create("revealage", gpu::Format::R16Float)?;
+            targets.insert((width, height), (accum, revealage));
+        }
+        Ok(targets.get(&(width, height)).unwrap().clone())
+    }
+
+    /// Create and insert or get a bundle referencing the targets for width and height and return it
+    pub fn bundle(
+        &self,
+        device: &gpu::Device,
+        width: u32,
+        height: u32,
+    ) -> Result<gfx::Bundle, gpu::Error> {
+        let (accum, revealage) = self.targets(device, width, height)?;
+        let mut bundles = self.bundles.lock().unwrap();
+        let key = (width, height);
+        if bundles.get(&key).is_none() {
+            let b = match self
+                .pipeline
+                .bundle()
+                .unwrap()
+                .set_resource("u_accum", &accum)
+                .unwrap()
+                .set_resource("u_revealage", &revealage)
+                .unwrap()
+                .set_resource("u_sampler", &self.sampler)
+                .unwrap()
+                .build(device)
+            {
+                Ok(b) => b,
+                Err(e) => match e {
+                    gfx::BundleBuildError::Gpu(e) => Err(e)?,
+                    e => unreachable!("{}", e),
+                },
+            };
+            bundles.insert(key, b);
+        }
+        Ok(bundles.get(&key).unwrap().clone())
+    }
+
+    /// Blend the transparent materials drawn this frame over the output map of the geometry buffer
+    ///
+    /// Should be called after all lighting and before post processing
+    pub fn composite_pass<'a>(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &'a GeometryBuffer,
+    ) -> Result<(), gpu::Error> {
+        let bundle = self.bundle(device, buffer.width, buffer.height)?;
+
+        let mut pass = encoder.graphics_pass_reflected::<()>(
+            device,
+            &[gfx::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Borrowed(&buffer.get("output").unwrap().view),
+                    gpu::ClearValue::ColorFloat([0.0; 4]),
+                ),
+                load: gpu::LoadOp::Load,
+                store: gpu::StoreOp::Store,
+            }],
+            &[],
+            None,
+            &self.pipeline,
+        )?;
+
+        pass.set_bundle_owned(bundle);
+        pass.draw(0, 3, 0, 1);
+
+        Ok(())
+    }
+
+    /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
+    /// Specifically references in command buffers or descriptor sets keep other objects alive until the command buffer is reset or the descriptor set is destroyed
+    /// This function drops Descriptor sets cached by self
+    pub fn clear(&mut self) {
+        self.bundles.lock().unwrap().clear();
+        self.targets.lock().unwrap().clear();
+        self.pipeline.clear();
+    }
+}
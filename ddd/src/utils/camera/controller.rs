@@ -320,11 +320,6 @@ impl CameraController for DebugController {
     fn cam_data(&self) -> CameraData {
         let view =
             glam::Mat4::look_at_rh(self.position, self.position + self.forward, self.world_up);
-        super::CameraData {
-            view,
-            z_far: self.z_far,
-            projection: self.projection,
-            position: glam::vec4(self.position.x, self.position.y, self.position.z, 1.0),
-        }
+        super::CameraData::new(self.projection, view, self.position, self.z_far)
     }
 }
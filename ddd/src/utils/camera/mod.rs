@@ -5,6 +5,9 @@
 //!  - view: Mat4
 //!  - position: Mat4
 //!
+//! For temporal anti-aliasing the projection can be jittered by a fraction of a pixel each frame
+//! and the view projection of the previous frame stored for calculating velocity, see [`CameraData::jittered`]
+//!
 //! The camera's matrices transform between different corrdinate systems.
 //! The fourth component of the matrix is used to translate corrdinates.
 //!
@@ -38,7 +41,134 @@ pub struct CameraData {
     pub position: glam::Vec4,
     /// far plane of the projection matrix
     pub z_far: f32,
+    /// unused, keeps jitter aligned
+    pub _padding: f32,
+    /// offset in normalized device coordinates that has been applied to the projection matrix
+    pub jitter: glam::Vec2,
+    /// view projection matrix of the previous frame without jitter, used for calculating velocity
+    pub prev_view_projection: glam::Mat4,
 }
 
 unsafe impl bytemuck::Pod for CameraData {}
 unsafe impl bytemuck::Zeroable for CameraData {}
+
+impl CameraData {
+    /// Create new camera data without jitter that hasn't moved since the previous frame
+    pub fn new(projection: glam::Mat4, view: glam::Mat4, position: glam::Vec3, z_far: f32) -> Self {
+        Self {
+            projection,
+            view,
+            position: glam::vec4(position.x, position.y, position.z, 1.0),
+            z_far,
+            _padding: 0.0,
+            jitter: glam::Vec2::ZERO,
+            prev_view_projection: projection * view,
+        }
+    }
+
+    /// The view projection matrix with any jitter removed
+    pub fn view_projection(&self) -> glam::Mat4 {
+        glam::Mat4::from_translation(glam::vec3(-self.jitter.x, -self.jitter.y, 0.0))
+            * self.projection
+            * self.view
+    }
+
+    /// Offset the projection by a fraction of a pixel
+    ///
+    /// jitter is in pixels and should be in the range -0.5..0.5, see [`CameraData::halton_jitter`]
+    /// width and height are the dimensions of the target being rendered to
+    pub fn jittered(mut self, jitter: glam::Vec2, width: u32, height: u32) -> Self {
+        let jitter = jitter * 2.0 / glam::vec2(width as f32, height as f32);
+        let remove = glam::Mat4::from_translation(glam::vec3(-self.jitter.x, -self.jitter.y, 0.0));
+        let add = glam::Mat4::from_translation(glam::vec3(jitter.x, jitter.y, 0.0));
+        self.projection = add * remove * self.projection;
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set the view projection of the previous frame so that velocity can be calculated
+    pub fn with_previous(mut self, previous: &CameraData) -> Self {
+        self.prev_view_projection = previous.view_projection();
+        self
+    }
+
+    /// A sub pixel jitter in the range -0.5..0.5 from the halton (2, 3) sequence
+    ///
+    /// The sequence repeats every samples frames
+    pub fn halton_jitter(frame: u32, samples: u32) -> glam::Vec2 {
+        let index = frame % samples.max(1) + 1;
+        glam::vec2(halton(index, 2), halton(index, 3)) - glam::Vec2::splat(0.5)
+    }
+}
+
+/// Physical camera settings that determine how bright the scene appears
+///
+/// Light values in the scene should be in physical units (luminance in cd/m^2) for these to give sensible results
+/// The exposure is applied when tonemapping, see [`crate::cone::GlobalToneMapRenderer::set_exposure`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalCamera {
+    /// the f-number of the lens, larger values let in less light
+    pub aperture: f32,
+    /// the time in seconds the sensor is exposed for
+    pub shutter_speed: f32,
+    /// the sensitivity of the sensor
+    pub iso: f32,
+    /// added to the exposure value in stops, positive values brighten the image
+    pub compensation: f32,
+}
+
+impl Default for PhysicalCamera {
+    /// The "sunny 16" settings for a bright day
+    fn default() -> Self {
+        Self {
+            aperture: 16.0,
+            shutter_speed: 1.0 / 125.0,
+            iso: 100.0,
+            compensation: 0.0,
+        }
+    }
+}
+
+impl PhysicalCamera {
+    pub fn new(aperture: f32, shutter_speed: f32, iso: f32) -> Self {
+        Self {
+            aperture,
+            shutter_speed,
+            iso,
+            compensation: 0.0,
+        }
+    }
+
+    /// The exposure value of the settings at iso 100
+    pub fn ev100(&self) -> f32 {
+        (self.aperture * self.aperture / self.shutter_speed * 100.0 / self.iso).log2()
+    }
+
+    /// The exposure value at iso 100 that would expose the average luminance as middle grey
+    pub fn ev100_from_luminance(luminance: f32) -> f32 {
+        (luminance * 100.0 / 12.5).log2()
+    }
+
+    /// The value to multiply linear colors by so that the maximum luminance that doesn't saturate the sensor maps to 1.0
+    ///
+    /// source <https://seblagarde.files.wordpress.com/2015/07/course_notes_moving_frostbite_to_pbr_v32.pdf> page 85
+    pub fn exposure(&self) -> f32 {
+        Self::exposure_from_ev100(self.ev100() - self.compensation)
+    }
+
+    /// The value to multiply linear colors by for an exposure value at iso 100
+    pub fn exposure_from_ev100(ev100: f32) -> f32 {
+        1.0 / (1.2 * 2.0f32.powf(ev100))
+    }
+}
+
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut f = 1.0;
+    let mut r = 0.0;
+    while index > 0 {
+        f /= base as f32;
+        r += f * (index % base) as f32;
+        index /= base;
+    }
+    r
+}
@@ -1,5 +1,3 @@
-use gfx::prelude::*;
-
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -63,9 +61,6 @@ impl CopyRenderer {
         src: &gpu::TextureView,
         target: gfx::Attachment<'a>,
     ) -> Result<(), gpu::Error> {
-        let mut pass =
-            encoder.graphics_pass_reflected::<()>(device, &[target], &[], None, &self.pipeline)?;
-
         let mut bundles = self.bundles.lock().unwrap();
         if bundles.get(&src.id()).is_none() {
             let b = match self
@@ -86,11 +81,8 @@ impl CopyRenderer {
             };
             bundles.insert(src.id(), b);
         }
-        let bundle = bundles.get(&src.id()).unwrap().clone();
-        pass.set_bundle_owned(bundle);
-        pass.draw(0, 3, 0, 1);
-
-        Ok(())
+        let bundle = bundles.get(&src.id()).unwrap();
+        encoder.fullscreen_pass(device, &self.pipeline, Some(bundle), &[target])
     }
 
     /// To avoid memory use after free issues vulkan objects are kept alive as long as they can be used
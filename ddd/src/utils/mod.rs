@@ -5,17 +5,20 @@
 //! See sub modules for more specifics
 //!  - [`camera`]
 //!  - [`instance`]
+//!  - [`scene`]
 //!  - [`vertices`]
 //!  - [`smaa`]
 
 pub mod camera;
 pub mod copy;
 pub mod instance;
+pub mod scene;
 pub mod smaa;
 pub mod vertices;
 
 pub use camera::*;
 pub use copy::*;
 pub use instance::*;
+pub use scene::*;
 pub use smaa::*;
 pub use vertices::*;
@@ -0,0 +1,283 @@
+//! Transform hierarchy
+//!
+//! A [`SceneGraph`] holds nodes with a local [`mesh::Transform`] relative to their parent. World transforms are only
+//! recalculated for nodes that have changed, or whose ancestors have changed, when the graph is updated.
+//! [`SceneGraph::sync`] writes the world transforms of a list of nodes into an [`Instances`] buffer so that
+//! each node can be drawn as one instance.
+
+use std::collections::HashMap;
+
+use super::{InstanceData, Instances};
+
+/// Identifies a node in a [`SceneGraph`]
+///
+/// Ids of removed nodes are never valid again even if their slot is reused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    pub(crate) index: usize,
+    pub(crate) generation: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    local: mesh::Transform,
+    world: glam::Mat4,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    /// the local transform or parent has changed since the last update
+    dirty: bool,
+    /// the update the world transform last changed in
+    changed: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Slot {
+    generation: u32,
+    node: Option<Node>,
+}
+
+/// A hierarchy of transforms
+#[derive(Debug, Clone, Default)]
+pub struct SceneGraph {
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+    roots: Vec<NodeId>,
+    dirty: bool,
+    /// the number of updates that have changed world transforms
+    version: u64,
+    /// map from instance buffer to the version it was last synced at and the nodes written to it
+    synced: HashMap<u64, (u64, Vec<NodeId>)>,
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn node(&self, id: NodeId) -> &Node {
+        self.slots
+            .get(id.index)
+            .filter(|s| s.generation == id.generation)
+            .and_then(|s| s.node.as_ref())
+            .unwrap_or_else(|| panic!("ERROR: {:?} doesn't exist in the SceneGraph", id))
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        self.slots
+            .get_mut(id.index)
+            .filter(|s| s.generation == id.generation)
+            .and_then(|s| s.node.as_mut())
+            .unwrap_or_else(|| panic!("ERROR: {:?} doesn't exist in the SceneGraph", id))
+    }
+
+    /// Returns true if the node hasn't been removed
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.slots
+            .get(id.index)
+            .map(|s| s.generation == id.generation && s.node.is_some())
+            .unwrap_or(false)
+    }
+
+    /// The number of nodes in the graph
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add a node with local transform relative to parent, or the world if parent is None
+    pub fn add(&mut self, local: mesh::Transform, parent: Option<NodeId>) -> NodeId {
+        let node = Node {
+            local,
+            world: glam::Mat4::IDENTITY,
+            parent,
+            children: Vec::new(),
+            dirty: true,
+            changed: 0,
+        };
+        let id = if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index];
+            slot.node = Some(node);
+            NodeId {
+                index,
+                generation: slot.generation,
+            }
+        } else {
+            self.slots.push(Slot {
+                generation: 0,
+                node: Some(node),
+            });
+            NodeId {
+                index: self.slots.len() - 1,
+                generation: 0,
+            }
+        };
+        match parent {
+            Some(p) => self.node_mut(p).children.push(id),
+            None => self.roots.push(id),
+        }
+        self.dirty = true;
+        id
+    }
+
+    /// Remove the node and all of its descendants
+    pub fn remove(&mut self, id: NodeId) {
+        let parent = self.node(id).parent;
+        match parent {
+            Some(p) => self.node_mut(p).children.retain(|c| *c != id),
+            None => self.roots.retain(|r| *r != id),
+        }
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let slot = &mut self.slots[id.index];
+            let node = slot.node.take().unwrap();
+            slot.generation += 1;
+            self.free.push(id.index);
+            stack.extend(node.children);
+        }
+    }
+
+    /// The parent of the node if any
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).parent
+    }
+
+    /// The children of the node
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.node(id).children
+    }
+
+    /// The nodes without parents
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    /// Move the node to be a child of parent, or a root if parent is None, keeping its local transform
+    ///
+    /// Will panic if parent is the node or one of its descendants
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) {
+        let mut ancestor = parent;
+        while let Some(a) = ancestor {
+            if a == id {
+                panic!(
+                    "ERROR: Cannot set the parent of {:?} to {:?} as it would create a cycle",
+                    id, parent
+                );
+            }
+            ancestor = self.node(a).parent;
+        }
+
+        let old = self.node(id).parent;
+        match old {
+            Some(p) => self.node_mut(p).children.retain(|c| *c != id),
+            None => self.roots.retain(|r| *r != id),
+        }
+        match parent {
+            Some(p) => self.node_mut(p).children.push(id),
+            None => self.roots.push(id),
+        }
+        let node = self.node_mut(id);
+        node.parent = parent;
+        node.dirty = true;
+        self.dirty = true;
+    }
+
+    /// The transform of the node relative to its parent
+    pub fn local(&self, id: NodeId) -> mesh::Transform {
+        self.node(id).local
+    }
+
+    /// Set the transform of the node relative to its parent
+    ///
+    /// The world transform of the node and its descendants will be recalculated on the next update
+    pub fn set_local(&mut self, id: NodeId, local: mesh::Transform) {
+        let node = self.node_mut(id);
+        node.local = local;
+        node.dirty = true;
+        self.dirty = true;
+    }
+
+    /// The transform of the node in world space as of the last update
+    pub fn world(&self, id: NodeId) -> glam::Mat4 {
+        self.node(id).world
+    }
+
+    /// Returns true if the world transform of the node changed in the last update
+    pub fn changed(&self, id: NodeId) -> bool {
+        self.node(id).changed == self.version
+    }
+
+    /// Recalculate the world transforms of nodes that have changed and their descendants
+    ///
+    /// Does nothing if no nodes have changed since the last update
+    pub fn update(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        self.version += 1;
+        let version = self.version;
+
+        let mut stack = self
+            .roots
+            .iter()
+            .map(|r| (*r, glam::Mat4::IDENTITY, false))
+            .collect::<Vec<_>>();
+        while let Some((id, parent_world, parent_changed)) = stack.pop() {
+            let node = self.node_mut(id);
+            let changed = node.dirty || parent_changed;
+            if changed {
+                node.world = parent_world * node.local.matrix();
+                node.dirty = false;
+                node.changed = version;
+            }
+            let world = node.world;
+            stack.extend(node.children.iter().map(|c| (*c, world, changed)));
+        }
+        self.dirty = false;
+    }
+
+    /// The world transforms of the nodes as instance data
+    ///
+    /// Updates the graph first if any nodes have changed
+    pub fn instance_data(&mut self, nodes: &[NodeId]) -> Vec<InstanceData> {
+        self.update();
+        nodes.iter().map(|n| self.world(*n).into()).collect()
+    }
+
+    /// Write the world transforms of the nodes into instances, the nth node becoming the nth instance
+    ///
+    /// Updates the graph first if any nodes have changed, then only writes to instances if the nodes are different
+    /// to those last synced to instances or one of them changed since so the same nodes can be synced every frame.
+    /// The data on the gpu won't be correct until the encoder is submitted
+    /// Returns true if the buffer was reallocated, in which case any bundles or descriptors
+    /// referencing the old buffer must be recreated
+    pub fn sync(
+        &mut self,
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        nodes: &[NodeId],
+        instances: &mut Instances,
+    ) -> Result<bool, gpu::Error> {
+        self.update();
+        let changed = match self.synced.get(&instances.buffer.id()) {
+            Some((last, synced)) => {
+                synced.as_slice() != nodes || nodes.iter().any(|n| self.node(*n).changed > *last)
+            }
+            None => true,
+        };
+        if !changed && instances.length == nodes.len() {
+            return Ok(false);
+        }
+        let reallocated = instances.resize(encoder, device, nodes.len())?;
+        let data = nodes
+            .iter()
+            .map(|n| InstanceData::from(self.world(*n)))
+            .collect::<Vec<_>>();
+        instances.update_range(encoder, device, 0, &data)?;
+        self.synced
+            .insert(instances.buffer.id(), (self.version, nodes.to_vec()));
+        Ok(reallocated)
+    }
+}
@@ -1180,14 +1180,10 @@ fn main() {
         if input_helper.update(&event) {
             match sim.redraw(&input_helper) {
                 Ok(_) => (),
-                Err(e) => {
-                    if let Some(e) = e.downcast_ref::<gpu::Error>() {
-                        if e.can_continue() {
-                            return;
-                        }
-                    }
-                    panic!("{}", e);
-                }
+                Err(e) => match e.downcast_ref::<gpu::Error>().map(gpu::Error::recovery) {
+                    Some(gpu::Recovery::RecreateSwapchain) => return,
+                    _ => panic!("{}", e),
+                },
             }
 
             if input_helper.quit() {
@@ -27,29 +27,6 @@ struct Uniform {
 unsafe impl bytemuck::Pod for Uniform {}
 unsafe impl bytemuck::Zeroable for Uniform {}
 
-#[repr(C)]
-#[derive(Copy, Clone, Debug)]
-struct Vertex {
-    pos: [f32; 2],
-    tex_coord: [f32; 2],
-}
-
-unsafe impl bytemuck::Pod for Vertex {}
-unsafe impl bytemuck::Zeroable for Vertex {}
-
-impl gfx::Vertex for Vertex {
-    fn get(name: &str) -> Option<(u32, gpu::VertexFormat)> {
-        match name {
-            "in_pos" => Some((0, gpu::VertexFormat::Vec2)),
-            "in_uv" => Some((
-                std::mem::size_of::<[f32; 2]>() as u32,
-                gpu::VertexFormat::Vec2,
-            )),
-            _ => None,
-        }
-    }
-}
-
 struct Fractal {
     _instance: gpu::Instance,
     _surface: gpu::Surface,
@@ -67,7 +44,6 @@ struct Fractal {
     display_graphics: gfx::ReflectedGraphics,
     display_bundle: gfx::Bundle,
 
-    mesh: gfx::Mesh<Vertex>,
     uniform: gfx::Uniform<Uniform>,
 
     prev_time: std::time::Instant,
@@ -104,31 +80,6 @@ impl Fractal {
 
         let sampler = device.create_sampler(&gpu::SamplerDesc::LINEAR)?;
 
-        let mesh = gfx::Mesh::indexed(
-            &mut encoder,
-            &device,
-            &[
-                Vertex {
-                    pos: [-1.0, -1.0],
-                    tex_coord: [0.0, 0.0],
-                },
-                Vertex {
-                    pos: [1.0, -1.0],
-                    tex_coord: [1.0, 0.0],
-                },
-                Vertex {
-                    pos: [1.0, 1.0],
-                    tex_coord: [1.0, 1.0],
-                },
-                Vertex {
-                    pos: [-1.0, 1.0],
-                    tex_coord: [0.0, 1.0],
-                },
-            ],
-            &[0, 1, 2, 2, 3, 0],
-            None,
-        )?;
-
         let uniform = gfx::Uniform::new(
             &mut encoder,
             &device,
@@ -162,17 +113,12 @@ impl Fractal {
         //     samples: gpu::Samples::S1,
         // })?;
 
-        let fractal_graphics = gfx::ReflectedGraphics::from_spirv(
+        let fractal_graphics = gfx::ReflectedGraphics::fullscreen_from_spirv(
             &device,
-            &gpu::include_spirv!("vert.spv"),
-            None,
-            Some(&gpu::include_spirv!("fractal_frag.spv")),
-            // fractal_pass,
-            gpu::Rasterizer::default(),
+            &gpu::include_spirv!("fractal_frag.spv"),
             &[gpu::BlendState::REPLACE],
             None,
             None,
-            None,
         )?;
 
         let fractal_bundle = fractal_graphics
@@ -197,17 +143,12 @@ impl Fractal {
         //     samples: gpu::Samples::S1,
         // })?;
 
-        let display_graphics = gfx::ReflectedGraphics::from_spirv(
+        let display_graphics = gfx::ReflectedGraphics::fullscreen_from_spirv(
             &device,
-            &gpu::include_spirv!("vert.spv"),
-            None,
-            Some(&gpu::include_spirv!("display_frag.spv")),
-            // display_pass,
-            gpu::Rasterizer::default(),
+            &gpu::include_spirv!("display_frag.spv"),
             &[gpu::BlendState::REPLACE],
             None,
             None,
-            None,
         )?;
 
         let display_bundle = display_graphics
@@ -236,7 +177,6 @@ impl Fractal {
             display_graphics,
             display_bundle,
 
-            mesh,
             uniform,
 
             prev_time,
@@ -322,8 +262,10 @@ impl Fractal {
 
         self.uniform.update_gpu_ref(&mut encoder);
 
-        let mut pass = encoder.graphics_pass_reflected(
+        encoder.fullscreen_pass(
             &self.device,
+            &self.fractal_graphics,
+            Some(&self.fractal_bundle),
             &[gfx::Attachment {
                 raw: gpu::Attachment::View(
                     Cow::Borrowed(&self.target.view),
@@ -332,31 +274,19 @@ impl Fractal {
                 load: gpu::LoadOp::DontCare,
                 store: gpu::StoreOp::Store,
             }],
-            &[],
-            None,
-            &self.fractal_graphics,
         )?;
 
-        pass.set_bundle_ref(&self.fractal_bundle);
-        pass.draw_mesh_ref(&self.mesh);
-        pass.finish();
-
-        let mut pass = encoder.graphics_pass_reflected(
+        encoder.fullscreen_pass(
             &self.device,
+            &self.display_graphics,
+            Some(&self.display_bundle),
             &[gfx::Attachment {
                 raw: gpu::Attachment::Swapchain(&view, gpu::ClearValue::ColorFloat([0.0; 4])),
                 load: gpu::LoadOp::DontCare,
                 store: gpu::StoreOp::Store,
             }],
-            &[],
-            None,
-            &self.display_graphics,
         )?;
 
-        pass.set_bundle_ref(&self.display_bundle);
-        pass.draw_mesh_ref(&self.mesh);
-        pass.finish();
-
         encoder.submit(&mut self.command, true)?;
 
         self.swapchain.present(view)?;
@@ -384,14 +314,10 @@ fn main() {
         if input_helper.update(&event) {
             match fractal.redraw(&input_helper) {
                 Ok(_) => (),
-                Err(e) => {
-                    if let Some(e) = e.downcast_ref::<gpu::Error>() {
-                        if e.can_continue() {
-                            return;
-                        }
-                    }
-                    panic!("{}", e);
-                }
+                Err(e) => match e.downcast_ref::<gpu::Error>().map(gpu::Error::recovery) {
+                    Some(gpu::Recovery::RecreateSwapchain) => return,
+                    _ => panic!("{}", e),
+                },
             }
 
             if input_helper.quit() {
@@ -318,30 +318,12 @@ impl Slime {
 
             std::mem::swap(&mut trail_map, &mut self.trail_map);
 
-            self.update_bundle = self
-                .update
-                .bundle()
-                .unwrap()
-                .set_resource("u_trail_map", &self.trail_map)?
-                .set_resource("u_data", &self.uniform)?
-                .set_resource("u_agents", &self.agents)?
-                .build(&self.device)?;
-
-            self.fade_bundle = self
-                .fade
-                .bundle()
-                .unwrap()
-                .set_resource("u_trail_map", &self.trail_map)?
-                .set_resource("u_data", &self.uniform)?
-                .build(&self.device)?;
-
-            self.graphics_bundle = self
-                .graphics
-                .bundle()
-                .unwrap()
-                .set_resource("u_texture", &self.trail_map)?
-                .set_resource("u_sampler", &self.sampler)?
-                .build(&self.device)?;
+            self.update_bundle
+                .update_resource(&self.device, "u_trail_map", &self.trail_map)?;
+            self.fade_bundle
+                .update_resource(&self.device, "u_trail_map", &self.trail_map)?;
+            self.graphics_bundle
+                .update_resource(&self.device, "u_texture", &self.trail_map)?;
 
             let old_extent: gpu::Extent3D = trail_map.dimension().into();
             let new_extent: gpu::Extent3D = self.trail_map.dimension().into();
@@ -481,14 +463,10 @@ fn main() {
         if input_helper.update(&event) {
             match slime.redraw(&input_helper) {
                 Ok(_) => (),
-                Err(e) => {
-                    if let Some(e) = e.downcast_ref::<gpu::Error>() {
-                        if e.can_continue() {
-                            return;
-                        }
-                    }
-                    panic!("{}", e);
-                }
+                Err(e) => match e.downcast_ref::<gpu::Error>().map(gpu::Error::recovery) {
+                    Some(gpu::Recovery::RecreateSwapchain) => return,
+                    _ => panic!("{}", e),
+                },
             }
 
             if input_helper.quit() {
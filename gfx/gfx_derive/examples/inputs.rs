@@ -0,0 +1,29 @@
+use gfx::spv;
+use gfx::VertexBuilderExt;
+
+#[allow(dead_code)]
+#[derive(Copy, Clone, Default, gfx::Vertex, gfx::VertexInputs)]
+struct MyVertex {
+    position: [f32; 3],
+    uv: glam::Vec2,
+}
+
+unsafe impl bytemuck::Pod for MyVertex { }
+unsafe impl bytemuck::Zeroable for MyVertex { }
+
+fn main() {
+    let b = spv::Builder::new();
+
+    let inputs = b.inputs_from_vertex::<MyVertex>();
+    let vk_pos = b.vk_position();
+
+    b.entry(spv::Stage::Vertex, "main", || {
+        let pos = inputs.position.load();
+        let _uv = inputs.uv.load();
+        vk_pos.store(b.vec4(pos.x(), pos.y(), pos.z(), 1.0));
+    });
+
+    for input in b.get_inputs() {
+        println!("{:?}", input);
+    }
+}
@@ -0,0 +1,35 @@
+use glam::{Mat4, Vec3, Vec4};
+
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Copy, Clone, Default, gfx::Std140)]
+struct Light {
+    position: Vec3,
+    strength: f32,
+    color: Vec4,
+}
+
+unsafe impl bytemuck::Pod for Light { }
+unsafe impl bytemuck::Zeroable for Light { }
+
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Copy, Clone, Default, gfx::Std140)]
+struct Camera {
+    projection: Mat4,
+    view: Mat4,
+    position: Vec3,
+    // a vec3 followed by a scalar packs into one vec4 in std140
+    exposure: f32,
+    lights: [Light; 4],
+}
+
+unsafe impl bytemuck::Pod for Camera { }
+unsafe impl bytemuck::Zeroable for Camera { }
+
+fn main() {
+    use gfx::Std140;
+
+    println!("Light  align: {} size: {}", Light::STD140_ALIGN, Light::STD140_SIZE);
+    println!("Camera align: {} size: {}", Camera::STD140_ALIGN, Camera::STD140_SIZE);
+}
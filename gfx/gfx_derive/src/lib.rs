@@ -168,3 +168,135 @@ pub fn derive_vertex(input: TokenStream) -> TokenStream {
 
     TokenStream::from(expanded)
 }
+
+#[proc_macro_derive(VertexInputs)]
+pub fn derive_vertex_inputs(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let name = input.ident;
+    let vis = input.vis;
+    let inputs_name = syn::Ident::new(&format!("{}Inputs", name), name.span());
+
+    // fields of the inputs struct and the types and functions that declare them, in lockstep
+    let mut fields = Vec::new();
+    let mut names = Vec::new();
+    let mut tys = Vec::new();
+    let mut fns = Vec::new();
+
+    let Data::Struct(struct_data) = input.data else {
+        unimplemented!("{}", ERROR);
+    };
+
+    for field in &struct_data.fields {
+        let Some(ident) = &field.ident else {
+            unimplemented!("{}", ERROR)
+        };
+        let Some(format) = syn_type_to_gpu_vertex_format(&field.ty) else {
+            unimplemented!("{}", ERROR)
+        };
+
+        fields.push(ident.clone());
+        names.push(ident.to_string());
+        match format {
+            Format::Float => {
+                tys.push(quote!(gfx::spv::IOFloat));
+                fns.push(quote!(in_float_auto));
+            },
+            Format::Vec2 => {
+                tys.push(quote!(gfx::spv::IOVec2));
+                fns.push(quote!(in_vec2_auto));
+            },
+            Format::Vec3 => {
+                tys.push(quote!(gfx::spv::IOVec3));
+                fns.push(quote!(in_vec3_auto));
+            },
+            Format::Vec4 => {
+                tys.push(quote!(gfx::spv::IOVec4));
+                fns.push(quote!(in_vec4_auto));
+            },
+        }
+    }
+
+    let expanded = quote!(
+        /// Inputs matching each field of the vertex
+        #vis struct #inputs_name {
+            #(
+                pub #fields: gfx::spv::Input<#tys>,
+            )*
+        }
+
+        impl gfx::VertexInputs for #name {
+            type Inputs = #inputs_name;
+
+            fn inputs(b: &gfx::spv::Builder) -> #inputs_name {
+                #inputs_name {
+                    #(
+                        #fields: b.#fns(#names),
+                    )*
+                }
+            }
+        }
+    );
+
+    TokenStream::from(expanded)
+}
+
+const STD140_ERROR: &'static str = "Structs to derive Std140 must have named fields of types that implement gfx::Std140 and no generics";
+
+#[proc_macro_derive(Std140)]
+pub fn derive_std140(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let name = input.ident;
+
+    if !input.generics.params.is_empty() {
+        unimplemented!("{}", STD140_ERROR);
+    }
+
+    let Data::Struct(struct_data) = input.data else {
+        unimplemented!("{}", STD140_ERROR);
+    };
+
+    let mut fields = Vec::new();
+    let mut tys = Vec::new();
+    let mut errors = Vec::new();
+
+    for field in &struct_data.fields {
+        let Some(ident) = &field.ident else {
+            unimplemented!("{}", STD140_ERROR)
+        };
+        errors.push(format!(
+            "ERROR: Field {} of {} isn't at its std140 offset, add padding before it",
+            ident, name
+        ));
+        fields.push(ident.clone());
+        tys.push(field.ty.clone());
+    }
+
+    // structs are aligned to the largest alignment of their members rounded up to a vec4
+    let align = tys.iter().fold(quote!(16), |a, ty| {
+        quote!(gfx::std140_max(#a, <#ty as gfx::Std140>::STD140_ALIGN))
+    });
+
+    let expanded = quote!(
+        unsafe impl gfx::Std140 for #name {
+            const STD140_ALIGN: usize = gfx::std140_round_up(#align, 16);
+            const STD140_SIZE: usize = {
+                let mut offset = 0;
+                #(
+                    offset = gfx::std140_round_up(offset, <#tys as gfx::Std140>::STD140_ALIGN);
+                    if offset != ::std::mem::offset_of!(#name, #fields) {
+                        panic!(#errors);
+                    }
+                    offset += <#tys as gfx::Std140>::STD140_SIZE;
+                )*
+                gfx::std140_round_up(offset, 16)
+            };
+        }
+
+        // evaluate the layout checks even if the struct is never nested
+        const _: usize = <#name as gfx::Std140>::STD140_SIZE;
+    );
+
+    TokenStream::from(expanded)
+}
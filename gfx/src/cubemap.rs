@@ -0,0 +1,167 @@
+//! Render a scene from a point into each face of a cube texture
+//!
+//! Used for baking skyboxes, reflection probes and other environment captures
+
+use std::borrow::Cow;
+
+/// The view matrices looking out of each face of a cube from position in the order of [`crate::CubeFace`]
+pub fn cube_face_views(position: glam::Vec3) -> [glam::Mat4; 6] {
+    [
+        glam::Mat4::look_at_rh(position, position - glam::Vec3::X, glam::Vec3::Y),
+        glam::Mat4::look_at_rh(position, position + glam::Vec3::X, glam::Vec3::Y),
+        glam::Mat4::look_at_rh(position, position - glam::Vec3::Y, glam::Vec3::Z),
+        glam::Mat4::look_at_rh(position, position + glam::Vec3::Y, -glam::Vec3::Z),
+        glam::Mat4::look_at_rh(position, position + glam::Vec3::Z, glam::Vec3::Y),
+        glam::Mat4::look_at_rh(position, position - glam::Vec3::Z, glam::Vec3::Y),
+    ]
+}
+
+/// The projection covering one face of a cube, a 90 degree field of view with square aspect
+pub fn cube_face_projection(z_near: f32, z_far: f32) -> glam::Mat4 {
+    glam::Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, z_near, z_far)
+}
+
+/// Describes a cube capture, see [`capture_cubemap`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CubeCaptureDesc {
+    /// the point the scene is rendered from
+    pub position: glam::Vec3,
+    /// the near plane of the face projections
+    pub z_near: f32,
+    /// the far plane of the face projections
+    pub z_far: f32,
+    /// the format of the cube texture
+    pub format: gpu::Format,
+    /// the format of the depth attachment shared by each face, None for no depth
+    pub depth_format: Option<gpu::Format>,
+    /// usage of the cube texture in addition to COLOR_OUTPUT, COPY_SRC and COPY_DST
+    pub usage: gpu::TextureUsage,
+    /// if more than 1 the levels are generated from the captured faces
+    pub mip_levels: u32,
+    /// the name of the cube texture
+    pub name: Option<String>,
+}
+
+impl Default for CubeCaptureDesc {
+    fn default() -> Self {
+        Self {
+            position: glam::Vec3::ZERO,
+            z_near: 0.1,
+            z_far: 100.0,
+            format: gpu::Format::Rgba16Float,
+            depth_format: Some(gpu::Format::Depth32Float),
+            usage: gpu::TextureUsage::SAMPLED,
+            mip_levels: 1,
+            name: None,
+        }
+    }
+}
+
+/// One face of a cube capture passed to the render callback of [`capture_cubemap`]
+#[derive(Debug, Clone)]
+pub struct CubeCaptureFace {
+    /// the face being rendered
+    pub face: crate::CubeFace,
+    /// view of the face to render into
+    pub color: gpu::TextureView,
+    /// the depth texture if the capture has a depth format, shared between faces
+    pub depth: Option<gpu::TextureView>,
+    /// the view matrix of the face
+    pub view: glam::Mat4,
+    /// the projection matrix of the face
+    pub projection: glam::Mat4,
+    /// the point the scene is rendered from
+    pub position: glam::Vec3,
+}
+
+impl CubeCaptureFace {
+    /// The projection and view of the face combined
+    pub fn view_projection(&self) -> glam::Mat4 {
+        self.projection * self.view
+    }
+
+    /// An attachment clearing the face to clear and storing the result
+    pub fn color_attachment<'a>(&self, clear: [f32; 4]) -> crate::Attachment<'a> {
+        crate::Attachment {
+            raw: gpu::Attachment::View(
+                Cow::Owned(self.color.clone()),
+                gpu::ClearValue::ColorFloat(clear),
+            ),
+            load: gpu::LoadOp::Clear,
+            store: gpu::StoreOp::Store,
+        }
+    }
+
+    /// An attachment clearing the depth texture to 1.0, None if the capture has no depth
+    pub fn depth_attachment<'a>(&self) -> Option<crate::Attachment<'a>> {
+        self.depth.as_ref().map(|depth| crate::Attachment {
+            raw: gpu::Attachment::View(
+                Cow::Owned(depth.clone()),
+                gpu::ClearValue::Depth(1.0),
+            ),
+            load: gpu::LoadOp::Clear,
+            store: gpu::StoreOp::DontCare,
+        })
+    }
+}
+
+/// Create a cube texture of resolution x resolution faces and fill it by calling render_fn once per face
+///
+/// render_fn should record passes drawing the scene into [`CubeCaptureFace::color`] using the face's camera.
+/// The faces are rendered in the order of [`crate::CubeFace`] and the capture will only be complete when
+/// the command encoder is submitted
+pub fn capture_cubemap<'a, F>(
+    encoder: &mut crate::CommandEncoder<'a>,
+    device: &gpu::Device,
+    mut render_fn: F,
+    resolution: gpu::Size,
+    desc: &CubeCaptureDesc,
+) -> Result<crate::GTextureCube, gpu::Error>
+where
+    F: FnMut(&mut crate::CommandEncoder<'a>, &CubeCaptureFace) -> Result<(), gpu::Error>,
+{
+    let cube = crate::GTextureCube::new(
+        device,
+        resolution,
+        desc.usage
+            | gpu::TextureUsage::COLOR_OUTPUT
+            | gpu::TextureUsage::COPY_SRC
+            | gpu::TextureUsage::COPY_DST,
+        desc.mip_levels,
+        desc.format,
+        desc.name.as_deref(),
+    )?;
+
+    let depth = if let Some(format) = desc.depth_format {
+        Some(crate::GTexture2D::new(
+            device,
+            resolution,
+            resolution,
+            gpu::Samples::S1,
+            gpu::TextureUsage::DEPTH_OUTPUT,
+            1,
+            format,
+            desc.name.as_ref().map(|n| format!("{}_depth", n)).as_deref(),
+        )?)
+    } else {
+        None
+    };
+
+    let projection = cube_face_projection(desc.z_near, desc.z_far);
+    let views = cube_face_views(desc.position);
+
+    for face in crate::CubeFace::iter() {
+        let capture = CubeCaptureFace {
+            face,
+            color: cube.face_mip_view(face, 0)?,
+            depth: depth.as_ref().map(|d| d.view.clone()),
+            view: views[face as usize],
+            projection,
+            position: desc.position,
+        };
+        render_fn(encoder, &capture)?;
+    }
+
+    cube.gen_mipmaps_owned(encoder);
+    Ok(cube)
+}
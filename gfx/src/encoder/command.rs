@@ -12,11 +12,21 @@ pub enum Command<'a> {
         buffers: Vec<gpu::BufferAccessInfo<'a>>,
         textures: Vec<gpu::TextureAccessInfo<'a>>,
     },
+    AcquireTextures {
+        semaphore: gpu::Semaphore,
+        src_queue: gpu::Queue,
+        dst_stage: gpu::PipelineStageFlags,
+        textures: Vec<gpu::TextureAccessInfo<'a>>,
+    },
     UpdateBuffer {
         buffer: Cow<'a, gpu::Buffer>,
         offset: u64,
         data: Cow<'a, [u8]>,
     },
+    FillBuffer {
+        buffer: gpu::BufferSlice<'a>,
+        value: u32,
+    },
     ClearTexture {
         texture: gpu::TextureSlice<'a>,
         layout: gpu::TextureLayout,
@@ -29,6 +39,11 @@ pub enum Command<'a> {
         dst_layout: gpu::TextureLayout,
         filter: gpu::FilterMode,
     },
+    GenerateMipmaps {
+        texture: gpu::TextureSlice<'a>,
+        layout: gpu::TextureLayout,
+        filter: gpu::FilterMode,
+    },
     ResolveTextures {
         src: gpu::TextureSlice<'a>,
         src_layout: gpu::TextureLayout,
@@ -96,6 +111,7 @@ impl<'a> Command<'a> {
                 offset,
                 data,
             } => command_buffer.update_buffer(buffer.as_ref(), *offset, data)?,
+            Command::FillBuffer { buffer, value } => command_buffer.fill_buffer(buffer, *value)?,
             Command::ResolveTextures {
                 src,
                 src_layout,
@@ -109,12 +125,36 @@ impl<'a> Command<'a> {
                 dst_layout,
                 filter,
             } => command_buffer.blit_textures(src, *src_layout, dst, *dst_layout, *filter)?,
+            Command::GenerateMipmaps {
+                texture,
+                layout,
+                filter,
+            } => command_buffer.generate_mipmaps(texture, *layout, *filter)?,
             Command::PipelineBarrier {
                 buffers,
                 textures,
                 src_stage,
                 dst_stage,
             } => command_buffer.pipeline_barrier(*src_stage, *dst_stage, buffers, textures)?,
+            Command::AcquireTextures {
+                semaphore,
+                src_queue,
+                dst_stage,
+                textures,
+            } => {
+                command_buffer.wait_semaphore(semaphore, *dst_stage);
+                let dst_queue = command_buffer.queue();
+                // within a queue family the release barrier has already transitioned the textures
+                if src_queue.family() != dst_queue.family() {
+                    command_buffer.queue_ownership_barrier(
+                        *src_queue,
+                        dst_queue,
+                        gpu::PipelineStageFlags::TOP_OF_PIPE,
+                        *dst_stage,
+                        textures,
+                    )?
+                }
+            }
             Command::CopyBufferToBuffer { src, dst } => {
                 command_buffer.copy_buffer_to_buffer(src, dst)?
             }
@@ -236,6 +276,18 @@ impl<'a> Command<'a> {
     pub fn textures(&self) -> HashMap<(gpu::Texture, u32, u32), gpu::TextureLayout> {
         let mut result = HashMap::new();
         match self {
+            Command::AcquireTextures { textures, .. } => {
+                // the textures are left in dst_layout by the acquire
+                for texture in textures {
+                    for i in texture.base_mip_level..(texture.base_mip_level + texture.mip_levels) {
+                        for j in texture.base_array_layer
+                            ..(texture.base_array_layer + texture.array_layers)
+                        {
+                            result.insert(((*texture.texture).clone(), i, j), texture.dst_layout);
+                        }
+                    }
+                }
+            }
             Command::ClearTexture {
                 texture, layout, ..
             } => {
@@ -248,6 +300,19 @@ impl<'a> Command<'a> {
                     }
                 }
             }
+            Command::GenerateMipmaps {
+                texture, layout, ..
+            } => {
+                // every mip level is left in layout after the command
+                for i in texture.base_mip_level()..(texture.base_mip_level() + texture.mip_levels())
+                {
+                    for j in texture.base_array_layer()
+                        ..(texture.base_array_layer() + texture.array_layers())
+                    {
+                        result.insert((texture.texture().clone(), i, j), *layout);
+                    }
+                }
+            }
             Command::ResolveTextures {
                 src,
                 src_layout,
@@ -435,12 +500,15 @@ impl<'a> Command<'a> {
                 data,
             } => match buffer {
                 Cow::Borrowed(b) => {
-                    result.insert(b.slice_ref((*offset)..(data.len() as _)));
+                    result.insert(b.slice_ref((*offset)..(*offset + data.len() as u64)));
                 }
                 Cow::Owned(b) => {
-                    result.insert(b.slice_owned((*offset)..(data.len() as _)));
+                    result.insert(b.slice_owned((*offset)..(*offset + data.len() as u64)));
                 }
             },
+            Command::FillBuffer { buffer, .. } => {
+                result.insert(buffer.clone());
+            }
             Command::CopyBufferToBuffer { src, dst } => {
                 result.insert(src.clone());
                 result.insert(dst.clone());
@@ -514,15 +582,19 @@ impl<'a> Command<'a> {
         samplers
     }
 
-    /// returns access flags for this command
-    pub(crate) fn texture_access(&self) -> gpu::AccessFlags {
+    /// returns access flags for a texture used in layout by this command
+    pub(crate) fn texture_access(&self, layout: gpu::TextureLayout) -> gpu::AccessFlags {
         match self {
             // Command::ExecuteSecondary(_) => gpu::AccessFlags::empty(),
             Command::ClearTexture { .. } => gpu::AccessFlags::COPY_WRITE,
             Command::UpdateBuffer { .. } => gpu::AccessFlags::COPY_WRITE,
+            Command::FillBuffer { .. } => gpu::AccessFlags::COPY_WRITE,
             Command::BlitTextures { .. } => {
                 gpu::AccessFlags::COPY_READ | gpu::AccessFlags::COPY_WRITE
             }
+            Command::GenerateMipmaps { .. } => {
+                gpu::AccessFlags::COPY_READ | gpu::AccessFlags::COPY_WRITE
+            }
             Command::CopyBufferToBuffer { .. } => {
                 gpu::AccessFlags::COPY_READ | gpu::AccessFlags::COPY_WRITE
             }
@@ -538,9 +610,11 @@ impl<'a> Command<'a> {
             Command::ResolveTextures { .. } => {
                 gpu::AccessFlags::COPY_READ | gpu::AccessFlags::COPY_WRITE
             }
-            Command::GraphicsPass { .. } => gpu::AccessFlags::MEMORY_READ,
+            Command::GraphicsPass { .. } => layout_access(layout),
             Command::PipelineBarrier { .. } => gpu::AccessFlags::empty(),
-            Command::ComputePass { .. } => gpu::AccessFlags::empty(),
+            // the layout transition of the acquire has to complete before the textures are used again
+            Command::AcquireTextures { .. } => gpu::AccessFlags::MEMORY_WRITE,
+            Command::ComputePass { .. } => layout_access(layout),
             Command::WriteTimeStamp { .. } => gpu::AccessFlags::empty(),
             Command::ResetTimeQuery { .. } => gpu::AccessFlags::empty(),
         }
@@ -551,7 +625,9 @@ impl<'a> Command<'a> {
             // Command::ExecuteSecondary(_) => gpu::AccessFlags::empty(),
             Command::ClearTexture { .. } => gpu::AccessFlags::empty(),
             Command::BlitTextures { .. } => gpu::AccessFlags::empty(),
+            Command::GenerateMipmaps { .. } => gpu::AccessFlags::empty(),
             Command::UpdateBuffer { .. } => gpu::AccessFlags::COPY_WRITE,
+            Command::FillBuffer { .. } => gpu::AccessFlags::COPY_WRITE,
             Command::CopyBufferToBuffer { .. } => {
                 gpu::AccessFlags::COPY_READ | gpu::AccessFlags::COPY_WRITE
             }
@@ -565,9 +641,22 @@ impl<'a> Command<'a> {
                 gpu::AccessFlags::COPY_READ | gpu::AccessFlags::COPY_WRITE
             }
             Command::ResolveTextures { .. } => gpu::AccessFlags::empty(),
-            Command::GraphicsPass { .. } => gpu::AccessFlags::MEMORY_READ,
+            Command::GraphicsPass { .. } => {
+                gpu::AccessFlags::INDEX_READ
+                    | gpu::AccessFlags::INDIRECT_COMMAND_READ
+                    | gpu::AccessFlags::VERTEX_ATTRIBUTE_READ
+                    | gpu::AccessFlags::UNIFORM_READ
+                    | gpu::AccessFlags::SHADER_READ
+                    | gpu::AccessFlags::SHADER_WRITE
+                    | gpu::AccessFlags::MEMORY_READ
+            }
             Command::PipelineBarrier { .. } => gpu::AccessFlags::empty(),
-            Command::ComputePass { .. } => gpu::AccessFlags::empty(),
+            Command::AcquireTextures { .. } => gpu::AccessFlags::empty(),
+            Command::ComputePass { .. } => {
+                gpu::AccessFlags::UNIFORM_READ
+                    | gpu::AccessFlags::SHADER_READ
+                    | gpu::AccessFlags::SHADER_WRITE
+            }
             Command::WriteTimeStamp { .. } => gpu::AccessFlags::empty(),
             Command::ResetTimeQuery { .. } => gpu::AccessFlags::empty(),
         }
@@ -580,22 +669,49 @@ impl<'a> Command<'a> {
             //     gpu::PipelineStageFlags::TOP_OF_PIPE | gpu::PipelineStageFlags::BOTTOM_OF_PIPE
             // }
             Command::UpdateBuffer { .. } => gpu::PipelineStageFlags::COPY,
+            Command::FillBuffer { .. } => gpu::PipelineStageFlags::COPY,
             Command::ClearTexture { .. } => gpu::PipelineStageFlags::COPY,
             Command::BlitTextures { .. } => gpu::PipelineStageFlags::COPY,
+            Command::GenerateMipmaps { .. } => gpu::PipelineStageFlags::COPY,
             Command::CopyBufferToBuffer { .. } => gpu::PipelineStageFlags::COPY,
             Command::CopyTextureToBuffer { .. } => gpu::PipelineStageFlags::COPY,
             Command::CopyBufferToTexture { .. } => gpu::PipelineStageFlags::COPY,
             Command::CopyTextureToTexture { .. } => gpu::PipelineStageFlags::COPY,
             Command::ResolveTextures { .. } => gpu::PipelineStageFlags::COPY,
-            Command::GraphicsPass { .. } => {
-                gpu::PipelineStageFlags::FRAGMENT
-                    | gpu::PipelineStageFlags::DEPTH_STENCIL_EARLY
-                    | gpu::PipelineStageFlags::DEPTH_STENCIL_LATE
-            }
+            Command::GraphicsPass { .. } => gpu::PipelineStageFlags::ALL_GRAPHICS,
             Command::PipelineBarrier { .. } => gpu::PipelineStageFlags::empty(),
+            Command::AcquireTextures { dst_stage, .. } => *dst_stage,
             Command::ComputePass { .. } => gpu::PipelineStageFlags::COMPUTE,
             Command::WriteTimeStamp { .. } => gpu::PipelineStageFlags::empty(),
             Command::ResetTimeQuery { .. } => gpu::PipelineStageFlags::empty(),
         }
     }
 }
+
+/// The access a pass makes to a texture it uses in layout
+fn layout_access(layout: gpu::TextureLayout) -> gpu::AccessFlags {
+    match layout {
+        gpu::TextureLayout::General => {
+            gpu::AccessFlags::SHADER_READ | gpu::AccessFlags::SHADER_WRITE
+        }
+        gpu::TextureLayout::ColorAttachmentOptimal => {
+            gpu::AccessFlags::COLOR_ATTACHMENT_READ | gpu::AccessFlags::COLOR_ATTACHMENT_WRITE
+        }
+        gpu::TextureLayout::DepthStencilAttachmentOptimal
+        | gpu::TextureLayout::DepthAttachmentOptimal => {
+            gpu::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                | gpu::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE
+        }
+        gpu::TextureLayout::DepthStencilReadOnlyOptimal
+        | gpu::TextureLayout::DepthReadOnlyOptimal
+        | gpu::TextureLayout::StencilReadOnlyOptimal => {
+            gpu::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | gpu::AccessFlags::SHADER_READ
+        }
+        gpu::TextureLayout::ShaderReadOnlyOptimal => gpu::AccessFlags::SHADER_READ,
+        gpu::TextureLayout::CopySrcOptimal => gpu::AccessFlags::COPY_READ,
+        gpu::TextureLayout::CopyDstOptimal => gpu::AccessFlags::COPY_WRITE,
+        gpu::TextureLayout::Undefined | gpu::TextureLayout::SwapchainPresent => {
+            gpu::AccessFlags::empty()
+        }
+    }
+}
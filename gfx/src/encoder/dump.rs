@@ -0,0 +1,513 @@
+//! Export the structure of a [`CommandEncoder`] for debugging
+//!
+//! [`CommandEncoder::dump`] formats the encoder and describes each command, the resources it uses and the
+//! pipeline barriers between them. The description can be written as a graphviz graph with [`FrameDump::to_graphviz`]
+//! or as json with [`FrameDump::to_json`] so that synchronization issues can be inspected
+
+use std::fmt::Write;
+
+use super::Command;
+use super::CommandEncoder;
+
+/// Access flags that write to a resource
+pub(crate) const WRITE_ACCESS: gpu::AccessFlags = gpu::AccessFlags::from_bits_truncate(
+    gpu::AccessFlags::SHADER_WRITE.bits()
+        | gpu::AccessFlags::COLOR_ATTACHMENT_WRITE.bits()
+        | gpu::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE.bits()
+        | gpu::AccessFlags::COPY_WRITE.bits()
+        | gpu::AccessFlags::MEMORY_WRITE.bits(),
+);
+
+/// A subresource of a texture used by a command
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureUse {
+    /// id of the texture
+    pub id: u64,
+    /// name of the texture
+    pub name: Option<String>,
+    /// mip level used
+    pub mip: u32,
+    /// array layer used
+    pub layer: u32,
+    /// the layout the subresource is in while used
+    pub layout: gpu::TextureLayout,
+    /// if the command writes to the subresource
+    pub write: bool,
+}
+
+/// A buffer used by a command
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferUse {
+    /// id of the buffer
+    pub id: u64,
+    /// name of the buffer
+    pub name: Option<String>,
+    /// offset in bytes of the slice used
+    pub offset: u64,
+    /// size in bytes of the slice used
+    pub size: u64,
+    /// if the command writes to the buffer
+    pub write: bool,
+}
+
+/// A texture transition or memory dependency in a pipeline barrier
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureBarrier {
+    /// id of the texture
+    pub id: u64,
+    /// name of the texture
+    pub name: Option<String>,
+    /// first mip level of the barrier
+    pub base_mip_level: u32,
+    /// number of mip levels of the barrier
+    pub mip_levels: u32,
+    /// first array layer of the barrier
+    pub base_array_layer: u32,
+    /// number of array layers of the barrier
+    pub array_layers: u32,
+    /// layout before the barrier
+    pub src_layout: gpu::TextureLayout,
+    /// layout after the barrier
+    pub dst_layout: gpu::TextureLayout,
+    /// accesses waited on
+    pub src_access: gpu::AccessFlags,
+    /// accesses made to wait
+    pub dst_access: gpu::AccessFlags,
+}
+
+/// A memory dependency on a buffer in a pipeline barrier
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferBarrier {
+    /// id of the buffer
+    pub id: u64,
+    /// name of the buffer
+    pub name: Option<String>,
+    /// accesses waited on
+    pub src_access: gpu::AccessFlags,
+    /// accesses made to wait
+    pub dst_access: gpu::AccessFlags,
+}
+
+/// A pipeline barrier between commands
+#[derive(Debug, Clone, PartialEq)]
+pub struct BarrierDump {
+    /// stages waited on
+    pub src_stage: gpu::PipelineStageFlags,
+    /// stages made to wait
+    pub dst_stage: gpu::PipelineStageFlags,
+    /// the textures synchronized or transitioned
+    pub textures: Vec<TextureBarrier>,
+    /// the buffers synchronized
+    pub buffers: Vec<BufferBarrier>,
+}
+
+/// A single command of an encoder
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandDump {
+    /// position of the command in the encoder
+    pub index: usize,
+    /// the type of the command such as "GraphicsPass" or "CopyBufferToBuffer"
+    pub kind: &'static str,
+    /// name of the pipeline of a graphics or compute pass
+    pub pipeline: Option<String>,
+    /// the stages the command executes in
+    pub stage: gpu::PipelineStageFlags,
+    /// textures used by the command
+    pub textures: Vec<TextureUse>,
+    /// buffers used by the command
+    pub buffers: Vec<BufferUse>,
+    /// Some if the command is a pipeline barrier
+    pub barrier: Option<BarrierDump>,
+}
+
+/// A description of every command in an encoder, see [`CommandEncoder::dump`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameDump {
+    /// the commands in the order they are recorded
+    pub commands: Vec<CommandDump>,
+}
+
+fn kind(command: &Command<'_>) -> &'static str {
+    match command {
+        Command::PipelineBarrier { .. } => "PipelineBarrier",
+        Command::AcquireTextures { .. } => "AcquireTextures",
+        Command::UpdateBuffer { .. } => "UpdateBuffer",
+        Command::FillBuffer { .. } => "FillBuffer",
+        Command::ClearTexture { .. } => "ClearTexture",
+        Command::BlitTextures { .. } => "BlitTextures",
+        Command::GenerateMipmaps { .. } => "GenerateMipmaps",
+        Command::ResolveTextures { .. } => "ResolveTextures",
+        Command::CopyBufferToBuffer { .. } => "CopyBufferToBuffer",
+        Command::CopyTextureToBuffer { .. } => "CopyTextureToBuffer",
+        Command::CopyBufferToTexture { .. } => "CopyBufferToTexture",
+        Command::CopyTextureToTexture { .. } => "CopyTextureToTexture",
+        Command::GraphicsPass { .. } => "GraphicsPass",
+        Command::ComputePass { .. } => "ComputePass",
+        Command::WriteTimeStamp { .. } => "WriteTimeStamp",
+        Command::ResetTimeQuery { .. } => "ResetTimeQuery",
+    }
+}
+
+impl CommandDump {
+    fn new(index: usize, command: &Command<'_>) -> Self {
+        let pipeline = match command {
+            Command::GraphicsPass { pipeline, .. } => pipeline.name().map(|n| n.to_string()),
+            Command::ComputePass { pipeline, .. } => pipeline.name().map(|n| n.to_string()),
+            _ => None,
+        };
+
+        let barrier = if let Command::PipelineBarrier {
+            src_stage,
+            dst_stage,
+            buffers,
+            textures,
+        } = command
+        {
+            Some(BarrierDump {
+                src_stage: *src_stage,
+                dst_stage: *dst_stage,
+                textures: textures
+                    .iter()
+                    .map(|t| TextureBarrier {
+                        id: t.texture.id(),
+                        name: t.texture.name().map(|n| n.to_string()),
+                        base_mip_level: t.base_mip_level,
+                        mip_levels: t.mip_levels,
+                        base_array_layer: t.base_array_layer,
+                        array_layers: t.array_layers,
+                        src_layout: t.src_layout,
+                        dst_layout: t.dst_layout,
+                        src_access: t.src_access,
+                        dst_access: t.dst_access,
+                    })
+                    .collect(),
+                buffers: buffers
+                    .iter()
+                    .map(|b| BufferBarrier {
+                        id: b.buffer.cow_buffer().id(),
+                        name: b.buffer.cow_buffer().name().map(|n| n.to_string()),
+                        src_access: b.src_access,
+                        dst_access: b.dst_access,
+                    })
+                    .collect(),
+            })
+        } else {
+            None
+        };
+
+        let (textures, buffers) = if barrier.is_none() {
+            let written = command
+                .layout_changes()
+                .into_iter()
+                .map(|(t, mip, layer, _)| (t.id(), mip, layer))
+                .collect::<Vec<_>>();
+            let mut textures = command
+                .textures()
+                .into_iter()
+                .map(|((t, mip, layer), layout)| TextureUse {
+                    id: t.id(),
+                    name: t.name().map(|n| n.to_string()),
+                    mip,
+                    layer,
+                    layout,
+                    write: command.texture_access(layout).intersects(WRITE_ACCESS)
+                        || written.contains(&(t.id(), mip, layer)),
+                })
+                .collect::<Vec<_>>();
+            textures.sort_by_key(|t| (t.id, t.mip, t.layer));
+
+            let buffer_write = command.buffer_access().intersects(WRITE_ACCESS);
+            let mut buffers = command
+                .buffers()
+                .into_iter()
+                .map(|b| BufferUse {
+                    id: b.cow_buffer().id(),
+                    name: b.cow_buffer().name().map(|n| n.to_string()),
+                    offset: b.offset(),
+                    size: b.size(),
+                    write: buffer_write,
+                })
+                .collect::<Vec<_>>();
+            buffers.sort_by_key(|b| (b.id, b.offset));
+            (textures, buffers)
+        } else {
+            (Vec::new(), Vec::new())
+        };
+
+        Self {
+            index,
+            kind: kind(command),
+            pipeline,
+            stage: command.stage(),
+            textures,
+            buffers,
+            barrier,
+        }
+    }
+}
+
+fn resource_label(kind: &str, id: u64, name: &Option<String>) -> String {
+    match name {
+        Some(n) => format!("{} {}", kind, n),
+        None => format!("{} {:#x}", kind, id),
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            c if (c as u32) < 0x20 => write!(result, "\\u{:04x}", c as u32).unwrap(),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn json_name(name: &Option<String>) -> String {
+    match name {
+        Some(n) => format!("\"{}\"", escape(n)),
+        None => "null".to_string(),
+    }
+}
+
+impl FrameDump {
+    /// Write the dump as a graphviz digraph
+    ///
+    /// Commands are chained in recording order with barriers drawn as dashed boxes listing what they wait on.
+    /// Each texture and buffer is a node with edges to the commands that read it and from the commands that write it
+    pub fn to_graphviz(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "digraph frame {{").unwrap();
+        writeln!(out, "    rankdir=TB;").unwrap();
+        writeln!(out, "    node [fontname=\"monospace\"];").unwrap();
+
+        let mut resources = Vec::new();
+
+        for command in &self.commands {
+            let mut label = format!("{}: {}", command.index, command.kind);
+            let style = if let Some(barrier) = &command.barrier {
+                write!(label, "\n{:?} -> {:?}", barrier.src_stage, barrier.dst_stage).unwrap();
+                for t in &barrier.textures {
+                    write!(
+                        label,
+                        "\n{} mip {}..{} layer {}..{} {:?} -> {:?}",
+                        resource_label("texture", t.id, &t.name),
+                        t.base_mip_level,
+                        t.base_mip_level + t.mip_levels,
+                        t.base_array_layer,
+                        t.base_array_layer + t.array_layers,
+                        t.src_layout,
+                        t.dst_layout,
+                    )
+                    .unwrap();
+                }
+                for b in &barrier.buffers {
+                    write!(
+                        label,
+                        "\n{} {:?} -> {:?}",
+                        resource_label("buffer", b.id, &b.name),
+                        b.src_access,
+                        b.dst_access,
+                    )
+                    .unwrap();
+                }
+                "shape=box, style=dashed"
+            } else {
+                if let Some(p) = &command.pipeline {
+                    write!(label, "\n{}", p).unwrap();
+                }
+                "shape=box, style=filled, fillcolor=lightgrey"
+            };
+            writeln!(out, "    c{} [label=\"{}\", {}];", command.index, escape(&label), style).unwrap();
+
+            for t in &command.textures {
+                let node = format!("t{:x}", t.id);
+                if !resources.contains(&node) {
+                    writeln!(
+                        out,
+                        "    {} [label=\"{}\", shape=ellipse];",
+                        node,
+                        escape(&resource_label("texture", t.id, &t.name))
+                    )
+                    .unwrap();
+                    resources.push(node.clone());
+                }
+                let edge_label = escape(&format!("mip {} layer {} {:?}", t.mip, t.layer, t.layout));
+                if t.write {
+                    writeln!(out, "    c{} -> {} [label=\"{}\", color=red];", command.index, node, edge_label).unwrap();
+                } else {
+                    writeln!(out, "    {} -> c{} [label=\"{}\", color=blue];", node, command.index, edge_label).unwrap();
+                }
+            }
+
+            for b in &command.buffers {
+                let node = format!("b{:x}", b.id);
+                if !resources.contains(&node) {
+                    writeln!(
+                        out,
+                        "    {} [label=\"{}\", shape=ellipse];",
+                        node,
+                        escape(&resource_label("buffer", b.id, &b.name))
+                    )
+                    .unwrap();
+                    resources.push(node.clone());
+                }
+                let edge_label = format!("{}..{}", b.offset, b.offset + b.size);
+                if b.write {
+                    writeln!(out, "    c{} -> {} [label=\"{}\", color=red];", command.index, node, edge_label).unwrap();
+                } else {
+                    writeln!(out, "    {} -> c{} [label=\"{}\", color=blue];", node, command.index, edge_label).unwrap();
+                }
+            }
+        }
+
+        for pair in self.commands.windows(2) {
+            writeln!(out, "    c{} -> c{} [style=bold];", pair[0].index, pair[1].index).unwrap();
+        }
+
+        writeln!(out, "}}").unwrap();
+        out
+    }
+
+    /// Write the dump as json
+    ///
+    /// The root object has a "commands" array, flags and layouts are written with their debug formatting
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        out.push_str("{\"commands\":[");
+        for (i, command) in self.commands.iter().enumerate() {
+            if i != 0 {
+                out.push(',');
+            }
+            write!(
+                out,
+                "{{\"index\":{},\"kind\":\"{}\",\"pipeline\":{},\"stage\":\"{:?}\"",
+                command.index,
+                command.kind,
+                json_name(&command.pipeline),
+                command.stage,
+            )
+            .unwrap();
+
+            out.push_str(",\"textures\":[");
+            for (i, t) in command.textures.iter().enumerate() {
+                if i != 0 {
+                    out.push(',');
+                }
+                write!(
+                    out,
+                    "{{\"id\":{},\"name\":{},\"mip\":{},\"layer\":{},\"layout\":\"{:?}\",\"write\":{}}}",
+                    t.id,
+                    json_name(&t.name),
+                    t.mip,
+                    t.layer,
+                    t.layout,
+                    t.write,
+                )
+                .unwrap();
+            }
+
+            out.push_str("],\"buffers\":[");
+            for (i, b) in command.buffers.iter().enumerate() {
+                if i != 0 {
+                    out.push(',');
+                }
+                write!(
+                    out,
+                    "{{\"id\":{},\"name\":{},\"offset\":{},\"size\":{},\"write\":{}}}",
+                    b.id,
+                    json_name(&b.name),
+                    b.offset,
+                    b.size,
+                    b.write,
+                )
+                .unwrap();
+            }
+            out.push(']');
+
+            if let Some(barrier) = &command.barrier {
+                write!(
+                    out,
+                    ",\"barrier\":{{\"src_stage\":\"{}\",\"dst_stage\":\"{}\",\"textures\":[",
+                    escape(&format!("{:?}", barrier.src_stage)),
+                    escape(&format!("{:?}", barrier.dst_stage)),
+                )
+                .unwrap();
+                for (i, t) in barrier.textures.iter().enumerate() {
+                    if i != 0 {
+                        out.push(',');
+                    }
+                    write!(
+                        out,
+                        "{{\"id\":{},\"name\":{},\"base_mip_level\":{},\"mip_levels\":{},\"base_array_layer\":{},\"array_layers\":{},\"src_layout\":\"{:?}\",\"dst_layout\":\"{:?}\",\"src_access\":\"{}\",\"dst_access\":\"{}\"}}",
+                        t.id,
+                        json_name(&t.name),
+                        t.base_mip_level,
+                        t.mip_levels,
+                        t.base_array_layer,
+                        t.array_layers,
+                        t.src_layout,
+                        t.dst_layout,
+                        escape(&format!("{:?}", t.src_access)),
+                        escape(&format!("{:?}", t.dst_access)),
+                    )
+                    .unwrap();
+                }
+                out.push_str("],\"buffers\":[");
+                for (i, b) in barrier.buffers.iter().enumerate() {
+                    if i != 0 {
+                        out.push(',');
+                    }
+                    write!(
+                        out,
+                        "{{\"id\":{},\"name\":{},\"src_access\":\"{}\",\"dst_access\":\"{}\"}}",
+                        b.id,
+                        json_name(&b.name),
+                        escape(&format!("{:?}", b.src_access)),
+                        escape(&format!("{:?}", b.dst_access)),
+                    )
+                    .unwrap();
+                }
+                out.push_str("]}");
+            } else {
+                out.push_str(",\"barrier\":null");
+            }
+            out.push('}');
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+impl<'a> CommandEncoder<'a> {
+    /// Describe the commands, resources and barriers of the encoder
+    ///
+    /// The encoder is formatted first so that the barriers are the ones that will be recorded
+    pub fn dump(&mut self) -> FrameDump {
+        if !self.formatted {
+            self.format();
+        }
+        FrameDump {
+            commands: self
+                .commands
+                .iter()
+                .enumerate()
+                .map(|(i, c)| CommandDump::new(i, c))
+                .collect(),
+        }
+    }
+
+    /// Describe the encoder as a graphviz digraph, see [`FrameDump::to_graphviz`]
+    pub fn dump_graphviz(&mut self) -> String {
+        self.dump().to_graphviz()
+    }
+
+    /// Describe the encoder as json, see [`FrameDump::to_json`]
+    pub fn dump_json(&mut self) -> String {
+        self.dump().to_json()
+    }
+}
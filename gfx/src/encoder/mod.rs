@@ -4,6 +4,8 @@
 //!
 //! CommmandEncoders main utility comes from managing texture layouts automatically
 //! They also manage pipeline barriers and allow for reflected pipelines to be much more useful
+//! The last access of every buffer and texture is tracked so barriers between passes wait on the right work,
+//! use [`CommandEncoder::set_auto_barriers`] and [`CommandEncoder::pipeline_barrier`] to take manual control
 //!
 //! [`CommandEncoder::record`] formats the encoders commands then begins the command buffer, records commands and ends the buffer
 //! [`CommandEncoder::submit`] does the same as record but submits the command buffer afterwards
@@ -15,14 +17,22 @@ use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::mem::ManuallyDrop as Md;
+use std::sync::Arc;
 
 pub mod command;
+pub mod dump;
+pub mod parallel;
 
 pub use command::Command;
+pub use dump::FrameDump;
+pub use parallel::ParallelRecorder;
 
 pub struct CommandEncoder<'a> {
     pub(crate) formatted: bool,
+    pub(crate) auto_barriers: bool,
     pub(crate) commands: Vec<Command<'a>>,
+    /// staging belt chunks written to through the encoder, tied to the next submit
+    pub(crate) staging_chunks: crate::staging::EncoderChunks,
 }
 
 impl<'a> CommandEncoder<'a> {
@@ -31,10 +41,70 @@ impl<'a> CommandEncoder<'a> {
         Self {
             // device,
             formatted: false,
+            auto_barriers: true,
             commands: Vec::new(),
+            staging_chunks: crate::staging::EncoderChunks::default(),
         }
     }
 
+    /// Enable or disable automatic pipeline barriers for commands pushed after this call
+    ///
+    /// While disabled no barriers are inserted before commands so any synchronization and
+    /// layout transitions must be added with [`Self::pipeline_barrier`]. Barriers added that way are still
+    /// completed with the access of the previous use of each resource when the encoder is formatted.
+    /// Textures are always returned to their initial layout at the end of the encoder
+    pub fn set_auto_barriers(&mut self, enabled: bool) {
+        self.auto_barriers = enabled;
+    }
+
+    /// Returns true if barriers are inserted automatically before commands
+    pub fn auto_barriers(&self) -> bool {
+        self.auto_barriers
+    }
+
+    /// Insert a pipeline barrier
+    ///
+    /// The stages and access flags given are added to those found by tracking resource usage
+    /// and the src_layout of each texture is replaced by the layout the encoder has tracked it in
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdPipelineBarrier.html>
+    pub fn pipeline_barrier(
+        &mut self,
+        src_stage: gpu::PipelineStageFlags,
+        dst_stage: gpu::PipelineStageFlags,
+        buffers: Vec<gpu::BufferAccessInfo<'a>>,
+        textures: Vec<gpu::TextureAccessInfo<'a>>,
+    ) {
+        self.commands.push(Command::PipelineBarrier {
+            src_stage,
+            dst_stage,
+            buffers,
+            textures,
+        });
+        self.formatted = false;
+    }
+
+    /// Wait for the semaphore and acquire ownership of textures released by a submission on src_queue
+    ///
+    /// The semaphore is waited on by the command buffer that self is recorded into before dst_stage and
+    /// the textures should be exclusive and have been released with the same layouts by [`gpu::CommandBuffer::queue_ownership_barrier`],
+    /// the textures are tracked in their dst_layout afterwards so must be acquired before any other use in self.
+    /// As the semaphore is waited on each time self is recorded the encoder should only be submitted once
+    pub fn acquire_textures(
+        &mut self,
+        semaphore: &gpu::Semaphore,
+        src_queue: gpu::Queue,
+        dst_stage: gpu::PipelineStageFlags,
+        textures: Vec<gpu::TextureAccessInfo<'a>>,
+    ) {
+        self.commands.push(Command::AcquireTextures {
+            semaphore: semaphore.clone(),
+            src_queue,
+            dst_stage,
+            textures,
+        });
+        self.formatted = false;
+    }
+
     /// Record the commands into a command buffer
     pub fn record(
         &mut self,
@@ -54,10 +124,14 @@ impl<'a> CommandEncoder<'a> {
             command_buffer.end()?;
         }
 
+        self.staging_chunks.flush()?;
+
         Ok(())
     }
 
     /// Record the commands into a command buffer and then submit
+    ///
+    /// Any [`crate::StagingBelt`] chunks written through the encoder are reused once the submission completes
     pub fn submit(
         &mut self,
         command_buffer: &mut gpu::CommandBuffer,
@@ -65,11 +139,21 @@ impl<'a> CommandEncoder<'a> {
     ) -> Result<(), gpu::Error> {
         let proof = self.record(command_buffer, one_time_submit)?;
         command_buffer.submit()?;
+        let submission = command_buffer.last_submission();
+        self.staging_chunks.close(Some(&submission));
         Ok(proof)
     }
 
     /// Push a command to the end of self
+    ///
+    /// Unless disabled with [`Self::set_auto_barriers`] a pipeline barrier is pushed before the command
+    /// for every resource it uses, the details are filled in when the encoder is formatted
     pub fn push_command(&mut self, command: Command<'a>) {
+        if !self.auto_barriers {
+            self.commands.push(command);
+            self.formatted = false;
+            return;
+        }
         let textures = command
             .textures()
             .into_iter()
@@ -132,6 +216,13 @@ impl<'a> CommandEncoder<'a> {
         })
     }
 
+    /// Fill the buffer slice with repeated copies of value
+    ///
+    /// Useful for resetting counters without a compute pass
+    pub fn fill_buffer(&mut self, buffer: gpu::BufferSlice<'a>, value: u32) {
+        self.push_command(Command::FillBuffer { buffer, value })
+    }
+
     /// Clear the texture owning it
     pub fn clear_texture(&mut self, texture: gpu::TextureSlice<'a>, value: gpu::ClearValue) {
         self.push_command(Command::ClearTexture {
@@ -157,6 +248,17 @@ impl<'a> CommandEncoder<'a> {
         })
     }
 
+    /// fill every mip level of texture after the base by repeatedly blitting from the level above
+    ///
+    /// The texture format must support blitting and linear filtering if filter is Linear
+    pub fn generate_mipmaps(&mut self, texture: gpu::TextureSlice<'a>, filter: gpu::FilterMode) {
+        self.push_command(Command::GenerateMipmaps {
+            texture,
+            layout: gpu::TextureLayout::CopySrcOptimal,
+            filter,
+        })
+    }
+
     /// copy the src buffer to the dst buffer taking ownership of the buffers
     pub fn copy_buffer_to_buffer(&mut self, src: gpu::BufferSlice<'a>, dst: gpu::BufferSlice<'a>) {
         self.push_command(Command::CopyBufferToBuffer { src, dst })
@@ -202,7 +304,9 @@ impl<'a> CommandEncoder<'a> {
         })
     }
 
-    /// resolve the src texture into the dst texture by reference
+    /// resolve the multisampled src texture into the single sampled dst texture by reference
+    ///
+    /// To resolve at the end of a graphics pass instead supply resolve attachments when beginning the pass
     pub fn resolve_texture(&mut self, src: gpu::TextureSlice<'a>, dst: gpu::TextureSlice<'a>) {
         self.push_command(Command::ResolveTextures {
             src,
@@ -249,6 +353,9 @@ impl<'a> CommandEncoder<'a> {
     }
 
     /// Begin a reflected graphics pass owning the data
+    ///
+    /// If resolves isn't empty there must be one per color attachment and each color attachment
+    /// will be resolved into the matching resolve attachment at the end of the pass
     #[cfg(feature = "reflect")]
     pub fn graphics_pass_reflected<'b, V: crate::Vertex>(
         &'b mut self,
@@ -257,6 +364,24 @@ impl<'a> CommandEncoder<'a> {
         resolves: &[crate::Attachment<'a>],
         depth: Option<crate::Attachment<'a>>,
         graphics: &crate::reflect::ReflectedGraphics,
+    ) -> Result<crate::pass::ReflectedGraphicsPass<'a, 'b, V>, gpu::Error> {
+        self.graphics_pass_reflected_viewport(device, colors, resolves, depth, None, graphics)
+    }
+
+    /// Begin a reflected graphics pass drawing into a region of the attachments
+    ///
+    /// If viewport is None it covers the whole of the attachments, the scissor defaults to the viewport so fragments
+    /// outside of it aren't touched. The viewport is part of the pipeline so one variant of graphics is created and cached
+    /// for each distinct viewport used
+    #[cfg(feature = "reflect")]
+    pub fn graphics_pass_reflected_viewport<'b, V: crate::Vertex>(
+        &'b mut self,
+        device: &gpu::Device,
+        colors: &[crate::Attachment<'a>],
+        resolves: &[crate::Attachment<'a>],
+        depth: Option<crate::Attachment<'a>>,
+        viewport: Option<gpu::Viewport>,
+        graphics: &crate::reflect::ReflectedGraphics,
     ) -> Result<crate::pass::ReflectedGraphicsPass<'a, 'b, V>, gpu::Error> {
         use std::hash::Hasher;
 
@@ -272,13 +397,7 @@ impl<'a> CommandEncoder<'a> {
             panic!("Cannot begin graphics pass with no color or depth attachments");
         };
 
-        let samples = if colors.len() != 0 {
-            colors[0].raw.view().samples()
-        } else if let Some(d) = depth.as_ref() {
-            d.raw.view().samples()
-        } else {
-            panic!("Cannot begin graphics pass with no color or depth attachments");
-        };
+        let samples = pass_samples(colors, resolves, depth.as_ref());
 
         let colors_desc = colors
             .iter()
@@ -314,15 +433,16 @@ impl<'a> CommandEncoder<'a> {
         colors_desc.hash(&mut hasher);
         resolves_desc.hash(&mut hasher);
         depth_desc.hash(&mut hasher);
+        samples.hash(&mut hasher);
         let pass_hash = hasher.finish();
 
-        let viewport = gpu::Viewport {
+        let viewport = viewport.unwrap_or(gpu::Viewport {
             x: 0,
             y: 0,
             width: extent.width as _,
             height: extent.height as _,
             ..Default::default()
-        };
+        });
 
         let c = graphics.pipeline_map.read();
 
@@ -334,6 +454,8 @@ impl<'a> CommandEncoder<'a> {
         };
 
         if let None = c.get(&key) {
+            // variants of the same graphics share most state so derive from an existing one
+            let base = c.values().next().cloned();
             drop(c);
             let pass_name = graphics
                 .pipeline_data
@@ -349,13 +471,25 @@ impl<'a> CommandEncoder<'a> {
                 samples,
             })?;
 
-            let vertex_state = gpu::VertexState {
-                stride: std::mem::size_of::<V>() as u32,
-                input_rate: gpu::VertexInputRate::Vertex,
-                attributes: &graphics.vertex_attributes::<V>(),
+            let (attributes, default_attributes) = match graphics.match_vertex::<V>() {
+                Ok(a) => a,
+                Err(report) => panic!("{}", report),
             };
 
-            let vertex_states = &[vertex_state];
+            let mut vertex_states = vec![gpu::VertexState {
+                stride: std::mem::size_of::<V>() as u32,
+                input_rate: gpu::VertexInputRate::Vertex,
+                attributes: &attributes,
+            }];
+
+            // default attributes all vertices read the same value so have no stride
+            if !default_attributes.is_empty() {
+                vertex_states.push(gpu::VertexState {
+                    stride: 0,
+                    input_rate: gpu::VertexInputRate::Vertex,
+                    attributes: &default_attributes,
+                });
+            }
 
             let pipeline_name = graphics
                 .pipeline_data
@@ -372,14 +506,15 @@ impl<'a> CommandEncoder<'a> {
                 geometry: graphics.pipeline_data.geometry.as_ref().map(|s| (s, None)),
                 fragment: graphics.pipeline_data.fragment.as_ref().map(|s| (s, None)),
                 rasterizer: graphics.pipeline_data.rasterizer,
-                vertex_states,
+                vertex_states: &vertex_states,
                 blend_states: &graphics.pipeline_data.blend_states[..colors.len()],
                 depth_stencil: graphics.pipeline_data.depth_stencil,
                 viewports: &[viewport],
                 cache: None,
+                base: base.as_ref(),
             };
 
-            if std::mem::size_of::<V>() == 0 {
+            if std::mem::size_of::<V>() == 0 && default_attributes.is_empty() {
                 desc.vertex_states = &[];
             }
 
@@ -392,19 +527,112 @@ impl<'a> CommandEncoder<'a> {
 
         Ok(crate::pass::ReflectedGraphicsPass {
             parent_id: graphics.id,
+            layout_id: graphics.layout_id(),
             bundle_needed: graphics.bundle_needed(),
             push_constant_names: graphics.reflect_data.push_constant_names.clone(),
+            push_constant_ranges: Arc::clone(&graphics.reflect_data.push_constant_ranges),
+            dynamic_offset_names: graphics.reflect_data.dynamic_offset_names.clone(),
+            dynamic_offsets: vec![0; graphics.reflect_data.dynamic_offset_count],
+            bound_descriptors: None,
             color_attachments: colors.to_vec(),
             resolve_attachments: resolves.to_vec(),
             depth_attachment: depth,
             pipeline: Md::new(Cow::Owned(pipeline.clone())),
-            commands: Vec::new(),
+            commands: graphics
+                .default_buffer
+                .as_ref()
+                .map(|b| crate::pass::GraphicsPassCommand::BindVertexBuffer {
+                    buffer: b.slice_owned(..),
+                    binding: 1,
+                })
+                .into_iter()
+                .collect(),
             encoder: self,
             marker: std::marker::PhantomData,
         })
     }
 
-    /// Begin a reflected graphics pass owning the data
+    /// Begin a reflected graphics pass that renders into multisampled targets and resolves into the attachments
+    ///
+    /// There must be one resolve attachment for each color texture in the targets. The load op and clear value
+    /// of each resolve attachment is used for the matching multisampled texture, as the multisampled contents aren't
+    /// kept between passes LoadOp::Load behaves like LoadOp::DontCare. If the targets have a depth texture it is
+    /// cleared with depth_clear if supplied otherwise its contents are undefined
+    #[cfg(feature = "reflect")]
+    pub fn graphics_pass_reflected_msaa<'b, V: crate::Vertex>(
+        &'b mut self,
+        device: &gpu::Device,
+        targets: &'a crate::MsaaTargets,
+        resolves: &[crate::Attachment<'a>],
+        depth_clear: Option<gpu::ClearValue>,
+        graphics: &crate::reflect::ReflectedGraphics,
+    ) -> Result<crate::pass::ReflectedGraphicsPass<'a, 'b, V>, gpu::Error> {
+        if resolves.len() != targets.colors.len() {
+            panic!("ERROR: Attempt to begin msaa graphics pass with {} resolve attachments for {} multisampled color textures", resolves.len(), targets.colors.len());
+        }
+
+        let colors = targets
+            .colors
+            .iter()
+            .zip(resolves)
+            .map(|(t, r)| crate::Attachment {
+                raw: gpu::Attachment::View(Cow::Borrowed(&t.view), r.raw.clear_value()),
+                load: match r.load {
+                    gpu::LoadOp::Clear => gpu::LoadOp::Clear,
+                    _ => gpu::LoadOp::DontCare,
+                },
+                store: gpu::StoreOp::DontCare,
+            })
+            .collect::<Vec<_>>();
+
+        // the resolve overwrites the whole attachment so the previous contents aren't needed
+        let resolves = resolves
+            .iter()
+            .map(|r| crate::Attachment {
+                raw: r.raw.clone(),
+                load: gpu::LoadOp::DontCare,
+                store: r.store,
+            })
+            .collect::<Vec<_>>();
+
+        let depth = targets.depth.as_ref().map(|d| crate::Attachment {
+            raw: gpu::Attachment::View(
+                Cow::Borrowed(&d.view),
+                depth_clear.unwrap_or(gpu::ClearValue::Depth(1.0)),
+            ),
+            load: if depth_clear.is_some() {
+                gpu::LoadOp::Clear
+            } else {
+                gpu::LoadOp::DontCare
+            },
+            store: gpu::StoreOp::DontCare,
+        });
+
+        self.graphics_pass_reflected(device, &colors, &resolves, depth, graphics)
+    }
+
+    /// Begin a reflected graphics pass rendering into one layer of each of the layered targets
+    ///
+    /// Each target is supplied with the load op and clear value to use for the layer.
+    /// For cube textures the layer of a face is `index * 6 + face as u32`
+    #[cfg(feature = "reflect")]
+    pub fn graphics_pass_reflected_layer<'b, V: crate::Vertex>(
+        &'b mut self,
+        device: &gpu::Device,
+        colors: &[(&'a crate::LayerViews, gpu::LoadOp, gpu::ClearValue)],
+        depth: Option<(&'a crate::LayerViews, gpu::LoadOp, gpu::ClearValue)>,
+        layer: u32,
+        graphics: &crate::reflect::ReflectedGraphics,
+    ) -> Result<crate::pass::ReflectedGraphicsPass<'a, 'b, V>, gpu::Error> {
+        let colors = colors
+            .iter()
+            .map(|(views, load, clear)| views.attachment(layer, *load, *clear))
+            .collect::<Vec<_>>();
+        let depth = depth.map(|(views, load, clear)| views.attachment(layer, load, clear));
+        self.graphics_pass_reflected(device, &colors, &[], depth, graphics)
+    }
+
+    /// Begin a reflected graphics pass setting specialization constants by name
     #[cfg(feature = "reflect")]
     pub fn graphics_pass_specialized<'b, 'c, V: crate::Vertex>(
         &'b mut self,
@@ -415,55 +643,29 @@ impl<'a> CommandEncoder<'a> {
         graphics: &crate::reflect::ReflectedGraphics,
         spec_constants: impl IntoIterator<Item=(&'c str, crate::SpecVal)>,
     ) -> Result<crate::pass::ReflectedGraphicsPass<'a, 'b, V>, gpu::Error> {
-        use std::hash::Hasher;
+        let constants = spec_constants
+            .into_iter()
+            .fold(crate::SpecConstants::new(), |c, (name, value)| c.set(name, value));
+        self.graphics_pass_constants(device, colors, resolves, depth, graphics, &constants)
+    }
 
-        let spec_constants = spec_constants.into_iter().collect::<HashMap<_, _>>();
-
-        let mut vertex_spec_entries = Vec::new();
-        let mut vertex_spec_data = Vec::<u8>::new();
-        let mut vertex_offset = 0;
-
-        let mut geometry_spec_entries = Vec::new();
-        let mut geometry_spec_data = Vec::<u8>::new();
-        let mut geometry_offset = 0;
-
-        let mut fragment_spec_entries = Vec::new();
-        let mut fragment_spec_data = Vec::<u8>::new();
-        let mut fragment_offset = 0;
-
-        for (name, info) in graphics.reflect_data.specialization_names.as_ref().unwrap() {
-            let entry = spec_constants.get(&**name).expect("ERROR No spec constant entry found");
-            let bytes = entry.bytes();
-            let size = bytes.len();
-            if let Some((vertex_id, _)) = info.stages.iter().find(|(_, s)| s.contains(gpu::ShaderStages::VERTEX)) {
-                vertex_spec_entries.push(gpu::SpecializationEntry {
-                    id: *vertex_id,
-                    offset: vertex_offset,
-                    size,
-                });
-                vertex_spec_data.extend(bytes);
-                vertex_offset += size as u32;
-            }
-            if let Some((geometry_id, _)) = info.stages.iter().find(|(_, s)| s.contains(gpu::ShaderStages::GEOMETRY)) {
-                geometry_spec_entries.push(gpu::SpecializationEntry {
-                    id: *geometry_id,
-                    offset: geometry_offset,
-                    size,
-                });
-                geometry_spec_data.extend(bytes);
-                geometry_offset += size as u32;
-            }
+    /// Begin a reflected graphics pass with specialization constants
+    #[cfg(feature = "reflect")]
+    pub fn graphics_pass_constants<'b, V: crate::Vertex>(
+        &'b mut self,
+        device: &gpu::Device,
+        colors: &[crate::Attachment<'a>],
+        resolves: &[crate::Attachment<'a>],
+        depth: Option<crate::Attachment<'a>>,
+        graphics: &crate::reflect::ReflectedGraphics,
+        constants: &crate::SpecConstants,
+    ) -> Result<crate::pass::ReflectedGraphicsPass<'a, 'b, V>, gpu::Error> {
+        use std::hash::Hasher;
 
-            if let Some((fragment_id, _)) = info.stages.iter().find(|(_, s)| s.contains(gpu::ShaderStages::FRAGMENT)) {
-                fragment_spec_entries.push(gpu::SpecializationEntry {
-                    id: *fragment_id,
-                    offset: fragment_offset,
-                    size,
-                });
-                fragment_spec_data.extend(bytes);
-                fragment_offset += size as u32;
-            }
-        }
+        let names = graphics.reflect_data.specialization_names.as_ref();
+        let (vertex_spec_entries, vertex_spec_data) = constants.entries(names, gpu::ShaderStages::VERTEX);
+        let (geometry_spec_entries, geometry_spec_data) = constants.entries(names, gpu::ShaderStages::GEOMETRY);
+        let (fragment_spec_entries, fragment_spec_data) = constants.entries(names, gpu::ShaderStages::FRAGMENT);
 
         let mut hasher = std::collections::hash_map::DefaultHasher::new();
 
@@ -490,13 +692,7 @@ impl<'a> CommandEncoder<'a> {
             panic!("Cannot begin graphics pass with no color or depth attachments");
         };
 
-        let samples = if colors.len() != 0 {
-            colors[0].raw.view().samples()
-        } else if let Some(d) = depth.as_ref() {
-            d.raw.view().samples()
-        } else {
-            panic!("Cannot begin graphics pass with no color or depth attachments");
-        };
+        let samples = pass_samples(colors, resolves, depth.as_ref());
 
         let colors_desc = colors
             .iter()
@@ -532,6 +728,7 @@ impl<'a> CommandEncoder<'a> {
         colors_desc.hash(&mut hasher);
         resolves_desc.hash(&mut hasher);
         depth_desc.hash(&mut hasher);
+        samples.hash(&mut hasher);
         let pass_hash = hasher.finish();
 
         let viewport = gpu::Viewport {
@@ -552,6 +749,8 @@ impl<'a> CommandEncoder<'a> {
         };
 
         if let None = c.get(&key) {
+            // variants of the same graphics share most state so derive from an existing one
+            let base = c.values().next().cloned();
             drop(c);
             let pass_name = graphics
                 .pipeline_data
@@ -567,13 +766,25 @@ impl<'a> CommandEncoder<'a> {
                 samples,
             })?;
 
-            let vertex_state = gpu::VertexState {
-                stride: std::mem::size_of::<V>() as u32,
-                input_rate: gpu::VertexInputRate::Vertex,
-                attributes: &graphics.vertex_attributes::<V>(),
+            let (attributes, default_attributes) = match graphics.match_vertex::<V>() {
+                Ok(a) => a,
+                Err(report) => panic!("{}", report),
             };
 
-            let vertex_states = &[vertex_state];
+            let mut vertex_states = vec![gpu::VertexState {
+                stride: std::mem::size_of::<V>() as u32,
+                input_rate: gpu::VertexInputRate::Vertex,
+                attributes: &attributes,
+            }];
+
+            // default attributes all vertices read the same value so have no stride
+            if !default_attributes.is_empty() {
+                vertex_states.push(gpu::VertexState {
+                    stride: 0,
+                    input_rate: gpu::VertexInputRate::Vertex,
+                    attributes: &default_attributes,
+                });
+            }
 
             let pipeline_name = graphics
                 .pipeline_data
@@ -585,19 +796,20 @@ impl<'a> CommandEncoder<'a> {
                 name: pipeline_name,
                 layout: &graphics.pipeline_data.layout,
                 pass: &pass,
-                vertex: (&graphics.pipeline_data.vertex, None),
+                vertex: (&graphics.pipeline_data.vertex, specialization(&vertex_spec_entries, &vertex_spec_data)),
                 tessellation: None,
-                geometry: graphics.pipeline_data.geometry.as_ref().map(|s| (s, None)),
-                fragment: graphics.pipeline_data.fragment.as_ref().map(|s| (s, None)),
+                geometry: graphics.pipeline_data.geometry.as_ref().map(|s| (s, specialization(&geometry_spec_entries, &geometry_spec_data))),
+                fragment: graphics.pipeline_data.fragment.as_ref().map(|s| (s, specialization(&fragment_spec_entries, &fragment_spec_data))),
                 rasterizer: graphics.pipeline_data.rasterizer,
-                vertex_states,
+                vertex_states: &vertex_states,
                 blend_states: &graphics.pipeline_data.blend_states[..colors.len()],
                 depth_stencil: graphics.pipeline_data.depth_stencil,
                 viewports: &[viewport],
                 cache: None,
+                base: base.as_ref(),
             };
 
-            if std::mem::size_of::<V>() == 0 {
+            if std::mem::size_of::<V>() == 0 && default_attributes.is_empty() {
                 desc.vertex_states = &[];
             }
 
@@ -610,18 +822,54 @@ impl<'a> CommandEncoder<'a> {
 
         Ok(crate::pass::ReflectedGraphicsPass {
             parent_id: graphics.id,
+            layout_id: graphics.layout_id(),
             bundle_needed: graphics.bundle_needed(),
             push_constant_names: graphics.reflect_data.push_constant_names.clone(),
+            push_constant_ranges: Arc::clone(&graphics.reflect_data.push_constant_ranges),
+            dynamic_offset_names: graphics.reflect_data.dynamic_offset_names.clone(),
+            dynamic_offsets: vec![0; graphics.reflect_data.dynamic_offset_count],
+            bound_descriptors: None,
             color_attachments: colors.to_vec(),
             resolve_attachments: resolves.to_vec(),
             depth_attachment: depth,
             pipeline: Md::new(Cow::Owned(pipeline.clone())),
-            commands: Vec::new(),
+            commands: graphics
+                .default_buffer
+                .as_ref()
+                .map(|b| crate::pass::GraphicsPassCommand::BindVertexBuffer {
+                    buffer: b.slice_owned(..),
+                    binding: 1,
+                })
+                .into_iter()
+                .collect(),
             encoder: self,
             marker: std::marker::PhantomData,
         })
     }
 
+    /// Draw a single triangle covering the color attachments
+    ///
+    /// graphics must have a vertex shader that takes no vertex buffers and generates positions
+    /// from the vertex index such as the one used by [`crate::ReflectedGraphics::fullscreen`]
+    #[cfg(feature = "reflect")]
+    pub fn fullscreen_pass(
+        &mut self,
+        device: &gpu::Device,
+        graphics: &crate::reflect::ReflectedGraphics,
+        bundle: Option<&crate::reflect::Bundle>,
+        colors: &[crate::Attachment<'a>],
+    ) -> Result<(), gpu::Error> {
+        use crate::pass::GraphicsPass;
+
+        let mut pass = self.graphics_pass_reflected::<()>(device, colors, &[], None, graphics)?;
+        if let Some(bundle) = bundle {
+            pass.set_bundle_owned(bundle.clone());
+        }
+        pass.draw(0, 3, 0, 1);
+        pass.finish();
+        Ok(())
+    }
+
     /// begin a compute pass owning the pipeline
     pub fn compute_pass_ref<'b>(
         &'b mut self,
@@ -676,43 +924,47 @@ impl<'a> CommandEncoder<'a> {
 
         Ok(crate::pass::ReflectedComputePass {
             parent_id: compute.id,
+            layout_id: compute.layout_id(),
             bundle_needed: compute.bundle_needed(),
             push_constant_names: Cow::Owned(compute.reflect_data.push_constant_names.clone()),
+            push_constant_ranges: Arc::clone(&compute.reflect_data.push_constant_ranges),
+            dynamic_offset_names: compute.reflect_data.dynamic_offset_names.clone(),
+            dynamic_offsets: vec![0; compute.reflect_data.dynamic_offset_count],
+            bound_descriptors: None,
             pipeline: Md::new(Cow::Owned(pipeline)),
             commands: Vec::new(),
             encoder: self,
         })
     }
 
+    /// Begin a reflected compute pass setting specialization constants by name
     #[cfg(feature = "reflect")]
     pub fn compute_pass_specialized<'b, 'c>(
         &'b mut self,
         device: &gpu::Device,
         compute: &crate::reflect::ReflectedCompute,
         spec_constants: impl IntoIterator<Item=(&'c str, crate::SpecVal)>,
+    ) -> Result<crate::pass::ReflectedComputePass<'a, 'b>, gpu::Error> {
+        let constants = spec_constants
+            .into_iter()
+            .fold(crate::SpecConstants::new(), |c, (name, value)| c.set(name, value));
+        self.compute_pass_constants(device, compute, &constants)
+    }
+
+    /// Begin a reflected compute pass with specialization constants
+    #[cfg(feature = "reflect")]
+    pub fn compute_pass_constants<'b>(
+        &'b mut self,
+        device: &gpu::Device,
+        compute: &crate::reflect::ReflectedCompute,
+        constants: &crate::SpecConstants,
     ) -> Result<crate::pass::ReflectedComputePass<'a, 'b>, gpu::Error> {
         use std::hash::Hasher;
 
-        let mut spec_entries = Vec::new();
-        let mut spec_data = Vec::<u8>::new();
-        let spec_constants = spec_constants.into_iter().collect::<HashMap<_, _>>();
-        let mut offset = 0;
-        for (name, info) in compute.reflect_data.specialization_names.as_ref().unwrap() {
-            let entry = spec_constants.get(&**name).expect("ERROR No spec constant entry found");
-            let bytes = entry.bytes();
-            let size = bytes.len();
-            spec_data.extend(bytes);
-
-            let id = info.stages.iter().find(|(_, s)| s.contains(gpu::ShaderStages::COMPUTE)).unwrap().0;
-
-            spec_entries.push(gpu::SpecializationEntry {
-                id,
-                offset,
-                size,
-            });
-
-            offset += size as u32;
-        }
+        let (spec_entries, spec_data) = constants.entries(
+            compute.reflect_data.specialization_names.as_ref(),
+            gpu::ShaderStages::COMPUTE,
+        );
 
         let pipeline_map = compute.pipeline_map.read();
 
@@ -731,10 +983,7 @@ impl<'a> CommandEncoder<'a> {
             let pipeline = device.create_compute_pipeline(&gpu::ComputePipelineDesc {
                 name: compute.pipeline_data.name.clone(),
                 layout: &compute.pipeline_data.layout,
-                shader: (&compute.pipeline_data.shader, Some(gpu::Specialization {
-                    entries: &spec_entries,
-                    data: &spec_data,
-                })),
+                shader: (&compute.pipeline_data.shader, specialization(&spec_entries, &spec_data)),
                 cache: Some(&compute.pipeline_data.cache),
             })?;
 
@@ -745,8 +994,13 @@ impl<'a> CommandEncoder<'a> {
 
         Ok(crate::pass::ReflectedComputePass {
             parent_id: compute.id,
+            layout_id: compute.layout_id(),
             bundle_needed: compute.bundle_needed(),
             push_constant_names: Cow::Owned(compute.reflect_data.push_constant_names.clone()),
+            push_constant_ranges: Arc::clone(&compute.reflect_data.push_constant_ranges),
+            dynamic_offset_names: compute.reflect_data.dynamic_offset_names.clone(),
+            dynamic_offsets: vec![0; compute.reflect_data.dynamic_offset_count],
+            bound_descriptors: None,
             pipeline: Md::new(Cow::Owned(pipeline)),
             commands: Vec::new(),
             encoder: self,
@@ -810,19 +1064,30 @@ impl<'a> CommandEncoder<'a> {
     }
 
     /// fill in any pipeline barriers to contain the correct src and dst flags
+    ///
+    /// The last access of each buffer and texture subresource is tracked through the commands so
+    /// that barriers wait on the previous use and make its writes visible to the next use.
+    /// Barriers between uses that only read and don't change layout are removed
     /// TODO different layers of array textures are allowed to be in different formats
     /// at the moment this will not work as it doesn't know that so will report error saying that
     /// texture is trying to be in multiple layouts at the same time which is wrong
     pub fn format(&mut self) {
         self.formatted = true;
 
+        if self.commands.is_empty() {
+            return;
+        }
+
         let mut i = 0;
         let mut j = self.commands.len() - 1;
 
-        let mut forward_buffer = HashMap::new();
+        // buffers are tracked as a whole so that different slices of the same buffer are synchronized
+        let mut forward_buffer: HashMap<gpu::Buffer, (gpu::AccessFlags, gpu::PipelineStageFlags)> =
+            HashMap::new();
         let mut forward_texture = HashMap::new();
 
-        let mut back_buffer = HashMap::new();
+        let mut back_buffer: HashMap<gpu::Buffer, (gpu::AccessFlags, gpu::PipelineStageFlags)> =
+            HashMap::new();
         let mut back_texture = HashMap::new();
 
         let commands_len = self.commands.len();
@@ -838,9 +1103,11 @@ impl<'a> CommandEncoder<'a> {
             } = forward_command
             {
                 for buffer in buffers {
-                    if let Some((a, s)) = forward_buffer.get_mut(&buffer.buffer) {
+                    if let Some((a, s)) =
+                        forward_buffer.get_mut(buffer.buffer.cow_buffer().as_ref())
+                    {
                         *src_stage |= *s;
-                        buffer.src_access = *a;
+                        buffer.src_access |= *a;
                         *a = gpu::AccessFlags::empty();
                         *s = gpu::PipelineStageFlags::empty();
                     }
@@ -854,7 +1121,7 @@ impl<'a> CommandEncoder<'a> {
                             let key = ((*texture.texture).clone(), i, j);
                             if let Some((a, s, l)) = forward_texture.get_mut(&key) {
                                 *src_stage |= *s;
-                                texture.src_access = *a;
+                                texture.src_access |= *a;
                                 texture.src_layout = *l;
                                 *l = texture.dst_layout;
                                 *a = gpu::AccessFlags::empty();
@@ -867,29 +1134,33 @@ impl<'a> CommandEncoder<'a> {
                 let stage = forward_command.stage();
 
                 let buffer_access = forward_command.buffer_access();
-                let texture_access = forward_command.texture_access();
 
                 for buffer in forward_command.buffers() {
+                    let buffer = buffer.cow_buffer().as_ref().clone();
                     if let Some((a, s)) = forward_buffer.get_mut(&buffer) {
-                        *a = buffer_access;
-                        *s = stage;
+                        *a |= buffer_access;
+                        *s |= stage;
                     } else {
                         forward_buffer.insert(buffer, (buffer_access, stage));
                     }
                 }
 
                 for (texture, layout) in forward_command.textures() {
+                    let texture_access = forward_command.texture_access(layout);
                     if let Some((a, s, l)) = forward_texture.get_mut(&texture) {
-                        *a = texture_access;
-                        *s = stage;
+                        *a |= texture_access;
+                        *s |= stage;
                         *l = layout;
                     } else {
                         forward_texture.insert(texture, (texture_access, stage, layout));
                     }
                 }
 
+                // attachments are written by the pass and left in their final layout
                 for (texture, mip, array, layout) in forward_command.layout_changes() {
-                    let (_, _, l) = forward_texture.get_mut(&(texture, mip, array)).unwrap();
+                    let (a, _, l) = forward_texture.get_mut(&(texture, mip, array)).unwrap();
+                    *a |= gpu::AccessFlags::COLOR_ATTACHMENT_WRITE
+                        | gpu::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE;
                     *l = layout;
                 }
             }
@@ -904,9 +1175,9 @@ impl<'a> CommandEncoder<'a> {
             } = back_command
             {
                 for buffer in buffers {
-                    if let Some((a, s)) = back_buffer.get_mut(&buffer.buffer) {
+                    if let Some((a, s)) = back_buffer.get_mut(buffer.buffer.cow_buffer().as_ref()) {
                         *dst_stage |= *s;
-                        buffer.dst_access = *a;
+                        buffer.dst_access |= *a;
                         *a = gpu::AccessFlags::empty();
                         *s = gpu::PipelineStageFlags::empty();
                     }
@@ -920,7 +1191,7 @@ impl<'a> CommandEncoder<'a> {
                             let key = ((*texture.texture).clone(), i, j);
                             if let Some((a, s, l)) = back_texture.get_mut(&key) {
                                 *dst_stage |= *s;
-                                texture.dst_access = *a;
+                                texture.dst_access |= *a;
                                 *l = texture.src_layout;
                                 *a = gpu::AccessFlags::empty();
                                 *s = gpu::PipelineStageFlags::empty();
@@ -932,21 +1203,22 @@ impl<'a> CommandEncoder<'a> {
                 let stage = back_command.stage();
 
                 let buffer_access = back_command.buffer_access();
-                let texture_access = back_command.texture_access();
 
                 for buffer in back_command.buffers() {
+                    let buffer = buffer.cow_buffer().as_ref().clone();
                     if let Some((a, s)) = back_buffer.get_mut(&buffer) {
-                        *a = buffer_access;
-                        *s = stage;
+                        *a |= buffer_access;
+                        *s |= stage;
                     } else {
                         back_buffer.insert(buffer, (buffer_access, stage));
                     }
                 }
 
                 for (texture, layout) in back_command.textures() {
+                    let texture_access = back_command.texture_access(layout);
                     if let Some((a, s, l)) = back_texture.get_mut(&texture) {
-                        *a = texture_access;
-                        *s = stage;
+                        *a |= texture_access;
+                        *s |= stage;
                         *l = layout;
                     } else {
                         back_texture.insert(texture, (texture_access, stage, layout));
@@ -994,5 +1266,86 @@ impl<'a> CommandEncoder<'a> {
                 textures,
             })
         }
+
+        self.remove_redundant_barriers();
+    }
+
+    /// remove barrier entries between uses that only read and barriers left with nothing to do
+    fn remove_redundant_barriers(&mut self) {
+        let writes = dump::WRITE_ACCESS;
+
+        self.commands.retain_mut(|command| match command {
+            Command::PipelineBarrier {
+                src_stage,
+                dst_stage,
+                buffers,
+                textures,
+            } => {
+                buffers.retain(|b| (b.src_access | b.dst_access).intersects(writes));
+                textures.retain(|t| {
+                    t.src_layout != t.dst_layout || (t.src_access | t.dst_access).intersects(writes)
+                });
+                // the stages can't be empty if the barrier is kept
+                if src_stage.is_empty() {
+                    *src_stage = gpu::PipelineStageFlags::TOP_OF_PIPE;
+                }
+                if dst_stage.is_empty() {
+                    *dst_stage = gpu::PipelineStageFlags::BOTTOM_OF_PIPE;
+                }
+                !buffers.is_empty() || !textures.is_empty()
+            }
+            _ => true,
+        });
+    }
+}
+
+/// Create a specialization if there are any entries
+#[cfg(feature = "reflect")]
+fn specialization<'a>(
+    entries: &'a [gpu::SpecializationEntry],
+    data: &'a [u8],
+) -> Option<gpu::Specialization<'a>> {
+    if entries.is_empty() {
+        None
+    } else {
+        Some(gpu::Specialization { entries, data })
+    }
+}
+
+/// Get the sample count of a pass checking that the attachments are compatible
+#[cfg(feature = "reflect")]
+fn pass_samples(
+    colors: &[crate::Attachment<'_>],
+    resolves: &[crate::Attachment<'_>],
+    depth: Option<&crate::Attachment<'_>>,
+) -> gpu::Samples {
+    let samples = if colors.len() != 0 {
+        colors[0].raw.view().samples()
+    } else if let Some(d) = depth {
+        d.raw.view().samples()
+    } else {
+        panic!("Cannot begin graphics pass with no color or depth attachments");
+    };
+
+    for a in colors.iter().chain(depth) {
+        if a.raw.view().samples() != samples {
+            panic!("ERROR: Attempt to begin graphics pass with attachments of different sample counts {:?} and {:?}", samples, a.raw.view().samples());
+        }
+    }
+
+    if resolves.len() != 0 {
+        if resolves.len() != colors.len() {
+            panic!("ERROR: Attempt to begin graphics pass with {} resolve attachments for {} color attachments", resolves.len(), colors.len());
+        }
+        if samples == gpu::Samples::S1 {
+            panic!("ERROR: Attempt to begin graphics pass with resolve attachments but single sampled color attachments");
+        }
+        for r in resolves {
+            if r.raw.view().samples() != gpu::Samples::S1 {
+                panic!("ERROR: Attempt to begin graphics pass with multisampled resolve attachment {:?}", r.raw.view().samples());
+            }
+        }
     }
+
+    samples
 }
@@ -0,0 +1,219 @@
+//! Recording the passes of an encoder on multiple threads
+
+use super::{Command, CommandEncoder};
+
+/// A pass to be recorded into a secondary command buffer on a worker thread
+enum PassJob<'b, 'a> {
+    Graphics(
+        &'b gpu::GraphicsPipeline,
+        &'b [crate::pass::GraphicsPassCommand<'a>],
+    ),
+    Compute(
+        &'b gpu::ComputePipeline,
+        &'b [crate::pass::ComputePassCommand<'a>],
+    ),
+}
+
+impl<'b, 'a> PassJob<'b, 'a> {
+    fn record(
+        &self,
+        secondary: &mut gpu::SecondaryCommandBuffer,
+        one_time_submit: bool,
+    ) -> Result<(), gpu::Error> {
+        match self {
+            PassJob::Graphics(pipeline, commands) => {
+                secondary.begin_graphics_pass(pipeline, one_time_submit)?;
+                for command in commands.iter() {
+                    command.execute(secondary, pipeline.layout())?;
+                }
+            }
+            PassJob::Compute(pipeline, commands) => {
+                secondary.begin(one_time_submit)?;
+                secondary.begin_compute_pass(pipeline)?;
+                for command in commands.iter() {
+                    command.execute(secondary, pipeline.layout())?;
+                }
+            }
+        }
+        secondary.end()
+    }
+}
+
+/// Records the graphics and compute passes of a [`CommandEncoder`] into secondary command buffers in parallel
+///
+/// Each pass is recorded into its own [`gpu::SecondaryCommandBuffer`], the passes are split evenly between
+/// worker threads and the secondary command buffers are executed by the primary command buffer
+/// in the order the passes were pushed to the encoder so the result is the same as [`CommandEncoder::record`].
+/// Commands outside of passes are recorded directly into the primary command buffer.
+///
+/// The secondary command buffers are reused between recordings so a ParallelRecorder should only be
+/// used with one primary command buffer at a time
+#[derive(Debug)]
+pub struct ParallelRecorder {
+    secondaries: Vec<gpu::SecondaryCommandBuffer>,
+    threads: usize,
+    name: Option<String>,
+}
+
+impl ParallelRecorder {
+    /// Create a new ParallelRecorder
+    ///
+    /// If threads is 0 then the available parallelism of the system is used
+    pub fn new(threads: usize, name: Option<&str>) -> Self {
+        let threads = if threads == 0 {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        } else {
+            threads
+        };
+        Self {
+            secondaries: Vec::new(),
+            threads,
+            name: name.map(|n| n.to_string()),
+        }
+    }
+
+    /// The number of worker threads used to record passes
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Record the commands of the encoder into the command buffer
+    ///
+    /// Waits for the previous submission of the command buffer to complete before recording
+    pub fn record(
+        &mut self,
+        device: &gpu::Device,
+        encoder: &mut CommandEncoder<'_>,
+        command_buffer: &mut gpu::CommandBuffer,
+        one_time_submit: bool,
+    ) -> Result<(), gpu::Error> {
+        if !encoder.formatted {
+            encoder.format();
+        }
+
+        // waits for the last submission so the secondaries it executed can be reused
+        command_buffer.begin(one_time_submit)?;
+
+        let jobs = encoder
+            .commands
+            .iter()
+            .filter_map(|command| match command {
+                Command::GraphicsPass {
+                    pipeline, commands, ..
+                } => Some(PassJob::Graphics(pipeline.as_ref(), commands.as_slice())),
+                Command::ComputePass { pipeline, commands } => {
+                    Some(PassJob::Compute(pipeline.as_ref(), commands.as_slice()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        while self.secondaries.len() < jobs.len() {
+            let index = self.secondaries.len();
+            self.secondaries.push(gpu::SecondaryCommandBuffer::new(
+                device,
+                self.name
+                    .as_ref()
+                    .map(|n| format!("{}_secondary_{}", n, index)),
+            )?);
+        }
+
+        if !jobs.is_empty() {
+            let chunk_size = (jobs.len() + self.threads - 1) / self.threads;
+            let results = std::thread::scope(|scope| {
+                let handles = jobs
+                    .chunks(chunk_size)
+                    .zip(self.secondaries.chunks_mut(chunk_size))
+                    .map(|(jobs, secondaries)| {
+                        scope.spawn(move || {
+                            for (job, secondary) in jobs.iter().zip(secondaries) {
+                                job.record(secondary, one_time_submit)?;
+                            }
+                            Ok(())
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                handles
+                    .into_iter()
+                    .map(|h| {
+                        h.join()
+                            .expect("ERROR: ParallelRecorder worker thread panicked")
+                    })
+                    .collect::<Vec<Result<(), gpu::Error>>>()
+            });
+            // report the error from the earliest pass to be deterministic
+            for result in results {
+                result?;
+            }
+        }
+
+        let mut secondaries = self.secondaries.iter();
+        for command in &encoder.commands {
+            match command {
+                Command::GraphicsPass {
+                    color_attachments,
+                    resolve_attachments,
+                    depth_attachment,
+                    pipeline,
+                    ..
+                } => {
+                    command_buffer.begin_secondary_pass(
+                        color_attachments,
+                        resolve_attachments,
+                        depth_attachment.clone(),
+                        pipeline.pass(),
+                    )?;
+                    command_buffer.execute_secondary(secondaries.next().unwrap())?;
+                    command_buffer.end_graphics_pass()?;
+                }
+                Command::ComputePass { .. } => {
+                    command_buffer.execute_secondary(secondaries.next().unwrap())?;
+                }
+                command => unsafe { command.execute(command_buffer)? },
+            }
+        }
+
+        command_buffer.end()
+    }
+
+    /// Record the commands of the encoder into the command buffer and then submit
+    pub fn submit(
+        &mut self,
+        device: &gpu::Device,
+        encoder: &mut CommandEncoder<'_>,
+        command_buffer: &mut gpu::CommandBuffer,
+        one_time_submit: bool,
+    ) -> Result<(), gpu::Error> {
+        self.record(device, encoder, command_buffer, one_time_submit)?;
+        command_buffer.submit()
+    }
+}
+
+impl<'a> CommandEncoder<'a> {
+    /// Create count empty encoders that can be filled independently, for example on different threads,
+    /// and then appended back onto self in order with [`Self::join`]
+    ///
+    /// The encoders use the same automatic barrier setting as self
+    pub fn split(&self, count: usize) -> Vec<CommandEncoder<'a>> {
+        (0..count)
+            .map(|_| {
+                let mut encoder = CommandEncoder::new();
+                encoder.auto_barriers = self.auto_barriers;
+                encoder
+            })
+            .collect()
+    }
+
+    /// Append the commands of each encoder onto self in the order given
+    ///
+    /// Barriers between the commands of different encoders are filled in when self is formatted
+    pub fn join<I: IntoIterator<Item = CommandEncoder<'a>>>(&mut self, encoders: I) {
+        for encoder in encoders {
+            self.commands.extend(encoder.commands);
+            self.staging_chunks.append(encoder.staging_chunks);
+        }
+        self.formatted = false;
+    }
+}
@@ -0,0 +1,390 @@
+//! A [`RenderGraph`] records a frame out of passes that declare the resources they use
+//!
+//! Passes name the resources that they read and write instead of ordering themselves and managing
+//! intermediate textures by hand. Compiling the graph works out which passes contribute to an
+//! imported resource, what order they have to run in and allocates transient textures, reusing
+//! the same gpu texture for transients whose lifetimes don't overlap.
+//!
+//! Recording the graph runs each pass on a single [`crate::CommandEncoder`] which inserts the
+//! pipeline barriers and layout transitions between passes.
+//!
+//! When more than one pass writes to the same resource the order that the passes were added in
+//! decides which write a read sees, this means ping-ponging between resources works as expected
+
+use std::collections::HashMap;
+
+/// Describes a texture that is created by the graph and only lives between passes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GraphTextureDesc {
+    /// The format of the texture
+    pub format: gpu::Format,
+    /// The usage of the texture
+    pub usage: gpu::TextureUsage,
+    /// The dimension of the texture
+    pub dimension: gpu::TextureDimension,
+    /// The number of mip levels of the texture
+    pub mip_levels: std::num::NonZeroU32,
+}
+
+#[derive(Debug)]
+enum GraphResource {
+    Texture(gpu::Texture),
+    Buffer(gpu::Buffer),
+    Transient(GraphTextureDesc),
+}
+
+/// The resources of a graph available to passes as they are being recorded
+#[derive(Debug, Default)]
+pub struct GraphResources {
+    textures: HashMap<String, (gpu::Texture, gpu::TextureView)>,
+    buffers: HashMap<String, gpu::Buffer>,
+}
+
+impl GraphResources {
+    /// Get the texture with the name supplied
+    pub fn texture(&self, name: &str) -> &gpu::Texture {
+        match self.textures.get(name) {
+            Some((t, _)) => t,
+            None => panic!("ERROR: RenderGraph has no texture called {}", name),
+        }
+    }
+
+    /// Get a view of the whole texture with the name supplied
+    pub fn view(&self, name: &str) -> &gpu::TextureView {
+        match self.textures.get(name) {
+            Some((_, v)) => v,
+            None => panic!("ERROR: RenderGraph has no texture called {}", name),
+        }
+    }
+
+    /// Get the buffer with the name supplied
+    pub fn buffer(&self, name: &str) -> &gpu::Buffer {
+        match self.buffers.get(name) {
+            Some(b) => b,
+            None => panic!("ERROR: RenderGraph has no buffer called {}", name),
+        }
+    }
+}
+
+/// A function that records a pass of a graph
+pub type GraphPassFn<'a> = Box<
+    dyn for<'b> FnMut(&mut crate::CommandEncoder<'b>, &'b GraphResources) -> Result<(), gpu::Error>
+        + 'a,
+>;
+
+struct GraphPass<'a> {
+    name: String,
+    reads: Vec<String>,
+    writes: Vec<String>,
+    record: GraphPassFn<'a>,
+}
+
+impl std::fmt::Debug for GraphPass<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "GraphPass {}, reads: {:?}, writes: {:?}",
+            self.name, self.reads, self.writes
+        )
+    }
+}
+
+#[derive(Debug)]
+struct PooledTexture {
+    desc: GraphTextureDesc,
+    texture: gpu::Texture,
+    view: gpu::TextureView,
+}
+
+/// Collection of passes and the resources they use
+#[derive(Debug)]
+pub struct RenderGraph<'a> {
+    name: Option<String>,
+    resources: HashMap<String, GraphResource>,
+    passes: Vec<GraphPass<'a>>,
+    /// indices of passes to execute, None if the graph has changed since last compiled
+    order: Option<Vec<usize>>,
+    compiled: GraphResources,
+    /// transient textures kept between compilations
+    pool: Vec<PooledTexture>,
+}
+
+impl<'a> RenderGraph<'a> {
+    /// Create a new empty RenderGraph
+    pub fn new(name: Option<String>) -> Self {
+        Self {
+            name,
+            resources: HashMap::new(),
+            passes: Vec::new(),
+            order: None,
+            compiled: GraphResources::default(),
+            pool: Vec::new(),
+        }
+    }
+
+    /// Add a texture that lives outside of the graph
+    ///
+    /// Passes that write to imported resources are always executed.
+    /// If a resource with the same name already exists it will be replaced
+    pub fn import_texture(&mut self, name: impl Into<String>, texture: gpu::Texture) {
+        self.resources
+            .insert(name.into(), GraphResource::Texture(texture));
+        self.order = None;
+    }
+
+    /// Add a buffer that lives outside of the graph
+    ///
+    /// Passes that write to imported resources are always executed.
+    /// If a resource with the same name already exists it will be replaced
+    pub fn import_buffer(&mut self, name: impl Into<String>, buffer: gpu::Buffer) {
+        self.resources
+            .insert(name.into(), GraphResource::Buffer(buffer));
+        self.order = None;
+    }
+
+    /// Add a texture that only lives for the duration of the graph
+    ///
+    /// The contents of the texture are undefined before the first pass that writes to it
+    pub fn create_texture(&mut self, name: impl Into<String>, desc: GraphTextureDesc) {
+        self.resources
+            .insert(name.into(), GraphResource::Transient(desc));
+        self.order = None;
+    }
+
+    /// Add a pass to the graph
+    ///
+    /// reads and writes are the names of the resources that the pass uses, a resource that is both read
+    /// and written should be in both. The function will be called every time the graph is recorded
+    pub fn add_pass<F>(
+        &mut self,
+        name: impl Into<String>,
+        reads: &[&str],
+        writes: &[&str],
+        record: F,
+    ) where
+        F: for<'b> FnMut(
+                &mut crate::CommandEncoder<'b>,
+                &'b GraphResources,
+            ) -> Result<(), gpu::Error>
+            + 'a,
+    {
+        self.passes.push(GraphPass {
+            name: name.into(),
+            reads: reads.iter().map(|r| r.to_string()).collect(),
+            writes: writes.iter().map(|w| w.to_string()).collect(),
+            record: Box::new(record),
+        });
+        self.order = None;
+    }
+
+    /// Get the name of the graph
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|n| n.as_str())
+    }
+
+    /// Returns the names of the passes in the order that they will execute
+    /// or None if the graph hasn't been compiled since it was last changed
+    pub fn order(&self) -> Option<Vec<&str>> {
+        self.order
+            .as_ref()
+            .map(|o| o.iter().map(|&i| self.passes[i].name.as_str()).collect())
+    }
+
+    /// Get the resources of the graph if the graph has been compiled
+    pub fn resources(&self) -> Option<&GraphResources> {
+        self.order.as_ref().map(|_| &self.compiled)
+    }
+
+    /// Work out the order of the passes and allocate transient textures
+    ///
+    /// Called automatically by [`RenderGraph::record`] if the graph has changed
+    pub fn compile(&mut self, device: &gpu::Device) -> Result<(), gpu::Error> {
+        for pass in &self.passes {
+            for r in pass.reads.iter().chain(&pass.writes) {
+                if !self.resources.contains_key(r) {
+                    panic!(
+                        "ERROR: RenderGraph {:?} pass {} uses resource {} that hasn't been declared",
+                        self.name, pass.name, r
+                    );
+                }
+            }
+        }
+
+        // each pass depends on the last write to resources it reads
+        // and on the last write and all reads since of resources it writes
+        let mut dependencies = vec![Vec::new(); self.passes.len()];
+        let mut last_write: HashMap<&str, usize> = HashMap::new();
+        let mut reads_since_write: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, pass) in self.passes.iter().enumerate() {
+            for r in &pass.reads {
+                if let Some(&w) = last_write.get(r.as_str()) {
+                    dependencies[i].push(w);
+                } else if let GraphResource::Transient(_) = self.resources[r] {
+                    panic!(
+                        "ERROR: RenderGraph {:?} pass {} reads transient texture {} before any pass writes to it",
+                        self.name, pass.name, r
+                    );
+                }
+                reads_since_write.entry(r.as_str()).or_default().push(i);
+            }
+            for w in &pass.writes {
+                if let Some(&p) = last_write.get(w.as_str()) {
+                    dependencies[i].push(p);
+                }
+                if let Some(readers) = reads_since_write.remove(w.as_str()) {
+                    dependencies[i].extend(readers.into_iter().filter(|&p| p != i));
+                }
+                last_write.insert(w.as_str(), i);
+            }
+        }
+
+        // only passes that contribute to an imported resource need to run
+        let mut needed = vec![false; self.passes.len()];
+        let mut stack = self
+            .passes
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| {
+                p.writes
+                    .iter()
+                    .any(|w| !matches!(self.resources[w], GraphResource::Transient(_)))
+            })
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        while let Some(i) = stack.pop() {
+            if !needed[i] {
+                needed[i] = true;
+                stack.extend(dependencies[i].iter().copied());
+            }
+        }
+
+        // dependencies always point to passes added earlier so the order passes
+        // were added in is already a valid execution order
+        let order = (0..self.passes.len())
+            .filter(|&i| needed[i])
+            .collect::<Vec<_>>();
+
+        // first and last position in the order that each transient is used
+        let mut lifetimes: HashMap<&str, (usize, usize)> = HashMap::new();
+        for (position, &i) in order.iter().enumerate() {
+            let pass = &self.passes[i];
+            for r in pass.reads.iter().chain(&pass.writes) {
+                if let GraphResource::Transient(_) = self.resources[r] {
+                    lifetimes
+                        .entry(r.as_str())
+                        .and_modify(|(_, last)| *last = position)
+                        .or_insert((position, position));
+                }
+            }
+        }
+
+        let mut transients = lifetimes.into_iter().collect::<Vec<_>>();
+        transients.sort_by_key(|(name, (first, _))| (*first, *name));
+
+        // alias transients with the same description whose lifetimes don't overlap
+        let mut old_pool = std::mem::take(&mut self.pool)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        let mut busy_until: Vec<usize> = Vec::new();
+        let mut textures = HashMap::new();
+        for (name, (first, last)) in transients {
+            let desc = match self.resources[name] {
+                GraphResource::Transient(d) => d,
+                _ => unreachable!(),
+            };
+            let slot = match (0..self.pool.len())
+                .find(|&s| self.pool[s].desc == desc && busy_until[s] < first)
+            {
+                Some(s) => s,
+                None => {
+                    let reused = old_pool
+                        .iter_mut()
+                        .find(|p| p.as_ref().map(|p| p.desc == desc).unwrap_or(false))
+                        .and_then(|p| p.take());
+                    let pooled = match reused {
+                        Some(p) => p,
+                        None => {
+                            let texture = device.create_texture(&gpu::TextureDesc {
+                                name: self
+                                    .name
+                                    .as_ref()
+                                    .map(|n| format!("{}_transient_{}", n, self.pool.len())),
+                                format: desc.format,
+                                usage: desc.usage,
+                                dimension: desc.dimension,
+                                mip_levels: desc.mip_levels,
+                                memory: gpu::MemoryType::Device,
+                                layout: gpu::TextureLayout::General,
+                            })?;
+                            let view = texture.create_default_view()?;
+                            PooledTexture {
+                                desc,
+                                texture,
+                                view,
+                            }
+                        }
+                    };
+                    self.pool.push(pooled);
+                    busy_until.push(last);
+                    self.pool.len() - 1
+                }
+            };
+            busy_until[slot] = last;
+            textures.insert(
+                name.to_string(),
+                (
+                    self.pool[slot].texture.clone(),
+                    self.pool[slot].view.clone(),
+                ),
+            );
+        }
+
+        let mut buffers = HashMap::new();
+        for (name, resource) in &self.resources {
+            match resource {
+                GraphResource::Texture(t) => {
+                    textures.insert(name.clone(), (t.clone(), t.create_default_view()?));
+                }
+                GraphResource::Buffer(b) => {
+                    buffers.insert(name.clone(), b.clone());
+                }
+                GraphResource::Transient(_) => (),
+            }
+        }
+
+        self.compiled = GraphResources { textures, buffers };
+        self.order = Some(order);
+        Ok(())
+    }
+
+    /// Record the passes of the graph into the command buffer
+    pub fn record(
+        &mut self,
+        device: &gpu::Device,
+        command_buffer: &mut gpu::CommandBuffer,
+        one_time_submit: bool,
+    ) -> Result<(), gpu::Error> {
+        if self.order.is_none() {
+            self.compile(device)?;
+        }
+        let order = self.order.as_ref().unwrap();
+
+        let mut encoder = crate::CommandEncoder::new();
+        for &i in order {
+            (self.passes[i].record)(&mut encoder, &self.compiled)?;
+        }
+        encoder.record(command_buffer, one_time_submit)
+    }
+
+    /// Record the passes of the graph into the command buffer then submit
+    pub fn submit(
+        &mut self,
+        device: &gpu::Device,
+        command_buffer: &mut gpu::CommandBuffer,
+        one_time_submit: bool,
+    ) -> Result<(), gpu::Error> {
+        self.record(device, command_buffer, one_time_submit)?;
+        command_buffer.submit()
+    }
+}
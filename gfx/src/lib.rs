@@ -3,10 +3,20 @@
 //! Built on top of [`gpu`] to simplify various things
 //!
 
+pub mod cubemap;
 pub mod encoder;
+pub mod graph;
 pub mod mesh;
+pub mod msaa;
 pub mod pass;
+pub mod pingpong;
 pub mod prelude;
+pub mod profiler;
+pub mod readback;
+pub mod registry;
+pub mod resources;
+pub mod shadow;
+pub mod staging;
 pub mod storage;
 pub mod texture;
 pub mod uniform;
@@ -14,9 +24,35 @@ pub mod uniform;
 #[cfg(feature = "reflect")]
 pub mod reflect;
 
+#[cfg(all(feature = "reflect", feature = "spv"))]
+pub mod mipchain;
+
+#[cfg(all(feature = "reflect", feature = "spv"))]
+pub mod particles;
+
+#[cfg(feature = "image")]
+pub mod streaming;
+
+#[cfg(feature = "text")]
+pub mod text;
+
+#[cfg(feature = "ui")]
+pub mod ui;
+
+pub use cubemap::*;
 pub use encoder::CommandEncoder;
+pub use encoder::ParallelRecorder;
+pub use graph::*;
 pub use mesh::*;
+pub use msaa::*;
+pub use pingpong::*;
 pub use prelude::*;
+pub use profiler::*;
+pub use readback::*;
+pub use registry::*;
+pub use resources::*;
+pub use shadow::*;
+pub use staging::*;
 pub use storage::*;
 pub use texture::*;
 pub use uniform::*;
@@ -24,9 +60,29 @@ pub use uniform::*;
 #[cfg(feature = "reflect")]
 pub use reflect::*;
 
+#[cfg(all(feature = "reflect", feature = "spv"))]
+pub use mipchain::*;
+
+#[cfg(all(feature = "reflect", feature = "spv"))]
+pub use particles::*;
+
+#[cfg(feature = "image")]
+pub use streaming::*;
+
+#[cfg(feature = "text")]
+pub use text::*;
+
+#[cfg(feature = "ui")]
+pub use ui::*;
+
 pub use image;
 
+#[cfg(feature = "spv")]
+pub use spv;
+
 pub use gfx_derive::Vertex;
+pub use gfx_derive::VertexInputs;
+pub use gfx_derive::Std140;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Attachment<'a> {
@@ -0,0 +1,265 @@
+//! Meshes whose contents change every frame
+
+use super::Vertex;
+
+use std::ops::Range;
+
+/// A buffer that grows to fit its contents and only uploads the bytes that have changed
+#[derive(Debug)]
+struct DynamicBuffer {
+    usage: gpu::BufferUsage,
+    /// one buffer per copy, created when first written to
+    buffers: Vec<Option<gpu::Buffer>>,
+    /// the range of bytes of each copy that is out of date
+    dirty: Vec<Option<Range<usize>>>,
+    /// the current contents of the buffer
+    data: Vec<u8>,
+    name: Option<String>,
+}
+
+impl DynamicBuffer {
+    fn new(copies: usize, usage: gpu::BufferUsage, name: Option<String>) -> Self {
+        Self {
+            usage,
+            buffers: vec![None; copies],
+            dirty: vec![None; copies],
+            data: Vec::new(),
+            name,
+        }
+    }
+
+    /// Set the contents marking the bytes that differ from the previous contents as dirty in every copy
+    fn set(&mut self, data: &[u8]) {
+        let common = self.data.len().min(data.len());
+        let start = (0..common)
+            .find(|&i| self.data[i] != data[i])
+            .unwrap_or(common);
+        let end = if data.len() == self.data.len() {
+            (start..common)
+                .rev()
+                .find(|&i| self.data[i] != data[i])
+                .map(|i| i + 1)
+                .unwrap_or(start)
+        } else {
+            data.len()
+        };
+
+        if start < end {
+            for dirty in &mut self.dirty {
+                *dirty = Some(match dirty.take() {
+                    Some(r) => r.start.min(start)..r.end.max(end),
+                    None => start..end,
+                });
+            }
+        }
+
+        self.data.clear();
+        self.data.extend_from_slice(data);
+    }
+
+    /// Upload the dirty range of copy, growing the buffer if needed
+    fn flush(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        copy: usize,
+    ) -> Result<(), gpu::Error> {
+        let len = self.data.len();
+        if len == 0 {
+            self.dirty[copy] = None;
+            return Ok(());
+        }
+
+        if self.buffers[copy]
+            .as_ref()
+            .map(|b| b.size() < len as u64)
+            .unwrap_or(true)
+        {
+            self.buffers[copy] = Some(device.create_buffer(&gpu::BufferDesc {
+                name: self.name.as_ref().map(|n| format!("{}_{}", n, copy)),
+                size: (len as u64).next_power_of_two(),
+                usage: gpu::BufferUsage::COPY_SRC | gpu::BufferUsage::COPY_DST | self.usage,
+                memory: gpu::MemoryType::Device,
+            })?);
+            self.dirty[copy] = Some(0..len);
+        }
+
+        let range = match self.dirty[copy].take() {
+            Some(r) => r.start.min(len)..r.end.min(len),
+            None => return Ok(()),
+        };
+        if range.is_empty() {
+            return Ok(());
+        }
+
+        let staging_buffer = device.create_buffer(&gpu::BufferDesc {
+            size: range.len() as u64,
+            usage: gpu::BufferUsage::COPY_SRC,
+            memory: gpu::MemoryType::Host,
+            name: None,
+        })?;
+        staging_buffer
+            .slice_ref(..)
+            .write(&self.data[range.clone()])?;
+        encoder.copy_buffer_to_buffer(
+            staging_buffer.into_slice(..),
+            self.buffers[copy]
+                .as_ref()
+                .unwrap()
+                .slice_owned((range.start as u64)..(range.end as u64)),
+        );
+        Ok(())
+    }
+
+    fn buffer(&self, copy: usize) -> &gpu::Buffer {
+        self.buffers[copy].as_ref().unwrap()
+    }
+}
+
+/// A mesh that can be updated after creation
+///
+/// Vertex and index buffers grow as needed and only the parts of the data that have changed
+/// since the last update are uploaded.
+/// If double buffered the mesh alternates between two sets of buffers on each update so the buffers
+/// being drawn by the previous frame aren't written to while it may still be in flight
+#[derive(Debug)]
+pub struct DynamicMesh<V: Vertex> {
+    vertices: DynamicBuffer,
+    indices: DynamicBuffer,
+    vertex_count: u32,
+    /// None if the mesh was last updated without indices
+    index_count: Option<u32>,
+    /// the index of the buffers that were last written to
+    copy: usize,
+    _vertex_marker: std::marker::PhantomData<V>,
+}
+
+impl<V: Vertex> DynamicMesh<V> {
+    /// Create a new empty DynamicMesh
+    ///
+    /// Buffers are created on the first update
+    pub fn new(double_buffered: bool, name: Option<&str>) -> Self {
+        let copies = if double_buffered { 2 } else { 1 };
+        Self {
+            vertices: DynamicBuffer::new(
+                copies,
+                gpu::BufferUsage::VERTEX,
+                name.map(|n| format!("{}_vertex_buffer", n)),
+            ),
+            indices: DynamicBuffer::new(
+                copies,
+                gpu::BufferUsage::INDEX,
+                name.map(|n| format!("{}_index_buffer", n)),
+            ),
+            vertex_count: 0,
+            index_count: None,
+            copy: 0,
+            _vertex_marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Set the vertices of the mesh, the mesh will be drawn without indices
+    ///
+    /// The update won't be visible until the encoder is submitted
+    pub fn update(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        vertices: &[V],
+    ) -> Result<(), gpu::Error> {
+        self.write(encoder, device, vertices, None)
+    }
+
+    /// Set the vertices and indices of the mesh
+    ///
+    /// The update won't be visible until the encoder is submitted
+    pub fn update_indexed(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        vertices: &[V],
+        indices: &[u32],
+    ) -> Result<(), gpu::Error> {
+        self.write(encoder, device, vertices, Some(indices))
+    }
+
+    fn write(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        vertices: &[V],
+        indices: Option<&[u32]>,
+    ) -> Result<(), gpu::Error> {
+        self.copy = (self.copy + 1) % self.vertices.buffers.len();
+
+        self.vertices.set(bytemuck::cast_slice(vertices));
+        self.vertices.flush(encoder, device, self.copy)?;
+        self.vertex_count = vertices.len() as u32;
+
+        if let Some(indices) = indices {
+            self.indices.set(bytemuck::cast_slice(indices));
+            self.indices.flush(encoder, device, self.copy)?;
+            self.index_count = Some(indices.len() as u32);
+        } else {
+            self.index_count = None;
+        }
+        Ok(())
+    }
+
+    /// The number of vertices set by the last update
+    pub fn vertex_count(&self) -> u32 {
+        self.vertex_count
+    }
+
+    /// The number of indices set by the last update or None if the last update had no indices
+    pub fn index_count(&self) -> Option<u32> {
+        self.index_count
+    }
+
+    /// The vertex buffer written to by the last update
+    pub fn vertex_buffer(&self) -> Option<&gpu::Buffer> {
+        self.vertices.buffers[self.copy].as_ref()
+    }
+
+    /// The index buffer written to by the last update
+    pub fn index_buffer(&self) -> Option<&gpu::Buffer> {
+        self.index_count
+            .and(self.indices.buffers[self.copy].as_ref())
+    }
+
+    /// Draw self by reference
+    ///
+    /// Does nothing if the mesh is empty
+    pub fn draw_ref<'a>(&'a self, pass: &mut dyn crate::GraphicsPass<'a>) {
+        self.draw_instanced_ref(pass, 0, 1)
+    }
+
+    /// Draw self by reference
+    ///
+    /// The instance buffer needs to be set first
+    pub fn draw_instanced_ref<'a>(
+        &'a self,
+        pass: &mut dyn crate::GraphicsPass<'a>,
+        first_instance: u32,
+        instance_count: u32,
+    ) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        let vertex_size = self.vertices.data.len() as u64;
+        pass.bind_vertex_buffer(self.vertices.buffer(self.copy).slice_ref(0..vertex_size), 0);
+
+        match self.index_count {
+            Some(0) => (),
+            Some(index_count) => {
+                let index_size = self.indices.data.len() as u64;
+                pass.bind_index_buffer(
+                    self.indices.buffer(self.copy).slice_ref(0..index_size),
+                    gpu::IndexType::U32,
+                );
+                pass.draw_indexed(0, index_count, first_instance, instance_count, 0);
+            }
+            None => pass.draw(0, self.vertex_count, first_instance, instance_count),
+        }
+    }
+}
@@ -1,11 +1,14 @@
 //! Mesh types and Vertex traits
 //!
 //! [`IndexedMesh`] and [`BasicMesh`] wrap vertex (and index buffers) and statically type the vertex that the mesh has as well as drawing operations
+//! [`DynamicMesh`] can be updated every frame for procedural geometry
 //!
 //! The [`Vertex`] trait should be implemented by vertices, It allows different types of vertices to be used with the same pipeine as long as they have the correct attributes
 
+pub mod dynamic;
 pub mod vertex;
 
+pub use dynamic::*;
 pub use vertex::*;
 
 /// A mesh with indexing
@@ -17,7 +20,7 @@ pub struct Mesh<V: Vertex> {
     pub vertex_buffer: gpu::Buffer,
     /// (index buffer, index_count), buffer usage: COPY_SRC COPY_DST INDEX
     pub indices: Option<(gpu::Buffer, u32)>,
-    /// (indirect_buffer, draw_count), buffer usage: COPY_SRC COPY_DST STORAGE UNIFORM
+    /// (indirect_buffer, draw_count), buffer usage: COPY_SRC COPY_DST STORAGE UNIFORM INDIRECT
     pub indirect: Option<(gpu::Buffer, u32)>,
 
     /// Marks the mesh so that the vertex state can be infered
@@ -85,6 +88,7 @@ impl<V: Vertex> Mesh<V> {
                 | gpu::BufferUsage::COPY_DST
                 | gpu::BufferUsage::STORAGE
                 | gpu::BufferUsage::UNIFORM
+                | gpu::BufferUsage::INDIRECT
                 | indirect_usage,
             memory: gpu::MemoryType::Device,
             name: indirect_name,
@@ -357,6 +361,52 @@ impl<V: Vertex> Mesh<V> {
         }
     }
 
+    /// Draw self by reference with draw commands read from the indirect buffer
+    ///
+    /// The buffer should contain [`gpu::DrawIndexedIndirectCommand`] if the mesh is indexed
+    /// and [`gpu::DrawIndirectCommand`] otherwise, written for example by a culling compute pass.
+    /// If the mesh has an indirect draw buffer that will be ignored
+    pub fn draw_indirect_ref<'a>(
+        &'a self,
+        pass: &mut dyn crate::GraphicsPass<'a>,
+        buffer: &'a gpu::Buffer,
+        offset: u64,
+        draw_count: u32,
+        stride: u32,
+    ) {
+        pass.bind_vertex_buffer(self.vertex_buffer.slice_ref(..), 0);
+
+        if let Some((index_buffer, _)) = &self.indices {
+            pass.bind_index_buffer(index_buffer.slice_ref(..), gpu::IndexType::U32);
+            pass.draw_indexed_indirect_ref(buffer, offset, draw_count, stride);
+        } else {
+            pass.draw_indirect_ref(buffer, offset, draw_count, stride);
+        }
+    }
+
+    /// Draw self by clone with draw commands read from the indirect buffer
+    ///
+    /// The buffer should contain [`gpu::DrawIndexedIndirectCommand`] if the mesh is indexed
+    /// and [`gpu::DrawIndirectCommand`] otherwise, written for example by a culling compute pass.
+    /// If the mesh has an indirect draw buffer that will be ignored
+    pub fn draw_indirect_owned<'a>(
+        self,
+        pass: &mut dyn crate::GraphicsPass<'a>,
+        buffer: gpu::Buffer,
+        offset: u64,
+        draw_count: u32,
+        stride: u32,
+    ) {
+        pass.bind_vertex_buffer(self.vertex_buffer.slice_owned(..), 0);
+
+        if let Some((index_buffer, _)) = &self.indices {
+            pass.bind_index_buffer(index_buffer.slice_owned(..), gpu::IndexType::U32);
+            pass.draw_indexed_indirect_owned(buffer, offset, draw_count, stride);
+        } else {
+            pass.draw_indirect_owned(buffer, offset, draw_count, stride);
+        }
+    }
+
     /// Draw self by reference
     ///
     /// The instance buffer needs to be set first
@@ -11,3 +11,43 @@ impl Vertex for () {
         None
     }
 }
+
+/// Vertices that can declare matching inputs on a [`spv::Builder`]
+///
+/// Derive with `#[derive(gfx::Vertex, gfx::VertexInputs)]` which generates a struct with an input for each field
+#[cfg(feature = "spv")]
+pub trait VertexInputs: Vertex {
+    /// struct of input handles, one per field of the vertex
+    type Inputs;
+
+    /// declare an input named after each field at the next free location
+    fn inputs(b: &spv::Builder) -> Self::Inputs;
+}
+
+/// Extension to [`spv::Builder`] for declaring inputs from vertex types
+#[cfg(feature = "spv")]
+pub trait VertexBuilderExt {
+    /// Declare inputs matching the vertex V
+    ///
+    /// The inputs have the same names as the fields so always match V when creating pipelines from the builder
+    /// ```no_run
+    /// #[derive(Clone, Copy, gfx::Vertex, gfx::VertexInputs)]
+    /// struct MyVertex {
+    ///     pos: glam::Vec3,
+    ///     uv: glam::Vec2,
+    /// }
+    ///
+    /// let inputs = b.inputs_from_vertex::<MyVertex>();
+    /// b.entry(spv::Stage::Vertex, "main", || {
+    ///     let pos = inputs.pos.load();
+    /// });
+    /// ```
+    fn inputs_from_vertex<V: VertexInputs>(&self) -> V::Inputs;
+}
+
+#[cfg(feature = "spv")]
+impl VertexBuilderExt for spv::Builder {
+    fn inputs_from_vertex<V: VertexInputs>(&self) -> V::Inputs {
+        V::inputs(self)
+    }
+}
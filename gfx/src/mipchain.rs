@@ -0,0 +1,417 @@
+//! Chains of progressively smaller textures for blurs and other post effects
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::pass::GraphicsPass;
+
+/// An error from creating or using a [`MipChain`]
+#[derive(Debug)]
+pub enum MipChainError {
+    /// An error from the gpu
+    Gpu(gpu::Error),
+    /// An error creating the filter pipelines
+    Reflected(crate::ReflectedError),
+    /// An error creating a bundle to sample a texture
+    Bundle(crate::BundleBuildError),
+}
+
+impl std::fmt::Display for MipChainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gpu(e) => writeln!(f, "{}", e),
+            Self::Reflected(e) => writeln!(f, "{}", e),
+            Self::Bundle(e) => writeln!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for MipChainError {}
+
+impl From<gpu::Error> for MipChainError {
+    fn from(e: gpu::Error) -> Self {
+        Self::Gpu(e)
+    }
+}
+
+impl From<crate::ReflectedError> for MipChainError {
+    fn from(e: crate::ReflectedError) -> Self {
+        Self::Reflected(e)
+    }
+}
+
+impl From<crate::BundleBuildError> for MipChainError {
+    fn from(e: crate::BundleBuildError) -> Self {
+        Self::Bundle(e)
+    }
+}
+
+impl From<crate::SetResourceError> for MipChainError {
+    fn from(e: crate::SetResourceError) -> Self {
+        Self::Bundle(e.into())
+    }
+}
+
+/// The filter used to move between levels of a [`MipChain`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MipFilter {
+    /// Average of 4 bilinear taps, cheapest but can show blocky artifacts
+    Box,
+    /// 3x3 binomial approximation of a gaussian
+    Gaussian,
+    /// Dual kawase filter, 5 taps when downsampling and 8 when upsampling
+    Kawase,
+}
+
+impl MipFilter {
+    /// The (offset in texels, weight) of each tap of the filter
+    fn taps(&self, up: bool) -> Vec<([f32; 2], f32)> {
+        match self {
+            MipFilter::Box => vec![
+                ([-1.0, -1.0], 0.25),
+                ([1.0, -1.0], 0.25),
+                ([-1.0, 1.0], 0.25),
+                ([1.0, 1.0], 0.25),
+            ],
+            MipFilter::Gaussian => {
+                let w = [0.25, 0.5, 0.25];
+                let mut taps = Vec::new();
+                for y in 0..3 {
+                    for x in 0..3 {
+                        taps.push(([x as f32 - 1.0, y as f32 - 1.0], w[x] * w[y]));
+                    }
+                }
+                taps
+            }
+            MipFilter::Kawase if !up => vec![
+                ([0.0, 0.0], 0.5),
+                ([-1.0, -1.0], 0.125),
+                ([1.0, -1.0], 0.125),
+                ([-1.0, 1.0], 0.125),
+                ([1.0, 1.0], 0.125),
+            ],
+            MipFilter::Kawase => vec![
+                ([-1.0, 0.0], 1.0 / 12.0),
+                ([1.0, 0.0], 1.0 / 12.0),
+                ([0.0, -1.0], 1.0 / 12.0),
+                ([0.0, 1.0], 1.0 / 12.0),
+                ([-0.5, -0.5], 2.0 / 12.0),
+                ([0.5, -0.5], 2.0 / 12.0),
+                ([-0.5, 0.5], 2.0 / 12.0),
+                ([0.5, 0.5], 2.0 / 12.0),
+            ],
+        }
+    }
+}
+
+/// Push constants of the filter shaders
+#[derive(Debug, Clone, Copy, spv::AsStructType)]
+#[repr(C)]
+struct MipChainParams {
+    texel_size: glam::Vec2,
+    radius: f32,
+}
+
+/// A chain of textures each half the size of the last
+///
+/// [`MipChain::downsample`] filters a texture down the chain and [`MipChain::upsample`]
+/// filters back up the chain adding each level onto the one above it and finally into a target.
+/// Downsampling then upsampling gives a wide blur cheaply as used for bloom and similar effects
+pub struct MipChain {
+    /// the levels of the chain, level 0 is half the size of the source
+    pub levels: Vec<crate::GTexture2D>,
+    pub(crate) level_bundles: Vec<crate::Bundle>,
+    pub(crate) source_bundles: HashMap<u64, crate::Bundle>,
+    pub(crate) downsample: crate::ReflectedGraphics,
+    pub(crate) upsample: crate::ReflectedGraphics,
+    pub(crate) sampler: gpu::Sampler,
+    pub(crate) filter: MipFilter,
+    pub(crate) name: Option<String>,
+}
+
+impl std::fmt::Debug for MipChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MipChain")
+            .field("levels", &self.levels.len())
+            .field("filter", &self.filter)
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl MipChain {
+    /// Create a new MipChain for sources of width x height
+    ///
+    /// The number of levels is clamped so that the smallest level is at least 1 x 1
+    pub fn new(
+        device: &gpu::Device,
+        width: gpu::Size,
+        height: gpu::Size,
+        levels: u32,
+        format: gpu::Format,
+        filter: MipFilter,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, MipChainError> {
+        let downsample = crate::ReflectedGraphics::fullscreen(
+            device,
+            &Self::fragment(filter, false),
+            &[gpu::BlendState::REPLACE],
+            cache.clone(),
+            name.map(|n| format!("{}_downsample", n)).as_deref(),
+        )?;
+        let upsample = crate::ReflectedGraphics::fullscreen(
+            device,
+            &Self::fragment(filter, true),
+            &[gpu::BlendState::ADD],
+            cache,
+            name.map(|n| format!("{}_upsample", n)).as_deref(),
+        )?;
+        let sampler = device.create_sampler(&gpu::SamplerDesc::new(
+            gpu::FilterMode::Linear,
+            gpu::WrapMode::ClampToEdge,
+            name.map(|n| format!("{}_sampler", n)),
+        ))?;
+
+        let mut chain = Self {
+            levels: Vec::new(),
+            level_bundles: Vec::new(),
+            source_bundles: HashMap::new(),
+            downsample,
+            upsample,
+            sampler,
+            filter,
+            name: name.map(|n| n.to_string()),
+        };
+        chain.create_levels(device, width, height, levels, format)?;
+        Ok(chain)
+    }
+
+    fn fragment(filter: MipFilter, up: bool) -> spv::Builder {
+        let builder = spv::Builder::new();
+        {
+            let b = &builder;
+            let in_uv = b.in_vec2(0, "in_uv");
+            let out_color = b.out_vec4(0, "out_color");
+
+            let u_texture = b.texture2d(0, 0, Some("u_texture"));
+            let u_sampler = b.sampler(0, 1, Some("u_sampler"));
+            let params = b.push_constants::<SpvMipChainParams>(Some("params"));
+
+            b.entry(spv::Stage::Fragment, "main", || {
+                let uv = in_uv.load();
+                let params = params.load();
+                let step = params.texel_size() * params.radius();
+                let combined = spv::combine(&u_texture, u_sampler);
+
+                let mut taps = filter.taps(up).into_iter().map(|(offset, weight)| {
+                    let coord = uv + step * glam::Vec2::from(offset);
+                    spv::sample(&combined, coord) * weight
+                });
+                let first = taps.next().unwrap();
+                out_color.store(taps.fold(first, |sum, tap| sum + tap));
+            });
+        }
+        builder
+    }
+
+    fn create_levels(
+        &mut self,
+        device: &gpu::Device,
+        width: gpu::Size,
+        height: gpu::Size,
+        levels: u32,
+        format: gpu::Format,
+    ) -> Result<(), MipChainError> {
+        let levels = levels.min(crate::full_mip_levels(width, height) - 1).max(1);
+        self.levels = (0..levels)
+            .map(|i| {
+                crate::GTexture2D::new(
+                    device,
+                    (width >> (i + 1)).max(1),
+                    (height >> (i + 1)).max(1),
+                    gpu::Samples::S1,
+                    gpu::TextureUsage::COLOR_OUTPUT | gpu::TextureUsage::SAMPLED,
+                    1,
+                    format,
+                    self.name
+                        .as_ref()
+                        .map(|n| format!("{}_level_{}", n, i))
+                        .as_deref(),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.level_bundles = self
+            .levels
+            .iter()
+            .map(|level| self.bundle(device, &level.view))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.source_bundles.clear();
+        Ok(())
+    }
+
+    fn bundle(
+        &self,
+        device: &gpu::Device,
+        view: &gpu::TextureView,
+    ) -> Result<crate::Bundle, MipChainError> {
+        Ok(self
+            .downsample
+            .bundle()
+            .unwrap()
+            .set_resource("u_texture", view)?
+            .set_resource("u_sampler", &self.sampler)?
+            .build(device)?)
+    }
+
+    fn source_bundle(
+        &mut self,
+        device: &gpu::Device,
+        view: &gpu::TextureView,
+    ) -> Result<crate::Bundle, MipChainError> {
+        if let Some(b) = self.source_bundles.get(&view.id()) {
+            return Ok(b.clone());
+        }
+        let b = self.bundle(device, view)?;
+        self.source_bundles.insert(view.id(), b.clone());
+        Ok(b)
+    }
+
+    /// Recreate the levels if the size of the sources has changed
+    pub fn resize(
+        &mut self,
+        device: &gpu::Device,
+        width: gpu::Size,
+        height: gpu::Size,
+    ) -> Result<(), MipChainError> {
+        let first = &self.levels[0];
+        if first.width() == (width >> 1).max(1) && first.height() == (height >> 1).max(1) {
+            return Ok(());
+        }
+        let levels = self.levels.len() as u32;
+        let format = first.format();
+        self.create_levels(device, width, height, levels, format)
+    }
+
+    fn filter_pass(
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        graphics: &crate::ReflectedGraphics,
+        bundle: crate::Bundle,
+        src: &gpu::TextureView,
+        dst: &gpu::TextureView,
+        load: gpu::LoadOp,
+        radius: f32,
+    ) -> Result<(), gpu::Error> {
+        let mut pass = encoder.graphics_pass_reflected::<()>(
+            device,
+            &[crate::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Owned(dst.clone()),
+                    gpu::ClearValue::ColorFloat([0.0; 4]),
+                ),
+                load,
+                store: gpu::StoreOp::Store,
+            }],
+            &[],
+            None,
+            graphics,
+        )?;
+        pass.set_bundle_owned(bundle);
+        let extent = src.extent();
+        pass.push_vec2(
+            "texel_size",
+            [1.0 / extent.width as f32, 1.0 / extent.height as f32],
+        );
+        pass.push_f32("radius", radius);
+        pass.draw(0, 3, 0, 1);
+        pass.finish();
+        Ok(())
+    }
+
+    /// Filter src down through each level of the chain
+    ///
+    /// src should be twice the size of the first level
+    pub fn downsample(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        src: &gpu::TextureView,
+    ) -> Result<(), MipChainError> {
+        let bundle = self.source_bundle(device, src)?;
+        let mut src = src.clone();
+        for (i, level) in self.levels.iter().enumerate() {
+            let bundle = if i == 0 {
+                bundle.clone()
+            } else {
+                self.level_bundles[i - 1].clone()
+            };
+            Self::filter_pass(
+                encoder,
+                device,
+                &self.downsample,
+                bundle,
+                &src,
+                &level.view,
+                gpu::LoadOp::DontCare,
+                1.0,
+            )?;
+            src = level.view.clone();
+        }
+        Ok(())
+    }
+
+    /// Filter back up the chain adding each level onto the one above and finally onto dst
+    ///
+    /// If clear_dst is true then dst is cleared before the chain is added, radius scales the filter offsets
+    pub fn upsample(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        dst: &gpu::TextureView,
+        clear_dst: bool,
+        radius: f32,
+    ) -> Result<(), MipChainError> {
+        for i in (1..self.levels.len()).rev() {
+            Self::filter_pass(
+                encoder,
+                device,
+                &self.upsample,
+                self.level_bundles[i].clone(),
+                &self.levels[i].view,
+                &self.levels[i - 1].view,
+                gpu::LoadOp::Load,
+                radius,
+            )?;
+        }
+        Self::filter_pass(
+            encoder,
+            device,
+            &self.upsample,
+            self.level_bundles[0].clone(),
+            &self.levels[0].view,
+            dst,
+            if clear_dst {
+                gpu::LoadOp::Clear
+            } else {
+                gpu::LoadOp::Load
+            },
+            radius,
+        )?;
+        Ok(())
+    }
+
+    /// The filter used between levels
+    pub fn filter(&self) -> MipFilter {
+        self.filter
+    }
+
+    /// Drop the bundles cached for source textures
+    ///
+    /// Bundles keep the textures they reference alive so call when a source texture is no longer used
+    pub fn clear(&mut self) {
+        self.source_bundles.clear();
+        self.downsample.clear();
+        self.upsample.clear();
+    }
+}
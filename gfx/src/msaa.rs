@@ -0,0 +1,141 @@
+//! Multisampled render targets
+
+/// Owns multisampled color and depth textures to render into before resolving
+///
+/// Pass to [`crate::CommandEncoder::graphics_pass_reflected_msaa`] along with single sampled
+/// attachments to resolve into so that a pass can be multisampled without managing the
+/// intermediate textures. Call [`MsaaTargets::resize`] when the resolve targets change size
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MsaaTargets {
+    /// the multisampled color textures in the order of the resolve attachments
+    pub colors: Vec<crate::GTexture2D>,
+    /// the multisampled depth texture, this is never resolved
+    pub depth: Option<crate::GTexture2D>,
+    pub(crate) samples: gpu::Samples,
+    pub(crate) name: Option<String>,
+}
+
+impl MsaaTargets {
+    /// Create new multisampled targets with one color texture for each format
+    ///
+    /// Use [`MsaaTargets::max_samples`] to choose a sample count the formats support
+    pub fn new(
+        device: &gpu::Device,
+        width: gpu::Size,
+        height: gpu::Size,
+        samples: gpu::Samples,
+        color_formats: &[gpu::Format],
+        depth_format: Option<gpu::Format>,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        if samples == gpu::Samples::S1 {
+            panic!("ERROR: Attempt to create MsaaTargets with Samples::S1");
+        }
+
+        let colors = color_formats
+            .iter()
+            .enumerate()
+            .map(|(i, &format)| {
+                crate::GTexture2D::new(
+                    device,
+                    width,
+                    height,
+                    samples,
+                    gpu::TextureUsage::COLOR_OUTPUT | gpu::TextureUsage::TRANSIENT,
+                    1,
+                    format,
+                    name.map(|n| format!("{}_color_{}", n, i)).as_deref(),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let depth = depth_format
+            .map(|format| {
+                crate::GTexture2D::new(
+                    device,
+                    width,
+                    height,
+                    samples,
+                    gpu::TextureUsage::DEPTH_OUTPUT | gpu::TextureUsage::TRANSIENT,
+                    1,
+                    format,
+                    name.map(|n| format!("{}_depth", n)).as_deref(),
+                )
+            })
+            .transpose()?;
+
+        Ok(Self {
+            colors,
+            depth,
+            samples,
+            name: name.map(|n| n.to_string()),
+        })
+    }
+
+    /// The highest number of samples that targets of all the formats can be created with
+    ///
+    /// Returns [`gpu::Samples::S1`] if the formats don't support multisampling
+    pub fn max_samples(
+        device: &gpu::Device,
+        color_formats: &[gpu::Format],
+        depth_format: Option<gpu::Format>,
+    ) -> gpu::Samples {
+        let colors = color_formats.iter().map(|&f| {
+            device.max_samples_for(f, gpu::TextureUsage::COLOR_OUTPUT | gpu::TextureUsage::TRANSIENT)
+        });
+        let depth = depth_format.map(|f| {
+            device.max_samples_for(f, gpu::TextureUsage::DEPTH_OUTPUT | gpu::TextureUsage::TRANSIENT)
+        });
+        colors
+            .chain(depth)
+            .min_by_key(|s| *s as u32)
+            .unwrap_or(gpu::Samples::S1)
+    }
+
+    /// Recreate the textures if the size is different from the current size
+    pub fn resize(
+        &mut self,
+        device: &gpu::Device,
+        width: gpu::Size,
+        height: gpu::Size,
+    ) -> Result<(), gpu::Error> {
+        if width == self.width() && height == self.height() {
+            return Ok(());
+        }
+        let color_formats = self.colors.iter().map(|c| c.format()).collect::<Vec<_>>();
+        let depth_format = self.depth.as_ref().map(|d| d.format());
+        *self = Self::new(
+            device,
+            width,
+            height,
+            self.samples,
+            &color_formats,
+            depth_format,
+            self.name.as_deref(),
+        )?;
+        Ok(())
+    }
+
+    /// The number of samples of the targets
+    pub fn samples(&self) -> gpu::Samples {
+        self.samples
+    }
+
+    /// The width of the targets
+    pub fn width(&self) -> gpu::Size {
+        self.colors
+            .first()
+            .or(self.depth.as_ref())
+            .map(|t| t.width())
+            .unwrap_or(0)
+    }
+
+    /// The height of the targets
+    pub fn height(&self) -> gpu::Size {
+        self.colors
+            .first()
+            .or(self.depth.as_ref())
+            .map(|t| t.height())
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,680 @@
+//! Gpu particle systems simulated in compute shaders and drawn as instanced billboards
+//!
+//! Particles are spawned on the cpu by [`Emitter`]s into a ring buffer of particles on the gpu,
+//! [`ParticleSystem::update`] integrates them in a compute pass and optionally sorts them
+//! back to front and [`ParticleSystem::draw`] draws a camera facing quad for each live particle
+
+use std::borrow::Cow;
+
+use crate::pass::ComputePass;
+use crate::pass::GraphicsPass;
+
+/// An error from creating or using a [`ParticleSystem`]
+#[derive(Debug)]
+pub enum ParticlesError {
+    /// An error from the gpu
+    Gpu(gpu::Error),
+    /// An error creating the simulation or render pipelines
+    Reflected(crate::ReflectedError),
+    /// An error creating a bundle for the particle buffers
+    Bundle(crate::BundleBuildError),
+}
+
+impl std::fmt::Display for ParticlesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gpu(e) => writeln!(f, "{}", e),
+            Self::Reflected(e) => writeln!(f, "{}", e),
+            Self::Bundle(e) => writeln!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParticlesError {}
+
+impl From<gpu::Error> for ParticlesError {
+    fn from(e: gpu::Error) -> Self {
+        Self::Gpu(e)
+    }
+}
+
+impl From<crate::ReflectedError> for ParticlesError {
+    fn from(e: crate::ReflectedError) -> Self {
+        Self::Reflected(e)
+    }
+}
+
+impl From<crate::BundleBuildError> for ParticlesError {
+    fn from(e: crate::BundleBuildError) -> Self {
+        Self::Bundle(e)
+    }
+}
+
+impl From<crate::SetResourceError> for ParticlesError {
+    fn from(e: crate::SetResourceError) -> Self {
+        Self::Bundle(e.into())
+    }
+}
+
+/// A single particle as stored on the gpu
+#[derive(Debug, Default, Clone, Copy, PartialEq, spv::AsStructType)]
+#[repr(C)]
+pub struct Particle {
+    /// xyz world position, w remaining life in seconds, dead when <= 0
+    pub position: glam::Vec4,
+    /// xyz velocity, w size of the billboard in world units
+    pub velocity: glam::Vec4,
+    /// color multiplied with the billboard
+    pub color: glam::Vec4,
+}
+
+unsafe impl bytemuck::Pod for Particle {}
+unsafe impl bytemuck::Zeroable for Particle {}
+
+/// Push constants of the simulation shader
+#[derive(Debug, Clone, Copy, spv::AsStructType)]
+#[repr(C)]
+struct SimulateParams {
+    gravity: glam::Vec4,
+    dt: f32,
+    count: i32,
+}
+
+/// Push constants of the shader writing sort keys
+#[derive(Debug, Clone, Copy, spv::AsStructType)]
+#[repr(C)]
+struct KeyParams {
+    camera: glam::Vec4,
+    count: i32,
+}
+
+/// Push constants of one step of the bitonic sort
+#[derive(Debug, Clone, Copy, spv::AsStructType)]
+#[repr(C)]
+struct SortParams {
+    j: i32,
+    k: i32,
+}
+
+/// Push constants of the billboard vertex shader
+#[derive(Debug, Clone, Copy, spv::AsStructType)]
+#[repr(C)]
+struct RenderParams {
+    view_projection: glam::Mat4,
+    right: glam::Vec4,
+    up: glam::Vec4,
+    count: i32,
+}
+
+/// Workgroup size of the compute shaders
+const GROUP_SIZE: u32 = 64;
+
+/// Key given to dead particles and padding so they sort after live particles
+const DEAD_KEY: f32 = 1.0e30;
+
+/// Max bytes written by one update buffer command
+const MAX_UPDATE_SIZE: usize = 65536;
+
+/// How particles are ordered before drawing
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ParticleSort {
+    /// Draw in the order they are stored, fine for additive or opaque blending
+    None,
+    /// Bitonic sort by distance from the camera each update so alpha blending composites correctly
+    BackToFront,
+}
+
+/// Describes how particles are spawned
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Emitter {
+    /// world position particles are spawned at
+    pub position: glam::Vec3,
+    /// initial velocity of spawned particles
+    pub velocity: glam::Vec3,
+    /// max length of a random velocity added to each particle
+    pub spread: f32,
+    /// life of spawned particles in seconds
+    pub lifetime: f32,
+    /// fraction of the lifetime each particle can randomly lose
+    pub lifetime_variance: f32,
+    /// particles spawned per second
+    pub rate: f32,
+    /// size of the billboard in world units
+    pub size: f32,
+    /// color of spawned particles
+    pub color: glam::Vec4,
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        Self {
+            position: glam::Vec3::ZERO,
+            velocity: glam::Vec3::Y,
+            spread: 0.5,
+            lifetime: 2.0,
+            lifetime_variance: 0.25,
+            rate: 100.0,
+            size: 0.1,
+            color: glam::Vec4::ONE,
+        }
+    }
+}
+
+/// A pool of particles on the gpu
+///
+/// Particles are spawned into a ring buffer so when the pool is full the oldest particles are replaced
+pub struct ParticleSystem {
+    /// emitters that spawn particles each update
+    pub emitters: Vec<Emitter>,
+    /// acceleration applied to every particle
+    pub gravity: glam::Vec3,
+    pub(crate) particles: gpu::Buffer,
+    pub(crate) keys: gpu::Buffer,
+    pub(crate) capacity: u32,
+    pub(crate) padded: u32,
+    pub(crate) cursor: u32,
+    pub(crate) accumulators: Vec<f32>,
+    pub(crate) pending: Vec<(u32, Particle)>,
+    pub(crate) rng: u32,
+    pub(crate) sort: ParticleSort,
+    pub(crate) simulate: crate::ReflectedCompute,
+    pub(crate) key: crate::ReflectedCompute,
+    pub(crate) bitonic: crate::ReflectedCompute,
+    pub(crate) render: crate::ReflectedGraphics,
+    pub(crate) simulate_bundle: crate::Bundle,
+    pub(crate) key_bundle: crate::Bundle,
+    pub(crate) bitonic_bundle: crate::Bundle,
+    pub(crate) render_bundle: crate::Bundle,
+    pub(crate) name: Option<String>,
+}
+
+impl std::fmt::Debug for ParticleSystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParticleSystem")
+            .field("capacity", &self.capacity)
+            .field("emitters", &self.emitters.len())
+            .field("sort", &self.sort)
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl ParticleSystem {
+    /// Create a new ParticleSystem holding up to capacity particles
+    ///
+    /// blend is used when drawing, the pipeline tests against but doesn't write to a depth attachment if one is given
+    pub fn new(
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        capacity: u32,
+        sort: ParticleSort,
+        blend: gpu::BlendState,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, ParticlesError> {
+        let capacity = capacity.max(1);
+        let padded = capacity.next_power_of_two().max(GROUP_SIZE);
+
+        let particles = device.create_buffer(&gpu::BufferDesc {
+            name: name.map(|n| format!("{}_particles", n)),
+            size: (capacity as usize * std::mem::size_of::<Particle>()) as u64,
+            usage: gpu::BufferUsage::STORAGE | gpu::BufferUsage::COPY_DST,
+            memory: gpu::MemoryType::Device,
+        })?;
+        let keys = device.create_buffer(&gpu::BufferDesc {
+            name: name.map(|n| format!("{}_keys", n)),
+            size: (padded as usize * std::mem::size_of::<glam::Vec2>()) as u64,
+            usage: gpu::BufferUsage::STORAGE | gpu::BufferUsage::COPY_DST,
+            memory: gpu::MemoryType::Device,
+        })?;
+
+        // all particles start dead and keys in storage order for when sorting is disabled
+        encoder.fill_buffer(particles.slice_owned(..), 0);
+        let identity = (0..padded)
+            .map(|i| glam::vec2(DEAD_KEY, i as f32))
+            .collect::<Vec<_>>();
+        for (i, chunk) in bytemuck::cast_slice::<_, u8>(&identity)
+            .chunks(MAX_UPDATE_SIZE)
+            .enumerate()
+        {
+            encoder.update_buffer_owned(
+                keys.clone(),
+                (i * MAX_UPDATE_SIZE) as u64,
+                chunk.to_vec(),
+            );
+        }
+
+        let simulate = crate::ReflectedCompute::from_builder(
+            device,
+            &Self::simulate_compute(),
+            cache.clone(),
+            name.map(|n| format!("{}_simulate", n)).as_deref(),
+        )?;
+        let key = crate::ReflectedCompute::from_builder(
+            device,
+            &Self::key_compute(),
+            cache.clone(),
+            name.map(|n| format!("{}_key", n)).as_deref(),
+        )?;
+        let bitonic = crate::ReflectedCompute::from_builder(
+            device,
+            &Self::bitonic_compute(),
+            cache.clone(),
+            name.map(|n| format!("{}_sort", n)).as_deref(),
+        )?;
+        let render = crate::ReflectedGraphics::from_builders(
+            device,
+            &Self::billboard_vertex(),
+            None,
+            Some(&Self::billboard_fragment()),
+            gpu::Rasterizer::default(),
+            &[blend],
+            Some(gpu::DepthStencilState::depth(
+                true,
+                false,
+                gpu::CompareOp::LessEqual,
+            )),
+            cache,
+            name.map(|n| format!("{}_render", n)).as_deref(),
+        )?;
+
+        let simulate_bundle = simulate
+            .bundle()
+            .unwrap()
+            .set_resource("u_particles", &particles)?
+            .build(device)?;
+        let key_bundle = key
+            .bundle()
+            .unwrap()
+            .set_resource("u_particles", &particles)?
+            .set_resource("u_keys", &keys)?
+            .build(device)?;
+        let bitonic_bundle = bitonic
+            .bundle()
+            .unwrap()
+            .set_resource("u_keys", &keys)?
+            .build(device)?;
+        let render_bundle = render
+            .bundle()
+            .unwrap()
+            .set_resource("u_particles", &particles)?
+            .set_resource("u_keys", &keys)?
+            .build(device)?;
+
+        Ok(Self {
+            emitters: Vec::new(),
+            gravity: glam::vec3(0.0, -9.81, 0.0),
+            particles,
+            keys,
+            capacity,
+            padded,
+            cursor: 0,
+            accumulators: Vec::new(),
+            pending: Vec::new(),
+            rng: 0x9E37_79B9,
+            sort,
+            simulate,
+            key,
+            bitonic,
+            render,
+            simulate_bundle,
+            key_bundle,
+            bitonic_bundle,
+            render_bundle,
+            name: name.map(|n| n.to_string()),
+        })
+    }
+
+    fn simulate_compute() -> spv::Builder {
+        let builder = spv::Builder::new();
+        {
+            let b = &builder;
+            b.local_size(GROUP_SIZE, 1, 1);
+            let id = b.global_invocation_id();
+            let particles = b.storage::<SpvParticle>(0, 0, Some("u_particles"));
+            let params = b.push_constants::<SpvSimulateParams>(Some("params"));
+
+            b.entry(spv::Stage::Compute, "main", || {
+                let i = spv::Int::from(id.load().x());
+                let params = params.load();
+                spv::spv_if(i.lt(params.count()), || {
+                    let position = particles.member::<spv::Vec4>(i, "position");
+                    let velocity = particles.member::<spv::Vec4>(i, "velocity");
+                    let p = position.load();
+                    spv::spv_if(p.w().gt(0.0), || {
+                        let v = velocity.load();
+                        let dt = params.dt();
+                        let new_v = v.xyz() + params.gravity().xyz() * dt;
+                        let new_p = p.xyz() + new_v * dt;
+                        velocity.store(b.vec4(new_v.x(), new_v.y(), new_v.z(), v.w()));
+                        position.store(b.vec4(new_p.x(), new_p.y(), new_p.z(), p.w() - dt));
+                    });
+                });
+            });
+        }
+        builder
+    }
+
+    fn key_compute() -> spv::Builder {
+        let builder = spv::Builder::new();
+        {
+            let b = &builder;
+            b.local_size(GROUP_SIZE, 1, 1);
+            let id = b.global_invocation_id();
+            let particles = b.readonly_storage::<SpvParticle>(0, 0, Some("u_particles"));
+            let keys = b.writeonly_storage::<spv::Vec2>(0, 1, Some("u_keys"));
+            let params = b.push_constants::<SpvKeyParams>(Some("params"));
+
+            b.entry(spv::Stage::Compute, "main", || {
+                let i = spv::Int::from(id.load().x());
+                let params = params.load();
+                let index = spv::Float::from(i);
+                spv::spv_if(i.lt(params.count()), || {
+                    let p = particles.load_element(i).position();
+                    // negative distance so the furthest particles are drawn first
+                    let distance = (p.xyz() - params.camera().xyz()).length() * -1.0;
+                    let key = spv::spv_select(p.w().gt(0.0), distance, distance * 0.0 + DEAD_KEY);
+                    keys.store_element(i, b.vec2(key, index));
+                })
+                .spv_else(|| {
+                    keys.store_element(i, b.vec2(DEAD_KEY, index));
+                });
+            });
+        }
+        builder
+    }
+
+    fn bitonic_compute() -> spv::Builder {
+        let builder = spv::Builder::new();
+        {
+            let b = &builder;
+            b.local_size(GROUP_SIZE, 1, 1);
+            let id = b.global_invocation_id();
+            let keys = b.storage::<spv::Vec2>(0, 0, Some("u_keys"));
+            let params = b.push_constants::<SpvSortParams>(Some("params"));
+
+            b.entry(spv::Stage::Compute, "main", || {
+                let i = spv::Int::from(id.load().x());
+                let params = params.load();
+                let j = params.j();
+                let k = params.k();
+                // only the lower element of each pair (i ^ j > i) does the compare and swap
+                let q = i / j;
+                let lower = (q - (q / 2) * 2).eq(0);
+                spv::spv_if(lower, || {
+                    let l = i + j;
+                    let a = keys.load_element(i);
+                    let c = keys.load_element(l);
+                    let qk = i / k;
+                    let ascending = (qk - (qk / 2) * 2).eq(0);
+                    let swap = spv::spv_select(ascending, a.x().gt(c.x()), a.x().lt(c.x()));
+                    spv::spv_if(swap, || {
+                        keys.store_element(i, c);
+                        keys.store_element(l, a);
+                    });
+                });
+            });
+        }
+        builder
+    }
+
+    fn billboard_vertex() -> spv::Builder {
+        let builder = spv::Builder::new();
+        {
+            let b = &builder;
+            b.topology(spv::PrimitiveTopology::TriangleStrip);
+            let vertex_index = b.vertex_index();
+            let instance_index = b.instance_index();
+            let vk_pos = b.vk_position();
+            let out_uv = b.out_vec2(0, "out_uv");
+            let out_color = b.out_vec4(1, "out_color");
+
+            let particles = b.readonly_storage::<SpvParticle>(0, 0, Some("u_particles"));
+            let keys = b.readonly_storage::<spv::Vec2>(0, 1, Some("u_keys"));
+            let params = b.push_constants::<SpvRenderParams>(Some("params"));
+
+            b.entry(spv::Stage::Vertex, "main", || {
+                let params = params.load();
+                let v = vertex_index.load();
+                let n = instance_index.load();
+
+                // padding in the key buffer points past the end of the pool
+                let index = spv::Int::from(keys.load_element(n).y());
+                let valid = index.lt(params.count());
+                let particle = particles.load_element(spv::spv_select(valid, index, b.const_int(0)));
+                let p = particle.position();
+                let velocity = particle.velocity();
+                let size = velocity.w();
+                // dead particles collapse to a point and produce no fragments
+                let size = spv::spv_select(valid, spv::spv_select(p.w().gt(0.0), size, size * 0.0), size * 0.0);
+
+                // corners (-1, -1), (1, -1), (-1, 1), (1, 1) of the strip
+                let x = spv::Float::from(v - (v / 2) * 2) * 2.0 - 1.0;
+                let y = spv::Float::from(v / 2) * 2.0 - 1.0;
+                let offset = (params.right().xyz() * x + params.up().xyz() * y) * size;
+                let world = p.xyz() + offset;
+                vk_pos.store(params.view_projection() * b.vec4(world.x(), world.y(), world.z(), 1.0));
+                out_uv.store(b.vec2(x, y));
+                out_color.store(particle.color());
+            });
+        }
+        builder
+    }
+
+    fn billboard_fragment() -> spv::Builder {
+        let builder = spv::Builder::new();
+        {
+            let b = &builder;
+            let in_uv = b.in_vec2(0, "in_uv");
+            let in_color = b.in_vec4(1, "in_color");
+            let out_color = b.out_vec4(0, "out_color");
+
+            b.entry(spv::Stage::Fragment, "main", || {
+                let d = in_uv.load().length();
+                let falloff = spv::spv_select(d.lt(1.0), 1.0 - d, d * 0.0);
+                let color = in_color.load();
+                out_color.store(b.vec4(color.x(), color.y(), color.z(), color.w() * falloff));
+            });
+        }
+        builder
+    }
+
+    fn random(&mut self) -> f32 {
+        // xorshift32
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    fn random_in_sphere(&mut self) -> glam::Vec3 {
+        loop {
+            let v = glam::vec3(self.random(), self.random(), self.random()) * 2.0 - glam::Vec3::ONE;
+            if v.length_squared() <= 1.0 {
+                return v;
+            }
+        }
+    }
+
+    /// Spawn count particles from the emitter on the next update
+    pub fn burst(&mut self, emitter: &Emitter, count: u32) {
+        for _ in 0..count {
+            let velocity = emitter.velocity + self.random_in_sphere() * emitter.spread;
+            let life = emitter.lifetime * (1.0 - emitter.lifetime_variance * self.random());
+            let particle = Particle {
+                position: emitter.position.extend(life),
+                velocity: velocity.extend(emitter.size),
+                color: emitter.color,
+            };
+            self.pending.push((self.cursor, particle));
+            self.cursor = (self.cursor + 1) % self.capacity;
+        }
+    }
+
+    /// Spawn particles from the emitters, simulate the pool by dt seconds and sort
+    /// by distance from camera if enabled
+    ///
+    /// The update will only be complete when the command encoder is submitted
+    pub fn update(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        dt: f32,
+        camera: glam::Vec3,
+    ) -> Result<(), gpu::Error> {
+        self.accumulators.resize(self.emitters.len(), 0.0);
+        for i in 0..self.emitters.len() {
+            let emitter = self.emitters[i];
+            self.accumulators[i] += emitter.rate * dt;
+            let count = self.accumulators[i].floor();
+            self.accumulators[i] -= count;
+            self.burst(&emitter, count as u32);
+        }
+        self.upload_pending(encoder);
+
+        let groups = |n: u32| (n + GROUP_SIZE - 1) / GROUP_SIZE;
+
+        let mut pass = encoder.compute_pass_reflected(device, &self.simulate)?;
+        pass.set_bundle_owned(self.simulate_bundle.clone());
+        pass.push_vec4("gravity", self.gravity.extend(0.0).into());
+        pass.push_f32("dt", dt);
+        pass.push_i32("count", self.capacity as i32);
+        pass.dispatch(groups(self.capacity), 1, 1);
+        pass.finish();
+
+        if self.sort == ParticleSort::BackToFront {
+            let mut pass = encoder.compute_pass_reflected(device, &self.key)?;
+            pass.set_bundle_owned(self.key_bundle.clone());
+            pass.push_vec4("camera", camera.extend(1.0).into());
+            pass.push_i32("count", self.capacity as i32);
+            pass.dispatch(groups(self.padded), 1, 1);
+            pass.finish();
+
+            // each step is a separate pass so the encoder inserts barriers between them
+            let mut k = 2;
+            while k <= self.padded {
+                let mut j = k / 2;
+                while j > 0 {
+                    let mut pass = encoder.compute_pass_reflected(device, &self.bitonic)?;
+                    pass.set_bundle_owned(self.bitonic_bundle.clone());
+                    pass.push_i32("j", j as i32);
+                    pass.push_i32("k", k as i32);
+                    pass.dispatch(groups(self.padded), 1, 1);
+                    pass.finish();
+                    j /= 2;
+                }
+                k *= 2;
+            }
+        }
+        Ok(())
+    }
+
+    fn upload_pending(&mut self, encoder: &mut crate::CommandEncoder<'_>) {
+        // only the most recent particles spawned into each slot are kept
+        if self.pending.len() > self.capacity as usize {
+            let excess = self.pending.len() - self.capacity as usize;
+            self.pending.drain(..excess);
+        }
+        let size = std::mem::size_of::<Particle>();
+        let per_update = MAX_UPDATE_SIZE / size;
+        let mut start = 0;
+        while start < self.pending.len() {
+            // group runs of consecutive slots into single updates
+            let mut end = start + 1;
+            while end < self.pending.len()
+                && end - start < per_update
+                && self.pending[end].0 == self.pending[end - 1].0 + 1
+            {
+                end += 1;
+            }
+            let data = self.pending[start..end]
+                .iter()
+                .flat_map(|(_, p)| bytemuck::bytes_of(p).iter().copied())
+                .collect::<Vec<u8>>();
+            encoder.update_buffer_owned(
+                self.particles.clone(),
+                (self.pending[start].0 as usize * size) as u64,
+                data,
+            );
+            start = end;
+        }
+        self.pending.clear();
+    }
+
+    /// Draw the live particles onto color testing against depth if given
+    ///
+    /// view and projection are the camera matrices, billboards face the camera
+    pub fn draw(
+        &self,
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        color: &gpu::TextureView,
+        depth: Option<&gpu::TextureView>,
+        view: glam::Mat4,
+        projection: glam::Mat4,
+    ) -> Result<(), gpu::Error> {
+        let depth = depth.map(|d| crate::Attachment {
+            raw: gpu::Attachment::View(Cow::Owned(d.clone()), gpu::ClearValue::Depth(1.0)),
+            load: gpu::LoadOp::Load,
+            store: gpu::StoreOp::Store,
+        });
+        let mut pass = encoder.graphics_pass_reflected::<()>(
+            device,
+            &[crate::Attachment {
+                raw: gpu::Attachment::View(
+                    Cow::Owned(color.clone()),
+                    gpu::ClearValue::ColorFloat([0.0; 4]),
+                ),
+                load: gpu::LoadOp::Load,
+                store: gpu::StoreOp::Store,
+            }],
+            &[],
+            depth,
+            &self.render,
+        )?;
+        pass.set_bundle_owned(self.render_bundle.clone());
+        let inverse = view.inverse();
+        pass.push_mat4("view_projection", (projection * view).to_cols_array_2d());
+        pass.push_vec4("right", inverse.x_axis.into());
+        pass.push_vec4("up", inverse.y_axis.into());
+        pass.push_i32("count", self.capacity as i32);
+        pass.draw(0, 4, 0, self.capacity);
+        pass.finish();
+        Ok(())
+    }
+
+    /// The max number of particles alive at once
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// How particles are ordered before drawing
+    pub fn sort(&self) -> ParticleSort {
+        self.sort
+    }
+
+    /// Set how particles are ordered before drawing
+    ///
+    /// When sorting is disabled the last sorted order is kept
+    pub fn set_sort(&mut self, sort: ParticleSort) {
+        self.sort = sort;
+    }
+
+    /// Kill every particle and reset the spawn accumulators
+    pub fn clear(&mut self, encoder: &mut crate::CommandEncoder<'_>) {
+        self.pending.clear();
+        self.accumulators.clear();
+        self.cursor = 0;
+        encoder.fill_buffer(self.particles.slice_owned(..), 0);
+    }
+
+    /// The storage buffer of [`Particle`]s
+    pub fn particles(&self) -> &gpu::Buffer {
+        &self.particles
+    }
+
+    /// The storage buffer of (sort key, particle index) pairs in the order particles are drawn
+    pub fn keys(&self) -> &gpu::Buffer {
+        &self.keys
+    }
+}
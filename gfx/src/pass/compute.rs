@@ -2,6 +2,7 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::mem::ManuallyDrop as Md;
+use std::sync::Arc;
 
 use crate::reflect::Bundle;
 use std::any::TypeId;
@@ -17,6 +18,7 @@ pub enum ComputePassCommand<'a> {
     BindDescriptorSets {
         descriptors: Cow<'a, [Cow<'a, gpu::DescriptorSet>]>,
         first_location: u32,
+        dynamic_offsets: Vec<u32>,
     },
     Dispatch {
         x: u32,
@@ -51,9 +53,11 @@ impl<'a> ComputePassCommand<'a> {
             ComputePassCommand::BindDescriptorSets {
                 descriptors,
                 first_location,
-            } => command_buffer.bind_descriptors(
+                dynamic_offsets,
+            } => command_buffer.bind_descriptors_dynamic(
                 *first_location,
                 descriptors,
+                dynamic_offsets,
                 gpu::PipelineBindPoint::Compute,
                 layout,
             ),
@@ -146,6 +150,7 @@ pub trait ComputePass<'a> {
 
     /// set a single bind descriptor
     fn bind_descriptor_ref(&mut self, location: u32, descriptor: &'a gpu::DescriptorSet) {
+        super::check_dynamic_offsets(std::iter::once(descriptor), &[]);
         self.push_command(ComputePassCommand::BindDescriptorSet {
             location,
             descriptor: Cow::Borrowed(descriptor),
@@ -154,6 +159,7 @@ pub trait ComputePass<'a> {
 
     /// set a single bind descriptor
     fn bind_descriptor_owned(&mut self, location: u32, descriptor: gpu::DescriptorSet) {
+        super::check_dynamic_offsets(std::iter::once(&descriptor), &[]);
         self.push_command(ComputePassCommand::BindDescriptorSet {
             location,
             descriptor: Cow::Owned(descriptor),
@@ -161,33 +167,65 @@ pub trait ComputePass<'a> {
     }
 
     /// set the bind descriptors
+    ///
+    /// Panics if the descriptor sets contain dynamic buffers, see [`ComputePass::bind_descriptors_dynamic_ref`]
     fn bind_descriptors_ref(
         &mut self,
         first_location: u32,
         descriptors: &[&'a gpu::DescriptorSet],
     ) {
+        self.bind_descriptors_dynamic_ref(first_location, descriptors, &[])
+    }
+
+    /// set the bind descriptors
+    ///
+    /// Panics if the descriptor sets contain dynamic buffers, see [`ComputePass::bind_descriptors_dynamic_owned`]
+    fn bind_descriptors_owned(
+        &mut self,
+        first_location: u32,
+        descriptors: Vec<gpu::DescriptorSet>,
+    ) {
+        self.bind_descriptors_dynamic_owned(first_location, descriptors, &[])
+    }
+
+    /// set bind descriptors that contain dynamic uniform or storage buffers
+    ///
+    /// One offset must be supplied for each dynamic descriptor in the sets ordered by set and then by binding
+    fn bind_descriptors_dynamic_ref(
+        &mut self,
+        first_location: u32,
+        descriptors: &[&'a gpu::DescriptorSet],
+        dynamic_offsets: &[u32],
+    ) {
+        super::check_dynamic_offsets(descriptors.iter().map(|&g| g), dynamic_offsets);
         let descriptors = descriptors
-            .into_iter()
-            .map(|g| Cow::Borrowed(*g))
+            .iter()
+            .map(|&g| Cow::Borrowed(g))
             .collect::<Vec<_>>();
         self.push_command(ComputePassCommand::BindDescriptorSets {
             first_location,
+            dynamic_offsets: dynamic_offsets.to_vec(),
             descriptors: Cow::Owned(descriptors),
         })
     }
 
-    /// set the bind descriptors
-    fn bind_descriptors_owned(
+    /// set bind descriptors that contain dynamic uniform or storage buffers
+    ///
+    /// One offset must be supplied for each dynamic descriptor in the sets ordered by set and then by binding
+    fn bind_descriptors_dynamic_owned(
         &mut self,
         first_location: u32,
         descriptors: Vec<gpu::DescriptorSet>,
+        dynamic_offsets: &[u32],
     ) {
+        super::check_dynamic_offsets(descriptors.iter(), dynamic_offsets);
         let descriptors = descriptors
             .into_iter()
             .map(|g| Cow::Owned(g))
             .collect::<Vec<_>>();
         self.push_command(ComputePassCommand::BindDescriptorSets {
             first_location,
+            dynamic_offsets: dynamic_offsets.to_vec(),
             descriptors: Cow::Owned(descriptors),
         })
     }
@@ -250,9 +288,14 @@ impl<'a, 'b> Drop for BasicComputePass<'a, 'b> {
 /// Will automatically dispatch on drop
 pub struct ReflectedComputePass<'a, 'b> {
     pub(crate) parent_id: u64,
+    pub(crate) layout_id: u64,
     pub(crate) bundle_needed: bool,
     pub(crate) push_constant_names:
         Cow<'a, Option<HashMap<String, crate::reflect::PushConstantInfo>>>,
+    pub(crate) push_constant_ranges: Arc<[gpu::PushConstantRange]>,
+    pub(crate) dynamic_offset_names: Option<HashMap<String, usize>>,
+    pub(crate) dynamic_offsets: Vec<u32>,
+    pub(crate) bound_descriptors: Option<Cow<'a, [Cow<'a, gpu::DescriptorSet>]>>,
     /// Pipeline contained inside a manually drop so that it can be taken an moved into the encoder
     pub(crate) pipeline: Md<Cow<'a, gpu::ComputePipeline>>,
     pub(crate) commands: Vec<ComputePassCommand<'a>>,
@@ -261,7 +304,7 @@ pub struct ReflectedComputePass<'a, 'b> {
 
 impl std::fmt::Debug for ReflectedComputePass<'_, '_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "ReflectedComputePass parent id {}", self.parent_id)
+        writeln!(f, "ReflectedComputePass parent id {} layout id {}", self.parent_id, self.layout_id)
     }
 }
 
@@ -302,13 +345,13 @@ impl<'a, 'b> ReflectedComputePass<'a, 'b> {
             )
         }
         #[cfg(feature = "logging")]
-        if self.parent_id != bundle.parent_id {
+        if self.layout_id != bundle.layout_id {
             log::warn!(
-                "GFX: Attempt to set bundle {:?} of different parent id than pass",
+                "GFX: Attempt to set bundle {:?} with descriptor layouts incompatible with pass",
                 bundle
             )
         }
-        self.bind_descriptors_ref(0, &bundle.descriptor_sets.iter().collect::<Vec<_>>());
+        self.bind_bundle(bundle.descriptor_sets.iter().map(|d| Cow::Borrowed(d)).collect());
     }
 
     /// Set a bundle cloning its data
@@ -321,16 +364,77 @@ impl<'a, 'b> ReflectedComputePass<'a, 'b> {
             )
         }
         #[cfg(feature = "logging")]
-        if self.parent_id != bundle.parent_id {
+        if self.layout_id != bundle.layout_id {
             log::warn!(
-                "GFX: Attempt to set bundle {:?} of different parent id than pass",
+                "GFX: Attempt to set bundle {:?} with descriptor layouts incompatible with pass",
                 bundle
             )
         }
-        self.bind_descriptors_owned(
-            0,
-            bundle.descriptor_sets.iter().cloned().collect::<Vec<_>>(),
+        self.bind_bundle(bundle.descriptor_sets.into_iter().map(|d| Cow::Owned(d)).collect());
+    }
+
+    fn bind_bundle(&mut self, descriptors: Vec<Cow<'a, gpu::DescriptorSet>>) {
+        let descriptors = Cow::<[_]>::Owned(descriptors);
+        if self.dynamic_offset_names.is_some() {
+            self.bound_descriptors = Some(descriptors.clone());
+        }
+        self.push_command(ComputePassCommand::BindDescriptorSets {
+            descriptors,
+            first_location: 0,
+            dynamic_offsets: self.dynamic_offsets.clone(),
+        })
+    }
+
+    /// Set the offset into the dynamic buffer by name for the following dispatches
+    ///
+    /// Offsets default to 0 and must be a multiple of the device's min uniform/storage buffer offset alignment.
+    /// If the buffer by name isn't dynamic no action will be taken
+    /// see [`crate::ReflectedCompute::with_dynamic_buffers`]
+    pub fn set_dynamic_offset(&mut self, name: &str, offset: u32) {
+        let index = match self.dynamic_offset_names.as_ref().and_then(|m| m.get(name)) {
+            Some(&i) => i,
+            None => {
+                #[cfg(feature = "logging")]
+                log::error!("Call to set_dynamic_offset at {} with offset {}, with no dynamic buffer by that name, No action taken", name, offset);
+                return;
+            }
+        };
+        self.dynamic_offsets[index] = offset;
+        if let Some(descriptors) = self.bound_descriptors.clone() {
+            self.push_command(ComputePassCommand::BindDescriptorSets {
+                descriptors,
+                first_location: 0,
+                dynamic_offsets: self.dynamic_offsets.clone(),
+            })
+        }
+    }
+
+    /// Push raw bytes to the push constant by variable name or to the push constant block of shader stages
+    ///
+    /// The offset and stages are found from the reflected push constant ranges of the pipeline
+    /// If there are no constants by the name no action will be taken
+    /// The type of the constant isn't checked so bytes must match the layout in the shader
+    pub fn push_constants<'n>(&mut self, target: impl Into<crate::pass::PushTarget<'n>>, bytes: &[u8]) {
+        let target = target.into();
+        let resolved = crate::pass::resolve_push_target(
+            target,
+            bytes.len() as u32,
+            self.push_constant_names.as_ref().as_ref(),
+            &self.push_constant_ranges,
         );
+        if let Some((offset, stages)) = resolved {
+            ComputePass::push_constants(self, offset, bytes, stages)
+        } else {
+            #[cfg(feature = "logging")]
+            log::error!("Call to push_constants at {:?} with {} bytes, with no push constant by that name, No action taken", target, bytes.len());
+        }
+    }
+
+    /// Push raw bytes to the push constant by variable name
+    /// If there are no constants by the name no action will be taken
+    /// The type of the constant isn't checked so bytes must match the layout in the shader
+    pub fn push(&mut self, name: &str, bytes: &[u8]) {
+        self.push_constants(name, bytes)
     }
 
     /// Push a single constant by variable name
@@ -345,7 +449,7 @@ impl<'a, 'b> ReflectedComputePass<'a, 'b> {
                     "ERROR: Call to push_constant with different type of constant than in spirv in pipeline {:?}",
                     self.pipeline
                 );
-                self.push_constants(info.offset, bytemuck::bytes_of(&constant), info.stages)
+                ComputePass::push_constants(self, info.offset, bytemuck::bytes_of(&constant), info.stages)
             } else {
                 #[cfg(feature = "logging")]
                 log::error!("Call to push_constant at {} with value {:?}, with different type than expected", name, constant);
@@ -4,6 +4,7 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::mem::ManuallyDrop as Md;
+use std::sync::Arc;
 
 #[cfg(feature = "reflect")]
 use crate::reflect::Bundle;
@@ -59,6 +60,7 @@ pub enum GraphicsPassCommand<'a> {
     BindDescriptorSets {
         descriptors: Cow<'a, [Cow<'a, gpu::DescriptorSet>]>,
         first_location: u32,
+        dynamic_offsets: Vec<u32>,
     },
     BindDescriptorSet {
         descriptor: Cow<'a, gpu::DescriptorSet>,
@@ -69,6 +71,22 @@ pub enum GraphicsPassCommand<'a> {
         constants: Vec<u8>,
         stages: gpu::ShaderStages,
     },
+    SetScissor {
+        first_scissor: u32,
+        scissors: Vec<gpu::Scissor>,
+    },
+    SetStencilReference {
+        faces: gpu::StencilFace,
+        reference: u32,
+    },
+    SetStencilCompareMask {
+        faces: gpu::StencilFace,
+        mask: u32,
+    },
+    SetStencilWriteMask {
+        faces: gpu::StencilFace,
+        mask: u32,
+    },
 }
 
 impl<'a> GraphicsPassCommand<'a> {
@@ -138,9 +156,11 @@ impl<'a> GraphicsPassCommand<'a> {
             GraphicsPassCommand::BindDescriptorSets {
                 descriptors,
                 first_location,
-            } => command_buffer.bind_descriptors(
+                dynamic_offsets,
+            } => command_buffer.bind_descriptors_dynamic(
                 *first_location,
                 descriptors,
+                dynamic_offsets,
                 gpu::PipelineBindPoint::Graphics,
                 layout,
             ),
@@ -158,6 +178,19 @@ impl<'a> GraphicsPassCommand<'a> {
                 constants,
                 stages,
             } => command_buffer.push_constants(*offset, constants, *stages, layout),
+            GraphicsPassCommand::SetScissor {
+                first_scissor,
+                scissors,
+            } => command_buffer.set_scissor(*first_scissor, scissors),
+            GraphicsPassCommand::SetStencilReference { faces, reference } => {
+                command_buffer.set_stencil_reference(*faces, *reference)
+            }
+            GraphicsPassCommand::SetStencilCompareMask { faces, mask } => {
+                command_buffer.set_stencil_compare_mask(*faces, *mask)
+            }
+            GraphicsPassCommand::SetStencilWriteMask { faces, mask } => {
+                command_buffer.set_stencil_write_mask(*faces, *mask)
+            }
         }
     }
 
@@ -255,12 +288,43 @@ impl<'a> GraphicsPassCommand<'a> {
     }
 }
 
+/// Validate the arguments to an indirect draw reading commands of type C
+fn check_indirect<C>(buffer: &gpu::Buffer, offset: u64, draw_count: u32, stride: u32) {
+    if !buffer.usage().contains(gpu::BufferUsage::INDIRECT) {
+        panic!("ERROR: Buffer {:?} missing usage INDIRECT", buffer)
+    }
+    if offset % 4 != 0 {
+        panic!(
+            "ERROR: Indirect draw offset {} into buffer {:?} not a multiple of 4",
+            offset, buffer
+        )
+    }
+    let size = std::mem::size_of::<C>() as u64;
+    if draw_count > 1 && (stride % 4 != 0 || (stride as u64) < size) {
+        panic!(
+            "ERROR: Indirect draw stride {} must be a multiple of 4 and at least {}",
+            stride, size
+        )
+    }
+    if draw_count > 0 && offset + stride as u64 * (draw_count as u64 - 1) + size > buffer.size() {
+        panic!(
+            "ERROR: Indirect draw of {} commands from offset {} with stride {} out of range of buffer {:?}",
+            draw_count, offset, stride, buffer
+        )
+    }
+}
+
 /// represents and object that can record graphics pass commands
 pub trait GraphicsPass<'a> {
     /// push a command to the queue
     fn push_command(&mut self, command: GraphicsPassCommand<'a>);
 
-    /// Draw Indirect
+    /// Draw with [`gpu::DrawIndirectCommand`]s read from the buffer
+    ///
+    /// # valid usage
+    ///
+    /// The buffer must have usage INDIRECT and draw counts greater than 1 require
+    /// [`gpu::DeviceFeatures::MULTI_DRAW_INDIRECT`]
     fn draw_indirect_ref(
         &mut self,
         buffer: &'a gpu::Buffer,
@@ -268,15 +332,21 @@ pub trait GraphicsPass<'a> {
         draw_count: u32,
         stride: u32,
     ) {
-        self.push_command(GraphicsPassCommand::DrawIndirect { 
-            buffer: Cow::Borrowed(buffer), 
-            offset, 
-            draw_count, 
-            stride 
+        check_indirect::<gpu::DrawIndirectCommand>(buffer, offset, draw_count, stride);
+        self.push_command(GraphicsPassCommand::DrawIndirect {
+            buffer: Cow::Borrowed(buffer),
+            offset,
+            draw_count,
+            stride,
         })
     }
 
-    /// Draw Indirect 
+    /// Draw with [`gpu::DrawIndirectCommand`]s read from the buffer
+    ///
+    /// # valid usage
+    ///
+    /// The buffer must have usage INDIRECT and draw counts greater than 1 require
+    /// [`gpu::DeviceFeatures::MULTI_DRAW_INDIRECT`]
     fn draw_indirect_owned(
         &mut self,
         buffer: gpu::Buffer,
@@ -284,15 +354,21 @@ pub trait GraphicsPass<'a> {
         draw_count: u32,
         stride: u32,
     ) {
-        self.push_command(GraphicsPassCommand::DrawIndirect { 
-            buffer: Cow::Owned(buffer), 
-            offset, 
-            draw_count, 
-            stride 
+        check_indirect::<gpu::DrawIndirectCommand>(&buffer, offset, draw_count, stride);
+        self.push_command(GraphicsPassCommand::DrawIndirect {
+            buffer: Cow::Owned(buffer),
+            offset,
+            draw_count,
+            stride,
         })
     }
 
-    /// Draw indexed Indirect
+    /// Draw with [`gpu::DrawIndexedIndirectCommand`]s read from the buffer
+    ///
+    /// # valid usage
+    ///
+    /// An index buffer must be bound, the buffer must have usage INDIRECT and draw counts
+    /// greater than 1 require [`gpu::DeviceFeatures::MULTI_DRAW_INDIRECT`]
     fn draw_indexed_indirect_ref(
         &mut self,
         buffer: &'a gpu::Buffer,
@@ -300,15 +376,21 @@ pub trait GraphicsPass<'a> {
         draw_count: u32,
         stride: u32,
     ) {
-        self.push_command(GraphicsPassCommand::DrawIndexedIndirect { 
-            buffer: Cow::Borrowed(buffer), 
-            offset, 
-            draw_count, 
-            stride 
+        check_indirect::<gpu::DrawIndexedIndirectCommand>(buffer, offset, draw_count, stride);
+        self.push_command(GraphicsPassCommand::DrawIndexedIndirect {
+            buffer: Cow::Borrowed(buffer),
+            offset,
+            draw_count,
+            stride,
         })
     }
 
-    /// Draw Indexed Indirect 
+    /// Draw with [`gpu::DrawIndexedIndirectCommand`]s read from the buffer
+    ///
+    /// # valid usage
+    ///
+    /// An index buffer must be bound, the buffer must have usage INDIRECT and draw counts
+    /// greater than 1 require [`gpu::DeviceFeatures::MULTI_DRAW_INDIRECT`]
     fn draw_indexed_indirect_owned(
         &mut self,
         buffer: gpu::Buffer,
@@ -316,11 +398,12 @@ pub trait GraphicsPass<'a> {
         draw_count: u32,
         stride: u32,
     ) {
-        self.push_command(GraphicsPassCommand::DrawIndexedIndirect { 
-            buffer: Cow::Owned(buffer), 
-            offset, 
-            draw_count, 
-            stride 
+        check_indirect::<gpu::DrawIndexedIndirectCommand>(&buffer, offset, draw_count, stride);
+        self.push_command(GraphicsPassCommand::DrawIndexedIndirect {
+            buffer: Cow::Owned(buffer),
+            offset,
+            draw_count,
+            stride,
         })
     }
 
@@ -422,6 +505,7 @@ pub trait GraphicsPass<'a> {
     ///
     /// The bind descriptor being set must match the pipeline
     fn bind_descriptor_ref(&mut self, location: u32, descriptor: &'a gpu::DescriptorSet) {
+        super::check_dynamic_offsets(std::iter::once(descriptor), &[]);
         self.push_command(GraphicsPassCommand::BindDescriptorSet {
             location,
             descriptor: Cow::Borrowed(descriptor),
@@ -434,6 +518,7 @@ pub trait GraphicsPass<'a> {
     ///
     /// The bind descriptor being set must match the pipeline
     fn bind_descriptor_owned(&mut self, location: u32, descriptor: gpu::DescriptorSet) {
+        super::check_dynamic_offsets(std::iter::once(&descriptor), &[]);
         self.push_command(GraphicsPassCommand::BindDescriptorSet {
             location,
             descriptor: Cow::Owned(descriptor),
@@ -445,37 +530,69 @@ pub trait GraphicsPass<'a> {
     /// # valid usage
     ///
     /// The bind descriptor being set must match the pipeline
+    ///
+    /// Panics if the descriptor sets contain dynamic buffers, see [`GraphicsPass::bind_descriptors_dynamic_ref`]
     fn bind_descriptors_ref(
         &mut self,
         first_location: u32,
         descriptors: &[&'a gpu::DescriptorSet],
     ) {
+        self.bind_descriptors_dynamic_ref(first_location, descriptors, &[])
+    }
+
+    /// set the bind descriptors
+    ///
+    /// # valid usage
+    ///
+    /// The bind descriptor being set must match the pipeline
+    ///
+    /// Panics if the descriptor sets contain dynamic buffers, see [`GraphicsPass::bind_descriptors_dynamic_owned`]
+    fn bind_descriptors_owned(
+        &mut self,
+        first_location: u32,
+        descriptors: Vec<gpu::DescriptorSet>,
+    ) {
+        self.bind_descriptors_dynamic_owned(first_location, descriptors, &[])
+    }
+
+    /// set bind descriptors that contain dynamic uniform or storage buffers
+    ///
+    /// One offset must be supplied for each dynamic descriptor in the sets ordered by set and then by binding
+    fn bind_descriptors_dynamic_ref(
+        &mut self,
+        first_location: u32,
+        descriptors: &[&'a gpu::DescriptorSet],
+        dynamic_offsets: &[u32],
+    ) {
+        super::check_dynamic_offsets(descriptors.iter().map(|&g| g), dynamic_offsets);
         let descriptors = descriptors
             .iter()
             .map(|&g| Cow::Borrowed(g))
             .collect::<Vec<_>>();
         self.push_command(GraphicsPassCommand::BindDescriptorSets {
             first_location,
+            dynamic_offsets: dynamic_offsets.to_vec(),
             descriptors: Cow::from(descriptors),
         })
     }
 
-    /// set the bind descriptors
-    ///
-    /// # valid usage
+    /// set bind descriptors that contain dynamic uniform or storage buffers
     ///
-    /// The bind descriptor being set must match the pipeline
-    fn bind_descriptors_owned(
+    /// One offset must be supplied for each dynamic descriptor in the sets ordered by set and then by binding
+    fn bind_descriptors_dynamic_owned(
         &mut self,
         first_location: u32,
         descriptors: Vec<gpu::DescriptorSet>,
+        dynamic_offsets: &[u32],
     ) {
+        super::check_dynamic_offsets(descriptors.iter(), dynamic_offsets);
         let descriptors = descriptors
             .into_iter()
             .map(|g| Cow::Owned(g))
             .collect::<Vec<_>>();
         self.push_command(GraphicsPassCommand::BindDescriptorSets {
             first_location,
+            dynamic_offsets: dynamic_offsets.to_vec(),
             descriptors: Cow::from(descriptors),
         })
     }
@@ -488,6 +605,42 @@ pub trait GraphicsPass<'a> {
             stages,
         })
     }
+
+    /// Only draw fragments inside the scissor
+    ///
+    /// The scissor is reset to cover the whole viewport at the start of each pass
+    fn set_scissor(&mut self, scissor: gpu::Scissor) {
+        self.set_scissors(0, &[scissor])
+    }
+
+    /// Set multiple scissors for pipelines with multiple viewports
+    fn set_scissors(&mut self, first_scissor: u32, scissors: &[gpu::Scissor]) {
+        self.push_command(GraphicsPassCommand::SetScissor {
+            first_scissor,
+            scissors: Vec::from(scissors),
+        })
+    }
+
+    /// Set the value stencil values are compared against and written when replacing
+    ///
+    /// The pipeline must have stencil state, the reference is reset to the pipeline's at the start of each pass
+    fn set_stencil_reference(&mut self, faces: gpu::StencilFace, reference: u32) {
+        self.push_command(GraphicsPassCommand::SetStencilReference { faces, reference })
+    }
+
+    /// Set the bits of stencil values that are compared
+    ///
+    /// The pipeline must have stencil state, the mask is reset to the pipeline's at the start of each pass
+    fn set_stencil_compare_mask(&mut self, faces: gpu::StencilFace, mask: u32) {
+        self.push_command(GraphicsPassCommand::SetStencilCompareMask { faces, mask })
+    }
+
+    /// Set the bits of stencil values that are written
+    ///
+    /// The pipeline must have stencil state, the mask is reset to the pipeline's at the start of each pass
+    fn set_stencil_write_mask(&mut self, faces: gpu::StencilFace, mask: u32) {
+        self.push_command(GraphicsPassCommand::SetStencilWriteMask { faces, mask })
+    }
 }
 
 /// A GraphicsPass
@@ -543,6 +696,7 @@ impl<'a, 'b> Drop for BasicGraphicsPass<'a, 'b> {
 #[cfg(feature = "reflect")]
 pub struct ReflectedGraphicsPass<'a, 'b, V: crate::Vertex> {
     pub(crate) parent_id: u64,
+    pub(crate) layout_id: u64,
     pub(crate) bundle_needed: bool,
     /// Pipeline contained inside a manually drop so that it can be taken an moved into the encoder
     pub(crate) pipeline: Md<Cow<'a, gpu::GraphicsPipeline>>,
@@ -550,6 +704,10 @@ pub struct ReflectedGraphicsPass<'a, 'b, V: crate::Vertex> {
     pub(crate) resolve_attachments: Vec<crate::Attachment<'a>>,
     pub(crate) depth_attachment: Option<crate::Attachment<'a>>,
     pub(crate) push_constant_names: Option<HashMap<String, crate::reflect::PushConstantInfo>>,
+    pub(crate) push_constant_ranges: Arc<[gpu::PushConstantRange]>,
+    pub(crate) dynamic_offset_names: Option<HashMap<String, usize>>,
+    pub(crate) dynamic_offsets: Vec<u32>,
+    pub(crate) bound_descriptors: Option<Cow<'a, [Cow<'a, gpu::DescriptorSet>]>>,
     pub(crate) commands: Vec<GraphicsPassCommand<'a>>,
     /// The encoder that the graphics pass will be recorded into
     pub encoder: &'b mut crate::CommandEncoder<'a>,
@@ -559,7 +717,7 @@ pub struct ReflectedGraphicsPass<'a, 'b, V: crate::Vertex> {
 #[cfg(feature = "reflect")]
 impl<V: crate::Vertex> std::fmt::Debug for ReflectedGraphicsPass<'_, '_, V> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "ReflectedGraphicsPass parent id {}", self.parent_id)
+        writeln!(f, "ReflectedGraphicsPass parent id {} layout id {}", self.parent_id, self.layout_id)
     }
 }
 
@@ -627,6 +785,49 @@ impl<'a, 'b, V: crate::Vertex> ReflectedGraphicsPass<'a, 'b, V> {
         mesh.draw_instanced_owned(self, first_instance, instance_count);
     }
 
+    /// Draw a mesh referencing the mesh's buffers with draw commands read from the indirect buffer
+    ///
+    /// see [`crate::Mesh::draw_indirect_ref`]
+    pub fn draw_mesh_indirect_ref(
+        &mut self,
+        mesh: &'a crate::Mesh<V>,
+        buffer: &'a gpu::Buffer,
+        offset: u64,
+        draw_count: u32,
+        stride: u32,
+    ) {
+        mesh.draw_indirect_ref(self, buffer, offset, draw_count, stride);
+    }
+
+    /// Draw a mesh cloning the mesh's buffers with draw commands read from the indirect buffer
+    ///
+    /// see [`crate::Mesh::draw_indirect_owned`]
+    pub fn draw_mesh_indirect_owned(
+        &mut self,
+        mesh: crate::Mesh<V>,
+        buffer: gpu::Buffer,
+        offset: u64,
+        draw_count: u32,
+        stride: u32,
+    ) {
+        mesh.draw_indirect_owned(self, buffer, offset, draw_count, stride);
+    }
+
+    /// Draw a dynamic mesh referencing the mesh's buffers
+    pub fn draw_dynamic_mesh_ref(&mut self, mesh: &'a crate::DynamicMesh<V>) {
+        mesh.draw_ref(self);
+    }
+
+    /// Draw a dynamic mesh referencing the mesh's buffers
+    pub fn draw_instanced_dynamic_mesh_ref(
+        &mut self,
+        mesh: &'a crate::DynamicMesh<V>,
+        first_instance: u32,
+        instance_count: u32,
+    ) {
+        mesh.draw_instanced_ref(self, first_instance, instance_count);
+    }
+
     /// Set a bundle referencing the bundle
     pub fn set_bundle_ref(&mut self, bundle: &'a Bundle) {
         #[cfg(feature = "logging")]
@@ -637,13 +838,13 @@ impl<'a, 'b, V: crate::Vertex> ReflectedGraphicsPass<'a, 'b, V> {
             )
         }
         #[cfg(feature = "logging")]
-        if self.parent_id != bundle.parent_id {
+        if self.layout_id != bundle.layout_id {
             log::warn!(
-                "GFX: Attempt to set bundle {:?} of different parent id than pass",
+                "GFX: Attempt to set bundle {:?} with descriptor layouts incompatible with pass",
                 bundle
             )
         }
-        self.bind_descriptors_ref(0, &bundle.descriptor_sets.iter().collect::<Vec<_>>());
+        self.bind_bundle(bundle.descriptor_sets.iter().map(|d| Cow::Borrowed(d)).collect());
     }
 
     /// Set a bundle cloning the bundle data
@@ -656,16 +857,77 @@ impl<'a, 'b, V: crate::Vertex> ReflectedGraphicsPass<'a, 'b, V> {
             )
         }
         #[cfg(feature = "logging")]
-        if self.parent_id != bundle.parent_id {
+        if self.layout_id != bundle.layout_id {
             log::warn!(
-                "GFX: Attempt to set bundle {:?} of different parent id than pass",
+                "GFX: Attempt to set bundle {:?} with descriptor layouts incompatible with pass",
                 bundle
             )
         }
-        self.bind_descriptors_owned(
-            0,
-            bundle.descriptor_sets.iter().cloned().collect::<Vec<_>>(),
+        self.bind_bundle(bundle.descriptor_sets.into_iter().map(|d| Cow::Owned(d)).collect());
+    }
+
+    fn bind_bundle(&mut self, descriptors: Vec<Cow<'a, gpu::DescriptorSet>>) {
+        let descriptors = Cow::<[_]>::Owned(descriptors);
+        if self.dynamic_offset_names.is_some() {
+            self.bound_descriptors = Some(descriptors.clone());
+        }
+        self.push_command(GraphicsPassCommand::BindDescriptorSets {
+            descriptors,
+            first_location: 0,
+            dynamic_offsets: self.dynamic_offsets.clone(),
+        })
+    }
+
+    /// Set the offset into the dynamic buffer by name for the following draws
+    ///
+    /// Offsets default to 0 and must be a multiple of the device's min uniform/storage buffer offset alignment.
+    /// If the buffer by name isn't dynamic no action will be taken
+    /// see [`crate::ReflectedGraphics::with_dynamic_buffers`]
+    pub fn set_dynamic_offset(&mut self, name: &str, offset: u32) {
+        let index = match self.dynamic_offset_names.as_ref().and_then(|m| m.get(name)) {
+            Some(&i) => i,
+            None => {
+                #[cfg(feature = "logging")]
+                log::error!("Call to set_dynamic_offset at {} with offset {}, with no dynamic buffer by that name, No action taken", name, offset);
+                return;
+            }
+        };
+        self.dynamic_offsets[index] = offset;
+        if let Some(descriptors) = self.bound_descriptors.clone() {
+            self.push_command(GraphicsPassCommand::BindDescriptorSets {
+                descriptors,
+                first_location: 0,
+                dynamic_offsets: self.dynamic_offsets.clone(),
+            })
+        }
+    }
+
+    /// Push raw bytes to the push constant by variable name or to the push constant block of shader stages
+    ///
+    /// The offset and stages are found from the reflected push constant ranges of the pipeline
+    /// If there are no constants by the name no action will be taken
+    /// The type of the constant isn't checked so bytes must match the layout in the shader
+    pub fn push_constants<'n>(&mut self, target: impl Into<crate::pass::PushTarget<'n>>, bytes: &[u8]) {
+        let target = target.into();
+        let resolved = crate::pass::resolve_push_target(
+            target,
+            bytes.len() as u32,
+            self.push_constant_names.as_ref(),
+            &self.push_constant_ranges,
         );
+        if let Some((offset, stages)) = resolved {
+            GraphicsPass::push_constants(self, offset, bytes, stages)
+        } else {
+            #[cfg(feature = "logging")]
+            log::error!("Call to push_constants at {:?} with {} bytes, with no push constant by that name, No action taken", target, bytes.len());
+        }
+    }
+
+    /// Push raw bytes to the push constant by variable name
+    /// If there are no constants by the name no action will be taken
+    /// The type of the constant isn't checked so bytes must match the layout in the shader
+    pub fn push(&mut self, name: &str, bytes: &[u8]) {
+        self.push_constants(name, bytes)
     }
 
     /// Push a single constant by variable name
@@ -681,7 +943,7 @@ impl<'a, 'b, V: crate::Vertex> ReflectedGraphicsPass<'a, 'b, V> {
                     "ERROR: Call to push_constant with different type of constant than in spirv in pipeline {:?}",
                     self.pipeline
                 );
-                self.push_constants(info.offset, bytemuck::bytes_of(&constant), info.stages)
+                GraphicsPass::push_constants(self, info.offset, bytemuck::bytes_of(&constant), info.stages)
             } else {
                 #[cfg(feature = "logging")]
                 log::error!("Call to push_constant at {} with value {:?}, with no rust type found for field, No action taken", name, constant);
@@ -18,3 +18,86 @@ pub mod graphics;
 
 pub use compute::*;
 pub use graphics::*;
+
+#[cfg(feature = "reflect")]
+use std::collections::HashMap;
+
+/// Panics if the number of dynamic offsets doesn't match the dynamic descriptors in the sets
+pub(crate) fn check_dynamic_offsets<'s>(
+    descriptors: impl Iterator<Item = &'s gpu::DescriptorSet>,
+    dynamic_offsets: &[u32],
+) {
+    let count = descriptors.map(|d| d.dynamic_count()).sum::<u32>();
+    if count as usize != dynamic_offsets.len() {
+        panic!(
+            "ERROR: Attempt to bind descriptor sets with {} dynamic descriptors with {} dynamic offsets",
+            count,
+            dynamic_offsets.len()
+        );
+    }
+}
+
+/// Where to write push constants with [`ReflectedGraphicsPass::push_constants`] or [`ReflectedComputePass::push_constants`]
+///
+/// Created from a `&str` to write to the push constant member by that name or from
+/// [`gpu::ShaderStages`] to write the push constant block of those stages from offset 0
+#[cfg(feature = "reflect")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PushTarget<'a> {
+    /// The name of a member of the push constant block
+    Name(&'a str),
+    /// The stages whose push constant block to write
+    Stage(gpu::ShaderStages),
+}
+
+#[cfg(feature = "reflect")]
+impl<'a> From<&'a str> for PushTarget<'a> {
+    fn from(name: &'a str) -> Self {
+        Self::Name(name)
+    }
+}
+
+#[cfg(feature = "reflect")]
+impl From<gpu::ShaderStages> for PushTarget<'_> {
+    fn from(stages: gpu::ShaderStages) -> Self {
+        Self::Stage(stages)
+    }
+}
+
+/// Get the offset and stages to push size bytes to the target at
+///
+/// Returns None if there is no push constant by the name
+/// Panics if the bytes overflow the push constant blocks of the stages
+#[cfg(feature = "reflect")]
+pub(crate) fn resolve_push_target(
+    target: PushTarget<'_>,
+    size: u32,
+    names: Option<&HashMap<String, crate::reflect::PushConstantInfo>>,
+    ranges: &[gpu::PushConstantRange],
+) -> Option<(u32, gpu::ShaderStages)> {
+    let (offset, stages) = match target {
+        PushTarget::Name(name) => {
+            let info = names?.get(name)?;
+            (info.offset, info.stages)
+        }
+        PushTarget::Stage(stages) => (0, stages),
+    };
+    // every stage of a range that overlaps the pushed bytes has to be included
+    let mut flags = gpu::ShaderStages::empty();
+    let mut covered = gpu::ShaderStages::empty();
+    for range in ranges {
+        if range.offset < offset + size && offset < range.offset + range.size {
+            flags |= range.stage;
+        }
+        if range.offset <= offset && offset + size <= range.offset + range.size {
+            covered |= range.stage;
+        }
+    }
+    if !covered.contains(stages) {
+        panic!(
+            "ERROR: Attempt to push {} bytes at offset {} to {:?} outside of push constant ranges {:?}",
+            size, offset, target, ranges
+        );
+    }
+    Some((offset, flags))
+}
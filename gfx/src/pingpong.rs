@@ -0,0 +1,125 @@
+//! Double buffered resources
+
+/// Owns two copies of a resource, one to be read from and one to be written to
+///
+/// Commonly used for simulations where each step reads the output of the last
+/// Call [`PingPong::swap`] after each step to swap which copy is read and written
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PingPong<T> {
+    /// both copies of the resource
+    pub items: [T; 2],
+    /// the index of the copy currently being read from
+    pub(crate) index: usize,
+}
+
+impl<T> PingPong<T> {
+    /// Create a new PingPong that reads from a and writes to b
+    pub fn new(a: T, b: T) -> Self {
+        Self {
+            items: [a, b],
+            index: 0,
+        }
+    }
+
+    /// Create a new PingPong calling f for each copy
+    pub fn from_fn<E, F>(mut f: F) -> Result<Self, E>
+    where
+        F: FnMut(usize) -> Result<T, E>,
+    {
+        let a = f(0)?;
+        let b = f(1)?;
+        Ok(Self::new(a, b))
+    }
+
+    /// The copy that should be read from
+    pub fn read(&self) -> &T {
+        &self.items[self.index]
+    }
+
+    /// The copy that should be written to
+    pub fn write(&self) -> &T {
+        &self.items[1 - self.index]
+    }
+
+    /// The copy that should be read from
+    pub fn read_mut(&mut self) -> &mut T {
+        &mut self.items[self.index]
+    }
+
+    /// The copy that should be written to
+    pub fn write_mut(&mut self) -> &mut T {
+        &mut self.items[1 - self.index]
+    }
+
+    /// Swap which copy is read from and written to
+    pub fn swap(&mut self) {
+        self.index = 1 - self.index;
+    }
+
+    /// The index of the copy currently being read from
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Set the index of the copy currently being read from, useful for keeping PingPongs in step
+    pub fn set_index(&mut self, index: usize) {
+        assert!(index < 2, "ERROR: PingPong index {} out of range", index);
+        self.index = index;
+    }
+
+    /// Map each copy to create a new PingPong with the same index
+    pub fn map<U, F: FnMut(&T) -> U>(&self, mut f: F) -> PingPong<U> {
+        PingPong {
+            items: [f(&self.items[0]), f(&self.items[1])],
+            index: self.index,
+        }
+    }
+}
+
+#[cfg(feature = "reflect")]
+impl<T> PingPong<T> {
+    /// Create a pair of bundles, one for each way round the copies can be
+    ///
+    /// f is called with (read, write) for each pair and should set the resources on the builder
+    /// The returned bundles have the same index as self so `bundles.read()` is the bundle to use
+    /// for the current state as long as both are swapped together
+    pub fn bundles<'a, F>(
+        &'a self,
+        device: &gpu::Device,
+        mut f: F,
+    ) -> Result<PingPong<crate::Bundle>, crate::BundleBuildError>
+    where
+        F: FnMut(&'a T, &'a T) -> Result<crate::BundleBuilder<'a>, crate::SetResourceError>,
+    {
+        let a = f(&self.items[0], &self.items[1])?.build(device)?;
+        let b = f(&self.items[1], &self.items[0])?.build(device)?;
+        Ok(PingPong {
+            items: [a, b],
+            index: self.index,
+        })
+    }
+}
+
+#[cfg(feature = "reflect")]
+impl<T: crate::reflect::Resource> PingPong<T> {
+    /// Create a pair of bundles setting the read copy at read_name and the write copy at write_name
+    ///
+    /// builder should have any other resources already set
+    /// see [`PingPong::bundles`]
+    pub fn bundles_named<'a>(
+        &'a self,
+        device: &gpu::Device,
+        builder: crate::BundleBuilder<'a>,
+        read_name: &str,
+        write_name: Option<&str>,
+    ) -> Result<PingPong<crate::Bundle>, crate::BundleBuildError> {
+        self.bundles(device, |read, write| {
+            let b = builder.clone().set_resource(read_name, read)?;
+            if let Some(write_name) = write_name {
+                b.set_resource(write_name, write)
+            } else {
+                Ok(b)
+            }
+        })
+    }
+}
@@ -1,6 +1,8 @@
 //! re-export of traits for convienience
 
 pub use crate::mesh::vertex::Vertex;
+#[cfg(feature = "spv")]
+pub use crate::mesh::vertex::VertexBuilderExt;
 pub use crate::mesh::Mesh;
 pub use crate::pass::ComputePass;
 pub use crate::pass::GraphicsPass;
@@ -0,0 +1,301 @@
+//! Measuring how long gpu work takes with timestamp queries
+
+use std::time::Duration;
+
+/// The gpu time taken by a named scope and the scopes nested inside it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeTiming {
+    /// the name the scope was given
+    pub name: String,
+    /// the time between the start and end of the scope on the gpu
+    pub duration: Duration,
+    /// scopes begun and ended inside this one
+    pub children: Vec<ScopeTiming>,
+}
+
+impl ScopeTiming {
+    fn fmt_depth(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:indent$}{}: {:.3}ms",
+            "",
+            self.name,
+            self.duration.as_secs_f64() * 1000.0,
+            indent = depth * 2
+        )?;
+        for child in &self.children {
+            child.fmt_depth(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for ScopeTiming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_depth(f, 0)
+    }
+}
+
+/// A scope recorded in a frame
+#[derive(Debug, Clone)]
+struct Scope {
+    name: String,
+    depth: usize,
+    /// index of the scope, the start and end timestamps are at 2 * index and 2 * index + 1
+    index: u32,
+}
+
+/// The queries used by one frame in flight
+#[derive(Debug)]
+struct Frame {
+    query: gpu::TimeQuery,
+    /// the number of scopes the query has room for
+    capacity: u32,
+    scopes: Vec<Scope>,
+    /// the number of scopes requested this frame including those that didn't fit
+    requested: u32,
+    /// true between end_frame and the results being read
+    pending: bool,
+}
+
+/// Times named scopes of gpu work
+///
+/// Each frame
+/// - call [`GpuProfiler::begin_frame`] before recording
+/// - wrap work in [`GpuProfiler::scope`] or [`GpuProfiler::begin_scope`] and [`GpuProfiler::end_scope`]
+/// - call [`GpuProfiler::end_frame`] once all scopes have been recorded
+///
+/// Each frame in flight has its own queries so results are read frames_in_flight frames later
+/// once the gpu has finished with them, [`GpuProfiler::results`] returns the latest complete frame.
+/// If a frame records more scopes than the queries have room for the extra scopes aren't timed
+/// and the queries are grown the next time that frame is begun
+#[derive(Debug)]
+pub struct GpuProfiler {
+    frames: Vec<Frame>,
+    frame: usize,
+    /// the scopes that have been begun but not ended
+    stack: Vec<Scope>,
+    results: Option<Vec<ScopeTiming>>,
+    timestamp_period: f32,
+    name: Option<String>,
+}
+
+impl GpuProfiler {
+    /// Create a new GpuProfiler
+    ///
+    /// scope_capacity is the initial number of scopes per frame, frames_in_flight should be at least 2
+    /// to avoid waiting on the results of the previous frame
+    pub fn new(
+        device: &gpu::Device,
+        scope_capacity: u32,
+        frames_in_flight: usize,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let name = name.map(|n| n.to_string());
+        let frames = (0..frames_in_flight.max(1))
+            .map(|i| Self::create_frame(device, scope_capacity.max(1), i, &name))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            frames,
+            frame: 0,
+            stack: Vec::new(),
+            results: None,
+            timestamp_period: device.limits().timestamp_period,
+            name,
+        })
+    }
+
+    fn create_frame(
+        device: &gpu::Device,
+        capacity: u32,
+        index: usize,
+        name: &Option<String>,
+    ) -> Result<Frame, gpu::Error> {
+        Ok(Frame {
+            query: device.create_time_query(
+                capacity * 2,
+                name.as_ref()
+                    .map(|n| format!("{}_query_{}", n, index))
+                    .as_deref(),
+            )?,
+            capacity,
+            scopes: Vec::new(),
+            requested: 0,
+            pending: false,
+        })
+    }
+
+    /// Begin recording a new frame
+    ///
+    /// Reads the results of the last frame to use the same queries if the gpu has finished with them
+    pub fn begin_frame(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+    ) -> Result<(), gpu::Error> {
+        if !self.stack.is_empty() {
+            panic!(
+                "ERROR: GpuProfiler {:?} begin_frame called with scopes {:?} not ended",
+                self.name,
+                self.stack.iter().map(|s| &s.name).collect::<Vec<_>>()
+            );
+        }
+
+        self.frame = (self.frame + 1) % self.frames.len();
+        self.read_results()?;
+
+        let frame = &mut self.frames[self.frame];
+        if frame.requested > frame.capacity {
+            *frame = Self::create_frame(
+                device,
+                frame.requested.next_power_of_two(),
+                self.frame,
+                &self.name,
+            )?;
+        }
+        frame.scopes.clear();
+        frame.requested = 0;
+        encoder.reset_time_query_owned(frame.query.clone(), 0, frame.capacity * 2);
+        Ok(())
+    }
+
+    /// Mark the current frame as finished
+    ///
+    /// Its results will be read when the frame's queries are next used
+    pub fn end_frame(&mut self) {
+        if !self.stack.is_empty() {
+            panic!(
+                "ERROR: GpuProfiler {:?} end_frame called with scopes {:?} not ended",
+                self.name,
+                self.stack.iter().map(|s| &s.name).collect::<Vec<_>>()
+            );
+        }
+        self.frames[self.frame].pending = true;
+    }
+
+    /// Begin a named scope, scopes begun before this one is ended will be nested inside it
+    pub fn begin_scope(&mut self, encoder: &mut crate::CommandEncoder<'_>, name: &str) {
+        let frame = &mut self.frames[self.frame];
+        let index = frame.requested;
+        frame.requested += 1;
+        if index < frame.capacity {
+            encoder.write_timestamp_owned(
+                frame.query.clone(),
+                index * 2,
+                gpu::PipelineStage::TopOfPipe,
+            );
+        }
+        self.stack.push(Scope {
+            name: name.to_string(),
+            depth: self.stack.len(),
+            index,
+        });
+    }
+
+    /// End the most recently begun scope
+    pub fn end_scope(&mut self, encoder: &mut crate::CommandEncoder<'_>) {
+        let scope = match self.stack.pop() {
+            Some(s) => s,
+            None => panic!(
+                "ERROR: GpuProfiler {:?} end_scope called with no scope begun",
+                self.name
+            ),
+        };
+        let frame = &mut self.frames[self.frame];
+        if scope.index < frame.capacity {
+            encoder.write_timestamp_owned(
+                frame.query.clone(),
+                scope.index * 2 + 1,
+                gpu::PipelineStage::BottomOfPipe,
+            );
+            frame.scopes.push(scope);
+        }
+    }
+
+    /// Time the commands f records into the encoder
+    ///
+    /// For nested scopes use [`GpuProfiler::begin_scope`] and [`GpuProfiler::end_scope`]
+    pub fn scope<'a, R, F: FnOnce(&mut crate::CommandEncoder<'a>) -> R>(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'a>,
+        name: &str,
+        f: F,
+    ) -> R {
+        self.begin_scope(encoder, name);
+        let r = f(encoder);
+        self.end_scope(encoder);
+        r
+    }
+
+    /// The scope timings of the most recent frame whose results have been read
+    pub fn results(&self) -> Option<&[ScopeTiming]> {
+        self.results.as_ref().map(|r| &**r)
+    }
+
+    /// Read the results of the current frame if it is pending
+    ///
+    /// If the gpu hasn't finished with the frame the results are discarded
+    fn read_results(&mut self) -> Result<(), gpu::Error> {
+        let frame = &mut self.frames[self.frame];
+        if !frame.pending {
+            return Ok(());
+        }
+        frame.pending = false;
+
+        // only the slots of scopes that fit were written, the rest never become available
+        let written = frame.requested.min(frame.capacity);
+        if written == 0 {
+            return Ok(());
+        }
+
+        let stamps = match frame.query.check_results(0, written * 2)? {
+            Some(s) => s,
+            None => {
+                #[cfg(feature = "logging")]
+                log::warn!(
+                    "GFX: GpuProfiler {:?} results not ready before queries reused, discarding",
+                    self.name
+                );
+                return Ok(());
+            }
+        };
+
+        // scopes are pushed as they end so sort back into the order they began
+        let mut scopes = frame.scopes.clone();
+        scopes.sort_by_key(|s| s.index);
+
+        let period = self.timestamp_period as f64;
+        let mut roots = Vec::new();
+        let mut stack: Vec<ScopeTiming> = Vec::new();
+        let mut depths: Vec<usize> = Vec::new();
+        for scope in scopes {
+            while depths.last().map(|&d| d >= scope.depth).unwrap_or(false) {
+                Self::close(&mut stack, &mut depths, &mut roots);
+            }
+            let start = stamps[(scope.index * 2) as usize];
+            let end = stamps[(scope.index * 2 + 1) as usize];
+            stack.push(ScopeTiming {
+                name: scope.name,
+                duration: Duration::from_secs_f64(end.saturating_sub(start) as f64 * period * 1e-9),
+                children: Vec::new(),
+            });
+            depths.push(scope.depth);
+        }
+        while !stack.is_empty() {
+            Self::close(&mut stack, &mut depths, &mut roots);
+        }
+
+        self.results = Some(roots);
+        Ok(())
+    }
+
+    /// Pop the top of the stack into its parent or the roots
+    fn close(stack: &mut Vec<ScopeTiming>, depths: &mut Vec<usize>, roots: &mut Vec<ScopeTiming>) {
+        let timing = stack.pop().unwrap();
+        depths.pop();
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(timing),
+            None => roots.push(timing),
+        }
+    }
+}
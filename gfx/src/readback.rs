@@ -0,0 +1,316 @@
+//! Reading the results of gpu work back to the cpu
+
+/// Data being copied from the gpu into a host visible staging buffer
+///
+/// Created by [`crate::CommandEncoder::read_buffer`] and [`crate::CommandEncoder::read_texture`]
+/// The data is only available once the command buffer that the encoder was submitted to has completed
+/// use [`ReadbackFuture::is_ready`] to poll or [`ReadbackFuture::wait`] to block
+pub struct ReadbackFuture<T> {
+    buffer: gpu::Buffer,
+    resolve: Box<dyn FnOnce(Vec<u8>) -> T>,
+}
+
+impl<T> std::fmt::Debug for ReadbackFuture<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadbackFuture")
+            .field("buffer", &self.buffer)
+            .finish()
+    }
+}
+
+impl<T> ReadbackFuture<T> {
+    /// Returns true if the command buffer the encoder was submitted to has completed
+    ///
+    /// command_buffer must be the command buffer the encoder was submitted to
+    /// and must already have been submitted
+    pub fn is_ready(&self, command_buffer: &gpu::CommandBuffer) -> Result<bool, gpu::Error> {
+        command_buffer.is_complete()
+    }
+
+    /// Read the data out of the staging buffer
+    ///
+    /// Should only be called once [`ReadbackFuture::is_ready`] has returned true
+    /// otherwise the data returned may be incomplete
+    pub fn resolve(self) -> Result<T, gpu::Error> {
+        let mut data = vec![0; self.buffer.size() as usize];
+        self.buffer.slice_ref(..).read(&mut data)?;
+        Ok((self.resolve)(data))
+    }
+
+    /// Block until the command buffer the encoder was submitted to has completed and then read the data
+    pub fn wait(self, command_buffer: &mut gpu::CommandBuffer) -> Result<T, gpu::Error> {
+        command_buffer.wait(!0)?;
+        self.resolve()
+    }
+
+    /// Map the result of the readback
+    pub fn map<U, F: FnOnce(T) -> U + 'static>(self, f: F) -> ReadbackFuture<U>
+    where
+        T: 'static,
+    {
+        let resolve = self.resolve;
+        ReadbackFuture {
+            buffer: self.buffer,
+            resolve: Box::new(move |data| f(resolve(data))),
+        }
+    }
+}
+
+impl<'a> crate::CommandEncoder<'a> {
+    /// Copy the contents of a buffer slice back to the cpu
+    ///
+    /// The slice size must be a multiple of the size of T
+    pub fn read_buffer<T: bytemuck::Pod>(
+        &mut self,
+        device: &gpu::Device,
+        src: gpu::BufferSlice<'a>,
+    ) -> Result<ReadbackFuture<Vec<T>>, gpu::Error> {
+        if src.size() % std::mem::size_of::<T>() as u64 != 0 {
+            panic!(
+                "ERROR: Attempt to read buffer slice of size {} as elements of size {}",
+                src.size(),
+                std::mem::size_of::<T>()
+            );
+        }
+        let buffer = self.readback_buffer(device, src.size())?;
+        self.copy_buffer_to_buffer(src, buffer.slice_owned(..));
+        self.push_readback_barrier(&buffer);
+        Ok(ReadbackFuture {
+            buffer,
+            resolve: Box::new(|data| bytemuck::pod_collect_to_vec(&data)),
+        })
+    }
+
+    /// Copy the contents of a texture slice back to the cpu as raw bytes
+    ///
+    /// Only the base mip level of the slice is read, layers are tightly packed one after another
+    pub fn read_texture_raw(
+        &mut self,
+        device: &gpu::Device,
+        src: gpu::TextureSlice<'a>,
+    ) -> Result<ReadbackFuture<Vec<u8>>, gpu::Error> {
+        let extent = src.extent();
+        let size = src
+            .cow_texture()
+            .format()
+            .data_size(extent.width, extent.height, extent.depth)
+            * src.array_layers() as usize;
+        let buffer = self.readback_buffer(device, size as u64)?;
+        self.copy_texture_to_buffer(src, buffer.slice_owned(..));
+        self.push_readback_barrier(&buffer);
+        Ok(ReadbackFuture {
+            buffer,
+            resolve: Box::new(|data| data),
+        })
+    }
+
+    fn readback_buffer(&self, device: &gpu::Device, size: u64) -> Result<gpu::Buffer, gpu::Error> {
+        device.create_buffer(&gpu::BufferDesc {
+            name: None,
+            size,
+            usage: gpu::BufferUsage::COPY_DST,
+            memory: gpu::MemoryType::Host,
+        })
+    }
+
+    /// make the copy into buffer visible to the host once the submission completes
+    fn push_readback_barrier(&mut self, buffer: &gpu::Buffer) {
+        self.commands
+            .push(crate::encoder::Command::PipelineBarrier {
+                src_stage: gpu::PipelineStageFlags::COPY,
+                dst_stage: gpu::PipelineStageFlags::HOST,
+                buffers: vec![gpu::BufferAccessInfo {
+                    buffer: buffer.slice_owned(..),
+                    src_access: gpu::AccessFlags::COPY_WRITE,
+                    dst_access: gpu::AccessFlags::HOST_READ,
+                }],
+                textures: Vec::new(),
+            });
+        self.formatted = false;
+    }
+}
+
+#[cfg(feature = "image")]
+impl<'a> crate::CommandEncoder<'a> {
+    /// Copy a 2d texture slice back to the cpu as an rgba image
+    ///
+    /// Supports 8 bit rgba and bgra formats (such as swapchain images), R8Unorm, R16Float, R32Float, Rgba16Float and Rgba32Float
+    /// Float formats are clamped to 0..1. Will panic for other formats, use [`Self::read_texture_raw`] instead
+    pub fn read_texture(
+        &mut self,
+        device: &gpu::Device,
+        src: gpu::TextureSlice<'a>,
+    ) -> Result<ReadbackFuture<image::RgbaImage>, gpu::Error> {
+        self.read_texture_with(device, src, false)
+    }
+
+    /// Copy a 2d texture slice holding linear color back to the cpu as an srgb encoded rgba image
+    ///
+    /// Use this for textures that would be encoded when written to an srgb swapchain, such as the output of tonemapping.
+    /// Formats that are already srgb encoded aren't changed, see [`Self::read_texture`] for the supported formats
+    pub fn read_texture_srgb(
+        &mut self,
+        device: &gpu::Device,
+        src: gpu::TextureSlice<'a>,
+    ) -> Result<ReadbackFuture<image::RgbaImage>, gpu::Error> {
+        self.read_texture_with(device, src, true)
+    }
+
+    fn read_texture_with(
+        &mut self,
+        device: &gpu::Device,
+        src: gpu::TextureSlice<'a>,
+        srgb: bool,
+    ) -> Result<ReadbackFuture<image::RgbaImage>, gpu::Error> {
+        let format = src.cow_texture().format();
+        let extent = src.extent();
+        let encode: fn(f32) -> f32 = if srgb { linear_to_srgb } else { |c| c };
+        let quantize = move |c: f32| (encode(c).clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+        // the alpha channel is always linear
+        let rgba = move |p: &[f32]| {
+            [
+                quantize(p[0]),
+                quantize(p[1]),
+                quantize(p[2]),
+                (p[3].clamp(0.0, 1.0) * 255.0 + 0.5) as u8,
+            ]
+        };
+        let grey = move |r: f32| {
+            let r = quantize(r);
+            [r, r, r, 255]
+        };
+        let unorm = |d: &[u8]| d.iter().map(|&c| c as f32 / 255.0).collect::<Vec<_>>();
+        let convert: Box<dyn Fn(&[u8]) -> Vec<u8>> = match format {
+            gpu::Format::Rgba8Srgb => Box::new(|d| d.to_vec()),
+            gpu::Format::Bgra8Srgb => Box::new(|d| {
+                d.chunks_exact(4)
+                    .flat_map(|p| [p[2], p[1], p[0], p[3]])
+                    .collect()
+            }),
+            gpu::Format::Rgba8Unorm if !srgb => Box::new(|d| d.to_vec()),
+            gpu::Format::Bgra8Unorm if !srgb => Box::new(|d| {
+                d.chunks_exact(4)
+                    .flat_map(|p| [p[2], p[1], p[0], p[3]])
+                    .collect()
+            }),
+            gpu::Format::Rgba8Unorm => {
+                Box::new(move |d| unorm(d).chunks_exact(4).flat_map(rgba).collect())
+            }
+            gpu::Format::Bgra8Unorm => Box::new(move |d| {
+                unorm(d)
+                    .chunks_exact(4)
+                    .flat_map(|p| rgba(&[p[2], p[1], p[0], p[3]]))
+                    .collect()
+            }),
+            gpu::Format::R8Unorm => {
+                Box::new(move |d| unorm(d).into_iter().flat_map(grey).collect())
+            }
+            gpu::Format::R16Float => Box::new(move |d| {
+                bytemuck::pod_collect_to_vec::<u8, u16>(d)
+                    .into_iter()
+                    .flat_map(|r| grey(f16_to_f32(r)))
+                    .collect()
+            }),
+            gpu::Format::Rgba16Float => Box::new(move |d| {
+                bytemuck::pod_collect_to_vec::<u8, u16>(d)
+                    .into_iter()
+                    .map(f16_to_f32)
+                    .collect::<Vec<_>>()
+                    .chunks_exact(4)
+                    .flat_map(rgba)
+                    .collect()
+            }),
+            gpu::Format::R32Float => Box::new(move |d| {
+                bytemuck::pod_collect_to_vec::<u8, f32>(d)
+                    .into_iter()
+                    .flat_map(grey)
+                    .collect()
+            }),
+            gpu::Format::Rgba32Float => Box::new(move |d| {
+                bytemuck::pod_collect_to_vec::<u8, f32>(d)
+                    .chunks_exact(4)
+                    .flat_map(rgba)
+                    .collect()
+            }),
+            f => panic!(
+                "ERROR: Can't read texture with format {:?} into an rgba image",
+                f
+            ),
+        };
+        if src.array_layers() != 1 || extent.depth != 1 {
+            panic!("ERROR: Can only read 2d texture slices into an rgba image");
+        }
+        Ok(self.read_texture_raw(device, src)?.map(move |data| {
+            image::RgbaImage::from_raw(extent.width, extent.height, convert(&data)).unwrap()
+        }))
+    }
+}
+
+/// Copy a texture back to the cpu as an rgba image, blocking until the copy has completed
+///
+/// Records and submits its own command buffer, the texture must have been created with [`gpu::TextureUsage::COPY_SRC`]
+/// Only the first layer and mip level are read, see [`crate::CommandEncoder::read_texture`] for the supported formats
+#[cfg(feature = "image")]
+pub fn capture_frame(
+    device: &gpu::Device,
+    texture: &gpu::Texture,
+) -> Result<image::RgbaImage, gpu::Error> {
+    capture(device, texture, false)
+}
+
+/// Copy a texture holding linear color back to the cpu as an srgb encoded rgba image, blocking until the copy has completed
+///
+/// See [`capture_frame`] and [`crate::CommandEncoder::read_texture_srgb`]
+#[cfg(feature = "image")]
+pub fn capture_frame_srgb(
+    device: &gpu::Device,
+    texture: &gpu::Texture,
+) -> Result<image::RgbaImage, gpu::Error> {
+    capture(device, texture, true)
+}
+
+#[cfg(feature = "image")]
+fn capture(
+    device: &gpu::Device,
+    texture: &gpu::Texture,
+    srgb: bool,
+) -> Result<image::RgbaImage, gpu::Error> {
+    let extent: gpu::Extent3D = texture.dimension().into();
+    let slice = texture.slice_ref(&gpu::TextureSliceDesc {
+        offset: gpu::Offset3D::ZERO,
+        extent: gpu::Extent3D { depth: 1, ..extent },
+        base_array_layer: 0,
+        array_layers: 1,
+        base_mip_level: 0,
+        mip_levels: 1,
+    });
+    let mut encoder = crate::CommandEncoder::new();
+    let future = encoder.read_texture_with(device, slice, srgb)?;
+    let mut command_buffer = device.create_command_buffer(None)?;
+    encoder.submit(&mut command_buffer, true)?;
+    future.wait(&mut command_buffer)
+}
+
+#[cfg(feature = "image")]
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Convert the bits of a half precision float to f32
+#[cfg(feature = "image")]
+fn f16_to_f32(h: u16) -> f32 {
+    let sign = if h >> 15 != 0 { -1.0 } else { 1.0 };
+    let exp = ((h >> 10) & 0x1f) as i32;
+    let mant = (h & 0x3ff) as f32;
+    match exp {
+        // zero and subnormal
+        0 => sign * mant * 2f32.powi(-24),
+        0x1f if mant == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mant / 1024.0) * 2f32.powi(exp - 15),
+    }
+}
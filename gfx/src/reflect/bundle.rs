@@ -12,6 +12,7 @@ use super::resource::Resource;
 /// locations or binding names
 ///
 /// See [`crate::ReflectedGraphics::bundle`] or [`crate::ReflectedCompute::bundle`] to create a bundle builder
+#[derive(Clone)]
 pub struct BundleBuilder<'a> {
     /// key of the pipeline this is related to
     pub(crate) parent_id: u64,
@@ -121,6 +122,32 @@ impl<'a> BundleBuilder<'a> {
         }
     }
 
+    /// set the texel buffer by location name
+    pub fn set_texel_buffer_ref(
+        self,
+        name: &str,
+        view: &'a gpu::BufferView,
+    ) -> Result<Self, error::SetResourceError> {
+        if let Some(&(set, binding)) = self.reflect_data.descriptor_set_map.as_ref().unwrap().get(name) {
+            self.set_texel_buffer_ref_by_location(set as _, binding as _, view)
+        } else {
+            Err(error::SetResourceError::IdNotFound(name.to_string()).into())
+        }
+    }
+
+    /// set the texel buffer by location name
+    pub fn set_texel_buffer_owned(
+        self,
+        name: &str,
+        view: gpu::BufferView,
+    ) -> Result<Self, error::SetResourceError> {
+        if let Some(&(set, binding)) = self.reflect_data.descriptor_set_map.as_ref().unwrap().get(name) {
+            self.set_texel_buffer_owned_by_location(set as _, binding as _, view)
+        } else {
+            Err(error::SetResourceError::IdNotFound(name.to_string()).into())
+        }
+    }
+
     /// set the combined texture and sampler by location name
     pub fn set_combined_texture_sampler_ref(
         self,
@@ -357,10 +384,12 @@ impl<'a> BundleBuilder<'a> {
             Err(error::SetResourceError::SingleExpected)?;
         }
         match self.reflect_data.descriptor_set_types.as_ref().unwrap()[set][binding].0 {
-            gpu::DescriptorLayoutEntryType::UniformBuffer => {
+            gpu::DescriptorLayoutEntryType::UniformBuffer
+            | gpu::DescriptorLayoutEntryType::UniformBufferDynamic => {
                 self.descriptors[set][binding] = Some(gpu::DescriptorSetEntry::buffer(buffer))
             }
-            gpu::DescriptorLayoutEntryType::StorageBuffer { .. } => {
+            gpu::DescriptorLayoutEntryType::StorageBuffer { .. }
+            | gpu::DescriptorLayoutEntryType::StorageBufferDynamic { .. } => {
                 self.descriptors[set][binding] = Some(gpu::DescriptorSetEntry::buffer(buffer))
             }
             _ => Err(error::SetResourceError::WrongType(
@@ -438,6 +467,76 @@ impl<'a> BundleBuilder<'a> {
         Ok(self)
     }
 
+    /// set the texel buffer by set and binding
+    pub fn set_texel_buffer_ref_by_location(
+        mut self,
+        set: usize,
+        binding: usize,
+        view: &'a gpu::BufferView,
+    ) -> Result<Self, error::SetResourceError> {
+        if self
+            .reflect_data
+            .descriptor_set_types
+            .as_ref()
+            .unwrap()
+            .get(set)
+            .expect("ERROR: Bundle created with largest set greater that max number of sets")
+            .get(binding)
+            .expect("ERROR: Bundle created with largest binding greater than max bindings")
+            .1
+            != 1
+        {
+            Err(error::SetResourceError::SingleExpected)?;
+        }
+        match self.reflect_data.descriptor_set_types.as_ref().unwrap()[set][binding].0 {
+            gpu::DescriptorLayoutEntryType::UniformTexelBuffer
+            | gpu::DescriptorLayoutEntryType::StorageTexelBuffer { .. } => {
+                self.descriptors[set][binding] =
+                    Some(gpu::DescriptorSetEntry::texel_buffer_ref(view))
+            }
+            _ => Err(error::SetResourceError::WrongType(
+                gpu::DescriptorLayoutEntryType::UniformTexelBuffer,
+                self.reflect_data.descriptor_set_types.as_ref().unwrap()[set][binding].0,
+            ))?,
+        }
+        Ok(self)
+    }
+
+    /// set the texel buffer by set and binding
+    pub fn set_texel_buffer_owned_by_location(
+        mut self,
+        set: usize,
+        binding: usize,
+        view: gpu::BufferView,
+    ) -> Result<Self, error::SetResourceError> {
+        if self
+            .reflect_data
+            .descriptor_set_types
+            .as_ref()
+            .unwrap()
+            .get(set)
+            .expect("ERROR: Bundle created with largest set greater that max number of sets")
+            .get(binding)
+            .expect("ERROR: Bundle created with largest binding greater than max bindings")
+            .1
+            != 1
+        {
+            Err(error::SetResourceError::SingleExpected)?;
+        }
+        match self.reflect_data.descriptor_set_types.as_ref().unwrap()[set][binding].0 {
+            gpu::DescriptorLayoutEntryType::UniformTexelBuffer
+            | gpu::DescriptorLayoutEntryType::StorageTexelBuffer { .. } => {
+                self.descriptors[set][binding] =
+                    Some(gpu::DescriptorSetEntry::texel_buffer_owned(view))
+            }
+            _ => Err(error::SetResourceError::WrongType(
+                gpu::DescriptorLayoutEntryType::UniformTexelBuffer,
+                self.reflect_data.descriptor_set_types.as_ref().unwrap()[set][binding].0,
+            ))?,
+        }
+        Ok(self)
+    }
+
     /// set the combined texture and sampler by location
     pub fn set_combined_texture_sampler_ref_by_location(
         mut self,
@@ -614,11 +713,13 @@ impl<'a> BundleBuilder<'a> {
             Err(error::SetResourceError::ArrayExpected)?;
         }
         match self.reflect_data.descriptor_set_types.as_ref().unwrap()[set][binding].0 {
-            gpu::DescriptorLayoutEntryType::UniformBuffer => {
+            gpu::DescriptorLayoutEntryType::UniformBuffer
+            | gpu::DescriptorLayoutEntryType::UniformBufferDynamic => {
                 self.descriptors[set][binding] =
                     Some(gpu::DescriptorSetEntry::buffer_array_ref(buffers))
             }
-            gpu::DescriptorLayoutEntryType::StorageBuffer { .. } => {
+            gpu::DescriptorLayoutEntryType::StorageBuffer { .. }
+            | gpu::DescriptorLayoutEntryType::StorageBufferDynamic { .. } => {
                 self.descriptors[set][binding] =
                     Some(gpu::DescriptorSetEntry::buffer_array_ref(buffers))
             }
@@ -652,11 +753,13 @@ impl<'a> BundleBuilder<'a> {
             Err(error::SetResourceError::ArrayExpected)?;
         }
         match self.reflect_data.descriptor_set_types.as_ref().unwrap()[set][binding].0 {
-            gpu::DescriptorLayoutEntryType::UniformBuffer => {
+            gpu::DescriptorLayoutEntryType::UniformBuffer
+            | gpu::DescriptorLayoutEntryType::UniformBufferDynamic => {
                 self.descriptors[set][binding] =
                     Some(gpu::DescriptorSetEntry::buffer_array_owned(buffers))
             }
-            gpu::DescriptorLayoutEntryType::StorageBuffer { .. } => {
+            gpu::DescriptorLayoutEntryType::StorageBuffer { .. }
+            | gpu::DescriptorLayoutEntryType::StorageBufferDynamic { .. } => {
                 self.descriptors[set][binding] =
                     Some(gpu::DescriptorSetEntry::buffer_array_owned(buffers))
             }
@@ -889,7 +992,17 @@ impl<'a> BundleBuilder<'a> {
 
         Ok(Bundle {
             parent_id: self.parent_id,
+            layout_id: self.reflect_data.layout_id(),
             descriptor_sets,
+            source: Some(BundleSource {
+                parent_name: self.parent_name.map(|n| n.to_string()),
+                reflect_data: self.reflect_data.clone(),
+                entries: self
+                    .descriptors
+                    .iter()
+                    .map(|v| v.iter().map(|e| e.as_ref().unwrap().as_owned()).collect())
+                    .collect(),
+            }),
         })
     }
 
@@ -900,20 +1013,140 @@ impl<'a> BundleBuilder<'a> {
 }
 
 /// a collection of DescriptorSets specific to a Renderer
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone)]
 pub struct Bundle {
     /// The key this bundle is related to
     pub(crate) parent_id: u64,
+    /// The id of the descriptor layouts the sets were created from
+    pub(crate) layout_id: u64,
     /// the DescriptorSets
     pub descriptor_sets: Vec<gpu::DescriptorSet>,
+    /// the data the bundle was built from, used to rebuild sets when a resource changes
+    pub(crate) source: Option<BundleSource>,
+}
+
+/// The entries and reflected data a [`Bundle`] was built from
+#[derive(Clone)]
+pub(crate) struct BundleSource {
+    pub(crate) parent_name: Option<String>,
+    pub(crate) reflect_data: super::ReflectData,
+    pub(crate) entries: Vec<Vec<gpu::DescriptorSetEntry<'static>>>,
+}
+
+impl std::fmt::Debug for BundleSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "BundleSource {:?}", self.parent_name)
+    }
+}
+
+impl std::cmp::PartialEq for Bundle {
+    fn eq(&self, other: &Bundle) -> bool {
+        self.parent_id == other.parent_id && self.descriptor_sets == other.descriptor_sets
+    }
+}
+
+impl std::cmp::Eq for Bundle {}
+
+impl std::hash::Hash for Bundle {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.parent_id.hash(state);
+        self.descriptor_sets.hash(state);
+    }
 }
 
 impl Bundle {
     /// Create a bundle from raw
+    ///
+    /// Bundles created from raw can't be updated with [`Bundle::update_resource`]
     pub fn from_raw(parent_id: u64, sets: Vec<gpu::DescriptorSet>) -> Self {
         Self {
             parent_id,
+            layout_id: super::layout_id(sets.iter().map(|s| s.layout_id())),
             descriptor_sets: sets,
+            source: None,
+        }
+    }
+
+    /// Get the id of the pipeline the bundle was built from
+    pub fn parent_id(&self) -> u64 {
+        self.parent_id
+    }
+
+    /// Get the id of the descriptor layouts the bundle was built for
+    ///
+    /// The bundle can be set on any pass of a pipeline with the same [`crate::ReflectedGraphics::layout_id`]
+    /// or [`crate::ReflectedCompute::layout_id`] not just the pipeline it was built from
+    pub fn layout_id(&self) -> u64 {
+        self.layout_id
+    }
+
+    /// Replace the resource by name rebuilding only the descriptor set that contains it
+    ///
+    /// The old descriptor set is kept alive by any command buffers that still reference it
+    /// so this is safe to call while previous frames are in flight, for example on resize
+    ///
+    /// Will panic if the bundle was created with [`Bundle::from_raw`]
+    pub fn update_resource<R: Resource + ?Sized>(
+        &mut self,
+        device: &gpu::Device,
+        name: &str,
+        resource: &R,
+    ) -> Result<(), error::BundleBuildError> {
+        let source = self
+            .source
+            .as_ref()
+            .expect("ERROR: Attempt to update resource on bundle created from raw");
+
+        let (set, binding) = match source
+            .reflect_data
+            .descriptor_set_map
+            .as_ref()
+            .unwrap()
+            .get(name)
+        {
+            Some(&(set, binding)) => (set as usize, binding as usize),
+            None => Err(error::SetResourceError::IdNotFound(name.to_string()))?,
+        };
+
+        let builder = BundleBuilder {
+            parent_id: self.parent_id,
+            parent_name: source.parent_name.as_ref().map(|n| &**n),
+            reflect_data: &source.reflect_data,
+            descriptors: source
+                .entries
+                .iter()
+                .map(|v| v.iter().map(|_| None).collect())
+                .collect(),
+        };
+        let mut builder = resource.set(builder, name)?;
+        let entry = builder.descriptors[set][binding].take().unwrap().into_owned();
+
+        let mut entries = source.entries[set].clone();
+        entries[binding] = entry;
+
+        let descriptor_set = device.create_descriptor_set(&gpu::DescriptorSetDesc {
+            name: source
+                .parent_name
+                .as_ref()
+                .map(|n| format!("{}_descriptor_set_{}", n, set)),
+            entries: &entries,
+            layout: &source.reflect_data.descriptor_set_layouts.as_ref().unwrap()[set],
+        })?;
+
+        self.descriptor_sets[set] = descriptor_set;
+        self.source.as_mut().unwrap().entries[set] = entries;
+        Ok(())
+    }
+
+    /// Replace multiple resources by name, see [`Bundle::update_resource`]
+    pub fn update_resources<'a>(
+        &mut self,
+        device: &gpu::Device,
+        resources: impl IntoIterator<Item = (&'a str, &'a dyn Resource)>,
+    ) -> Result<(), error::BundleBuildError> {
+        for (name, resource) in resources {
+            self.update_resource(device, name, resource)?;
         }
+        Ok(())
     }
 }
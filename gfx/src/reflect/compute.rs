@@ -58,6 +58,47 @@ impl ReflectedCompute {
             spirv: compute,
         })?;
 
+        Self::from_module(device, reflect_builder, module, cache, name)
+    }
+
+    /// Create a new ReflectedCompute from a [`spv::Builder`]
+    ///
+    /// The pipeline layout is created from the resources declared on the builder
+    /// rather than by reflecting the compiled spir-v
+    #[cfg(feature = "spv")]
+    pub fn from_builder(
+        device: &gpu::Device,
+        compute: &spv::Builder,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, error::ReflectedError> {
+        let mut reflect_builder = super::ReflectDataBuilder::new();
+
+        let entry = compute
+            .get_entry_name(spv::Stage::Compute)
+            .ok_or(super::ParseSpirvError::from(super::ReflectError::from(
+                "compute builder has no compute entry point",
+            )))?;
+        reflect_builder.parse_builder(compute, gpu::ShaderStages::COMPUTE)?;
+
+        let module_name = name.as_ref().map(|n| format!("{}_shader_module", n));
+
+        let module = device.create_shader_module(&gpu::ShaderModuleDesc {
+            name: module_name,
+            entries: &[(gpu::ShaderStages::COMPUTE, entry)],
+            spirv: &compute.compile(),
+        })?;
+
+        Self::from_module(device, reflect_builder, module, cache, name)
+    }
+
+    fn from_module(
+        device: &gpu::Device,
+        reflect_builder: super::ReflectDataBuilder,
+        module: gpu::ShaderModule,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, error::ReflectedError> {
         let (pipeline_layout, reflect_data) = reflect_builder.build(device, name)?;
 
         let mut hasher = DefaultHasher::new();
@@ -114,11 +155,71 @@ impl ReflectedCompute {
         self.reflect_data.descriptor_set_layouts.is_some()
     }
 
+    /// Mark the uniform or storage buffers with the supplied names as dynamic
+    ///
+    /// Dynamic buffers have an offset supplied at dispatch time through
+    /// [`crate::ReflectedComputePass::set_dynamic_offset`].
+    /// Bundles have to be created after calling this as the descriptor layouts are recreated
+    ///
+    /// Will panic if a name doesn't refer to a uniform or storage buffer
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/VkDescriptorType.html>
+    pub fn with_dynamic_buffers(mut self, device: &gpu::Device, names: &[&str]) -> Result<Self, gpu::Error> {
+        let name = self.pipeline_data.name.clone();
+        self.pipeline_data.layout = self.reflect_data.make_dynamic(device, names, name.as_ref().map(|n| &**n))?;
+        self.pipeline_map = Arc::default();
+
+        let mut hasher = DefaultHasher::new();
+        self.id.hash(&mut hasher);
+        names.hash(&mut hasher);
+        self.id = hasher.finish();
+
+        Ok(self)
+    }
+
+    /// Create the same ReflectedCompute on another device, see [`gpu::Device::on_recreate`]
+    ///
+    /// The shader module and layouts are recreated and the cached pipelines are dropped.
+    /// Bundles have to be recreated from the new ReflectedCompute
+    pub fn recreate(&self, device: &gpu::Device) -> Result<Self, gpu::Error> {
+        let name = self.pipeline_data.name.as_ref().map(|n| &**n);
+        let mut reflect_data = self.reflect_data.clone();
+        let layout = reflect_data.recreate(device, name)?;
+
+        let shader = self.pipeline_data.shader.recreate(device)?;
+        let cache = device.create_pipeline_cache(&gpu::PipelineCacheDesc {
+            name: name.map(|n| format!("{}_pipeline_cache", n)),
+            initial_data: None,
+        })?;
+
+        let mut hasher = DefaultHasher::new();
+        self.id.hash(&mut hasher);
+        shader.hash(&mut hasher);
+
+        Ok(Self {
+            id: hasher.finish(),
+            pipeline_map: Arc::default(),
+            pipeline_data: PipelineData {
+                layout,
+                shader,
+                cache,
+                name: self.pipeline_data.name.clone(),
+            },
+            reflect_data,
+        })
+    }
+
     /// Get the id of the ReflectedCompute
     pub fn id(&self) -> u64 {
         self.id
     }
 
+    /// Get the id of the descriptor layouts of the ReflectedCompute
+    ///
+    /// Pipelines with identical descriptor layouts share a layout id and can use each others bundles
+    pub fn layout_id(&self) -> u64 {
+        self.reflect_data.layout_id()
+    }
+
     pub fn clear(&self) {
         self.pipeline_map.write().clear();
     }
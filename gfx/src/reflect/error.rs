@@ -65,6 +65,20 @@ pub enum ParseSpirvError {
         /// the type that the dst accepts
         dst_type: spirq::ty::Type,
     },
+    /// Builders {src_stage_name} and {dst_stage_name} output and input at location {location} have different types {src_type} {dst_type}
+    #[cfg(feature = "spv")]
+    BuilderStageIncompatibility {
+        /// the location of the conflict
+        location: u32,
+        /// the name in the src stage
+        src_stage_name: String,
+        /// the type that the src emmits
+        src_type: spv::IOType,
+        /// the name in the dst stage
+        dst_stage_name: String,
+        /// the type that the dst accepts
+        dst_type: spv::IOType,
+    },
 }
 
 impl std::fmt::Display for ParseSpirvError {
@@ -82,6 +96,14 @@ impl std::fmt::Display for ParseSpirvError {
                 dst_stage_name,
                 dst_type,
             } => writeln!(f, "ERROR: Shader stages {} and {} input and output at location {} have different types {:?} and {:?}", src_stage_name, dst_stage_name, location, src_type, dst_type),
+            #[cfg(feature = "spv")]
+            Self::BuilderStageIncompatibility {
+                location,
+                src_stage_name,
+                src_type,
+                dst_stage_name,
+                dst_type,
+            } => writeln!(f, "ERROR: Builders {} and {} output and input at location {} have different types or interpolation {:?} and {:?}", src_stage_name, dst_stage_name, location, src_type, dst_type),
             Self::DescriptorNameUndecidable(n, s0, b0, s1, b1) => writeln!(f, "ERROR: Descriptor name {} points to both (set {} binding {}) and (set {} binding {})", n, s0, b0, s1, b1),
             Self::DescriptorTypeConflict(s, b, t1, t2) => writeln!(f, "ERROR: Descriptor set {} binding {} wants both {:?} and {:?} cannot satisfy", s, b, t1, t2),
             Self::PushNameConflict(n, o1, t1, o2, t2) => writeln!(f, "Push constant name {} points to both offset {} ty {:?} and offset {} ty {:?}", n, o1, t1, o2, t2),
@@ -161,6 +183,7 @@ impl std::error::Error for SetResourceError {}
 pub enum BundleBuildError {
     Gpu(gpu::Error),
     MissingField(u32, u32),
+    SetResource(SetResourceError),
 }
 
 impl std::fmt::Display for BundleBuildError {
@@ -172,6 +195,7 @@ impl std::fmt::Display for BundleBuildError {
                 "Error Cannot create bundle set {} binding {} not set",
                 s, b
             ),
+            BundleBuildError::SetResource(e) => writeln!(f, "{}", e),
         }
     }
 }
@@ -183,3 +207,66 @@ impl From<gpu::Error> for BundleBuildError {
         Self::Gpu(e)
     }
 }
+
+impl From<SetResourceError> for BundleBuildError {
+    fn from(e: SetResourceError) -> Self {
+        Self::SetResource(e)
+    }
+}
+
+/// A vertex shader input that a vertex type doesn't supply
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexAttributeMismatch {
+    /// the name of the input in the shader
+    pub name: String,
+    /// the location of the input in the shader
+    pub location: u32,
+    /// the format the shader expects
+    pub expected: gpu::VertexFormat,
+    /// the format the vertex supplies, None if the vertex has no attribute with the name
+    pub found: Option<gpu::VertexFormat>,
+}
+
+impl std::fmt::Display for VertexAttributeMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.found {
+            Some(found) => write!(
+                f,
+                "location {} name {} expected {:?} found {:?}",
+                self.location, self.name, self.expected, found
+            ),
+            None => write!(
+                f,
+                "location {} name {} expected {:?} found no attribute with that name",
+                self.location, self.name, self.expected
+            ),
+        }
+    }
+}
+
+/// Every vertex shader input that a vertex type fails to supply to a [`crate::ReflectedGraphics`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexMismatchReport {
+    /// the name of the vertex type
+    pub vertex: &'static str,
+    /// the name of the graphics
+    pub graphics: Option<String>,
+    /// the inputs that couldn't be matched ordered by location
+    pub mismatches: Vec<VertexAttributeMismatch>,
+}
+
+impl std::fmt::Display for VertexMismatchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "ERROR: Vertex {} doesn't match the vertex inputs of graphics {:?}",
+            self.vertex, self.graphics
+        )?;
+        for mismatch in &self.mismatches {
+            writeln!(f, "    {}", mismatch)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for VertexMismatchReport {}
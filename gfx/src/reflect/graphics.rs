@@ -31,6 +31,7 @@ impl std::cmp::PartialEq for GraphicsPipelineKey {
             && self.viewport.min_depth.to_bits() == other.viewport.min_depth.to_bits()
             && self.viewport.max_depth.to_bits() == other.viewport.max_depth.to_bits()
             && self.pass_hash == other.pass_hash
+            && self.spec_hash == other.spec_hash
     }
 }
 
@@ -46,11 +47,13 @@ impl std::hash::Hash for GraphicsPipelineKey {
         self.viewport.min_depth.to_bits().hash(state);
         self.viewport.max_depth.to_bits().hash(state);
         self.pass_hash.hash(state);
+        self.spec_hash.hash(state);
     }
 }
 
 pub struct VertexLocationInfo {
     pub name: String,
+    pub location: u32,
     pub format: gpu::VertexFormat,
 }
 
@@ -72,8 +75,6 @@ pub struct PipelineData {
 #[derive(Clone)]
 pub struct ReflectedGraphics {
     pub(crate) id: u64,
-    /// Map from RenderPassDesc to RenderPass
-    pub(crate) pass_map: Arc<RwLock<HashMap<gpu::RenderPassDesc<'static>, gpu::RenderPass>>>,
     /// Map from (raw_render_pass, vertex_type) to pipeline
     /// Example usage
     /// ```
@@ -88,6 +89,12 @@ pub struct ReflectedGraphics {
     pub(crate) pipeline_data: PipelineData,
     /// ordered list of vertex inputs required
     pub(crate) vertex_map: Arc<[super::graphics::VertexLocationInfo]>,
+    /// Map from input name to offset in default_buffer of values for inputs missing from vertices
+    pub(crate) default_attributes: Arc<HashMap<String, u32>>,
+    /// Buffer of default values bound to vertex binding 1
+    pub(crate) default_buffer: Option<gpu::Buffer>,
+    /// Values written to default_buffer, kept to recreate it
+    pub(crate) default_values: Arc<[[f32; 4]]>,
     /// Data needed to build bundles and for push_T functions
     pub(crate) reflect_data: super::ReflectData,
 }
@@ -217,9 +224,172 @@ impl ReflectedGraphics {
 
         Ok(Self {
             id: hasher.finish(),
-            pass_map: Arc::new(RwLock::default()),
             pipeline_map: Arc::new(RwLock::default()),
             vertex_map: vertex_map.into(),
+            default_attributes: Arc::default(),
+            default_buffer: None,
+            default_values: Arc::new([]),
+            reflect_data,
+            pipeline_data: PipelineData {
+                layout: pipeline_layout,
+                vertex: vertex_module,
+                fragment: fragment_module,
+                geometry: geometry_module,
+                rasterizer,
+                blend_states: blend_states.to_vec().into(),
+                depth_stencil,
+                name: name.map(|n| n.to_string()),
+                cache,
+            },
+        })
+    }
+
+    /// Create a new Graphics from spirv data that only writes depth
+    ///
+    /// see [`ReflectedGraphics::depth_only`]
+    pub fn depth_only_from_spirv(
+        device: &gpu::Device,
+        vertex: &[u32],
+        fragment: Option<&[u32]>,
+        rasterizer: gpu::Rasterizer,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, error::ReflectedError> {
+        Self::from_spirv(
+            device,
+            vertex,
+            None,
+            fragment,
+            rasterizer,
+            &[],
+            Some(DEPTH_ONLY_STATE),
+            cache,
+            name,
+        )
+    }
+}
+
+/// Depth state of pipelines created by [`ReflectedGraphics::depth_only`]
+#[cfg(feature = "reflect")]
+const DEPTH_ONLY_STATE: gpu::DepthStencilState = gpu::DepthStencilState {
+    depth: Some(gpu::DepthState {
+        test_enable: true,
+        write_enable: true,
+        compare_op: gpu::CompareOp::LessEqual,
+    }),
+    stencil_front: None,
+    stencil_back: None,
+};
+
+#[cfg(feature = "spv")]
+impl ReflectedGraphics {
+    /// Create a new Graphics from [`spv::Builder`]s
+    ///
+    /// The pipeline layout is created from the resources declared on the builders
+    /// rather than by reflecting the compiled spir-v
+    ///
+    /// Inputs declared with automatic locations are moved to the location of the output with the same name in the previous stage,
+    /// see [`spv::Builder::link_inputs`]
+    ///
+    /// If the vertex builder declares a primitive topology it overrides the topology of the rasterizer, see [`spv::Builder::topology`]
+    pub fn from_builders(
+        device: &gpu::Device,
+        vertex: &spv::Builder,
+        geometry: Option<&spv::Builder>,
+        fragment: Option<&spv::Builder>,
+        rasterizer: gpu::Rasterizer,
+        blend_states: &[gpu::BlendState],
+        depth_stencil: Option<gpu::DepthStencilState>,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, error::ReflectedError> {
+        let mut reflect_builder = super::ReflectDataBuilder::new();
+
+        let vertex_entry = vertex
+            .get_entry_name(spv::Stage::Vertex)
+            .ok_or(super::ParseSpirvError::from(super::ReflectError::from(
+                "vertex builder has no vertex entry point",
+            )))?;
+        reflect_builder.parse_builder(vertex, gpu::ShaderStages::VERTEX)?;
+        let vertex_map = super::parse_builder_vertex_states(vertex)?;
+
+        let mut rasterizer = rasterizer;
+        if let Some(topology) = super::parse_builder_topology(vertex) {
+            rasterizer.primitive_topology = topology;
+        }
+
+        let vertex_name = name.as_ref().map(|n| format!("{}_vertex_module", n));
+
+        let vertex_module = device.create_shader_module(&gpu::ShaderModuleDesc {
+            entries: &[(gpu::ShaderStages::VERTEX, vertex_entry)],
+            spirv: &vertex.compile(),
+            name: vertex_name,
+        })?;
+
+        let geometry_module = if let Some(geometry) = geometry {
+            geometry.link_inputs(vertex);
+            super::check_builder_compatibility(vertex, "vertex", geometry, "geometry")?;
+
+            let entry = geometry
+                .get_entry_name(spv::Stage::Geometry)
+                .ok_or(super::ParseSpirvError::from(super::ReflectError::from(
+                    "geometry builder has no geometry entry point",
+                )))?;
+            reflect_builder.parse_builder(geometry, gpu::ShaderStages::GEOMETRY)?;
+
+            let geometry_name = name.as_ref().map(|n| format!("{}_geometry_module", n));
+
+            Some(device.create_shader_module(&gpu::ShaderModuleDesc {
+                entries: &[(gpu::ShaderStages::GEOMETRY, entry)],
+                spirv: &geometry.compile(),
+                name: geometry_name,
+            })?)
+        } else {
+            None
+        };
+
+        let fragment_module = if let Some(fragment) = fragment {
+            if let Some(geometry) = geometry {
+                fragment.link_inputs(geometry);
+                super::check_builder_compatibility(geometry, "geometry", fragment, "fragment")?;
+            } else {
+                fragment.link_inputs(vertex);
+                super::check_builder_compatibility(vertex, "vertex", fragment, "fragment")?;
+            }
+
+            let entry = fragment
+                .get_entry_name(spv::Stage::Fragment)
+                .ok_or(super::ParseSpirvError::from(super::ReflectError::from(
+                    "fragment builder has no fragment entry point",
+                )))?;
+            reflect_builder.parse_builder(fragment, gpu::ShaderStages::FRAGMENT)?;
+
+            let fragment_name = name.as_ref().map(|n| format!("{}_fragment_module", n));
+
+            Some(device.create_shader_module(&gpu::ShaderModuleDesc {
+                entries: &[(gpu::ShaderStages::FRAGMENT, entry)],
+                spirv: &fragment.compile(),
+                name: fragment_name,
+            })?)
+        } else {
+            None
+        };
+
+        let (pipeline_layout, reflect_data) = reflect_builder.build(device, name)?;
+
+        let mut hasher = DefaultHasher::new();
+
+        vertex_module.hash(&mut hasher);
+        fragment_module.hash(&mut hasher);
+        geometry_module.hash(&mut hasher);
+
+        Ok(Self {
+            id: hasher.finish(),
+            pipeline_map: Arc::new(RwLock::default()),
+            vertex_map: vertex_map.into(),
+            default_attributes: Arc::default(),
+            default_buffer: None,
+            default_values: Arc::new([]),
             reflect_data,
             pipeline_data: PipelineData {
                 layout: pipeline_layout,
@@ -236,6 +406,106 @@ impl ReflectedGraphics {
     }
 }
 
+#[cfg(feature = "spv")]
+impl ReflectedGraphics {
+    /// The vertex shader used by fullscreen pipelines
+    ///
+    /// Takes no vertex buffers and outputs a single triangle covering the screen from 3 vertices
+    /// uv coordinates are written to location 0 as a vec2 with (0, 0) in the top left of the screen
+    pub fn fullscreen_vertex() -> spv::Builder {
+        let builder = spv::Builder::new();
+        {
+            let b = &builder;
+            let vertex_index = b.vertex_index();
+            let vk_pos = b.vk_position();
+            let out_uv = b.out_vec2(0, "out_uv");
+
+            b.entry(spv::Stage::Vertex, "main", || {
+                // vertices (-1, -1), (3, -1), (-1, 3) without branching on the index
+                let i = spv::Float::from(vertex_index.load());
+                let x = 4.0 * i * (2.0 - i) - 1.0;
+                let y = 2.0 * i * (i - 1.0) - 1.0;
+                vk_pos.store(b.vec4(x, y, 0.0, 1.0));
+                out_uv.store(b.vec2(x * 0.5 + 0.5, y * 0.5 + 0.5));
+            });
+        }
+        builder
+    }
+
+    /// Create a new Graphics that draws a fullscreen triangle with the fragment shader
+    ///
+    /// The fragment shader can read uv coordinates from a vec2 input at location 0
+    /// Draw with [`crate::CommandEncoder::fullscreen_pass`]
+    pub fn fullscreen(
+        device: &gpu::Device,
+        fragment: &spv::Builder,
+        blend_states: &[gpu::BlendState],
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, error::ReflectedError> {
+        Self::from_builders(
+            device,
+            &Self::fullscreen_vertex(),
+            None,
+            Some(fragment),
+            gpu::Rasterizer::default(),
+            blend_states,
+            None,
+            cache,
+            name,
+        )
+    }
+
+    /// Create a new Graphics that draws a fullscreen triangle with the fragment shader from spirv data
+    ///
+    /// see [`ReflectedGraphics::fullscreen`]
+    pub fn fullscreen_from_spirv(
+        device: &gpu::Device,
+        fragment: &[u32],
+        blend_states: &[gpu::BlendState],
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, error::ReflectedError> {
+        Self::from_spirv(
+            device,
+            &Self::fullscreen_vertex().compile(),
+            None,
+            Some(fragment),
+            gpu::Rasterizer::default(),
+            blend_states,
+            None,
+            cache,
+            name,
+        )
+    }
+
+    /// Create a new Graphics that only writes depth, for shadow maps and depth prepasses
+    ///
+    /// The pipeline has no blend states so is used in passes with only a depth attachment, any color outputs of
+    /// the fragment shader are discarded. The fragment shader is only needed for discarding fragments such as
+    /// with alpha testing. Use the depth bias of the rasterizer to avoid shadow acne
+    pub fn depth_only(
+        device: &gpu::Device,
+        vertex: &spv::Builder,
+        fragment: Option<&spv::Builder>,
+        rasterizer: gpu::Rasterizer,
+        cache: Option<gpu::PipelineCache>,
+        name: Option<&str>,
+    ) -> Result<Self, error::ReflectedError> {
+        Self::from_builders(
+            device,
+            vertex,
+            None,
+            fragment,
+            rasterizer,
+            &[],
+            Some(DEPTH_ONLY_STATE),
+            cache,
+            name,
+        )
+    }
+}
+
 impl ReflectedGraphics {
     /// Create a new BundleBuilder for this Graphics
     ///
@@ -268,31 +538,188 @@ impl ReflectedGraphics {
         self.reflect_data.descriptor_set_layouts.is_some()
     }
 
-    /// create vertex attributes for a type that implements vertex
-    /// to match the pipeline contained in self
-    pub fn vertex_attributes<V: crate::Vertex>(&self) -> Vec<gpu::VertexAttribute> {
-        let attribs = self
-            .vertex_map
-            .iter()
-            .enumerate()
-            .map(|(i, info)| {
-                if let Some((offset, format)) = V::get(&info.name) {
-                    if format == info.format {
-                        gpu::VertexAttribute {
-                            location: i as _,
+    /// Mark the uniform or storage buffers with the supplied names as dynamic
+    ///
+    /// Dynamic buffers have an offset supplied at draw time through
+    /// [`crate::ReflectedGraphicsPass::set_dynamic_offset`] so that one large buffer can be
+    /// bound once in a [`crate::Bundle`] and indexed per draw.
+    /// Bundles have to be created after calling this as the descriptor layouts are recreated
+    ///
+    /// Will panic if a name doesn't refer to a uniform or storage buffer
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/VkDescriptorType.html>
+    pub fn with_dynamic_buffers(mut self, device: &gpu::Device, names: &[&str]) -> Result<Self, gpu::Error> {
+        let name = self.pipeline_data.name.clone();
+        self.pipeline_data.layout = self.reflect_data.make_dynamic(device, names, name.as_ref().map(|n| &**n))?;
+        self.pipeline_map = Arc::new(RwLock::default());
+
+        let mut hasher = DefaultHasher::new();
+        self.id.hash(&mut hasher);
+        names.hash(&mut hasher);
+        self.id = hasher.finish();
+
+        Ok(self)
+    }
+
+    /// Supply constant values for vertex shader inputs that a vertex type doesn't have
+    ///
+    /// For example a default white vertex color for meshes without colors. The values are read
+    /// from a buffer bound at vertex binding 1 so vertex buffers shouldn't be bound there.
+    /// Only the components needed by the input format are read
+    pub fn with_default_attributes(
+        mut self,
+        device: &gpu::Device,
+        defaults: &[(&str, [f32; 4])],
+    ) -> Result<Self, gpu::Error> {
+        let values = defaults.iter().map(|(_, v)| *v).collect::<Vec<_>>();
+        self.default_buffer = self.create_default_buffer(device, &values)?;
+        self.default_values = values.into();
+        if defaults.is_empty() {
+            self.default_attributes = Arc::default();
+        } else {
+            self.default_attributes = Arc::new(
+                defaults
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (n, _))| (n.to_string(), (i * std::mem::size_of::<[f32; 4]>()) as u32))
+                    .collect(),
+            );
+        }
+        self.pipeline_map = Arc::new(RwLock::default());
+
+        let mut hasher = DefaultHasher::new();
+        self.id.hash(&mut hasher);
+        for (name, value) in defaults {
+            name.hash(&mut hasher);
+            value.iter().for_each(|v| v.to_bits().hash(&mut hasher));
+        }
+        self.id = hasher.finish();
+
+        Ok(self)
+    }
+
+    fn create_default_buffer(&self, device: &gpu::Device, values: &[[f32; 4]]) -> Result<Option<gpu::Buffer>, gpu::Error> {
+        if values.is_empty() {
+            return Ok(None);
+        }
+        let buffer = device.create_buffer(&gpu::BufferDesc {
+            name: self
+                .pipeline_data
+                .name
+                .as_ref()
+                .map(|n| format!("{}_default_attributes", n)),
+            size: (values.len() * std::mem::size_of::<[f32; 4]>()) as u64,
+            usage: gpu::BufferUsage::VERTEX,
+            memory: gpu::MemoryType::Host,
+        })?;
+        buffer.slice_ref(..).write(bytemuck::cast_slice(values))?;
+        Ok(Some(buffer))
+    }
+
+    /// Create the same ReflectedGraphics on another device, see [`gpu::Device::on_recreate`]
+    ///
+    /// The shader modules, layouts and default attributes are recreated and the cached passes and pipelines are dropped.
+    /// Bundles have to be recreated from the new ReflectedGraphics
+    pub fn recreate(&self, device: &gpu::Device) -> Result<Self, gpu::Error> {
+        let name = self.pipeline_data.name.as_ref().map(|n| &**n);
+        let mut reflect_data = self.reflect_data.clone();
+        let layout = reflect_data.recreate(device, name)?;
+
+        let vertex = self.pipeline_data.vertex.recreate(device)?;
+        let fragment = self.pipeline_data.fragment.as_ref().map(|m| m.recreate(device)).transpose()?;
+        let geometry = self.pipeline_data.geometry.as_ref().map(|m| m.recreate(device)).transpose()?;
+        let cache = self.pipeline_data.cache
+            .as_ref()
+            .map(|_| device.create_pipeline_cache(&gpu::PipelineCacheDesc {
+                name: name.map(|n| format!("{}_pipeline_cache", n)),
+                initial_data: None,
+            }))
+            .transpose()?;
+
+        let mut hasher = DefaultHasher::new();
+        self.id.hash(&mut hasher);
+        vertex.hash(&mut hasher);
+
+        Ok(Self {
+            id: hasher.finish(),
+            pipeline_map: Arc::new(RwLock::default()),
+            vertex_map: Arc::clone(&self.vertex_map),
+            default_attributes: Arc::clone(&self.default_attributes),
+            default_buffer: self.create_default_buffer(device, &self.default_values)?,
+            default_values: Arc::clone(&self.default_values),
+            reflect_data,
+            pipeline_data: PipelineData {
+                layout,
+                rasterizer: self.pipeline_data.rasterizer,
+                blend_states: Arc::clone(&self.pipeline_data.blend_states),
+                vertex,
+                fragment,
+                geometry,
+                depth_stencil: self.pipeline_data.depth_stencil,
+                cache,
+                name: self.pipeline_data.name.clone(),
+            },
+        })
+    }
+
+    /// Match the attributes of a vertex type to the inputs of the vertex shader by name
+    ///
+    /// Returns the attributes read from the vertex buffer and the attributes read from the default attributes
+    /// or a report of every input that the vertex doesn't supply with the right format
+    pub fn match_vertex<V: crate::Vertex>(
+        &self,
+    ) -> Result<(Vec<gpu::VertexAttribute>, Vec<gpu::VertexAttribute>), error::VertexMismatchReport> {
+        let mut attributes = Vec::new();
+        let mut defaults = Vec::new();
+        let mut mismatches = Vec::new();
+        for info in self.vertex_map.iter() {
+            match V::get(&info.name) {
+                Some((offset, format)) if format == info.format => {
+                    attributes.push(gpu::VertexAttribute {
+                        location: info.location,
+                        format: info.format,
+                        offset,
+                    })
+                }
+                found => {
+                    if let (None, Some(&offset)) = (found, self.default_attributes.get(&info.name)) {
+                        defaults.push(gpu::VertexAttribute {
+                            location: info.location,
                             format: info.format,
                             offset,
-                        }
+                        })
                     } else {
-                        panic!("ERROR: Vertex format type mismatch at position name = {}\nexpected {:?} found {:?}", info.name, info.format, format)
+                        mismatches.push(error::VertexAttributeMismatch {
+                            name: info.name.clone(),
+                            location: info.location,
+                            expected: info.format,
+                            found: found.map(|(_, f)| f),
+                        })
                     }
-                } else {
-                    panic!("ERROR: Vertex doesn't have attribute with name {}", info.name)
                 }
+            }
+        }
+
+        if mismatches.is_empty() {
+            Ok((attributes, defaults))
+        } else {
+            Err(error::VertexMismatchReport {
+                vertex: std::any::type_name::<V>(),
+                graphics: self.pipeline_data.name.clone(),
+                mismatches,
             })
-            .collect::<Vec<_>>();
+        }
+    }
 
-        attribs
+    /// create vertex attributes for a type that implements vertex
+    /// to match the pipeline contained in self
+    ///
+    /// Will panic with a report of every mismatched input if the vertex doesn't match,
+    /// inputs supplied by default attributes aren't included
+    pub fn vertex_attributes<V: crate::Vertex>(&self) -> Vec<gpu::VertexAttribute> {
+        match self.match_vertex::<V>() {
+            Ok((attributes, _)) => attributes,
+            Err(report) => panic!("{}", report),
+        }
     }
 
     /// Get the id of the ReflectedGraphics
@@ -300,10 +727,16 @@ impl ReflectedGraphics {
         self.id
     }
 
-    /// The Reflected Graphics caches [`gpu::RenderPass`] and [`gpu::GraphicsPipeline`] to be reused
+    /// Get the id of the descriptor layouts of the ReflectedGraphics
+    ///
+    /// Pipelines with identical descriptor layouts share a layout id and can use each others bundles
+    pub fn layout_id(&self) -> u64 {
+        self.reflect_data.layout_id()
+    }
+
+    /// The Reflected Graphics caches [`gpu::GraphicsPipeline`]s, each owning the [`gpu::RenderPass`] it was created with
     /// this function will clear all the old pipelines
     pub fn clear(&self) {
-        self.pass_map.write().clear();
         self.pipeline_map.write().clear();
     }
 }
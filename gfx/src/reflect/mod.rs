@@ -76,6 +76,7 @@ pub(crate) fn parse_vertex_states(
                 if let Some(name) = name {
                     info.push((location.loc(), super::graphics::VertexLocationInfo {
                         name,
+                        location: location.loc(),
                         format: match ty {
                             spirq::ty::Type::Scalar(s) => match s {
                                 spirq::ty::ScalarType::Float(c) => match c {
@@ -430,6 +431,130 @@ pub(crate) fn check_stage_compatibility(
     Ok(())
 }
 
+/// Get the vertex inputs of a [`spv::Builder`] in location order
+#[cfg(feature = "spv")]
+pub(crate) fn parse_builder_vertex_states(
+    vertex: &spv::Builder,
+) -> Result<Vec<super::graphics::VertexLocationInfo>, error::ParseSpirvError> {
+    let mut info = Vec::new();
+
+    for input in vertex.get_inputs() {
+        // built in inputs aren't supplied by vertex buffers
+        let location = match input.location {
+            spv::either::Left(l) => l,
+            spv::either::Right(_) => continue,
+        };
+        let name = match input.name {
+            Some(n) => n.to_string(),
+            None => return Err(error::ReflectError("vertex inputs must be named to match them with vertex attributes").into()),
+        };
+        info.push((location, super::graphics::VertexLocationInfo {
+            name,
+            location,
+            format: match input.ty {
+                spv::IOType::Float => gpu::VertexFormat::Float,
+                spv::IOType::Vec2 => gpu::VertexFormat::Vec2,
+                spv::IOType::Vec3 => gpu::VertexFormat::Vec3,
+                spv::IOType::Vec4 => gpu::VertexFormat::Vec4,
+                t => unimplemented!("Vertex input type {:?} not supported at the moment", t),
+            },
+        }));
+    }
+
+    info.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+    Ok(info.into_iter().map(|i| i.1).collect::<Vec<_>>())
+}
+
+/// Get the primitive topology declared on a [`spv::Builder`]
+#[cfg(feature = "spv")]
+pub(crate) fn parse_builder_topology(vertex: &spv::Builder) -> Option<gpu::PrimitiveTopology> {
+    vertex.get_topology().map(|t| match t {
+        spv::PrimitiveTopology::PointList => gpu::PrimitiveTopology::PointList,
+        spv::PrimitiveTopology::LineList => gpu::PrimitiveTopology::LineList,
+        spv::PrimitiveTopology::LineStrip => gpu::PrimitiveTopology::LineStrip,
+        spv::PrimitiveTopology::TriangleList => gpu::PrimitiveTopology::TriangleList,
+        spv::PrimitiveTopology::TriangleStrip => gpu::PrimitiveTopology::TriangleStrip,
+        spv::PrimitiveTopology::TriangleFan => gpu::PrimitiveTopology::TriangleFan,
+    })
+}
+
+#[cfg(feature = "spv")]
+fn get_spv_type_id(ty: &spv::Type) -> TypeId {
+    macro_rules! scalar_type_id {
+        ($s:expr, $t:ident => $wrap:ty) => {
+            match $s {
+                spv::ScalarType::Bool => { type $t = bool; TypeId::of::<$wrap>() },
+                spv::ScalarType::Signed(8) => { type $t = i8; TypeId::of::<$wrap>() },
+                spv::ScalarType::Signed(16) => { type $t = i16; TypeId::of::<$wrap>() },
+                spv::ScalarType::Signed(32) => { type $t = i32; TypeId::of::<$wrap>() },
+                spv::ScalarType::Signed(64) => { type $t = i64; TypeId::of::<$wrap>() },
+                spv::ScalarType::Unsigned(8) => { type $t = u8; TypeId::of::<$wrap>() },
+                spv::ScalarType::Unsigned(16) => { type $t = u16; TypeId::of::<$wrap>() },
+                spv::ScalarType::Unsigned(32) => { type $t = u32; TypeId::of::<$wrap>() },
+                spv::ScalarType::Unsigned(64) => { type $t = u64; TypeId::of::<$wrap>() },
+                spv::ScalarType::Float(32) => { type $t = f32; TypeId::of::<$wrap>() },
+                spv::ScalarType::Float(64) => { type $t = f64; TypeId::of::<$wrap>() },
+                s => panic!("unsupported scalar type in shader {:?}", s),
+            }
+        };
+    }
+
+    match ty {
+        spv::Type::Void => TypeId::of::<()>(),
+        spv::Type::Scalar(s) => scalar_type_id!(s, T => T),
+        spv::Type::Vector(v) => match v.n_scalar {
+            2 => scalar_type_id!(v.scalar_ty, T => [T; 2]),
+            3 => scalar_type_id!(v.scalar_ty, T => [T; 3]),
+            4 => scalar_type_id!(v.scalar_ty, T => [T; 4]),
+            _ => panic!("unsupported vector size in push constant"),
+        },
+        spv::Type::Matrix(m) => {
+            assert_eq!(m.n_vec, m.vec_ty.n_scalar, "ERROR only square matrices are supported in push constant blocks of shaders at the moment");
+            match m.n_vec {
+                2 => scalar_type_id!(m.vec_ty.scalar_ty, T => [[T; 2]; 2]),
+                3 => scalar_type_id!(m.vec_ty.scalar_ty, T => [[T; 3]; 3]),
+                4 => scalar_type_id!(m.vec_ty.scalar_ty, T => [[T; 4]; 4]),
+                _ => panic!("unsupported matrix size in push constant"),
+            }
+        },
+        _ => unimplemented!(),
+    }
+}
+
+/// Check that the outputs of one [`spv::Builder`] match the inputs of the next
+#[cfg(feature = "spv")]
+pub(crate) fn check_builder_compatibility(
+    src: &spv::Builder,
+    src_stage_name: &str,
+    dst: &spv::Builder,
+    dst_stage_name: &str,
+) -> Result<(), error::ParseSpirvError> {
+    let src_outputs = src.get_outputs();
+
+    for input in dst.get_inputs() {
+        let input_loc = match input.location {
+            spv::either::Left(l) => l,
+            spv::either::Right(_) => continue,
+        };
+
+        // src stages can write to outputs not consumed by the input so find rather than iter zip
+        if let Some(output) = src_outputs.iter().find(|o| o.location == spv::either::Left(input_loc)) {
+            if output.ty != input.ty || output.flat != input.flat {
+                Err(error::ParseSpirvError::BuilderStageIncompatibility {
+                    location: input_loc,
+                    src_stage_name: src_stage_name.to_owned(),
+                    src_type: output.ty,
+                    dst_stage_name: dst_stage_name.to_owned(),
+                    dst_type: input.ty,
+                })?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
 impl ReflectDataBuilder {
     pub fn new() -> Self {
         Self {
@@ -495,7 +620,8 @@ impl ReflectDataBuilder {
                             spirq::DescriptorType::CombinedImageSampler() => gpu::DescriptorLayoutEntryType::CombinedTextureSampler,
                             spirq::DescriptorType::SampledImage() => gpu::DescriptorLayoutEntryType::SampledTexture,
                             spirq::DescriptorType::StorageImage(a) => gpu::DescriptorLayoutEntryType::StorageTexture { read_only: a == spirq::AccessType::ReadOnly },
-                            spirq::DescriptorType::UniformTexelBuffer() => gpu::DescriptorLayoutEntryType::UniformBuffer,
+                            spirq::DescriptorType::UniformTexelBuffer() => gpu::DescriptorLayoutEntryType::UniformTexelBuffer,
+                            spirq::DescriptorType::StorageTexelBuffer(a) => gpu::DescriptorLayoutEntryType::StorageTexelBuffer { read_only: a == spirq::AccessType::ReadOnly },
                             spirq::DescriptorType::UniformBuffer() => gpu::DescriptorLayoutEntryType::UniformBuffer,
                             spirq::DescriptorType::StorageBuffer(a) => gpu::DescriptorLayoutEntryType::StorageBuffer { read_only: a == spirq::AccessType::ReadOnly },
                             t => unimplemented!("Descriptor type {:?} not supported at the moment", t),
@@ -565,6 +691,102 @@ impl ReflectDataBuilder {
         Ok(name.unwrap())
     }
 
+    /// Update selfs internal state from the resources declared on the builder
+    #[cfg(feature = "spv")]
+    pub fn parse_builder(&mut self, builder: &spv::Builder, stages: gpu::ShaderStages) -> Result<(), error::ParseSpirvError> {
+        let mut descriptors = Vec::new();
+
+        for uniform in builder.get_uniforms() {
+            descriptors.push((uniform.set, uniform.binding, uniform.name, gpu::DescriptorLayoutEntryType::UniformBuffer, 1));
+        }
+        for storage in builder.get_storages() {
+            descriptors.push((storage.set, storage.binding, storage.name, gpu::DescriptorLayoutEntryType::StorageBuffer { read_only: !storage.write }, 1));
+        }
+        for texture in builder.get_textures() {
+            let ty = match texture.ty.format {
+                spv::TextureSpvFormat::Color(_) => gpu::DescriptorLayoutEntryType::StorageTexture { read_only: false },
+                _ => gpu::DescriptorLayoutEntryType::SampledTexture,
+            };
+            descriptors.push((texture.set, texture.binding, texture.name, ty, 1));
+        }
+        for texture in builder.get_sampled_textures() {
+            descriptors.push((texture.set, texture.binding, texture.name, gpu::DescriptorLayoutEntryType::CombinedTextureSampler, texture.count.unwrap_or(1)));
+        }
+        for sampler in builder.get_samplers() {
+            descriptors.push((sampler.set, sampler.binding, sampler.name, gpu::DescriptorLayoutEntryType::Sampler, 1));
+        }
+
+        for (set, bind, name, gpu_ty, count) in descriptors {
+            if let Some(name) = name {
+                let prev = self.descriptor_set_names.insert(name.to_string(), (set, bind));
+                if let Some((pset, pbind)) = prev {
+                    if pset != set || pbind != bind {
+                        return Err(error::ParseSpirvError::DescriptorNameUndecidable(name.to_string(), set, bind, pset, pbind));
+                    }
+                }
+            }
+
+            let map = self.descriptor_set_layout_entries.entry(set).or_insert(HashMap::new());
+            let e = map.entry(bind).or_insert(gpu::DescriptorLayoutEntry {
+                ty: gpu_ty,
+                stage: stages,
+                count: std::num::NonZeroU32::new(count).unwrap(),
+            });
+            e.stage |= stages;
+            if e.ty != gpu_ty {
+                return Err(error::ParseSpirvError::DescriptorTypeConflict(set, bind, e.ty, gpu_ty))
+            }
+        }
+
+        if let Some(push) = builder.get_push_constants() {
+            if let spv::Type::Struct(s) = &push.ty {
+                self.push_constant_ranges.push(gpu::PushConstantRange {
+                    stage: stages,
+                    offset: 0,
+                    size: s.size().expect("ERROR: push constant block must have a known size"),
+                });
+
+                for member in s.members.iter() {
+                    if let Some(n) = &member.name {
+                        let n = n.as_ref().either(|n| n.to_string(), |n| n.clone());
+                        let ty_id = get_spv_type_id(&member.ty);
+                        let info = super::PushConstantInfo {
+                            offset: member.offset,
+                            stages,
+                            type_id: ty_id,
+                        };
+                        let prev = self.push_constant_names.entry(n.clone()).or_insert(info);
+                        prev.stages |= stages;
+                        if prev.offset != member.offset || prev.type_id != ty_id {
+                            return Err(error::ParseSpirvError::PushNameConflict(n, member.offset, ty_id, prev.offset, prev.type_id))
+                        }
+                    }
+                }
+            } else {
+                self.push_constant_ranges.push(gpu::PushConstantRange {
+                    stage: stages,
+                    offset: 0,
+                    size: push.ty.size().expect("ERROR: push constant block must have a known size"),
+                });
+                if let Some(n) = push.name {
+                    let ty_id = get_spv_type_id(&push.ty);
+                    let info = super::PushConstantInfo {
+                        offset: 0,
+                        stages,
+                        type_id: ty_id,
+                    };
+                    let prev = self.push_constant_names.entry(n.to_string()).or_insert(info);
+                    prev.stages |= stages;
+                    if prev.offset != 0 || prev.type_id != ty_id {
+                        return Err(error::ParseSpirvError::PushNameConflict(n.to_string(), 0, ty_id, prev.offset, prev.type_id))
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn build(self, device: &gpu::Device, name: Option<&str>) -> Result<(gpu::PipelineLayout, ReflectData), gpu::Error> {
         // sort the hashmaps into ordered vecs
         let mut sorted = self.descriptor_set_layout_entries
@@ -595,6 +817,7 @@ impl ReflectDataBuilder {
                 let l = device.create_descriptor_layout(&gpu::DescriptorLayoutDesc {
                     name: layout_name,
                     entries: &v,
+                    immutable_samplers: &[],
                 });
                 i += 1;
                 l
@@ -639,6 +862,9 @@ impl ReflectDataBuilder {
             } else {
                 None
             },
+            push_constant_ranges: self.push_constant_ranges.into(),
+            dynamic_offset_names: None,
+            dynamic_offset_count: 0,
         };
 
         Ok((pipeline_layout, reflect_data))
@@ -653,8 +879,142 @@ pub(crate) struct ReflectData {
     pub descriptor_set_layouts: Option<Arc<[gpu::DescriptorLayout]>>,
     pub push_constant_names: Option<HashMap<String, PushConstantInfo>>,
     pub specialization_names: Option<HashMap<String, SpecConstantInfo>>,
+    pub push_constant_ranges: Arc<[gpu::PushConstantRange]>,
+    /// map from name to the index of the first dynamic offset for that descriptor
+    pub dynamic_offset_names: Option<HashMap<String, usize>>,
+    /// the total number of dynamic offsets needed when binding all the descriptor sets
+    pub dynamic_offset_count: usize,
+}
+
+/// Hash the ids of descriptor layouts in set order
+///
+/// Identical layouts share an id so pipelines with equal layout ids can bind each others descriptor sets
+pub(crate) fn layout_id(ids: impl Iterator<Item = u64>) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for id in ids {
+        id.hash(&mut hasher);
+    }
+    hasher.finish()
 }
 
+impl ReflectData {
+    /// The id of the descriptor layouts, bundles with the same layout id can be bound
+    pub fn layout_id(&self) -> u64 {
+        layout_id(self
+            .descriptor_set_layouts
+            .iter()
+            .flat_map(|l| l.iter())
+            .map(|l| l.id()))
+    }
+
+    /// Recreate the descriptor layouts on another device returning the new pipeline layout
+    pub fn recreate(&mut self, device: &gpu::Device, name: Option<&str>) -> Result<gpu::PipelineLayout, gpu::Error> {
+        let descriptor_set_layouts = self.descriptor_set_layouts
+            .as_ref()
+            .map(|layouts| layouts
+                .iter()
+                .enumerate()
+                .map(|(i, l)| {
+                    device.create_descriptor_layout(&gpu::DescriptorLayoutDesc {
+                        name: name.as_ref().map(|n| format!("{}_descriptor_layout_{}", n, i)),
+                        entries: l.entries(),
+                        immutable_samplers: &[],
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>())
+            .transpose()?
+            .unwrap_or_default();
+
+        let pipeline_layout = device.create_pipeline_layout(&gpu::PipelineLayoutDesc {
+            name: name.as_ref().map(|n| format!("{}_pipeline_layout", n)),
+            descriptor_sets: &descriptor_set_layouts.iter().collect::<Vec<_>>(),
+            push_constants: &self.push_constant_ranges,
+        })?;
+
+        if self.descriptor_set_layouts.is_some() {
+            self.descriptor_set_layouts = Some(descriptor_set_layouts.into());
+        }
+
+        Ok(pipeline_layout)
+    }
+
+    /// Change the uniform and storage buffers with the supplied names into dynamic buffers
+    /// recreating the descriptor layouts and returning the new pipeline layout
+    pub fn make_dynamic(&mut self, device: &gpu::Device, names: &[&str], name: Option<&str>) -> Result<gpu::PipelineLayout, gpu::Error> {
+        let map = self.descriptor_set_map
+            .as_ref()
+            .expect("ERROR: Attempt to make buffers dynamic in pipeline without descriptors");
+        
+        let mut entries = self.descriptor_set_layouts
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|l| l.entries().to_vec())
+            .collect::<Vec<_>>();
+
+        for &n in names {
+            let (set, binding) = match map.get(n) {
+                Some(&l) => l,
+                None => panic!("ERROR: Attempt to make descriptor {} dynamic, no descriptor by that name", n),
+            };
+            let entry = &mut entries[set as usize][binding as usize];
+            entry.ty = match entry.ty {
+                gpu::DescriptorLayoutEntryType::UniformBuffer 
+                | gpu::DescriptorLayoutEntryType::UniformBufferDynamic => gpu::DescriptorLayoutEntryType::UniformBufferDynamic,
+                gpu::DescriptorLayoutEntryType::StorageBuffer { read_only } 
+                | gpu::DescriptorLayoutEntryType::StorageBufferDynamic { read_only } => gpu::DescriptorLayoutEntryType::StorageBufferDynamic { read_only },
+                t => panic!("ERROR: Attempt to make descriptor {} dynamic, type {:?} is not a uniform or storage buffer", n, t),
+            };
+        }
+
+        // dynamic offsets are consumed in order of set then binding
+        let mut offset_indices = HashMap::new();
+        let mut count = 0;
+        for (set, v) in entries.iter().enumerate() {
+            for (binding, e) in v.iter().enumerate() {
+                if e.ty.is_dynamic() {
+                    offset_indices.insert((set as u32, binding as u32), count);
+                    count += e.count.get() as usize;
+                }
+            }
+        }
+
+        let descriptor_set_layouts = entries
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                device.create_descriptor_layout(&gpu::DescriptorLayoutDesc {
+                    name: name.as_ref().map(|n| format!("{}_descriptor_layout_{}", n, i)),
+                    entries: v,
+                    immutable_samplers: &[],
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let pipeline_layout = device.create_pipeline_layout(&gpu::PipelineLayoutDesc {
+            name: name.as_ref().map(|n| format!("{}_pipeline_layout", n)),
+            descriptor_sets: &descriptor_set_layouts.iter().collect::<Vec<_>>(),
+            push_constants: &self.push_constant_ranges,
+        })?;
+
+        self.dynamic_offset_names = Some(map
+            .iter()
+            .filter_map(|(n, l)| offset_indices.get(l).map(|&i| (n.clone(), i)))
+            .collect());
+        self.dynamic_offset_count = count;
+        self.descriptor_set_types = Some(entries
+            .iter()
+            .map(|v| v.iter().map(|e| (e.ty, e.count.get())).collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+            .into());
+        self.descriptor_set_layouts = Some(descriptor_set_layouts.into());
+
+        Ok(pipeline_layout)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SpecVal {
     Int(i32),
     UInt(u32),
@@ -716,4 +1076,83 @@ impl SpecVal {
             SpecVal::DVec4(d) => bytemuck::bytes_of(d),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Specialization constants to create a pipeline with
+///
+/// Constants can be set by name using reflected data or by the constant_id declared in the shader
+/// Any constants not set will use the default value declared in the shader
+/// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/VkSpecializationInfo.html>
+#[derive(Debug, Clone, Default)]
+pub struct SpecConstants {
+    names: Vec<(String, SpecVal)>,
+    ids: Vec<(u32, SpecVal)>,
+}
+
+impl SpecConstants {
+    /// Create a new empty set of specialization constants
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the specialization constant by name
+    ///
+    /// Will panic when used to create a pass if there is no constant by name or the type is different to in the shader
+    pub fn set(mut self, name: &str, value: SpecVal) -> Self {
+        self.names.push((name.to_string(), value));
+        self
+    }
+
+    /// Set the specialization constant by the constant_id declared in the shader
+    ///
+    /// The type of the value isn't checked against the shader
+    pub fn specialize(mut self, id: u32, value: SpecVal) -> Self {
+        self.ids.push((id, value));
+        self
+    }
+
+    /// Returns true if no constants have been set
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.ids.is_empty()
+    }
+
+    /// Get the entries and data for the shader stage
+    pub(crate) fn entries(
+        &self,
+        names: Option<&HashMap<String, SpecConstantInfo>>,
+        stage: gpu::ShaderStages,
+    ) -> (Vec<gpu::SpecializationEntry>, Vec<u8>) {
+        let mut entries = Vec::new();
+        let mut data = Vec::new();
+        let mut push = |id: u32, value: &SpecVal| {
+            let bytes = value.bytes();
+            // later values replace earlier values with the same id
+            entries.retain(|e: &gpu::SpecializationEntry| e.id != id);
+            entries.push(gpu::SpecializationEntry {
+                id,
+                offset: data.len() as u32,
+                size: bytes.len(),
+            });
+            data.extend_from_slice(bytes);
+        };
+
+        for (name, value) in &self.names {
+            let info = match names.and_then(|m| m.get(name)) {
+                Some(info) => info,
+                None => panic!("ERROR: No specialization constant found with name {}", name),
+            };
+            if info.type_id != value.type_id() {
+                panic!("ERROR: Specialization constant {} set with value {:?} of different type than in spirv", name, value);
+            }
+            if let Some((id, _)) = info.stages.iter().find(|(_, s)| s.contains(stage)) {
+                push(*id, value);
+            }
+        }
+
+        for (id, value) in &self.ids {
+            push(*id, value);
+        }
+
+        (entries, data)
+    }
+}
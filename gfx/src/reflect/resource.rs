@@ -116,6 +116,25 @@ impl Resource for gpu::Sampler {
     }
 }
 
+impl Resource for gpu::BufferView {
+    fn set<'a>(
+        &'a self,
+        builder: BundleBuilder<'a>,
+        name: &str,
+    ) -> Result<BundleBuilder<'a>, error::SetResourceError> {
+        builder.set_texel_buffer_ref(name, self)
+    }
+
+    fn set_by_location<'a>(
+        &'a self,
+        builder: BundleBuilder<'a>,
+        set: usize,
+        binding: usize,
+    ) -> Result<BundleBuilder<'a>, error::SetResourceError> {
+        builder.set_texel_buffer_ref_by_location(set, binding, self)
+    }
+}
+
 impl Resource for gpu::BufferSlice<'_> {
     fn set<'a>(
         &'a self,
@@ -0,0 +1,405 @@
+//! Render targets that follow the size of the swapchain
+//!
+//! Instead of recreating every render target and the bundles that reference them by hand when the
+//! window is resized, declare targets with a [`TargetSize`] relative to the swapchain and register
+//! anything built from them as a dependent. A single call to [`ResourceRegistry::resize`] then
+//! recreates the targets that changed size and rebuilds the dependents that use them.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// The size of a render target in a [`ResourceRegistry`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetSize {
+    /// A fixed size in pixels that doesn't change when the registry is resized
+    Absolute(gpu::Size, gpu::Size),
+    /// A multiple of the registry extent, Relative(0.5) is half the width and half the height
+    Relative(f32),
+    /// Separate multiples of the registry width and height
+    RelativeXY(f32, f32),
+}
+
+impl TargetSize {
+    /// The size in pixels for the extent, never less than 1
+    pub fn resolve(&self, extent: gpu::Extent2D) -> (gpu::Size, gpu::Size) {
+        let (width, height) = match *self {
+            TargetSize::Absolute(w, h) => (w, h),
+            TargetSize::Relative(s) => (
+                (extent.width as f32 * s) as gpu::Size,
+                (extent.height as f32 * s) as gpu::Size,
+            ),
+            TargetSize::RelativeXY(x, y) => (
+                (extent.width as f32 * x) as gpu::Size,
+                (extent.height as f32 * y) as gpu::Size,
+            ),
+        };
+        (width.max(1), height.max(1))
+    }
+
+    /// Returns true if the size depends on the registry extent
+    pub fn is_relative(&self) -> bool {
+        !matches!(self, TargetSize::Absolute(..))
+    }
+}
+
+/// Describes a render target owned by a [`ResourceRegistry`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TargetDesc {
+    /// the size of the target
+    pub size: TargetSize,
+    /// the format of the target
+    pub format: gpu::Format,
+    /// the usage of the target
+    pub usage: gpu::TextureUsage,
+    /// the number of samples of the target
+    pub samples: gpu::Samples,
+    /// the number of mip levels of the target
+    pub mip_levels: u32,
+}
+
+impl TargetDesc {
+    /// Describe a single sampled target with one mip level
+    pub fn new(size: TargetSize, format: gpu::Format, usage: gpu::TextureUsage) -> Self {
+        Self {
+            size,
+            format,
+            usage,
+            samples: gpu::Samples::S1,
+            mip_levels: 1,
+        }
+    }
+}
+
+/// An error from creating the resources of a [`ResourceRegistry`]
+#[derive(Debug)]
+pub enum RegistryError {
+    /// An error from the gpu creating a target
+    Gpu(gpu::Error),
+    /// An error returned by the function building the dependent by name
+    Dependent(String, Box<dyn std::error::Error>),
+}
+
+impl std::fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gpu(e) => writeln!(f, "{}", e),
+            Self::Dependent(n, e) => {
+                writeln!(f, "ERROR: Failed to build registry dependent {}: {}", n, e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+impl From<gpu::Error> for RegistryError {
+    fn from(e: gpu::Error) -> Self {
+        Self::Gpu(e)
+    }
+}
+
+/// The render targets of a [`ResourceRegistry`]
+///
+/// Passed to dependents when they are built so that they can reference the current targets
+#[derive(Debug)]
+pub struct RegistryTargets {
+    extent: gpu::Extent2D,
+    textures: HashMap<String, (TargetDesc, crate::GTexture2D)>,
+}
+
+impl RegistryTargets {
+    /// The extent that relative targets are sized from
+    pub fn extent(&self) -> gpu::Extent2D {
+        self.extent
+    }
+
+    /// Get the target with the name supplied
+    pub fn texture(&self, name: &str) -> &crate::GTexture2D {
+        match self.textures.get(name) {
+            Some((_, t)) => t,
+            None => panic!("ERROR: ResourceRegistry has no target called {}", name),
+        }
+    }
+
+    /// Get the target with the name supplied if there is one
+    pub fn get(&self, name: &str) -> Option<&crate::GTexture2D> {
+        self.textures.get(name).map(|(_, t)| t)
+    }
+
+    /// Get the description of the target with the name supplied if there is one
+    pub fn desc(&self, name: &str) -> Option<&TargetDesc> {
+        self.textures.get(name).map(|(d, _)| d)
+    }
+}
+
+/// A function that builds a dependent of a registry
+type DependentFn =
+    Box<dyn FnMut(&gpu::Device, &RegistryTargets) -> Result<Box<dyn Any>, RegistryError>>;
+
+struct Dependent {
+    name: String,
+    targets: Vec<String>,
+    build: DependentFn,
+    value: Box<dyn Any>,
+}
+
+/// Owns render targets sized relative to the swapchain and resources built from them
+///
+/// Each frame
+/// - call [`ResourceRegistry::resize`] with the swapchain extent when the window is resized
+/// - get targets with [`ResourceRegistry::target`] and dependents with [`ResourceRegistry::get`]
+///
+/// Dependents are rebuilt in the order that they were added whenever one of the targets they
+/// list is recreated. Dependents that list no targets are rebuilt on every resize.
+pub struct ResourceRegistry {
+    targets: RegistryTargets,
+    dependents: Vec<Dependent>,
+    generation: u64,
+    name: Option<String>,
+}
+
+impl std::fmt::Debug for ResourceRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResourceRegistry")
+            .field("name", &self.name)
+            .field("targets", &self.targets)
+            .field(
+                "dependents",
+                &self.dependents.iter().map(|d| &d.name).collect::<Vec<_>>(),
+            )
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+impl ResourceRegistry {
+    /// Create a new empty registry, extent is usually the extent of the swapchain
+    pub fn new(extent: gpu::Extent2D, name: Option<&str>) -> Self {
+        Self {
+            targets: RegistryTargets {
+                extent,
+                textures: HashMap::new(),
+            },
+            dependents: Vec::new(),
+            generation: 0,
+            name: name.map(|n| n.to_string()),
+        }
+    }
+
+    /// Add a render target to the registry, replacing any target with the same name
+    ///
+    /// Dependents of a replaced target are rebuilt
+    pub fn add_target(
+        &mut self,
+        device: &gpu::Device,
+        name: &str,
+        desc: TargetDesc,
+    ) -> Result<&crate::GTexture2D, RegistryError> {
+        let texture = self.create_target(device, name, &desc)?;
+        let replaced = self
+            .targets
+            .textures
+            .insert(name.to_string(), (desc, texture))
+            .is_some();
+        if replaced {
+            let mut changed = HashSet::new();
+            changed.insert(name.to_string());
+            self.rebuild(device, &changed, false)?;
+        }
+        Ok(self.targets.texture(name))
+    }
+
+    /// Add a dependent built from the targets, it is built immediately and again whenever one of
+    /// the targets named is recreated
+    ///
+    /// Typically used for bundles that sample from a target or passes that cache per size state,
+    /// if targets is empty the dependent is rebuilt on every resize
+    pub fn add_dependent<T, E, F>(
+        &mut self,
+        device: &gpu::Device,
+        name: &str,
+        targets: &[&str],
+        mut build: F,
+    ) -> Result<&T, RegistryError>
+    where
+        T: Any,
+        E: std::error::Error + 'static,
+        F: FnMut(&gpu::Device, &RegistryTargets) -> Result<T, E> + 'static,
+    {
+        for target in targets {
+            if !self.targets.textures.contains_key(*target) {
+                panic!(
+                    "ERROR: Attempt to add dependent {} of target {} not in ResourceRegistry",
+                    name, target
+                );
+            }
+        }
+        let dependent_name = name.to_string();
+        let mut build: DependentFn = Box::new(move |device, targets| {
+            build(device, targets)
+                .map(|v| Box::new(v) as Box<dyn Any>)
+                .map_err(|e| RegistryError::Dependent(dependent_name.clone(), Box::new(e)))
+        });
+        let value = build(device, &self.targets)?;
+        self.dependents.retain(|d| d.name != name);
+        self.dependents.push(Dependent {
+            name: name.to_string(),
+            targets: targets.iter().map(|t| t.to_string()).collect(),
+            build,
+            value,
+        });
+        Ok(self.get(name))
+    }
+
+    /// Resize relative targets to the new extent and rebuild their dependents
+    ///
+    /// Returns false without doing anything if the extent hasn't changed
+    pub fn resize(
+        &mut self,
+        device: &gpu::Device,
+        extent: gpu::Extent2D,
+    ) -> Result<bool, RegistryError> {
+        if extent == self.targets.extent {
+            return Ok(false);
+        }
+        self.targets.extent = extent;
+
+        let mut changed = HashSet::new();
+        let names = self.targets.textures.keys().cloned().collect::<Vec<_>>();
+        for name in names {
+            let desc = self.targets.textures[&name].0;
+            let (width, height) = desc.size.resolve(extent);
+            let current = &self.targets.textures[&name].1;
+            if current.width() == width && current.height() == height {
+                continue;
+            }
+            let texture = self.create_target(device, &name, &desc)?;
+            self.targets.textures.insert(name.clone(), (desc, texture));
+            changed.insert(name);
+        }
+
+        self.rebuild(device, &changed, true)?;
+        Ok(true)
+    }
+
+    /// The extent that relative targets are sized from
+    pub fn extent(&self) -> gpu::Extent2D {
+        self.targets.extent
+    }
+
+    /// Incremented every time a target or dependent is recreated
+    ///
+    /// Compare against a stored value to detect when references to registry resources are stale
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Get the target with the name supplied
+    pub fn target(&self, name: &str) -> &crate::GTexture2D {
+        self.targets.texture(name)
+    }
+
+    /// The targets of the registry
+    pub fn targets(&self) -> &RegistryTargets {
+        &self.targets
+    }
+
+    /// Get the dependent with the name supplied
+    ///
+    /// Will panic if there is no dependent by the name or it isn't of type T
+    pub fn get<T: Any>(&self, name: &str) -> &T {
+        match self.try_get(name) {
+            Some(v) => v,
+            None => panic!(
+                "ERROR: ResourceRegistry has no dependent called {} of type {}",
+                name,
+                std::any::type_name::<T>()
+            ),
+        }
+    }
+
+    /// Get the dependent with the name supplied if there is one of type T
+    pub fn try_get<T: Any>(&self, name: &str) -> Option<&T> {
+        self.dependents
+            .iter()
+            .find(|d| d.name == name)
+            .and_then(|d| d.value.downcast_ref())
+    }
+
+    /// Get the dependent with the name supplied mutably
+    ///
+    /// Will panic if there is no dependent by the name or it isn't of type T
+    pub fn get_mut<T: Any>(&mut self, name: &str) -> &mut T {
+        match self
+            .dependents
+            .iter_mut()
+            .find(|d| d.name == name)
+            .and_then(|d| d.value.downcast_mut())
+        {
+            Some(v) => v,
+            None => panic!(
+                "ERROR: ResourceRegistry has no dependent called {} of type {}",
+                name,
+                std::any::type_name::<T>()
+            ),
+        }
+    }
+
+    /// Remove a target and any dependents of it from the registry
+    pub fn remove_target(&mut self, name: &str) -> Option<crate::GTexture2D> {
+        let (_, texture) = self.targets.textures.remove(name)?;
+        let uses_target = |d: &Dependent| d.targets.iter().any(|t| t == name);
+        self.dependents.retain(|d| !uses_target(d));
+        self.generation += 1;
+        Some(texture)
+    }
+
+    /// Remove a dependent from the registry
+    pub fn remove_dependent(&mut self, name: &str) {
+        self.dependents.retain(|d| d.name != name);
+    }
+
+    fn create_target(
+        &self,
+        device: &gpu::Device,
+        name: &str,
+        desc: &TargetDesc,
+    ) -> Result<crate::GTexture2D, gpu::Error> {
+        let (width, height) = desc.size.resolve(self.targets.extent);
+        crate::GTexture2D::new(
+            device,
+            width,
+            height,
+            desc.samples,
+            desc.usage,
+            desc.mip_levels,
+            desc.format,
+            Some(&match &self.name {
+                Some(n) => format!("{}_{}", n, name),
+                None => name.to_string(),
+            }),
+        )
+    }
+
+    /// Rebuild the dependents of the changed targets in the order they were added
+    fn rebuild(
+        &mut self,
+        device: &gpu::Device,
+        changed: &HashSet<String>,
+        resized: bool,
+    ) -> Result<(), RegistryError> {
+        self.generation += 1;
+        for dependent in &mut self.dependents {
+            let stale = if dependent.targets.is_empty() {
+                resized
+            } else {
+                dependent.targets.iter().any(|t| changed.contains(t))
+            };
+            if stale {
+                dependent.value = (dependent.build)(device, &self.targets)?;
+            }
+        }
+        Ok(())
+    }
+}
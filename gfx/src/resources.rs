@@ -0,0 +1,283 @@
+//! An arena of long lived resources referenced by handles
+//!
+//! Instead of storing every `Bundle`, `ReflectedGraphics` and texture in a struct
+//! passed around the app, insert them into [`Resources`] and store the returned [`Handle`]s. Handles
+//! are cheap to copy, typed, and become stale when the resource is removed so a removed slot being
+//! reused can't be mistaken for the old resource.
+//!
+//! Removed resources may still be in use by command buffers in flight so they are kept alive until
+//! [`Resources::end_frame`] has been called frames_in_flight times.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// A typed reference to a resource in a [`Resources`]
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    /// The index of the slot in the arena, slots are reused after resources are removed
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// The generation of the slot when the resource was inserted
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> std::hash::Hash for Handle<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Handle<{}> index: {} generation: {}",
+            std::any::type_name::<T>(),
+            self.index,
+            self.generation
+        )
+    }
+}
+
+struct Slot {
+    generation: u32,
+    value: Option<Box<dyn Any>>,
+    name: Option<String>,
+}
+
+/// Owns resources of any type referenced by [`Handle`]s and optionally by name
+///
+/// Each frame
+/// - get resources with [`Resources::get`] or [`Resources::by_name`] when recording
+/// - call [`Resources::end_frame`] once the frame has been submitted
+///
+/// frames_in_flight should match the number of command buffers cycled through, as the app waits on the
+/// fence of a command buffer before reusing it a resource removed frames_in_flight frames ago can't be in use
+pub struct Resources {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    names: HashMap<String, u32>,
+    /// removed resources and the frame they were removed in
+    pending: Vec<(u64, Box<dyn Any>)>,
+    frame: u64,
+    frames_in_flight: u64,
+    name: Option<String>,
+}
+
+impl std::fmt::Debug for Resources {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Resources")
+            .field("name", &self.name)
+            .field("len", &self.len())
+            .field("names", &self.names.keys().collect::<Vec<_>>())
+            .field("pending", &self.pending.len())
+            .field("frame", &self.frame)
+            .finish()
+    }
+}
+
+impl Resources {
+    /// Create a new empty arena
+    pub fn new(frames_in_flight: usize, name: Option<&str>) -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            names: HashMap::new(),
+            pending: Vec::new(),
+            frame: 0,
+            frames_in_flight: frames_in_flight as u64,
+            name: name.map(|n| n.to_string()),
+        }
+    }
+
+    /// Insert a resource returning a handle to it
+    pub fn insert<T: Any>(&mut self, value: T) -> Handle<T> {
+        self.insert_slot(Box::new(value), None)
+    }
+
+    /// Insert a resource that can also be found by name
+    ///
+    /// A resource already inserted with the name is removed as if by [`Resources::remove`]
+    pub fn insert_named<T: Any>(&mut self, name: &str, value: T) -> Handle<T> {
+        if let Some(index) = self.names.remove(name) {
+            self.remove_slot(index);
+        }
+        let handle = self.insert_slot(Box::new(value), Some(name.to_string()));
+        self.names.insert(name.to_string(), handle.index);
+        handle
+    }
+
+    fn insert_slot<T>(&mut self, value: Box<dyn Any>, name: Option<String>) -> Handle<T> {
+        let index = match self.free.pop() {
+            Some(i) => i,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: None,
+                    name: None,
+                });
+                (self.slots.len() - 1) as u32
+            }
+        };
+        let slot = &mut self.slots[index as usize];
+        slot.value = Some(value);
+        slot.name = name;
+        Handle {
+            index,
+            generation: slot.generation,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns true if the handle refers to a resource that hasn't been removed
+    pub fn contains<T: Any>(&self, handle: Handle<T>) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// Get the resource the handle refers to or None if it has been removed
+    pub fn get<T: Any>(&self, handle: Handle<T>) -> Option<&T> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|s| s.generation == handle.generation)
+            .and_then(|s| s.value.as_ref())
+            .and_then(|v| v.downcast_ref())
+    }
+
+    /// Get the resource the handle refers to mutably or None if it has been removed
+    pub fn get_mut<T: Any>(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|s| s.generation == handle.generation)
+            .and_then(|s| s.value.as_mut())
+            .and_then(|v| v.downcast_mut())
+    }
+
+    /// Get a handle to the resource with the name if there is one of type T
+    pub fn handle<T: Any>(&self, name: &str) -> Option<Handle<T>> {
+        let index = *self.names.get(name)?;
+        let slot = &self.slots[index as usize];
+        if !slot.value.as_ref()?.is::<T>() {
+            return None;
+        }
+        Some(Handle {
+            index,
+            generation: slot.generation,
+            marker: PhantomData,
+        })
+    }
+
+    /// Get the resource with the name if there is one of type T
+    pub fn by_name<T: Any>(&self, name: &str) -> Option<&T> {
+        self.get(self.handle::<T>(name)?)
+    }
+
+    /// Get the resource with the name mutably if there is one of type T
+    pub fn by_name_mut<T: Any>(&mut self, name: &str) -> Option<&mut T> {
+        let handle = self.handle::<T>(name)?;
+        self.get_mut(handle)
+    }
+
+    /// Get the name the resource was inserted with if any
+    pub fn name_of<T: Any>(&self, handle: Handle<T>) -> Option<&str> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|s| s.generation == handle.generation && s.value.is_some())
+            .and_then(|s| s.name.as_deref())
+    }
+
+    /// Remove the resource, it is dropped once frames_in_flight more frames have ended
+    ///
+    /// Returns false if the handle had already been removed
+    pub fn remove<T: Any>(&mut self, handle: Handle<T>) -> bool {
+        if !self.contains(handle) {
+            return false;
+        }
+        self.remove_slot(handle.index);
+        true
+    }
+
+    /// Remove the resource and return it immediately
+    ///
+    /// The caller is responsible for keeping it alive until the gpu has finished with it
+    pub fn take<T: Any>(&mut self, handle: Handle<T>) -> Option<T> {
+        if !self.contains(handle) {
+            return None;
+        }
+        let value = self.release_slot(handle.index);
+        value.downcast().ok().map(|v| *v)
+    }
+
+    fn remove_slot(&mut self, index: u32) {
+        let value = self.release_slot(index);
+        self.pending.push((self.frame, value));
+    }
+
+    /// Empty the slot so that handles to it are stale and it can be reused
+    fn release_slot(&mut self, index: u32) -> Box<dyn Any> {
+        let slot = &mut self.slots[index as usize];
+        let value = slot.value.take().unwrap();
+        if let Some(name) = slot.name.take() {
+            self.names.remove(&name);
+        }
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(index);
+        value
+    }
+
+    /// Mark the end of a frame, dropping resources removed frames_in_flight frames ago
+    pub fn end_frame(&mut self) {
+        self.frame += 1;
+        let frame = self.frame;
+        let frames_in_flight = self.frames_in_flight;
+        self.pending.retain(|(f, _)| f + frames_in_flight > frame);
+    }
+
+    /// Drop all removed resources immediately
+    ///
+    /// Only call once the gpu has finished with them, eg after [`gpu::Device::wait_idle`]
+    pub fn flush(&mut self) {
+        self.pending.clear();
+    }
+
+    /// The number of resources in the arena, not including removed resources waiting to be dropped
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    /// Returns true if there are no resources in the arena
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of frames that have ended
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+}
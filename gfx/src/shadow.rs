@@ -0,0 +1,209 @@
+//! A single depth texture shared between many shadow casting lights
+//!
+//! Rather than one depth map per light a [`ShadowAtlas`] hands out square [`AtlasSlot`]s of a large depth texture.
+//! Slots are power of two sizes allocated from a quadtree so freeing slots merges them back into larger ones
+
+/// A square region of a [`ShadowAtlas`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AtlasSlot {
+    /// x offset in texels of the slot
+    pub x: u32,
+    /// y offset in texels of the slot
+    pub y: u32,
+    /// width and height in texels of the slot
+    pub size: u32,
+    pub(crate) level: u32,
+}
+
+impl AtlasSlot {
+    /// The viewport to render into the slot with
+    pub fn viewport(&self) -> gpu::Viewport {
+        gpu::Viewport {
+            x: self.x,
+            y: self.y,
+            width: self.size,
+            height: self.size,
+            ..Default::default()
+        }
+    }
+
+    /// The (offset, scale) to map uv coordinates in 0..1 over the slot into uv coordinates of the atlas
+    ///
+    /// atlas_uv = offset + uv * scale
+    pub fn uv_transform(&self, atlas_size: gpu::Size) -> (glam::Vec2, f32) {
+        let atlas_size = atlas_size as f32;
+        (
+            glam::vec2(self.x as f32, self.y as f32) / atlas_size,
+            self.size as f32 / atlas_size,
+        )
+    }
+}
+
+/// A depth texture divided into slots each used as the shadow map of a light
+#[derive(Debug, Clone)]
+pub struct ShadowAtlas {
+    /// the depth texture of the atlas
+    pub texture: crate::GTexture2D,
+    pub(crate) size: gpu::Size,
+    /// free (x, y) slot offsets of each level, level 0 is the whole atlas and each level halves the size
+    pub(crate) free: Vec<Vec<(u32, u32)>>,
+    pub(crate) allocated: usize,
+}
+
+impl ShadowAtlas {
+    /// Create a new ShadowAtlas of size x size texels
+    ///
+    /// size is rounded up to a power of two and no slot will be smaller than min_slot_size
+    pub fn new(
+        device: &gpu::Device,
+        size: gpu::Size,
+        min_slot_size: gpu::Size,
+        format: gpu::Format,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let size = size.next_power_of_two();
+        let min_slot_size = min_slot_size.next_power_of_two().min(size).max(1);
+        let levels = (size / min_slot_size).trailing_zeros() + 1;
+
+        let texture = crate::GTexture2D::new(
+            device,
+            size,
+            size,
+            gpu::Samples::S1,
+            gpu::TextureUsage::DEPTH_OUTPUT
+                | gpu::TextureUsage::SAMPLED
+                | gpu::TextureUsage::COPY_DST,
+            1,
+            format,
+            name,
+        )?;
+
+        let mut free = vec![Vec::new(); levels as usize];
+        free[0].push((0, 0));
+
+        Ok(Self {
+            texture,
+            size,
+            free,
+            allocated: 0,
+        })
+    }
+
+    /// The width and height in texels of the atlas
+    pub fn size(&self) -> gpu::Size {
+        self.size
+    }
+
+    /// The number of slots currently allocated
+    pub fn allocated(&self) -> usize {
+        self.allocated
+    }
+
+    fn level_size(&self, level: u32) -> u32 {
+        self.size >> level
+    }
+
+    /// Allocate a slot of at least size x size texels
+    ///
+    /// size is rounded up to a power of two, returns None if there isn't a free region large enough
+    pub fn allocate(&mut self, size: gpu::Size) -> Option<AtlasSlot> {
+        let size = size.next_power_of_two().min(self.size).max(1);
+        let level = ((self.size / size).trailing_zeros()).min(self.free.len() as u32 - 1);
+
+        // find the smallest free slot that is large enough then split it down to the level
+        let mut found = (0..=level).rev().find(|l| !self.free[*l as usize].is_empty())?;
+        let (x, y) = self.free[found as usize].pop().unwrap();
+        while found < level {
+            found += 1;
+            let half = self.level_size(found);
+            self.free[found as usize].extend([(x + half, y), (x, y + half), (x + half, y + half)]);
+        }
+
+        self.allocated += 1;
+        Some(AtlasSlot {
+            x,
+            y,
+            size: self.level_size(level),
+            level,
+        })
+    }
+
+    /// Return a slot to the atlas so the region can be reused
+    ///
+    /// The slot must have been allocated from this atlas and not already freed
+    pub fn free(&mut self, slot: AtlasSlot) {
+        let (mut x, mut y, mut level) = (slot.x, slot.y, slot.level);
+        if self.free[level as usize].contains(&(x, y)) {
+            panic!("ERROR: Attempt to free shadow atlas slot {:?} twice", slot);
+        }
+        self.allocated -= 1;
+
+        // merge with the three siblings while they are all free
+        while level > 0 {
+            let parent_size = self.level_size(level - 1);
+            let (px, py) = (x - x % parent_size, y - y % parent_size);
+            let half = self.level_size(level);
+            let siblings = [(px, py), (px + half, py), (px, py + half), (px + half, py + half)];
+            let free = &mut self.free[level as usize];
+            if siblings
+                .iter()
+                .filter(|s| **s != (x, y))
+                .all(|s| free.contains(s))
+            {
+                free.retain(|s| !siblings.contains(s));
+                x = px;
+                y = py;
+                level -= 1;
+            } else {
+                break;
+            }
+        }
+        self.free[level as usize].push((x, y));
+    }
+
+    /// Free every slot
+    pub fn reset(&mut self) {
+        self.free.iter_mut().for_each(|f| f.clear());
+        self.free[0].push((0, 0));
+        self.allocated = 0;
+    }
+
+    /// Clear the whole atlas to the far plane
+    ///
+    /// Passes into slots load the existing contents so the atlas should be cleared before rendering any slots
+    pub fn clear(&self, encoder: &mut crate::CommandEncoder<'_>) {
+        encoder.clear_texture(
+            self.texture.whole_slice_owned(),
+            gpu::ClearValue::Depth(1.0),
+        );
+    }
+
+    /// Begin a pass rendering depth into the slot
+    ///
+    /// graphics should only write depth such as from [`crate::ReflectedGraphics::depth_only`].
+    /// A pipeline variant of graphics is created for each distinct slot rendered to
+    #[cfg(feature = "reflect")]
+    pub fn pass<'a, 'b, V: crate::Vertex>(
+        &self,
+        encoder: &'b mut crate::CommandEncoder<'a>,
+        device: &gpu::Device,
+        slot: AtlasSlot,
+        graphics: &crate::ReflectedGraphics,
+    ) -> Result<crate::pass::ReflectedGraphicsPass<'a, 'b, V>, gpu::Error> {
+        encoder.graphics_pass_reflected_viewport(
+            device,
+            &[],
+            &[],
+            Some(crate::Attachment {
+                raw: gpu::Attachment::View(
+                    std::borrow::Cow::Owned(self.texture.view.clone()),
+                    gpu::ClearValue::Depth(1.0),
+                ),
+                load: gpu::LoadOp::Load,
+                store: gpu::StoreOp::Store,
+            }),
+            Some(slot.viewport()),
+            graphics,
+        )
+    }
+}
@@ -0,0 +1,297 @@
+//! Staging belt for coalescing small per frame uploads
+
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// A single persistently mapped staging buffer
+#[derive(Debug)]
+struct Chunk {
+    mapped: gpu::MappedBuffer,
+    offset: u64,
+    /// identifies the chunk to the encoders that have written to it
+    id: u64,
+    /// the number of encoders that have written to the chunk and not been submitted yet
+    writers: usize,
+    /// the submissions that copy out of the chunk
+    submissions: Vec<gpu::Submission>,
+}
+
+impl Chunk {
+    fn size(&self) -> u64 {
+        self.mapped.buffer().size()
+    }
+}
+
+/// The chunks of a staging belt, shared with the encoders that have been written to
+///
+/// A chunk stays active while any encoder that wrote to it hasn't been submitted so several encoders
+/// can write to the same chunk, it's only reused once every submission that copies out of it has completed
+#[derive(Debug)]
+pub(crate) struct BeltChunks {
+    active: Vec<Chunk>,
+    closed: Vec<Chunk>,
+    free: Vec<Chunk>,
+    next_id: u64,
+}
+
+impl BeltChunks {
+    /// Flush the data written to the chunks so that it's visible to the device
+    pub(crate) fn flush(&mut self, ids: &[u64]) -> Result<(), gpu::Error> {
+        for chunk in self.active.iter_mut().filter(|c| ids.contains(&c.id)) {
+            chunk.mapped.flush_range(0, chunk.offset)?;
+        }
+        Ok(())
+    }
+
+    /// Mark the chunks written by an encoder as in use by submission, or release them without a
+    /// submission if the encoder was dropped
+    ///
+    /// Chunks that no other unsubmitted encoder has written to are closed until their submissions complete
+    pub(crate) fn close(&mut self, ids: &[u64], submission: Option<&gpu::Submission>) {
+        let mut i = 0;
+        while i < self.active.len() {
+            let chunk = &mut self.active[i];
+            if ids.contains(&chunk.id) {
+                if let Some(submission) = submission {
+                    chunk.submissions.push(submission.clone());
+                }
+                chunk.writers -= 1;
+                if chunk.writers == 0 {
+                    let chunk = self.active.swap_remove(i);
+                    self.closed.push(chunk);
+                    continue;
+                }
+            }
+            i += 1;
+        }
+    }
+
+    /// Move closed chunks whose submissions have completed to the free list
+    fn recall(&mut self) -> Result<(), gpu::Error> {
+        let mut i = 0;
+        while i < self.closed.len() {
+            let mut complete = true;
+            for submission in &self.closed[i].submissions {
+                complete &= submission.is_complete()?;
+            }
+            if complete {
+                let mut chunk = self.closed.swap_remove(i);
+                chunk.offset = 0;
+                chunk.submissions.clear();
+                self.free.push(chunk);
+            } else {
+                i += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The chunks of each staging belt that an encoder has written to
+///
+/// Dropping an encoder before it's submitted releases its chunks without a submission
+#[derive(Debug, Default)]
+pub(crate) struct EncoderChunks {
+    belts: Vec<(Arc<Mutex<BeltChunks>>, Vec<u64>)>,
+}
+
+impl EncoderChunks {
+    /// Record that the chunk of the belt has been written to
+    ///
+    /// Returns true if the chunk hadn't been written to before
+    pub(crate) fn add(&mut self, chunks: &Arc<Mutex<BeltChunks>>, id: u64) -> bool {
+        match self.belts.iter_mut().find(|(b, _)| Arc::ptr_eq(b, chunks)) {
+            Some((_, ids)) if ids.contains(&id) => false,
+            Some((_, ids)) => {
+                ids.push(id);
+                true
+            }
+            None => {
+                self.belts.push((Arc::clone(chunks), vec![id]));
+                true
+            }
+        }
+    }
+
+    /// Move the chunks written through other onto self
+    pub(crate) fn append(&mut self, mut other: EncoderChunks) {
+        for (chunks, ids) in other.belts.drain(..) {
+            for id in ids {
+                if !self.add(&chunks, id) {
+                    // both encoders counted as a writer of the chunk
+                    chunks.lock().close(&[id], None);
+                }
+            }
+        }
+    }
+
+    /// Flush the data written to every chunk
+    pub(crate) fn flush(&self) -> Result<(), gpu::Error> {
+        for (chunks, ids) in &self.belts {
+            chunks.lock().flush(ids)?;
+        }
+        Ok(())
+    }
+
+    /// Close the chunks written of every belt
+    pub(crate) fn close(&mut self, submission: Option<&gpu::Submission>) {
+        for (chunks, ids) in self.belts.drain(..) {
+            chunks.lock().close(&ids, submission);
+        }
+    }
+
+    /// Close the chunks written of a single belt
+    pub(crate) fn close_belt(
+        &mut self,
+        chunks: &Arc<Mutex<BeltChunks>>,
+        submission: Option<&gpu::Submission>,
+    ) {
+        if let Some(i) = self.belts.iter().position(|(b, _)| Arc::ptr_eq(b, chunks)) {
+            let (belt, ids) = self.belts.swap_remove(i);
+            belt.lock().close(&ids, submission);
+        }
+    }
+}
+
+impl Drop for EncoderChunks {
+    fn drop(&mut self) {
+        self.close(None);
+    }
+}
+
+/// Coalesces small uploads into large mapped staging buffers
+///
+/// Instead of recording an update per buffer every frame data is written into
+/// persistently mapped chunks and copied into the destination buffers when the encoder is submitted.
+///
+/// Each frame call [`StagingBelt::write_buffer`] for each upload then submit the encoder with [`crate::CommandEncoder::submit`].
+/// The chunks written are tied to that submission of the command buffer and reused once it has completed.
+/// If the encoder is only recorded with [`crate::CommandEncoder::record`] call [`StagingBelt::finish`]
+/// after submitting the command buffer instead
+#[derive(Debug)]
+pub struct StagingBelt {
+    chunk_size: u64,
+    chunks: Arc<Mutex<BeltChunks>>,
+    name: Option<String>,
+}
+
+impl StagingBelt {
+    /// Create a new StagingBelt allocating chunks of chunk_size bytes
+    ///
+    /// Uploads larger than chunk_size get a dedicated chunk
+    pub fn new(chunk_size: u64, name: Option<&str>) -> Self {
+        Self {
+            chunk_size,
+            chunks: Arc::new(Mutex::new(BeltChunks {
+                active: Vec::new(),
+                closed: Vec::new(),
+                free: Vec::new(),
+                next_id: 0,
+            })),
+            name: name.map(|n| n.to_string()),
+        }
+    }
+
+    /// Write data into the staging belt and record a copy from the belt into dst
+    ///
+    /// The copy only happens when the encoder is submitted
+    /// if the encoder is dropped before being submitted then no update will occur
+    pub fn write_buffer<'a>(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'a>,
+        device: &gpu::Device,
+        dst: gpu::BufferSlice<'a>,
+        data: &[u8],
+    ) -> Result<(), gpu::Error> {
+        if data.len() as u64 > dst.size() {
+            panic!(
+                "ERROR: Attempt to write {} bytes to buffer slice of size {}",
+                data.len(),
+                dst.size()
+            );
+        }
+        if data.len() == 0 {
+            return Ok(());
+        }
+
+        let size = data.len() as u64;
+        let mut chunks = self.chunks.lock();
+        let chunk = Self::allocate(&mut chunks, device, size, self.chunk_size, &self.name)?;
+        let offset = chunk.offset;
+        chunk.mapped.write(offset, data);
+        // keep copies aligned to 4 bytes
+        chunk.offset = (offset + size + 3) & !3;
+
+        let src = chunk.mapped.buffer().slice_owned(offset..(offset + size));
+        let dst = if size == dst.size() {
+            dst
+        } else {
+            dst.buffer()
+                .slice_owned(dst.offset()..(dst.offset() + size))
+        };
+        if encoder.staging_chunks.add(&self.chunks, chunk.id) {
+            chunk.writers += 1;
+        }
+        encoder.copy_buffer_to_buffer(src, dst);
+        Ok(())
+    }
+
+    /// Mark the chunks that encoder has written to as in use by the last submission of command_buffer
+    ///
+    /// Only needed if the encoder that [`StagingBelt::write_buffer`] recorded into was recorded with
+    /// [`crate::CommandEncoder::record`] and the command buffer submitted separately,
+    /// [`crate::CommandEncoder::submit`] does this automatically
+    pub fn finish(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'_>,
+        command_buffer: &gpu::CommandBuffer,
+    ) {
+        encoder
+            .staging_chunks
+            .close_belt(&self.chunks, Some(&command_buffer.last_submission()));
+    }
+
+    /// Get an active chunk with at least size bytes free
+    ///
+    /// Chunks whose submission has completed are reclaimed first
+    fn allocate<'c>(
+        chunks: &'c mut BeltChunks,
+        device: &gpu::Device,
+        size: u64,
+        chunk_size: u64,
+        name: &Option<String>,
+    ) -> Result<&'c mut Chunk, gpu::Error> {
+        if let Some(i) = chunks
+            .active
+            .iter()
+            .position(|c| c.offset + size <= c.size())
+        {
+            return Ok(&mut chunks.active[i]);
+        }
+
+        chunks.recall()?;
+
+        let chunk = if let Some(i) = chunks.free.iter().position(|c| size <= c.size()) {
+            chunks.free.swap_remove(i)
+        } else {
+            let buffer = device.create_buffer(&gpu::BufferDesc {
+                name: name.as_ref().map(|n| format!("{}_chunk_{}", n, chunks.next_id)),
+                size: size.max(chunk_size),
+                usage: gpu::BufferUsage::COPY_SRC,
+                memory: gpu::MemoryType::Host,
+            })?;
+            let id = chunks.next_id;
+            chunks.next_id += 1;
+            Chunk {
+                mapped: buffer.map_persistent()?,
+                offset: 0,
+                id,
+                writers: 0,
+                submissions: Vec::new(),
+            }
+        };
+
+        chunks.active.push(chunk);
+        Ok(chunks.active.last_mut().unwrap())
+    }
+}
@@ -9,6 +9,7 @@ pub struct Storage<U: bytemuck::Pod> {
     pub length: usize,
     /// marks the type of the buffer
     pub _marker: std::marker::PhantomData<U>,
+    pub(crate) name: Option<String>,
 }
 
 impl<U: bytemuck::Pod> PartialEq for Storage<U> {
@@ -71,8 +72,7 @@ impl<U: bytemuck::Pod> Storage<U> {
             encoder.copy_buffer_to_buffer(staging_buffer.into_slice(..), buffer.slice_owned(..));
             println!("done");
         } else {
-            let (ptr, len, cap) = data.into_raw_parts();
-            let t = unsafe { Vec::from_raw_parts(ptr as *mut u8, len * 4, cap * 4) };
+            let t = bytemuck::cast_slice::<U, u8>(&data).to_vec();
             encoder.push_command(crate::encoder::Command::UpdateBuffer {
                 buffer: std::borrow::Cow::Owned(buffer.clone()),
                 offset: 0,
@@ -85,6 +85,7 @@ impl<U: bytemuck::Pod> Storage<U> {
             buffer,
             length,
             _marker: std::marker::PhantomData,
+            name: name.map(|n| n.to_string()),
         })
     }
 
@@ -139,6 +140,7 @@ impl<U: bytemuck::Pod> Storage<U> {
             buffer,
             length: data.len(),
             _marker: std::marker::PhantomData,
+            name: name.map(|n| n.to_string()),
         })
     }
 
@@ -226,6 +228,156 @@ impl<U: bytemuck::Pod> Storage<U> {
 
         Ok(())
     }
+
+    /// The number of elements that can be stored before the buffer has to be reallocated
+    pub fn capacity(&self) -> usize {
+        (self.buffer.size() / std::mem::size_of::<U>() as u64) as usize
+    }
+
+    /// Update the elements starting at offset on the gpu
+    /// --------------------------
+    ///
+    /// The update will only be complete when the command encoder is submitted
+    /// if the encoder is dropped before being submitted then no update will occur
+    /// Will panic if the range is out of the bounds of self.length
+    pub fn update_range<'a>(
+        &self,
+        encoder: &mut crate::CommandEncoder<'a>,
+        device: &gpu::Device,
+        offset: usize,
+        data: &[U],
+    ) -> Result<(), gpu::Error> {
+        if offset + data.len() > self.length {
+            panic!(
+                "ERROR: Attempt to update range {}..{} of storage with length {}",
+                offset,
+                offset + data.len(),
+                self.length
+            );
+        }
+        Self::write(
+            encoder,
+            device,
+            &self.buffer,
+            (offset * std::mem::size_of::<U>()) as u64,
+            bytemuck::cast_slice(data),
+        )
+    }
+
+    /// Append the data to the end of the storage growing the buffer if needed
+    ///
+    /// The data on the gpu won't be correct until the encoder is submitted
+    /// Returns true if the buffer was reallocated, in which case any bundles or descriptors
+    /// referencing the old buffer must be recreated
+    pub fn extend<'a>(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'a>,
+        device: &gpu::Device,
+        data: &[U],
+    ) -> Result<bool, gpu::Error> {
+        let offset = self.length;
+        let reallocated = self.reserve(encoder, device, self.length + data.len())?;
+        self.length += data.len();
+        self.update_range(encoder, device, offset, data)?;
+        Ok(reallocated)
+    }
+
+    /// Set the number of elements in the storage growing the buffer if needed
+    ///
+    /// Existing elements are kept and new elements are uninitialized
+    /// Returns true if the buffer was reallocated, in which case any bundles or descriptors
+    /// referencing the old buffer must be recreated
+    pub fn resize<'a>(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'a>,
+        device: &gpu::Device,
+        length: usize,
+    ) -> Result<bool, gpu::Error> {
+        let reallocated = self.reserve(encoder, device, length)?;
+        self.length = length;
+        Ok(reallocated)
+    }
+
+    /// Make sure that the buffer can hold at least capacity elements
+    ///
+    /// The buffer at least doubles in size when reallocated so that repeated calls to
+    /// [`Storage::extend`] don't reallocate every time. The existing elements are copied
+    /// into the new buffer when the encoder is submitted
+    /// Returns true if the buffer was reallocated, in which case any bundles or descriptors
+    /// referencing the old buffer must be recreated
+    pub fn reserve<'a>(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'a>,
+        device: &gpu::Device,
+        capacity: usize,
+    ) -> Result<bool, gpu::Error> {
+        if capacity <= self.capacity() {
+            return Ok(false);
+        }
+        let capacity = capacity.max(self.capacity() * 2);
+
+        let buffer = device.create_buffer(&gpu::BufferDesc {
+            size: std::mem::size_of::<U>() as u64 * capacity as u64,
+            usage: self.buffer.usage(),
+            memory: gpu::MemoryType::Device,
+            name: self.name.as_ref().map(|n| format!("{}_buffer", n)),
+        })?;
+
+        if self.length != 0 {
+            let size = (std::mem::size_of::<U>() * self.length) as u64;
+            encoder.copy_buffer_to_buffer(
+                self.buffer.slice_owned(..size),
+                buffer.slice_owned(..size),
+            );
+        }
+
+        self.buffer = buffer;
+        Ok(true)
+    }
+
+    /// Copy the contents of the storage back to the cpu
+    ///
+    /// The data will only be available once the command buffer the encoder is submitted to has completed
+    pub fn read_back<'a>(
+        &self,
+        encoder: &mut crate::CommandEncoder<'a>,
+        device: &gpu::Device,
+    ) -> Result<crate::ReadbackFuture<Vec<U>>, gpu::Error> {
+        let size = (std::mem::size_of::<U>() * self.length) as u64;
+        encoder.read_buffer(device, self.buffer.slice_owned(..size))
+    }
+
+    /// Write the bytes into buffer at offset either through update buffer or a staging buffer
+    fn write<'a>(
+        encoder: &mut crate::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &gpu::Buffer,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<(), gpu::Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        // update buffer is limited to 65536 bytes and requires 4 byte alignment
+        if data.len() <= 65536 && data.len() % 4 == 0 && offset % 4 == 0 {
+            encoder.update_buffer_owned(buffer.clone(), offset, data.to_vec());
+        } else {
+            let staging_buffer = device.create_buffer(&gpu::BufferDesc {
+                size: data.len() as u64,
+                usage: gpu::BufferUsage::COPY_SRC,
+                memory: gpu::MemoryType::Host,
+                name: None,
+            })?;
+
+            staging_buffer.slice_ref(..).write(data)?;
+
+            encoder.copy_buffer_to_buffer(
+                staging_buffer.into_slice(..),
+                buffer.slice_owned(offset..(offset + data.len() as u64)),
+            );
+        }
+        Ok(())
+    }
 }
 
 impl<U: bytemuck::Pod> std::ops::Deref for Storage<U> {
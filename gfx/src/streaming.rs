@@ -0,0 +1,512 @@
+//! Streaming textures from disk
+//!
+//! [`TextureStreamer`] decodes images and builds their mip chains on io threads then uploads them
+//! progressively, smallest mip first, so a low resolution texture is available almost immediately
+//! and detail is added over the following frames.
+//!
+//! Resident mips are counted against a byte budget, when it is exceeded the least recently used textures
+//! are evicted back down to their smallest mips and reloaded from disk once they are used again.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crate::{ColorSpace, GTexture2D};
+
+/// Identifies a texture in a [`TextureStreamer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StreamId(u32);
+
+impl StreamId {
+    /// The index of the texture in the streamer, ids are never reused
+    pub fn index(&self) -> u32 {
+        self.0
+    }
+}
+
+/// How much of a streamed texture is resident on the gpu
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Residency {
+    /// The image is being loaded and there is no texture yet
+    Loading,
+    /// Only mip levels from self.0 down are resident so the texture is smaller than the image
+    Partial(u32),
+    /// Every mip level of the image is resident
+    Resident,
+    /// The image couldn't be loaded, see [`TextureStreamer::error`]
+    Failed,
+}
+
+struct Job {
+    id: StreamId,
+    path: PathBuf,
+}
+
+enum Loaded {
+    Mips {
+        id: StreamId,
+        width: u32,
+        height: u32,
+        mips: Vec<Vec<u8>>,
+    },
+    Failed {
+        id: StreamId,
+        error: String,
+    },
+}
+
+struct Entry {
+    path: PathBuf,
+    name: Option<String>,
+    width: u32,
+    height: u32,
+    mip_levels: u32,
+    /// the most detailed resident mip level, mip_levels when nothing is resident
+    resident: u32,
+    texture: Option<GTexture2D>,
+    /// decoded mip data waiting to be uploaded indexed by level
+    pending: Vec<Option<Vec<u8>>>,
+    /// if a job for the entry is queued or running on an io thread
+    loading: bool,
+    error: Option<String>,
+    last_used: u64,
+}
+
+impl Entry {
+    fn level_size(&self, format: gpu::Format, level: u32) -> u64 {
+        format.data_size(
+            (self.width >> level).max(1),
+            (self.height >> level).max(1),
+            1,
+        ) as u64
+    }
+
+    fn resident_bytes(&self, format: gpu::Format) -> u64 {
+        (self.resident..self.mip_levels)
+            .map(|l| self.level_size(format, l))
+            .sum()
+    }
+
+    /// the most detailed level that is kept when the entry is evicted
+    fn min_level(&self, min_size: u32) -> u32 {
+        let mut level = 0;
+        while level + 1 < self.mip_levels && (self.width.max(self.height) >> level) > min_size {
+            level += 1;
+        }
+        level
+    }
+
+    /// Replace the texture with one holding mip levels from level down
+    ///
+    /// Levels already resident are copied from the old texture, the rest are uploaded from pending data
+    fn set_resident(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        level: u32,
+        format: gpu::Format,
+        usage: gpu::TextureUsage,
+    ) -> Result<(), gpu::Error> {
+        let width = (self.width >> level).max(1);
+        let height = (self.height >> level).max(1);
+        let texture = GTexture2D::new(
+            device,
+            width,
+            height,
+            gpu::Samples::S1,
+            usage | gpu::TextureUsage::COPY_DST | gpu::TextureUsage::COPY_SRC,
+            self.mip_levels - level,
+            format,
+            self.name.as_ref().map(|n| &**n),
+        )?;
+
+        let uploads = (level..self.resident.min(self.mip_levels))
+            .map(|l| (l, self.pending[l as usize].take().unwrap()))
+            .collect::<Vec<_>>();
+        if uploads.len() != 0 {
+            let total = uploads.iter().map(|(_, d)| d.len() as u64).sum::<u64>();
+            let staging = device.create_buffer(&gpu::BufferDesc {
+                name: None,
+                size: total,
+                usage: gpu::BufferUsage::COPY_SRC,
+                memory: gpu::MemoryType::Host,
+            })?;
+            let mut offset = 0;
+            for (l, data) in &uploads {
+                let size = data.len() as u64;
+                staging.slice_ref(offset..(offset + size)).write(data)?;
+                encoder.copy_buffer_to_texture(
+                    staging.slice_owned(offset..(offset + size)),
+                    texture.texture.slice_owned(&mip_desc(width, height, l - level)),
+                );
+                offset += size;
+            }
+        }
+
+        if let Some(old) = &self.texture {
+            for l in level.max(self.resident)..self.mip_levels {
+                encoder.copy_texture_to_texture(
+                    old.texture.slice_owned(&mip_desc(old.width(), old.height(), l - self.resident)),
+                    texture.texture.slice_owned(&mip_desc(width, height, l - level)),
+                );
+            }
+        }
+
+        // detail above the new level is dropped so must be loaded again
+        for data in &mut self.pending[..level as usize] {
+            *data = None;
+        }
+        self.resident = level;
+        self.texture = Some(texture);
+        Ok(())
+    }
+}
+
+fn mip_desc(width: u32, height: u32, mip: u32) -> gpu::TextureSliceDesc {
+    gpu::TextureSliceDesc {
+        offset: gpu::Offset3D::ZERO,
+        extent: gpu::Extent3D {
+            width: (width >> mip).max(1),
+            height: (height >> mip).max(1),
+            depth: 1,
+        },
+        base_array_layer: 0,
+        array_layers: 1,
+        base_mip_level: mip,
+        mip_levels: 1,
+    }
+}
+
+/// Decode the image at path into rgba8 mip levels, most detailed first
+fn decode(path: &Path) -> Result<(u32, u32, Vec<Vec<u8>>), String> {
+    let image = image::open(path).map_err(|e| e.to_string())?.to_rgba8();
+    let (width, height) = image.dimensions();
+    let levels = crate::full_mip_levels(width, height);
+    let mut mips = Vec::with_capacity(levels as usize);
+    let mut current = image;
+    for level in 1..levels {
+        let next = image::imageops::resize(
+            &current,
+            (width >> level).max(1),
+            (height >> level).max(1),
+            image::imageops::FilterType::Triangle,
+        );
+        mips.push(current.into_raw());
+        current = next;
+    }
+    mips.push(current.into_raw());
+    Ok((width, height, mips))
+}
+
+/// Loads textures on io threads and uploads them progressively within a gpu memory budget
+///
+/// Each frame
+/// - call [`TextureStreamer::touch`] for every texture drawn, this decides what is evicted first
+/// and reloads evicted textures
+/// - call [`TextureStreamer::update`] to upload loaded mips and evict textures over budget
+/// - rebuild any bundles referencing the textures returned by update as the texture has been replaced
+///
+/// Images are decoded to rgba8 so the textures use [`gpu::Format::Rgba8Unorm`] or its sRGB variant
+pub struct TextureStreamer {
+    jobs: Option<mpsc::Sender<Job>>,
+    results: mpsc::Receiver<Loaded>,
+    threads: Vec<JoinHandle<()>>,
+    entries: Vec<Option<Entry>>,
+    format: gpu::Format,
+    usage: gpu::TextureUsage,
+    budget: u64,
+    upload_budget: u64,
+    min_size: u32,
+    frame: u64,
+}
+
+impl std::fmt::Debug for TextureStreamer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TextureStreamer")
+            .field("textures", &self.entries.iter().filter(|e| e.is_some()).count())
+            .field("resident_bytes", &self.resident_bytes())
+            .field("budget", &self.budget)
+            .field("frame", &self.frame)
+            .finish()
+    }
+}
+
+impl TextureStreamer {
+    /// Create a new TextureStreamer decoding images on io_threads threads
+    ///
+    /// - budget is the number of bytes of resident mips before textures are evicted
+    /// - upload_budget is the number of bytes uploaded per call to update, at least one mip level is uploaded
+    /// - min_size is the size in pixels of the largest mip level kept when a texture is evicted
+    pub fn new(
+        io_threads: usize,
+        budget: u64,
+        upload_budget: u64,
+        min_size: u32,
+        usage: gpu::TextureUsage,
+        color_space: ColorSpace,
+    ) -> Self {
+        let (job_sender, job_receiver) = mpsc::channel::<Job>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let threads = (0..io_threads.max(1))
+            .map(|i| {
+                let jobs = Arc::clone(&job_receiver);
+                let results = result_sender.clone();
+                std::thread::Builder::new()
+                    .name(format!("gfx_texture_streamer_{}", i))
+                    .spawn(move || loop {
+                        let job = match jobs.lock().unwrap().recv() {
+                            Ok(job) => job,
+                            Err(_) => break,
+                        };
+                        let loaded = match decode(&job.path) {
+                            Ok((width, height, mips)) => Loaded::Mips {
+                                id: job.id,
+                                width,
+                                height,
+                                mips,
+                            },
+                            Err(error) => Loaded::Failed { id: job.id, error },
+                        };
+                        if results.send(loaded).is_err() {
+                            break;
+                        }
+                    })
+                    .expect("ERROR: Failed to spawn texture streaming thread")
+            })
+            .collect();
+
+        Self {
+            jobs: Some(job_sender),
+            results,
+            threads,
+            entries: Vec::new(),
+            format: color_space.format(gpu::Format::Rgba8Unorm),
+            usage,
+            budget,
+            upload_budget,
+            min_size,
+            frame: 0,
+        }
+    }
+
+    /// Start loading the image at path returning the id of the texture
+    pub fn load(&mut self, path: impl AsRef<Path>, name: Option<&str>) -> StreamId {
+        let id = StreamId(self.entries.len() as u32);
+        self.entries.push(Some(Entry {
+            path: path.as_ref().to_path_buf(),
+            name: name.map(|n| n.to_string()),
+            width: 0,
+            height: 0,
+            mip_levels: 0,
+            resident: 0,
+            texture: None,
+            pending: Vec::new(),
+            loading: false,
+            error: None,
+            last_used: self.frame,
+        }));
+        self.request(id);
+        id
+    }
+
+    fn request(&mut self, id: StreamId) {
+        let entry = self.entries[id.0 as usize].as_mut().unwrap();
+        entry.loading = true;
+        let job = Job {
+            id,
+            path: entry.path.clone(),
+        };
+        self.jobs.as_ref().unwrap().send(job).unwrap();
+    }
+
+    fn entry(&self, id: StreamId) -> &Entry {
+        match self.entries.get(id.0 as usize) {
+            Some(Some(e)) => e,
+            _ => panic!("ERROR: Attempt to use removed streamed texture {:?}", id),
+        }
+    }
+
+    /// Mark the texture as used this frame, reloading detail that was evicted
+    pub fn touch(&mut self, id: StreamId) {
+        let frame = self.frame;
+        let entry = match self.entries.get_mut(id.0 as usize) {
+            Some(Some(e)) => e,
+            _ => panic!("ERROR: Attempt to touch removed streamed texture {:?}", id),
+        };
+        entry.last_used = frame;
+        let evicted = entry.mip_levels != 0
+            && entry.resident > 0
+            && entry.pending[entry.resident as usize - 1].is_none();
+        if evicted && !entry.loading && entry.error.is_none() {
+            self.request(id);
+        }
+    }
+
+    /// Get the texture if any of it is resident
+    ///
+    /// The texture is replaced when mips are uploaded or evicted, see [`TextureStreamer::update`]
+    pub fn texture(&self, id: StreamId) -> Option<&GTexture2D> {
+        self.entry(id).texture.as_ref()
+    }
+
+    /// Get how much of the texture is resident
+    pub fn residency(&self, id: StreamId) -> Residency {
+        let entry = self.entry(id);
+        if entry.error.is_some() {
+            Residency::Failed
+        } else if entry.texture.is_none() {
+            Residency::Loading
+        } else if entry.resident == 0 {
+            Residency::Resident
+        } else {
+            Residency::Partial(entry.resident)
+        }
+    }
+
+    /// Get the error from loading the image if it failed
+    pub fn error(&self, id: StreamId) -> Option<&str> {
+        self.entry(id).error.as_ref().map(|e| &**e)
+    }
+
+    /// Remove the texture from the streamer
+    ///
+    /// The texture is kept alive by any command buffers still using it
+    pub fn remove(&mut self, id: StreamId) -> Option<GTexture2D> {
+        self.entries
+            .get_mut(id.0 as usize)
+            .and_then(|e| e.take())
+            .and_then(|e| e.texture)
+    }
+
+    /// The number of bytes of mips resident across all textures
+    pub fn resident_bytes(&self) -> u64 {
+        self.entries
+            .iter()
+            .flatten()
+            .map(|e| e.resident_bytes(self.format))
+            .sum()
+    }
+
+    /// Set the number of bytes of resident mips before textures are evicted
+    pub fn set_budget(&mut self, budget: u64) {
+        self.budget = budget;
+    }
+
+    /// Upload loaded mips and evict the least recently used textures if over budget
+    ///
+    /// Returns the textures that have been replaced, bundles referencing them must be rebuilt
+    /// The uploads and copies only happen when the encoder is submitted
+    pub fn update(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+    ) -> Result<Vec<StreamId>, gpu::Error> {
+        while let Ok(loaded) = self.results.try_recv() {
+            match loaded {
+                Loaded::Mips {
+                    id,
+                    width,
+                    height,
+                    mips,
+                } => {
+                    // the texture may have been removed while loading
+                    if let Some(Some(entry)) = self.entries.get_mut(id.0 as usize) {
+                        entry.loading = false;
+                        if entry.mip_levels == 0 {
+                            entry.width = width;
+                            entry.height = height;
+                            entry.mip_levels = mips.len() as u32;
+                            entry.resident = entry.mip_levels;
+                        }
+                        entry.pending = mips
+                            .into_iter()
+                            .enumerate()
+                            .map(|(l, d)| if (l as u32) < entry.resident { Some(d) } else { None })
+                            .collect();
+                    }
+                }
+                Loaded::Failed { id, error } => {
+                    if let Some(Some(entry)) = self.entries.get_mut(id.0 as usize) {
+                        entry.loading = false;
+                        entry.error = Some(error);
+                    }
+                }
+            }
+        }
+
+        let mut changed = Vec::new();
+
+        // upload the most recently used textures first
+        let mut order = (0..self.entries.len())
+            .filter(|&i| match &self.entries[i] {
+                Some(e) => e.resident > 0 && e.pending.get(e.resident as usize - 1).map_or(false, |d| d.is_some()),
+                None => false,
+            })
+            .collect::<Vec<_>>();
+        order.sort_by_key(|&i| std::cmp::Reverse(self.entries[i].as_ref().unwrap().last_used));
+
+        let mut remaining = self.upload_budget;
+        for i in order {
+            if remaining == 0 {
+                break;
+            }
+            let entry = self.entries[i].as_mut().unwrap();
+            let mut level = entry.resident;
+            while level > 0 {
+                let size = match &entry.pending[level as usize - 1] {
+                    Some(data) => data.len() as u64,
+                    None => break,
+                };
+                if size > remaining && level != entry.resident {
+                    break;
+                }
+                remaining = remaining.saturating_sub(size);
+                level -= 1;
+            }
+            entry.set_resident(encoder, device, level, self.format, self.usage)?;
+            changed.push(StreamId(i as u32));
+        }
+
+        // evict the least recently used textures that weren't used this frame
+        let mut total = self.resident_bytes();
+        while total > self.budget {
+            let min_size = self.min_size;
+            let frame = self.frame;
+            let victim = (0..self.entries.len())
+                .filter(|&i| match &self.entries[i] {
+                    Some(e) => e.texture.is_some() && e.last_used < frame && e.resident < e.min_level(min_size),
+                    None => false,
+                })
+                .min_by_key(|&i| self.entries[i].as_ref().unwrap().last_used);
+            let i = match victim {
+                Some(i) => i,
+                None => break,
+            };
+            let entry = self.entries[i].as_mut().unwrap();
+            let before = entry.resident_bytes(self.format);
+            let level = entry.min_level(min_size);
+            entry.set_resident(encoder, device, level, self.format, self.usage)?;
+            total -= before - entry.resident_bytes(self.format);
+            let id = StreamId(i as u32);
+            if !changed.contains(&id) {
+                changed.push(id);
+            }
+        }
+
+        self.frame += 1;
+        Ok(changed)
+    }
+}
+
+impl Drop for TextureStreamer {
+    fn drop(&mut self) {
+        // closing the channel stops the threads once their current job finishes
+        self.jobs = None;
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,516 @@
+//! Rendering text into attachments
+//!
+//! Glyphs are rasterized with [fontdue](https://crates.io/crates/fontdue) into a single channel atlas
+//! texture as they are first used, queued text is then drawn as textured quads in one draw call
+
+use crate::pass::GraphicsPass;
+use std::collections::HashMap;
+
+/// An error from creating a [`TextRenderer`]
+#[derive(Debug)]
+pub enum TextError {
+    /// An error from the gpu
+    Gpu(gpu::Error),
+    /// An error from fontdue parsing the font data
+    Font(&'static str),
+    /// An error creating the text pipeline
+    Reflected(crate::ReflectedError),
+    /// An error creating the atlas bundle
+    Bundle(crate::BundleBuildError),
+}
+
+impl std::fmt::Display for TextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gpu(e) => writeln!(f, "{}", e),
+            Self::Font(e) => writeln!(f, "Error parsing font: {}", e),
+            Self::Reflected(e) => writeln!(f, "{}", e),
+            Self::Bundle(e) => writeln!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for TextError {}
+
+impl From<gpu::Error> for TextError {
+    fn from(e: gpu::Error) -> Self {
+        Self::Gpu(e)
+    }
+}
+
+impl From<crate::ReflectedError> for TextError {
+    fn from(e: crate::ReflectedError) -> Self {
+        Self::Reflected(e)
+    }
+}
+
+impl From<crate::BundleBuildError> for TextError {
+    fn from(e: crate::BundleBuildError) -> Self {
+        Self::Bundle(e)
+    }
+}
+
+/// A corner of a glyph quad
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextVertex {
+    /// position in normalized device coordinates
+    pub pos: [f32; 2],
+    /// texture coordinate into the glyph atlas
+    pub uv: [f32; 2],
+    /// color of the text
+    pub color: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for TextVertex {}
+unsafe impl bytemuck::Zeroable for TextVertex {}
+
+impl crate::Vertex for TextVertex {
+    fn get(name: &str) -> Option<(u32, gpu::VertexFormat)> {
+        match name {
+            "in_pos" => Some((0, gpu::VertexFormat::Vec2)),
+            "in_uv" => Some((8, gpu::VertexFormat::Vec2)),
+            "in_color" => Some((16, gpu::VertexFormat::Vec4)),
+            _ => None,
+        }
+    }
+}
+
+/// A glyph that has been rasterized into the atlas
+#[derive(Debug, Clone, Copy)]
+struct Glyph {
+    metrics: fontdue::Metrics,
+    /// top left of the glyph in the atlas in pixels
+    x: u32,
+    y: u32,
+}
+
+/// A glyph quad in pixel coordinates waiting to be drawn
+///
+/// uvs are in pixels of the atlas as the atlas may grow before the quad is drawn
+#[derive(Debug, Clone, Copy)]
+struct Quad {
+    min: [f32; 2],
+    max: [f32; 2],
+    uv_min: [f32; 2],
+    uv_max: [f32; 2],
+    color: [f32; 4],
+}
+
+/// Draws text into attachments
+///
+/// Each frame call [`TextRenderer::queue`] for each piece of text then [`TextRenderer::pass`]
+/// to draw all the queued text into an attachment
+///
+/// When the glyph atlas is full its size is doubled on the next pass
+pub struct TextRenderer {
+    font: fontdue::Font,
+    atlas: crate::GTexture2D,
+    /// the size of the atlas that glyphs are packed into, larger than the atlas texture if it needs to grow
+    atlas_size: u32,
+    glyphs: HashMap<(char, u32), Glyph>,
+    /// position of the next glyph to be put in the atlas and the height of the current row
+    cursor: (u32, u32),
+    row_height: u32,
+    /// glyphs rasterized since the last pass (x, y, width, height, coverage)
+    pending: Vec<(u32, u32, u32, u32, Vec<u8>)>,
+    quads: Vec<Quad>,
+    vertex_buffer: Option<gpu::Buffer>,
+    pipeline: crate::ReflectedGraphics,
+    bundle: crate::Bundle,
+    sampler: gpu::Sampler,
+    name: Option<String>,
+}
+
+impl std::fmt::Debug for TextRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TextRenderer")
+            .field("atlas", &self.atlas)
+            .field("glyphs", &self.glyphs.len())
+            .field("queued", &self.quads.len())
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl TextRenderer {
+    /// Create a new TextRenderer from the contents of a ttf or otf file
+    ///
+    /// atlas_size is the width and height of the glyph atlas in pixels, 1024 is plenty for a few sizes of latin text
+    pub fn new(
+        device: &gpu::Device,
+        font_data: &[u8],
+        atlas_size: u32,
+        name: Option<&str>,
+    ) -> Result<Self, TextError> {
+        let font = fontdue::Font::from_bytes(font_data, fontdue::FontSettings::default())
+            .map_err(|e| TextError::Font(e))?;
+
+        let atlas = Self::create_atlas(device, atlas_size, name)?;
+
+        let sampler = device.create_sampler(&gpu::SamplerDesc {
+            name: name.map(|n| format!("{}_sampler", n)),
+            ..gpu::SamplerDesc::LINEAR
+        })?;
+
+        let pipeline = Self::create_pipeline(device, name)?;
+
+        let bundle = Self::create_bundle(device, &pipeline, &atlas, &sampler)?;
+
+        Ok(Self {
+            font,
+            atlas,
+            atlas_size,
+            glyphs: HashMap::new(),
+            cursor: (0, 0),
+            row_height: 0,
+            pending: Vec::new(),
+            quads: Vec::new(),
+            vertex_buffer: None,
+            pipeline,
+            bundle,
+            sampler,
+            name: name.map(|n| n.to_string()),
+        })
+    }
+
+    fn create_atlas(
+        device: &gpu::Device,
+        atlas_size: u32,
+        name: Option<&str>,
+    ) -> Result<crate::GTexture2D, gpu::Error> {
+        crate::GTexture2D::new(
+            device,
+            atlas_size,
+            atlas_size,
+            gpu::Samples::S1,
+            gpu::TextureUsage::SAMPLED | gpu::TextureUsage::COPY_DST,
+            1,
+            gpu::Format::R8Unorm,
+            name.map(|n| format!("{}_atlas", n)).as_deref(),
+        )
+    }
+
+    fn create_bundle(
+        device: &gpu::Device,
+        pipeline: &crate::ReflectedGraphics,
+        atlas: &crate::GTexture2D,
+        sampler: &gpu::Sampler,
+    ) -> Result<crate::Bundle, TextError> {
+        Ok(pipeline
+            .bundle()
+            .unwrap()
+            .set_resource("u_atlas", atlas)
+            .map_err(|e| crate::BundleBuildError::from(e))?
+            .set_resource("u_sampler", sampler)
+            .map_err(|e| crate::BundleBuildError::from(e))?
+            .build(device)?)
+    }
+
+    fn create_pipeline(
+        device: &gpu::Device,
+        name: Option<&str>,
+    ) -> Result<crate::ReflectedGraphics, crate::ReflectedError> {
+        let vertex = spv::Builder::new();
+        {
+            let b = &vertex;
+            let in_pos = b.in_vec2(0, "in_pos");
+            let in_uv = b.in_vec2(1, "in_uv");
+            let in_color = b.in_vec4(2, "in_color");
+
+            let vk_pos = b.vk_position();
+            let out_uv = b.out_vec2(0, "out_uv");
+            let out_color = b.out_vec4(1, "out_color");
+
+            b.entry(spv::Stage::Vertex, "main", || {
+                let pos = in_pos.load();
+                vk_pos.store(b.vec4(pos.x(), pos.y(), 0.0, 1.0));
+                out_uv.store(in_uv.load());
+                out_color.store(in_color.load());
+            });
+        }
+
+        let fragment = spv::Builder::new();
+        {
+            let b = &fragment;
+            let in_uv = b.in_vec2(0, "in_uv");
+            let in_color = b.in_vec4(1, "in_color");
+
+            let out_color = b.out_vec4(0, "out_color");
+
+            let u_atlas = b.texture2d(0, 0, Some("u_atlas"));
+            let u_sampler = b.sampler(0, 1, Some("u_sampler"));
+
+            b.entry(spv::Stage::Fragment, "main", || {
+                let color = in_color.load();
+                let combined = spv::combine(&u_atlas, u_sampler);
+                let coverage = spv::sample(&combined, in_uv.load()).x();
+                out_color.store(b.vec4(color.x(), color.y(), color.z(), color.w() * coverage));
+            });
+        }
+
+        crate::ReflectedGraphics::from_builders(
+            device,
+            &vertex,
+            None,
+            Some(&fragment),
+            gpu::Rasterizer::default(),
+            &[gpu::BlendState::ALPHA],
+            None,
+            None,
+            name.map(|n| format!("{}_pipeline", n)).as_deref(),
+        )
+    }
+
+    /// The glyph atlas texture
+    pub fn atlas(&self) -> &crate::GTexture2D {
+        &self.atlas
+    }
+
+    /// The height in pixels of one line of text at size
+    pub fn line_height(&self, size: f32) -> f32 {
+        self.font
+            .horizontal_line_metrics(size)
+            .map(|m| m.new_line_size)
+            .unwrap_or(size)
+    }
+
+    /// The width and height in pixels that text would take up at size
+    pub fn measure(&self, text: &str, size: f32) -> [f32; 2] {
+        let mut width = 0.0f32;
+        let mut line = 0.0;
+        let mut lines = 1;
+        for c in text.chars() {
+            if c == '\n' {
+                width = width.max(line);
+                line = 0.0;
+                lines += 1;
+            } else {
+                line += self.font.metrics(c, size).advance_width;
+            }
+        }
+        [width.max(line), lines as f32 * self.line_height(size)]
+    }
+
+    /// Queue text to be drawn on the next call to [`TextRenderer::pass`]
+    ///
+    /// pos is the top left of the text in pixels from the top left of the attachment
+    /// size is the height of the text in pixels
+    pub fn queue(&mut self, text: &str, pos: [f32; 2], size: f32, color: [f32; 4]) {
+        let line_metrics = self.font.horizontal_line_metrics(size);
+        let ascent = line_metrics.map(|m| m.ascent).unwrap_or(size);
+        let line_height = line_metrics.map(|m| m.new_line_size).unwrap_or(size);
+
+        let mut x = pos[0];
+        let mut baseline = pos[1] + ascent;
+        let mut prev = None;
+        for c in text.chars() {
+            if c == '\n' {
+                x = pos[0];
+                baseline += line_height;
+                prev = None;
+                continue;
+            }
+
+            if let Some(p) = prev {
+                x += self.font.horizontal_kern(p, c, size).unwrap_or(0.0);
+            }
+            prev = Some(c);
+
+            let glyph = self.glyph(c, size);
+            let m = glyph.metrics;
+            if m.width != 0 && m.height != 0 {
+                let min = [
+                    (x + m.xmin as f32).round(),
+                    (baseline - (m.ymin + m.height as i32) as f32).round(),
+                ];
+                self.quads.push(Quad {
+                    min,
+                    max: [min[0] + m.width as f32, min[1] + m.height as f32],
+                    uv_min: [glyph.x as f32, glyph.y as f32],
+                    uv_max: [
+                        (glyph.x + m.width as u32) as f32,
+                        (glyph.y + m.height as u32) as f32,
+                    ],
+                    color,
+                });
+            }
+            x += m.advance_width;
+        }
+    }
+
+    /// Get the glyph from the atlas rasterizing it if it hasn't been used before
+    fn glyph(&mut self, c: char, size: f32) -> Glyph {
+        let key = (c, size.to_bits());
+        if let Some(g) = self.glyphs.get(&key) {
+            return *g;
+        }
+
+        let (metrics, coverage) = self.font.rasterize(c, size);
+        let (width, height) = (metrics.width as u32, metrics.height as u32);
+
+        // pack glyphs into rows leaving a pixel of padding between them
+        // glyphs keep their position when the atlas grows so only the packing area is doubled here
+        // and the texture is recreated on the next pass
+        while width + 1 > self.atlas_size {
+            self.atlas_size *= 2;
+        }
+        if self.cursor.0 + width + 1 > self.atlas_size {
+            self.cursor = (0, self.cursor.1 + self.row_height + 1);
+            self.row_height = 0;
+        }
+        while self.cursor.1 + height + 1 > self.atlas_size {
+            self.atlas_size *= 2;
+        }
+
+        let glyph = Glyph {
+            metrics,
+            x: self.cursor.0,
+            y: self.cursor.1,
+        };
+        if width != 0 && height != 0 {
+            self.pending
+                .push((glyph.x, glyph.y, width, height, coverage));
+        }
+        self.cursor.0 += width + 1;
+        self.row_height = self.row_height.max(height);
+        self.glyphs.insert(key, glyph);
+        glyph
+    }
+
+    /// Recreate the atlas texture at the size glyphs have been packed into
+    /// and rasterize every glyph again to fill it
+    fn grow_atlas(&mut self, device: &gpu::Device) -> Result<(), TextError> {
+        #[cfg(feature = "logging")]
+        log::info!(
+            "GFX: TextRenderer {:?} glyph atlas full, growing to {}",
+            self.name,
+            self.atlas_size
+        );
+
+        let atlas = Self::create_atlas(device, self.atlas_size, self.name.as_deref())?;
+        self.bundle = Self::create_bundle(device, &self.pipeline, &atlas, &self.sampler)?;
+        self.atlas = atlas;
+
+        self.pending.clear();
+        for (&(c, size), glyph) in &self.glyphs {
+            let (width, height) = (glyph.metrics.width as u32, glyph.metrics.height as u32);
+            if width != 0 && height != 0 {
+                let (_, coverage) = self.font.rasterize(c, f32::from_bits(size));
+                self.pending
+                    .push((glyph.x, glyph.y, width, height, coverage));
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw all queued text into the target
+    ///
+    /// The target should be loaded if the text is to be drawn over what is already there
+    pub fn pass<'a>(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'a>,
+        device: &gpu::Device,
+        target: crate::Attachment<'a>,
+    ) -> Result<(), TextError> {
+        if self.atlas.dimension.0 != self.atlas_size {
+            self.grow_atlas(device)?;
+        }
+
+        for (x, y, width, height, coverage) in self.pending.drain(..) {
+            self.atlas.write_data_owned(
+                encoder,
+                device,
+                &coverage,
+                gpu::Offset3D {
+                    x: x as _,
+                    y: y as _,
+                    z: 0,
+                },
+                gpu::Extent3D {
+                    width,
+                    height,
+                    depth: 1,
+                },
+                0,
+                1,
+            )?;
+        }
+
+        if self.quads.is_empty() {
+            return Ok(());
+        }
+
+        let extent = target.raw.view().extent();
+        let s = self.atlas_size as f32;
+        let to_uv = |p: [f32; 2]| [p[0] / s, p[1] / s];
+        let to_ndc = |p: [f32; 2]| {
+            [
+                p[0] / extent.width as f32 * 2.0 - 1.0,
+                p[1] / extent.height as f32 * 2.0 - 1.0,
+            ]
+        };
+        let vertices = self
+            .quads
+            .drain(..)
+            .flat_map(|q| {
+                let min = to_ndc(q.min);
+                let max = to_ndc(q.max);
+                let (uv_min, uv_max) = (to_uv(q.uv_min), to_uv(q.uv_max));
+                let v = |pos: [f32; 2], uv: [f32; 2]| TextVertex {
+                    pos,
+                    uv,
+                    color: q.color,
+                };
+                [
+                    v(min, uv_min),
+                    v([max[0], min[1]], [uv_max[0], uv_min[1]]),
+                    v(max, uv_max),
+                    v(max, uv_max),
+                    v([min[0], max[1]], [uv_min[0], uv_max[1]]),
+                    v(min, uv_min),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let data: &[u8] = bytemuck::cast_slice(&vertices);
+        let size = data.len() as u64;
+
+        if self
+            .vertex_buffer
+            .as_ref()
+            .map(|b| b.size() < size)
+            .unwrap_or(true)
+        {
+            self.vertex_buffer = Some(device.create_buffer(&gpu::BufferDesc {
+                name: self.name.as_ref().map(|n| format!("{}_vertex_buffer", n)),
+                size: size.next_power_of_two(),
+                usage: gpu::BufferUsage::VERTEX | gpu::BufferUsage::COPY_DST,
+                memory: gpu::MemoryType::Device,
+            })?);
+        }
+        let vertex_buffer = self.vertex_buffer.as_ref().unwrap();
+
+        let staging = device.create_buffer(&gpu::BufferDesc {
+            name: None,
+            size,
+            usage: gpu::BufferUsage::COPY_SRC,
+            memory: gpu::MemoryType::Host,
+        })?;
+        staging.slice_ref(..).write(data)?;
+        encoder.copy_buffer_to_buffer(staging.into_slice(..), vertex_buffer.slice_owned(0..size));
+
+        let mut pass = encoder.graphics_pass_reflected::<TextVertex>(
+            device,
+            &[target],
+            &[],
+            None,
+            &self.pipeline,
+        )?;
+        pass.set_bundle_owned(self.bundle.clone());
+        pass.bind_vertex_buffer(vertex_buffer.slice_owned(0..size), 0);
+        pass.draw(0, vertices.len() as u32, 0, 1);
+        pass.finish();
+
+        Ok(())
+    }
+}
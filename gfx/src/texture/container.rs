@@ -0,0 +1,629 @@
+//! Loading textures from KTX2 and DDS container files
+//!
+//! Unlike the image crate path these can express block compressed formats, pre generated mip chains,
+//! cube maps and array textures, all of which are copied straight into the gpu texture
+
+use super::*;
+use std::borrow::Cow;
+
+/// An error from loading a texture container
+#[derive(Debug)]
+pub enum TextureLoadError {
+    /// An error from the gpu
+    Gpu(gpu::Error),
+    /// The data isn't a valid container, self.0 describes what is wrong
+    InvalidData(&'static str),
+    /// The container uses a format that isn't supported, self.0 is the raw format value from the file
+    UnsupportedFormat(u32),
+    /// The ktx2 container uses supercompression scheme self.0 which isn't supported
+    Supercompressed(u32),
+}
+
+impl std::fmt::Display for TextureLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gpu(e) => writeln!(f, "{}", e),
+            Self::InvalidData(m) => writeln!(f, "Error Invalid texture container data: {}", m),
+            Self::UnsupportedFormat(v) => {
+                writeln!(f, "Error Texture container format {} is not supported", v)
+            }
+            Self::Supercompressed(s) => writeln!(
+                f,
+                "Error Texture container supercompression scheme {} is not supported",
+                s
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TextureLoadError {}
+
+impl From<gpu::Error> for TextureLoadError {
+    fn from(e: gpu::Error) -> Self {
+        Self::Gpu(e)
+    }
+}
+
+/// A texture loaded from a container
+///
+/// The dimension of the texture is only known once the container has been read
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+pub enum ContainerTexture {
+    D1(GTexture1D),
+    D1Array(GTexture1DArray),
+    D2(GTexture2D),
+    D2Array(GTexture2DArray),
+    Cube(GTextureCube),
+    CubeArray(GTextureCubeArray),
+    D3(GTexture3D),
+}
+
+impl ContainerTexture {
+    /// The texture loaded
+    pub fn texture(&self) -> &gpu::Texture {
+        match self {
+            Self::D1(t) => &t.texture,
+            Self::D1Array(t) => &t.texture,
+            Self::D2(t) => &t.texture,
+            Self::D2Array(t) => &t.texture,
+            Self::Cube(t) => &t.texture,
+            Self::CubeArray(t) => &t.texture,
+            Self::D3(t) => &t.texture,
+        }
+    }
+
+    /// The default view of the whole texture
+    pub fn view(&self) -> &gpu::TextureView {
+        match self {
+            Self::D1(t) => &t.view,
+            Self::D1Array(t) => &t.view,
+            Self::D2(t) => &t.view,
+            Self::D2Array(t) => &t.view,
+            Self::Cube(t) => &t.view,
+            Self::CubeArray(t) => &t.view,
+            Self::D3(t) => &t.view,
+        }
+    }
+
+    /// The dimension of the texture
+    pub fn dimension(&self) -> gpu::TextureDimension {
+        self.texture().dimension()
+    }
+
+    /// Returns Some if the texture is 2 dimensional
+    pub fn into_2d(self) -> Option<GTexture2D> {
+        match self {
+            Self::D2(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Returns Some if the texture is a 2 dimensional array
+    pub fn into_2d_array(self) -> Option<GTexture2DArray> {
+        match self {
+            Self::D2Array(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Returns Some if the texture is a cube
+    pub fn into_cube(self) -> Option<GTextureCube> {
+        match self {
+            Self::Cube(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Returns Some if the texture is a cube array
+    pub fn into_cube_array(self) -> Option<GTextureCubeArray> {
+        match self {
+            Self::CubeArray(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Returns Some if the texture is 3 dimensional
+    pub fn into_3d(self) -> Option<GTexture3D> {
+        match self {
+            Self::D3(t) => Some(t),
+            _ => None,
+        }
+    }
+}
+
+/// The contents of a container ready to be uploaded
+struct ContainerData<'a> {
+    format: gpu::Format,
+    width: u32,
+    height: u32,
+    depth: u32,
+    /// number of array elements, 0 for non array textures
+    layers: u32,
+    /// 1 or 6 for cube maps
+    faces: u32,
+    /// data for each mip level, each containing every layer and face tightly packed
+    levels: Vec<Cow<'a, [u8]>>,
+    /// generate the mip chain from the first level
+    generate_mips: bool,
+}
+
+impl<'a> ContainerData<'a> {
+    /// The size in bytes of a mip level or None if it overflows
+    fn level_size(&self, level: u32) -> Option<usize> {
+        let w = self.width.checked_shr(level)?.max(1) as u64;
+        let h = self.height.checked_shr(level)?.max(1) as u64;
+        let d = self.depth.checked_shr(level)?.max(1) as u64;
+        let (bw, bh) = self.format.block_extent();
+        let blocks = ((w + bw as u64 - 1) / bw as u64)
+            .checked_mul((h + bh as u64 - 1) / bh as u64)?
+            .checked_mul(d)?;
+        let elements = (self.layers.max(1) as u64).checked_mul(self.faces as u64)?;
+        let size = (self.format.size() as u64)
+            .checked_mul(blocks)?
+            .checked_mul(elements)?;
+        usize::try_from(size).ok()
+    }
+
+    /// The number of mip levels in a full chain for the container's dimensions
+    fn max_levels(&self) -> u32 {
+        full_mip_levels(self.width.max(self.depth), self.height)
+    }
+
+    fn upload(
+        &self,
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        usage: gpu::TextureUsage,
+        name: Option<&str>,
+    ) -> Result<ContainerTexture, TextureLoadError> {
+        let w = self.width;
+        let h = self.height.max(1);
+        let d = self.depth.max(1);
+        let s = gpu::Samples::S1;
+        Ok(if self.faces == 6 {
+            if self.layers > 0 {
+                ContainerTexture::CubeArray(self.upload_dimension(
+                    encoder,
+                    device,
+                    CubeArray(w, self.layers),
+                    usage,
+                    name,
+                )?)
+            } else {
+                ContainerTexture::Cube(self.upload_dimension(
+                    encoder,
+                    device,
+                    Cube(w),
+                    usage,
+                    name,
+                )?)
+            }
+        } else if d > 1 {
+            ContainerTexture::D3(self.upload_dimension(
+                encoder,
+                device,
+                D3(w, h, d),
+                usage,
+                name,
+            )?)
+        } else if self.height == 0 {
+            if self.layers > 0 {
+                ContainerTexture::D1Array(self.upload_dimension(
+                    encoder,
+                    device,
+                    D1Array(w, self.layers),
+                    usage,
+                    name,
+                )?)
+            } else {
+                ContainerTexture::D1(self.upload_dimension(encoder, device, D1(w), usage, name)?)
+            }
+        } else if self.layers > 0 {
+            ContainerTexture::D2Array(self.upload_dimension(
+                encoder,
+                device,
+                D2Array(w, h, s, self.layers),
+                usage,
+                name,
+            )?)
+        } else {
+            ContainerTexture::D2(self.upload_dimension(
+                encoder,
+                device,
+                D2(w, h, s),
+                usage,
+                name,
+            )?)
+        })
+    }
+
+    fn upload_dimension<D: AsDimension>(
+        &self,
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        dimension: D,
+        usage: gpu::TextureUsage,
+        name: Option<&str>,
+    ) -> Result<GTexture<D>, TextureLoadError> {
+        let generate = self.generate_mips
+            && !self.format.is_compressed()
+            && can_generate_mipmaps(device, self.format);
+        let mip_levels = if generate {
+            full_mip_levels(self.width, self.height)
+        } else {
+            self.levels.len() as u32
+        };
+
+        let texture = GTexture::from_dimension(
+            device,
+            dimension,
+            usage | gpu::TextureUsage::COPY_DST | gpu::TextureUsage::COPY_SRC,
+            mip_levels,
+            self.format,
+            name,
+        )?;
+
+        let total = self.levels.iter().map(|l| l.len() as u64).sum::<u64>();
+        let staging = device.create_buffer(&gpu::BufferDesc {
+            name: None,
+            size: total,
+            usage: gpu::BufferUsage::COPY_SRC,
+            memory: gpu::MemoryType::Host,
+        })?;
+
+        let array_layers = texture.dimension.as_dimension().layers();
+        let extent: gpu::Extent3D = texture.dimension.as_dimension().into();
+        let mut offset = 0;
+        for (level, data) in self.levels.iter().enumerate() {
+            let level = level as u32;
+            let size = data.len() as u64;
+            staging.slice_ref(offset..(offset + size)).write(data)?;
+            encoder.copy_buffer_to_texture(
+                staging.slice_owned(offset..(offset + size)),
+                texture.texture.slice_owned(&gpu::TextureSliceDesc {
+                    offset: gpu::Offset3D::ZERO,
+                    extent: gpu::Extent3D {
+                        width: (extent.width >> level).max(1),
+                        height: (extent.height >> level).max(1),
+                        depth: (extent.depth >> level).max(1),
+                    },
+                    base_array_layer: 0,
+                    array_layers,
+                    base_mip_level: level,
+                    mip_levels: 1,
+                }),
+            );
+            offset += size;
+        }
+
+        if generate {
+            texture.gen_mipmaps_owned(encoder);
+        }
+
+        Ok(texture)
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, TextureLoadError> {
+    bytes
+        .get(offset..(offset + 4))
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(TextureLoadError::InvalidData("unexpected end of data"))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, TextureLoadError> {
+    Ok(read_u32(bytes, offset)? as u64 | (read_u32(bytes, offset + 4)? as u64) << 32)
+}
+
+fn read_range(bytes: &[u8], offset: usize, len: usize) -> Result<&[u8], TextureLoadError> {
+    offset
+        .checked_add(len)
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or(TextureLoadError::InvalidData("unexpected end of data"))
+}
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// Load a texture from the contents of a KTX2 file
+///
+/// All mip levels, array layers and cube faces in the file are copied into the texture
+/// If the file has a level count of 0 the mip chain is generated from the base level if the format supports it
+/// Supercompressed files (zstd, zlib, basis universal) are not supported
+///
+/// <https://registry.khronos.org/KTX/specs/2.0/ktxspec.v2.html>
+pub fn load_ktx2(
+    encoder: &mut crate::CommandEncoder<'_>,
+    device: &gpu::Device,
+    bytes: &[u8],
+    usage: gpu::TextureUsage,
+    name: Option<&str>,
+) -> Result<ContainerTexture, TextureLoadError> {
+    if bytes.get(0..12) != Some(&KTX2_IDENTIFIER[..]) {
+        return Err(TextureLoadError::InvalidData("missing ktx2 identifier"));
+    }
+
+    let vk_format = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 20)?;
+    let height = read_u32(bytes, 24)?;
+    let depth = read_u32(bytes, 28)?;
+    let layers = read_u32(bytes, 32)?;
+    let faces = read_u32(bytes, 36)?;
+    let level_count = read_u32(bytes, 40)?;
+    let supercompression = read_u32(bytes, 44)?;
+
+    if supercompression != 0 {
+        return Err(TextureLoadError::Supercompressed(supercompression));
+    }
+    let format = gpu::Format::from_raw(vk_format as i32);
+    if format == gpu::Format::Unknown {
+        return Err(TextureLoadError::UnsupportedFormat(vk_format));
+    }
+    if width == 0 {
+        return Err(TextureLoadError::InvalidData("ktx2 width is 0"));
+    }
+    if faces != 1 && faces != 6 {
+        return Err(TextureLoadError::InvalidData(
+            "ktx2 face count must be 1 or 6",
+        ));
+    }
+
+    let mut data = ContainerData {
+        format,
+        width,
+        height,
+        depth,
+        layers,
+        faces,
+        levels: Vec::new(),
+        generate_mips: level_count == 0,
+    };
+
+    if level_count > data.max_levels() {
+        return Err(TextureLoadError::InvalidData(
+            "ktx2 level count exceeds the full mip chain",
+        ));
+    }
+
+    // the level index follows the 80 byte header and section offsets
+    for level in 0..level_count.max(1) {
+        let index = 80 + level as usize * 24;
+        let offset = usize::try_from(read_u64(bytes, index)?)
+            .map_err(|_| TextureLoadError::InvalidData("ktx2 level offset out of range"))?;
+        let len = usize::try_from(read_u64(bytes, index + 8)?)
+            .map_err(|_| TextureLoadError::InvalidData("ktx2 level size out of range"))?;
+        let size = data
+            .level_size(level)
+            .ok_or(TextureLoadError::InvalidData("ktx2 level size overflows"))?;
+        if len != size {
+            return Err(TextureLoadError::InvalidData(
+                "ktx2 level size doesn't match dimensions",
+            ));
+        }
+        data.levels
+            .push(Cow::Borrowed(read_range(bytes, offset, len)?));
+    }
+
+    data.upload(encoder, device, usage, name)
+}
+
+const DDS_MAGIC: u32 = 0x20534444;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x20000;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_VOLUME: u32 = 0x200000;
+const DDS_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+const DDS_DIMENSION_TEXTURE1D: u32 = 2;
+
+const fn four_cc(c: &[u8; 4]) -> u32 {
+    u32::from_le_bytes(*c)
+}
+
+/// Load a texture from the contents of a DDS file
+///
+/// Supports the DX10 extended header as well as the legacy block compressed and rgba formats
+/// All mip levels, array layers and cube faces in the file are copied into the texture
+///
+/// <https://learn.microsoft.com/en-us/windows/win32/direct3ddds/dx-graphics-dds-pguide>
+pub fn load_dds(
+    encoder: &mut crate::CommandEncoder<'_>,
+    device: &gpu::Device,
+    bytes: &[u8],
+    usage: gpu::TextureUsage,
+    name: Option<&str>,
+) -> Result<ContainerTexture, TextureLoadError> {
+    if read_u32(bytes, 0)? != DDS_MAGIC || read_u32(bytes, 4)? != 124 {
+        return Err(TextureLoadError::InvalidData("missing dds magic"));
+    }
+
+    let flags = read_u32(bytes, 8)?;
+    let mut height = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 16)?;
+    let depth = read_u32(bytes, 24)?;
+    let mip_count = if flags & DDSD_MIPMAPCOUNT != 0 {
+        read_u32(bytes, 28)?.max(1)
+    } else {
+        1
+    };
+    let pf_flags = read_u32(bytes, 80)?;
+    let pf_four_cc = read_u32(bytes, 84)?;
+    let caps2 = read_u32(bytes, 112)?;
+
+    let mut data_offset = 128;
+    let mut layers = 0;
+    let mut faces = if caps2 & DDSCAPS2_CUBEMAP != 0 { 6 } else { 1 };
+    let volume = caps2 & DDSCAPS2_VOLUME != 0;
+
+    let format = if pf_flags & DDPF_FOURCC != 0 && pf_four_cc == four_cc(b"DX10") {
+        let dxgi_format = read_u32(bytes, 128)?;
+        let resource_dimension = read_u32(bytes, 132)?;
+        let misc = read_u32(bytes, 136)?;
+        let array_size = read_u32(bytes, 140)?;
+        data_offset = 148;
+        if misc & DDS_RESOURCE_MISC_TEXTURECUBE != 0 {
+            faces = 6;
+        }
+        if array_size > 1 {
+            layers = array_size;
+        }
+        if resource_dimension == DDS_DIMENSION_TEXTURE1D {
+            height = 0;
+        }
+        dxgi_to_format(dxgi_format).ok_or(TextureLoadError::UnsupportedFormat(dxgi_format))?
+    } else if pf_flags & DDPF_FOURCC != 0 {
+        legacy_four_cc_to_format(pf_four_cc)
+            .ok_or(TextureLoadError::UnsupportedFormat(pf_four_cc))?
+    } else {
+        let bit_count = read_u32(bytes, 88)?;
+        let r_mask = read_u32(bytes, 92)?;
+        let a_mask = read_u32(bytes, 104)?;
+        match (pf_flags & (DDPF_RGB | DDPF_LUMINANCE), bit_count, r_mask) {
+            (DDPF_RGB, 32, 0xff) => gpu::Format::Rgba8Unorm,
+            (DDPF_RGB, 32, 0xff0000) => gpu::Format::Bgra8Unorm,
+            (DDPF_RGB, 32, 0xffff) => gpu::Format::Rg16Unorm,
+            (DDPF_LUMINANCE, 8, _) => gpu::Format::R8Unorm,
+            (DDPF_LUMINANCE, 16, _) if pf_flags & DDPF_ALPHAPIXELS != 0 && a_mask == 0xff00 => {
+                gpu::Format::Rg8Unorm
+            }
+            (DDPF_LUMINANCE, 16, _) => gpu::Format::R16Unorm,
+            _ => return Err(TextureLoadError::UnsupportedFormat(pf_flags)),
+        }
+    };
+
+    if width == 0 {
+        return Err(TextureLoadError::InvalidData("dds width is 0"));
+    }
+
+    let mut data = ContainerData {
+        format,
+        width,
+        height,
+        depth: if volume { depth } else { 0 },
+        layers,
+        faces,
+        levels: Vec::new(),
+        generate_mips: false,
+    };
+
+    if mip_count > data.max_levels() {
+        return Err(TextureLoadError::InvalidData(
+            "dds mip count exceeds the full mip chain",
+        ));
+    }
+
+    // dds stores every mip level of one layer before the next layer
+    // but copies need every layer of one mip level so reorder the data
+    let elements = layers
+        .max(1)
+        .checked_mul(faces)
+        .ok_or(TextureLoadError::InvalidData("dds data size overflows"))?
+        as usize;
+    let element_sizes = (0..mip_count)
+        .map(|l| data.level_size(l).map(|s| s / elements))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(TextureLoadError::InvalidData("dds data size overflows"))?;
+    let total = element_sizes
+        .iter()
+        .try_fold(0usize, |a, &s| a.checked_add(s))
+        .and_then(|s| s.checked_mul(elements))
+        .ok_or(TextureLoadError::InvalidData("dds data size overflows"))?;
+    let element_size = total / elements;
+    let contents = read_range(bytes, data_offset, total)?;
+
+    for (level, &size) in element_sizes.iter().enumerate() {
+        let level_offset = element_sizes[..level].iter().sum::<usize>();
+        if elements == 1 {
+            data.levels.push(Cow::Borrowed(
+                &contents[level_offset..(level_offset + size)],
+            ));
+        } else {
+            let mut level_data = Vec::with_capacity(size * elements);
+            for element in 0..elements {
+                let start = element * element_size + level_offset;
+                level_data.extend_from_slice(&contents[start..(start + size)]);
+            }
+            data.levels.push(Cow::Owned(level_data));
+        }
+    }
+
+    data.upload(encoder, device, usage, name)
+}
+
+fn legacy_four_cc_to_format(four: u32) -> Option<gpu::Format> {
+    use gpu::Format::*;
+    Some(match four {
+        f if f == four_cc(b"DXT1") => Bc1RgbaUnorm,
+        f if f == four_cc(b"DXT2") || f == four_cc(b"DXT3") => Bc2Unorm,
+        f if f == four_cc(b"DXT4") || f == four_cc(b"DXT5") => Bc3Unorm,
+        f if f == four_cc(b"ATI1") || f == four_cc(b"BC4U") => Bc4Unorm,
+        f if f == four_cc(b"BC4S") => Bc4Snorm,
+        f if f == four_cc(b"ATI2") || f == four_cc(b"BC5U") => Bc5Unorm,
+        f if f == four_cc(b"BC5S") => Bc5Snorm,
+        // legacy D3DFORMAT values
+        36 => Rgba16Unorm,
+        110 => Rgba16Snorm,
+        111 => R16Float,
+        112 => Rg16Float,
+        113 => Rgba16Float,
+        114 => R32Float,
+        115 => Rg32Float,
+        116 => Rgba32Float,
+        _ => return None,
+    })
+}
+
+fn dxgi_to_format(dxgi: u32) -> Option<gpu::Format> {
+    use gpu::Format::*;
+    Some(match dxgi {
+        2 => Rgba32Float,
+        3 => Rgba32Uint,
+        4 => Rgba32Sint,
+        6 => Rgb32Float,
+        7 => Rgb32Uint,
+        8 => Rgb32Sint,
+        10 => Rgba16Float,
+        11 => Rgba16Unorm,
+        13 => Rgba16Snorm,
+        16 => Rg32Float,
+        17 => Rg32Uint,
+        18 => Rg32Sint,
+        28 => Rgba8Unorm,
+        29 => Rgba8Srgb,
+        31 => Rgba8Snorm,
+        34 => Rg16Float,
+        35 => Rg16Unorm,
+        37 => Rg16Snorm,
+        41 => R32Float,
+        42 => R32Uint,
+        43 => R32Sint,
+        49 => Rg8Unorm,
+        51 => Rg8Snorm,
+        54 => R16Float,
+        56 => R16Unorm,
+        58 => R16Snorm,
+        61 => R8Unorm,
+        63 => R8Snorm,
+        71 => Bc1RgbaUnorm,
+        72 => Bc1RgbaSrgb,
+        74 => Bc2Unorm,
+        75 => Bc2Srgb,
+        77 => Bc3Unorm,
+        78 => Bc3Srgb,
+        80 => Bc4Unorm,
+        81 => Bc4Snorm,
+        83 => Bc5Unorm,
+        84 => Bc5Snorm,
+        87 => Bgra8Unorm,
+        91 => Bgra8Srgb,
+        95 => Bc6hUfloat,
+        96 => Bc6hSfloat,
+        98 => Bc7Unorm,
+        99 => Bc7Srgb,
+        _ => return None,
+    })
+}
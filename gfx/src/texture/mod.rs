@@ -20,9 +20,11 @@
 //! find a format that works (If necissary can use image methods to change the pixel type of the image)
 //!
 
+pub mod container;
 pub mod formats;
 pub mod traits;
 
+pub use container::*;
 pub use formats::*;
 pub use traits::*;
 
@@ -215,6 +217,8 @@ pub fn alt_formats(format: gpu::Format) -> impl Iterator<Item = gpu::Format> {
         }
         Stencil8Uint => vec![Stencil8Uint].into_iter(),
         Unknown => vec![Unknown].into_iter(),
+        // compressed data can't be reinterpreted as another format
+        f => vec![f].into_iter(),
     }
 }
 
@@ -251,6 +255,40 @@ pub fn choose_format<'a>(
     None
 }
 
+/// How the color data in an image should be interpreted when sampled
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// Color data such as albedo or emission, decoded from sRGB when sampled
+    Srgb,
+    /// Non color data such as normals, roughness or metallic, sampled unchanged
+    Linear,
+}
+
+impl ColorSpace {
+    /// Get the format to use for data of this color space with the base format
+    ///
+    /// Formats without an sRGB variant are returned unchanged
+    pub fn format(&self, format: gpu::Format) -> gpu::Format {
+        match self {
+            Self::Srgb => format.to_srgb(),
+            Self::Linear => format.to_linear(),
+        }
+    }
+}
+
+/// The number of mip levels in a full mip chain for a texture of width x height
+pub fn full_mip_levels(width: gpu::Size, height: gpu::Size) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// Returns true if mipmaps can be generated by linear blits for textures of format
+pub fn can_generate_mipmaps(device: &gpu::Device, format: gpu::Format) -> bool {
+    let p = device.format_properties(format);
+    p.blit()
+        && p.texture_features
+            .contains(gpu::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR)
+}
+
 /// Calculates the maximum number of mip levels for a texture of dimensions supplied
 pub fn max_mip_levels<D: AsDimension>(dimension: D) -> u32 {
     let extent: gpu::Extent3D = dimension.as_dimension().into();
@@ -290,6 +328,50 @@ impl CubeFace {
     }
 }
 
+/// Views into each layer of a texture for rendering into one layer at a time
+///
+/// Created by [`GTexture2DArray::layer_views`], [`GTextureCube::face_views`], [`GTextureCubeArray::face_views`]
+/// or [`GTexture3D::slice_views`] each view covers the first mip level of one layer so can be used as an attachment.
+/// For cube textures the layer of a face is `index * 6 + face as u32`, for 3D textures the layer is the depth slice
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LayerViews {
+    /// one view for each layer of the texture
+    pub views: Vec<gpu::TextureView>,
+}
+
+impl LayerViews {
+    fn new(texture: &gpu::Texture, layers: gpu::Layer) -> Result<Self, gpu::Error> {
+        let views = (0..layers)
+            .map(|layer| texture.create_layer_view(layer, 0))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { views })
+    }
+
+    /// Get the view of the layer
+    pub fn layer(&self, layer: u32) -> &gpu::TextureView {
+        &self.views[layer as usize]
+    }
+
+    /// Get an attachment that renders into the layer storing the result
+    pub fn attachment(
+        &self,
+        layer: u32,
+        load: gpu::LoadOp,
+        clear: gpu::ClearValue,
+    ) -> crate::Attachment<'_> {
+        crate::Attachment {
+            raw: gpu::Attachment::View(std::borrow::Cow::Borrowed(self.layer(layer)), clear),
+            load,
+            store: gpu::StoreOp::Store,
+        }
+    }
+
+    /// The number of layers
+    pub fn len(&self) -> u32 {
+        self.views.len() as u32
+    }
+}
+
 /// A Staticly typed texture That provides assaurances when loading from files
 /// and Simple methods to do so,
 /// Also allows access to the base texture and view
@@ -374,12 +456,8 @@ impl<D: AsDimension> GTexture<D> {
 
     /// Generate mipmaps from the base mip level
     pub fn gen_mipmaps_ref<'a>(&'a self, encoder: &mut crate::CommandEncoder<'a>) {
-        for level in 1..self.texture.mip_levels() {
-            encoder.blit_textures(
-                self.mip_slice_ref(level - 1),
-                self.mip_slice_ref(level),
-                gpu::FilterMode::Linear,
-            );
+        if self.texture.mip_levels() > 1 {
+            encoder.generate_mipmaps(self.whole_slice_ref(), gpu::FilterMode::Linear);
         }
     }
 
@@ -420,12 +498,8 @@ impl<D: AsDimension> GTexture<D> {
 
     /// Generate mipmaps from the base mip level
     pub fn gen_mipmaps_owned(&self, encoder: &mut crate::CommandEncoder<'_>) {
-        for level in 1..self.texture.mip_levels() {
-            encoder.blit_textures(
-                self.mip_slice_owned(level - 1),
-                self.mip_slice_owned(level),
-                gpu::FilterMode::Linear,
-            );
+        if self.texture.mip_levels() > 1 {
+            encoder.generate_mipmaps(self.whole_slice_owned(), gpu::FilterMode::Linear);
         }
     }
 
@@ -943,6 +1017,204 @@ impl GTexture2D {
         Ok(t)
     }
 
+    /// Create a new texture from a dynamic image with a full mip chain
+    ///
+    /// 3 component images are converted to 4 components as 3 component formats are rarely supported
+    /// see [`Self::from_image_buffer_mipmapped`]
+    pub fn from_image_mipmapped(
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        image: &image::DynamicImage,
+        usage: gpu::TextureUsage,
+        color_space: ColorSpace,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        match image {
+            image::DynamicImage::ImageLuma8(buf) => Self::from_image_buffer_mipmapped(encoder, device, buf, usage, color_space, name),
+            image::DynamicImage::ImageLumaA8(buf) => Self::from_image_buffer_mipmapped(encoder, device, buf, usage, color_space, name),
+            image::DynamicImage::ImageRgb8(_) => Self::from_image_buffer_mipmapped(encoder, device, &image.to_rgba8(), usage, color_space, name),
+            image::DynamicImage::ImageRgba8(buf) => Self::from_image_buffer_mipmapped(encoder, device, buf, usage, color_space, name),
+            image::DynamicImage::ImageBgr8(_) => Self::from_image_buffer_mipmapped(encoder, device, &image.to_rgba8(), usage, color_space, name),
+            image::DynamicImage::ImageBgra8(buf) => Self::from_image_buffer_mipmapped(encoder, device, buf, usage, color_space, name),
+            image::DynamicImage::ImageLuma16(buf) => Self::from_image_buffer_mipmapped(encoder, device, buf, usage, color_space, name),
+            image::DynamicImage::ImageLumaA16(buf) => Self::from_image_buffer_mipmapped(encoder, device, buf, usage, color_space, name),
+            image::DynamicImage::ImageRgb16(_) => Self::from_image_buffer_mipmapped(encoder, device, &image.to_rgba16(), usage, color_space, name),
+            image::DynamicImage::ImageRgba16(buf) => Self::from_image_buffer_mipmapped(encoder, device, buf, usage, color_space, name),
+        }
+    }
+
+    /// Create a new texture from an image buffer with a full mip chain
+    ///
+    /// The format is inferred from the pixel type and converted to sRGB or linear by color_space
+    /// The mip chain is generated with linear blits from the base level, if the format doesn't support
+    /// linear blits then the texture will only have one mip level
+    ///
+    /// The upload and the blits are both recorded into encoder which must be submitted on a queue with
+    /// graphics support, see [`Self::from_image_buffer_mipmapped_transfer`] to upload on a transfer queue
+    pub fn from_image_buffer_mipmapped<C, P>(
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        image: &image::ImageBuffer<P, C>,
+        usage: gpu::TextureUsage,
+        color_space: ColorSpace,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error>
+    where
+        P: FormatData + image::Pixel + 'static,
+        P::Subpixel: 'static + bytemuck::Pod + bytemuck::Zeroable,
+        C: std::ops::Deref<Target = [P::Subpixel]>,
+    {
+        let (width, height) = image.dimensions();
+        let format = color_space.format(P::FORMAT);
+        let mip_levels = if can_generate_mipmaps(device, format) {
+            full_mip_levels(width, height)
+        } else {
+            1
+        };
+        let t = Self::from_dimension(
+            device,
+            D2(width, height, gpu::Samples::S1),
+            usage | gpu::TextureUsage::COPY_DST | gpu::TextureUsage::COPY_SRC,
+            mip_levels,
+            format,
+            name,
+        )?;
+        t.write_image_buffer(encoder, device, image)?;
+        Ok(t)
+    }
+
+    /// Create a new texture from an image buffer with a full mip chain, uploading the base level on a transfer queue
+    ///
+    /// The base level is copied into the texture by transfer which is recorded and submitted immediately, it should
+    /// submit to the queue of [`gpu::QueueKind::Transfer`] so the upload can overlap with other work.
+    /// Ownership of the texture is then released to the graphics queue of the device and a semaphore signaled,
+    /// encoder waits on the semaphore and acquires the texture before generating the mip chain with blits
+    /// so encoder must be submitted on the graphics queue.
+    ///
+    /// The texture is created exclusive to one queue family at a time, see [`gpu::Texture::new_exclusive`]
+    pub fn from_image_buffer_mipmapped_transfer<C, P>(
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        transfer: &mut gpu::CommandBuffer,
+        image: &image::ImageBuffer<P, C>,
+        usage: gpu::TextureUsage,
+        color_space: ColorSpace,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error>
+    where
+        P: FormatData + image::Pixel + 'static,
+        P::Subpixel: 'static + bytemuck::Pod + bytemuck::Zeroable,
+        C: std::ops::Deref<Target = [P::Subpixel]>,
+    {
+        let (width, height) = image.dimensions();
+        let format = color_space.format(P::FORMAT);
+        let mip_levels = if can_generate_mipmaps(device, format) {
+            full_mip_levels(width, height)
+        } else {
+            1
+        };
+        let dimension = D2(width, height, gpu::Samples::S1);
+        let texture = device.create_exclusive_texture(&gpu::TextureDesc {
+            format,
+            dimension: dimension.as_dimension(),
+            mip_levels: std::num::NonZeroU32::new(mip_levels).unwrap(),
+            usage: usage | gpu::TextureUsage::COPY_DST | gpu::TextureUsage::COPY_SRC,
+            memory: gpu::MemoryType::Device,
+            layout: gpu::TextureLayout::General,
+            name: name.map(|n| n.to_string()),
+        })?;
+        let view = texture.create_default_view()?;
+        let t = Self {
+            texture,
+            view,
+            dimension,
+        };
+
+        let data: &[u8] = bytemuck::cast_slice(image);
+        let staging_buffer = device.create_buffer(&gpu::BufferDesc {
+            size: data.len() as u64,
+            usage: gpu::BufferUsage::COPY_SRC,
+            memory: gpu::MemoryType::Host,
+            name: None,
+        })?;
+        staging_buffer.slice_ref(..).write(data)?;
+
+        // the base level is only written by the upload so its previous contents can be discarded
+        let base_level = |src_layout, dst_layout, src_access, dst_access| gpu::TextureAccessInfo {
+            texture: std::borrow::Cow::Owned(t.texture.clone()),
+            base_mip_level: 0,
+            mip_levels: 1,
+            base_array_layer: 0,
+            array_layers: 1,
+            src_access,
+            dst_access,
+            src_layout,
+            dst_layout,
+        };
+        let src_queue = transfer.queue();
+        let dst_queue = device.queue(gpu::QueueKind::Graphics);
+        let semaphore = device.create_semaphore(name.map(|n| format!("{}_upload", n)).as_deref())?;
+
+        transfer.begin(true)?;
+        transfer.pipeline_barrier(
+            gpu::PipelineStageFlags::TOP_OF_PIPE,
+            gpu::PipelineStageFlags::COPY,
+            &[],
+            &[base_level(
+                gpu::TextureLayout::Undefined,
+                gpu::TextureLayout::CopyDstOptimal,
+                gpu::AccessFlags::empty(),
+                gpu::AccessFlags::COPY_WRITE,
+            )],
+        )?;
+        transfer.copy_buffer_to_texture(
+            staging_buffer.slice_ref(..),
+            t.texture.slice_ref(&gpu::TextureSliceDesc {
+                offset: gpu::Offset3D::ZERO,
+                extent: gpu::Extent3D {
+                    width,
+                    height,
+                    depth: 1,
+                },
+                base_array_layer: 0,
+                array_layers: 1,
+                base_mip_level: 0,
+                mip_levels: 1,
+            }),
+            gpu::TextureLayout::CopyDstOptimal,
+        )?;
+        // release the base level back in the layout the encoder expects the texture to be in
+        transfer.queue_ownership_barrier(
+            src_queue,
+            dst_queue,
+            gpu::PipelineStageFlags::COPY,
+            gpu::PipelineStageFlags::BOTTOM_OF_PIPE,
+            &[base_level(
+                gpu::TextureLayout::CopyDstOptimal,
+                t.texture.initial_layout(),
+                gpu::AccessFlags::COPY_WRITE,
+                gpu::AccessFlags::empty(),
+            )],
+        )?;
+        transfer.signal_semaphore(&semaphore);
+        transfer.end()?;
+        transfer.submit()?;
+
+        encoder.acquire_textures(
+            &semaphore,
+            src_queue,
+            gpu::PipelineStageFlags::COPY,
+            vec![base_level(
+                gpu::TextureLayout::CopyDstOptimal,
+                t.texture.initial_layout(),
+                gpu::AccessFlags::empty(),
+                gpu::AccessFlags::COPY_READ | gpu::AccessFlags::COPY_WRITE,
+            )],
+        );
+        t.gen_mipmaps_owned(encoder);
+        Ok(t)
+    }
+
     /// Write an image to self
     ///
     /// Will panic if the dimensions don't match self
@@ -1100,6 +1372,43 @@ impl GTexture2DArray {
     pub fn layers(&self) -> gpu::Layer {
         self.dimension.3
     }
+
+    /// Create a view into the texture at the specific layer
+    pub fn layer_view(&self, layer: u32) -> Result<gpu::TextureView, gpu::Error> {
+        self.create_view(&gpu::TextureViewDesc {
+            name: None,
+            dimension: gpu::TextureDimension::D2(
+                self.dimension.0,
+                self.dimension.1,
+                self.dimension.2,
+            ),
+            base_mip_level: 0,
+            mip_levels: self.mip_levels(),
+            base_array_layer: layer,
+            format_change: None,
+        })
+    }
+
+    /// Create a view into the texture at the specific layer and mip level
+    pub fn layer_mip_view(&self, layer: u32, mip: u32) -> Result<gpu::TextureView, gpu::Error> {
+        self.create_view(&gpu::TextureViewDesc {
+            name: None,
+            dimension: gpu::TextureDimension::D2(
+                self.dimension.0,
+                self.dimension.1,
+                self.dimension.2,
+            ),
+            base_mip_level: mip,
+            mip_levels: 1,
+            base_array_layer: layer,
+            format_change: None,
+        })
+    }
+
+    /// Create views into each layer of the texture to be rendered to
+    pub fn layer_views(&self) -> Result<LayerViews, gpu::Error> {
+        LayerViews::new(&self.texture, self.dimension.3)
+    }
 }
 
 #[cfg(feature = "image")]
@@ -1347,6 +1656,11 @@ impl GTextureCube {
         })
     }
 
+    /// Create views into each face of the texture to be rendered to
+    pub fn face_views(&self) -> Result<LayerViews, gpu::Error> {
+        LayerViews::new(&self.texture, 6)
+    }
+
     pub fn size(&self) -> gpu::Size {
         self.dimension.0
     }
@@ -1612,6 +1926,11 @@ impl GTextureCubeArray {
         })
     }
 
+    /// Create views into each face of each cube in the texture to be rendered to
+    pub fn face_views(&self) -> Result<LayerViews, gpu::Error> {
+        LayerViews::new(&self.texture, 6 * self.dimension.1)
+    }
+
     pub fn width(&self) -> gpu::Size {
         self.dimension.0
     }
@@ -1740,4 +2059,12 @@ impl GTexture3D {
     pub fn depth(&self) -> gpu::Size {
         self.dimension.2
     }
+
+    /// Create a view for each depth slice of the texture to render into, eg to bake a 3D lookup table
+    ///
+    /// Requires [`gpu::DeviceFeatures::TEXTURE_3D_SLICES`] and the texture to be created with
+    /// [`gpu::TextureUsage::COLOR_OUTPUT`] or [`gpu::TextureUsage::DEPTH_OUTPUT`]
+    pub fn slice_views(&self) -> Result<LayerViews, gpu::Error> {
+        LayerViews::new(&self.texture, self.dimension.2)
+    }
 }
@@ -0,0 +1,490 @@
+//! Rendering [egui](https://crates.io/crates/egui) user interfaces
+//!
+//! The output of `egui::Context::tessellate` is drawn into an attachment with one indexed draw per mesh
+//! each scissored to its clip rect, textures requested by egui are created and updated as needed
+
+use crate::pass::GraphicsPass;
+use std::collections::HashMap;
+
+/// An error from creating an [`EguiRenderer`]
+#[derive(Debug)]
+pub enum UiError {
+    /// An error from the gpu
+    Gpu(gpu::Error),
+    /// An error creating the ui pipeline
+    Reflected(crate::ReflectedError),
+    /// An error creating a texture bundle
+    Bundle(crate::BundleBuildError),
+}
+
+impl std::fmt::Display for UiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gpu(e) => writeln!(f, "{}", e),
+            Self::Reflected(e) => writeln!(f, "{}", e),
+            Self::Bundle(e) => writeln!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for UiError {}
+
+impl From<gpu::Error> for UiError {
+    fn from(e: gpu::Error) -> Self {
+        Self::Gpu(e)
+    }
+}
+
+impl From<crate::ReflectedError> for UiError {
+    fn from(e: crate::ReflectedError) -> Self {
+        Self::Reflected(e)
+    }
+}
+
+impl From<crate::BundleBuildError> for UiError {
+    fn from(e: crate::BundleBuildError) -> Self {
+        Self::Bundle(e)
+    }
+}
+
+impl From<crate::SetResourceError> for UiError {
+    fn from(e: crate::SetResourceError) -> Self {
+        Self::Bundle(e.into())
+    }
+}
+
+/// A vertex of an egui mesh
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiVertex {
+    /// position in normalized device coordinates
+    pub pos: [f32; 2],
+    /// texture coordinate
+    pub uv: [f32; 2],
+    /// premultiplied color in the color space of the target
+    pub color: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for UiVertex {}
+unsafe impl bytemuck::Zeroable for UiVertex {}
+
+impl crate::Vertex for UiVertex {
+    fn get(name: &str) -> Option<(u32, gpu::VertexFormat)> {
+        match name {
+            "in_pos" => Some((0, gpu::VertexFormat::Vec2)),
+            "in_uv" => Some((8, gpu::VertexFormat::Vec2)),
+            "in_color" => Some((16, gpu::VertexFormat::Vec4)),
+            _ => None,
+        }
+    }
+}
+
+/// A texture that egui meshes can reference
+#[derive(Debug)]
+struct UiTexture {
+    texture: crate::GTexture2D,
+    bundle: crate::Bundle,
+}
+
+/// A mesh waiting to be drawn
+#[derive(Debug, Clone, Copy)]
+struct UiDraw {
+    first_index: u32,
+    index_count: u32,
+    vertex_offset: i32,
+    texture: egui::TextureId,
+    scissor: gpu::Scissor,
+}
+
+/// Draws egui user interfaces into attachments
+///
+/// Each frame call [`EguiRenderer::pass`] with the tessellated output of the egui context
+///
+/// Blending is done in the color space of the target format, if the target is sRGB then egui colors
+/// and textures are converted to linear before blending otherwise they are blended as is
+pub struct EguiRenderer {
+    srgb: bool,
+    textures: HashMap<egui::TextureId, UiTexture>,
+    next_user_id: u64,
+    vertex_buffer: Option<gpu::Buffer>,
+    index_buffer: Option<gpu::Buffer>,
+    pipeline: crate::ReflectedGraphics,
+    linear_sampler: gpu::Sampler,
+    nearest_sampler: gpu::Sampler,
+    name: Option<String>,
+}
+
+impl std::fmt::Debug for EguiRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EguiRenderer")
+            .field("srgb", &self.srgb)
+            .field("textures", &self.textures.len())
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl EguiRenderer {
+    /// Create a new EguiRenderer that will draw into attachments of format
+    pub fn new(
+        device: &gpu::Device,
+        format: gpu::Format,
+        name: Option<&str>,
+    ) -> Result<Self, UiError> {
+        let linear_sampler = device.create_sampler(&gpu::SamplerDesc {
+            name: name.map(|n| format!("{}_linear_sampler", n)),
+            wrap_x: gpu::WrapMode::ClampToEdge,
+            wrap_y: gpu::WrapMode::ClampToEdge,
+            ..gpu::SamplerDesc::LINEAR
+        })?;
+
+        let nearest_sampler = device.create_sampler(&gpu::SamplerDesc {
+            name: name.map(|n| format!("{}_nearest_sampler", n)),
+            wrap_x: gpu::WrapMode::ClampToEdge,
+            wrap_y: gpu::WrapMode::ClampToEdge,
+            ..gpu::SamplerDesc::NEAREST
+        })?;
+
+        let pipeline = Self::create_pipeline(device, name)?;
+
+        Ok(Self {
+            srgb: format.is_srgb(),
+            textures: HashMap::new(),
+            next_user_id: 0,
+            vertex_buffer: None,
+            index_buffer: None,
+            pipeline,
+            linear_sampler,
+            nearest_sampler,
+            name: name.map(|n| n.to_string()),
+        })
+    }
+
+    fn create_pipeline(
+        device: &gpu::Device,
+        name: Option<&str>,
+    ) -> Result<crate::ReflectedGraphics, crate::ReflectedError> {
+        let vertex = spv::Builder::new();
+        {
+            let b = &vertex;
+            let in_pos = b.in_vec2(0, "in_pos");
+            let in_uv = b.in_vec2(1, "in_uv");
+            let in_color = b.in_vec4(2, "in_color");
+
+            let vk_pos = b.vk_position();
+            let out_uv = b.out_vec2(0, "out_uv");
+            let out_color = b.out_vec4(1, "out_color");
+
+            b.entry(spv::Stage::Vertex, "main", || {
+                let pos = in_pos.load();
+                vk_pos.store(b.vec4(pos.x(), pos.y(), 0.0, 1.0));
+                out_uv.store(in_uv.load());
+                out_color.store(in_color.load());
+            });
+        }
+
+        let fragment = spv::Builder::new();
+        {
+            let b = &fragment;
+            let in_uv = b.in_vec2(0, "in_uv");
+            let in_color = b.in_vec4(1, "in_color");
+
+            let out_color = b.out_vec4(0, "out_color");
+
+            let u_texture = b.texture2d(0, 0, Some("u_texture"));
+            let u_sampler = b.sampler(0, 1, Some("u_sampler"));
+
+            b.entry(spv::Stage::Fragment, "main", || {
+                let combined = spv::combine(&u_texture, u_sampler);
+                let sample = spv::sample(&combined, in_uv.load());
+                out_color.store(in_color.load() * sample);
+            });
+        }
+
+        crate::ReflectedGraphics::from_builders(
+            device,
+            &vertex,
+            None,
+            Some(&fragment),
+            gpu::Rasterizer::default(),
+            &[gpu::BlendState::PREMULTIPLIED_ALPHA],
+            None,
+            None,
+            name.map(|n| format!("{}_pipeline", n)).as_deref(),
+        )
+    }
+
+    /// Make a texture available to egui, returns the id to use in `egui::Image` and similar widgets
+    ///
+    /// The format of the texture should be sRGB if the target is sRGB to blend correctly
+    pub fn register_texture(
+        &mut self,
+        device: &gpu::Device,
+        texture: &crate::GTexture2D,
+        filter: egui::TextureFilter,
+    ) -> Result<egui::TextureId, UiError> {
+        let id = egui::TextureId::User(self.next_user_id);
+        self.next_user_id += 1;
+        let bundle = self.texture_bundle(device, texture, filter)?;
+        self.textures.insert(
+            id,
+            UiTexture {
+                texture: texture.clone(),
+                bundle,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Stop a texture previously registered with [`EguiRenderer::register_texture`] being available to egui
+    pub fn unregister_texture(&mut self, id: egui::TextureId) {
+        self.textures.remove(&id);
+    }
+
+    fn texture_bundle(
+        &self,
+        device: &gpu::Device,
+        texture: &crate::GTexture2D,
+        filter: egui::TextureFilter,
+    ) -> Result<crate::Bundle, UiError> {
+        let sampler = match filter {
+            egui::TextureFilter::Linear => &self.linear_sampler,
+            egui::TextureFilter::Nearest => &self.nearest_sampler,
+        };
+        Ok(self
+            .pipeline
+            .bundle()
+            .unwrap()
+            .set_resource("u_texture", texture)?
+            .set_resource("u_sampler", sampler)?
+            .build(device)?)
+    }
+
+    /// Create or update the textures egui requested
+    fn set_textures(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'_>,
+        device: &gpu::Device,
+        delta: &egui::TexturesDelta,
+    ) -> Result<(), UiError> {
+        for (id, image_delta) in &delta.set {
+            let [width, height] = image_delta.image.size();
+            let data = match &image_delta.image {
+                egui::ImageData::Color(image) => image
+                    .pixels
+                    .iter()
+                    .flat_map(|c| c.to_array())
+                    .collect::<Vec<u8>>(),
+                egui::ImageData::Font(image) => image
+                    .srgba_pixels(1.0)
+                    .flat_map(|c| c.to_array())
+                    .collect::<Vec<u8>>(),
+            };
+
+            if image_delta.pos.is_none() {
+                let format = if self.srgb {
+                    crate::ColorSpace::Srgb
+                } else {
+                    crate::ColorSpace::Linear
+                }
+                .format(gpu::Format::Rgba8Unorm);
+                let texture = crate::GTexture2D::new(
+                    device,
+                    width as _,
+                    height as _,
+                    gpu::Samples::S1,
+                    gpu::TextureUsage::SAMPLED | gpu::TextureUsage::COPY_DST,
+                    1,
+                    format,
+                    self.name
+                        .as_ref()
+                        .map(|n| format!("{}_texture_{:?}", n, id))
+                        .as_deref(),
+                )?;
+                let bundle = self.texture_bundle(device, &texture, image_delta.filter)?;
+                self.textures.insert(*id, UiTexture { texture, bundle });
+            }
+
+            let [x, y] = image_delta.pos.unwrap_or([0, 0]);
+            let texture = match self.textures.get(id) {
+                Some(t) => &t.texture,
+                None => panic!(
+                    "ERROR: EguiRenderer {:?} asked to update texture {:?} that doesn't exist",
+                    self.name, id
+                ),
+            };
+            texture.write_data_owned(
+                encoder,
+                device,
+                &data,
+                gpu::Offset3D {
+                    x: x as _,
+                    y: y as _,
+                    z: 0,
+                },
+                gpu::Extent3D {
+                    width: width as _,
+                    height: height as _,
+                    depth: 1,
+                },
+                0,
+                1,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Grow buffer to hold data and record copying data into it
+    fn upload<'a>(
+        encoder: &mut crate::CommandEncoder<'a>,
+        device: &gpu::Device,
+        buffer: &mut Option<gpu::Buffer>,
+        data: &[u8],
+        usage: gpu::BufferUsage,
+        name: Option<String>,
+    ) -> Result<gpu::BufferSlice<'a>, gpu::Error> {
+        let size = data.len() as u64;
+        if buffer.as_ref().map(|b| b.size() < size).unwrap_or(true) {
+            *buffer = Some(device.create_buffer(&gpu::BufferDesc {
+                name,
+                size: size.next_power_of_two(),
+                usage: usage | gpu::BufferUsage::COPY_DST,
+                memory: gpu::MemoryType::Device,
+            })?);
+        }
+        let buffer = buffer.as_ref().unwrap();
+
+        let staging = device.create_buffer(&gpu::BufferDesc {
+            name: None,
+            size,
+            usage: gpu::BufferUsage::COPY_SRC,
+            memory: gpu::MemoryType::Host,
+        })?;
+        staging.slice_ref(..).write(data)?;
+        encoder.copy_buffer_to_buffer(staging.into_slice(..), buffer.slice_owned(0..size));
+        Ok(buffer.slice_owned(0..size))
+    }
+
+    /// Draw the tessellated output of an egui context into the target
+    ///
+    /// textures_delta is the `textures_delta` of the `egui::FullOutput` the primitives were tessellated from
+    /// pixels_per_point should be `egui::Context::pixels_per_point`
+    /// The target should be loaded if the ui is to be drawn over what is already there
+    /// Paint callbacks are not supported and are skipped
+    pub fn pass<'a>(
+        &mut self,
+        encoder: &mut crate::CommandEncoder<'a>,
+        device: &gpu::Device,
+        target: crate::Attachment<'a>,
+        primitives: &[egui::ClippedPrimitive],
+        textures_delta: &egui::TexturesDelta,
+        pixels_per_point: f32,
+    ) -> Result<(), UiError> {
+        self.set_textures(encoder, device, textures_delta)?;
+
+        let extent = target.raw.view().extent();
+        let (width, height) = (extent.width as f32, extent.height as f32);
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut draws = Vec::new();
+        for primitive in primitives {
+            let mesh = match &primitive.primitive {
+                egui::epaint::Primitive::Mesh(mesh) => mesh,
+                egui::epaint::Primitive::Callback(_) => {
+                    #[cfg(feature = "logging")]
+                    log::warn!("GFX: EguiRenderer doesn't support paint callbacks, skipping");
+                    continue;
+                }
+            };
+            if mesh.indices.is_empty() {
+                continue;
+            }
+
+            // clip rect is in points, convert to pixels and clamp to the target
+            let clip = primitive.clip_rect;
+            let min_x = (clip.min.x * pixels_per_point).round().clamp(0.0, width);
+            let min_y = (clip.min.y * pixels_per_point).round().clamp(0.0, height);
+            let max_x = (clip.max.x * pixels_per_point).round().clamp(min_x, width);
+            let max_y = (clip.max.y * pixels_per_point).round().clamp(min_y, height);
+            if max_x == min_x || max_y == min_y {
+                continue;
+            }
+
+            draws.push(UiDraw {
+                first_index: indices.len() as u32,
+                index_count: mesh.indices.len() as u32,
+                vertex_offset: vertices.len() as i32,
+                texture: mesh.texture_id,
+                scissor: gpu::Scissor {
+                    x: min_x as i32,
+                    y: min_y as i32,
+                    width: (max_x - min_x) as u32,
+                    height: (max_y - min_y) as u32,
+                },
+            });
+            indices.extend_from_slice(&mesh.indices);
+            vertices.extend(mesh.vertices.iter().map(|v| UiVertex {
+                pos: [
+                    v.pos.x * pixels_per_point / width * 2.0 - 1.0,
+                    v.pos.y * pixels_per_point / height * 2.0 - 1.0,
+                ],
+                uv: [v.uv.x, v.uv.y],
+                color: if self.srgb {
+                    egui::Rgba::from(v.color).to_array()
+                } else {
+                    v.color.to_array().map(|c| c as f32 / 255.0)
+                },
+            }));
+        }
+
+        if !draws.is_empty() {
+            let vertex_slice = Self::upload(
+                encoder,
+                device,
+                &mut self.vertex_buffer,
+                bytemuck::cast_slice(&vertices),
+                gpu::BufferUsage::VERTEX,
+                self.name.as_ref().map(|n| format!("{}_vertex_buffer", n)),
+            )?;
+            let index_slice = Self::upload(
+                encoder,
+                device,
+                &mut self.index_buffer,
+                bytemuck::cast_slice(&indices),
+                gpu::BufferUsage::INDEX,
+                self.name.as_ref().map(|n| format!("{}_index_buffer", n)),
+            )?;
+
+            let mut pass = encoder.graphics_pass_reflected::<UiVertex>(
+                device,
+                &[target],
+                &[],
+                None,
+                &self.pipeline,
+            )?;
+            pass.bind_vertex_buffer(vertex_slice, 0);
+            pass.bind_index_buffer(index_slice, gpu::IndexType::U32);
+            for draw in draws {
+                let texture = match self.textures.get(&draw.texture) {
+                    Some(t) => t,
+                    None => panic!(
+                        "ERROR: EguiRenderer {:?} asked to draw with texture {:?} that doesn't exist",
+                        self.name, draw.texture
+                    ),
+                };
+                pass.set_bundle_owned(texture.bundle.clone());
+                pass.set_scissor(draw.scissor);
+                pass.draw_indexed(draw.first_index, draw.index_count, 0, 1, draw.vertex_offset);
+            }
+            pass.finish();
+        }
+
+        // the pass holds onto the resources it uses so textures can be freed straight away
+        for id in &textures_delta.free {
+            self.textures.remove(id);
+        }
+
+        Ok(())
+    }
+}
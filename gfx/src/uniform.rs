@@ -3,6 +3,8 @@
 /// A uniform that is used to send T into shaders
 /// as well as being Pod and Zeroable T should be repr(C) for the binary data
 /// to be interpreted correctly in the shaders
+///
+/// Derive [`Std140`] on T to check at compile time that its layout matches the std140 layout of the shader
 #[derive(Debug, Clone)]
 pub struct Uniform<U: bytemuck::Pod> {
     /// the uniform buffer, default usage: COPY_SRC COPY_DST UNIFORM
@@ -103,6 +105,20 @@ impl<U: bytemuck::Pod> Uniform<U> {
         );
     }
 
+    /// Update the data on the gpu through a staging belt
+    /// --------------------------
+    ///
+    /// The update will only be complete when the command encoder is submitted
+    /// if the encoder is dropped before being submitted then no update will occur
+    pub fn update_gpu_staged<'a>(
+        &'a self,
+        encoder: &mut crate::CommandEncoder<'a>,
+        device: &gpu::Device,
+        belt: &mut crate::StagingBelt,
+    ) -> Result<(), gpu::Error> {
+        belt.write_buffer(encoder, device, self.buffer.slice_ref(..), bytemuck::bytes_of(&self.data))
+    }
+
     /// Update the data on the cpu
     pub fn update_cpu(
         &mut self,
@@ -129,3 +145,80 @@ impl<U: bytemuck::Pod> Uniform<U> {
         Ok(())
     }
 }
+
+/// A type with the same layout in rust as in a std140 uniform block
+///
+/// Implement with `#[derive(gfx::Std140)]` on a struct whose fields are all Std140, the derive
+/// fails to compile if a field isn't at its std140 offset so padding has to be added explicitly
+///
+/// Vectors and matrices are the glam types, there is no mat3 as std140 pads its columns to vec4
+/// `[T; N]` is an array with a stride of 16 bytes so `[f32; N]` fails to compile, use [`glam::Vec4`] for a vec4
+///
+/// # Safety
+///
+/// STD140_ALIGN and STD140_SIZE must be the alignment and size of the type in a std140 block
+/// and the type must have the same layout in rust up to STD140_SIZE
+pub unsafe trait Std140: bytemuck::Pod {
+    /// The base alignment of the type in a std140 block
+    const STD140_ALIGN: usize;
+    /// The size of the type in a std140 block, members following it start at or after this
+    const STD140_SIZE: usize;
+}
+
+/// Round offset up to the next multiple of align, used by the Std140 derive
+#[doc(hidden)]
+pub const fn std140_round_up(offset: usize, align: usize) -> usize {
+    (offset + align - 1) / align * align
+}
+
+/// The max of a and b, used by the Std140 derive
+#[doc(hidden)]
+pub const fn std140_max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+macro_rules! std140 {
+    ($(($ty:ty, $align:expr, $size:expr),)*) => {
+        $(
+            unsafe impl Std140 for $ty {
+                const STD140_ALIGN: usize = $align;
+                const STD140_SIZE: usize = $size;
+            }
+        )*
+    };
+}
+
+#[rustfmt::skip]
+std140!(
+    (f32, 4, 4),
+    (i32, 4, 4),
+    (u32, 4, 4),
+    (glam::Vec2, 8, 8),
+    (glam::Vec3, 16, 12),
+    (glam::Vec4, 16, 16),
+    (glam::IVec2, 8, 8),
+    (glam::IVec3, 16, 12),
+    (glam::IVec4, 16, 16),
+    (glam::UVec2, 8, 8),
+    (glam::UVec3, 16, 12),
+    (glam::UVec4, 16, 16),
+    (glam::Mat4, 16, 64),
+);
+
+/// Arrays have a stride of the element size rounded up to 16 bytes
+unsafe impl<T: Std140, const N: usize> Std140 for [T; N]
+where
+    [T; N]: bytemuck::Pod,
+{
+    const STD140_ALIGN: usize = std140_round_up(T::STD140_ALIGN, 16);
+    const STD140_SIZE: usize = {
+        if std::mem::size_of::<T>() != std140_round_up(T::STD140_SIZE, 16) {
+            panic!("ERROR: Array element size doesn't match std140 array stride, element size must be a multiple of 16 bytes");
+        }
+        N * std140_round_up(T::STD140_SIZE, 16)
+    };
+}
@@ -0,0 +1,29 @@
+//! Reflected pipelines are cached by key so keys that differ must map to different pipelines
+#![cfg(feature = "reflect")]
+
+use std::any::TypeId;
+use std::collections::HashMap;
+
+use gfx::reflect::graphics::GraphicsPipelineKey;
+
+fn key(spec_hash: Option<u64>) -> GraphicsPipelineKey {
+    GraphicsPipelineKey {
+        pass_hash: 0,
+        viewport: gpu::Viewport::default(),
+        vertex_ty: TypeId::of::<()>(),
+        spec_hash,
+    }
+}
+
+#[test]
+fn spec_constants() {
+    let mut pipelines = HashMap::new();
+    pipelines.insert(key(Some(1)), "one");
+    pipelines.insert(key(Some(2)), "two");
+    pipelines.insert(key(None), "none");
+
+    assert_eq!(pipelines.len(), 3);
+    assert_eq!(pipelines.get(&key(Some(1))), Some(&"one"));
+    assert_eq!(pipelines.get(&key(Some(2))), Some(&"two"));
+    assert_eq!(pipelines.get(&key(None)), Some(&"none"));
+}
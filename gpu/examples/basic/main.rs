@@ -69,26 +69,20 @@ fn main() {
 
                 let view = match swapchain.acquire(!0) {
                     Ok((view, _)) => view,
-                    Err(e) => {
-                        if e.can_continue() {
-                            resized = true;
-                            return;
-                        } else {
-                            panic!("{}", e)
-                        }
+                    Err(gpu::Error::OutOfDateSwapchain) => {
+                        resized = true;
+                        return;
                     }
+                    Err(e) => panic!("{}", e),
                 };
 
                 match swapchain.present(view) {
                     Ok(_) => (),
-                    Err(e) => {
-                        if e.can_continue() {
-                            resized = true;
-                            return;
-                        } else {
-                            panic!("{}", e);
-                        }
+                    Err(gpu::Error::OutOfDateSwapchain) => {
+                        resized = true;
+                        return;
                     }
+                    Err(e) => panic!("{}", e),
                 }
             }
             _ => (),
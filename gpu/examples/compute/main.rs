@@ -41,6 +41,7 @@ fn main() {
                 stage: gpu::ShaderStages::COMPUTE,
                 count: std::num::NonZeroU32::new(1).unwrap(),
             }],
+            immutable_samplers: &[],
         })
         .unwrap();
 
@@ -307,6 +307,7 @@ fn main() {
                 stage: gpu::ShaderStages::VERTEX,
                 count: std::num::NonZeroU32::new(1).unwrap(),
             }],
+            immutable_samplers: &[],
         })
         .unwrap();
 
@@ -401,6 +402,7 @@ fn main() {
             depth_stencil,
             viewports: &[viewport],
             cache: None,
+            base: None,
         })
         .unwrap();
 
@@ -471,6 +473,7 @@ fn main() {
                             depth_stencil,
                             viewports: &[viewport],
                             cache: None,
+                            base: None,
                         })
                         .unwrap();
 
@@ -485,14 +488,11 @@ fn main() {
 
                 let view = match swapchain.acquire(!0) {
                     Ok((view, _)) => view,
-                    Err(e) => {
-                        if e.can_continue() {
-                            resized = true;
-                            return;
-                        } else {
-                            panic!("{}", e)
-                        }
+                    Err(gpu::Error::OutOfDateSwapchain) => {
+                        resized = true;
+                        return;
                     }
+                    Err(e) => panic!("{}", e),
                 };
 
                 command.begin(true).unwrap();
@@ -539,14 +539,11 @@ fn main() {
 
                 match swapchain.present(view) {
                     Ok(_) => (),
-                    Err(e) => {
-                        if e.can_continue() {
-                            resized = true;
-                            return;
-                        } else {
-                            panic!("{}", e);
-                        }
+                    Err(gpu::Error::OutOfDateSwapchain) => {
+                        resized = true;
+                        return;
                     }
+                    Err(e) => panic!("{}", e),
                 }
             }
             _ => (),
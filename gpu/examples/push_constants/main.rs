@@ -152,6 +152,7 @@ fn main() {
             depth_stencil: None,
             viewports: &[viewport],
             cache: None,
+            base: None,
         })
         .unwrap();
 
@@ -206,6 +207,7 @@ fn main() {
                             depth_stencil: None,
                             viewports: &[viewport],
                             cache: None,
+                            base: None,
                         })
                         .unwrap();
                 }
@@ -222,14 +224,11 @@ fn main() {
 
                 let view = match swapchain.acquire(!0) {
                     Ok((view, _)) => view,
-                    Err(e) => {
-                        if e.can_continue() {
-                            resized = true;
-                            return;
-                        } else {
-                            panic!("{}", e)
-                        }
+                    Err(gpu::Error::OutOfDateSwapchain) => {
+                        resized = true;
+                        return;
                     }
+                    Err(e) => panic!("{}", e),
                 };
 
                 command_buffer.begin(true).unwrap();
@@ -269,14 +268,11 @@ fn main() {
 
                 match swapchain.present(view) {
                     Ok(_) => (),
-                    Err(e) => {
-                        if e.can_continue() {
-                            resized = true;
-                            return;
-                        } else {
-                            panic!("{}", e);
-                        }
+                    Err(gpu::Error::OutOfDateSwapchain) => {
+                        resized = true;
+                        return;
                     }
+                    Err(e) => panic!("{}", e),
                 }
             }
             _ => (),
@@ -128,6 +128,7 @@ fn main() {
                 stage: gpu::ShaderStages::FRAGMENT,
                 count: std::num::NonZeroU32::new(1).unwrap(),
             }],
+            immutable_samplers: &[],
         })
         .unwrap();
 
@@ -188,6 +189,7 @@ fn main() {
             depth_stencil: None,
             viewports: &[viewport],
             cache: None,
+            base: None,
         })
         .unwrap();
 
@@ -342,20 +344,18 @@ fn main() {
                             depth_stencil: None,
                             viewports: &[viewport],
                             cache: None,
+                            base: None,
                         })
                         .unwrap();
                 }
 
                 let view = match swapchain.acquire(!0) {
                     Ok((view, _)) => view,
-                    Err(e) => {
-                        if e.can_continue() {
-                            resized = true;
-                            return;
-                        } else {
-                            panic!("{}", e)
-                        }
+                    Err(gpu::Error::OutOfDateSwapchain) => {
+                        resized = true;
+                        return;
                     }
+                    Err(e) => panic!("{}", e),
                 };
 
                 command_buffer.begin(true).unwrap();
@@ -401,14 +401,11 @@ fn main() {
 
                 match swapchain.present(view) {
                     Ok(_) => (),
-                    Err(e) => {
-                        if e.can_continue() {
-                            resized = true;
-                            return;
-                        } else {
-                            panic!("{}", e);
-                        }
+                    Err(gpu::Error::OutOfDateSwapchain) => {
+                        resized = true;
+                        return;
                     }
+                    Err(e) => panic!("{}", e),
                 }
             }
             _ => (),
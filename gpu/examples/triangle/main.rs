@@ -155,6 +155,7 @@ fn main() {
             depth_stencil: None,
             viewports: &[viewport],
             cache: None,
+            base: None,
         })
         .unwrap();
     let mut command_buffer = device.create_command_buffer(None).unwrap();
@@ -199,20 +200,18 @@ fn main() {
                             depth_stencil: None,
                             viewports: &[viewport],
                             cache: None,
+                            base: None,
                         })
                         .unwrap();
                 }
 
                 let view = match swapchain.acquire(!0) {
                     Ok((view, _)) => view,
-                    Err(e) => {
-                        if e.can_continue() {
-                            resized = true;
-                            return;
-                        } else {
-                            panic!("{}", e)
-                        }
+                    Err(gpu::Error::OutOfDateSwapchain) => {
+                        resized = true;
+                        return;
                     }
+                    Err(e) => panic!("{}", e),
                 };
 
                 command_buffer.begin(true).unwrap();
@@ -243,14 +242,11 @@ fn main() {
 
                 match swapchain.present(view) {
                     Ok(_) => (),
-                    Err(e) => {
-                        if e.can_continue() {
-                            resized = true;
-                            return;
-                        } else {
-                            panic!("{}", e);
-                        }
+                    Err(gpu::Error::OutOfDateSwapchain) => {
+                        resized = true;
+                        return;
                     }
+                    Err(e) => panic!("{}", e),
                 }
             }
             _ => (),
@@ -105,6 +105,7 @@ fn main() {
                 stage: gpu::ShaderStages::FRAGMENT,
                 count: std::num::NonZeroU32::new(2).unwrap(),
             }],
+            immutable_samplers: &[],
         })
         .unwrap();
 
@@ -202,6 +203,7 @@ fn main() {
             depth_stencil: None,
             viewports: &[viewport],
             cache: None,
+            base: None,
         })
         .unwrap();
     let mut command_buffer = device.create_command_buffer(None).unwrap();
@@ -270,20 +272,18 @@ fn main() {
                             depth_stencil: None,
                             viewports: &[viewport],
                             cache: None,
+                            base: None,
                         })
                         .unwrap();
                 }
 
                 let view = match swapchain.acquire(!0) {
                     Ok((view, _)) => view,
-                    Err(e) => {
-                        if e.can_continue() {
-                            resized = true;
-                            return;
-                        } else {
-                            panic!("{}", e)
-                        }
+                    Err(gpu::Error::OutOfDateSwapchain) => {
+                        resized = true;
+                        return;
                     }
+                    Err(e) => panic!("{}", e),
                 };
 
                 command_buffer.begin(true).unwrap();
@@ -332,14 +332,11 @@ fn main() {
 
                 match swapchain.present(view) {
                     Ok(_) => (),
-                    Err(e) => {
-                        if e.can_continue() {
-                            resized = true;
-                            return;
-                        } else {
-                            panic!("{}", e);
-                        }
+                    Err(gpu::Error::OutOfDateSwapchain) => {
+                        resized = true;
+                        return;
                     }
+                    Err(e) => panic!("{}", e),
                 }
             }
             _ => (),
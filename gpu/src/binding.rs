@@ -21,17 +21,26 @@ pub struct DescriptorLayoutDesc<'a> {
     pub name: Option<String>,
     /// All the entries in the DescriptorLayout
     pub entries: &'a [crate::DescriptorLayoutEntry],
+    /// Samplers baked into the layout as (binding, sampler), the binding must be a
+    /// [`crate::DescriptorLayoutEntryType::Sampler`] or [`crate::DescriptorLayoutEntryType::CombinedTextureSampler`]
+    /// entry and every element of it uses the sampler, samplers supplied when writing the set are ignored.
+    /// Required for samplers created with [`crate::Sampler::new_ycbcr`]
+    pub immutable_samplers: &'a [(u32, &'a crate::Sampler)],
 }
 
 /// A DescriptorLayout
 ///
 /// Describes the layout of a DescriptorSet
+///
+/// Layouts with identical entries and no immutable samplers are deduplicated by the device so
+/// DescriptorSets created from one can be bound with any pipeline using an identical layout
 /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/VkDescriptorSetLayout.html>
 pub struct DescriptorLayout {
     pub(crate) shader_stages: crate::ShaderStages,
     pub(crate) entries: Arc<[crate::DescriptorLayoutEntry]>,
     pub(crate) device: Arc<crate::RawDevice>,
     pub(crate) raw: Md<Arc<vk::DescriptorSetLayout>>,
+    pub(crate) immutable_samplers: Arc<[crate::Sampler]>,
     pub(crate) name: Option<String>,
 }
 
@@ -57,6 +66,7 @@ impl Clone for DescriptorLayout {
             raw: Md::new(Arc::clone(&self.raw)),
             name: self.name.clone(),
             entries: Arc::clone(&self.entries),
+            immutable_samplers: Arc::clone(&self.immutable_samplers),
         }
     }
 }
@@ -83,6 +93,50 @@ impl DescriptorLayout {
         #[cfg(feature = "logging")]
         log::trace!("GPU: Create DescriptorLayout, name {:?}", desc.name);
 
+        let mut shader_stages = crate::ShaderStages::empty();
+        for e in desc.entries.as_ref() {
+            shader_stages |= e.stage;
+        }
+
+        // identically defined layouts share a handle, layouts with immutable samplers are never shared
+        let entries: Arc<[crate::DescriptorLayoutEntry]> = desc.entries.into();
+        let mut cache = if desc.immutable_samplers.is_empty() {
+            let cache = device.raw.descriptor_layouts.lock();
+            if let Some(raw) = cache.get(&entries).and_then(|w| w.upgrade()) {
+                return Ok(Self {
+                    device: Arc::clone(&device.raw),
+                    raw: Md::new(raw),
+                    entries,
+                    immutable_samplers: Arc::new([]),
+                    shader_stages,
+                    name: desc.name.as_ref().map(|s| s.to_string()),
+                });
+            }
+            Some(cache)
+        } else {
+            None
+        };
+
+        // one sampler for each element of each binding with an immutable sampler
+        let immutable_samplers = desc
+            .immutable_samplers
+            .iter()
+            .map(|&(binding, sampler)| {
+                let entry = desc.entries.get(binding as usize).unwrap_or_else(|| {
+                    panic!("ERROR: Attempt to create DescriptorLayout with immutable sampler at binding {} out of range", binding)
+                });
+                match entry.ty {
+                    crate::DescriptorLayoutEntryType::Sampler
+                    | crate::DescriptorLayoutEntryType::CombinedTextureSampler => (),
+                    ty => panic!(
+                        "ERROR: Attempt to create DescriptorLayout with immutable sampler at binding {} of type {:?}",
+                        binding, ty
+                    ),
+                }
+                (binding, vec![**sampler.raw; entry.count.get() as usize])
+            })
+            .collect::<Vec<_>>();
+
         let bindings = desc
             .entries
             .iter()
@@ -92,7 +146,11 @@ impl DescriptorLayout {
                 descriptor_type: e.ty.into(),
                 stage_flags: e.stage.into(),
                 descriptor_count: e.count.get(),
-                p_immutable_samplers: ptr::null(),
+                p_immutable_samplers: immutable_samplers
+                    .iter()
+                    .find(|(b, _)| *b == binding as u32)
+                    .map(|(_, s)| s.as_ptr())
+                    .unwrap_or(ptr::null()),
             })
             .collect::<Vec<vk::DescriptorSetLayoutBinding>>();
 
@@ -109,15 +167,18 @@ impl DescriptorLayout {
             Err(e) => return Err(e.into()),
         };
 
-        let mut shader_stages = crate::ShaderStages::empty();
-        for e in desc.entries.as_ref() {
-            shader_stages |= e.stage;
+        let raw = Arc::new(layout);
+        if let Some(cache) = &mut cache {
+            cache.retain(|_, w| w.strong_count() != 0);
+            cache.insert(Arc::clone(&entries), Arc::downgrade(&raw));
         }
+        drop(cache);
 
         let s = Self {
             device: Arc::clone(&device.raw),
-            raw: Md::new(Arc::new(layout)),
-            entries: desc.entries.into(),
+            raw: Md::new(raw),
+            entries,
+            immutable_samplers: desc.immutable_samplers.iter().map(|(_, s)| (*s).clone()).collect(),
             shader_stages,
             name: desc.name.as_ref().map(|s| s.to_string()),
         };
@@ -128,10 +189,25 @@ impl DescriptorLayout {
         Ok(s)
     }
 
+    /// Get the entries the descriptor layout was created with
+    pub fn entries(&self) -> &[crate::DescriptorLayoutEntry] {
+        &self.entries
+    }
+
     /// Get the id of the descriptor layout
+    ///
+    /// Layouts created with identical entries and no immutable samplers share a handle and so an id
     pub fn id(&self) -> u64 {
         unsafe { std::mem::transmute(**self.raw) }
     }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_descriptor_layout_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
 }
 
 impl Drop for DescriptorLayout {
@@ -148,6 +224,7 @@ impl Drop for DescriptorLayout {
 union Descriptor {
     buffer: vk::DescriptorBufferInfo,
     image: vk::DescriptorImageInfo,
+    texel: vk::BufferView,
 }
 
 /// Describes a DescriptorSet
@@ -170,11 +247,14 @@ pub struct DescriptorSet {
     pub(crate) pool: Md<Arc<vk::DescriptorPool>>,
     pub(crate) set: Md<Arc<vk::DescriptorSet>>,
     pub(crate) shader_stages: crate::ShaderStages,
+    /// the number of dynamic offsets that have to be supplied when binding the set
+    pub(crate) dynamic_count: u32,
     pub(crate) device: Arc<crate::RawDevice>,
     // keep resources alive while bind group is alive
     pub(crate) textures: Arc<[(crate::TextureView, crate::TextureLayout)]>,
     pub(crate) buffers: Arc<[crate::BufferSlice<'static>]>,
     pub(crate) samplers: Arc<[crate::Sampler]>,
+    pub(crate) buffer_views: Arc<[crate::BufferView]>,
     pub(crate) name: Option<String>,
 }
 
@@ -199,10 +279,12 @@ impl Clone for DescriptorSet {
             pool: Md::new(Arc::clone(&self.pool)),
             set: Md::new(Arc::clone(&self.set)),
             shader_stages: self.shader_stages,
+            dynamic_count: self.dynamic_count,
             device: Arc::clone(&self.device),
             textures: Arc::clone(&self.textures),
             buffers: Arc::clone(&self.buffers),
             samplers: Arc::clone(&self.samplers),
+            buffer_views: Arc::clone(&self.buffer_views),
             name: self.name.clone(),
         }
     }
@@ -216,6 +298,11 @@ impl DescriptorSet {
     pub unsafe fn raw_set(&self) -> vk::DescriptorSet {
         **self.set
     }
+
+    /// The number of dynamic offsets that have to be supplied when binding the set
+    pub fn dynamic_count(&self) -> u32 {
+        self.dynamic_count
+    }
 }
 
 impl std::fmt::Debug for DescriptorSet {
@@ -234,7 +321,7 @@ impl DescriptorSet {
         #[cfg(feature = "logging")]
         log::trace!("GPU: Create DescriptorSet, name {:?}", desc.name);
 
-        let (textures, buffers, samplers) = Self::make_cache(desc);
+        let (textures, buffers, samplers, buffer_views) = Self::make_cache(desc);
 
         let (pool, set) = Self::raw(device, desc)?;
         let descriptors = match Self::descriptors(desc) {
@@ -251,12 +338,20 @@ impl DescriptorSet {
             pool: Md::new(Arc::new(pool)),
             set: Md::new(Arc::new(set)),
             shader_stages: desc.layout.shader_stages,
+            dynamic_count: desc
+                .layout
+                .entries
+                .iter()
+                .filter(|e| e.ty.is_dynamic())
+                .map(|e| e.count.get())
+                .sum(),
             layout: Md::new(Arc::clone(&desc.layout.raw)),
             device: Arc::clone(&device.raw),
 
             textures: textures.into_iter().collect::<Arc<[_]>>(),
             buffers: buffers.into_iter().collect::<Arc<[_]>>(),
             samplers: samplers.into_iter().collect::<Arc<[_]>>(),
+            buffer_views: buffer_views.into_iter().collect::<Arc<[_]>>(),
 
             name: desc.name.as_ref().map(|s| s.to_string()),
         };
@@ -273,10 +368,12 @@ impl DescriptorSet {
         HashMap<crate::TextureView, crate::TextureLayout>,
         HashSet<crate::BufferSlice<'static>>,
         HashSet<crate::Sampler>,
+        HashSet<crate::BufferView>,
     ) {
         let mut textures = HashMap::new();
         let mut buffers = HashSet::new();
         let mut samplers = HashSet::new();
+        let mut buffer_views = HashSet::new();
         for entry in desc.entries.as_ref() {
             match entry {
                 crate::DescriptorSetEntry::Buffer(slice) => {
@@ -359,9 +456,19 @@ impl DescriptorSet {
                         samplers.insert(sampler.clone().into_owned());
                     }
                 }
+                crate::DescriptorSetEntry::TexelBuffer(view) => {
+                    buffers.insert(view.buffer.as_owned());
+                    buffer_views.insert(view.clone().into_owned());
+                }
+                crate::DescriptorSetEntry::TexelBufferArray(array) => {
+                    for view in array.as_ref() {
+                        buffers.insert(view.buffer.as_owned());
+                        buffer_views.insert(view.clone().into_owned());
+                    }
+                }
             }
         }
-        (textures, buffers, samplers)
+        (textures, buffers, samplers, buffer_views)
     }
 
     fn write_descriptors(
@@ -370,14 +477,29 @@ impl DescriptorSet {
         desc: &DescriptorSetDesc<'_, '_>,
         set: vk::DescriptorSet,
     ) {
+        // texel buffer views are read as a contiguous array so can't point into the descriptor list
+        let texel_views = descriptors
+            .iter()
+            .zip(&*desc.layout.entries)
+            .map(|(list, entry)| match entry.ty {
+                crate::DescriptorLayoutEntryType::UniformTexelBuffer
+                | crate::DescriptorLayoutEntryType::StorageTexelBuffer { .. } => {
+                    list.iter().map(|d| unsafe { d.texel }).collect::<Vec<_>>()
+                }
+                _ => Vec::new(),
+            })
+            .collect::<Vec<_>>();
         let mut write = Vec::new();
         let mut i = 0;
         for list in &descriptors {
             let buffer = match desc.layout.entries[i].ty {
                 crate::DescriptorLayoutEntryType::UniformBuffer => true,
                 crate::DescriptorLayoutEntryType::StorageBuffer { .. } => true,
+                crate::DescriptorLayoutEntryType::UniformBufferDynamic => true,
+                crate::DescriptorLayoutEntryType::StorageBufferDynamic { .. } => true,
                 _ => false,
             };
+            let texel = !texel_views[i].is_empty();
 
             let w = vk::WriteDescriptorSet {
                 s_type: vk::StructureType::WRITE_DESCRIPTOR_SET,
@@ -392,12 +514,16 @@ impl DescriptorSet {
                 } else {
                     ptr::null()
                 },
-                p_image_info: if !buffer {
+                p_image_info: if !buffer && !texel {
                     unsafe { &list[0].image }
                 } else {
                     ptr::null()
                 },
-                p_texel_buffer_view: ptr::null(),
+                p_texel_buffer_view: if texel {
+                    texel_views[i].as_ptr()
+                } else {
+                    ptr::null()
+                },
             };
             write.push(w);
             i += 1;
@@ -414,7 +540,8 @@ impl DescriptorSet {
     ) -> Result<Vec<Descriptor>, Error> {
         let count = l.count;
         match l.ty {
-            crate::DescriptorLayoutEntryType::UniformBuffer => {
+            crate::DescriptorLayoutEntryType::UniformBuffer
+            | crate::DescriptorLayoutEntryType::UniformBufferDynamic => {
                 if count.get() == 1 {
                     if let crate::DescriptorSetEntry::Buffer(b) = e {
                         Ok(vec![Descriptor {
@@ -451,7 +578,8 @@ impl DescriptorSet {
                     }
                 }
             }
-            crate::DescriptorLayoutEntryType::StorageBuffer { .. } => {
+            crate::DescriptorLayoutEntryType::StorageBuffer { .. }
+            | crate::DescriptorLayoutEntryType::StorageBufferDynamic { .. } => {
                 if count.get() == 1 {
                     if let crate::DescriptorSetEntry::Buffer(b) = e {
                         Ok(vec![Descriptor {
@@ -636,6 +764,32 @@ impl DescriptorSet {
                     }
                 }
             }
+            crate::DescriptorLayoutEntryType::UniformTexelBuffer
+            | crate::DescriptorLayoutEntryType::StorageTexelBuffer { .. } => {
+                if count.get() == 1 {
+                    if let crate::DescriptorSetEntry::TexelBuffer(v) = e {
+                        Ok(vec![Descriptor { texel: **v.raw }])
+                    } else {
+                        panic!("ERROR: Attempt to write to DescriptorLayoutEntryType::{:?} {{ count: {}, .. }} with type {:?} (not TexelBuffer)", l.ty, count.get(), e);
+                    }
+                } else {
+                    if let crate::DescriptorSetEntry::TexelBufferArray(a) = e {
+                        let mut i = 0;
+                        Ok(a.iter()
+                            .map_while(|v| {
+                                if i < count.get() {
+                                    i += 1;
+                                    Some(Descriptor { texel: **v.raw })
+                                } else {
+                                    None
+                                }
+                            })
+                            .collect::<_>())
+                    } else {
+                        panic!("ERROR: Attempt to write to DescriptorLayoutEntryType::{:?} {{ count: {}, .. }} with type {:?} (not TexelBufferArray)", l.ty, count.get(), e);
+                    }
+                }
+            }
         }
     }
 
@@ -706,10 +860,28 @@ impl DescriptorSet {
         &self.samplers
     }
 
+    /// Get a reference to all the texel buffer views used in self
+    pub fn buffer_views<'a>(&'a self) -> &'a [crate::BufferView] {
+        &self.buffer_views
+    }
+
     /// Get the id of the descriptor set
     pub fn id(&self) -> u64 {
         unsafe { std::mem::transmute(**self.pool) }
     }
+
+    /// Get the id of the [`DescriptorLayout`] the set was created from
+    pub fn layout_id(&self) -> u64 {
+        unsafe { std::mem::transmute(**self.layout) }
+    }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_descriptor_set_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
 }
 
 impl Drop for DescriptorSet {
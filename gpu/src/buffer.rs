@@ -5,7 +5,10 @@ use std::{
     mem::ManuallyDrop as Md,
     ops::{Bound, RangeBounds},
     ptr,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
 use ash::vk;
@@ -49,6 +52,12 @@ pub struct Buffer {
     pub(crate) size: u64,
     pub(crate) usage: crate::BufferUsage,
     pub(crate) mem_ty: crate::MemoryType,
+    /// if the memory chosen for the buffer is host coherent
+    pub(crate) coherent: bool,
+    /// set while a [`MappedBuffer`] exists for the buffer
+    pub(crate) mapped: Arc<AtomicBool>,
+    /// the handle type if the memory can be shared with other apis
+    pub(crate) external: Option<crate::ExternalHandleType>,
     pub(crate) device: Arc<crate::RawDevice>,
     pub(crate) name: Option<String>,
 }
@@ -75,6 +84,9 @@ impl Clone for Buffer {
             size: self.size,
             usage: self.usage,
             mem_ty: self.mem_ty,
+            coherent: self.coherent,
+            mapped: Arc::clone(&self.mapped),
+            external: self.external,
             device: Arc::clone(&self.device),
             name: self.name.clone(),
         }
@@ -100,18 +112,70 @@ impl Buffer {
 impl Buffer {
     /// Create a new Buffer
     pub fn new(device: &crate::Device, desc: &BufferDesc) -> Result<Self, Error> {
+        Self::raw_new(device, desc, None)
+    }
+
+    /// Create a new Buffer whose memory can be exported with [`Buffer::export`]
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkExportMemoryAllocateInfo.html>
+    pub fn new_exportable(
+        device: &crate::Device,
+        desc: &BufferDesc,
+        handle_type: crate::ExternalHandleType,
+    ) -> Result<Self, Error> {
+        Self::raw_new(device, desc, Some(crate::ExternalMemory::Export(handle_type)))
+    }
+
+    /// Create a new Buffer using memory exported by another api
+    ///
+    /// Safety: the handle must refer to memory at least as large as the buffer requires
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkImportMemoryFdInfoKHR.html>
+    pub unsafe fn import(
+        device: &crate::Device,
+        desc: &BufferDesc,
+        handle: crate::ExternalHandle,
+    ) -> Result<Self, Error> {
+        Self::raw_new(device, desc, Some(crate::ExternalMemory::Import(handle)))
+    }
+
+    fn raw_new(
+        device: &crate::Device,
+        desc: &BufferDesc,
+        external: Option<crate::ExternalMemory>,
+    ) -> Result<Self, Error> {
         #[cfg(feature = "logging")]
         log::trace!("GPU: Create Buffer, name {:?}", desc.name);
 
+        if external.is_some()
+            && !device
+                .raw
+                .features
+                .contains(crate::DeviceFeatures::EXTERNAL_MEMORY)
+        {
+            panic!("ERROR: Attempt to create external Buffer without DeviceFeatures::EXTERNAL_MEMORY enabled");
+        }
+
+        let external_info = vk::ExternalMemoryBufferCreateInfo {
+            s_type: vk::StructureType::EXTERNAL_MEMORY_BUFFER_CREATE_INFO,
+            p_next: ptr::null(),
+            handle_types: external
+                .map(|e| e.handle_type().into())
+                .unwrap_or(vk::ExternalMemoryHandleTypeFlags::empty()),
+        };
+
+        let (sharing_mode, queue_families) = device.sharing_mode();
         let create_info = vk::BufferCreateInfo {
             s_type: vk::StructureType::BUFFER_CREATE_INFO,
-            p_next: ptr::null(),
+            p_next: if external.is_some() {
+                &external_info as *const _ as *const _
+            } else {
+                ptr::null()
+            },
             flags: vk::BufferCreateFlags::empty(),
             size: desc.size,
             usage: desc.usage.into(),
-            sharing_mode: vk::SharingMode::EXCLUSIVE,
-            queue_family_index_count: 0,
-            p_queue_family_indices: ptr::null(),
+            sharing_mode,
+            queue_family_index_count: queue_families.len() as u32,
+            p_queue_family_indices: queue_families.as_ptr(),
         };
 
         let raw_result = unsafe { device.raw.create_buffer(&create_info, None) };
@@ -125,9 +189,14 @@ impl Buffer {
 
         let mem_type = find_memory_type(mem_req, desc.memory, device.info.mem_properties)?;
 
+        let external_alloc = external.map(crate::ExternalAllocateInfo::new);
+
         let allocate_info = vk::MemoryAllocateInfo {
             s_type: vk::StructureType::MEMORY_ALLOCATE_INFO,
-            p_next: ptr::null(),
+            p_next: match (&external_alloc, external) {
+                (Some(info), Some(external)) => info.p_next(external),
+                _ => ptr::null(),
+            },
             allocation_size: mem_req.size,
             memory_type_index: mem_type,
         };
@@ -152,6 +221,11 @@ impl Buffer {
             size: desc.size,
             usage: desc.usage,
             mem_ty: desc.memory,
+            coherent: device.info.mem_properties.memory_types[mem_type as usize]
+                .property_flags
+                .contains(vk::MemoryPropertyFlags::HOST_COHERENT),
+            mapped: Arc::new(AtomicBool::new(false)),
+            external: external.map(|e| e.handle_type()),
             device: Arc::clone(&device.raw),
             name: desc.name.clone().map(|s| s.to_string()),
         };
@@ -229,6 +303,75 @@ impl Buffer {
         }
     }
 
+    /// Map the whole buffer into cpu memory until the returned [`MappedBuffer`] is dropped
+    ///
+    /// This avoids mapping and unmapping every write for buffers that are updated every frame
+    /// the buffer must have been created with a memory type visible to the cpu
+    ///
+    /// While the buffer is mapped [`BufferSlice::write`] and [`BufferSlice::read`] can't be used
+    pub fn map_persistent(&self) -> Result<MappedBuffer, Error> {
+        if self.mem_ty == crate::MemoryType::Device {
+            panic!("ERROR: Can't map buffer with memory type not visible to cpu")
+        }
+
+        if self.mapped.swap(true, Ordering::AcqRel) {
+            panic!("ERROR: Can't map buffer that is already mapped")
+        }
+
+        let p_result = unsafe {
+            self.device.map_memory(
+                **self.memory,
+                0,
+                vk::WHOLE_SIZE,
+                vk::MemoryMapFlags::empty(),
+            )
+        };
+
+        let p = match p_result {
+            Ok(p) => p,
+            Err(e) => {
+                self.mapped.store(false, Ordering::Release);
+                return Err(e.into());
+            }
+        };
+
+        self.device.check_errors()?;
+
+        Ok(MappedBuffer {
+            buffer: self.clone(),
+            ptr: p as *mut u8,
+        })
+    }
+
+    /// Export a handle to the memory of the buffer so that it can be used by another api
+    ///
+    /// Panics if the buffer wasn't created with [`Buffer::new_exportable`] or [`Buffer::import`].
+    /// The caller is responsible for closing the returned handle
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkGetMemoryFdKHR.html>
+    pub fn export(&self) -> Result<crate::ExternalHandle, Error> {
+        let handle_type = self
+            .external
+            .expect("ERROR: Attempt to export Buffer that wasn't created as exportable");
+        crate::export_memory(&self.device, **self.memory, handle_type)
+    }
+
+    /// Returns the handle type that the buffer memory can be shared with if any
+    pub fn external_handle_type(&self) -> Option<crate::ExternalHandleType> {
+        self.external
+    }
+
+    /// Returns true if the buffer is currently mapped by a [`MappedBuffer`]
+    pub fn is_mapped(&self) -> bool {
+        self.mapped.load(Ordering::Acquire)
+    }
+
+    /// Returns true if the memory of the buffer is host coherent
+    ///
+    /// If not then writes through [`MappedBuffer`] must be flushed and reads invalidated
+    pub fn coherent(&self) -> bool {
+        self.coherent
+    }
+
     /// Get the usage of the buffer
     pub fn usage(&self) -> crate::BufferUsage {
         self.usage
@@ -248,6 +391,19 @@ impl Buffer {
     pub fn id(&self) -> u64 {
         unsafe { std::mem::transmute(**self.raw) }
     }
+
+    /// Get the name of self
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|n| &**n)
+    }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_buffer_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
 }
 
 impl Drop for Buffer {
@@ -342,6 +498,10 @@ impl<'a> BufferSlice<'a> {
             panic!("ERROR: Can't write to buffer with memory type not visible to cpu")
         }
 
+        if self.buffer.is_mapped() {
+            panic!("ERROR: Can't write to buffer slice while the buffer is persistently mapped, use the MappedBuffer instead")
+        }
+
         if (data.len() as u64) < self.size {
             panic!("ERROR: Can't write to buffer with size less that slice size");
         }
@@ -377,6 +537,10 @@ impl<'a> BufferSlice<'a> {
             panic!("ERROR: Can't write to buffer with memory type not visible to cpu")
         }
 
+        if self.buffer.is_mapped() {
+            panic!("ERROR: Can't read from buffer slice while the buffer is persistently mapped, use the MappedBuffer instead")
+        }
+
         if (data.len() as u64) < self.size {
             panic!("ERROR: Can't read from buffer with size less that slice size");
         }
@@ -406,6 +570,140 @@ impl<'a> BufferSlice<'a> {
     }
 }
 
+/// A MappedBuffer
+///
+/// Keeps the memory of a buffer mapped for it's lifetime so that data can be written
+/// and read repeatedly without mapping and unmapping each time
+///
+/// If the buffer isn't coherent then writes must be made visible to the gpu with [`MappedBuffer::flush_range`]
+/// and writes from the gpu made visible to the cpu with [`MappedBuffer::invalidate_range`]
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkMapMemory.html>
+pub struct MappedBuffer {
+    pub(crate) buffer: Buffer,
+    pub(crate) ptr: *mut u8,
+}
+
+impl std::fmt::Debug for MappedBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MappedBuffer {:?} ptr: {:?}", self.buffer, self.ptr)
+    }
+}
+
+impl MappedBuffer {
+    pub unsafe fn raw_ptr(&self) -> *mut u8 {
+        self.ptr
+    }
+}
+
+impl MappedBuffer {
+    /// Get the buffer that is mapped
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Get the mapped memory as a slice
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.buffer.size as usize) }
+    }
+
+    /// Get the mapped memory as a mutable slice
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.buffer.size as usize) }
+    }
+
+    /// Copy data into the mapped memory starting at offset
+    ///
+    /// If the buffer isn't coherent the range must be flushed before the gpu uses it
+    pub fn write(&mut self, offset: u64, data: &[u8]) {
+        if offset + data.len() as u64 > self.buffer.size {
+            panic!("ERROR: Mapped buffer write out of bounds")
+        }
+        self.as_mut_slice()[offset as usize..offset as usize + data.len()].copy_from_slice(data);
+    }
+
+    /// Copy data from the mapped memory starting at offset
+    ///
+    /// If the buffer isn't coherent the range must be invalidated after the gpu writes to it
+    pub fn read(&self, offset: u64, data: &mut [u8]) {
+        if offset + data.len() as u64 > self.buffer.size {
+            panic!("ERROR: Mapped buffer read out of bounds")
+        }
+        data.copy_from_slice(&self.as_slice()[offset as usize..offset as usize + data.len()]);
+    }
+
+    /// Expand the range to the non coherent atom size and create the vulkan struct
+    fn mapped_range(&self, offset: u64, size: u64) -> vk::MappedMemoryRange {
+        let atom = self.buffer.device.limits.non_coherent_atom_size.max(1);
+        let start = (offset / atom) * atom;
+        let end = offset + size;
+        let size = if end >= self.buffer.size {
+            vk::WHOLE_SIZE
+        } else {
+            ((end - start + atom - 1) / atom) * atom
+        };
+        vk::MappedMemoryRange {
+            s_type: vk::StructureType::MAPPED_MEMORY_RANGE,
+            p_next: ptr::null(),
+            memory: **self.buffer.memory,
+            offset: start,
+            size,
+        }
+    }
+
+    /// Make writes by the cpu to the range visible to the gpu
+    ///
+    /// Does nothing if the buffer memory is coherent
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkFlushMappedMemoryRanges.html>
+    pub fn flush_range(&self, offset: u64, size: u64) -> Result<(), Error> {
+        if self.buffer.coherent {
+            return Ok(());
+        }
+        let range = self.mapped_range(offset, size);
+        let result = unsafe { self.buffer.device.flush_mapped_memory_ranges(&[range]) };
+        match result {
+            Ok(_) => (),
+            Err(e) => return Err(e.into()),
+        }
+        self.buffer.device.check_errors()
+    }
+
+    /// Make writes by the gpu to the range visible to the cpu
+    ///
+    /// Does nothing if the buffer memory is coherent
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkInvalidateMappedMemoryRanges.html>
+    pub fn invalidate_range(&self, offset: u64, size: u64) -> Result<(), Error> {
+        if self.buffer.coherent {
+            return Ok(());
+        }
+        let range = self.mapped_range(offset, size);
+        let result = unsafe { self.buffer.device.invalidate_mapped_memory_ranges(&[range]) };
+        match result {
+            Ok(_) => (),
+            Err(e) => return Err(e.into()),
+        }
+        self.buffer.device.check_errors()
+    }
+
+    /// Flush the whole buffer, see [`MappedBuffer::flush_range`]
+    pub fn flush(&self) -> Result<(), Error> {
+        self.flush_range(0, self.buffer.size)
+    }
+
+    /// Invalidate the whole buffer, see [`MappedBuffer::invalidate_range`]
+    pub fn invalidate(&self) -> Result<(), Error> {
+        self.invalidate_range(0, self.buffer.size)
+    }
+}
+
+impl Drop for MappedBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            self.buffer.device.unmap_memory(**self.buffer.memory);
+        }
+        self.buffer.mapped.store(false, Ordering::Release);
+    }
+}
+
 /// Buffer Access
 /// Describes how a buffer is accessed between cpu commands
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -417,3 +715,175 @@ pub struct BufferAccessInfo<'a> {
     /// How the buffer will be accessed after
     pub dst_access: crate::AccessFlags,
 }
+
+/// Describes a BufferView
+#[derive(Debug)]
+pub struct BufferViewDesc<'a> {
+    /// The name of the BufferView
+    pub name: Option<String>,
+    /// The part of the buffer to view, the offset must be a multiple of minTexelBufferOffsetAlignment
+    pub buffer: BufferSlice<'a>,
+    /// The format of the texels in the buffer
+    pub format: crate::Format,
+}
+
+/// A BufferView
+///
+/// Allows a buffer created with [`crate::BufferUsage::UNIFORM_TEXEL`] or [`crate::BufferUsage::STORAGE_TEXEL`]
+/// to be accessed in shaders as an array of formatted texels
+/// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/VkBufferView.html>
+pub struct BufferView {
+    pub(crate) raw: Md<Arc<vk::BufferView>>,
+    pub(crate) buffer: BufferSlice<'static>,
+    pub(crate) format: crate::Format,
+    pub(crate) device: Arc<crate::RawDevice>,
+    pub(crate) name: Option<String>,
+}
+
+impl std::hash::Hash for BufferView {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (**self.raw).hash(state)
+    }
+}
+
+impl PartialEq for BufferView {
+    fn eq(&self, other: &BufferView) -> bool {
+        **self.raw == **other.raw
+    }
+}
+
+impl Eq for BufferView {}
+
+impl Clone for BufferView {
+    fn clone(&self) -> Self {
+        Self {
+            raw: Md::new(Arc::clone(&self.raw)),
+            buffer: self.buffer.clone(),
+            format: self.format,
+            device: Arc::clone(&self.device),
+            name: self.name.clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for BufferView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BufferView id: {:?} name: {:?}", **self.raw, self.name)
+    }
+}
+
+impl BufferView {
+    pub unsafe fn raw_buffer_view(&self) -> vk::BufferView {
+        **self.raw
+    }
+}
+
+impl BufferView {
+    /// Create a new BufferView
+    pub fn new(device: &crate::Device, desc: &BufferViewDesc<'_>) -> Result<Self, Error> {
+        #[cfg(feature = "logging")]
+        log::trace!("GPU: Create BufferView, name {:?}", desc.name);
+
+        if !desc
+            .buffer
+            .buffer
+            .usage
+            .intersects(crate::BufferUsage::UNIFORM_TEXEL | crate::BufferUsage::STORAGE_TEXEL)
+        {
+            panic!(
+                "ERROR: Attempt to create BufferView of {:?} without BufferUsage::UNIFORM_TEXEL or BufferUsage::STORAGE_TEXEL",
+                desc.buffer.buffer
+            );
+        }
+
+        let properties = device.format_properties(desc.format);
+        let usage = desc.buffer.buffer.usage;
+        if (usage.contains(crate::BufferUsage::UNIFORM_TEXEL) && !properties.uniform_texel_buffer())
+            || (usage.contains(crate::BufferUsage::STORAGE_TEXEL) && !properties.storage_texel_buffer())
+        {
+            panic!(
+                "ERROR: Attempt to create BufferView with format {:?} which doesn't support texel buffer usage {:?}",
+                desc.format, usage
+            );
+        }
+
+        let limits = &device.raw.limits;
+        if desc.buffer.offset % limits.min_texel_buffer_offset_alignment.max(1) != 0 {
+            panic!(
+                "ERROR: Attempt to create BufferView with offset {} not a multiple of minTexelBufferOffsetAlignment {}",
+                desc.buffer.offset, limits.min_texel_buffer_offset_alignment
+            );
+        }
+
+        let elements = desc.buffer.size / desc.format.size() as u64;
+        if elements > limits.max_texel_buffer_elements as u64 {
+            panic!(
+                "ERROR: Attempt to create BufferView of {} texels, more than maxTexelBufferElements {}",
+                elements, limits.max_texel_buffer_elements
+            );
+        }
+
+        let create_info = vk::BufferViewCreateInfo {
+            s_type: vk::StructureType::BUFFER_VIEW_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::BufferViewCreateFlags::empty(),
+            buffer: **desc.buffer.buffer.raw,
+            format: desc.format.into(),
+            offset: desc.buffer.offset,
+            range: desc.buffer.size,
+        };
+
+        let view_result = unsafe { device.raw.create_buffer_view(&create_info, None) };
+        let view = match view_result {
+            Ok(v) => v,
+            Err(e) => return Err(e.into()),
+        };
+
+        let s = Self {
+            raw: Md::new(Arc::new(view)),
+            buffer: desc.buffer.as_owned(),
+            format: desc.format,
+            device: Arc::clone(&device.raw),
+            name: desc.name.clone(),
+        };
+        if let Some(name) = &desc.name {
+            device.raw.set_buffer_view_name(&s, name)?;
+        }
+        device.raw.check_errors()?;
+        Ok(s)
+    }
+
+    /// Get the part of the buffer viewed
+    pub fn buffer(&self) -> &BufferSlice<'static> {
+        &self.buffer
+    }
+
+    /// Get the format of the texels in the buffer
+    pub fn format(&self) -> crate::Format {
+        self.format
+    }
+
+    /// Get the id of the buffer view
+    pub fn id(&self) -> u64 {
+        unsafe { std::mem::transmute(**self.raw) }
+    }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_buffer_view_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
+}
+
+impl Drop for BufferView {
+    fn drop(&mut self) {
+        unsafe {
+            let raw = Md::take(&mut self.raw);
+            if let Ok(raw) = Arc::try_unwrap(raw) {
+                self.device.destroy_buffer_view(raw, None);
+            }
+        }
+    }
+}
@@ -1,22 +1,83 @@
 use std::borrow::Borrow;
+use std::cell::RefCell;
 use std::mem::ManuallyDrop as Md;
 use std::ptr;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 use super::raw;
 
 use ash::vk;
+use vk::Handle;
+
+/// The fence of a command buffer shared with the [`Submission`]s made from it
+pub(crate) struct SubmitFence {
+    pub raw: vk::Fence,
+    /// the number of times the command buffer has been submitted
+    pub submits: AtomicU64,
+    pub device: Arc<crate::RawDevice>,
+}
+
+impl Drop for SubmitFence {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_fence(self.raw, None);
+        }
+    }
+}
+
+/// A submission of a [`CommandBuffer`]
+///
+/// Can be kept after the command buffer is submitted again or dropped to check
+/// if the work it submitted has completed, for example before reusing memory the work read from
+#[derive(Clone)]
+pub struct Submission {
+    pub(crate) fence: Arc<SubmitFence>,
+    /// the value of fence.submits after the submission
+    pub(crate) index: u64,
+}
+
+impl std::fmt::Debug for Submission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Submission fence: {:?} index: {}", self.fence.raw, self.index)
+    }
+}
+
+impl Submission {
+    /// Returns true if the submitted work has completed without waiting
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkGetFenceStatus.html>
+    pub fn is_complete(&self) -> Result<bool, crate::Error> {
+        // command buffers wait for their last submission before submitting again
+        if self.fence.submits.load(Ordering::Acquire) > self.index {
+            return Ok(true);
+        }
+
+        let status_result = unsafe { self.fence.device.get_fence_status(self.fence.raw) };
+
+        match status_result {
+            Ok(b) => Ok(b),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
 
 pub struct CommandBuffer {
     pub(crate) name: Option<String>,
 
-    pub(crate) pool: vk::CommandPool,
+    pub(crate) pool: Md<Arc<vk::CommandPool>>,
     pub(crate) buffer: vk::CommandBuffer,
+    /// true if the command buffer can only be executed by other command buffers
+    pub(crate) secondary: bool,
+    /// Some if the pool is shared with other command buffers from a [`super::CommandPool`]
+    pub(crate) pool_state: Option<Rc<RefCell<super::PoolState>>>,
+    /// the number of times the shared pool had been reset when this was last synced with it
+    pub(crate) pool_resets: u64,
 
     pub(crate) semaphore: Md<Arc<vk::Semaphore>>,
-    pub(crate) fence: vk::Fence,
+    pub(crate) fence: Arc<SubmitFence>,
 
-    pub(crate) queue: vk::Queue,
+    pub(crate) queue: crate::Queue,
     pub(crate) device: Arc<crate::RawDevice>,
     /// version shouldn't overflow
     ///
@@ -26,6 +87,11 @@ pub struct CommandBuffer {
     pub(crate) version: u64,
 
     pub(crate) swapchain: Option<(vk::Semaphore, vk::Semaphore)>,
+    /// semaphores from other submissions or shared with other apis to wait on / signal at the next submit
+    pub(crate) external_wait: Vec<(vk::Semaphore, vk::PipelineStageFlags)>,
+    pub(crate) external_signal: Vec<vk::Semaphore>,
+    /// layout transitions to record when the current dynamic rendering pass ends
+    pub(crate) end_transitions: Vec<raw::AttachmentTransition>,
     pub(crate) garbage: super::Garbage,
 }
 
@@ -37,7 +103,7 @@ impl std::fmt::Debug for CommandBuffer {
 
 impl CommandBuffer {
     pub unsafe fn raw_pool(&self) -> vk::CommandPool {
-        self.pool
+        **self.pool
     }
 
     pub unsafe fn raw_command_buffer(&self) -> vk::CommandBuffer {
@@ -49,21 +115,40 @@ impl CommandBuffer {
     }
 
     pub unsafe fn raw_fence(&self) -> vk::Fence {
-        self.fence
+        self.fence.raw
     }
 
     pub unsafe fn raw_queue(&self) -> vk::Queue {
-        self.queue
+        self.queue.raw
     }
 }
 
 impl CommandBuffer {
+    /// Create a new command buffer that submits to the main queue of the device
     pub fn new(device: &crate::Device, name: Option<String>) -> Result<Self, crate::Error> {
+        Self::with_queue(device, device.queue(crate::QueueKind::Graphics), name)
+    }
+
+    /// Create a new command buffer that submits to the queue supplied
+    pub fn with_queue(
+        device: &crate::Device,
+        queue: crate::Queue,
+        name: Option<String>,
+    ) -> Result<Self, crate::Error> {
+        Self::create(device, queue, false, name)
+    }
+
+    pub(crate) fn create(
+        device: &crate::Device,
+        queue: crate::Queue,
+        secondary: bool,
+        name: Option<String>,
+    ) -> Result<Self, crate::Error> {
         let pool_create_info = vk::CommandPoolCreateInfo {
             s_type: vk::StructureType::COMMAND_POOL_CREATE_INFO,
             p_next: ptr::null(),
             flags: vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
-            queue_family_index: device.queue_family,
+            queue_family_index: queue.family,
         };
 
         let pool_result = unsafe { device.raw.create_command_pool(&pool_create_info, None) };
@@ -73,15 +158,31 @@ impl CommandBuffer {
             Err(e) => return Err(e.into()),
         };
 
+        Self::from_pool(&device.raw, Arc::new(pool), queue, secondary, None, name)
+    }
+
+    /// Allocate a command buffer from the pool
+    pub(crate) fn from_pool(
+        device: &Arc<crate::RawDevice>,
+        pool: Arc<vk::CommandPool>,
+        queue: crate::Queue,
+        secondary: bool,
+        pool_state: Option<Rc<RefCell<super::PoolState>>>,
+        name: Option<String>,
+    ) -> Result<Self, crate::Error> {
         let buffer_alloc_info = vk::CommandBufferAllocateInfo {
             s_type: vk::StructureType::COMMAND_BUFFER_ALLOCATE_INFO,
             p_next: ptr::null(),
             command_buffer_count: 1,
-            command_pool: pool,
-            level: vk::CommandBufferLevel::PRIMARY,
+            command_pool: *pool,
+            level: if secondary {
+                vk::CommandBufferLevel::SECONDARY
+            } else {
+                vk::CommandBufferLevel::PRIMARY
+            },
         };
 
-        let buffer_result = unsafe { device.raw.allocate_command_buffers(&buffer_alloc_info) };
+        let buffer_result = unsafe { device.allocate_command_buffers(&buffer_alloc_info) };
 
         let buffer = match buffer_result {
             Ok(b) => b[0],
@@ -94,7 +195,7 @@ impl CommandBuffer {
             flags: vk::FenceCreateFlags::SIGNALED,
         };
 
-        let fence_result = unsafe { device.raw.create_fence(&fence_create_info, None) };
+        let fence_result = unsafe { device.create_fence(&fence_create_info, None) };
 
         let fence = match fence_result {
             Ok(f) => f,
@@ -107,52 +208,91 @@ impl CommandBuffer {
             flags: vk::SemaphoreCreateFlags::empty(),
         };
 
-        let semaphore_result = unsafe { device.raw.create_semaphore(&semaphore_create_info, None) };
+        let semaphore_result = unsafe { device.create_semaphore(&semaphore_create_info, None) };
 
         let semaphore = match semaphore_result {
             Ok(s) => s,
             Err(e) => return Err(e.into()),
         };
 
+        let pool_resets = match &pool_state {
+            Some(state) => {
+                let mut state = state.borrow_mut();
+                state.fences.push(fence);
+                state.resets
+            }
+            None => 0,
+        };
+
         let s = Self {
             name: name,
-            pool,
+            pool: Md::new(pool),
             buffer,
-            fence,
+            secondary,
+            pool_state,
+            pool_resets,
+            fence: Arc::new(SubmitFence {
+                raw: fence,
+                submits: AtomicU64::new(0),
+                device: Arc::clone(device),
+            }),
             semaphore: Md::new(Arc::new(semaphore)),
-            queue: device.queue,
-            device: Arc::clone(&device.raw),
+            queue,
+            device: Arc::clone(device),
             version: 0,
             swapchain: None,
+            external_wait: Vec::new(),
+            external_signal: Vec::new(),
+            end_transitions: Vec::new(),
             garbage: super::Garbage::default(),
         };
 
         if let Some(name) = &s.name {
-            device.raw.set_command_buffer_name(&s, name)?;
+            device.set_command_buffer_name(&s, name)?;
         }
 
-        device.raw.check_errors()?;
+        device.check_errors()?;
 
         Ok(s)
     }
 
     /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkQueueSubmit.html>
     pub fn submit(&mut self) -> Result<(), crate::Error> {
+        if self.secondary {
+            panic!(
+                "ERROR: Attempt to submit secondary command buffer {:?}, execute it from a primary command buffer instead",
+                self
+            );
+        }
         self.wait(!0)?;
         raw::submit(
             &self.device,
-            self.queue,
+            self.queue.raw,
             self.buffer,
             &self.semaphore,
             self.swapchain,
-            self.fence,
+            &self.external_wait,
+            &self.external_signal,
+            self.fence.raw,
             &mut self.garbage,
-        )
+        )?;
+        self.fence.submits.fetch_add(1, Ordering::Release);
+        Ok(())
+    }
+
+    /// The last submission of the command buffer
+    ///
+    /// If the command buffer hasn't been submitted the submission is already complete
+    pub fn last_submission(&self) -> Submission {
+        Submission {
+            fence: Arc::clone(&self.fence),
+            index: self.fence.submits.load(Ordering::Acquire),
+        }
     }
 
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkWaitForFences.html>
     pub fn wait(&mut self, timeout: u64) -> Result<(), crate::Error> {
-        let wait_result = unsafe { self.device.wait_for_fences(&[self.fence], true, timeout) };
+        let wait_result = unsafe { self.device.wait_for_fences(&[self.fence.raw], true, timeout) };
 
         match wait_result {
             Ok(_) => Ok(()),
@@ -160,17 +300,67 @@ impl CommandBuffer {
         }
     }
 
+    /// Returns true if the last submission of the command buffer has completed without waiting
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkGetFenceStatus.html>
+    pub fn is_complete(&self) -> Result<bool, crate::Error> {
+        let status_result = unsafe { self.device.get_fence_status(self.fence.raw) };
+
+        match status_result {
+            Ok(b) => Ok(b),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkResetCommandPool.html>
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkResetCommandBuffer.html>
     pub fn reset(&mut self) -> Result<(), crate::Error> {
+        self.sync_pool();
         self.wait(!0)?;
         unsafe {
             self.garbage.clean(&self.device);
         }
 
         self.version += 1;
+        self.reset_raw()
+    }
+
+    /// true if the pool is shared with other command buffers from a [`super::CommandPool`]
+    pub(crate) fn pooled(&self) -> bool {
+        self.pool_state.is_some()
+    }
+
+    /// The number of times the shared pool has been reset since self was last synced with it
+    fn pending_pool_resets(&self) -> u64 {
+        match &self.pool_state {
+            Some(state) => RefCell::borrow(state).resets - self.pool_resets,
+            None => 0,
+        }
+    }
+
+    /// Catch up with resets of the shared pool
+    ///
+    /// The pool waited for self to complete before resetting so the garbage can be cleaned
+    pub(crate) fn sync_pool(&mut self) {
+        let pending = self.pending_pool_resets();
+        if pending != 0 {
+            unsafe {
+                self.garbage.clean(&self.device);
+            }
+            self.version += pending;
+            self.pool_resets += pending;
+        }
+    }
+
+    /// Reset the pool if it's owned by self otherwise only the command buffer
+    pub(crate) fn reset_raw(&self) -> Result<(), crate::Error> {
         let result = unsafe {
-            self.device
-                .reset_command_pool(self.pool, vk::CommandPoolResetFlags::empty())
+            if self.pooled() {
+                self.device
+                    .reset_command_buffer(self.buffer, vk::CommandBufferResetFlags::empty())
+            } else {
+                self.device
+                    .reset_command_pool(**self.pool, vk::CommandPoolResetFlags::empty())
+            }
         };
 
         match result {
@@ -180,18 +370,32 @@ impl CommandBuffer {
     }
 
     /// Get a unique id of the command bufer
-    /// equivalent to buffer.id() == mem::transmute(buffer.raw_pool())
+    /// equivalent to buffer.id() == mem::transmute(buffer.raw_command_buffer())
     pub fn id(&self) -> u64 {
-        unsafe { std::mem::transmute(self.raw_pool()) }
+        unsafe { self.raw_command_buffer().as_raw() }
+    }
+
+    /// Get the queue that the command buffer submits to
+    pub fn queue(&self) -> crate::Queue {
+        self.queue
     }
 
     /// Command buffers keep track of how many times they have been recorded to
+    ///
+    /// Resetting the [`super::CommandPool`] the command buffer was allocated from also counts
     pub fn version(&self) -> u64 {
-        self.version
+        self.version + self.pending_pool_resets()
     }
 
     /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkBeginCommandBuffer.html>
     pub fn begin(&mut self, one_time_submit: bool) -> Result<(), crate::Error> {
+        if self.secondary {
+            panic!(
+                "ERROR: Attempt to begin secondary command buffer {:?} as primary, use SecondaryCommandBuffer::begin instead",
+                self
+            );
+        }
+        self.sync_pool();
         // wait for previous submission to complete if any
         if self.version != 0 {
             self.wait(!0)?;
@@ -201,9 +405,56 @@ impl CommandBuffer {
         }
 
         self.version += 1;
+        self.external_wait.clear();
+        self.external_signal.clear();
+        self.end_transitions.clear();
         raw::begin_primary(self.buffer, &self.device, one_time_submit)
     }
 
+    /// Wait for the semaphore to be signaled by another api before executing the stages of this command buffer
+    ///
+    /// Applies to the next submit of the command buffer
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkSubmitInfo.html>
+    pub fn wait_external_semaphore(
+        &mut self,
+        semaphore: &crate::ExternalSemaphore,
+        stages: crate::PipelineStageFlags,
+    ) {
+        self.garbage.semaphores.push(Arc::clone(&semaphore.raw));
+        self.external_wait.push((**semaphore.raw, stages.into()));
+    }
+
+    /// Signal the semaphore for another api once this command buffer has finished executing
+    ///
+    /// Applies to the next submit of the command buffer
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkSubmitInfo.html>
+    pub fn signal_external_semaphore(&mut self, semaphore: &crate::ExternalSemaphore) {
+        self.garbage.semaphores.push(Arc::clone(&semaphore.raw));
+        self.external_signal.push(**semaphore.raw);
+    }
+
+    /// Wait for the semaphore to be signaled by another submission before executing the stages of this command buffer
+    ///
+    /// Applies to the next submit of the command buffer
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkSubmitInfo.html>
+    pub fn wait_semaphore(
+        &mut self,
+        semaphore: &crate::Semaphore,
+        stages: crate::PipelineStageFlags,
+    ) {
+        self.garbage.semaphores.push(Arc::clone(&semaphore.raw));
+        self.external_wait.push((**semaphore.raw, stages.into()));
+    }
+
+    /// Signal the semaphore once this command buffer has finished executing
+    ///
+    /// Applies to the next submit of the command buffer
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkSubmitInfo.html>
+    pub fn signal_semaphore(&mut self, semaphore: &crate::Semaphore) {
+        self.garbage.semaphores.push(Arc::clone(&semaphore.raw));
+        self.external_signal.push(**semaphore.raw);
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdPipelineBarrier.html>
     pub fn end(&mut self) -> Result<(), crate::Error> {
         raw::end_recording(self.buffer, &self.device)
@@ -224,10 +475,86 @@ impl CommandBuffer {
             dst_stages,
             buffers,
             textures,
+            None,
+        )
+    }
+
+    /// Transfer ownership of exclusive textures from the queue family of src_queue to that of dst_queue
+    ///
+    /// The same barrier must be recorded on a command buffer of src_queue to release the textures and then on one of
+    /// dst_queue to acquire them, with a semaphore between the two submissions. When releasing dst_stages and dst_access
+    /// are ignored and when acquiring src_stages and src_access are ignored but the layouts must match in both.
+    /// Textures that aren't exclusive are shared between queue families so only get a normal pipeline barrier
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkspec.html#synchronization-queue-transfers>
+    pub fn queue_ownership_barrier(
+        &mut self,
+        src_queue: crate::Queue,
+        dst_queue: crate::Queue,
+        src_stages: crate::PipelineStageFlags,
+        dst_stages: crate::PipelineStageFlags,
+        textures: &[crate::TextureAccessInfo<'_>],
+    ) -> Result<(), crate::Error> {
+        raw::pipeline_barrier(
+            self.buffer,
+            &self.device,
+            src_stages,
+            dst_stages,
+            &[],
+            textures,
+            Some((src_queue.family, dst_queue.family)),
+        )
+    }
+
+    /// Signal the event once all previously submitted commands have completed the stages
+    ///
+    /// Together with [`CommandBuffer::wait_events`] splits a pipeline barrier in two
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdSetEvent.html>
+    pub fn set_event(
+        &mut self,
+        event: &crate::Event,
+        stages: crate::PipelineStageFlags,
+    ) -> Result<(), crate::Error> {
+        raw::set_event(self.buffer, &self.device, event, stages, &mut self.garbage)
+    }
+
+    /// Unsignal the event once all previously submitted commands have completed the stages
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdResetEvent.html>
+    pub fn reset_event(
+        &mut self,
+        event: &crate::Event,
+        stages: crate::PipelineStageFlags,
+    ) -> Result<(), crate::Error> {
+        raw::reset_event(self.buffer, &self.device, event, stages, &mut self.garbage)
+    }
+
+    /// Wait for the events to be signaled before executing dst_stages of subsequent commands
+    ///
+    /// src_stages must include the stages the events were set with, commands between the set and
+    /// the wait are not affected so they can run while the work before the set completes
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdWaitEvents.html>
+    pub fn wait_events(
+        &mut self,
+        events: &[&crate::Event],
+        src_stages: crate::PipelineStageFlags,
+        dst_stages: crate::PipelineStageFlags,
+        buffers: &[crate::BufferAccessInfo<'_>],
+        textures: &[crate::TextureAccessInfo<'_>],
+    ) -> Result<(), crate::Error> {
+        raw::wait_events(
+            self.buffer,
+            &self.device,
+            events,
+            src_stages,
+            dst_stages,
+            buffers,
+            textures,
+            &mut self.garbage,
         )
     }
 
     /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdUpdateBuffer.html>
+    ///
+    /// Updates larger than 65536 bytes are split into multiple commands
     pub fn update_buffer<B>(
         &mut self,
         buffer: B,
@@ -247,6 +574,16 @@ impl CommandBuffer {
         )
     }
 
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdFillBuffer.html>
+    ///
+    /// Fills the slice with repeated copies of value
+    pub fn fill_buffer<'a, B>(&mut self, buffer: B, value: u32) -> Result<(), crate::Error>
+    where
+        B: Borrow<crate::BufferSlice<'a>>,
+    {
+        raw::fill_buffer(self.buffer, &self.device, buffer, value, &mut self.garbage)
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdClearColorImage.html>
     /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdClearDepthStencilImage.html>
     pub fn clear_texture<'a, T>(
@@ -294,6 +631,57 @@ impl CommandBuffer {
         )
     }
 
+    /// Blit the src to the dst scaling and converting between formats
+    ///
+    /// Unlike [`Self::blit_textures`] the slices are transitioned from the initial layout of their textures
+    /// (such as PresentSrc for swapchain images) and returned to it after the blit
+    /// The formats must support blitting, integer formats can only be blitted to integer formats
+    /// and depth and stencil formats must match and use nearest filtering.
+    /// Only the base mip level of the slices will be used for the blit
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdBlitImage.html>
+    pub fn blit_texture<'a, T1, T2>(
+        &mut self,
+        src: T1,
+        dst: T2,
+        filter: crate::FilterMode,
+    ) -> Result<(), crate::Error>
+    where
+        T1: Borrow<crate::TextureSlice<'a>>,
+        T2: Borrow<crate::TextureSlice<'a>>,
+    {
+        raw::blit_texture(
+            self.buffer,
+            &self.device,
+            src.borrow(),
+            dst.borrow(),
+            filter,
+            &mut self.garbage,
+        )
+    }
+
+    /// Fill every mip level of the slice after the base level by repeatedly blitting from the level above
+    ///
+    /// The slice must be in the layout supplied and will be returned to it once the chain is generated
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdBlitImage.html>
+    pub fn generate_mipmaps<'a, T>(
+        &mut self,
+        texture: T,
+        layout: crate::TextureLayout,
+        filter: crate::FilterMode,
+    ) -> Result<(), crate::Error>
+    where
+        T: Borrow<crate::TextureSlice<'a>>,
+    {
+        raw::generate_mipmaps(
+            self.buffer,
+            &self.device,
+            texture.borrow(),
+            layout,
+            filter,
+            &mut self.garbage,
+        )
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/VkBufferImageCopy.html>
     pub fn copy_buffer_to_buffer<'a, B1, B2>(
         &mut self,
@@ -372,6 +760,9 @@ impl CommandBuffer {
         )
     }
 
+    /// Resolve a multisampled texture into a single sampled texture of the same format
+    ///
+    /// Only the base mip level of the slices will be used for the resolve
     /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdResolveImage.html>
     pub fn resolve_texture<'a, T1, T2>(
         &mut self,
@@ -407,6 +798,7 @@ impl CommandBuffer {
     where
         B: std::borrow::Borrow<crate::Attachment<'a>>,
     {
+        self.check_primary("empty_pass");
         if let Some(swapchain) = raw::begin_render_pass(
             self.buffer,
             &self.device,
@@ -414,12 +806,14 @@ impl CommandBuffer {
             resolve_attachments,
             depth_attachment,
             render_pass,
+            vk::SubpassContents::INLINE,
             &mut self.garbage,
+            &mut self.end_transitions,
         )? {
             self.swapchain = Some(swapchain)
         }
 
-        raw::end_render_pass(self.buffer, &self.device)
+        raw::end_render_pass(self.buffer, &self.device, &mut self.end_transitions)
     }
 
     /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdBeginRenderPass.html>
@@ -434,6 +828,7 @@ impl CommandBuffer {
     where
         B: std::borrow::Borrow<crate::Attachment<'a>>,
     {
+        self.check_primary("begin_graphics_pass");
         if let Some(swapchain) = raw::begin_graphics_pass(
             self.buffer,
             &self.device,
@@ -442,6 +837,7 @@ impl CommandBuffer {
             depth_attachment,
             pipeline,
             &mut self.garbage,
+            &mut self.end_transitions,
         )? {
             self.swapchain = Some(swapchain)
         }
@@ -451,7 +847,70 @@ impl CommandBuffer {
 
     /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdEndRenderPass.html>
     pub fn end_graphics_pass(&mut self) -> Result<(), crate::Error> {
-        raw::end_render_pass(self.buffer, &self.device)
+        raw::end_render_pass(self.buffer, &self.device, &mut self.end_transitions)
+    }
+
+    /// Begin a render pass whose contents are recorded in secondary command buffers
+    ///
+    /// Only [`CommandBuffer::execute_secondary`] can be called until the pass is ended with [`CommandBuffer::end_graphics_pass`]
+    /// The secondary command buffers must have been begun with [`SecondaryCommandBuffer::begin_graphics_pass`]
+    /// with a pipeline using a render pass compatible with render_pass
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdBeginRenderPass.html>
+    pub fn begin_secondary_pass<'a, B>(
+        &mut self,
+        color_attachments: &[B],
+        resolve_attachments: &[B],
+        depth_attachment: Option<B>,
+        render_pass: &crate::RenderPass,
+    ) -> Result<(), crate::Error>
+    where
+        B: std::borrow::Borrow<crate::Attachment<'a>>,
+    {
+        self.check_primary("begin_secondary_pass");
+        if let Some(swapchain) = raw::begin_render_pass(
+            self.buffer,
+            &self.device,
+            color_attachments,
+            resolve_attachments,
+            depth_attachment,
+            render_pass,
+            vk::SubpassContents::SECONDARY_COMMAND_BUFFERS,
+            &mut self.garbage,
+            &mut self.end_transitions,
+        )? {
+            self.swapchain = Some(swapchain)
+        }
+        Ok(())
+    }
+
+    /// Execute the commands recorded into the secondary command buffer
+    ///
+    /// The secondary must have been ended and shouldn't be begun again until this command buffer has completed
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdExecuteCommands.html>
+    pub fn execute_secondary(
+        &mut self,
+        secondary: &crate::SecondaryCommandBuffer,
+    ) -> Result<(), crate::Error> {
+        self.check_primary("execute_secondary");
+        self.garbage.extend_from(&secondary.inner.garbage);
+        self.garbage
+            .command_pools
+            .push(Arc::clone(&secondary.inner.pool));
+        raw::execute_secondary(self.buffer, &self.device, secondary.inner.buffer)
+    }
+
+    /// Returns true if the command buffer is a secondary command buffer
+    pub fn is_secondary(&self) -> bool {
+        self.secondary
+    }
+
+    fn check_primary(&self, function: &str) {
+        if self.secondary {
+            panic!(
+                "ERROR: Attempt to call {} on secondary command buffer {:?}",
+                function, self
+            );
+        }
     }
 
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdDrawIndirect.html>
@@ -530,6 +989,57 @@ impl CommandBuffer {
         )
     }
 
+    /// Set the scissor rectangles starting at first_scissor
+    ///
+    /// Must be called while in a graphics pass, the scissors are reset when the pass begins
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdSetScissor.html>
+    pub fn set_scissor(
+        &mut self,
+        first_scissor: u32,
+        scissors: &[crate::Scissor],
+    ) -> Result<(), crate::Error> {
+        raw::set_scissor(self.buffer, &self.device, first_scissor, scissors)
+    }
+
+    /// Set the stencil reference value of the bound pipeline
+    ///
+    /// Must be called while in a graphics pass with a pipeline that has stencil state bound,
+    /// the value is reset to the pipeline's [`crate::StencilState::reference`] when a pipeline is bound
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdSetStencilReference.html>
+    pub fn set_stencil_reference(
+        &mut self,
+        faces: crate::StencilFace,
+        reference: u32,
+    ) -> Result<(), crate::Error> {
+        raw::set_stencil_reference(self.buffer, &self.device, faces.into(), reference)
+    }
+
+    /// Set the stencil compare mask of the bound pipeline
+    ///
+    /// Must be called while in a graphics pass with a pipeline that has stencil state bound,
+    /// the mask is reset to the pipeline's [`crate::StencilState::compare_mask`] when a pipeline is bound
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdSetStencilCompareMask.html>
+    pub fn set_stencil_compare_mask(
+        &mut self,
+        faces: crate::StencilFace,
+        mask: u32,
+    ) -> Result<(), crate::Error> {
+        raw::set_stencil_compare_mask(self.buffer, &self.device, faces.into(), mask)
+    }
+
+    /// Set the stencil write mask of the bound pipeline
+    ///
+    /// Must be called while in a graphics pass with a pipeline that has stencil state bound,
+    /// the mask is reset to the pipeline's [`crate::StencilState::write_mask`] when a pipeline is bound
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdSetStencilWriteMask.html>
+    pub fn set_stencil_write_mask(
+        &mut self,
+        faces: crate::StencilFace,
+        mask: u32,
+    ) -> Result<(), crate::Error> {
+        raw::set_stencil_write_mask(self.buffer, &self.device, faces.into(), mask)
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdBindIndexBuffer.html>
     pub fn bind_index_buffer<'a, B>(
         &mut self,
@@ -590,6 +1100,7 @@ impl CommandBuffer {
             &self.device,
             location,
             &[group],
+            &[],
             bind_point,
             layout,
             &mut self.garbage,
@@ -612,6 +1123,36 @@ impl CommandBuffer {
             &self.device,
             first_location,
             groups,
+            &[],
+            bind_point,
+            layout,
+            &mut self.garbage,
+        )
+    }
+
+    /// Bind descriptor sets that contain dynamic uniform or storage buffers
+    ///
+    /// One offset must be supplied for each dynamic descriptor in the sets being bound,
+    /// ordered by set and then by binding. Each offset is added to the offset of the
+    /// buffer slice that was written into the descriptor
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdBindDescriptorSets.html>
+    pub fn bind_descriptors_dynamic<G>(
+        &mut self,
+        first_location: u32,
+        groups: &[G],
+        dynamic_offsets: &[u32],
+        bind_point: crate::PipelineBindPoint,
+        layout: &crate::PipelineLayout,
+    ) -> Result<(), crate::Error>
+    where
+        G: Borrow<crate::DescriptorSet>,
+    {
+        raw::bind_descriptors(
+            self.buffer,
+            &self.device,
+            first_location,
+            groups,
+            dynamic_offsets,
             bind_point,
             layout,
             &mut self.garbage,
@@ -675,6 +1216,34 @@ impl CommandBuffer {
             &mut self.garbage,
         )
     }
+
+    /// Open a labeled region of commands that shows up in debugging tools such as RenderDoc
+    ///
+    /// Must be closed with [`CommandBuffer::end_debug_label`], does nothing if debug utils are not enabled
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdBeginDebugUtilsLabelEXT.html>
+    pub fn begin_debug_label(&mut self, name: &str, color: [f32; 4]) -> Result<(), crate::Error> {
+        raw::begin_debug_label(self.buffer, &self.device, name, color)
+    }
+
+    /// Close the most recently opened labeled region
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdEndDebugUtilsLabelEXT.html>
+    pub fn end_debug_label(&mut self) -> Result<(), crate::Error> {
+        raw::end_debug_label(self.buffer, &self.device)
+    }
+
+    /// Insert a single label into the command stream
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdInsertDebugUtilsLabelEXT.html>
+    pub fn insert_label(&mut self, name: &str, color: [f32; 4]) -> Result<(), crate::Error> {
+        raw::insert_debug_label(self.buffer, &self.device, name, color)
+    }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_command_buffer_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
 }
 
 impl Drop for CommandBuffer {
@@ -683,12 +1252,19 @@ impl Drop for CommandBuffer {
             self.wait(!0).unwrap();
             self.garbage.clean(&self.device);
 
-            self.device.destroy_command_pool(self.pool, None);
+            let pool = Md::take(&mut self.pool);
+            if let Some(state) = &self.pool_state {
+                let fence = self.fence.raw;
+                state.borrow_mut().fences.retain(|&f| f != fence);
+                self.device.free_command_buffers(*pool, &[self.buffer]);
+            }
+            if let Ok(pool) = Arc::try_unwrap(pool) {
+                self.device.destroy_command_pool(pool, None);
+            }
             let semaphore = Md::take(&mut self.semaphore);
             if let Ok(semaphore) = Arc::try_unwrap(semaphore) {
                 self.device.destroy_semaphore(semaphore, None);
             }
-            self.device.destroy_fence(self.fence, None);
         }
     }
 }
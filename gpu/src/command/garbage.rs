@@ -7,6 +7,7 @@ pub(crate) struct Garbage {
     pub textures: Vec<Arc<vk::Image>>,
     pub views: Vec<Arc<vk::ImageView>>,
     pub buffers: Vec<Arc<vk::Buffer>>,
+    pub buffer_views: Vec<Arc<vk::BufferView>>,
     pub samplers: Vec<Arc<vk::Sampler>>,
     pub descriptor_layouts: Vec<Arc<vk::DescriptorSetLayout>>,
     pub descriptor_pools: Vec<Arc<vk::DescriptorPool>>,
@@ -16,6 +17,9 @@ pub(crate) struct Garbage {
     pub framebuffers: Vec<Arc<vk::Framebuffer>>,
     pub swapchains: Vec<crate::SwapchainInner>,
     pub queries: Vec<Arc<vk::QueryPool>>,
+    pub events: Vec<Arc<vk::Event>>,
+    pub semaphores: Vec<Arc<vk::Semaphore>>,
+    pub command_pools: Vec<Arc<vk::CommandPool>>,
     pub prev_semaphore: Option<Arc<vk::Semaphore>>,
 }
 
@@ -26,6 +30,7 @@ impl std::default::Default for Garbage {
             textures: Vec::new(),
             views: Vec::new(),
             buffers: Vec::new(),
+            buffer_views: Vec::new(),
             samplers: Vec::new(),
             descriptor_layouts: Vec::new(),
             descriptor_pools: Vec::new(),
@@ -35,12 +40,39 @@ impl std::default::Default for Garbage {
             framebuffers: Vec::new(),
             swapchains: Vec::new(),
             queries: Vec::new(),
+            events: Vec::new(),
+            semaphores: Vec::new(),
+            command_pools: Vec::new(),
             prev_semaphore: None,
         }
     }
 }
 
 impl Garbage {
+    /// Keep everything referenced by other alive until self is cleaned as well
+    pub fn extend_from(&mut self, other: &Garbage) {
+        self.memory.extend(other.memory.iter().cloned());
+        self.textures.extend(other.textures.iter().cloned());
+        self.views.extend(other.views.iter().cloned());
+        self.buffers.extend(other.buffers.iter().cloned());
+        self.buffer_views.extend(other.buffer_views.iter().cloned());
+        self.samplers.extend(other.samplers.iter().cloned());
+        self.descriptor_layouts
+            .extend(other.descriptor_layouts.iter().cloned());
+        self.descriptor_pools
+            .extend(other.descriptor_pools.iter().cloned());
+        self.pipeline_layouts
+            .extend(other.pipeline_layouts.iter().cloned());
+        self.render_passes.extend(other.render_passes.iter().cloned());
+        self.pipelines.extend(other.pipelines.iter().cloned());
+        self.framebuffers.extend(other.framebuffers.iter().cloned());
+        self.swapchains.extend(other.swapchains.iter().cloned());
+        self.queries.extend(other.queries.iter().cloned());
+        self.events.extend(other.events.iter().cloned());
+        self.semaphores.extend(other.semaphores.iter().cloned());
+        self.command_pools.extend(other.command_pools.iter().cloned());
+    }
+
     pub unsafe fn clean(&mut self, device: &crate::RawDevice) {
         for mem in self.memory.drain(..) {
             if let Ok(mem) = Arc::try_unwrap(mem) {
@@ -60,6 +92,12 @@ impl Garbage {
             }
         }
 
+        for view in self.buffer_views.drain(..) {
+            if let Ok(view) = Arc::try_unwrap(view) {
+                device.destroy_buffer_view(view, None);
+            }
+        }
+
         for buffer in self.buffers.drain(..) {
             if let Ok(buffer) = Arc::try_unwrap(buffer) {
                 device.destroy_buffer(buffer, None);
@@ -92,7 +130,9 @@ impl Garbage {
 
         for render_pass in self.render_passes.drain(..) {
             if let Ok(render_pass) = Arc::try_unwrap(render_pass) {
-                device.destroy_render_pass(render_pass, None);
+                if render_pass != vk::RenderPass::null() {
+                    device.destroy_render_pass(render_pass, None);
+                }
             }
         }
 
@@ -118,6 +158,24 @@ impl Garbage {
             }
         }
 
+        for event in self.events.drain(..) {
+            if let Ok(event) = Arc::try_unwrap(event) {
+                device.destroy_event(event, None);
+            }
+        }
+
+        for semaphore in self.semaphores.drain(..) {
+            if let Ok(semaphore) = Arc::try_unwrap(semaphore) {
+                device.destroy_semaphore(semaphore, None);
+            }
+        }
+
+        for pool in self.command_pools.drain(..) {
+            if let Ok(pool) = Arc::try_unwrap(pool) {
+                device.destroy_command_pool(pool, None);
+            }
+        }
+
         if let Some(prev_semaphore) = self.prev_semaphore.take() {
             if let Ok(semaphore) = Arc::try_unwrap(prev_semaphore) {
                 device.destroy_semaphore(semaphore, None);
@@ -3,7 +3,11 @@
 pub mod buffer;
 pub(crate) mod garbage;
 pub(crate) mod raw;
+pub mod pool;
+pub mod secondary;
 
 pub use buffer::*;
+pub use pool::*;
+pub use secondary::*;
 
 pub(crate) use garbage::*;
@@ -0,0 +1,177 @@
+//! [`CommandPool`]'s allocate many command buffers from one vulkan command pool
+
+use std::cell::RefCell;
+use std::mem::ManuallyDrop as Md;
+use std::ptr;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use ash::vk;
+
+/// Describes a command pool
+#[derive(Debug, Clone)]
+pub struct CommandPoolDesc {
+    /// the name of the command pool, used for debugging
+    pub name: Option<String>,
+    /// the queue that command buffers allocated from the pool submit to
+    pub queue: crate::Queue,
+    /// hint that command buffers allocated from the pool are short lived
+    /// and recorded once before the pool is reset
+    pub transient: bool,
+}
+
+/// State shared between a [`CommandPool`] and the command buffers allocated from it
+#[derive(Debug, Default)]
+pub(crate) struct PoolState {
+    /// the fences of every command buffer allocated from the pool that is still alive
+    pub fences: Vec<vk::Fence>,
+    /// the number of times the pool has been reset
+    pub resets: u64,
+}
+
+/// A CommandPool
+///
+/// [`crate::Device::create_command_buffer`] creates a pool for every command buffer, apps recording many small
+/// command buffers every frame can instead allocate them from a shared pool and reset the pool once per frame.
+///
+/// Vulkan requires a pool and the command buffers allocated from it to be externally synchronized
+/// so the pool and its command buffers are neither Send or Sync, use a pool per thread to record on many threads
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkCommandPool.html>
+pub struct CommandPool {
+    pub(crate) name: Option<String>,
+    pub(crate) raw: Md<Arc<vk::CommandPool>>,
+    pub(crate) queue: crate::Queue,
+    pub(crate) device: Arc<crate::RawDevice>,
+    /// shared with the command buffers allocated from the pool, also keeps the pool on one thread
+    pub(crate) state: Rc<RefCell<PoolState>>,
+}
+
+impl std::fmt::Debug for CommandPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CommandPool id: {:?} name: {:?}", **self.raw, self.name)
+    }
+}
+
+impl CommandPool {
+    pub unsafe fn raw_pool(&self) -> vk::CommandPool {
+        **self.raw
+    }
+}
+
+impl CommandPool {
+    /// Create a new command pool
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCreateCommandPool.html>
+    pub fn new(device: &crate::Device, desc: &CommandPoolDesc) -> Result<Self, crate::Error> {
+        #[cfg(feature = "logging")]
+        log::trace!("GPU: Create CommandPool, name {:?}", desc.name);
+
+        let mut flags = vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER;
+        if desc.transient {
+            flags |= vk::CommandPoolCreateFlags::TRANSIENT;
+        }
+
+        let create_info = vk::CommandPoolCreateInfo {
+            s_type: vk::StructureType::COMMAND_POOL_CREATE_INFO,
+            p_next: ptr::null(),
+            flags,
+            queue_family_index: desc.queue.family,
+        };
+
+        let raw_result = unsafe { device.raw.create_command_pool(&create_info, None) };
+
+        let raw = match raw_result {
+            Ok(p) => p,
+            Err(e) => return Err(e.into()),
+        };
+
+        let s = Self {
+            name: desc.name.clone(),
+            raw: Md::new(Arc::new(raw)),
+            queue: desc.queue,
+            device: Arc::clone(&device.raw),
+            state: Rc::new(RefCell::new(PoolState::default())),
+        };
+
+        if let Some(name) = &desc.name {
+            device.raw.set_command_pool_name(&s, name)?;
+        }
+
+        device.raw.check_errors()?;
+
+        Ok(s)
+    }
+
+    /// Allocate a command buffer from the pool
+    ///
+    /// The command buffer keeps the pool alive and is freed back to the pool when dropped
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkAllocateCommandBuffers.html>
+    pub fn allocate(&self, name: Option<String>) -> Result<crate::CommandBuffer, crate::Error> {
+        crate::CommandBuffer::from_pool(
+            &self.device,
+            Arc::clone(&self.raw),
+            self.queue,
+            false,
+            Some(Rc::clone(&self.state)),
+            name,
+        )
+    }
+
+    /// Reset every command buffer allocated from the pool at once, usually at the start of a frame
+    ///
+    /// Waits for every command buffer allocated from the pool to finish executing first
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkResetCommandPool.html>
+    pub fn reset(&mut self) -> Result<(), crate::Error> {
+        self.reset_flags(vk::CommandPoolResetFlags::empty())
+    }
+
+    /// Reset the pool and return the memory it holds to the system
+    ///
+    /// Waits for every command buffer allocated from the pool to finish executing first
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkResetCommandPool.html>
+    pub fn reset_release(&mut self) -> Result<(), crate::Error> {
+        self.reset_flags(vk::CommandPoolResetFlags::RELEASE_RESOURCES)
+    }
+
+    fn reset_flags(&mut self, flags: vk::CommandPoolResetFlags) -> Result<(), crate::Error> {
+        let mut state = self.state.borrow_mut();
+
+        if !state.fences.is_empty() {
+            let wait_result = unsafe { self.device.wait_for_fences(&state.fences, true, !0) };
+            if let Err(e) = wait_result {
+                return Err(e.into());
+            }
+        }
+
+        let result = unsafe { self.device.reset_command_pool(**self.raw, flags) };
+
+        match result {
+            Ok(_) => {
+                // command buffers clean up their garbage when they next see the pool was reset
+                state.resets += 1;
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Get the queue that command buffers allocated from the pool submit to
+    pub fn queue(&self) -> crate::Queue {
+        self.queue
+    }
+
+    /// Get a unique id of the command pool
+    pub fn id(&self) -> u64 {
+        unsafe { std::mem::transmute(**self.raw) }
+    }
+}
+
+impl Drop for CommandPool {
+    fn drop(&mut self) {
+        unsafe {
+            let raw = Md::take(&mut self.raw);
+            if let Ok(raw) = Arc::try_unwrap(raw) {
+                self.device.destroy_command_pool(raw, None);
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::borrow::Borrow;
+use std::ffi::c_void;
 use std::ptr;
 use std::sync::Arc;
 
@@ -6,35 +7,38 @@ use ash::vk;
 
 use parking_lot::Mutex;
 
-pub(crate) fn pipeline_barrier(
-    command_buffer: vk::CommandBuffer,
-    device: &crate::RawDevice,
-    src_stages: crate::PipelineStageFlags,
-    dst_stages: crate::PipelineStageFlags,
+/// queue_families is the source and destination family of a queue family ownership transfer,
+/// only textures created exclusive are transferred as shared resources don't need it
+fn memory_barriers(
     buffers: &[crate::BufferAccessInfo<'_>],
     textures: &[crate::TextureAccessInfo<'_>],
-) -> Result<(), crate::Error> {
-    #[cfg(feature = "logging")]
-    log::trace!("GPU: cmd_pipeline_barrier");
+    queue_families: Option<(u32, u32)>,
+) -> (Vec<vk::BufferMemoryBarrier>, Vec<vk::ImageMemoryBarrier>) {
     let image_barriers = textures
         .iter()
-        .map(|info| vk::ImageMemoryBarrier {
-            s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
-            p_next: ptr::null(),
-            src_access_mask: info.src_access.into(),
-            dst_access_mask: info.dst_access.into(),
-            old_layout: info.src_layout.into(),
-            new_layout: info.dst_layout.into(),
-            image: **info.texture.raw,
-            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
-            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
-            subresource_range: vk::ImageSubresourceRange {
-                aspect_mask: info.texture.format.aspects().into(),
-                base_mip_level: info.base_mip_level,
-                level_count: info.mip_levels,
-                base_array_layer: info.base_array_layer,
-                layer_count: info.array_layers,
-            },
+        .map(|info| {
+            let (src_family, dst_family) = match queue_families {
+                Some(families) if info.texture.exclusive => families,
+                _ => (vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED),
+            };
+            vk::ImageMemoryBarrier {
+                s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+                p_next: ptr::null(),
+                src_access_mask: info.src_access.into(),
+                dst_access_mask: info.dst_access.into(),
+                old_layout: info.src_layout.into(),
+                new_layout: info.dst_layout.into(),
+                image: **info.texture.raw,
+                src_queue_family_index: src_family,
+                dst_queue_family_index: dst_family,
+                subresource_range: vk::ImageSubresourceRange {
+                    aspect_mask: info.texture.format.aspects().into(),
+                    base_mip_level: info.base_mip_level,
+                    level_count: info.mip_levels,
+                    base_array_layer: info.base_array_layer,
+                    layer_count: info.array_layers,
+                },
+            }
         })
         .collect::<Vec<_>>();
 
@@ -52,6 +56,22 @@ pub(crate) fn pipeline_barrier(
             size: info.buffer.size,
         })
         .collect::<Vec<_>>();
+
+    (buffer_barriers, image_barriers)
+}
+
+pub(crate) fn pipeline_barrier(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    src_stages: crate::PipelineStageFlags,
+    dst_stages: crate::PipelineStageFlags,
+    buffers: &[crate::BufferAccessInfo<'_>],
+    textures: &[crate::TextureAccessInfo<'_>],
+    queue_families: Option<(u32, u32)>,
+) -> Result<(), crate::Error> {
+    #[cfg(feature = "logging")]
+    log::trace!("GPU: cmd_pipeline_barrier");
+    let (buffer_barriers, image_barriers) = memory_barriers(buffers, textures, queue_families);
     unsafe {
         device.cmd_pipeline_barrier(
             command_buffer,
@@ -67,6 +87,66 @@ pub(crate) fn pipeline_barrier(
     Ok(device.check_errors()?)
 }
 
+pub(crate) fn set_event(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    event: &crate::Event,
+    stages: crate::PipelineStageFlags,
+    garbage: &mut super::Garbage,
+) -> Result<(), crate::Error> {
+    #[cfg(feature = "logging")]
+    log::trace!("GPU: cmd_set_event");
+    garbage.events.push(Arc::clone(&event.raw));
+    unsafe { device.cmd_set_event(command_buffer, **event.raw, stages.into()) }
+
+    Ok(device.check_errors()?)
+}
+
+pub(crate) fn reset_event(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    event: &crate::Event,
+    stages: crate::PipelineStageFlags,
+    garbage: &mut super::Garbage,
+) -> Result<(), crate::Error> {
+    #[cfg(feature = "logging")]
+    log::trace!("GPU: cmd_reset_event");
+    garbage.events.push(Arc::clone(&event.raw));
+    unsafe { device.cmd_reset_event(command_buffer, **event.raw, stages.into()) }
+
+    Ok(device.check_errors()?)
+}
+
+pub(crate) fn wait_events(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    events: &[&crate::Event],
+    src_stages: crate::PipelineStageFlags,
+    dst_stages: crate::PipelineStageFlags,
+    buffers: &[crate::BufferAccessInfo<'_>],
+    textures: &[crate::TextureAccessInfo<'_>],
+    garbage: &mut super::Garbage,
+) -> Result<(), crate::Error> {
+    #[cfg(feature = "logging")]
+    log::trace!("GPU: cmd_wait_events");
+    garbage.events.extend(events.iter().map(|e| Arc::clone(&e.raw)));
+    let raw_events = events.iter().map(|e| **e.raw).collect::<Vec<_>>();
+    let (buffer_barriers, image_barriers) = memory_barriers(buffers, textures, None);
+    unsafe {
+        device.cmd_wait_events(
+            command_buffer,
+            &raw_events,
+            src_stages.into(),
+            dst_stages.into(),
+            &[],
+            &buffer_barriers,
+            &image_barriers,
+        )
+    }
+
+    Ok(device.check_errors()?)
+}
+
 pub(crate) fn update_buffer<B>(
     command_buffer: vk::CommandBuffer,
     device: &crate::RawDevice,
@@ -78,13 +158,68 @@ pub(crate) fn update_buffer<B>(
 where
     B: Borrow<crate::Buffer>,
 {
+    if offset % 4 != 0 || data.len() % 4 != 0 {
+        panic!(
+            "ERROR: Attempt to update buffer with offset {} and size {}, both must be multiples of 4",
+            offset,
+            data.len()
+        );
+    }
+
     garbage.buffers.push(Arc::clone(&*(buffer.borrow().raw)));
     garbage.memory.push(Arc::clone(&*buffer.borrow().memory));
 
     #[cfg(feature = "logging")]
     log::trace!("GPU: cmd_update_buffer");
+    // vkCmdUpdateBuffer is limited to 65536 bytes per call so split larger updates
+    for (i, chunk) in data.chunks(MAX_UPDATE_SIZE).enumerate() {
+        unsafe {
+            device.cmd_update_buffer(
+                command_buffer,
+                **buffer.borrow().raw,
+                offset + (i * MAX_UPDATE_SIZE) as u64,
+                chunk,
+            );
+        }
+    }
+    Ok(device.check_errors()?)
+}
+
+/// Maximum number of bytes a single vkCmdUpdateBuffer can write
+const MAX_UPDATE_SIZE: usize = 65536;
+
+pub(crate) fn fill_buffer<'a, B>(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    buffer: B,
+    value: u32,
+    garbage: &mut super::Garbage,
+) -> Result<(), crate::Error>
+where
+    B: Borrow<crate::BufferSlice<'a>>,
+{
+    let slice = buffer.borrow();
+    if slice.offset % 4 != 0 {
+        panic!(
+            "ERROR: Attempt to fill buffer at offset {}, offset must be a multiple of 4",
+            slice.offset
+        );
+    }
+
+    garbage.buffers.push(Arc::clone(&*(slice.buffer.raw)));
+    garbage.memory.push(Arc::clone(&*slice.buffer.memory));
+
+    #[cfg(feature = "logging")]
+    log::trace!("GPU: cmd_fill_buffer {:?}, value: {}", slice, value);
     unsafe {
-        device.cmd_update_buffer(command_buffer, **buffer.borrow().raw, offset, data);
+        // fill rounds the size down to a multiple of 4
+        device.cmd_fill_buffer(
+            command_buffer,
+            **slice.buffer.raw,
+            slice.offset,
+            slice.size & !3,
+            value,
+        );
     }
     Ok(device.check_errors()?)
 }
@@ -222,6 +357,320 @@ where
     Ok(device.check_errors()?)
 }
 
+pub(crate) fn blit_texture<'a, T1, T2>(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    src: T1,
+    dst: T2,
+    filter: crate::FilterMode,
+    garbage: &mut super::Garbage,
+) -> Result<(), crate::Error>
+where
+    T1: Borrow<crate::TextureSlice<'a>>,
+    T2: Borrow<crate::TextureSlice<'a>>,
+{
+    let (s, d) = (src.borrow(), dst.borrow());
+    if s.texture.dimension.samples() != crate::Samples::S1
+        || d.texture.dimension.samples() != crate::Samples::S1
+    {
+        panic!("ERROR: Attempt to blit multisampled texture, use resolve_texture instead");
+    }
+    if s.texture.format.is_compressed() || d.texture.format.is_compressed() {
+        panic!(
+            "ERROR: Attempt to blit from {:?} to {:?}, compressed formats can't be blitted",
+            s.texture.format, d.texture.format
+        );
+    }
+    let depth_stencil = crate::TextureAspects::DEPTH | crate::TextureAspects::STENCIL;
+    if s.texture.format.aspects().intersects(depth_stencil)
+        || d.texture.format.aspects().intersects(depth_stencil)
+    {
+        if s.texture.format != d.texture.format || filter != crate::FilterMode::Nearest {
+            panic!(
+                "ERROR: Attempt to blit from {:?} to {:?} with filter {:?}, depth and stencil formats must match and use nearest filtering",
+                s.texture.format, d.texture.format, filter
+            );
+        }
+    }
+
+    let barrier = |slice: &crate::TextureSlice<'_>,
+                   src_access_mask: vk::AccessFlags,
+                   dst_access_mask: vk::AccessFlags,
+                   old_layout: vk::ImageLayout,
+                   new_layout: vk::ImageLayout| vk::ImageMemoryBarrier {
+        s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+        p_next: ptr::null(),
+        src_access_mask,
+        dst_access_mask,
+        old_layout,
+        new_layout,
+        image: **slice.texture.raw,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        subresource_range: vk::ImageSubresourceRange {
+            aspect_mask: slice.texture.format.aspects().into(),
+            base_mip_level: slice.base_mip_level,
+            level_count: 1,
+            base_array_layer: slice.base_array_layer,
+            layer_count: slice.array_layers,
+        },
+    };
+
+    // textures are kept in their initial layout between commands
+    let src_layout: vk::ImageLayout = s.texture.initial_layout().into();
+    let dst_layout: vk::ImageLayout = d.texture.initial_layout().into();
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[
+                barrier(
+                    s,
+                    vk::AccessFlags::MEMORY_WRITE,
+                    vk::AccessFlags::TRANSFER_READ,
+                    src_layout,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                ),
+                barrier(
+                    d,
+                    vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    dst_layout,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                ),
+            ],
+        );
+    }
+    device.check_errors()?;
+
+    blit_textures(
+        command_buffer,
+        device,
+        s,
+        crate::TextureLayout::CopySrcOptimal,
+        d,
+        crate::TextureLayout::CopyDstOptimal,
+        filter,
+        garbage,
+    )?;
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[
+                barrier(
+                    s,
+                    vk::AccessFlags::TRANSFER_READ,
+                    vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    src_layout,
+                ),
+                barrier(
+                    d,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    dst_layout,
+                ),
+            ],
+        );
+    }
+    Ok(device.check_errors()?)
+}
+
+pub(crate) fn generate_mipmaps<'a, T>(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    texture: T,
+    layout: crate::TextureLayout,
+    filter: crate::FilterMode,
+    garbage: &mut super::Garbage,
+) -> Result<(), crate::Error>
+where
+    T: Borrow<crate::TextureSlice<'a>>,
+{
+    let slice = texture.borrow();
+    if let Some(mem) = &slice.texture.memory {
+        garbage.textures.push(Arc::clone(&*(slice.texture.raw)));
+        garbage.memory.push(Arc::clone(mem));
+    }
+
+    #[cfg(feature = "logging")]
+    log::trace!(
+        "GPU: cmd_generate_mipmaps texture: {:?} in layout {:?}, filter mode: {:?}",
+        slice,
+        layout,
+        filter
+    );
+
+    if slice.mip_levels <= 1 {
+        return Ok(());
+    }
+
+    let image = **slice.texture.raw;
+    let aspect_mask: vk::ImageAspectFlags = slice.texture.format.aspects().into();
+    let layout: vk::ImageLayout = layout.into();
+
+    let barrier = |base_mip_level: u32,
+                   level_count: u32,
+                   src_access_mask: vk::AccessFlags,
+                   dst_access_mask: vk::AccessFlags,
+                   old_layout: vk::ImageLayout,
+                   new_layout: vk::ImageLayout| vk::ImageMemoryBarrier {
+        s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+        p_next: ptr::null(),
+        src_access_mask,
+        dst_access_mask,
+        old_layout,
+        new_layout,
+        image,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        subresource_range: vk::ImageSubresourceRange {
+            aspect_mask,
+            base_mip_level,
+            level_count,
+            base_array_layer: slice.base_array_layer,
+            layer_count: slice.array_layers,
+        },
+    };
+
+    let mip_offset = |level: u32| vk::Offset3D {
+        x: slice.offset.x >> level,
+        y: slice.offset.y >> level,
+        z: slice.offset.z >> level,
+    };
+    let mip_end = |level: u32| {
+        let o = mip_offset(level);
+        vk::Offset3D {
+            x: o.x + (slice.extent.width >> level).max(1) as i32,
+            y: o.y + (slice.extent.height >> level).max(1) as i32,
+            z: o.z + (slice.extent.depth >> level).max(1) as i32,
+        }
+    };
+
+    let base = slice.base_mip_level;
+    let last = base + slice.mip_levels - 1;
+
+    unsafe {
+        // every level other than the base is overwritten so the previous contents can be discarded
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[
+                barrier(
+                    base,
+                    1,
+                    vk::AccessFlags::MEMORY_WRITE,
+                    vk::AccessFlags::TRANSFER_READ,
+                    layout,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                ),
+                barrier(
+                    base + 1,
+                    slice.mip_levels - 1,
+                    vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::ImageLayout::UNDEFINED,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                ),
+            ],
+        );
+
+        for level in (base + 1)..=last {
+            let src_level = level - base - 1;
+            let dst_level = level - base;
+            device.cmd_blit_image(
+                command_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[vk::ImageBlit {
+                    src_subresource: vk::ImageSubresourceLayers {
+                        aspect_mask,
+                        mip_level: level - 1,
+                        base_array_layer: slice.base_array_layer,
+                        layer_count: slice.array_layers,
+                    },
+                    src_offsets: [mip_offset(src_level), mip_end(src_level)],
+                    dst_subresource: vk::ImageSubresourceLayers {
+                        aspect_mask,
+                        mip_level: level,
+                        base_array_layer: slice.base_array_layer,
+                        layer_count: slice.array_layers,
+                    },
+                    dst_offsets: [mip_offset(dst_level), mip_end(dst_level)],
+                }],
+                filter.into(),
+            );
+
+            if level != last {
+                // the level just written becomes the source of the next blit
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[barrier(
+                        level,
+                        1,
+                        vk::AccessFlags::TRANSFER_WRITE,
+                        vk::AccessFlags::TRANSFER_READ,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    )],
+                );
+            }
+        }
+
+        // return every level to the layout the slice was in
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[
+                barrier(
+                    base,
+                    slice.mip_levels - 1,
+                    vk::AccessFlags::TRANSFER_READ,
+                    vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    layout,
+                ),
+                barrier(
+                    last,
+                    1,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    layout,
+                ),
+            ],
+        );
+    }
+    Ok(device.check_errors()?)
+}
+
 pub(crate) fn copy_buffer_to_buffer<'a, B1, B2>(
     command_buffer: vk::CommandBuffer,
     device: &crate::RawDevice,
@@ -267,6 +716,23 @@ where
     Ok(device.check_errors()?)
 }
 
+/// Get the buffer row length and image height for copies between a buffer and the texture slice
+///
+/// Compressed formats are copied in whole blocks so the dimensions are rounded up to a multiple of the block size
+fn buffer_texture_copy_dimensions(slice: &crate::TextureSlice<'_>) -> (u32, u32) {
+    let format = slice.texture.format;
+    let (bw, bh) = format.block_extent();
+    if slice.offset.x as u32 % bw != 0 || slice.offset.y as u32 % bh != 0 {
+        panic!(
+            "ERROR: Attempt to copy between buffer and texture slice {:?} with offset not aligned to the block size of format {:?}",
+            slice, format
+        );
+    }
+    let width = (slice.extent.width + bw - 1) / bw * bw;
+    let height = (slice.extent.height + bh - 1) / bh * bh;
+    (width, height)
+}
+
 pub(crate) fn copy_texture_to_buffer<'a, B, T>(
     command_buffer: vk::CommandBuffer,
     device: &crate::RawDevice,
@@ -301,6 +767,7 @@ where
     if src.borrow().mip_levels != 1 {
         log::warn!("GPU: copy texture to buffer with src: \"{:?}\" of multiple mip levels: only the base mip level is used", src.borrow())
     }
+    let (buffer_row_length, buffer_image_height) = buffer_texture_copy_dimensions(src.borrow());
     unsafe {
         device.cmd_copy_image_to_buffer(
             command_buffer,
@@ -309,8 +776,8 @@ where
             **dst.borrow().buffer.raw,
             &[vk::BufferImageCopy {
                 buffer_offset: dst.borrow().offset,
-                buffer_row_length: src.borrow().extent.width,
-                buffer_image_height: src.borrow().extent.height,
+                buffer_row_length,
+                buffer_image_height,
                 image_subresource: vk::ImageSubresourceLayers {
                     aspect_mask: src.borrow().texture.format.aspects().into(),
                     base_array_layer: src.borrow().base_array_layer,
@@ -359,6 +826,7 @@ where
     if dst.borrow().mip_levels != 1 {
         log::warn!("GPU: copy buffer to texture with dst: \"{:?}\" of multiple mip levels: only the base mip level is used", dst.borrow())
     }
+    let (buffer_row_length, buffer_image_height) = buffer_texture_copy_dimensions(dst.borrow());
     unsafe {
         device.cmd_copy_buffer_to_image(
             command_buffer,
@@ -367,8 +835,8 @@ where
             dst_layout.into(),
             &[vk::BufferImageCopy {
                 buffer_offset: src.borrow().offset,
-                buffer_row_length: dst.borrow().extent.width,
-                buffer_image_height: dst.borrow().extent.height,
+                buffer_row_length,
+                buffer_image_height,
                 image_subresource: vk::ImageSubresourceLayers {
                     aspect_mask: dst.borrow().texture.format.aspects().into(),
                     base_array_layer: dst.borrow().base_array_layer,
@@ -467,6 +935,26 @@ where
     T1: Borrow<crate::TextureSlice<'a>>,
     T2: Borrow<crate::TextureSlice<'a>>,
 {
+    if src.borrow().texture.dimension.samples() == crate::Samples::S1 {
+        panic!(
+            "ERROR: Attempt to resolve from texture {:?} that isn't multisampled",
+            src.borrow().texture
+        );
+    }
+    if dst.borrow().texture.dimension.samples() != crate::Samples::S1 {
+        panic!(
+            "ERROR: Attempt to resolve into multisampled texture {:?}",
+            dst.borrow().texture
+        );
+    }
+    if src.borrow().texture.format != dst.borrow().texture.format {
+        panic!(
+            "ERROR: Attempt to resolve texture with format {:?} into texture with format {:?}",
+            src.borrow().texture.format,
+            dst.borrow().texture.format
+        );
+    }
+
     if let Some(mem) = &src.borrow().texture.memory {
         garbage
             .textures
@@ -546,40 +1034,80 @@ pub(crate) fn begin_primary(
     Ok(device.check_errors()?)
 }
 
-// pub(crate) fn begin_secondary(
-//     command_buffer: vk::CommandBuffer,
-//     device: &crate::RawDevice,
-//     render_pass: Option<vk::RenderPass>,
-// ) -> Result<(), crate::Error> {
-//     #[cfg(feature = "logging")]
-//     log::trace!("GPU: begin_command_buffer");
-//     let t = vk::CommandBufferInheritanceInfo {
-//         s_type: vk::StructureType::COMMAND_BUFFER_INHERITANCE_INFO,
-//         p_next: ptr::null(),
-//         render_pass: render_pass.unwrap_or(vk::RenderPass::null()),
-//         subpass: 0,
-//         framebuffer: vk::Framebuffer::null(),
-//         query_flags: vk::QueryControlFlags::empty(),
-//         occlusion_query_enable: vk::FALSE,
-//         pipeline_statistics: vk::QueryPipelineStatisticFlags::empty(),
-//     };
-//     let result = unsafe {
-//         device.begin_command_buffer(
-//             command_buffer,
-//             &vk::CommandBufferBeginInfo {
-//                 s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
-//                 p_next: ptr::null(),
-//                 p_inheritance_info: &t,
-//                 flags: vk::CommandBufferUsageFlags::empty(),
-//             },
-//         )
-//     };
-//     match result {
-//         Ok(_) => (),
-//         Err(e) => return Err(e.into()),
-//     }
-//     Ok(device.check_errors()?)
-// }
+pub(crate) fn begin_secondary(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    render_pass: Option<&crate::RenderPass>,
+    one_time_submit: bool,
+) -> Result<(), crate::Error> {
+    #[cfg(feature = "logging")]
+    log::trace!("GPU: begin_command_buffer secondary");
+    // with dynamic rendering the attachment formats are inherited instead of the render pass
+    let color_formats = render_pass
+        .map(|p| p.colors.iter().map(|c| c.format.into()).collect::<Vec<vk::Format>>())
+        .unwrap_or_default();
+    let depth_format = render_pass.and_then(|p| p.depth).map(|d| d.format);
+    let rendering_info = vk::CommandBufferInheritanceRenderingInfo {
+        s_type: vk::StructureType::COMMAND_BUFFER_INHERITANCE_RENDERING_INFO,
+        p_next: ptr::null(),
+        flags: vk::RenderingFlags::empty(),
+        view_mask: 0,
+        color_attachment_count: color_formats.len() as u32,
+        p_color_attachment_formats: color_formats.as_ptr(),
+        depth_attachment_format: depth_format
+            .filter(|f| f.aspects().contains(crate::TextureAspects::DEPTH))
+            .map(|f| f.into())
+            .unwrap_or(vk::Format::UNDEFINED),
+        stencil_attachment_format: depth_format
+            .filter(|f| f.aspects().contains(crate::TextureAspects::STENCIL))
+            .map(|f| f.into())
+            .unwrap_or(vk::Format::UNDEFINED),
+        rasterization_samples: render_pass
+            .map(|p| p.samples.into())
+            .unwrap_or(vk::SampleCountFlags::TYPE_1),
+    };
+    let dynamic_rendering = device.dynamic_rendering.is_some() && render_pass.is_some();
+    let inheritance_info = vk::CommandBufferInheritanceInfo {
+        s_type: vk::StructureType::COMMAND_BUFFER_INHERITANCE_INFO,
+        p_next: if dynamic_rendering {
+            &rendering_info as *const _ as *const c_void
+        } else {
+            ptr::null()
+        },
+        render_pass: render_pass
+            .map(|p| **p.raw)
+            .unwrap_or(vk::RenderPass::null()),
+        subpass: 0,
+        framebuffer: vk::Framebuffer::null(),
+        occlusion_query_enable: vk::FALSE,
+        query_flags: vk::QueryControlFlags::empty(),
+        pipeline_statistics: vk::QueryPipelineStatisticFlags::empty(),
+    };
+    let mut flags = if one_time_submit {
+        vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT
+    } else {
+        vk::CommandBufferUsageFlags::empty()
+    };
+    if render_pass.is_some() {
+        flags |= vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE;
+    }
+    let result = unsafe {
+        device.begin_command_buffer(
+            command_buffer,
+            &vk::CommandBufferBeginInfo {
+                s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
+                p_next: ptr::null(),
+                p_inheritance_info: &inheritance_info,
+                flags,
+            },
+        )
+    };
+    match result {
+        Ok(_) => (),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(device.check_errors()?)
+}
 
 pub(crate) fn end_recording(
     command_buffer: vk::CommandBuffer,
@@ -623,6 +1151,7 @@ pub(crate) fn begin_graphics_pass<'a, B>(
     depth_attachment: Option<B>,
     pipeline: &crate::GraphicsPipeline,
     garbage: &mut super::Garbage,
+    end_transitions: &mut Vec<AttachmentTransition>,
 ) -> Result<Option<(vk::Semaphore, vk::Semaphore)>, crate::Error>
 where
     B: std::borrow::Borrow<crate::Attachment<'a>>,
@@ -636,9 +1165,21 @@ where
         resolve_attachments,
         depth_attachment,
         &pipeline.pass,
+        vk::SubpassContents::INLINE,
         garbage,
+        end_transitions,
     )?;
 
+    bind_graphics_pipeline(command_buffer, device, pipeline, garbage)?;
+    Ok(swapchain)
+}
+
+pub(crate) fn bind_graphics_pipeline(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    pipeline: &crate::GraphicsPipeline,
+    garbage: &mut super::Garbage,
+) -> Result<(), crate::Error> {
     garbage
         .pipeline_layouts
         .push(Arc::clone(&pipeline.layout.raw));
@@ -652,7 +1193,17 @@ where
         )
     };
     device.check_errors()?;
-    Ok(swapchain)
+    if let Some((front, back)) = &pipeline.stencil {
+        for (face, state) in [
+            (vk::StencilFaceFlags::FRONT, front),
+            (vk::StencilFaceFlags::BACK, back),
+        ] {
+            set_stencil_compare_mask(command_buffer, device, face, state.compare_mask)?;
+            set_stencil_write_mask(command_buffer, device, face, state.write_mask)?;
+            set_stencil_reference(command_buffer, device, face, state.reference)?;
+        }
+    }
+    set_scissor(command_buffer, device, 0, &pipeline.scissors)
 }
 
 pub(crate) fn begin_render_pass<'a, B>(
@@ -662,11 +1213,28 @@ pub(crate) fn begin_render_pass<'a, B>(
     resolve_attachments: &[B],
     depth_attachment: Option<B>,
     pass: &crate::RenderPass,
+    contents: vk::SubpassContents,
     garbage: &mut super::Garbage,
+    end_transitions: &mut Vec<AttachmentTransition>,
 ) -> Result<Option<(vk::Semaphore, vk::Semaphore)>, crate::Error>
 where
     B: std::borrow::Borrow<crate::Attachment<'a>>,
 {
+    if let Some(dynamic_rendering) = &device.dynamic_rendering {
+        return begin_rendering(
+            command_buffer,
+            device,
+            dynamic_rendering,
+            color_attachments,
+            resolve_attachments,
+            depth_attachment,
+            pass,
+            contents,
+            garbage,
+            end_transitions,
+        );
+    }
+
     garbage.render_passes.push(Arc::clone(&pass.raw));
 
     let (framebuffer_key, swapchain, extent) = framebuffer_key(
@@ -719,7 +1287,259 @@ where
                 clear_value_count: clear_values.len() as u32,
                 p_clear_values: clear_values.as_ptr(),
             },
-            vk::SubpassContents::INLINE,
+            contents,
+        );
+    }
+
+    device.check_errors()?;
+
+    Ok(swapchain)
+}
+
+/// A layout transition of an attachment made when dynamic rendering ends
+///
+/// Render pass objects transition attachments to their final layout implicitly,
+/// with dynamic rendering the transitions are recorded as barriers after vkCmdEndRendering
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AttachmentTransition {
+    pub image: vk::Image,
+    pub range: vk::ImageSubresourceRange,
+    pub old_layout: vk::ImageLayout,
+    pub new_layout: vk::ImageLayout,
+}
+
+const ATTACHMENT_STAGES: vk::PipelineStageFlags = vk::PipelineStageFlags::from_raw(
+    vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT.as_raw()
+        | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS.as_raw()
+        | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS.as_raw(),
+);
+
+fn attachment_transition(
+    view: &crate::TextureView,
+    aspects: crate::TextureAspects,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+) -> AttachmentTransition {
+    // views of single slices of 3D textures use the array layer as the slice but barriers cover the whole depth
+    let (base_array_layer, layer_count) = match view.texture.dimension {
+        crate::TextureDimension::D3(..) => (0, 1),
+        _ => (view.base_array_layer, view.array_layers),
+    };
+    AttachmentTransition {
+        image: **view.texture.raw,
+        range: vk::ImageSubresourceRange {
+            aspect_mask: aspects.into(),
+            base_mip_level: view.base_mip_level,
+            level_count: view.mip_levels,
+            base_array_layer,
+            layer_count,
+        },
+        old_layout,
+        new_layout,
+    }
+}
+
+fn record_transitions(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    transitions: &[AttachmentTransition],
+    src_stages: vk::PipelineStageFlags,
+    src_access: vk::AccessFlags,
+    dst_stages: vk::PipelineStageFlags,
+    dst_access: vk::AccessFlags,
+) {
+    let barriers = transitions
+        .iter()
+        .filter(|t| t.old_layout != t.new_layout)
+        .map(|t| vk::ImageMemoryBarrier {
+            s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+            p_next: ptr::null(),
+            src_access_mask: src_access,
+            dst_access_mask: dst_access,
+            old_layout: t.old_layout,
+            new_layout: t.new_layout,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: t.image,
+            subresource_range: t.range,
+        })
+        .collect::<Vec<_>>();
+    if barriers.len() == 0 {
+        return;
+    }
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            src_stages,
+            dst_stages,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &barriers,
+        )
+    }
+}
+
+/// Begin rendering to the attachments without a render pass or framebuffer
+///
+/// Attachments are transitioned from the initial layouts of pass before rendering,
+/// the transitions to the final layouts are pushed to end_transitions for [`end_render_pass`]
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdBeginRendering.html>
+fn begin_rendering<'a, B>(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    dynamic_rendering: &ash::extensions::khr::DynamicRendering,
+    color_attachments: &[B],
+    resolve_attachments: &[B],
+    depth_attachment: Option<B>,
+    pass: &crate::RenderPass,
+    contents: vk::SubpassContents,
+    garbage: &mut super::Garbage,
+    end_transitions: &mut Vec<AttachmentTransition>,
+) -> Result<Option<(vk::Semaphore, vk::Semaphore)>, crate::Error>
+where
+    B: std::borrow::Borrow<crate::Attachment<'a>>,
+{
+    // only used to keep the attachments alive and find the swapchain and extent
+    let (_, swapchain, extent) = framebuffer_key(
+        color_attachments,
+        resolve_attachments,
+        depth_attachment.as_ref(),
+        vk::RenderPass::null(),
+        garbage,
+    );
+
+    let color_layout = vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL;
+    let depth_layout = vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL;
+
+    let mut begin_transitions = Vec::new();
+    for (a, desc) in color_attachments.iter().zip(pass.colors.iter()) {
+        let view = a.borrow().view();
+        let aspects = crate::TextureAspects::COLOR;
+        begin_transitions.push(attachment_transition(view, aspects, desc.initial_layout.into(), color_layout));
+        end_transitions.push(attachment_transition(view, aspects, color_layout, desc.final_layout.into()));
+    }
+    for (a, desc) in resolve_attachments.iter().zip(pass.resolves.iter()) {
+        let view = a.borrow().view();
+        let aspects = crate::TextureAspects::COLOR;
+        begin_transitions.push(attachment_transition(view, aspects, desc.initial_layout.into(), color_layout));
+        end_transitions.push(attachment_transition(view, aspects, color_layout, desc.final_layout.into()));
+    }
+    if let (Some(a), Some(desc)) = (depth_attachment.as_ref(), pass.depth.as_ref()) {
+        let view = a.borrow().view();
+        let aspects = desc.format.aspects();
+        begin_transitions.push(attachment_transition(view, aspects, desc.initial_layout.into(), depth_layout));
+        end_transitions.push(attachment_transition(view, aspects, depth_layout, desc.final_layout.into()));
+    }
+
+    // the attachments could have been written by anything before the pass
+    record_transitions(
+        command_buffer,
+        device,
+        &begin_transitions,
+        vk::PipelineStageFlags::ALL_COMMANDS,
+        vk::AccessFlags::MEMORY_WRITE,
+        ATTACHMENT_STAGES,
+        vk::AccessFlags::COLOR_ATTACHMENT_READ
+            | vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+            | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+            | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+    );
+
+    let color_infos = color_attachments
+        .iter()
+        .zip(pass.colors.iter())
+        .enumerate()
+        .map(|(i, (a, desc))| {
+            let a = a.borrow();
+            let resolve = resolve_attachments.get(i).map(|r| r.borrow());
+            vk::RenderingAttachmentInfo {
+                s_type: vk::StructureType::RENDERING_ATTACHMENT_INFO,
+                p_next: ptr::null(),
+                image_view: **a.view().raw,
+                image_layout: color_layout,
+                resolve_mode: match resolve {
+                    Some(_) if desc.format.is_integer() => vk::ResolveModeFlags::SAMPLE_ZERO,
+                    Some(_) => vk::ResolveModeFlags::AVERAGE,
+                    None => vk::ResolveModeFlags::NONE,
+                },
+                resolve_image_view: resolve
+                    .map(|r| **r.view().raw)
+                    .unwrap_or(vk::ImageView::null()),
+                resolve_image_layout: if resolve.is_some() {
+                    color_layout
+                } else {
+                    vk::ImageLayout::UNDEFINED
+                },
+                load_op: desc.load.into(),
+                store_op: desc.store.into(),
+                clear_value: a.clear_value().into(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let depth_info = depth_attachment
+        .as_ref()
+        .zip(pass.depth.as_ref())
+        .map(|(a, desc)| vk::RenderingAttachmentInfo {
+            s_type: vk::StructureType::RENDERING_ATTACHMENT_INFO,
+            p_next: ptr::null(),
+            image_view: **a.borrow().view().raw,
+            image_layout: depth_layout,
+            resolve_mode: vk::ResolveModeFlags::NONE,
+            resolve_image_view: vk::ImageView::null(),
+            resolve_image_layout: vk::ImageLayout::UNDEFINED,
+            load_op: desc.load.into(),
+            store_op: desc.store.into(),
+            clear_value: a.borrow().clear_value().into(),
+        });
+    let aspects = pass.depth.map(|d| d.format.aspects()).unwrap_or(crate::TextureAspects::empty());
+    let p_depth_attachment = match &depth_info {
+        Some(d) if aspects.contains(crate::TextureAspects::DEPTH) => d as *const _,
+        _ => ptr::null(),
+    };
+    // formats with a stencil aspect load and store it the same as depth
+    let p_stencil_attachment = match &depth_info {
+        Some(d) if aspects.contains(crate::TextureAspects::STENCIL) => d as *const _,
+        _ => ptr::null(),
+    };
+
+    // render to every layer the attachments have in common
+    let layer_count = color_attachments
+        .iter()
+        .chain(resolve_attachments.iter())
+        .chain(depth_attachment.iter())
+        .map(|a| a.borrow().view().array_layers)
+        .min()
+        .unwrap_or(1);
+
+    let flags = if contents == vk::SubpassContents::SECONDARY_COMMAND_BUFFERS {
+        vk::RenderingFlags::CONTENTS_SECONDARY_COMMAND_BUFFERS
+    } else {
+        vk::RenderingFlags::empty()
+    };
+
+    unsafe {
+        dynamic_rendering.cmd_begin_rendering(
+            command_buffer,
+            &vk::RenderingInfo {
+                s_type: vk::StructureType::RENDERING_INFO,
+                p_next: ptr::null(),
+                flags,
+                render_area: vk::Rect2D {
+                    offset: vk::Offset2D { x: 0, y: 0 },
+                    extent: vk::Extent2D {
+                        width: extent.width,
+                        height: extent.height,
+                    },
+                },
+                layer_count,
+                view_mask: 0,
+                color_attachment_count: color_infos.len() as u32,
+                p_color_attachments: color_infos.as_ptr(),
+                p_depth_attachment,
+                p_stencil_attachment,
+            },
         );
     }
 
@@ -843,10 +1663,26 @@ pub(crate) fn raw_framebuffer(
 pub(crate) fn end_render_pass(
     command_buffer: vk::CommandBuffer,
     device: &crate::RawDevice,
+    end_transitions: &mut Vec<AttachmentTransition>,
 ) -> Result<(), crate::Error> {
     #[cfg(feature = "logging")]
     log::trace!("GPU: cmd_end_graphics_pass");
-    unsafe { device.cmd_end_render_pass(command_buffer) }
+    if let Some(dynamic_rendering) = &device.dynamic_rendering {
+        unsafe { dynamic_rendering.cmd_end_rendering(command_buffer) }
+        record_transitions(
+            command_buffer,
+            device,
+            end_transitions,
+            ATTACHMENT_STAGES,
+            vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+        );
+        end_transitions.clear();
+    } else {
+        unsafe { device.cmd_end_render_pass(command_buffer) }
+    }
     Ok(device.check_errors()?)
 }
 
@@ -952,6 +1788,74 @@ pub(crate) fn draw_indexed(
     Ok(device.check_errors()?)
 }
 
+pub(crate) fn set_scissor(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    first_scissor: u32,
+    scissors: &[crate::Scissor],
+) -> Result<(), crate::Error> {
+    #[cfg(feature = "logging")]
+    log::trace!(
+        "GPU: cmd_set_scissor first_scissor: {}, scissors: {:?}",
+        first_scissor,
+        scissors
+    );
+    let scissors = scissors
+        .iter()
+        .map(|s| (*s).into())
+        .collect::<Vec<vk::Rect2D>>();
+    unsafe { device.cmd_set_scissor(command_buffer, first_scissor, &scissors) };
+    Ok(device.check_errors()?)
+}
+
+pub(crate) fn set_stencil_reference(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    faces: vk::StencilFaceFlags,
+    reference: u32,
+) -> Result<(), crate::Error> {
+    #[cfg(feature = "logging")]
+    log::trace!(
+        "GPU: cmd_set_stencil_reference faces: {:?}, reference: {}",
+        faces,
+        reference
+    );
+    unsafe { device.cmd_set_stencil_reference(command_buffer, faces, reference) };
+    Ok(device.check_errors()?)
+}
+
+pub(crate) fn set_stencil_compare_mask(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    faces: vk::StencilFaceFlags,
+    mask: u32,
+) -> Result<(), crate::Error> {
+    #[cfg(feature = "logging")]
+    log::trace!(
+        "GPU: cmd_set_stencil_compare_mask faces: {:?}, mask: {}",
+        faces,
+        mask
+    );
+    unsafe { device.cmd_set_stencil_compare_mask(command_buffer, faces, mask) };
+    Ok(device.check_errors()?)
+}
+
+pub(crate) fn set_stencil_write_mask(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    faces: vk::StencilFaceFlags,
+    mask: u32,
+) -> Result<(), crate::Error> {
+    #[cfg(feature = "logging")]
+    log::trace!(
+        "GPU: cmd_set_stencil_write_mask faces: {:?}, mask: {}",
+        faces,
+        mask
+    );
+    unsafe { device.cmd_set_stencil_write_mask(command_buffer, faces, mask) };
+    Ok(device.check_errors()?)
+}
+
 pub(crate) fn dispatch(
     command_buffer: vk::CommandBuffer,
     device: &crate::RawDevice,
@@ -1026,22 +1930,23 @@ where
     Ok(device.check_errors()?)
 }
 
-// pub(crate) fn execute_secondary(
-//     command_buffer: vk::CommandBuffer,
-//     device: &crate::RawDevice,
-//     secondary_buffer: vk::CommandBuffer,
-// ) -> Result<(), crate::Error> {
-//     #[cfg(feature = "logging")]
-//     log::trace!("GPU: cmd_execute_secondary {:?}", secondary_buffer);
-//     unsafe { device.cmd_execute_commands(command_buffer, &[secondary_buffer]) };
-//     Ok(device.check_errors()?)
-// }
+pub(crate) fn execute_secondary(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    secondary_buffer: vk::CommandBuffer,
+) -> Result<(), crate::Error> {
+    #[cfg(feature = "logging")]
+    log::trace!("GPU: cmd_execute_secondary {:?}", secondary_buffer);
+    unsafe { device.cmd_execute_commands(command_buffer, &[secondary_buffer]) };
+    Ok(device.check_errors()?)
+}
 
 pub(crate) fn bind_descriptors<G>(
     command_buffer: vk::CommandBuffer,
     device: &crate::RawDevice,
     first_location: u32,
     groups: &[G],
+    dynamic_offsets: &[u32],
     bind_point: crate::PipelineBindPoint,
     layout: &crate::PipelineLayout,
     garbage: &mut super::Garbage,
@@ -1051,6 +1956,14 @@ where
 {
     #[cfg(feature = "logging")]
     log::trace!("GPU: cmd_set_descriptors");
+    let dynamic_count = groups.iter().map(|g| g.borrow().dynamic_count).sum::<u32>();
+    if dynamic_count as usize != dynamic_offsets.len() {
+        panic!(
+            "ERROR: Attempt to bind descriptor sets with {} dynamic descriptors with {} dynamic offsets",
+            dynamic_count,
+            dynamic_offsets.len()
+        );
+    }
     let descriptor_sets = groups
         .iter()
         .map(|g| {
@@ -1069,6 +1982,9 @@ where
             for sampler in &*set.samplers {
                 garbage.samplers.push(Arc::clone(&*sampler.raw));
             }
+            for view in &*set.buffer_views {
+                garbage.buffer_views.push(Arc::clone(&*view.raw));
+            }
             garbage
                 .descriptor_layouts
                 .push(Arc::clone(&*g.borrow().layout));
@@ -1083,7 +1999,7 @@ where
             **layout.raw,
             first_location,
             &descriptor_sets,
-            &[],
+            dynamic_offsets,
         )
     };
     Ok(device.check_errors()?)
@@ -1138,12 +2054,76 @@ pub(crate) fn reset_time_query(
     Ok(device.check_errors()?)
 }
 
+pub(crate) fn begin_debug_label(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    name: &str,
+    color: [f32; 4],
+) -> Result<(), crate::Error> {
+    #[cfg(feature = "logging")]
+    log::trace!("GPU: cmd_begin_debug_utils_label {}", name);
+    if let Some(loader) = &device.debug_loader {
+        let c = crate::RawDevice::debug_cstring(name);
+        unsafe {
+            loader.cmd_begin_debug_utils_label(
+                command_buffer,
+                &vk::DebugUtilsLabelEXT {
+                    s_type: vk::StructureType::DEBUG_UTILS_LABEL_EXT,
+                    p_next: ptr::null(),
+                    p_label_name: c.as_ptr(),
+                    color,
+                },
+            )
+        }
+    }
+    Ok(device.check_errors()?)
+}
+
+pub(crate) fn end_debug_label(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+) -> Result<(), crate::Error> {
+    #[cfg(feature = "logging")]
+    log::trace!("GPU: cmd_end_debug_utils_label");
+    if let Some(loader) = &device.debug_loader {
+        unsafe { loader.cmd_end_debug_utils_label(command_buffer) }
+    }
+    Ok(device.check_errors()?)
+}
+
+pub(crate) fn insert_debug_label(
+    command_buffer: vk::CommandBuffer,
+    device: &crate::RawDevice,
+    name: &str,
+    color: [f32; 4],
+) -> Result<(), crate::Error> {
+    #[cfg(feature = "logging")]
+    log::trace!("GPU: cmd_insert_debug_utils_label {}", name);
+    if let Some(loader) = &device.debug_loader {
+        let c = crate::RawDevice::debug_cstring(name);
+        unsafe {
+            loader.cmd_insert_debug_utils_label(
+                command_buffer,
+                &vk::DebugUtilsLabelEXT {
+                    s_type: vk::StructureType::DEBUG_UTILS_LABEL_EXT,
+                    p_next: ptr::null(),
+                    p_label_name: c.as_ptr(),
+                    color,
+                },
+            )
+        }
+    }
+    Ok(device.check_errors()?)
+}
+
 pub(crate) fn submit(
     device: &crate::RawDevice,
     queue: vk::Queue,
     command_buffer: vk::CommandBuffer,
     semaphore: &Arc<vk::Semaphore>,
     swapchain_sync: Option<(vk::Semaphore, vk::Semaphore)>,
+    external_wait: &[(vk::Semaphore, vk::PipelineStageFlags)],
+    external_signal: &[vk::Semaphore],
     fence: vk::Fence,
     garbage: &mut super::Garbage,
 ) -> Result<(), crate::Error> {
@@ -1171,26 +2151,30 @@ pub(crate) fn submit(
     }
     semaphores.insert(std::thread::current().id(), Arc::clone(semaphore));
 
-    let wait_dst_stage_mask = if wait_semaphores.len() == 0 {
-        [vk::PipelineStageFlags::empty(); 2]
+    let mut wait_dst_stage_mask = if wait_semaphores.len() == 0 {
+        vec![]
     } else if wait_semaphores.len() == 1 {
-        [
-            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
-            vk::PipelineStageFlags::empty(),
-        ]
+        vec![vk::PipelineStageFlags::BOTTOM_OF_PIPE]
     } else {
-        [
+        vec![
             vk::PipelineStageFlags::BOTTOM_OF_PIPE,
             vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
         ]
     };
+    for &(semaphore, stages) in external_wait {
+        wait_semaphores.push(semaphore);
+        wait_dst_stage_mask.push(stages);
+    }
+    signal_semaphores.extend_from_slice(external_signal);
+    // keep a valid pointer even if nothing is waited on
+    wait_dst_stage_mask.push(vk::PipelineStageFlags::empty());
 
     let submit_info = vk::SubmitInfo {
         s_type: vk::StructureType::SUBMIT_INFO,
         p_next: ptr::null(),
         wait_semaphore_count: wait_semaphores.len() as _,
         p_wait_semaphores: wait_semaphores.as_ptr(),
-        p_wait_dst_stage_mask: &wait_dst_stage_mask[0],
+        p_wait_dst_stage_mask: wait_dst_stage_mask.as_ptr(),
         signal_semaphore_count: signal_semaphores.len() as _,
         p_signal_semaphores: signal_semaphores.as_ptr(),
         command_buffer_count: 1,
@@ -0,0 +1,98 @@
+//! [`SecondaryCommandBuffer`]'s record commands that are executed by other command buffers
+
+use super::raw;
+
+/// A command buffer that is executed by a primary [`crate::CommandBuffer`] instead of being submitted
+///
+/// Each secondary command buffer has its own command pool so different secondary command buffers
+/// can be recorded on different threads at the same time. Derefs to [`crate::CommandBuffer`] for recording commands
+/// but will panic if used to begin, submit or begin a render pass
+/// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkAllocateCommandBuffers.html>
+#[derive(Debug)]
+pub struct SecondaryCommandBuffer {
+    pub(crate) inner: crate::CommandBuffer,
+}
+
+// Command buffers are only not Send because of the swapchains they reference from render passes
+// and the state shared with a CommandPool, secondary command buffers panic if a render pass is begun
+// so never reference a swapchain and always own their pool
+unsafe impl Send for SecondaryCommandBuffer {}
+
+impl SecondaryCommandBuffer {
+    /// Create a new secondary command buffer for primary command buffers that submit to the main queue of the device
+    pub fn new(device: &crate::Device, name: Option<String>) -> Result<Self, crate::Error> {
+        Self::with_queue(device, device.queue(crate::QueueKind::Graphics), name)
+    }
+
+    /// Create a new secondary command buffer for primary command buffers that submit to the queue supplied
+    pub fn with_queue(
+        device: &crate::Device,
+        queue: crate::Queue,
+        name: Option<String>,
+    ) -> Result<Self, crate::Error> {
+        Ok(Self {
+            inner: crate::CommandBuffer::create(device, queue, true, name)?,
+        })
+    }
+
+    fn prepare(&mut self) -> Result<(), crate::Error> {
+        unsafe {
+            self.inner.garbage.clean(&self.inner.device);
+        }
+        self.inner.version += 1;
+        self.inner.reset_raw()
+    }
+
+    /// Begin recording commands outside of a render pass
+    ///
+    /// Any primary command buffers that executed self must have completed before calling
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkBeginCommandBuffer.html>
+    pub fn begin(&mut self, one_time_submit: bool) -> Result<(), crate::Error> {
+        self.prepare()?;
+        raw::begin_secondary(self.inner.buffer, &self.inner.device, None, one_time_submit)
+    }
+
+    /// Begin recording commands inside a render pass and bind the pipeline
+    ///
+    /// The primary command buffer must execute self between [`crate::CommandBuffer::begin_secondary_pass`]
+    /// and [`crate::CommandBuffer::end_graphics_pass`] with a render pass compatible with the pipeline's.
+    /// Any primary command buffers that executed self must have completed before calling
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/VkCommandBufferInheritanceInfo.html>
+    pub fn begin_graphics_pass(
+        &mut self,
+        pipeline: &crate::GraphicsPipeline,
+        one_time_submit: bool,
+    ) -> Result<(), crate::Error> {
+        self.prepare()?;
+        self.inner
+            .garbage
+            .render_passes
+            .push(std::sync::Arc::clone(&pipeline.pass.raw));
+        raw::begin_secondary(
+            self.inner.buffer,
+            &self.inner.device,
+            Some(&pipeline.pass),
+            one_time_submit,
+        )?;
+        raw::bind_graphics_pipeline(
+            self.inner.buffer,
+            &self.inner.device,
+            pipeline,
+            &mut self.inner.garbage,
+        )
+    }
+}
+
+impl std::ops::Deref for SecondaryCommandBuffer {
+    type Target = crate::CommandBuffer;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl std::ops::DerefMut for SecondaryCommandBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
@@ -6,13 +6,12 @@ use ash::vk;
 use std::ptr;
 
 pub use vk::FormatFeatureFlags;
-pub use vk::PhysicalDeviceLimits as DeviceLimits;
 pub use vk::PhysicalDeviceMemoryProperties as MemoryProperties;
 pub use vk::SampleCountFlags;
 
 bitflags::bitflags! {
     /// Optional features that a device can support
-    pub struct DeviceFeatures: u32 {
+    pub struct DeviceFeatures: u64 {
         /// Device supports graphics operations
         const GRAPHICS              = 0b000000000000000000000000001;
         /// Device supports compute operations
@@ -52,6 +51,65 @@ bitflags::bitflags! {
         const VARIABLE_RATE_SHADING = 0b000000000100000000000000000;
         /// Allows for use of TimeQueries
         const TIME_QUERIES          = 0b000000001000000000000000000;
+        /// Allows different blend states for each color attachment
+        const INDEPENDENT_BLEND     = 0b000000010000000000000000000;
+        /// Allows blend operations that take two sources
+        const DUAL_SRC_BLEND        = 0b000000100000000000000000000;
+        /// Allows the use of logic operations when writing to color attachments
+        const LOGIC_OP              = 0b000001000000000000000000000;
+        /// Allows indirect draws with a draw count greater than 1
+        const MULTI_DRAW_INDIRECT   = 0b000010000000000000000000000;
+        /// Allows indirect draws to have a first instance other than 0
+        const DRAW_INDIRECT_FIRST_INSTANCE = 0b000100000000000000000000000;
+        /// Allows the full range of 32 bit indices to be used
+        const FULL_DRAW_INDEX_UINT32 = 0b001000000000000000000000000;
+        /// Allows depth bias to be clamped
+        const DEPTH_BIAS_CLAMP      = 0b010000000000000000000000000;
+        /// Allows the use of depth bounds testing
+        const DEPTH_BOUNDS          = 0b100000000000000000000000000;
+        /// Allows more than one viewport to be used
+        const MULTI_VIEWPORT        = 0b1000000000000000000000000000;
+        /// Allows the use of BC compressed texture formats
+        const TEXTURE_COMPRESSION_BC = 0b10000000000000000000000000000;
+        /// Allows the use of ETC2 and EAC compressed texture formats
+        const TEXTURE_COMPRESSION_ETC2 = 0b100000000000000000000000000000;
+        /// Allows the use of ASTC LDR compressed texture formats
+        const TEXTURE_COMPRESSION_ASTC_LDR = 0b1000000000000000000000000000000;
+        /// Allows the use of clip distances in shaders
+        const SHADER_CLIP_DISTANCE  = 0b10000000000000000000000000000000;
+        /// Allows the use of cull distances in shaders
+        const SHADER_CULL_DISTANCE  = 0b100000000000000000000000000000000;
+        /// Allows the use of pipeline statistics queries
+        const PIPELINE_STATISTICS_QUERY = 0b1000000000000000000000000000000000;
+        /// Allows the use of extended formats for storage images
+        const STORAGE_IMAGE_EXTENDED_FORMATS = 0b10000000000000000000000000000000000;
+        /// Allows storage images to be written to without specifying a format
+        const STORAGE_IMAGE_WRITE_NO_FORMAT = 0b100000000000000000000000000000000000;
+        /// Allows samplers to return the min or max of texels instead of a weighted average
+        /// (VK_EXT_sampler_filter_minmax)
+        const SAMPLER_REDUCTION     = 0b1000000000000000000000000000000000000;
+        /// Allows 16 bit values in storage and uniform buffers
+        const STORAGE_16_BIT        = 0b10000000000000000000000000000000000000;
+        /// Allows usage of 16 bit floating point variables in shaders
+        const SHADER_FLOAT_16       = 0b100000000000000000000000000000000000000;
+        /// Allows buffer and texture memory to be shared with other apis
+        const EXTERNAL_MEMORY       = 0b1000000000000000000000000000000000000000;
+        /// Allows semaphores to be shared with other apis
+        const EXTERNAL_SEMAPHORE    = 0b10000000000000000000000000000000000000000;
+        /// Allows arrays of sampled textures to be indexed with values that differ between invocations
+        const DESCRIPTOR_INDEXING   = 0b100000000000000000000000000000000000000000;
+        /// Allows single depth slices of 3D textures to be used as attachments
+        /// (Vulkan 1.1 or VK_KHR_maintenance1)
+        const TEXTURE_3D_SLICES     = 0b1000000000000000000000000000000000000000000;
+        /// Allows sampling multi-planar YCbCr textures such as video frames with [`crate::SamplerYcbcrConversion`]
+        /// (Vulkan 1.1)
+        const SAMPLER_YCBCR_CONVERSION = 0b10000000000000000000000000000000000000000000;
+        /// Graphics passes are recorded without render pass or framebuffer objects
+        /// (Vulkan 1.3 or VK_KHR_dynamic_rendering)
+        const DYNAMIC_RENDERING     = 0b100000000000000000000000000000000000000000000;
+        /// Allows waiting until presented frames are shown with [`crate::Swapchain::wait_for_present`]
+        /// (VK_KHR_present_id and VK_KHR_present_wait)
+        const PRESENT_WAIT          = 0b1000000000000000000000000000000000000000000000;
 
         /// Device supports all types of operations
         const BASE = Self::GRAPHICS.bits | Self::COMPUTE.bits | Self::TRANSFER.bits;
@@ -80,14 +138,95 @@ impl Into<vk::PhysicalDeviceFeatures> for DeviceFeatures {
             shader_int16: self.contains(DeviceFeatures::SHADER_INT_16).into(),
             depth_clamp: self.contains(DeviceFeatures::DEPTH_CLAMP).into(),
             sample_rate_shading: self.contains(DeviceFeatures::VARIABLE_RATE_SHADING).into(),
+            independent_blend: self.contains(DeviceFeatures::INDEPENDENT_BLEND).into(),
+            dual_src_blend: self.contains(DeviceFeatures::DUAL_SRC_BLEND).into(),
+            logic_op: self.contains(DeviceFeatures::LOGIC_OP).into(),
+            multi_draw_indirect: self.contains(DeviceFeatures::MULTI_DRAW_INDIRECT).into(),
+            draw_indirect_first_instance: self
+                .contains(DeviceFeatures::DRAW_INDIRECT_FIRST_INSTANCE)
+                .into(),
+            full_draw_index_uint32: self.contains(DeviceFeatures::FULL_DRAW_INDEX_UINT32).into(),
+            depth_bias_clamp: self.contains(DeviceFeatures::DEPTH_BIAS_CLAMP).into(),
+            depth_bounds: self.contains(DeviceFeatures::DEPTH_BOUNDS).into(),
+            multi_viewport: self.contains(DeviceFeatures::MULTI_VIEWPORT).into(),
+            texture_compression_bc: self.contains(DeviceFeatures::TEXTURE_COMPRESSION_BC).into(),
+            texture_compression_etc2: self
+                .contains(DeviceFeatures::TEXTURE_COMPRESSION_ETC2)
+                .into(),
+            texture_compression_astc_ldr: self
+                .contains(DeviceFeatures::TEXTURE_COMPRESSION_ASTC_LDR)
+                .into(),
+            shader_clip_distance: self.contains(DeviceFeatures::SHADER_CLIP_DISTANCE).into(),
+            shader_cull_distance: self.contains(DeviceFeatures::SHADER_CULL_DISTANCE).into(),
+            pipeline_statistics_query: self
+                .contains(DeviceFeatures::PIPELINE_STATISTICS_QUERY)
+                .into(),
+            shader_storage_image_extended_formats: self
+                .contains(DeviceFeatures::STORAGE_IMAGE_EXTENDED_FORMATS)
+                .into(),
+            shader_storage_image_write_without_format: self
+                .contains(DeviceFeatures::STORAGE_IMAGE_WRITE_NO_FORMAT)
+                .into(),
             shader_uniform_buffer_array_dynamic_indexing: vk::TRUE,
             shader_storage_buffer_array_dynamic_indexing: vk::TRUE,
             shader_storage_image_array_dynamic_indexing: vk::TRUE,
+            shader_sampled_image_array_dynamic_indexing: vk::TRUE,
             ..Default::default()
         }
     }
 }
 
+impl From<vk::PhysicalDeviceFeatures> for DeviceFeatures {
+    /// The core features that are supported, features that need extensions are not included
+    fn from(f: vk::PhysicalDeviceFeatures) -> Self {
+        let mut s = Self::BASE;
+        let mut set = |flag: Self, supported: vk::Bool32| {
+            if supported == vk::TRUE {
+                s |= flag;
+            }
+        };
+        set(Self::TESSELLATION_SHADER, f.tessellation_shader);
+        set(Self::GEOMETRY_SHADER, f.geometry_shader);
+        set(Self::CUBE_TEXTURE_ARRAY, f.image_cube_array);
+        set(Self::NON_SOLID, f.fill_mode_non_solid);
+        set(Self::WIDE_LINES, f.wide_lines);
+        set(Self::LARGE_POINTS, f.large_points);
+        set(Self::VERTEX_ATOMICS, f.vertex_pipeline_stores_and_atomics);
+        set(Self::FRAGMENT_ATOMICS, f.fragment_stores_and_atomics);
+        set(Self::SAMPLER_ANISOTROPY, f.sampler_anisotropy);
+        set(Self::MULTISAMPLE_STORAGE, f.shader_storage_image_multisample);
+        set(Self::SHADER_FLOAT_64, f.shader_float64);
+        set(Self::SHADER_INT_64, f.shader_int64);
+        set(Self::SHADER_INT_16, f.shader_int16);
+        set(Self::DEPTH_CLAMP, f.depth_clamp);
+        set(Self::VARIABLE_RATE_SHADING, f.sample_rate_shading);
+        set(Self::INDEPENDENT_BLEND, f.independent_blend);
+        set(Self::DUAL_SRC_BLEND, f.dual_src_blend);
+        set(Self::LOGIC_OP, f.logic_op);
+        set(Self::MULTI_DRAW_INDIRECT, f.multi_draw_indirect);
+        set(Self::DRAW_INDIRECT_FIRST_INSTANCE, f.draw_indirect_first_instance);
+        set(Self::FULL_DRAW_INDEX_UINT32, f.full_draw_index_uint32);
+        set(Self::DEPTH_BIAS_CLAMP, f.depth_bias_clamp);
+        set(Self::DEPTH_BOUNDS, f.depth_bounds);
+        set(Self::MULTI_VIEWPORT, f.multi_viewport);
+        set(Self::TEXTURE_COMPRESSION_BC, f.texture_compression_bc);
+        set(Self::TEXTURE_COMPRESSION_ETC2, f.texture_compression_etc2);
+        set(Self::TEXTURE_COMPRESSION_ASTC_LDR, f.texture_compression_astc_ldr);
+        set(Self::SHADER_CLIP_DISTANCE, f.shader_clip_distance);
+        set(Self::SHADER_CULL_DISTANCE, f.shader_cull_distance);
+        set(Self::PIPELINE_STATISTICS_QUERY, f.pipeline_statistics_query);
+        set(
+            Self::STORAGE_IMAGE_EXTENDED_FORMATS,
+            f.shader_storage_image_extended_formats,
+        );
+        set(
+            Self::STORAGE_IMAGE_WRITE_NO_FORMAT,
+            f.shader_storage_image_write_without_format,
+        );
+        s
+    }
+}
+
 /// Types of Physical devices
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum DeviceType {
@@ -103,6 +242,48 @@ pub enum DeviceType {
     DiscreteGpu,
 }
 
+bitflags::bitflags! {
+    /// The types of operations that a family of queues supports
+    pub struct QueueFlags: u32 {
+        /// Queues in the family support graphics operations
+        const GRAPHICS = 0b001;
+        /// Queues in the family support compute operations
+        const COMPUTE  = 0b010;
+        /// Queues in the family support transfer operations
+        const TRANSFER = 0b100;
+    }
+}
+
+impl From<vk::QueueFlags> for QueueFlags {
+    fn from(f: vk::QueueFlags) -> Self {
+        let mut result = QueueFlags::empty();
+        if f.contains(vk::QueueFlags::GRAPHICS) {
+            result |= QueueFlags::GRAPHICS;
+        }
+        if f.contains(vk::QueueFlags::COMPUTE) {
+            result |= QueueFlags::COMPUTE;
+        }
+        if f.contains(vk::QueueFlags::TRANSFER) {
+            result |= QueueFlags::TRANSFER;
+        }
+        result
+    }
+}
+
+/// The kind of work a [`crate::Queue`] is requested for
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum QueueKind {
+    /// The main queue of the device, supports all operations requested in [`crate::DeviceDesc::features`]
+    /// and presenting to any compatible surfaces
+    Graphics,
+    /// A queue for compute work, if possible from a family without graphics support
+    /// so that it can run asynchronously to the main queue
+    Compute,
+    /// A queue for transfer work, if possible from a family without graphics or compute support
+    /// so that uploads can run asynchronously to the main queue
+    Transfer,
+}
+
 /// Methods on how images can be presented to the screen
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
 pub enum PresentMode {
@@ -661,6 +842,8 @@ bitflags::bitflags! {
         /// Mesh shader
         #[cfg(feature = "mesh")]
         const MESH_SHADER              = 0b100000000000000000000;
+        /// Host (cpu) access to device memory
+        const HOST                     = 0b1000000000000000000000;
     }
 }
 
@@ -715,6 +898,9 @@ impl Into<vk::PipelineStageFlags> for PipelineStageFlags {
         if self.contains(Self::ALL_COMMANDS) {
             result |= vk::PipelineStageFlags::ALL_COMMANDS
         }
+        if self.contains(Self::HOST) {
+            result |= vk::PipelineStageFlags::HOST
+        }
         result
     }
 }
@@ -770,6 +956,9 @@ impl From<vk::PipelineStageFlags> for PipelineStageFlags {
         if p.contains(vk::PipelineStageFlags::ALL_COMMANDS) {
             result |= Self::ALL_COMMANDS;
         }
+        if p.contains(vk::PipelineStageFlags::HOST) {
+            result |= Self::HOST;
+        }
         result
     }
 }
@@ -1040,6 +1229,18 @@ impl BlendState {
         alpha_blend_op: BlendOp::Add,
     };
 
+    /// Alpha blending where the color has already been multiplied by alpha
+    pub const PREMULTIPLIED_ALPHA: Self = Self {
+        blend_enable: true,
+        write_mask: ColorMask::all(),
+        src_blend: BlendFactor::One,
+        dst_blend: BlendFactor::OneMinusSrcAlpha,
+        blend_op: BlendOp::Add,
+        src_alpha_blend: BlendFactor::OneMinusDstAlpha,
+        dst_alpha_blend: BlendFactor::One,
+        alpha_blend_op: BlendOp::Add,
+    };
+
     /// Alpha blending based on the dst
     pub const DST_ALPHA: Self = Self {
         blend_enable: true,
@@ -1171,6 +1372,47 @@ impl Into<vk::Viewport> for Viewport {
     }
 }
 
+/// A rectangle of an attachment outside of which fragments are discarded
+///
+/// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/vkCmdSetScissor.html>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Scissor {
+    #[allow(missing_docs)]
+    pub x: i32,
+    #[allow(missing_docs)]
+    pub y: i32,
+    #[allow(missing_docs)]
+    pub width: u32,
+    #[allow(missing_docs)]
+    pub height: u32,
+}
+
+impl From<Viewport> for Scissor {
+    fn from(v: Viewport) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width: v.width,
+            height: v.height,
+        }
+    }
+}
+
+impl Into<vk::Rect2D> for Scissor {
+    fn into(self) -> vk::Rect2D {
+        vk::Rect2D {
+            offset: vk::Offset2D {
+                x: self.x,
+                y: self.y,
+            },
+            extent: vk::Extent2D {
+                width: self.width,
+                height: self.height,
+            },
+        }
+    }
+}
+
 /// Decides how verties should be interpreted
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
 pub enum PrimitiveTopology {
@@ -1375,14 +1617,38 @@ pub struct StencilState {
     pub depth_fail_op: StencilOp,
     /// How to compare the stencil state
     pub compare: CompareOp,
-    /// The value to compare to
+    /// The bits of the reference and stencil values that are compared
     pub compare_mask: u32,
-    /// The value to write when replacing
+    /// The bits of the stencil value that are written
     pub write_mask: u32,
-    /// idk
+    /// The value compared against and written when replacing
+    ///
+    /// compare_mask, write_mask and reference are set when the pipeline is bound and can be changed
+    /// with [`crate::CommandBuffer::set_stencil_reference`] and similar
     pub reference: u32,
 }
 
+/// Which faces to change the stencil state of
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum StencilFace {
+    /// Only front facing polygons
+    Front,
+    /// Only back facing polygons
+    Back,
+    /// Both front and back facing polygons
+    Both,
+}
+
+impl Into<vk::StencilFaceFlags> for StencilFace {
+    fn into(self) -> vk::StencilFaceFlags {
+        match self {
+            Self::Front => vk::StencilFaceFlags::FRONT,
+            Self::Back => vk::StencilFaceFlags::BACK,
+            Self::Both => vk::StencilFaceFlags::FRONT_AND_BACK,
+        }
+    }
+}
+
 /// Describes how a [`crate::GraphicsPipeline`] performs depth testing
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct DepthState {
@@ -1432,6 +1698,18 @@ impl DepthStencilState {
     pub fn default_depth() -> Self {
         Self::depth(true, true, CompareOp::LessEqual)
     }
+
+    /// Create a DepthStencilState with only stencil state that is the same for front and back faces
+    ///
+    /// For stencil only passes the depth attachment can be a [`crate::Format::Stencil8Uint`] texture,
+    /// formats with a stencil aspect load and store stencil with the same ops as depth
+    pub fn stencil(state: StencilState) -> Self {
+        Self {
+            depth: None,
+            stencil_front: Some(state),
+            stencil_back: Some(state),
+        }
+    }
 }
 
 impl Default for DepthStencilState {
@@ -1725,6 +2003,19 @@ pub enum DescriptorLayoutEntryType {
         /// If the buffer is read only
         read_only: bool,
     },
+    /// At this location shaders should accept a uniform buffer whose offset
+    /// is supplied when the descriptor set is bound
+    ///
+    /// Looks the same as [`DescriptorLayoutEntryType::UniformBuffer`] in glsl
+    UniformBufferDynamic,
+    /// At this location shaders should accept a storage buffer whose offset
+    /// is supplied when the descriptor set is bound
+    ///
+    /// Looks the same as [`DescriptorLayoutEntryType::StorageBuffer`] in glsl
+    StorageBufferDynamic {
+        /// If the buffer is read only
+        read_only: bool,
+    },
     /// At this location shaders should accept a sampled texture
     ///
     /// In glsl looks like
@@ -1748,6 +2039,29 @@ pub enum DescriptorLayoutEntryType {
     /// In glsl looks like
     /// `layout(set = _, binding = _) uniform sampler u_samper`
     Sampler,
+    /// At this location shaders should accept a read only buffer of formatted texels
+    ///
+    /// In glsl looks like
+    /// `layout(set = _, binding = _) uniform samplerBuffer u_texels;`
+    UniformTexelBuffer,
+    /// At this location shaders should accept a buffer of formatted texels that can be written to
+    ///
+    /// In glsl looks like
+    /// `layout(set = _, binding = _, r32f) uniform imageBuffer u_texels;`
+    StorageTexelBuffer {
+        /// If the buffer is read only
+        read_only: bool,
+    },
+}
+
+impl DescriptorLayoutEntryType {
+    /// Returns true if an offset for this entry has to be supplied when binding
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            Self::UniformBufferDynamic | Self::StorageBufferDynamic { .. } => true,
+            _ => false,
+        }
+    }
 }
 
 impl Into<vk::DescriptorType> for DescriptorLayoutEntryType {
@@ -1755,10 +2069,14 @@ impl Into<vk::DescriptorType> for DescriptorLayoutEntryType {
         match self {
             Self::UniformBuffer => vk::DescriptorType::UNIFORM_BUFFER,
             Self::StorageBuffer { .. } => vk::DescriptorType::STORAGE_BUFFER,
+            Self::UniformBufferDynamic => vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+            Self::StorageBufferDynamic { .. } => vk::DescriptorType::STORAGE_BUFFER_DYNAMIC,
             Self::SampledTexture => vk::DescriptorType::SAMPLED_IMAGE,
             Self::StorageTexture { .. } => vk::DescriptorType::STORAGE_IMAGE,
             Self::CombinedTextureSampler => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
             Self::Sampler => vk::DescriptorType::SAMPLER,
+            Self::UniformTexelBuffer => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+            Self::StorageTexelBuffer { .. } => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
         }
     }
 }
@@ -1804,6 +2122,10 @@ pub enum DescriptorSetEntry<'a> {
             )],
         >,
     ),
+    /// write a texel buffer to this binding
+    TexelBuffer(Cow<'a, crate::BufferView>),
+    /// write an array of texel buffers to this binding
+    TexelBufferArray(Cow<'a, [Cow<'a, crate::BufferView>]>),
 }
 
 impl<'a> DescriptorSetEntry<'a> {
@@ -1849,6 +2171,14 @@ impl<'a> DescriptorSetEntry<'a> {
                         .collect(),
                 )
             }
+            DescriptorSetEntry::TexelBuffer(v) => {
+                DescriptorSetEntry::TexelBuffer(Cow::Owned(v.into_owned()))
+            }
+            DescriptorSetEntry::TexelBufferArray(a) => DescriptorSetEntry::TexelBufferArray(
+                a.into_iter()
+                    .map(|v| Cow::Owned(v.clone().into_owned()))
+                    .collect(),
+            ),
         }
     }
 
@@ -1894,6 +2224,14 @@ impl<'a> DescriptorSetEntry<'a> {
                         .collect(),
                 )
             }
+            DescriptorSetEntry::TexelBuffer(v) => {
+                DescriptorSetEntry::TexelBuffer(Cow::Owned(v.clone().into_owned()))
+            }
+            DescriptorSetEntry::TexelBufferArray(a) => DescriptorSetEntry::TexelBufferArray(
+                a.iter()
+                    .map(|v| Cow::Owned(v.clone().into_owned()))
+                    .collect(),
+            ),
         }
     }
 
@@ -2027,6 +2365,32 @@ impl<'a> DescriptorSetEntry<'a> {
             .collect::<Vec<_>>();
         Self::CombinedTextureSamplerArray(Cow::Owned(result))
     }
+
+    /// Create a texel buffer entry from a reference to a buffer view
+    #[inline]
+    pub fn texel_buffer_ref(view: &'a crate::BufferView) -> Self {
+        Self::TexelBuffer(Cow::Borrowed(view))
+    }
+
+    /// Create a texel buffer entry from a buffer view
+    #[inline]
+    pub fn texel_buffer_owned(view: crate::BufferView) -> Self {
+        Self::TexelBuffer(Cow::Owned(view))
+    }
+
+    /// Create a texel buffer array entry from references to buffer views
+    #[inline]
+    pub fn texel_buffer_array_ref(views: &[&'a crate::BufferView]) -> Self {
+        let views = views.iter().map(|&v| Cow::Borrowed(v)).collect::<Vec<_>>();
+        Self::TexelBufferArray(Cow::Owned(views))
+    }
+
+    /// Create a texel buffer array entry from buffer views
+    #[inline]
+    pub fn texel_buffer_array_owned(views: Vec<crate::BufferView>) -> Self {
+        let views = views.into_iter().map(|v| Cow::Owned(v)).collect::<Vec<_>>();
+        Self::TexelBufferArray(Cow::Owned(views))
+    }
 }
 
 bitflags::bitflags! {
@@ -2046,6 +2410,12 @@ bitflags::bitflags! {
         const INDEX       = 0b00100000;
         #[cfg(feature = "ray")]
         const RAY_TRACING = 0b01000000;
+        /// Allows the buffer to be used as a uniform texel buffer through a [`crate::BufferView`]
+        const UNIFORM_TEXEL = 0b10000000;
+        /// Allows the buffer to be used as a storage texel buffer through a [`crate::BufferView`]
+        const STORAGE_TEXEL = 0b100000000;
+        /// Allows the buffer to be used as the source of indirect draw and dispatch commands
+        const INDIRECT = 0b1000000000;
     }
 }
 
@@ -2070,6 +2440,15 @@ impl Into<vk::BufferUsageFlags> for BufferUsage {
         if self.contains(BufferUsage::INDEX) {
             result |= vk::BufferUsageFlags::INDEX_BUFFER;
         }
+        if self.contains(BufferUsage::UNIFORM_TEXEL) {
+            result |= vk::BufferUsageFlags::UNIFORM_TEXEL_BUFFER;
+        }
+        if self.contains(BufferUsage::STORAGE_TEXEL) {
+            result |= vk::BufferUsageFlags::STORAGE_TEXEL_BUFFER;
+        }
+        if self.contains(BufferUsage::INDIRECT) {
+            result |= vk::BufferUsageFlags::INDIRECT_BUFFER;
+        }
         #[cfg(feature = "ray")]
         if self.contains(BufferUsage::RAY_TRACING) {
             result |= vk::BufferUsageFlags::RAY_TRACING_KHR;
@@ -2085,6 +2464,10 @@ pub enum MemoryType {
     Device,
     /// Slower to read and write from for the gpu but visible to the cpu
     Host,
+    /// Visible to the cpu and cached on the cpu, faster for the cpu to read from
+    /// but might not be coherent so writes must be flushed and reads invalidated
+    /// see [`crate::MappedBuffer`]
+    HostCached,
 }
 
 impl Into<vk::MemoryPropertyFlags> for MemoryType {
@@ -2094,6 +2477,9 @@ impl Into<vk::MemoryPropertyFlags> for MemoryType {
             Self::Host => {
                 vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT
             }
+            Self::HostCached => {
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_CACHED
+            }
         }
     }
 }
@@ -2226,6 +2612,282 @@ impl From<vk::ImageFormatProperties> for TextureFormatProperties {
     }
 }
 
+/// The operations a device supports for a format
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkFormatProperties.html>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FormatProperties {
+    /// features supported by textures of the format (all textures created by this crate use optimal tiling)
+    pub texture_features: FormatFeatureFlags,
+    /// features supported by textures of the format with linear tiling
+    pub linear_texture_features: FormatFeatureFlags,
+    /// features supported by buffers of the format
+    pub buffer_features: FormatFeatureFlags,
+}
+
+impl FormatProperties {
+    /// returns true if textures of the format can be sampled from
+    pub fn sampled(&self) -> bool {
+        self.texture_features
+            .contains(FormatFeatureFlags::SAMPLED_IMAGE)
+    }
+
+    /// returns true if textures of the format can be used as color attachments
+    pub fn color_attachment(&self) -> bool {
+        self.texture_features
+            .contains(FormatFeatureFlags::COLOR_ATTACHMENT)
+    }
+
+    /// returns true if textures of the format can be used as depth stencil attachments
+    pub fn depth_stencil_attachment(&self) -> bool {
+        self.texture_features
+            .contains(FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT)
+    }
+
+    /// returns true if textures of the format can be used as storage textures
+    pub fn storage(&self) -> bool {
+        self.texture_features
+            .contains(FormatFeatureFlags::STORAGE_IMAGE)
+    }
+
+    /// returns true if textures of the format can be the source and destination of blits
+    pub fn blit(&self) -> bool {
+        self.texture_features
+            .contains(FormatFeatureFlags::BLIT_SRC | FormatFeatureFlags::BLIT_DST)
+    }
+
+    /// returns true if buffer views of the format can be read in shaders as uniform texel buffers (samplerBuffer)
+    pub fn uniform_texel_buffer(&self) -> bool {
+        self.buffer_features
+            .contains(FormatFeatureFlags::UNIFORM_TEXEL_BUFFER)
+    }
+
+    /// returns true if buffer views of the format can be used in shaders as storage texel buffers (imageBuffer)
+    pub fn storage_texel_buffer(&self) -> bool {
+        self.buffer_features
+            .contains(FormatFeatureFlags::STORAGE_TEXEL_BUFFER)
+    }
+
+    /// returns true if the format can be used for vertex attributes
+    pub fn vertex_buffer(&self) -> bool {
+        self.buffer_features
+            .contains(FormatFeatureFlags::VERTEX_BUFFER)
+    }
+}
+
+impl From<vk::FormatProperties> for FormatProperties {
+    fn from(p: vk::FormatProperties) -> Self {
+        Self {
+            texture_features: p.optimal_tiling_features,
+            linear_texture_features: p.linear_tiling_features,
+            buffer_features: p.buffer_features,
+        }
+    }
+}
+
+/// The limits of a device
+///
+/// Values that are commonly needed to choose valid configurations are surfaced as typed fields,
+/// everything else is available through [`DeviceLimits::raw`]
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkPhysicalDeviceLimits.html>
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceLimits {
+    /// the maximum width of 1D textures
+    pub max_texture_dimension_1d: u32,
+    /// the maximum width and height of 2D textures
+    pub max_texture_dimension_2d: u32,
+    /// the maximum width, height and depth of 3D textures
+    pub max_texture_dimension_3d: u32,
+    /// the maximum width and height of cube textures
+    pub max_texture_dimension_cube: u32,
+    /// the maximum number of layers of array textures
+    pub max_texture_array_layers: u32,
+    /// the maximum number of texels in a [`crate::BufferView`]
+    pub max_texel_buffer_elements: u32,
+    /// the maximum size in bytes of a range of a uniform buffer descriptor
+    pub max_uniform_buffer_range: u32,
+    /// the maximum size in bytes of a range of a storage buffer descriptor
+    pub max_storage_buffer_range: u32,
+    /// the maximum size in bytes of all push constants in a pipeline layout
+    pub max_push_constants_size: u32,
+    /// the maximum number of descriptor sets in a pipeline layout
+    pub max_bound_descriptor_sets: u32,
+    /// the maximum number of sampler descriptors accessible to one shader stage
+    pub max_per_stage_descriptor_samplers: u32,
+    /// the maximum number of uniform buffer descriptors accessible to one shader stage
+    pub max_per_stage_descriptor_uniform_buffers: u32,
+    /// the maximum number of storage buffer descriptors accessible to one shader stage
+    pub max_per_stage_descriptor_storage_buffers: u32,
+    /// the maximum number of sampled texture descriptors accessible to one shader stage
+    pub max_per_stage_descriptor_sampled_textures: u32,
+    /// the maximum number of storage texture descriptors accessible to one shader stage
+    pub max_per_stage_descriptor_storage_textures: u32,
+    /// the maximum number of input attachment descriptors accessible to one shader stage
+    pub max_per_stage_descriptor_input_attachments: u32,
+    /// the maximum number of descriptors accessible to one shader stage
+    pub max_per_stage_resources: u32,
+    /// the maximum number of vertex attributes
+    pub max_vertex_input_attributes: u32,
+    /// the maximum number of vertex buffers
+    pub max_vertex_input_bindings: u32,
+    /// the maximum offset of a vertex attribute
+    pub max_vertex_input_attribute_offset: u32,
+    /// the maximum stride of a vertex buffer
+    pub max_vertex_input_binding_stride: u32,
+    /// the maximum number of work groups in each dimension of a dispatch
+    pub max_compute_work_group_count: [u32; 3],
+    /// the maximum size of a work group in each dimension
+    pub max_compute_work_group_size: [u32; 3],
+    /// the maximum number of invocations in one work group
+    pub max_compute_work_group_invocations: u32,
+    /// the maximum size in bytes of workgroup shared memory
+    pub max_compute_shared_memory_size: u32,
+    /// the maximum width of a framebuffer
+    pub max_framebuffer_width: u32,
+    /// the maximum height of a framebuffer
+    pub max_framebuffer_height: u32,
+    /// the maximum number of layers of a framebuffer
+    pub max_framebuffer_layers: u32,
+    /// the maximum number of color attachments of a subpass
+    pub max_color_attachments: u32,
+    /// sample counts supported by color attachments
+    pub framebuffer_color_sample_counts: SampleCountFlags,
+    /// sample counts supported by depth attachments
+    pub framebuffer_depth_sample_counts: SampleCountFlags,
+    /// sample counts supported by stencil attachments
+    pub framebuffer_stencil_sample_counts: SampleCountFlags,
+    /// sample counts supported by sampled color textures
+    pub sampled_texture_color_sample_counts: SampleCountFlags,
+    /// sample counts supported by sampled depth textures
+    pub sampled_texture_depth_sample_counts: SampleCountFlags,
+    /// sample counts supported by storage textures
+    pub storage_texture_sample_counts: SampleCountFlags,
+    /// the maximum anisotropy of a sampler
+    pub max_sampler_anisotropy: f32,
+    /// the maximum lod bias of a sampler
+    pub max_sampler_lod_bias: f32,
+    /// the number of nanoseconds that it takes for a timestamp query to increment by one
+    pub timestamp_period: f32,
+    /// the alignment required for offsets of dynamic uniform buffers and uniform buffer descriptors
+    pub min_uniform_buffer_offset_alignment: u64,
+    /// the alignment required for offsets of dynamic storage buffers and storage buffer descriptors
+    pub min_storage_buffer_offset_alignment: u64,
+    /// the alignment required for offsets of [`crate::BufferView`]s
+    pub min_texel_buffer_offset_alignment: u64,
+    /// the alignment required for flushing and invalidating host memory that isn't coherent
+    pub non_coherent_atom_size: u64,
+    /// the raw vulkan limits
+    pub raw: vk::PhysicalDeviceLimits,
+}
+
+impl DeviceLimits {
+    /// returns the maximum number of descriptors of the type accessible to one shader stage
+    pub fn max_per_stage_descriptors(&self, ty: DescriptorLayoutEntryType) -> u32 {
+        match ty {
+            DescriptorLayoutEntryType::UniformBuffer
+            | DescriptorLayoutEntryType::UniformBufferDynamic => {
+                self.max_per_stage_descriptor_uniform_buffers
+            }
+            DescriptorLayoutEntryType::StorageBuffer { .. }
+            | DescriptorLayoutEntryType::StorageBufferDynamic { .. } => {
+                self.max_per_stage_descriptor_storage_buffers
+            }
+            DescriptorLayoutEntryType::SampledTexture
+            | DescriptorLayoutEntryType::UniformTexelBuffer => {
+                self.max_per_stage_descriptor_sampled_textures
+            }
+            DescriptorLayoutEntryType::StorageTexture { .. }
+            | DescriptorLayoutEntryType::StorageTexelBuffer { .. } => {
+                self.max_per_stage_descriptor_storage_textures
+            }
+            DescriptorLayoutEntryType::Sampler => self.max_per_stage_descriptor_samplers,
+            DescriptorLayoutEntryType::CombinedTextureSampler => self
+                .max_per_stage_descriptor_samplers
+                .min(self.max_per_stage_descriptor_sampled_textures),
+        }
+    }
+
+    /// returns the sample counts the device supports for textures with the aspects and usage
+    ///
+    /// Doesn't account for limits of specific formats, see [`crate::Device::max_samples_for`]
+    pub fn sample_counts(&self, aspects: crate::TextureAspects, usage: TextureUsage) -> SampleCountFlags {
+        use crate::TextureAspects;
+        let depth = aspects.intersects(TextureAspects::DEPTH | TextureAspects::STENCIL);
+        let mut counts = SampleCountFlags::from_raw(0x7f);
+        if usage.contains(TextureUsage::COLOR_OUTPUT) {
+            counts &= self.framebuffer_color_sample_counts;
+        }
+        if usage.contains(TextureUsage::DEPTH_OUTPUT) {
+            if aspects.contains(TextureAspects::DEPTH) {
+                counts &= self.framebuffer_depth_sample_counts;
+            }
+            if aspects.contains(TextureAspects::STENCIL) {
+                counts &= self.framebuffer_stencil_sample_counts;
+            }
+        }
+        if usage.contains(TextureUsage::SAMPLED) {
+            counts &= if depth {
+                self.sampled_texture_depth_sample_counts
+            } else {
+                self.sampled_texture_color_sample_counts
+            };
+        }
+        if usage.contains(TextureUsage::STORAGE) {
+            counts &= self.storage_texture_sample_counts;
+        }
+        counts
+    }
+}
+
+impl From<vk::PhysicalDeviceLimits> for DeviceLimits {
+    fn from(l: vk::PhysicalDeviceLimits) -> Self {
+        Self {
+            max_texture_dimension_1d: l.max_image_dimension1_d,
+            max_texture_dimension_2d: l.max_image_dimension2_d,
+            max_texture_dimension_3d: l.max_image_dimension3_d,
+            max_texture_dimension_cube: l.max_image_dimension_cube,
+            max_texture_array_layers: l.max_image_array_layers,
+            max_texel_buffer_elements: l.max_texel_buffer_elements,
+            max_uniform_buffer_range: l.max_uniform_buffer_range,
+            max_storage_buffer_range: l.max_storage_buffer_range,
+            max_push_constants_size: l.max_push_constants_size,
+            max_bound_descriptor_sets: l.max_bound_descriptor_sets,
+            max_per_stage_descriptor_samplers: l.max_per_stage_descriptor_samplers,
+            max_per_stage_descriptor_uniform_buffers: l.max_per_stage_descriptor_uniform_buffers,
+            max_per_stage_descriptor_storage_buffers: l.max_per_stage_descriptor_storage_buffers,
+            max_per_stage_descriptor_sampled_textures: l.max_per_stage_descriptor_sampled_images,
+            max_per_stage_descriptor_storage_textures: l.max_per_stage_descriptor_storage_images,
+            max_per_stage_descriptor_input_attachments: l.max_per_stage_descriptor_input_attachments,
+            max_per_stage_resources: l.max_per_stage_resources,
+            max_vertex_input_attributes: l.max_vertex_input_attributes,
+            max_vertex_input_bindings: l.max_vertex_input_bindings,
+            max_vertex_input_attribute_offset: l.max_vertex_input_attribute_offset,
+            max_vertex_input_binding_stride: l.max_vertex_input_binding_stride,
+            max_compute_work_group_count: l.max_compute_work_group_count,
+            max_compute_work_group_size: l.max_compute_work_group_size,
+            max_compute_work_group_invocations: l.max_compute_work_group_invocations,
+            max_compute_shared_memory_size: l.max_compute_shared_memory_size,
+            max_framebuffer_width: l.max_framebuffer_width,
+            max_framebuffer_height: l.max_framebuffer_height,
+            max_framebuffer_layers: l.max_framebuffer_layers,
+            max_color_attachments: l.max_color_attachments,
+            framebuffer_color_sample_counts: l.framebuffer_color_sample_counts,
+            framebuffer_depth_sample_counts: l.framebuffer_depth_sample_counts,
+            framebuffer_stencil_sample_counts: l.framebuffer_stencil_sample_counts,
+            sampled_texture_color_sample_counts: l.sampled_image_color_sample_counts,
+            sampled_texture_depth_sample_counts: l.sampled_image_depth_sample_counts,
+            storage_texture_sample_counts: l.storage_image_sample_counts,
+            max_sampler_anisotropy: l.max_sampler_anisotropy,
+            max_sampler_lod_bias: l.max_sampler_lod_bias,
+            timestamp_period: l.timestamp_period,
+            min_uniform_buffer_offset_alignment: l.min_uniform_buffer_offset_alignment,
+            min_storage_buffer_offset_alignment: l.min_storage_buffer_offset_alignment,
+            min_texel_buffer_offset_alignment: l.min_texel_buffer_offset_alignment,
+            non_coherent_atom_size: l.non_coherent_atom_size,
+            raw: l,
+        }
+    }
+}
+
 /// Describes the dimension of a texture
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum TextureDimension {
@@ -2291,12 +2953,10 @@ impl TextureDimension {
 
     pub(crate) fn flags(&self) -> vk::ImageCreateFlags {
         match self {
-            TextureDimension::D2Array(_, _, _, _) => vk::ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE,
             TextureDimension::Cube(_) => vk::ImageCreateFlags::CUBE_COMPATIBLE,
             TextureDimension::CubeArray(_, _) => vk::ImageCreateFlags::CUBE_COMPATIBLE,
             //TextureDimension::CubeMs(_, _, _) => vk::ImageCreateFlags::CUBE_COMPATIBLE,
             //TextureDimension::CubeArrayMs(_, _, _, _) => vk::ImageCreateFlags::CUBE_COMPATIBLE,
-            TextureDimension::D3(_, _, _) => vk::ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE,
             _ => vk::ImageCreateFlags::empty(),
         }
     }
@@ -2466,6 +3126,72 @@ impl Into<vk::BorderColor> for BorderColor {
     }
 }
 
+/// The color model used to convert YCbCr texels to RGB when sampling with a [`crate::SamplerYcbcrConversion`]
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkSamplerYcbcrModelConversion.html>
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum YcbcrModel {
+    /// texels are already RGB and aren't converted
+    RgbIdentity,
+    /// texels are range expanded but not converted to RGB
+    YcbcrIdentity,
+    /// BT.709 conversion, used by most HD video
+    Ycbcr709,
+    /// BT.601 conversion, used by SD video
+    Ycbcr601,
+    /// BT.2020 conversion, used by UHD and HDR video
+    Ycbcr2020,
+}
+
+impl Into<vk::SamplerYcbcrModelConversion> for YcbcrModel {
+    fn into(self) -> vk::SamplerYcbcrModelConversion {
+        match self {
+            Self::RgbIdentity => vk::SamplerYcbcrModelConversion::RGB_IDENTITY,
+            Self::YcbcrIdentity => vk::SamplerYcbcrModelConversion::YCBCR_IDENTITY,
+            Self::Ycbcr709 => vk::SamplerYcbcrModelConversion::YCBCR_709,
+            Self::Ycbcr601 => vk::SamplerYcbcrModelConversion::YCBCR_601,
+            Self::Ycbcr2020 => vk::SamplerYcbcrModelConversion::YCBCR_2020,
+        }
+    }
+}
+
+/// The range of the encoded YCbCr values
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkSamplerYcbcrRange.html>
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum YcbcrRange {
+    /// values use the full range of the encoding
+    Full,
+    /// values have headroom and footroom reserved (eg 16-235 for 8 bit luma), used by most video
+    Narrow,
+}
+
+impl Into<vk::SamplerYcbcrRange> for YcbcrRange {
+    fn into(self) -> vk::SamplerYcbcrRange {
+        match self {
+            Self::Full => vk::SamplerYcbcrRange::ITU_FULL,
+            Self::Narrow => vk::SamplerYcbcrRange::ITU_NARROW,
+        }
+    }
+}
+
+/// Where downsampled chroma samples are located relative to luma samples
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkChromaLocation.html>
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ChromaLocation {
+    /// chroma samples are aligned with the even luma samples
+    CositedEven,
+    /// chroma samples are between the luma samples
+    Midpoint,
+}
+
+impl Into<vk::ChromaLocation> for ChromaLocation {
+    fn into(self) -> vk::ChromaLocation {
+        match self {
+            Self::CositedEven => vk::ChromaLocation::COSITED_EVEN,
+            Self::Midpoint => vk::ChromaLocation::MIDPOINT,
+        }
+    }
+}
+
 /// A Layout of a texture in memory
 ///
 /// will be different for different implementations
@@ -2574,6 +3300,8 @@ bitflags::bitflags! {
         #[cfg(feature = "ray")]
         #[allow(missing_docs)]
         const ACCELERATION_STRUCTURE_WRITE   = 0b010000000000000000;
+        #[allow(missing_docs)]
+        const INDIRECT_COMMAND_READ          = 0b100000000000000000;
     }
 }
 
@@ -2633,6 +3361,9 @@ impl Into<vk::AccessFlags> for AccessFlags {
         if self.contains(Self::ACCELERATION_STRUCTURE_WRITE) {
             result |= vk::AccessFlags::ACCELERATION_STRUCTURE_WRITE_KHR;
         }
+        if self.contains(Self::INDIRECT_COMMAND_READ) {
+            result |= vk::AccessFlags::INDIRECT_COMMAND_READ;
+        }
         return result;
     }
 }
@@ -12,7 +12,7 @@ use std::ptr;
 use std::sync::Arc;
 use std::sync::Mutex;
 
-use ash::extensions::ext;
+use ash::extensions::{ext, khr};
 use ash::vk;
 use vk::Handle;
 
@@ -22,6 +22,17 @@ pub(crate) mod raw;
 
 pub(crate) use raw::*;
 
+/// Infomation about a family of queues on a device
+#[derive(Debug, Clone)]
+pub struct QueueFamilyInfo {
+    /// the index of the queue family
+    pub index: u32,
+    /// the number of queues in the family
+    pub count: u32,
+    /// the operations that queues in the family support
+    pub flags: crate::QueueFlags,
+}
+
 /// Infomation about a device
 #[derive(Debug)]
 pub struct DeviceInfo {
@@ -41,13 +52,69 @@ pub struct DeviceInfo {
     pub mem_properties: crate::MemoryProperties,
     /// the limits of the device
     pub limits: crate::DeviceLimits,
+    /// the features that the device supports
+    pub features: crate::DeviceFeatures,
+    /// the queue families of the device
+    pub queue_families: Vec<QueueFamilyInfo>,
+}
+
+impl DeviceInfo {
+    /// returns true if the device supports all of the features
+    pub fn supports(&self, features: crate::DeviceFeatures) -> bool {
+        self.features.contains(features)
+    }
+
+    /// returns the features that are requested but not supported by the device
+    pub fn missing_features(&self, features: crate::DeviceFeatures) -> crate::DeviceFeatures {
+        features - self.features
+    }
+}
+
+/// Which physical device should be preferred when more than one is available
+///
+/// Useful on systems with multiple gpus such as laptops with integrated and discrete graphics
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AdapterPreference {
+    /// No preference, only [`DeviceDesc::predicate`] is used to order devices
+    Default,
+    /// Prefer discrete gpus
+    HighPerformance,
+    /// Prefer integrated gpus
+    LowPower,
+    /// Only use the device with the id from [`DeviceInfo::id`], see [`crate::Instance::devices`]
+    Id(u64),
 }
 
+impl AdapterPreference {
+    /// how well the device matches the preference, higher is better
+    fn rank(&self, info: &DeviceInfo) -> u8 {
+        match self {
+            Self::Default => 0,
+            Self::HighPerformance => (info.device_type == crate::DeviceType::DiscreteGpu) as u8,
+            Self::LowPower => (info.device_type == crate::DeviceType::IntegratedGpu) as u8,
+            Self::Id(id) => (info.id == *id) as u8,
+        }
+    }
+}
+
+/// Explicitly choose a device from every device that is compatible with the description
+///
+/// returns the index into the slice of the device to use or None if none are acceptable
+pub type DeviceSelector = fn(&[DeviceInfo]) -> Option<usize>;
+
 pub struct DeviceDesc<'a, F: Fn(&DeviceInfo, &DeviceInfo) -> Ordering> {
     /// Optional surface that the device should support presenting to
     pub compatible_surfaces: &'a [&'a crate::Surface],
     /// Features that the device should have
     pub features: crate::DeviceFeatures,
+    /// Additional queues that the device should create
+    /// if no dedicated queue family exists for a kind then [`Device::queue`] will return the main queue.
+    /// Buffers and textures are shared concurrently between the queue families so can be used on any queue
+    pub queues: &'a [crate::QueueKind],
+    /// Which type of device to prefer, takes priority over the predicate
+    pub adapter: AdapterPreference,
+    /// Explicitly choose the device, overrides both adapter and predicate if set
+    pub selector: Option<DeviceSelector>,
     /// How to choose the device the device
     /// The device with the greatest ordering will be chosen
     pub predicate: F,
@@ -70,6 +137,9 @@ impl Default for DeviceDesc<'static, fn(&DeviceInfo, &DeviceInfo) -> Ordering> {
         Self {
             compatible_surfaces: &[],
             features: crate::DeviceFeatures::BASE,
+            queues: &[],
+            adapter: AdapterPreference::Default,
+            selector: None,
             predicate: default_device_ordering,
         }
     }
@@ -88,6 +158,9 @@ pub struct Device {
     pub(crate) physical: vk::PhysicalDevice,
     pub(crate) queue_family: u32,
     pub(crate) queue: vk::Queue,
+    pub(crate) queues: Vec<crate::Queue>,
+    /// the distinct families of all the queues created, buffers and textures are shared between them
+    pub(crate) queue_families: Vec<u32>,
     pub(crate) info: DeviceInfo,
     // a command objects used for under the hood initialization
     pub(crate) command_pool: vk::CommandPool,
@@ -95,6 +168,10 @@ pub struct Device {
     pub(crate) semaphore: Md<Arc<vk::Semaphore>>,
     pub(crate) fence: vk::Fence,
     pub(crate) waiting_on_semaphore: Mutex<Option<Arc<vk::Semaphore>>>,
+    // the additional queues requested so the device can be recreated
+    pub(crate) queue_kinds: Vec<crate::QueueKind>,
+    // called with the new device by crate::Instance::recreate_device
+    pub(crate) recreate_callbacks: Mutex<Vec<Box<dyn FnMut(&Device) + Send>>>,
     // for debugging + error catching
     pub(crate) debug_utils: Option<ext::DebugUtils>,
     pub(crate) debug_messenger: Option<vk::DebugUtilsMessengerEXT>,
@@ -126,30 +203,177 @@ impl Device {
         info: DeviceInfo,
         features: crate::DeviceFeatures,
         compatible_surfaces: &'_ [&'_ crate::Surface],
+        queues: &'_ [crate::QueueKind],
     ) -> Result<Self, Error> {
         let queue_info = Self::get_queue_info(instance, features, compatible_surfaces, physical);
+        // (kind, family) for each additional queue that has a dedicated family
+        let extra_queues = queues
+            .iter()
+            .filter_map(|&kind| {
+                Self::dedicated_queue_family(&info, kind, queue_info.queue_family_index)
+                    .map(|family| (kind, family))
+            })
+            .collect::<Vec<_>>();
+        let mut queue_infos = vec![queue_info];
+        for &(_, family) in &extra_queues {
+            if queue_infos.iter().all(|i| i.queue_family_index != family) {
+                queue_infos.push(vk::DeviceQueueCreateInfo {
+                    queue_family_index: family,
+                    ..queue_info
+                });
+            }
+        }
         let validation = instance.validation_layers.len() == 0;
         let (enabled_layer_names, enabled_extensions) =
             Self::enabled_layers_extension(instance, physical)?;
 
-        let reset_features = vk::PhysicalDeviceHostQueryResetFeatures {
+        let missing = info.missing_features(features);
+        if !missing.is_empty() {
+            return Err(Error::MissingFeatures(missing));
+        }
+
+        let (major, minor, patch) = info.api_version;
+        let api = instance.api_version.min(vk::make_api_version(0, major, minor, patch));
+
+        let mut enabled_extensions = enabled_extensions;
+        if features.contains(crate::DeviceFeatures::DYNAMIC_RENDERING) {
+            enabled_extensions.push(vk::KhrDynamicRenderingFn::name().as_ptr());
+            // VK_KHR_dynamic_rendering depends on VK_KHR_depth_stencil_resolve and its dependencies
+            if api < vk::API_VERSION_1_2 {
+                enabled_extensions.push(vk::KhrDepthStencilResolveFn::name().as_ptr());
+                enabled_extensions.push(vk::KhrCreateRenderpass2Fn::name().as_ptr());
+            }
+            if api < vk::API_VERSION_1_1 {
+                enabled_extensions.push(vk::KhrMultiviewFn::name().as_ptr());
+                enabled_extensions.push(vk::KhrMaintenance2Fn::name().as_ptr());
+            }
+        }
+        if features.contains(crate::DeviceFeatures::PRESENT_WAIT) {
+            enabled_extensions.push(vk::KhrPresentIdFn::name().as_ptr());
+            enabled_extensions.push(vk::KhrPresentWaitFn::name().as_ptr());
+        }
+        if features.contains(crate::DeviceFeatures::SAMPLER_REDUCTION) {
+            enabled_extensions.push(vk::ExtSamplerFilterMinmaxFn::name().as_ptr());
+        }
+        if features.contains(crate::DeviceFeatures::TEXTURE_3D_SLICES) && api < vk::API_VERSION_1_1 {
+            enabled_extensions.push(vk::KhrMaintenance1Fn::name().as_ptr());
+        }
+        if api < vk::API_VERSION_1_2 {
+            if features.contains(crate::DeviceFeatures::TIME_QUERIES) {
+                enabled_extensions.push(vk::ExtHostQueryResetFn::name().as_ptr());
+            }
+            if features.contains(crate::DeviceFeatures::SHADER_FLOAT_16) {
+                enabled_extensions.push(vk::KhrShaderFloat16Int8Fn::name().as_ptr());
+            }
+            if features.contains(crate::DeviceFeatures::DESCRIPTOR_INDEXING) {
+                enabled_extensions.push(vk::KhrMaintenance3Fn::name().as_ptr());
+                enabled_extensions.push(vk::ExtDescriptorIndexingFn::name().as_ptr());
+            }
+        }
+        if features.contains(crate::DeviceFeatures::EXTERNAL_MEMORY) {
+            enabled_extensions.extend(
+                crate::ffi::external_memory_extension_names()
+                    .iter()
+                    .map(|n| n.as_ptr()),
+            );
+        }
+        if features.contains(crate::DeviceFeatures::EXTERNAL_SEMAPHORE) {
+            enabled_extensions.extend(
+                crate::ffi::external_semaphore_extension_names()
+                    .iter()
+                    .map(|n| n.as_ptr()),
+            );
+        }
+
+        // chain together the feature structs that aren't part of vk::PhysicalDeviceFeatures
+        let mut p_next: *mut c_void = ptr::null_mut();
+
+        let mut reset_features = vk::PhysicalDeviceHostQueryResetFeatures {
             s_type: vk::StructureType::PHYSICAL_DEVICE_HOST_QUERY_RESET_FEATURES,
             p_next: ptr::null_mut(),
             host_query_reset: vk::TRUE,
         };
+        if features.contains(crate::DeviceFeatures::TIME_QUERIES) {
+            reset_features.p_next = p_next;
+            p_next = &mut reset_features as *mut _ as *mut c_void;
+        }
 
-        let p_next = if features.contains(crate::DeviceFeatures::TIME_QUERIES) {
-            &reset_features as *const _ as *const _
-        } else {
-            ptr::null()
+        let mut float16_features = vk::PhysicalDeviceShaderFloat16Int8Features {
+            s_type: vk::StructureType::PHYSICAL_DEVICE_SHADER_FLOAT16_INT8_FEATURES,
+            p_next: ptr::null_mut(),
+            shader_float16: vk::TRUE,
+            shader_int8: vk::FALSE,
+        };
+        if features.contains(crate::DeviceFeatures::SHADER_FLOAT_16) {
+            float16_features.p_next = p_next;
+            p_next = &mut float16_features as *mut _ as *mut c_void;
+        }
+
+        let mut storage16_features = vk::PhysicalDevice16BitStorageFeatures {
+            s_type: vk::StructureType::PHYSICAL_DEVICE_16BIT_STORAGE_FEATURES,
+            p_next: ptr::null_mut(),
+            storage_buffer16_bit_access: vk::TRUE,
+            uniform_and_storage_buffer16_bit_access: vk::TRUE,
+            ..Default::default()
+        };
+        if features.contains(crate::DeviceFeatures::STORAGE_16_BIT) {
+            storage16_features.p_next = p_next;
+            p_next = &mut storage16_features as *mut _ as *mut c_void;
+        }
+
+        let mut descriptor_indexing_features = vk::PhysicalDeviceDescriptorIndexingFeatures {
+            s_type: vk::StructureType::PHYSICAL_DEVICE_DESCRIPTOR_INDEXING_FEATURES,
+            p_next: ptr::null_mut(),
+            shader_sampled_image_array_non_uniform_indexing: vk::TRUE,
+            ..Default::default()
+        };
+        if features.contains(crate::DeviceFeatures::DESCRIPTOR_INDEXING) {
+            descriptor_indexing_features.p_next = p_next;
+            p_next = &mut descriptor_indexing_features as *mut _ as *mut c_void;
+        }
+
+        let mut ycbcr_features = vk::PhysicalDeviceSamplerYcbcrConversionFeatures {
+            s_type: vk::StructureType::PHYSICAL_DEVICE_SAMPLER_YCBCR_CONVERSION_FEATURES,
+            p_next: ptr::null_mut(),
+            sampler_ycbcr_conversion: vk::TRUE,
+        };
+        if features.contains(crate::DeviceFeatures::SAMPLER_YCBCR_CONVERSION) {
+            ycbcr_features.p_next = p_next;
+            p_next = &mut ycbcr_features as *mut _ as *mut c_void;
+        }
+
+        let mut dynamic_rendering_features = vk::PhysicalDeviceDynamicRenderingFeatures {
+            s_type: vk::StructureType::PHYSICAL_DEVICE_DYNAMIC_RENDERING_FEATURES,
+            p_next: ptr::null_mut(),
+            dynamic_rendering: vk::TRUE,
+        };
+        if features.contains(crate::DeviceFeatures::DYNAMIC_RENDERING) {
+            dynamic_rendering_features.p_next = p_next;
+            p_next = &mut dynamic_rendering_features as *mut _ as *mut c_void;
+        }
+
+        let mut present_id_features = vk::PhysicalDevicePresentIdFeaturesKHR {
+            s_type: vk::StructureType::PHYSICAL_DEVICE_PRESENT_ID_FEATURES_KHR,
+            p_next: ptr::null_mut(),
+            present_id: vk::TRUE,
+        };
+        let mut present_wait_features = vk::PhysicalDevicePresentWaitFeaturesKHR {
+            s_type: vk::StructureType::PHYSICAL_DEVICE_PRESENT_WAIT_FEATURES_KHR,
+            p_next: ptr::null_mut(),
+            present_wait: vk::TRUE,
         };
+        if features.contains(crate::DeviceFeatures::PRESENT_WAIT) {
+            present_id_features.p_next = p_next;
+            present_wait_features.p_next = &mut present_id_features as *mut _ as *mut c_void;
+            p_next = &mut present_wait_features as *mut _ as *mut c_void;
+        }
 
         let create_info = vk::DeviceCreateInfo {
             s_type: vk::StructureType::DEVICE_CREATE_INFO,
-            p_next,
+            p_next: p_next as *const c_void,
             flags: vk::DeviceCreateFlags::empty(),
-            queue_create_info_count: 1,
-            p_queue_create_infos: &queue_info,
+            queue_create_info_count: queue_infos.len() as u32,
+            p_queue_create_infos: queue_infos.as_ptr(),
             enabled_layer_count: if validation {
                 instance.validation_layers.len()
             } else {
@@ -172,6 +396,20 @@ impl Device {
         };
 
         let queue = unsafe { raw.get_device_queue(queue_info.queue_family_index, 0) };
+        let queue_families = queue_infos
+            .iter()
+            .map(|i| i.queue_family_index)
+            .collect::<Vec<_>>();
+
+        let queue_kinds = queues.to_vec();
+        let queues = extra_queues
+            .into_iter()
+            .map(|(kind, family)| crate::Queue {
+                raw: unsafe { raw.get_device_queue(family, 0) },
+                family,
+                kind,
+            })
+            .collect::<Vec<_>>();
 
         let (command_pool, command_buffer, fence, semaphore) =
             Self::create_command(&raw, queue_info.queue_family_index)?;
@@ -182,12 +420,20 @@ impl Device {
             None
         };
 
+        let dynamic_rendering = if features.contains(crate::DeviceFeatures::DYNAMIC_RENDERING) {
+            Some(khr::DynamicRendering::new(&**instance.raw, &raw))
+        } else {
+            None
+        };
+
         let mut raw = Arc::new(RawDevice::new(
             raw,
             Arc::clone(&instance.raw),
             features,
             info.limits,
             debug_utils.clone(),
+            dynamic_rendering,
+            Arc::clone(&instance.validation_handler),
         ));
 
         // TODO: not this, it works but there's no way this is defined behaviour
@@ -225,12 +471,16 @@ impl Device {
             info,
             physical,
             queue,
+            queues,
+            queue_families,
             queue_family: queue_info.queue_family_index,
             command_pool,
             command_buffer,
             semaphore: Md::new(Arc::new(semaphore)),
             fence,
             waiting_on_semaphore: Mutex::new(None),
+            queue_kinds,
+            recreate_callbacks: Mutex::new(Vec::new()),
             debug_utils,
             debug_messenger,
         })
@@ -242,6 +492,7 @@ impl Device {
         id: u64,
         features: crate::DeviceFeatures,
         compatible_surfaces: &'_ [&'_ crate::Surface],
+        queues: &'_ [crate::QueueKind],
     ) -> Result<Self, Error> {
         let physical = vk::PhysicalDevice::from_raw(id);
         let info = match instance.device_info(physical) {
@@ -249,7 +500,7 @@ impl Device {
             Err(e) => return Err(e.into()),
         };
 
-        Self::from_raw(instance, physical, info, features, compatible_surfaces)
+        Self::from_raw(instance, physical, info, features, compatible_surfaces, queues)
     }
 
     /// Create a new Device
@@ -268,6 +519,7 @@ impl Device {
             info,
             desc.features,
             desc.compatible_surfaces,
+            desc.queues,
         )
     }
 
@@ -346,7 +598,7 @@ impl Device {
             Err(e) => return Err(e.into()),
         };
 
-        let physical_device = physical_devices
+        let mut candidates = physical_devices
             .iter()
             .filter_map(|&physical_device| unsafe {
                 let mut supported = false;
@@ -375,7 +627,39 @@ impl Device {
                     None
                 }
             })
-            .max_by(|l, r| (desc.predicate)(&l.1, &r.1));
+            .collect::<Vec<_>>();
+
+        if let Some(selector) = desc.selector {
+            let (physical, infos): (Vec<_>, Vec<_>) = candidates.into_iter().unzip();
+            return match selector(&infos) {
+                Some(i) if i < infos.len() => {
+                    let info = infos.into_iter().nth(i).unwrap();
+                    Ok((physical[i], info))
+                }
+                Some(i) => panic!(
+                    "ERROR: DeviceSelector returned index {} but only {} devices are compatible",
+                    i,
+                    physical.len()
+                ),
+                None => panic!("ERROR: DeviceSelector didn't select any device"),
+            };
+        }
+
+        if let AdapterPreference::Id(id) = desc.adapter {
+            candidates.retain(|(_, info)| info.id == id);
+            if candidates.is_empty() {
+                panic!("ERROR: No compatible device with id {} found", id);
+            }
+        }
+
+        // prefer devices that support the requested features so that the error at device creation
+        // only happens if no device supports them
+        let physical_device = candidates.into_iter().max_by(|l, r| {
+            l.1.supports(desc.features)
+                .cmp(&r.1.supports(desc.features))
+                .then_with(|| desc.adapter.rank(&l.1).cmp(&desc.adapter.rank(&r.1)))
+                .then_with(|| (desc.predicate)(&l.1, &r.1))
+        });
         if let Some(device) = physical_device {
             Ok(device)
         } else {
@@ -439,6 +723,33 @@ impl Device {
         }
     }
 
+    /// Find a queue family other than the main family that is best suited to the kind of queue
+    fn dedicated_queue_family(
+        info: &DeviceInfo,
+        kind: crate::QueueKind,
+        main_family: u32,
+    ) -> Option<u32> {
+        let candidates = info
+            .queue_families
+            .iter()
+            .filter(|f| f.index != main_family && f.count != 0);
+        match kind {
+            crate::QueueKind::Graphics => None,
+            crate::QueueKind::Compute => candidates
+                .filter(|f| f.flags.contains(crate::QueueFlags::COMPUTE))
+                .min_by_key(|f| f.flags.contains(crate::QueueFlags::GRAPHICS))
+                .map(|f| f.index),
+            crate::QueueKind::Transfer => candidates
+                .filter(|f| f.flags.contains(crate::QueueFlags::TRANSFER))
+                .min_by_key(|f| {
+                    (f.flags & (crate::QueueFlags::GRAPHICS | crate::QueueFlags::COMPUTE))
+                        .bits()
+                        .count_ones()
+                })
+                .map(|f| f.index),
+        }
+    }
+
     fn enabled_layers_extension(
         instance: &crate::Instance,
         physical: vk::PhysicalDevice,
@@ -479,6 +790,34 @@ impl Device {
         &self.info
     }
 
+    /// The sharing mode and queue families to create buffers and textures with
+    ///
+    /// If queues from more than one family were created resources are shared concurrently between them
+    /// so that they can be used on any queue without queue family ownership transfers
+    pub(crate) fn sharing_mode(&self) -> (vk::SharingMode, &[u32]) {
+        if self.queue_families.len() > 1 {
+            (vk::SharingMode::CONCURRENT, &self.queue_families)
+        } else {
+            (vk::SharingMode::EXCLUSIVE, &[])
+        }
+    }
+
+    /// Get a queue of the kind requested
+    ///
+    /// Returns the main queue if the kind wasn't requested in [`DeviceDesc::queues`]
+    /// or the device has no dedicated queue family for that kind
+    pub fn queue(&self, kind: crate::QueueKind) -> crate::Queue {
+        self.queues
+            .iter()
+            .find(|q| q.kind == kind)
+            .copied()
+            .unwrap_or(crate::Queue {
+                raw: self.queue,
+                family: self.queue_family,
+                kind,
+            })
+    }
+
     /// wait for the device to be idle
     pub fn wait_idle(&self) -> Result<(), Error> {
         self.raw.wait_idle()
@@ -494,6 +833,48 @@ impl Device {
         self.raw.features
     }
 
+    /// Register a function to be called with the new device when this device is recreated
+    /// by [`crate::Instance::recreate_device`], for example after [`crate::Error::is_device_lost`]
+    ///
+    /// Resources created from this device can't be used with the new device and should be rebuilt in the callback.
+    /// Callbacks are moved to the new device so they are called again if it is recreated
+    pub fn on_recreate<F: FnMut(&Device) + Send + 'static>(&self, f: F) {
+        self.recreate_callbacks.lock().unwrap().push(Box::new(f));
+    }
+
+    /// Create a new device on the same physical device with the same features and queues
+    /// then call the functions registered with [`Device::on_recreate`]
+    pub(crate) fn recreate(
+        &self,
+        instance: &crate::Instance,
+        compatible_surfaces: &'_ [&'_ crate::Surface],
+    ) -> Result<Self, Error> {
+        #[cfg(feature = "logging")]
+        log::trace!("GPU: Recreate Device");
+
+        let info = match instance.device_info(self.physical) {
+            Ok(i) => i,
+            Err(e) => return Err(e.into()),
+        };
+
+        let device = Self::from_raw(
+            instance,
+            self.physical,
+            info,
+            self.raw.features,
+            compatible_surfaces,
+            &self.queue_kinds,
+        )?;
+
+        let mut callbacks = std::mem::take(&mut *self.recreate_callbacks.lock().unwrap());
+        for callback in &mut callbacks {
+            callback(&device);
+        }
+        device.recreate_callbacks.lock().unwrap().extend(callbacks);
+
+        Ok(device)
+    }
+
     /// returns limits that apply to textures created with the format kind and usage supplied
     pub fn texture_properties(
         &self,
@@ -520,6 +901,45 @@ impl Device {
         }
     }
 
+    /// returns what operations the device supports for the format
+    ///
+    /// Use this to check for support of compressed formats before creating textures with them
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkGetPhysicalDeviceFormatProperties.html>
+    pub fn format_properties(&self, format: crate::Format) -> crate::FormatProperties {
+        unsafe {
+            self.raw
+                .instance
+                .get_physical_device_format_properties(self.physical, format.into())
+        }
+        .into()
+    }
+
+    /// returns the highest sample count that 2D textures of the format and usage can be created with
+    ///
+    /// Use to choose the number of samples for multisampled rendering instead of assuming a count is supported
+    pub fn max_samples_for(&self, format: crate::Format, usage: crate::TextureUsage) -> crate::Samples {
+        let counts = match self.texture_properties(format, crate::TextureKind::D2, usage) {
+            Ok(p) => p.sample_counts & self.raw.limits.sample_counts(format.aspects(), usage),
+            Err(_) => return crate::Samples::S1,
+        };
+        [
+            crate::Samples::S64,
+            crate::Samples::S32,
+            crate::Samples::S16,
+            crate::Samples::S8,
+            crate::Samples::S4,
+            crate::Samples::S2,
+        ]
+        .into_iter()
+        .find(|s| counts.contains(s.flags()))
+        .unwrap_or(crate::Samples::S1)
+    }
+
+    /// returns true if the format can be used for vertex attributes
+    pub fn supports_vertex_format(&self, format: crate::Format) -> bool {
+        self.format_properties(format).vertex_buffer()
+    }
+
     /// create a new swapchain to present to the surface supplied
     pub fn create_swapchain(
         &self,
@@ -546,6 +966,26 @@ impl Device {
         crate::CommandBuffer::new(self, name)
     }
 
+    /// Create a command buffer that will submit to the queue supplied
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCreateCommandPool.html>
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkAllocateCommandBuffers.html>
+    pub fn create_queue_command_buffer(
+        &self,
+        queue: crate::Queue,
+        name: Option<String>,
+    ) -> Result<crate::CommandBuffer, crate::Error> {
+        crate::CommandBuffer::with_queue(self, queue, name)
+    }
+
+    /// Create a pool to allocate many command buffers from
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCreateCommandPool.html>
+    pub fn create_command_pool(
+        &self,
+        desc: &crate::CommandPoolDesc,
+    ) -> Result<crate::CommandPool, crate::Error> {
+        crate::CommandPool::new(self, desc)
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCreateShaderModule.html>
     pub fn create_shader_module(
         &self,
@@ -559,6 +999,14 @@ impl Device {
         crate::Buffer::new(self, desc)
     }
 
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCreateBufferView.html>
+    pub fn create_buffer_view(
+        &self,
+        desc: &crate::BufferViewDesc<'_>,
+    ) -> Result<crate::BufferView, crate::Error> {
+        crate::BufferView::new(self, desc)
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCreateImage.html>
     pub fn create_texture(
         &self,
@@ -567,6 +1015,14 @@ impl Device {
         crate::Texture::new(self, desc)
     }
 
+    /// Create a texture owned by one queue family at a time, see [`crate::Texture::new_exclusive`]
+    pub fn create_exclusive_texture(
+        &self,
+        desc: &crate::TextureDesc,
+    ) -> Result<crate::Texture, crate::Error> {
+        crate::Texture::new_exclusive(self, desc)
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCreateSampler.html>
     pub fn create_sampler(
         &self,
@@ -575,6 +1031,23 @@ impl Device {
         crate::Sampler::new(self, desc)
     }
 
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkSamplerYcbcrConversionInfo.html>
+    pub fn create_ycbcr_sampler(
+        &self,
+        desc: &crate::SamplerDesc,
+        conversion: &crate::SamplerYcbcrConversion,
+    ) -> Result<crate::Sampler, crate::Error> {
+        crate::Sampler::new_ycbcr(self, desc, conversion)
+    }
+
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCreateSamplerYcbcrConversion.html>
+    pub fn create_sampler_ycbcr_conversion(
+        &self,
+        desc: &crate::SamplerYcbcrConversionDesc,
+    ) -> Result<crate::SamplerYcbcrConversion, crate::Error> {
+        crate::SamplerYcbcrConversion::new(self, desc)
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCreatePipelineLayout.html>
     pub fn create_pipeline_layout(
         &self,
@@ -625,6 +1098,16 @@ impl Device {
         crate::TimeQuery::new(self, count, name)
     }
 
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCreateEvent.html>
+    pub fn create_event(&self, name: Option<&str>) -> Result<crate::Event, crate::Error> {
+        crate::Event::new(self, name)
+    }
+
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCreateSemaphore.html>
+    pub fn create_semaphore(&self, name: Option<&str>) -> Result<crate::Semaphore, crate::Error> {
+        crate::Semaphore::new(self, name)
+    }
+
     /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCreatePipelineCache.html>
     pub fn create_pipeline_cache(
         &self,
@@ -632,6 +1115,14 @@ impl Device {
     ) -> Result<crate::PipelineCache, crate::Error> {
         crate::PipelineCache::new(self, desc)
     }
+
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkExportSemaphoreCreateInfo.html>
+    pub fn create_external_semaphore(
+        &self,
+        desc: &crate::ExternalSemaphoreDesc,
+    ) -> Result<crate::ExternalSemaphore, crate::Error> {
+        crate::ExternalSemaphore::new(self, desc)
+    }
 }
 
 impl Drop for Device {
@@ -1,8 +1,8 @@
 use std::ffi::CString;
 use std::thread::ThreadId;
-use std::{collections::HashMap, mem::ManuallyDrop as Md, ptr, sync::Arc};
+use std::{collections::HashMap, mem::ManuallyDrop as Md, ptr, sync::{Arc, Weak}};
 
-use ash::extensions::ext;
+use ash::extensions::{ext, khr};
 use ash::vk;
 use vk::Handle;
 
@@ -21,6 +21,10 @@ pub(crate) struct RawDevice {
     ///  - want to be able to keep framebuffers alive after textures that they referenced are dropped so long as a command
     ///    buffer that references them is still being processed
     pub framebuffers: RwLock<HashMap<crate::FramebufferKey, Arc<vk::Framebuffer>>>,
+    /// Descriptor layouts by their entries so that identically defined layouts share one handle
+    /// and descriptor sets allocated from one can be bound to any pipeline using another.
+    /// Weak so that layouts are still destroyed when the last layout or set referencing them is dropped
+    pub descriptor_layouts: Mutex<HashMap<Arc<[crate::DescriptorLayoutEntry]>, Weak<vk::DescriptorSetLayout>>>,
 
     pub device: ash::Device,
     pub features: crate::DeviceFeatures,
@@ -28,7 +32,10 @@ pub(crate) struct RawDevice {
     pub instance: Md<Arc<ash::Instance>>,
 
     pub debug_loader: Option<ext::DebugUtils>,
+    /// Some if graphics passes are recorded with vkCmdBeginRendering instead of render pass objects
+    pub dynamic_rendering: Option<khr::DynamicRendering>,
     pub error: RwLock<Vec<String>>,
+    pub validation_handler: Arc<RwLock<Option<crate::ValidationHandler>>>,
 
     pub semaphores: Mutex<HashMap<ThreadId, Arc<vk::Semaphore>>>,
 }
@@ -75,9 +82,12 @@ impl RawDevice {
         features: crate::DeviceFeatures,
         limits: crate::DeviceLimits,
         debug_loader: Option<ext::DebugUtils>,
+        dynamic_rendering: Option<khr::DynamicRendering>,
+        validation_handler: Arc<RwLock<Option<crate::ValidationHandler>>>,
     ) -> Self {
         Self {
             framebuffers: RwLock::new(HashMap::new()),
+            descriptor_layouts: Mutex::new(HashMap::new()),
 
             device: raw,
             features,
@@ -85,7 +95,9 @@ impl RawDevice {
             instance: Md::new(instance),
 
             debug_loader,
+            dynamic_rendering,
             error: RwLock::new(Vec::new()),
+            validation_handler,
 
             semaphores: Mutex::new(HashMap::new()),
         }
@@ -98,8 +110,15 @@ impl RawDevice {
         }
     }
 
+    /// Convert a debug name or label to a CString
+    ///
+    /// Names are only informational so interior nul bytes are removed instead of failing
+    pub fn debug_cstring(name: &str) -> CString {
+        CString::new(name.replace('\0', "")).unwrap()
+    }
+
     fn set_name(&self, obj: u64, ty: vk::ObjectType, name: &str) -> Result<(), Error> {
-        let c = CString::new(name.to_string()).unwrap();
+        let c = Self::debug_cstring(name);
         unsafe {
             if let Some(loader) = &self.debug_loader {
                 let result = loader.debug_utils_set_object_name(
@@ -138,6 +157,10 @@ impl RawDevice {
         self.set_name(buffer.raw.as_raw(), vk::ObjectType::BUFFER, name)
     }
 
+    pub fn set_buffer_view_name(&self, view: &crate::BufferView, name: &str) -> Result<(), Error> {
+        self.set_name(view.raw.as_raw(), vk::ObjectType::BUFFER_VIEW, name)
+    }
+
     pub fn set_texture_name(&self, texture: &crate::Texture, name: &str) -> Result<(), Error> {
         self.set_name(texture.raw.as_raw(), vk::ObjectType::IMAGE, name)
     }
@@ -155,14 +178,32 @@ impl RawDevice {
         buffer: &crate::CommandBuffer,
         name: &str,
     ) -> Result<(), Error> {
-        self.set_name(buffer.pool.as_raw(), vk::ObjectType::COMMAND_POOL, name)?;
+        if !buffer.pooled() {
+            self.set_name(buffer.pool.as_raw(), vk::ObjectType::COMMAND_POOL, name)?;
+        }
         self.set_name(buffer.buffer.as_raw(), vk::ObjectType::COMMAND_BUFFER, name)
     }
 
+    pub fn set_command_pool_name(&self, pool: &crate::CommandPool, name: &str) -> Result<(), Error> {
+        self.set_name(pool.raw.as_raw(), vk::ObjectType::COMMAND_POOL, name)
+    }
+
     pub fn set_sampler_name(&self, sampler: &crate::Sampler, name: &str) -> Result<(), Error> {
         self.set_name(sampler.raw.as_raw(), vk::ObjectType::SAMPLER, name)
     }
 
+    pub fn set_sampler_ycbcr_conversion_name(
+        &self,
+        conversion: &crate::SamplerYcbcrConversion,
+        name: &str,
+    ) -> Result<(), Error> {
+        self.set_name(
+            conversion.raw.as_raw(),
+            vk::ObjectType::SAMPLER_YCBCR_CONVERSION,
+            name,
+        )
+    }
+
     pub fn set_descriptor_set_name(
         &self,
         set: &crate::DescriptorSet,
@@ -193,6 +234,10 @@ impl RawDevice {
     }
 
     pub fn set_render_pass_name(&self, pass: &crate::RenderPass, name: &str) -> Result<(), Error> {
+        // with dynamic rendering there is no render pass object to name
+        if **pass.raw == vk::RenderPass::null() {
+            return Ok(());
+        }
         self.set_name(pass.raw.as_raw(), vk::ObjectType::RENDER_PASS, name)
     }
 
@@ -224,9 +269,25 @@ impl RawDevice {
         self.set_name(query.raw.as_raw(), vk::ObjectType::QUERY_POOL, name)
     }
 
+    pub fn set_event_name(&self, event: &crate::Event, name: &str) -> Result<(), Error> {
+        self.set_name(event.raw.as_raw(), vk::ObjectType::EVENT, name)
+    }
+
+    pub fn set_semaphore_name(&self, semaphore: &crate::Semaphore, name: &str) -> Result<(), Error> {
+        self.set_name(semaphore.raw.as_raw(), vk::ObjectType::SEMAPHORE, name)
+    }
+
     pub fn set_pipeline_cache_name(&self, cache: &crate::PipelineCache, name: &str) -> Result<(), Error> {
         self.set_name(cache.raw.as_raw(), vk::ObjectType::PIPELINE_CACHE, name)
     }
+
+    pub fn set_external_semaphore_name(
+        &self,
+        semaphore: &crate::ExternalSemaphore,
+        name: &str,
+    ) -> Result<(), Error> {
+        self.set_name(semaphore.raw.as_raw(), vk::ObjectType::SEMAPHORE, name)
+    }
 }
 
 impl Drop for RawDevice {
@@ -2,39 +2,94 @@ use ash::vk;
 
 pub use ash::vk::Result as VkResult;
 
+/// Which memory ran out in [`Error::OutOfMemory`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MemoryLocation {
+    /// Memory on the host (cpu) side
+    Host,
+    /// Memory on the device (gpu) side
+    Device,
+}
+
+/// The suggested way to recover from an [`Error`], see [`Error::recovery`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Recovery {
+    /// Recreate the swapchain with [`crate::Swapchain::recreate`] and skip to the next frame
+    RecreateSwapchain,
+    /// Drop unused resources such as textures and buffers then try again
+    FreeMemory,
+    /// Replace the device and the resources created from it with [`crate::Instance::recreate_device`]
+    RecreateDevice,
+    /// The error is from misuse of the api or an unsupported device and can't be recovered from
+    Fatal,
+}
+
 /// An all encompassing error type
 #[derive(Debug)]
 pub enum Error {
-    /// An explicit error returned from the vulkan api
-    /// Some variants such as ERROR_OUT_OF_DATE_KHR can be
-    /// recovered from
-    Explicit(vk::Result),
+    /// The swapchain no longer matches the surface, usually after the window was resized
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkResult.html>
+    OutOfDateSwapchain,
+    /// An allocation failed because the host or device is out of memory
+    OutOfMemory {
+        /// which memory ran out
+        location: MemoryLocation,
+    },
+    /// The device was lost from a driver crash, timeout or the gpu being removed
+    DeviceLost,
     /// An error from a validation layer
     /// Cannot be recovered from safely
     Validation(Vec<String>),
+    /// The device doesn't support features that were requested when creating it
+    MissingFeatures(crate::DeviceFeatures),
+    /// The instance doesn't have an extension required for the operation enabled
+    /// usually the platform surface extension when creating a surface
+    MissingExtension(&'static str),
+    /// Any other error returned from the vulkan api
+    Explicit(vk::Result),
 }
 
 impl Error {
-    /// Some erros such as Self::Explicit(vk::Result::ERROR_OUT_OF_DATE_KHR)
+    /// How the error can be recovered from
+    pub fn recovery(&self) -> Recovery {
+        match self {
+            Self::OutOfDateSwapchain => Recovery::RecreateSwapchain,
+            Self::OutOfMemory { .. } => Recovery::FreeMemory,
+            Self::DeviceLost => Recovery::RecreateDevice,
+            Self::Validation(_) => Recovery::Fatal,
+            Self::MissingFeatures(_) => Recovery::Fatal,
+            Self::MissingExtension(_) => Recovery::Fatal,
+            Self::Explicit(_) => Recovery::Fatal,
+        }
+    }
+
+    /// Some erros such as Self::OutOfDateSwapchain
     /// can be solved by continuing to the next iteration of the event loop
     /// and recreating the swapchain. This will return true if that is the case
     pub fn can_continue(&self) -> bool {
-        match self {
-            Self::Explicit(r) => match *r {
-                vk::Result::SUBOPTIMAL_KHR => true,
-                vk::Result::ERROR_OUT_OF_DATE_KHR => true,
-                _ => false,
-            },
-            Self::Validation(_) => false,
-        }
+        self.recovery() == Recovery::RecreateSwapchain
+    }
+
+    /// Returns true if the error is Self::DeviceLost
+    ///
+    /// The device can't be used any more and has to be replaced with [`crate::Instance::recreate_device`]
+    pub fn is_device_lost(&self) -> bool {
+        matches!(self, Self::DeviceLost)
     }
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Explicit(t) => {
-                writeln!(f, "{}", t)
+            Self::OutOfDateSwapchain => {
+                writeln!(f, "Swapchain is out of date and must be recreated")
+            }
+            Self::OutOfMemory { location } => match location {
+                MemoryLocation::Host => writeln!(f, "Out of host memory"),
+                MemoryLocation::Device => writeln!(f, "Out of device memory"),
+            },
+            Self::DeviceLost => {
+                writeln!(f, "Device lost")
             }
             Self::Validation(t) => {
                 for message in t {
@@ -43,6 +98,15 @@ impl std::fmt::Display for Error {
                 }
                 Ok(())
             }
+            Self::MissingFeatures(missing) => {
+                writeln!(f, "Device doesn't support requested features: {:?}", missing)
+            }
+            Self::MissingExtension(name) => {
+                writeln!(f, "Instance doesn't support required extension: {}", name)
+            }
+            Self::Explicit(t) => {
+                writeln!(f, "{}", t)
+            }
         }
     }
 }
@@ -51,7 +115,17 @@ impl std::error::Error for Error {}
 
 impl From<vk::Result> for Error {
     fn from(e: vk::Result) -> Self {
-        Self::Explicit(e)
+        match e {
+            vk::Result::ERROR_OUT_OF_DATE_KHR => Self::OutOfDateSwapchain,
+            vk::Result::ERROR_OUT_OF_HOST_MEMORY => Self::OutOfMemory {
+                location: MemoryLocation::Host,
+            },
+            vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Self::OutOfMemory {
+                location: MemoryLocation::Device,
+            },
+            vk::Result::ERROR_DEVICE_LOST => Self::DeviceLost,
+            e => Self::Explicit(e),
+        }
     }
 }
 
@@ -0,0 +1,141 @@
+//! [`Event`]'s are used for fine grained synchronization within a queue
+
+use ash::vk;
+
+use std::mem::ManuallyDrop as Md;
+use std::ptr;
+use std::sync::Arc;
+
+/// An Event
+///
+/// Used to split a pipeline barrier in two, [`crate::CommandBuffer::set_event`] marks where the source work ends
+/// and [`crate::CommandBuffer::wait_events`] where the destination work starts so that unrelated commands recorded
+/// between the two can overlap with the source work instead of waiting on it as they would with a full barrier.
+/// Can also be set and reset from the host
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkEvent.html>
+pub struct Event {
+    pub(crate) name: Option<String>,
+    pub(crate) raw: Md<Arc<vk::Event>>,
+    pub(crate) device: Arc<crate::RawDevice>,
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Event) -> bool {
+        **self.raw == **other.raw
+    }
+}
+
+impl Eq for Event {}
+
+impl std::hash::Hash for Event {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (**self.raw).hash(state)
+    }
+}
+
+impl Clone for Event {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            raw: Md::new(Arc::clone(&self.raw)),
+            device: Arc::clone(&self.device),
+        }
+    }
+}
+
+impl std::fmt::Debug for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Event id: {:?} name: {:?}", **self.raw, self.name)
+    }
+}
+
+impl Event {
+    pub unsafe fn raw_event(&self) -> vk::Event {
+        **self.raw
+    }
+}
+
+impl Event {
+    /// Create a new event in the unsignaled state
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCreateEvent.html>
+    pub fn new(device: &crate::Device, name: Option<&str>) -> Result<Self, crate::Error> {
+        #[cfg(feature = "logging")]
+        log::trace!("GPU: Create Event, name {:?}", name);
+
+        let create_info = vk::EventCreateInfo {
+            s_type: vk::StructureType::EVENT_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::EventCreateFlags::empty(),
+        };
+
+        let result = unsafe { device.raw.create_event(&create_info, None) };
+
+        let raw = match result {
+            Ok(e) => e,
+            Err(e) => return Err(e.into()),
+        };
+
+        let s = Self {
+            name: name.map(|n| n.to_string()),
+            raw: Md::new(Arc::new(raw)),
+            device: Arc::clone(&device.raw),
+        };
+
+        if let Some(name) = name {
+            device.raw.set_event_name(&s, name)?;
+        }
+
+        device.raw.check_errors()?;
+
+        Ok(s)
+    }
+
+    /// Returns true if the event is signaled
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkGetEventStatus.html>
+    pub fn is_set(&self) -> Result<bool, crate::Error> {
+        let result = unsafe { self.device.get_event_status(**self.raw) };
+
+        match result {
+            Ok(b) => Ok(b),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Signal the event from the host
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetEvent.html>
+    pub fn set(&self) -> Result<(), crate::Error> {
+        let result = unsafe { self.device.set_event(**self.raw) };
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Unsignal the event from the host
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkResetEvent.html>
+    pub fn reset(&self) -> Result<(), crate::Error> {
+        let result = unsafe { self.device.reset_event(**self.raw) };
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Get the id of the event
+    pub fn id(&self) -> u64 {
+        unsafe { std::mem::transmute(**self.raw) }
+    }
+}
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        unsafe {
+            let raw = Md::take(&mut self.raw);
+            if let Ok(raw) = Arc::try_unwrap(raw) {
+                self.device.destroy_event(raw, None);
+            }
+        }
+    }
+}
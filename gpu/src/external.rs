@@ -0,0 +1,404 @@
+//! Sharing memory and semaphores with other apis
+//!
+//! Buffers, textures and semaphores can be exported as platform handles (opaque fd on unix, win32 handles on windows)
+//! or created from handles exported by another api such as CUDA, OpenGL or a media encoder.
+//! Requires [`crate::DeviceFeatures::EXTERNAL_MEMORY`] and [`crate::DeviceFeatures::EXTERNAL_SEMAPHORE`] respectively
+
+use std::ffi::c_void;
+use std::mem::ManuallyDrop as Md;
+use std::ptr;
+use std::sync::Arc;
+
+use ash::extensions::khr;
+use ash::vk;
+
+use crate::error::*;
+
+/// The type of handle used to share an object with another api
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkExternalMemoryHandleTypeFlagBits.html>
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ExternalHandleType {
+    /// posix file descriptor
+    OpaqueFd,
+    /// windows NT handle
+    OpaqueWin32,
+    /// windows global share handle
+    OpaqueWin32Kmt,
+}
+
+impl Into<vk::ExternalMemoryHandleTypeFlags> for ExternalHandleType {
+    fn into(self) -> vk::ExternalMemoryHandleTypeFlags {
+        match self {
+            Self::OpaqueFd => vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD,
+            Self::OpaqueWin32 => vk::ExternalMemoryHandleTypeFlags::OPAQUE_WIN32,
+            Self::OpaqueWin32Kmt => vk::ExternalMemoryHandleTypeFlags::OPAQUE_WIN32_KMT,
+        }
+    }
+}
+
+impl Into<vk::ExternalSemaphoreHandleTypeFlags> for ExternalHandleType {
+    fn into(self) -> vk::ExternalSemaphoreHandleTypeFlags {
+        match self {
+            Self::OpaqueFd => vk::ExternalSemaphoreHandleTypeFlags::OPAQUE_FD,
+            Self::OpaqueWin32 => vk::ExternalSemaphoreHandleTypeFlags::OPAQUE_WIN32,
+            Self::OpaqueWin32Kmt => vk::ExternalSemaphoreHandleTypeFlags::OPAQUE_WIN32_KMT,
+        }
+    }
+}
+
+/// A platform handle to an object shared with another api
+///
+/// Importing a Fd handle transfers ownership of the file descriptor to vulkan.
+/// Win32 handles are not consumed by importing and must still be closed by the caller
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ExternalHandle {
+    Fd(i32),
+    Win32(*mut c_void),
+    Win32Kmt(*mut c_void),
+}
+
+impl ExternalHandle {
+    /// The type of the handle
+    pub fn handle_type(&self) -> ExternalHandleType {
+        match self {
+            Self::Fd(_) => ExternalHandleType::OpaqueFd,
+            Self::Win32(_) => ExternalHandleType::OpaqueWin32,
+            Self::Win32Kmt(_) => ExternalHandleType::OpaqueWin32Kmt,
+        }
+    }
+}
+
+/// How the memory of a buffer or texture is shared
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum ExternalMemory {
+    /// allocate memory that can be exported later
+    Export(ExternalHandleType),
+    /// allocate memory from a handle
+    Import(ExternalHandle),
+}
+
+impl ExternalMemory {
+    pub fn handle_type(&self) -> ExternalHandleType {
+        match self {
+            Self::Export(ty) => *ty,
+            Self::Import(handle) => handle.handle_type(),
+        }
+    }
+}
+
+/// Storage for the structs chained into vk::MemoryAllocateInfo when allocating external memory
+///
+/// The structs must outlive the call to allocate_memory
+pub(crate) struct ExternalAllocateInfo {
+    export: vk::ExportMemoryAllocateInfo,
+    import_fd: vk::ImportMemoryFdInfoKHR,
+    import_win32: vk::ImportMemoryWin32HandleInfoKHR,
+}
+
+impl ExternalAllocateInfo {
+    pub fn new(external: ExternalMemory) -> Self {
+        let handle_type: vk::ExternalMemoryHandleTypeFlags = external.handle_type().into();
+        let (fd, handle) = match external {
+            ExternalMemory::Import(ExternalHandle::Fd(fd)) => (fd, ptr::null_mut()),
+            ExternalMemory::Import(ExternalHandle::Win32(h)) => (-1, h),
+            ExternalMemory::Import(ExternalHandle::Win32Kmt(h)) => (-1, h),
+            ExternalMemory::Export(_) => (-1, ptr::null_mut()),
+        };
+        Self {
+            export: vk::ExportMemoryAllocateInfo {
+                s_type: vk::StructureType::EXPORT_MEMORY_ALLOCATE_INFO,
+                p_next: ptr::null(),
+                handle_types: handle_type,
+            },
+            import_fd: vk::ImportMemoryFdInfoKHR {
+                s_type: vk::StructureType::IMPORT_MEMORY_FD_INFO_KHR,
+                p_next: ptr::null(),
+                handle_type,
+                fd,
+            },
+            import_win32: vk::ImportMemoryWin32HandleInfoKHR {
+                s_type: vk::StructureType::IMPORT_MEMORY_WIN32_HANDLE_INFO_KHR,
+                p_next: ptr::null(),
+                handle_type,
+                handle,
+                name: ptr::null(),
+            },
+        }
+    }
+
+    /// get the pointer to use as the p_next of vk::MemoryAllocateInfo
+    pub fn p_next(&self, external: ExternalMemory) -> *const c_void {
+        match external {
+            ExternalMemory::Export(_) => &self.export as *const _ as *const c_void,
+            ExternalMemory::Import(ExternalHandle::Fd(_)) => {
+                &self.import_fd as *const _ as *const c_void
+            }
+            ExternalMemory::Import(_) => &self.import_win32 as *const _ as *const c_void,
+        }
+    }
+}
+
+/// Export a handle to device memory that was allocated with an export handle type
+pub(crate) fn export_memory(
+    device: &crate::RawDevice,
+    memory: vk::DeviceMemory,
+    handle_type: ExternalHandleType,
+) -> Result<ExternalHandle, Error> {
+    match handle_type {
+        ExternalHandleType::OpaqueFd => {
+            let loader = khr::ExternalMemoryFd::new(&device.instance, &device.device);
+            let result = unsafe {
+                loader.get_memory_fd(&vk::MemoryGetFdInfoKHR {
+                    s_type: vk::StructureType::MEMORY_GET_FD_INFO_KHR,
+                    p_next: ptr::null(),
+                    memory,
+                    handle_type: handle_type.into(),
+                })
+            };
+            match result {
+                Ok(fd) => Ok(ExternalHandle::Fd(fd)),
+                Err(e) => Err(e.into()),
+            }
+        }
+        ExternalHandleType::OpaqueWin32 | ExternalHandleType::OpaqueWin32Kmt => {
+            let loader = khr::ExternalMemoryWin32::new(&device.instance, &device.device);
+            let result = unsafe {
+                loader.get_memory_win32_handle(&vk::MemoryGetWin32HandleInfoKHR {
+                    s_type: vk::StructureType::MEMORY_GET_WIN32_HANDLE_INFO_KHR,
+                    p_next: ptr::null(),
+                    memory,
+                    handle_type: handle_type.into(),
+                })
+            };
+            match result {
+                Ok(h) if handle_type == ExternalHandleType::OpaqueWin32 => {
+                    Ok(ExternalHandle::Win32(h))
+                }
+                Ok(h) => Ok(ExternalHandle::Win32Kmt(h)),
+                Err(e) => Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Describes an ExternalSemaphore
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExternalSemaphoreDesc {
+    /// the name of the semaphore
+    pub name: Option<String>,
+    /// the type of handle the semaphore can be exported as
+    pub handle_type: ExternalHandleType,
+}
+
+/// A semaphore that can be shared with other apis
+///
+/// Can be waited on or signaled by a [`crate::CommandBuffer`] with
+/// [`crate::CommandBuffer::wait_external_semaphore`] and [`crate::CommandBuffer::signal_external_semaphore`]
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkExportSemaphoreCreateInfo.html>
+pub struct ExternalSemaphore {
+    pub(crate) raw: Md<Arc<vk::Semaphore>>,
+    pub(crate) handle_type: ExternalHandleType,
+    pub(crate) device: Arc<crate::RawDevice>,
+    pub(crate) name: Option<String>,
+}
+
+impl std::fmt::Debug for ExternalSemaphore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ExternalSemaphore id: {:?} name: {:?}",
+            **self.raw, self.name
+        )
+    }
+}
+
+impl ExternalSemaphore {
+    pub unsafe fn raw_semaphore(&self) -> vk::Semaphore {
+        **self.raw
+    }
+}
+
+impl ExternalSemaphore {
+    /// Create a new semaphore that can be exported
+    pub fn new(device: &crate::Device, desc: &ExternalSemaphoreDesc) -> Result<Self, Error> {
+        #[cfg(feature = "logging")]
+        log::trace!("GPU: Create ExternalSemaphore, name {:?}", desc.name);
+
+        if !device
+            .raw
+            .features
+            .contains(crate::DeviceFeatures::EXTERNAL_SEMAPHORE)
+        {
+            panic!("ERROR: Attempt to create ExternalSemaphore without DeviceFeatures::EXTERNAL_SEMAPHORE enabled");
+        }
+
+        let export_info = vk::ExportSemaphoreCreateInfo {
+            s_type: vk::StructureType::EXPORT_SEMAPHORE_CREATE_INFO,
+            p_next: ptr::null(),
+            handle_types: desc.handle_type.into(),
+        };
+
+        let s = Self::raw_new(
+            device,
+            &export_info as *const _ as *const c_void,
+            desc.handle_type,
+            desc.name.clone(),
+        )?;
+        if let Some(name) = &desc.name {
+            device.raw.set_external_semaphore_name(&s, name)?;
+        }
+        device.raw.check_errors()?;
+        Ok(s)
+    }
+
+    /// Create a new semaphore with the payload of the handle
+    ///
+    /// Safety: the handle must be a valid semaphore handle exported by another api
+    pub unsafe fn import(
+        device: &crate::Device,
+        handle: ExternalHandle,
+        name: Option<String>,
+    ) -> Result<Self, Error> {
+        #[cfg(feature = "logging")]
+        log::trace!("GPU: Import ExternalSemaphore, name {:?}", name);
+
+        if !device
+            .raw
+            .features
+            .contains(crate::DeviceFeatures::EXTERNAL_SEMAPHORE)
+        {
+            panic!("ERROR: Attempt to import ExternalSemaphore without DeviceFeatures::EXTERNAL_SEMAPHORE enabled");
+        }
+
+        let s = Self::raw_new(device, ptr::null(), handle.handle_type(), name.clone())?;
+        let result = match handle {
+            ExternalHandle::Fd(fd) => {
+                let loader = khr::ExternalSemaphoreFd::new(&device.raw.instance, &device.raw.device);
+                loader.import_semaphore_fd(&vk::ImportSemaphoreFdInfoKHR {
+                    s_type: vk::StructureType::IMPORT_SEMAPHORE_FD_INFO_KHR,
+                    p_next: ptr::null(),
+                    semaphore: **s.raw,
+                    flags: vk::SemaphoreImportFlags::empty(),
+                    handle_type: vk::ExternalSemaphoreHandleTypeFlags::OPAQUE_FD,
+                    fd,
+                })
+            }
+            ExternalHandle::Win32(h) | ExternalHandle::Win32Kmt(h) => {
+                let loader =
+                    khr::ExternalSemaphoreWin32::new(&device.raw.instance, &device.raw.device);
+                loader.import_semaphore_win32_handle(&vk::ImportSemaphoreWin32HandleInfoKHR {
+                    s_type: vk::StructureType::IMPORT_SEMAPHORE_WIN32_HANDLE_INFO_KHR,
+                    p_next: ptr::null(),
+                    semaphore: **s.raw,
+                    flags: vk::SemaphoreImportFlags::empty(),
+                    handle_type: handle.handle_type().into(),
+                    handle: h,
+                    name: ptr::null(),
+                })
+            }
+        };
+        match result {
+            Ok(_) => (),
+            Err(e) => return Err(e.into()),
+        }
+        if let Some(name) = &name {
+            device.raw.set_external_semaphore_name(&s, name)?;
+        }
+        device.raw.check_errors()?;
+        Ok(s)
+    }
+
+    fn raw_new(
+        device: &crate::Device,
+        p_next: *const c_void,
+        handle_type: ExternalHandleType,
+        name: Option<String>,
+    ) -> Result<Self, Error> {
+        let create_info = vk::SemaphoreCreateInfo {
+            s_type: vk::StructureType::SEMAPHORE_CREATE_INFO,
+            p_next,
+            flags: vk::SemaphoreCreateFlags::empty(),
+        };
+
+        let raw_result = unsafe { device.raw.create_semaphore(&create_info, None) };
+
+        let raw = match raw_result {
+            Ok(r) => r,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            raw: Md::new(Arc::new(raw)),
+            handle_type,
+            device: Arc::clone(&device.raw),
+            name,
+        })
+    }
+
+    /// Export a handle to the semaphore
+    ///
+    /// The caller is responsible for closing the returned handle
+    pub fn export(&self) -> Result<ExternalHandle, Error> {
+        let result = match self.handle_type {
+            ExternalHandleType::OpaqueFd => {
+                let loader = khr::ExternalSemaphoreFd::new(&self.device.instance, &self.device.device);
+                unsafe {
+                    loader.get_semaphore_fd(&vk::SemaphoreGetFdInfoKHR {
+                        s_type: vk::StructureType::SEMAPHORE_GET_FD_INFO_KHR,
+                        p_next: ptr::null(),
+                        semaphore: **self.raw,
+                        handle_type: self.handle_type.into(),
+                    })
+                }
+                .map(|fd| ExternalHandle::Fd(fd))
+            }
+            ExternalHandleType::OpaqueWin32 | ExternalHandleType::OpaqueWin32Kmt => {
+                let loader =
+                    khr::ExternalSemaphoreWin32::new(&self.device.instance, &self.device.device);
+                unsafe {
+                    loader.get_semaphore_win32_handle(&vk::SemaphoreGetWin32HandleInfoKHR {
+                        s_type: vk::StructureType::SEMAPHORE_GET_WIN32_HANDLE_INFO_KHR,
+                        p_next: ptr::null(),
+                        semaphore: **self.raw,
+                        handle_type: self.handle_type.into(),
+                    })
+                }
+                .map(|h| {
+                    if self.handle_type == ExternalHandleType::OpaqueWin32 {
+                        ExternalHandle::Win32(h)
+                    } else {
+                        ExternalHandle::Win32Kmt(h)
+                    }
+                })
+            }
+        };
+        match result {
+            Ok(h) => Ok(h),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The type of handle the semaphore is shared with
+    pub fn handle_type(&self) -> ExternalHandleType {
+        self.handle_type
+    }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_external_semaphore_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
+}
+
+impl Drop for ExternalSemaphore {
+    fn drop(&mut self) {
+        unsafe {
+            let raw = Md::take(&mut self.raw);
+            if let Ok(raw) = Arc::try_unwrap(raw) {
+                self.device.destroy_semaphore(raw, None);
+            }
+        }
+    }
+}
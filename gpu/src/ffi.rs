@@ -9,6 +9,14 @@ pub(crate) unsafe extern "system" fn vulkan_debug_utils_callback(
     p_user_data: *mut c_void,
 ) -> vk::Bool32 {
     let raw_device = &*(p_user_data as *const crate::RawDevice);
+    if let Some(handler) = &*raw_device.validation_handler.read() {
+        let message =
+            crate::ValidationMessage::from_raw(message_severity, message_type, &*p_callback_data);
+        if message.severity >= handler.min_severity {
+            (handler.callback)(&message);
+        }
+        return vk::FALSE;
+    }
     let message = CStr::from_ptr((*p_callback_data).p_message)
         .to_str()
         .unwrap();
@@ -56,7 +64,7 @@ pub(crate) unsafe extern "system" fn vulkan_debug_utils_callback(
 
 #[cfg(target_os = "macos")]
 fn required_extension_names() -> Vec<&'static CStr> {
-    vec![ash::extensions::mvk::MacOSSurface::name()]
+    vec![ash::extensions::ext::MetalSurface::name()]
 }
 
 #[cfg(windows)]
@@ -78,9 +86,51 @@ fn required_extension_names() -> Vec<&'static CStr> {
     vec![ash::extensions::khr::AndroidSurface::name()]
 }
 
+/// Device extensions required by [`crate::DeviceFeatures::EXTERNAL_MEMORY`]
+#[cfg(windows)]
+pub(crate) fn external_memory_extension_names() -> Vec<&'static CStr> {
+    vec![
+        vk::KhrExternalMemoryFn::name(),
+        vk::KhrExternalMemoryWin32Fn::name(),
+    ]
+}
+
+/// Device extensions required by [`crate::DeviceFeatures::EXTERNAL_MEMORY`]
+#[cfg(not(windows))]
+pub(crate) fn external_memory_extension_names() -> Vec<&'static CStr> {
+    vec![
+        vk::KhrExternalMemoryFn::name(),
+        vk::KhrExternalMemoryFdFn::name(),
+    ]
+}
+
+/// Device extensions required by [`crate::DeviceFeatures::EXTERNAL_SEMAPHORE`]
+#[cfg(windows)]
+pub(crate) fn external_semaphore_extension_names() -> Vec<&'static CStr> {
+    vec![
+        vk::KhrExternalSemaphoreFn::name(),
+        vk::KhrExternalSemaphoreWin32Fn::name(),
+    ]
+}
+
+/// Device extensions required by [`crate::DeviceFeatures::EXTERNAL_SEMAPHORE`]
+#[cfg(not(windows))]
+pub(crate) fn external_semaphore_extension_names() -> Vec<&'static CStr> {
+    vec![
+        vk::KhrExternalSemaphoreFn::name(),
+        vk::KhrExternalSemaphoreFdFn::name(),
+    ]
+}
+
 pub(crate) fn extension_names() -> Vec<&'static CStr> {
     let mut v = required_extension_names();
     v.push(ash::extensions::khr::Surface::name());
+    // surfaces without a window for running on ci, ignored if unavailable
+    v.push(ash::extensions::ext::HeadlessSurface::name());
+    // needed for external memory and semaphores on vulkan 1.0, ignored if unavailable
+    v.push(vk::KhrGetPhysicalDeviceProperties2Fn::name());
+    v.push(vk::KhrExternalMemoryCapabilitiesFn::name());
+    v.push(vk::KhrExternalSemaphoreCapabilitiesFn::name());
     v.push(ash::extensions::khr::Swapchain::name());
     // v.push(ash::extensions::ext::DebugUtils::name());
     #[cfg(feature = "ray_tracing")]
@@ -39,8 +39,10 @@ pub trait AsFormat: Sized {
 }
 
 macro_rules! create_formats {
+    (@block) => { (1, 1) };
+    (@block $w:expr, $h:expr) => { ($w, $h) };
     (
-        $($name:ident => $vk:ident => $size:expr => ($($aspect:ident,)*),)*
+        $($name:ident => $vk:ident => $size:expr => ($($aspect:ident,)*) $(=> [$bw:expr, $bh:expr])?,)*
     ) => {
         /// Represents a format of a texture
         #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
@@ -62,6 +64,8 @@ macro_rules! create_formats {
             }
 
             /// returns the size in bytes of one pixel of this format
+            ///
+            /// for compressed formats this is the size of one block see [`Format::block_extent`]
             pub fn size(&self) -> usize {
                 match self {
                     $(
@@ -69,6 +73,17 @@ macro_rules! create_formats {
                     )*
                 }
             }
+
+            /// returns the width and height in texels of one block of this format
+            ///
+            /// (1, 1) for uncompressed formats
+            pub fn block_extent(&self) -> (u32, u32) {
+                match self {
+                    $(
+                        Self::$name => create_formats!(@block $($bw, $bh)?),
+                    )*
+                }
+            }
         }
 
         impl Into<vk::Format> for Format {
@@ -104,6 +119,92 @@ macro_rules! create_formats {
     };
 }
 
+impl Format {
+    /// returns true if the format is block compressed
+    pub fn is_compressed(&self) -> bool {
+        self.block_extent() != (1, 1) && !self.is_multi_planar()
+    }
+
+    /// returns true if the format stores YCbCr data in separate planes, eg decoded video frames
+    ///
+    /// Textures of these formats must be sampled through a [`crate::SamplerYcbcrConversion`]
+    pub fn is_multi_planar(&self) -> bool {
+        match self {
+            Self::Nv12Unorm | Self::I420Unorm | Self::P010Unorm => true,
+            _ => false,
+        }
+    }
+
+    /// returns true if the format stores unnormalized signed or unsigned integers
+    pub fn is_integer(&self) -> bool {
+        match self {
+            Self::R32Uint | Self::R32Sint | Self::R64Uint | Self::R64Sint => true,
+            Self::Rg32Uint | Self::Rg32Sint | Self::Rg64Uint | Self::Rg64Sint => true,
+            Self::Rgb32Uint | Self::Rgb32Sint | Self::Rgb64Uint | Self::Rgb64Sint => true,
+            Self::Rgba32Uint | Self::Rgba32Sint | Self::Rgba64Uint | Self::Rgba64Sint => true,
+            _ => false,
+        }
+    }
+
+    /// returns the size in bytes of a region of a texture of this format
+    ///
+    /// for compressed formats partial blocks at the edges are rounded up to whole blocks
+    pub fn data_size(&self, width: u32, height: u32, depth: u32) -> usize {
+        let (bw, bh) = self.block_extent();
+        let blocks_x = (width + bw - 1) / bw;
+        let blocks_y = (height + bh - 1) / bh;
+        self.size() * (blocks_x * blocks_y * depth) as usize
+    }
+
+    /// returns the format with the raw VkFormat value or [`Format::Unknown`] if not supported
+    pub fn from_raw(raw: i32) -> Format {
+        vk::Format::from_raw(raw).into()
+    }
+
+    /// returns true if the format stores color data in sRGB encoding
+    pub fn is_srgb(&self) -> bool {
+        self.to_linear() != *self
+    }
+
+    /// returns the sRGB equivalent of the format or the format unchanged if there isn't one
+    pub fn to_srgb(&self) -> Format {
+        match self {
+            Self::Rgb8Unorm => Self::Rgb8Srgb,
+            Self::Rgba8Unorm => Self::Rgba8Srgb,
+            Self::Bgr8Unorm => Self::Bgr8Srgb,
+            Self::Bgra8Unorm => Self::Bgra8Srgb,
+            Self::Bc1RgbUnorm => Self::Bc1RgbSrgb,
+            Self::Bc1RgbaUnorm => Self::Bc1RgbaSrgb,
+            Self::Bc2Unorm => Self::Bc2Srgb,
+            Self::Bc3Unorm => Self::Bc3Srgb,
+            Self::Bc7Unorm => Self::Bc7Srgb,
+            Self::Etc2Rgb8Unorm => Self::Etc2Rgb8Srgb,
+            Self::Etc2Rgb8A1Unorm => Self::Etc2Rgb8A1Srgb,
+            Self::Etc2Rgba8Unorm => Self::Etc2Rgba8Srgb,
+            f => *f,
+        }
+    }
+
+    /// returns the linear (Unorm) equivalent of the format or the format unchanged if there isn't one
+    pub fn to_linear(&self) -> Format {
+        match self {
+            Self::Rgb8Srgb => Self::Rgb8Unorm,
+            Self::Rgba8Srgb => Self::Rgba8Unorm,
+            Self::Bgr8Srgb => Self::Bgr8Unorm,
+            Self::Bgra8Srgb => Self::Bgra8Unorm,
+            Self::Bc1RgbSrgb => Self::Bc1RgbUnorm,
+            Self::Bc1RgbaSrgb => Self::Bc1RgbaUnorm,
+            Self::Bc2Srgb => Self::Bc2Unorm,
+            Self::Bc3Srgb => Self::Bc3Unorm,
+            Self::Bc7Srgb => Self::Bc7Unorm,
+            Self::Etc2Rgb8Srgb => Self::Etc2Rgb8Unorm,
+            Self::Etc2Rgb8A1Srgb => Self::Etc2Rgb8A1Unorm,
+            Self::Etc2Rgba8Srgb => Self::Etc2Rgba8Unorm,
+            f => *f,
+        }
+    }
+}
+
 /// Indicates that the T should be interpreted as Srgb
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Srgb(pub i8);
@@ -197,5 +298,68 @@ create_formats! {
     Depth16UnormStencil8Uint => D16_UNORM_S8_UINT      => 24      => (DEPTH, STENCIL,),
     Stencil8Uint             => S8_UINT                => 8       => (STENCIL,),
 
+    Bc1RgbUnorm              => BC1_RGB_UNORM_BLOCK    => 8       => (COLOR,) => [4, 4],
+    Bc1RgbSrgb               => BC1_RGB_SRGB_BLOCK     => 8       => (COLOR,) => [4, 4],
+    Bc1RgbaUnorm             => BC1_RGBA_UNORM_BLOCK   => 8       => (COLOR,) => [4, 4],
+    Bc1RgbaSrgb              => BC1_RGBA_SRGB_BLOCK    => 8       => (COLOR,) => [4, 4],
+    Bc2Unorm                 => BC2_UNORM_BLOCK        => 16      => (COLOR,) => [4, 4],
+    Bc2Srgb                  => BC2_SRGB_BLOCK         => 16      => (COLOR,) => [4, 4],
+    Bc3Unorm                 => BC3_UNORM_BLOCK        => 16      => (COLOR,) => [4, 4],
+    Bc3Srgb                  => BC3_SRGB_BLOCK         => 16      => (COLOR,) => [4, 4],
+    Bc4Unorm                 => BC4_UNORM_BLOCK        => 8       => (COLOR,) => [4, 4],
+    Bc4Snorm                 => BC4_SNORM_BLOCK        => 8       => (COLOR,) => [4, 4],
+    Bc5Unorm                 => BC5_UNORM_BLOCK        => 16      => (COLOR,) => [4, 4],
+    Bc5Snorm                 => BC5_SNORM_BLOCK        => 16      => (COLOR,) => [4, 4],
+    Bc6hUfloat               => BC6H_UFLOAT_BLOCK      => 16      => (COLOR,) => [4, 4],
+    Bc6hSfloat               => BC6H_SFLOAT_BLOCK      => 16      => (COLOR,) => [4, 4],
+    Bc7Unorm                 => BC7_UNORM_BLOCK        => 16      => (COLOR,) => [4, 4],
+    Bc7Srgb                  => BC7_SRGB_BLOCK         => 16      => (COLOR,) => [4, 4],
+
+    Etc2Rgb8Unorm            => ETC2_R8G8B8_UNORM_BLOCK   => 8    => (COLOR,) => [4, 4],
+    Etc2Rgb8Srgb             => ETC2_R8G8B8_SRGB_BLOCK    => 8    => (COLOR,) => [4, 4],
+    Etc2Rgb8A1Unorm          => ETC2_R8G8B8A1_UNORM_BLOCK => 8    => (COLOR,) => [4, 4],
+    Etc2Rgb8A1Srgb           => ETC2_R8G8B8A1_SRGB_BLOCK  => 8    => (COLOR,) => [4, 4],
+    Etc2Rgba8Unorm           => ETC2_R8G8B8A8_UNORM_BLOCK => 16   => (COLOR,) => [4, 4],
+    Etc2Rgba8Srgb            => ETC2_R8G8B8A8_SRGB_BLOCK  => 16   => (COLOR,) => [4, 4],
+    EacR11Unorm              => EAC_R11_UNORM_BLOCK       => 8    => (COLOR,) => [4, 4],
+    EacR11Snorm              => EAC_R11_SNORM_BLOCK       => 8    => (COLOR,) => [4, 4],
+    EacRg11Unorm             => EAC_R11G11_UNORM_BLOCK    => 16   => (COLOR,) => [4, 4],
+    EacRg11Snorm             => EAC_R11G11_SNORM_BLOCK    => 16   => (COLOR,) => [4, 4],
+
+    Astc4x4Unorm             => ASTC_4X4_UNORM_BLOCK   => 16      => (COLOR,) => [4, 4],
+    Astc4x4Srgb              => ASTC_4X4_SRGB_BLOCK    => 16      => (COLOR,) => [4, 4],
+    Astc5x4Unorm             => ASTC_5X4_UNORM_BLOCK   => 16      => (COLOR,) => [5, 4],
+    Astc5x4Srgb              => ASTC_5X4_SRGB_BLOCK    => 16      => (COLOR,) => [5, 4],
+    Astc5x5Unorm             => ASTC_5X5_UNORM_BLOCK   => 16      => (COLOR,) => [5, 5],
+    Astc5x5Srgb              => ASTC_5X5_SRGB_BLOCK    => 16      => (COLOR,) => [5, 5],
+    Astc6x5Unorm             => ASTC_6X5_UNORM_BLOCK   => 16      => (COLOR,) => [6, 5],
+    Astc6x5Srgb              => ASTC_6X5_SRGB_BLOCK    => 16      => (COLOR,) => [6, 5],
+    Astc6x6Unorm             => ASTC_6X6_UNORM_BLOCK   => 16      => (COLOR,) => [6, 6],
+    Astc6x6Srgb              => ASTC_6X6_SRGB_BLOCK    => 16      => (COLOR,) => [6, 6],
+    Astc8x5Unorm             => ASTC_8X5_UNORM_BLOCK   => 16      => (COLOR,) => [8, 5],
+    Astc8x5Srgb              => ASTC_8X5_SRGB_BLOCK    => 16      => (COLOR,) => [8, 5],
+    Astc8x6Unorm             => ASTC_8X6_UNORM_BLOCK   => 16      => (COLOR,) => [8, 6],
+    Astc8x6Srgb              => ASTC_8X6_SRGB_BLOCK    => 16      => (COLOR,) => [8, 6],
+    Astc8x8Unorm             => ASTC_8X8_UNORM_BLOCK   => 16      => (COLOR,) => [8, 8],
+    Astc8x8Srgb              => ASTC_8X8_SRGB_BLOCK    => 16      => (COLOR,) => [8, 8],
+    Astc10x5Unorm            => ASTC_10X5_UNORM_BLOCK  => 16      => (COLOR,) => [10, 5],
+    Astc10x5Srgb             => ASTC_10X5_SRGB_BLOCK   => 16      => (COLOR,) => [10, 5],
+    Astc10x6Unorm            => ASTC_10X6_UNORM_BLOCK  => 16      => (COLOR,) => [10, 6],
+    Astc10x6Srgb             => ASTC_10X6_SRGB_BLOCK   => 16      => (COLOR,) => [10, 6],
+    Astc10x8Unorm            => ASTC_10X8_UNORM_BLOCK  => 16      => (COLOR,) => [10, 8],
+    Astc10x8Srgb             => ASTC_10X8_SRGB_BLOCK   => 16      => (COLOR,) => [10, 8],
+    Astc10x10Unorm           => ASTC_10X10_UNORM_BLOCK => 16      => (COLOR,) => [10, 10],
+    Astc10x10Srgb            => ASTC_10X10_SRGB_BLOCK  => 16      => (COLOR,) => [10, 10],
+    Astc12x10Unorm           => ASTC_12X10_UNORM_BLOCK => 16      => (COLOR,) => [12, 10],
+    Astc12x10Srgb            => ASTC_12X10_SRGB_BLOCK  => 16      => (COLOR,) => [12, 10],
+    Astc12x12Unorm           => ASTC_12X12_UNORM_BLOCK => 16      => (COLOR,) => [12, 12],
+    Astc12x12Srgb            => ASTC_12X12_SRGB_BLOCK  => 16      => (COLOR,) => [12, 12],
+
+    // multi-planar video formats, sample with a SamplerYcbcrConversion
+    // a 2x2 block holds 4 luma samples and one sample of each chroma channel
+    Nv12Unorm                => G8_B8R8_2PLANE_420_UNORM  => 6    => (COLOR,) => [2, 2],
+    I420Unorm                => G8_B8_R8_3PLANE_420_UNORM => 6    => (COLOR,) => [2, 2],
+    P010Unorm                => G10X6_B10X6R10X6_2PLANE_420_UNORM_3PACK16 => 12 => (COLOR,) => [2, 2],
+
     Unknown                  => UNDEFINED              => 0     => (COLOR,),
 }
@@ -61,25 +61,19 @@
 //!
 //!                 let view = match swapchain.acquire(!0) {
 //!                     Ok((view, _)) => view,
-//!                     Err(e) => {
-//!                         if e.can_continue() {
-//!                             resized = true;
-//!                             return;
-//!                         } else {
-//!                             panic!("{}", e)
-//!                         }
+//!                     Err(gpu::Error::OutOfDateSwapchain) => {
+//!                         resized = true;
+//!                         return;
 //!                     }
+//!                     Err(e) => panic!("{}", e),
 //!                 };
 //!                 match swapchain.present(view) {
 //!                     Ok(_) => (),
-//!                     Err(e) => {
-//!                         if e.can_continue() {
-//!                             resized = true;
-//!                             return;
-//!                         } else {
-//!                             panic!("{}", e);
-//!                         }
+//!                     Err(gpu::Error::OutOfDateSwapchain) => {
+//!                         resized = true;
+//!                         return;
 //!                     }
+//!                     Err(e) => panic!("{}", e),
 //!                 }
 //!             }
 //!             _ => (),
@@ -94,12 +88,14 @@ use ash::{vk, Entry};
 use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::HashSet;
-use std::ffi::{CStr, CString};
+use std::ffi::{c_void, CStr, CString};
 use std::mem::ManuallyDrop as Md;
 use std::ptr;
 use std::sync::Arc;
 use vk::Handle;
 
+use parking_lot::RwLock;
+
 use raw_window_handle::HasRawWindowHandle;
 
 pub mod binding;
@@ -108,16 +104,21 @@ pub mod command;
 pub mod data;
 pub mod device;
 pub mod error;
+pub mod event;
+pub mod external;
 mod ffi;
 pub mod format;
 pub mod pass;
 pub mod pipeline;
 pub mod query;
+pub mod queue;
 pub mod sampler;
+pub mod semaphore;
 pub mod shader;
 pub mod surface;
 pub mod swapchain;
 pub mod texture;
+pub mod validation;
 
 pub use binding::*;
 pub use buffer::*;
@@ -125,16 +126,21 @@ pub use command::*;
 pub use data::*;
 pub use device::*;
 pub use error::*;
+pub use event::*;
+pub use external::*;
 use ffi::*;
 pub use format::*;
 pub use pass::*;
 pub use pipeline::*;
 pub use query::*;
+pub use queue::*;
 pub use sampler::*;
+pub use semaphore::*;
 pub use shader::*;
 pub use surface::*;
 pub use swapchain::*;
 pub use texture::*;
+pub use validation::*;
 
 /// Makes `&[u8]` into `&[u32]` ensuring correct alignment
 ///
@@ -238,7 +244,12 @@ pub struct Instance {
     pub(crate) raw: Md<Arc<ash::Instance>>,
 
     pub(crate) extension_names: Vec<&'static CStr>,
+    /// the extensions from extension_names that were available and enabled on the instance
+    pub(crate) enabled_extensions: HashSet<&'static CStr>,
     pub(crate) validation_layers: Vec<CString>,
+    pub(crate) api_version: u32,
+
+    pub(crate) validation_handler: Arc<RwLock<Option<crate::ValidationHandler>>>,
 }
 
 impl std::fmt::Debug for Instance {
@@ -337,6 +348,11 @@ impl Instance {
             .iter()
             .map(|e| CStr::from_ptr(&e.extension_name[0]).to_str().unwrap())
             .collect::<HashSet<_>>();
+        let enabled_extensions = extension_names
+            .iter()
+            .filter(|n| available_extension_set.contains(n.to_str().unwrap()))
+            .cloned()
+            .collect::<HashSet<_>>();
         // TODO check this with user supplied extensions
         let pp_enabled_extension_names = extension_names
             .iter()
@@ -414,12 +430,37 @@ impl Instance {
                 raw: Md::new(Arc::new(raw)),
 
                 extension_names,
+                enabled_extensions,
                 validation_layers,
+                api_version,
+
+                validation_handler: Arc::new(RwLock::new(None)),
             },
             validation_available,
         ))
     }
 
+    /// Route validation messages to a user function instead of the default logging
+    ///
+    /// Only messages at least as severe as min_severity are passed to the callback.
+    /// While a handler is set validation errors are no longer collected and returned as
+    /// [`Error::Validation`], applies to all devices created from this instance
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/PFN_vkDebugUtilsMessengerCallbackEXT.html>
+    pub fn set_validation_handler<F>(&self, min_severity: crate::ValidationSeverity, callback: F)
+    where
+        F: Fn(&crate::ValidationMessage) + Send + Sync + 'static,
+    {
+        *self.validation_handler.write() = Some(crate::ValidationHandler {
+            min_severity,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Remove the handler set by [`Instance::set_validation_handler`] and restore the default behaviour
+    pub fn clear_validation_handler(&self) {
+        *self.validation_handler.write() = None;
+    }
+
     /// Get infomation about all the devices that are available
     pub fn devices(&self) -> Result<Vec<crate::DeviceInfo>, Error> {
         let devices_result = unsafe { self.raw.enumerate_physical_devices() };
@@ -496,7 +537,20 @@ impl Instance {
             self.raw
                 .get_physical_device_memory_properties(physical_device)
         };
-        let limits = properties.limits;
+        let limits = properties.limits.into();
+        let features = self.device_features(physical_device, api)?;
+        let queue_families = unsafe {
+            self.raw
+                .get_physical_device_queue_family_properties(physical_device)
+        }
+        .iter()
+        .enumerate()
+        .map(|(i, f)| crate::QueueFamilyInfo {
+            index: i as u32,
+            count: f.queue_count,
+            flags: f.queue_flags.into(),
+        })
+        .collect::<Vec<_>>();
         Ok(crate::DeviceInfo {
             id: physical_device.as_raw(),
             name,
@@ -516,9 +570,222 @@ impl Instance {
             },
             mem_properties,
             limits,
+            features,
+            queue_families,
         })
     }
 
+    /// Query the features that a physical device supports
+    fn device_features(
+        &self,
+        physical_device: vk::PhysicalDevice,
+        device_api: u32,
+    ) -> Result<crate::DeviceFeatures, Error> {
+        let core = unsafe { self.raw.get_physical_device_features(physical_device) };
+        let mut features = crate::DeviceFeatures::from(core);
+
+        let queue_flags = unsafe {
+            self.raw
+                .get_physical_device_queue_family_properties(physical_device)
+        }
+        .iter()
+        .fold(vk::QueueFlags::empty(), |acc, f| acc | f.queue_flags);
+        features.set(
+            crate::DeviceFeatures::GRAPHICS,
+            queue_flags.contains(vk::QueueFlags::GRAPHICS),
+        );
+        features.set(
+            crate::DeviceFeatures::COMPUTE,
+            queue_flags.contains(vk::QueueFlags::COMPUTE),
+        );
+        // graphics and compute queues implicitly support transfer operations
+        features.set(
+            crate::DeviceFeatures::TRANSFER,
+            queue_flags.intersects(
+                vk::QueueFlags::TRANSFER | vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE,
+            ),
+        );
+
+        let available_extensions_result =
+            unsafe { self.raw.enumerate_device_extension_properties(physical_device) };
+        let available_extensions = match available_extensions_result {
+            Ok(e) => e,
+            Err(e) => return Err(e.into()),
+        };
+        let available_extension_set = available_extensions
+            .iter()
+            .map(|e| unsafe { CStr::from_ptr(&e.extension_name[0]) })
+            .collect::<HashSet<_>>();
+        features.set(
+            crate::DeviceFeatures::SAMPLER_REDUCTION,
+            available_extension_set.contains(vk::ExtSamplerFilterMinmaxFn::name()),
+        );
+        features.set(
+            crate::DeviceFeatures::EXTERNAL_MEMORY,
+            crate::ffi::external_memory_extension_names()
+                .iter()
+                .all(|n| available_extension_set.contains(n)),
+        );
+        features.set(
+            crate::DeviceFeatures::EXTERNAL_SEMAPHORE,
+            crate::ffi::external_semaphore_extension_names()
+                .iter()
+                .all(|n| available_extension_set.contains(n)),
+        );
+
+        // the extended feature structs can be queried with vulkan 1.1 or VK_KHR_get_physical_device_properties2
+        let api = self.api_version.min(device_api);
+        let properties2 = api >= vk::API_VERSION_1_1
+            || self
+                .enabled_extensions
+                .contains(vk::KhrGetPhysicalDeviceProperties2Fn::name());
+        let dynamic_rendering_extension =
+            available_extension_set.contains(vk::KhrDynamicRenderingFn::name());
+        let present_wait_extensions = available_extension_set.contains(vk::KhrPresentIdFn::name())
+            && available_extension_set.contains(vk::KhrPresentWaitFn::name());
+        // features that are core in vulkan 1.2 are available through their extensions before that
+        let host_query_reset_available = api >= vk::API_VERSION_1_2
+            || available_extension_set.contains(vk::ExtHostQueryResetFn::name());
+        let float16_available = api >= vk::API_VERSION_1_2
+            || available_extension_set.contains(vk::KhrShaderFloat16Int8Fn::name());
+        let descriptor_indexing_available = api >= vk::API_VERSION_1_2
+            || (available_extension_set.contains(vk::ExtDescriptorIndexingFn::name())
+                && available_extension_set.contains(vk::KhrMaintenance3Fn::name()));
+        features.set(
+            crate::DeviceFeatures::TEXTURE_3D_SLICES,
+            api >= vk::API_VERSION_1_1
+                || available_extension_set.contains(vk::KhrMaintenance1Fn::name()),
+        );
+        if properties2 {
+            // chain together the structs of the features supported by the api version or available extensions
+            let mut p_next: *mut c_void = ptr::null_mut();
+
+            let mut present_wait = vk::PhysicalDevicePresentWaitFeaturesKHR {
+                s_type: vk::StructureType::PHYSICAL_DEVICE_PRESENT_WAIT_FEATURES_KHR,
+                p_next: ptr::null_mut(),
+                present_wait: vk::FALSE,
+            };
+            let mut present_id = vk::PhysicalDevicePresentIdFeaturesKHR {
+                s_type: vk::StructureType::PHYSICAL_DEVICE_PRESENT_ID_FEATURES_KHR,
+                p_next: ptr::null_mut(),
+                present_id: vk::FALSE,
+            };
+            if present_wait_extensions {
+                present_wait.p_next = p_next;
+                present_id.p_next = &mut present_wait as *mut _ as *mut c_void;
+                p_next = &mut present_id as *mut _ as *mut c_void;
+            }
+
+            let mut dynamic_rendering = vk::PhysicalDeviceDynamicRenderingFeatures {
+                s_type: vk::StructureType::PHYSICAL_DEVICE_DYNAMIC_RENDERING_FEATURES,
+                p_next: ptr::null_mut(),
+                dynamic_rendering: vk::FALSE,
+            };
+            if dynamic_rendering_extension {
+                dynamic_rendering.p_next = p_next;
+                p_next = &mut dynamic_rendering as *mut _ as *mut c_void;
+            }
+
+            let mut ycbcr = vk::PhysicalDeviceSamplerYcbcrConversionFeatures {
+                s_type: vk::StructureType::PHYSICAL_DEVICE_SAMPLER_YCBCR_CONVERSION_FEATURES,
+                p_next: ptr::null_mut(),
+                sampler_ycbcr_conversion: vk::FALSE,
+            };
+            let mut storage16 = vk::PhysicalDevice16BitStorageFeatures {
+                s_type: vk::StructureType::PHYSICAL_DEVICE_16BIT_STORAGE_FEATURES,
+                p_next: ptr::null_mut(),
+                ..Default::default()
+            };
+            if api >= vk::API_VERSION_1_1 {
+                ycbcr.p_next = p_next;
+                storage16.p_next = &mut ycbcr as *mut _ as *mut c_void;
+                p_next = &mut storage16 as *mut _ as *mut c_void;
+            }
+
+            let mut descriptor_indexing = vk::PhysicalDeviceDescriptorIndexingFeatures {
+                s_type: vk::StructureType::PHYSICAL_DEVICE_DESCRIPTOR_INDEXING_FEATURES,
+                p_next: ptr::null_mut(),
+                ..Default::default()
+            };
+            if descriptor_indexing_available {
+                descriptor_indexing.p_next = p_next;
+                p_next = &mut descriptor_indexing as *mut _ as *mut c_void;
+            }
+
+            let mut host_query_reset = vk::PhysicalDeviceHostQueryResetFeatures {
+                s_type: vk::StructureType::PHYSICAL_DEVICE_HOST_QUERY_RESET_FEATURES,
+                p_next: ptr::null_mut(),
+                host_query_reset: vk::FALSE,
+            };
+            if host_query_reset_available {
+                host_query_reset.p_next = p_next;
+                p_next = &mut host_query_reset as *mut _ as *mut c_void;
+            }
+
+            let mut float16 = vk::PhysicalDeviceShaderFloat16Int8Features {
+                s_type: vk::StructureType::PHYSICAL_DEVICE_SHADER_FLOAT16_INT8_FEATURES,
+                p_next: ptr::null_mut(),
+                shader_float16: vk::FALSE,
+                shader_int8: vk::FALSE,
+            };
+            if float16_available {
+                float16.p_next = p_next;
+                p_next = &mut float16 as *mut _ as *mut c_void;
+            }
+
+            let mut features2 = vk::PhysicalDeviceFeatures2 {
+                s_type: vk::StructureType::PHYSICAL_DEVICE_FEATURES_2,
+                p_next,
+                features: core,
+            };
+            if api >= vk::API_VERSION_1_1 {
+                unsafe {
+                    self.raw
+                        .get_physical_device_features2(physical_device, &mut features2)
+                };
+            } else {
+                let properties2 =
+                    ash::extensions::khr::GetPhysicalDeviceProperties2::new(&*VK_ENTRY, &self.raw);
+                unsafe {
+                    properties2.get_physical_device_features2(physical_device, &mut features2)
+                };
+            }
+            features.set(
+                crate::DeviceFeatures::SAMPLER_YCBCR_CONVERSION,
+                ycbcr.sampler_ycbcr_conversion == vk::TRUE,
+            );
+            features.set(
+                crate::DeviceFeatures::DYNAMIC_RENDERING,
+                dynamic_rendering_extension && dynamic_rendering.dynamic_rendering == vk::TRUE,
+            );
+            features.set(
+                crate::DeviceFeatures::PRESENT_WAIT,
+                present_wait_extensions
+                    && present_id.present_id == vk::TRUE
+                    && present_wait.present_wait == vk::TRUE,
+            );
+            features.set(
+                crate::DeviceFeatures::STORAGE_16_BIT,
+                storage16.storage_buffer16_bit_access == vk::TRUE
+                    && storage16.uniform_and_storage_buffer16_bit_access == vk::TRUE,
+            );
+            features.set(
+                crate::DeviceFeatures::SHADER_FLOAT_16,
+                float16.shader_float16 == vk::TRUE,
+            );
+            features.set(
+                crate::DeviceFeatures::TIME_QUERIES,
+                host_query_reset.host_query_reset == vk::TRUE,
+            );
+            features.set(
+                crate::DeviceFeatures::DESCRIPTOR_INDEXING,
+                descriptor_indexing.shader_sampled_image_array_non_uniform_indexing == vk::TRUE,
+            );
+        }
+
+        Ok(features)
+    }
+
     /// create a new surface
     pub fn create_surface<W: HasRawWindowHandle>(
         &self,
@@ -527,6 +794,17 @@ impl Instance {
         crate::Surface::new(self, window)
     }
 
+    /// create a new surface that isn't connected to a window
+    pub fn create_headless_surface(&self) -> Result<crate::Surface, Error> {
+        crate::Surface::headless(self)
+    }
+
+    /// Returns true if the instance extension with the name is used by this library and was available when creating the instance
+    /// eg to check if surfaces can be created for the platform before creating a window
+    pub fn extension_enabled(&self, name: &str) -> bool {
+        self.enabled_extensions.iter().any(|e| e.to_str() == Ok(name))
+    }
+
     /// create a new device
     pub fn create_device<F: Fn(&DeviceInfo, &DeviceInfo) -> Ordering>(
         &self,
@@ -541,8 +819,23 @@ impl Instance {
         id: u64,
         features: crate::DeviceFeatures,
         compatible_surfaces: &'_ [&'_ crate::Surface],
+        queues: &'_ [crate::QueueKind],
+    ) -> Result<crate::Device, Error> {
+        crate::Device::from_id(self, id, features, compatible_surfaces, queues)
+    }
+
+    /// Create a replacement for a device, usually after it has been lost
+    ///
+    /// The new device uses the same physical device, features and queues as the old one
+    /// and functions registered with [`crate::Device::on_recreate`] are called with it.
+    /// The old device and everything created from it should be dropped afterwards
+    /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/html/vkspec.html#devsandqueues-lost-device>
+    pub fn recreate_device(
+        &self,
+        device: &crate::Device,
+        compatible_surfaces: &'_ [&'_ crate::Surface],
     ) -> Result<crate::Device, Error> {
-        crate::Device::from_id(self, id, features, compatible_surfaces)
+        device.recreate(self, compatible_surfaces)
     }
 }
 
@@ -1,4 +1,7 @@
 //! A [`RenderPass`] describes what images will be rendered to by a [`crate::GraphicsPipeline`]
+//!
+//! If the device is created with [`crate::DeviceFeatures::DYNAMIC_RENDERING`] no render pass or framebuffer objects are created,
+//! the description is used to begin rendering directly and attachments are transitioned between layouts with barriers
 
 use std::mem::ManuallyDrop as Md;
 use std::ptr;
@@ -20,6 +23,9 @@ pub struct RenderPassDesc<'a> {
     /// Color attachment descriptions
     pub colors: &'a [crate::ColorAttachmentDesc],
     /// Resolve attachment descriptions
+    ///
+    /// Either empty or one per color attachment, each color attachment is resolved into the
+    /// matching resolve attachment at the end of the pass
     pub resolves: &'a [crate::ResolveAttachmentDesc],
     /// Depth attachment description
     pub depth: Option<crate::DepthAttachmentDesc>,
@@ -29,7 +35,9 @@ pub struct RenderPassDesc<'a> {
 
 /// RenderPass
 ///
+/// With dynamic rendering the raw render pass is null and only the description is kept
 /// <https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/VkRenderPass.html>
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VK_KHR_dynamic_rendering.html>
 pub struct RenderPass {
     pub(crate) raw: Md<Arc<vk::RenderPass>>,
     pub(crate) name: Option<String>,
@@ -40,15 +48,24 @@ pub struct RenderPass {
     pub(crate) depth: Option<crate::DepthAttachmentDesc>,
 }
 
+// the description is compared as well as the handle since every pass has a null handle with dynamic rendering
 impl std::hash::Hash for RenderPass {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        (**self.raw).hash(state)
+        (**self.raw).hash(state);
+        self.samples.hash(state);
+        self.colors.hash(state);
+        self.resolves.hash(state);
+        self.depth.hash(state);
     }
 }
 
 impl PartialEq for RenderPass {
     fn eq(&self, other: &RenderPass) -> bool {
         **self.raw == **other.raw
+            && self.samples == other.samples
+            && self.colors == other.colors
+            && self.resolves == other.resolves
+            && self.depth == other.depth
     }
 }
 
@@ -83,6 +100,36 @@ impl RenderPass {
 impl RenderPass {
     /// Create a new RenderPass
     pub fn new(device: &crate::Device, desc: &RenderPassDesc<'_>) -> Result<Self, crate::Error> {
+        if desc.resolves.len() != 0 {
+            if desc.resolves.len() != desc.colors.len() {
+                panic!(
+                    "ERROR: RenderPass {:?} has {} resolve attachments but {} color attachments, must have one resolve per color",
+                    desc.name,
+                    desc.resolves.len(),
+                    desc.colors.len()
+                );
+            }
+            if desc.samples == crate::Samples::S1 {
+                panic!(
+                    "ERROR: RenderPass {:?} has resolve attachments but isn't multisampled",
+                    desc.name
+                );
+            }
+        }
+
+        if device.raw.dynamic_rendering.is_some() {
+            let s = Self {
+                raw: Md::new(Arc::new(vk::RenderPass::null())),
+                device: Arc::clone(&device.raw),
+                name: desc.name.as_ref().map(|n| n.to_string()),
+                samples: desc.samples,
+                colors: desc.colors.to_vec().into(),
+                resolves: desc.resolves.to_vec().into(),
+                depth: desc.depth.clone(),
+            };
+            return Ok(s);
+        }
+
         let mut attachments = desc
             .colors
             .iter()
@@ -119,14 +166,24 @@ impl RenderPass {
         );
 
         if let Some(d) = desc.depth {
+            // formats with a stencil aspect load and store it the same as depth
+            let (stencil_load_op, stencil_store_op) =
+                if d.format.aspects().contains(crate::TextureAspects::STENCIL) {
+                    (d.load.into(), d.store.into())
+                } else {
+                    (
+                        vk::AttachmentLoadOp::DONT_CARE,
+                        vk::AttachmentStoreOp::DONT_CARE,
+                    )
+                };
             attachments.push(vk::AttachmentDescription {
                 flags: vk::AttachmentDescriptionFlags::empty(),
                 format: d.format.into(),
                 samples: desc.samples.into(),
                 load_op: d.load.into(),
                 store_op: d.store.into(),
-                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
-                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                stencil_load_op,
+                stencil_store_op,
                 initial_layout: d.initial_layout.into(),
                 final_layout: d.final_layout.into(),
             })
@@ -249,6 +306,14 @@ impl RenderPass {
     pub fn depth(&self) -> Option<crate::DepthAttachmentDesc> {
         self.depth.clone()
     }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_render_pass_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
 }
 
 impl Drop for RenderPass {
@@ -256,7 +321,9 @@ impl Drop for RenderPass {
         unsafe {
             let raw = Md::take(&mut self.raw);
             if let Ok(raw) = Arc::try_unwrap(raw) {
-                self.device.destroy_render_pass(raw, None);
+                if raw != vk::RenderPass::null() {
+                    self.device.destroy_render_pass(raw, None);
+                }
             }
         }
     }
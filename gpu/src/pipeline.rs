@@ -1,5 +1,6 @@
 //! [`PipelineLayout`] describes the inputs to either a [`GraphicsPipeline`] or [`ComputePipeline`]
 
+use std::ffi::c_void;
 use std::mem::ManuallyDrop as Md;
 use std::ptr;
 use std::sync::Arc;
@@ -120,6 +121,14 @@ impl PipelineLayout {
     pub fn name(&self) -> Option<&str> {
         self.name.as_ref().map(|n| &**n)
     }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_pipeline_layout_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
 }
 
 impl Drop for PipelineLayout {
@@ -273,6 +282,14 @@ impl PipelineCache {
 
         Ok(data)
     }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_pipeline_cache_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
 }
 
 impl Drop for PipelineCache {
@@ -312,9 +329,17 @@ pub struct GraphicsPipelineDesc<'a> {
     /// how the depth testing should be performed
     pub depth_stencil: Option<crate::DepthStencilState>,
     /// what portion of the texture to render to
+    /// the scissor is dynamic state and is reset to cover each viewport when the pipeline is bound
     pub viewports: &'a [crate::Viewport],
     /// cached pipeline creation data
     pub cache: Option<&'a PipelineCache>,
+    /// a pipeline that this one is a variant of
+    ///
+    /// Creating pipelines that share most of their state with the base (eg differ only in blend state or attachment formats)
+    /// as derivatives can be faster and allows the driver to switch between them more cheaply.
+    /// All graphics pipelines are created allowing derivatives so any pipeline can be a base
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/html/vkspec.html#pipelines-pipeline-derivatives>
+    pub base: Option<&'a GraphicsPipeline>,
 }
 
 /// A GraphicsPipeline
@@ -326,6 +351,9 @@ pub struct GraphicsPipeline {
     pub(crate) name: Option<String>,
     pub(crate) layout: PipelineLayout,
     pub(crate) pass: crate::RenderPass,
+    pub(crate) scissors: Arc<Vec<crate::Scissor>>,
+    /// (front, back) stencil state, the masks and reference are dynamic state set when the pipeline is bound
+    pub(crate) stencil: Option<(vk::StencilOpState, vk::StencilOpState)>,
     pub(crate) raw: Md<Arc<vk::Pipeline>>,
     pub(crate) device: Arc<crate::RawDevice>,
 }
@@ -350,6 +378,8 @@ impl Clone for GraphicsPipeline {
             name: self.name.clone(),
             layout: self.layout.clone(),
             pass: self.pass.clone(),
+            scissors: Arc::clone(&self.scissors),
+            stencil: self.stencil,
             raw: Md::new(Arc::clone(&self.raw)),
             device: Arc::clone(&self.device),
         }
@@ -541,18 +571,18 @@ impl GraphicsPipeline {
         let depth_state: Option<vk::PipelineDepthStencilStateCreateInfo> =
             desc.depth_stencil.map(|s| s.into());
 
-        let scissors = desc
+        // the scissor is dynamic state, these are the defaults set when the pipeline is bound
+        let default_scissors = desc
             .viewports
             .iter()
-            .map(|v| vk::Rect2D {
-                offset: vk::Offset2D { x: 0, y: 0 },
-                extent: vk::Extent2D {
-                    width: v.width as _,
-                    height: v.height,
-                },
-            })
+            .map(|v| crate::Scissor::from(*v))
             .collect::<Vec<_>>();
 
+        let scissors = default_scissors
+            .iter()
+            .map(|s| (*s).into())
+            .collect::<Vec<vk::Rect2D>>();
+
         let viewports = desc
             .viewports
             .iter()
@@ -569,10 +599,67 @@ impl GraphicsPipeline {
             p_viewports: viewports.as_ptr(),
         };
 
+        // the stencil masks and reference are dynamic state, the values in depth_stencil are set when the pipeline is bound
+        let stencil = depth_state
+            .filter(|d| d.stencil_test_enable == vk::TRUE)
+            .map(|d| (d.front, d.back));
+
+        let dynamic_states = if stencil.is_some() {
+            vec![
+                vk::DynamicState::SCISSOR,
+                vk::DynamicState::STENCIL_COMPARE_MASK,
+                vk::DynamicState::STENCIL_WRITE_MASK,
+                vk::DynamicState::STENCIL_REFERENCE,
+            ]
+        } else {
+            vec![vk::DynamicState::SCISSOR]
+        };
+
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_DYNAMIC_STATE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::PipelineDynamicStateCreateFlags::empty(),
+            dynamic_state_count: dynamic_states.len() as _,
+            p_dynamic_states: dynamic_states.as_ptr(),
+        };
+
+        let mut flags = vk::PipelineCreateFlags::ALLOW_DERIVATIVES;
+        if desc.base.is_some() {
+            flags |= vk::PipelineCreateFlags::DERIVATIVE;
+        }
+
+        // with dynamic rendering the attachment formats are given to the pipeline instead of a render pass
+        let color_formats = desc
+            .pass
+            .colors
+            .iter()
+            .map(|c| c.format.into())
+            .collect::<Vec<vk::Format>>();
+        let depth_format = desc.pass.depth.map(|d| d.format);
+        let rendering_info = vk::PipelineRenderingCreateInfo {
+            s_type: vk::StructureType::PIPELINE_RENDERING_CREATE_INFO,
+            p_next: ptr::null(),
+            view_mask: 0,
+            color_attachment_count: color_formats.len() as u32,
+            p_color_attachment_formats: color_formats.as_ptr(),
+            depth_attachment_format: depth_format
+                .filter(|f| f.aspects().contains(crate::TextureAspects::DEPTH))
+                .map(|f| f.into())
+                .unwrap_or(vk::Format::UNDEFINED),
+            stencil_attachment_format: depth_format
+                .filter(|f| f.aspects().contains(crate::TextureAspects::STENCIL))
+                .map(|f| f.into())
+                .unwrap_or(vk::Format::UNDEFINED),
+        };
+
         let create_info = vk::GraphicsPipelineCreateInfo {
             s_type: vk::StructureType::GRAPHICS_PIPELINE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineCreateFlags::empty(),
+            p_next: if device.raw.dynamic_rendering.is_some() {
+                &rendering_info as *const _ as *const c_void
+            } else {
+                ptr::null()
+            },
+            flags,
             stage_count: shader_stages.len() as _,
             p_stages: shader_stages.as_ptr(),
             p_vertex_input_state: &vertex_state,
@@ -591,12 +678,12 @@ impl GraphicsPipeline {
                 ptr::null()
             },
             p_color_blend_state: &color_blend_state,
-            p_dynamic_state: ptr::null(),
+            p_dynamic_state: &dynamic_state,
             layout: **desc.layout.raw,
             render_pass: **desc.pass.raw,
             subpass: 0,
-            base_pipeline_handle: vk::Pipeline::null(),
-            base_pipeline_index: 0,
+            base_pipeline_handle: desc.base.map(|b| **b.raw).unwrap_or(vk::Pipeline::null()),
+            base_pipeline_index: -1,
         };
 
         // let mut create_info = vk::GraphicsPipelineCreateInfo::builder()
@@ -634,6 +721,8 @@ impl GraphicsPipeline {
             name: desc.name.as_ref().map(|s| s.to_string()),
             layout: desc.layout.clone(),
             pass: desc.pass.clone(),
+            scissors: Arc::new(default_scissors),
+            stencil,
             raw: Md::new(Arc::new(raw)),
             device: Arc::clone(&device.raw),
         };
@@ -657,10 +746,23 @@ impl GraphicsPipeline {
         &self.pass
     }
 
+    /// Get the scissors that are set when self is bound, one for each viewport covering the viewport
+    pub fn default_scissors(&self) -> &[crate::Scissor] {
+        &self.scissors
+    }
+
     /// Get the name of self
     pub fn name(&self) -> Option<&str> {
         self.name.as_ref().map(|n| &**n)
     }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_graphics_pipeline_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
 }
 
 impl Drop for GraphicsPipeline {
@@ -833,6 +935,14 @@ impl ComputePipeline {
     pub fn name(&self) -> Option<&str> {
         self.name.as_ref().map(|n| &**n)
     }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_compute_pipeline_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
 }
 
 impl Drop for ComputePipeline {
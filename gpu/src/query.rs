@@ -231,6 +231,14 @@ impl TimeQuery {
     ) -> Result<Vec<Duration>, crate::Error> {
         Ok(self.pair_stamps_to_times(self.get_results(first_query, query_count)?))
     }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_time_query_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
 }
 
 impl Drop for TimeQuery {
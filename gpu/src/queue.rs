@@ -0,0 +1,36 @@
+//! A [`Queue`] is what [`crate::CommandBuffer`]s are submitted to
+//!
+//! The device always has one main queue which is used by default, additional queues can be requested
+//! in [`crate::DeviceDesc::queues`] to schedule compute or transfer work asynchronously
+
+use ash::vk;
+
+/// A Queue
+///
+/// Obtained from [`crate::Device::queue`] and used when creating command buffers with
+/// [`crate::Device::create_queue_command_buffer`]
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkQueue.html>
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Queue {
+    pub(crate) raw: vk::Queue,
+    pub(crate) family: u32,
+    pub(crate) kind: crate::QueueKind,
+}
+
+impl Queue {
+    pub unsafe fn raw_queue(&self) -> vk::Queue {
+        self.raw
+    }
+}
+
+impl Queue {
+    /// Get the index of the queue family that this queue belongs to
+    pub fn family(&self) -> u32 {
+        self.family
+    }
+
+    /// Get the kind of work that this queue was requested for
+    pub fn kind(&self) -> crate::QueueKind {
+        self.kind
+    }
+}
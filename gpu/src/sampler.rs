@@ -159,6 +159,7 @@ impl Default for SamplerDesc {
 pub struct Sampler {
     pub(crate) name: Option<String>,
     pub(crate) raw: Md<Arc<vk::Sampler>>,
+    pub(crate) ycbcr_conversion: Option<SamplerYcbcrConversion>,
     pub(crate) device: Arc<crate::RawDevice>,
 }
 
@@ -181,6 +182,7 @@ impl Clone for Sampler {
         Self {
             name: self.name.clone(),
             raw: Md::new(Arc::clone(&self.raw)),
+            ycbcr_conversion: self.ycbcr_conversion.clone(),
             device: Arc::clone(&self.device),
         }
     }
@@ -195,12 +197,53 @@ impl std::fmt::Debug for Sampler {
 impl Sampler {
     /// Create a new Sampler
     pub fn new(device: &crate::Device, desc: &SamplerDesc) -> Result<Self, Error> {
+        Self::raw_new(device, desc, None)
+    }
+
+    /// Create a new Sampler that converts YCbCr texels to RGB
+    ///
+    /// The sampler must be used as an immutable sampler in the descriptor layout, see
+    /// [`crate::DescriptorLayoutDesc::immutable_samplers`], and the textures sampled must be viewed with
+    /// [`crate::Texture::create_ycbcr_view`] using the same conversion. Wrap modes must be
+    /// [`crate::WrapMode::ClampToEdge`] and anisotropy and compare must be None
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkSamplerYcbcrConversionInfo.html>
+    pub fn new_ycbcr(
+        device: &crate::Device,
+        desc: &SamplerDesc,
+        conversion: &SamplerYcbcrConversion,
+    ) -> Result<Self, Error> {
+        if desc.wrap_x != crate::WrapMode::ClampToEdge
+            || desc.wrap_y != crate::WrapMode::ClampToEdge
+            || desc.wrap_z != crate::WrapMode::ClampToEdge
+        {
+            panic!("ERROR: Attempt to create YCbCr Sampler with wrap mode other than ClampToEdge");
+        }
+        if desc.max_anisotropy.is_some() || desc.compare.is_some() {
+            panic!("ERROR: Attempt to create YCbCr Sampler with anisotropy or compare enabled");
+        }
+        Self::raw_new(device, desc, Some(conversion))
+    }
+
+    fn raw_new(
+        device: &crate::Device,
+        desc: &SamplerDesc,
+        conversion: Option<&SamplerYcbcrConversion>,
+    ) -> Result<Self, Error> {
         #[cfg(feature = "logging")]
         log::trace!("GPU: Create Sampler, name {:?}", desc.name);
 
+        let conversion_info = conversion.map(|c| vk::SamplerYcbcrConversionInfo {
+            s_type: vk::StructureType::SAMPLER_YCBCR_CONVERSION_INFO,
+            p_next: ptr::null(),
+            conversion: **c.raw,
+        });
+
         let create_info = vk::SamplerCreateInfo {
             s_type: vk::StructureType::SAMPLER_CREATE_INFO,
-            p_next: ptr::null(),
+            p_next: match &conversion_info {
+                Some(info) => info as *const _ as *const _,
+                None => ptr::null(),
+            },
             flags: vk::SamplerCreateFlags::empty(),
             address_mode_u: desc.wrap_x.into(),
             address_mode_v: desc.wrap_y.into(),
@@ -240,6 +283,7 @@ impl Sampler {
         let s = Self {
             name: desc.name.as_ref().map(|s| s.to_string()),
             raw: Md::new(Arc::new(raw)),
+            ycbcr_conversion: conversion.cloned(),
             device: Arc::clone(&device.raw),
         };
 
@@ -252,10 +296,23 @@ impl Sampler {
         Ok(s)
     }
 
+    /// Get the YCbCr conversion the sampler was created with if any
+    pub fn ycbcr_conversion(&self) -> Option<&SamplerYcbcrConversion> {
+        self.ycbcr_conversion.as_ref()
+    }
+
     /// Get the id of the sampler
     pub fn id(&self) -> u64 {
         unsafe { std::mem::transmute(**self.raw) }
     }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_sampler_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
 }
 
 impl Drop for Sampler {
@@ -268,3 +325,164 @@ impl Drop for Sampler {
         }
     }
 }
+
+/// Describes a SamplerYcbcrConversion
+#[derive(Debug, Clone, PartialEq)]
+pub struct SamplerYcbcrConversionDesc {
+    /// the name of the conversion
+    pub name: Option<String>,
+    /// the multi-planar format of the textures to be sampled, see [`crate::Format::is_multi_planar`]
+    pub format: crate::Format,
+    /// the color model to convert with
+    pub model: crate::YcbcrModel,
+    /// the range of the encoded values
+    pub range: crate::YcbcrRange,
+    /// where the chroma samples are located horizontally
+    pub chroma_x: crate::ChromaLocation,
+    /// where the chroma samples are located vertically
+    pub chroma_y: crate::ChromaLocation,
+    /// how to filter when reconstructing chroma samples
+    pub chroma_filter: crate::FilterMode,
+}
+
+impl SamplerYcbcrConversionDesc {
+    /// A description for BT.709 narrow range video, what most decoders produce
+    pub fn video(format: crate::Format, name: Option<String>) -> Self {
+        Self {
+            name,
+            format,
+            model: crate::YcbcrModel::Ycbcr709,
+            range: crate::YcbcrRange::Narrow,
+            chroma_x: crate::ChromaLocation::CositedEven,
+            chroma_y: crate::ChromaLocation::Midpoint,
+            chroma_filter: crate::FilterMode::Linear,
+        }
+    }
+}
+
+/// A SamplerYcbcrConversion
+///
+/// Describes how YCbCr texels such as those of decoded video frames are converted to RGB when sampled.
+/// Used to create samplers with [`Sampler::new_ycbcr`] and views with [`crate::Texture::create_ycbcr_view`]
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkSamplerYcbcrConversion.html>
+pub struct SamplerYcbcrConversion {
+    pub(crate) name: Option<String>,
+    pub(crate) raw: Md<Arc<vk::SamplerYcbcrConversion>>,
+    pub(crate) format: crate::Format,
+    pub(crate) device: Arc<crate::RawDevice>,
+}
+
+impl PartialEq for SamplerYcbcrConversion {
+    fn eq(&self, other: &SamplerYcbcrConversion) -> bool {
+        **self.raw == **other.raw
+    }
+}
+
+impl Eq for SamplerYcbcrConversion {}
+
+impl std::hash::Hash for SamplerYcbcrConversion {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (**self.raw).hash(state)
+    }
+}
+
+impl Clone for SamplerYcbcrConversion {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            raw: Md::new(Arc::clone(&self.raw)),
+            format: self.format,
+            device: Arc::clone(&self.device),
+        }
+    }
+}
+
+impl std::fmt::Debug for SamplerYcbcrConversion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SamplerYcbcrConversion id: {:?} name: {:?}", **self.raw, self.name)
+    }
+}
+
+impl SamplerYcbcrConversion {
+    pub unsafe fn raw_sampler_ycbcr_conversion(&self) -> vk::SamplerYcbcrConversion {
+        **self.raw
+    }
+}
+
+impl SamplerYcbcrConversion {
+    /// Create a new SamplerYcbcrConversion
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCreateSamplerYcbcrConversion.html>
+    pub fn new(device: &crate::Device, desc: &SamplerYcbcrConversionDesc) -> Result<Self, Error> {
+        #[cfg(feature = "logging")]
+        log::trace!("GPU: Create SamplerYcbcrConversion, name {:?}", desc.name);
+
+        if !device
+            .raw
+            .features
+            .contains(crate::DeviceFeatures::SAMPLER_YCBCR_CONVERSION)
+        {
+            panic!("ERROR: Attempt to create SamplerYcbcrConversion without DeviceFeatures::SAMPLER_YCBCR_CONVERSION enabled");
+        }
+
+        let create_info = vk::SamplerYcbcrConversionCreateInfo {
+            s_type: vk::StructureType::SAMPLER_YCBCR_CONVERSION_CREATE_INFO,
+            p_next: ptr::null(),
+            format: desc.format.into(),
+            ycbcr_model: desc.model.into(),
+            ycbcr_range: desc.range.into(),
+            components: vk::ComponentMapping {
+                r: vk::ComponentSwizzle::IDENTITY,
+                g: vk::ComponentSwizzle::IDENTITY,
+                b: vk::ComponentSwizzle::IDENTITY,
+                a: vk::ComponentSwizzle::IDENTITY,
+            },
+            x_chroma_offset: desc.chroma_x.into(),
+            y_chroma_offset: desc.chroma_y.into(),
+            chroma_filter: desc.chroma_filter.into(),
+            force_explicit_reconstruction: vk::FALSE,
+        };
+
+        let raw_result = unsafe { device.raw.create_sampler_ycbcr_conversion(&create_info, None) };
+
+        let raw = match raw_result {
+            Ok(r) => r,
+            Err(e) => return Err(e.into()),
+        };
+
+        let s = Self {
+            name: desc.name.clone(),
+            raw: Md::new(Arc::new(raw)),
+            format: desc.format,
+            device: Arc::clone(&device.raw),
+        };
+
+        if let Some(name) = &desc.name {
+            device.raw.set_sampler_ycbcr_conversion_name(&s, name)?;
+        }
+
+        device.raw.check_errors()?;
+
+        Ok(s)
+    }
+
+    /// Get the format the conversion was created for
+    pub fn format(&self) -> crate::Format {
+        self.format
+    }
+
+    /// Get the id of the conversion
+    pub fn id(&self) -> u64 {
+        unsafe { std::mem::transmute(**self.raw) }
+    }
+}
+
+impl Drop for SamplerYcbcrConversion {
+    fn drop(&mut self) {
+        unsafe {
+            let raw = Md::take(&mut self.raw);
+            if let Ok(raw) = Arc::try_unwrap(raw) {
+                self.device.destroy_sampler_ycbcr_conversion(raw, None);
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! [`Semaphore`]'s are used to synchronize work submitted to different queues
+
+use ash::vk;
+
+use std::mem::ManuallyDrop as Md;
+use std::ptr;
+use std::sync::Arc;
+
+/// A Semaphore
+///
+/// Signaled by one submission with [`crate::CommandBuffer::signal_semaphore`] and waited on by another with
+/// [`crate::CommandBuffer::wait_semaphore`], for example so that work on a graphics queue waits for an upload on a transfer queue.
+/// Every signal must be waited on exactly once before the semaphore is signaled again
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkSemaphore.html>
+pub struct Semaphore {
+    pub(crate) name: Option<String>,
+    pub(crate) raw: Md<Arc<vk::Semaphore>>,
+    pub(crate) device: Arc<crate::RawDevice>,
+}
+
+impl PartialEq for Semaphore {
+    fn eq(&self, other: &Semaphore) -> bool {
+        **self.raw == **other.raw
+    }
+}
+
+impl Eq for Semaphore {}
+
+impl std::hash::Hash for Semaphore {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (**self.raw).hash(state)
+    }
+}
+
+impl Clone for Semaphore {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            raw: Md::new(Arc::clone(&self.raw)),
+            device: Arc::clone(&self.device),
+        }
+    }
+}
+
+impl std::fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Semaphore id: {:?} name: {:?}", **self.raw, self.name)
+    }
+}
+
+impl Semaphore {
+    pub unsafe fn raw_semaphore(&self) -> vk::Semaphore {
+        **self.raw
+    }
+}
+
+impl Semaphore {
+    /// Create a new semaphore in the unsignaled state
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCreateSemaphore.html>
+    pub fn new(device: &crate::Device, name: Option<&str>) -> Result<Self, crate::Error> {
+        #[cfg(feature = "logging")]
+        log::trace!("GPU: Create Semaphore, name {:?}", name);
+
+        let create_info = vk::SemaphoreCreateInfo {
+            s_type: vk::StructureType::SEMAPHORE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::SemaphoreCreateFlags::empty(),
+        };
+
+        let result = unsafe { device.raw.create_semaphore(&create_info, None) };
+
+        let raw = match result {
+            Ok(s) => s,
+            Err(e) => return Err(e.into()),
+        };
+
+        let s = Self {
+            name: name.map(|n| n.to_string()),
+            raw: Md::new(Arc::new(raw)),
+            device: Arc::clone(&device.raw),
+        };
+
+        if let Some(name) = name {
+            device.raw.set_semaphore_name(&s, name)?;
+        }
+
+        device.raw.check_errors()?;
+
+        Ok(s)
+    }
+
+    /// Get the id of the semaphore
+    pub fn id(&self) -> u64 {
+        unsafe { std::mem::transmute(**self.raw) }
+    }
+}
+
+impl Drop for Semaphore {
+    fn drop(&mut self) {
+        unsafe {
+            let raw = Md::take(&mut self.raw);
+            if let Ok(raw) = Arc::try_unwrap(raw) {
+                self.device.destroy_semaphore(raw, None);
+            }
+        }
+    }
+}
@@ -25,6 +25,8 @@ pub struct ShaderModule {
     pub(crate) name: Option<String>,
     pub(crate) raw: Md<Arc<vk::ShaderModule>>,
     pub(crate) map: HashMap<crate::ShaderStages, CString>,
+    /// kept to recreate the module on a new device
+    pub(crate) spirv: Arc<[u32]>,
     pub(crate) device: Arc<crate::RawDevice>,
 }
 
@@ -48,6 +50,7 @@ impl Clone for ShaderModule {
             name: self.name.clone(),
             raw: Md::new(Arc::clone(&self.raw)),
             map: self.map.clone(),
+            spirv: Arc::clone(&self.spirv),
             device: Arc::clone(&self.device),
         }
     }
@@ -101,6 +104,7 @@ impl ShaderModule {
             name: desc.name.clone(),
             raw: Md::new(Arc::new(raw)),
             map,
+            spirv: desc.spirv.into(),
             device: Arc::clone(&device.raw),
         };
 
@@ -113,10 +117,35 @@ impl ShaderModule {
         Ok(s)
     }
 
+    /// Create the same shader module on another device, see [`crate::Device::on_recreate`]
+    pub fn recreate(&self, device: &crate::Device) -> Result<Self, Error> {
+        let entries = self
+            .map
+            .iter()
+            .map(|(stage, entry)| (*stage, entry.to_str().unwrap()))
+            .collect::<Vec<_>>();
+        Self::new(
+            device,
+            &ShaderModuleDesc {
+                name: self.name.clone(),
+                entries: &entries,
+                spirv: &self.spirv,
+            },
+        )
+    }
+
     /// Get the id of the shader module
     pub fn id(&self) -> u64 {
         unsafe { std::mem::transmute(**self.raw) }
     }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_shader_module_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
 }
 
 impl Drop for ShaderModule {
@@ -1,7 +1,13 @@
 //! [`Surface`]'s connect vulkan to a specific window
 //!
 //! Use a surface to create a [`crate::Swapchain`]
+//!
+//! Window surfaces need the instance extension for the platform (VK_KHR_win32_surface, VK_KHR_xlib_surface,
+//! VK_KHR_xcb_surface, VK_KHR_wayland_surface, VK_EXT_metal_surface through MoltenVK) which is enabled if available
+//! when the instance is created, if it isn't [`crate::Error::MissingExtension`] is returned.
+//! Headless surfaces use VK_EXT_headless_surface so swapchains can be created without a window, eg on ci
 
+use std::ffi::CStr;
 use std::mem::ManuallyDrop as Md;
 use std::ptr;
 use std::sync::Arc;
@@ -40,6 +46,8 @@ pub struct SurfaceInfo {
 pub struct Surface {
     pub(crate) raw: Md<Arc<vk::SurfaceKHR>>,
     pub(crate) loader: khr::Surface,
+    /// true if the surface was created with [`Surface::headless`] and has no extent of its own
+    pub(crate) headless: bool,
 }
 
 impl std::fmt::Debug for Surface {
@@ -75,11 +83,40 @@ impl Surface {
                 Self::create_surface_from_windows(instance, h)
             },
             #[cfg(target_os = "macos")]
-            RawWindowHandle::MacOS(h) => {}
-            h => panic!("ERROR: Can't create surface from window of type {:?}", h),
+            RawWindowHandle::MacOS(h) => unsafe { Self::create_surface_from_macos(instance, h) },
+            h => panic!("ERROR: Can't create surface from window of type {:?} on this platform", h),
         }
     }
 
+    /// Create a new Surface that isn't connected to a window
+    ///
+    /// Swapchains created from the surface must set [`crate::SwapchainDesc::extent`]
+    /// as the surface doesn't have an extent of it's own
+    pub fn headless(instance: &crate::Instance) -> Result<Self, Error> {
+        #[cfg(feature = "logging")]
+        log::trace!("GPU: Create Headless Surface");
+
+        check_extension(instance, ash::extensions::ext::HeadlessSurface::name())?;
+
+        let headless_loader = ash::extensions::ext::HeadlessSurface::new(&*crate::VK_ENTRY, &**instance.raw);
+        let info = vk::HeadlessSurfaceCreateInfoEXT {
+            s_type: vk::StructureType::HEADLESS_SURFACE_CREATE_INFO_EXT,
+            p_next: ptr::null(),
+            flags: vk::HeadlessSurfaceCreateFlagsEXT::empty(),
+        };
+        let surface_result = unsafe { headless_loader.create_headless_surface(&info, None) };
+        let surface = match surface_result {
+            Ok(s) => s,
+            Err(e) => return Err(e.into()),
+        };
+        let loader = khr::Surface::new(&*crate::VK_ENTRY, &**instance.raw);
+        Ok(Self {
+            raw: Md::new(Arc::new(surface)),
+            loader,
+            headless: true,
+        })
+    }
+
     /// Get infomation about the surface
     pub fn info(&self, device: &crate::Device) -> Result<SurfaceInfo, Error> {
         let raw_formats_result = unsafe {
@@ -141,6 +178,8 @@ impl Surface {
         instance: &crate::Instance,
         h: raw_window_handle::unix::XlibHandle,
     ) -> Result<Self, Error> {
+        check_extension(instance, khr::XlibSurface::name())?;
+
         let xlib_loader = khr::XlibSurface::new(&*crate::VK_ENTRY, &**instance.raw);
         let info = vk::XlibSurfaceCreateInfoKHR {
             s_type: vk::StructureType::XLIB_SURFACE_CREATE_INFO_KHR,
@@ -158,6 +197,7 @@ impl Surface {
         Ok(Self {
             raw: Md::new(Arc::new(surface)),
             loader,
+            headless: false,
         })
     }
 
@@ -166,6 +206,8 @@ impl Surface {
         instance: &crate::Instance,
         h: raw_window_handle::unix::XcbHandle,
     ) -> Result<Self, Error> {
+        check_extension(instance, khr::XcbSurface::name())?;
+
         let xcb_loader = khr::XcbSurface::new(&*crate::VK_ENTRY, &**instance.raw);
         let info = vk::XcbSurfaceCreateInfoKHR {
             s_type: vk::StructureType::XCB_SURFACE_CREATE_INFO_KHR,
@@ -183,6 +225,7 @@ impl Surface {
         Ok(Self {
             raw: Md::new(Arc::new(surface)),
             loader,
+            headless: false,
         })
     }
 
@@ -191,6 +234,8 @@ impl Surface {
         instance: &crate::Instance,
         h: raw_window_handle::unix::WaylandHandle,
     ) -> Result<Self, Error> {
+        check_extension(instance, khr::WaylandSurface::name())?;
+
         let wayland_loader = khr::WaylandSurface::new(&*crate::VK_ENTRY, &**instance.raw);
         let info = vk::WaylandSurfaceCreateInfoKHR {
             s_type: vk::StructureType::WAYLAND_SURFACE_CREATE_INFO_KHR,
@@ -208,6 +253,7 @@ impl Surface {
         Ok(Self {
             raw: Md::new(Arc::new(surface)),
             loader,
+            headless: false,
         })
     }
 
@@ -216,6 +262,8 @@ impl Surface {
         instance: &crate::Instance,
         h: raw_window_handle::android::AndroidHandle,
     ) -> Result<Self, Error> {
+        check_extension(instance, khr::AndroidSurface::name())?;
+
         let a_loader = khr::AndroidSurface::new(&*crate::VK_ENTRY, &**instance.raw);
         let info = vk::AndroidSurfaceCreateInfoKHR {
             s_type: vk::StructureType::ANDROID_SURFACE_CREATE_INFO_KHR,
@@ -231,7 +279,8 @@ impl Surface {
         let loader = khr::Surface::new(&*crate::VK_ENTRY, &**instance.raw);
         Ok(Self {
             raw: Md::new(Arc::new(surface)),
-            loader: Arc::new(loader),
+            loader,
+            headless: false,
         })
     }
 
@@ -240,6 +289,8 @@ impl Surface {
         instance: &crate::Instance,
         h: raw_window_handle::windows::WindowsHandle,
     ) -> Result<Self, Error> {
+        check_extension(instance, khr::Win32Surface::name())?;
+
         let win_loader = khr::Win32Surface::new(&*crate::VK_ENTRY, &**instance.raw);
         let info = vk::Win32SurfaceCreateInfoKHR::builder()
             .flags(vk::Win32SurfaceCreateFlagsKHR::empty())
@@ -253,7 +304,8 @@ impl Surface {
         let loader = khr::Surface::new(&*crate::VK_ENTRY, &**instance.raw);
         Ok(Self {
             raw: Md::new(Arc::new(surface)),
-            loader: Arc::new(loader),
+            loader,
+            headless: false,
         })
     }
 
@@ -261,36 +313,35 @@ impl Surface {
     unsafe fn create_surface_from_macos(
         instance: &crate::Instance,
         h: raw_window_handle::macos::MacOSHandle,
-    ) -> Result<Self, error::SurfaceCreation> {
+    ) -> Result<Self, Error> {
         use cocoa::appkit::{NSView, NSWindow};
         use cocoa::base::id as cocoa_id;
-        use metal::CoreAnimationLayer;
         use objc::runtime::YES;
         use std::mem;
-        use std::os::raw::c_void;
 
+        check_extension(instance, ash::extensions::ext::MetalSurface::name())?;
+
+        // MoltenVK presents to a CAMetalLayer backing the view
         let wnd: cocoa_id = mem::transmute(h.ns_window);
-        let layer = CoreAnimationlayer::new();
+        let view: cocoa_id = mem::transmute(h.ns_view);
+        let layer = metal::MetalLayer::new();
 
         layer.set_edge_antialiasing_mask(0);
         layer.set_presents_with_transaction(false);
         layer.remove_all_animations();
+        layer.set_contents_scale(wnd.backingScaleFactor());
 
-        let view = wnd.contentView();
-
-        layer.set_contents_scale(view.backingScaleFactor());
         view.setLayer(mem::transmute(layer.as_ref()));
         view.setWantsLayer(YES);
 
-        let create_info = vk::MacOSSurfaceCreateInfoMVK {
-            s_type: vk::StructureType::MACOS_SURFACE_CREATE_INFO_M,
+        let metal_loader = ash::extensions::ext::MetalSurface::new(&*crate::VK_ENTRY, &**instance.raw);
+        let info = vk::MetalSurfaceCreateInfoEXT {
+            s_type: vk::StructureType::METAL_SURFACE_CREATE_INFO_EXT,
             p_next: ptr::null(),
-            flags: Default::default(),
-            p_view: window.ns_view() as *const c_void,
+            flags: vk::MetalSurfaceCreateFlagsEXT::empty(),
+            p_layer: layer.as_ref() as *const metal::MetalLayerRef as *const vk::CAMetalLayer,
         };
-
-        let macos_surface_loader = MacOSSurface::new(entry, instance);
-        let surface_result = macos_surface_loader.create_macos_surface_mvk(&create_info, None);
+        let surface_result = metal_loader.create_metal_surface(&info, None);
         let surface = match surface_result {
             Ok(s) => s,
             Err(e) => return Err(e.into()),
@@ -298,11 +349,21 @@ impl Surface {
         let loader = khr::Surface::new(&*crate::VK_ENTRY, &**instance.raw);
         Ok(Self {
             raw: Md::new(Arc::new(surface)),
-            loader: Arc::new(loader),
+            loader,
+            headless: false,
         })
     }
 }
 
+/// Returns [`Error::MissingExtension`] if the instance extension needed to create a surface isn't enabled
+fn check_extension(instance: &crate::Instance, name: &'static CStr) -> Result<(), Error> {
+    if instance.enabled_extensions.contains(name) {
+        Ok(())
+    } else {
+        Err(Error::MissingExtension(name.to_str().unwrap()))
+    }
+}
+
 impl Drop for Surface {
     fn drop(&mut self) {
         unsafe {
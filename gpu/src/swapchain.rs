@@ -28,6 +28,10 @@ pub struct SwapchainDesc {
     /// the maximum number of frames that are allowed
     /// to be being computed simultaniously
     pub frames_in_flight: usize,
+    /// the extent of the images if the surface doesn't define one (eg headless or wayland surfaces)
+    /// ignored when the surface has a current extent, required for headless surfaces
+    /// if None for other surfaces without an extent the largest supported extent is used
+    pub extent: Option<crate::Extent2D>,
     /// the name of the swapchain, used for debugging
     pub name: Option<String>,
 }
@@ -48,6 +52,7 @@ impl SwapchainDesc {
             texture_count,
             texture_usage: crate::TextureUsage::COLOR_OUTPUT,
             frames_in_flight: texture_count as _,
+            extent: None,
             name: None,
         })
     }
@@ -79,6 +84,27 @@ impl<'a> std::fmt::Debug for SwapchainView<'a> {
     }
 }
 
+impl<'a> SwapchainView<'a> {
+    /// The semaphore signaled when the view has been acquired
+    ///
+    /// Waited on by the first command buffer that renders to the view
+    pub unsafe fn raw_acquire_semaphore(&self) -> vk::Semaphore {
+        *self.inner.acquire_complete_semaphores[self.wait_semaphore]
+    }
+
+    /// The semaphore signaled when rendering to the view is complete
+    ///
+    /// Waited on by the presentation engine before the view is shown
+    pub unsafe fn raw_present_semaphore(&self) -> vk::Semaphore {
+        *self.inner.rendering_complete_semaphores[self.signal_semaphore]
+    }
+
+    /// The index of the texture in the swapchain that the view is of
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
 impl<'a> PartialEq for SwapchainView<'a> {
     fn eq(&self, _: &Self) -> bool {
         // swapchain views are always unique
@@ -139,12 +165,22 @@ pub struct Swapchain {
 
     pub(crate) format: vk::SurfaceFormatKHR,
     pub(crate) extent: vk::Extent2D,
+    /// the extent to use when the surface doesn't define one
+    pub(crate) requested_extent: Option<vk::Extent2D>,
     pub(crate) pre_transform: vk::SurfaceTransformFlagsKHR,
     pub(crate) present_mode: vk::PresentModeKHR,
     pub(crate) image_count: u32,
+    /// the usage the swapchain was created with, kept to recreate it on another device
+    pub(crate) usage: crate::TextureUsage,
+    pub(crate) name: Option<String>,
 
     pub(crate) frames_in_flight: usize,
     pub(crate) frame: Cell<usize>,
+
+    /// Some if the device has [`crate::DeviceFeatures::PRESENT_WAIT`]
+    pub(crate) present_wait: Option<khr::PresentWait>,
+    /// the id of the last present, 0 if nothing has been presented
+    pub(crate) present_id: Cell<u64>,
 }
 
 impl std::fmt::Debug for Swapchain {
@@ -231,6 +267,12 @@ impl Swapchain {
 
         let image_count = textures.len() as u32;
 
+        let present_wait = if device.raw.features.contains(crate::DeviceFeatures::PRESENT_WAIT) {
+            Some(khr::PresentWait::new(&**device.raw.instance, &**device.raw))
+        } else {
+            None
+        };
+
         let s = Self {
             inner: SwapchainInner {
                 loader,
@@ -252,15 +294,21 @@ impl Swapchain {
 
             format,
             extent,
+            requested_extent: desc.extent.map(|e| e.into()),
             pre_transform,
             present_mode: desc.present_mode.into(),
             image_count,
+            usage: desc.texture_usage,
+            name: desc.name.clone(),
 
             version: 0,
             queue: device.queue,
 
             frames_in_flight: desc.frames_in_flight,
             frame: Cell::new(0),
+
+            present_wait,
+            present_id: Cell::new(0),
         };
 
         if let Some(name) = &desc.name {
@@ -326,15 +374,10 @@ impl Swapchain {
             caps.current_transform
         };
 
-        let mut image_extent = caps.current_extent;
-        image_extent.width = image_extent
-            .width
-            .min(caps.max_image_extent.width)
-            .max(caps.min_image_extent.width);
-        image_extent.height = image_extent
-            .height
-            .min(caps.max_image_extent.height)
-            .max(caps.min_image_extent.height);
+        if surface.headless && desc.extent.is_none() {
+            panic!("ERROR: Attempt to create swapchain for headless surface without an extent, set SwapchainDesc::extent");
+        }
+        let image_extent = Self::choose_extent(&caps, desc.extent.map(|e| e.into()));
 
         let create_info = vk::SwapchainCreateInfoKHR {
             s_type: vk::StructureType::SWAPCHAIN_CREATE_INFO_KHR,
@@ -364,7 +407,30 @@ impl Swapchain {
             Err(e) => return Err(e.into()),
         };
 
-        return Ok((swapchain, format, caps.current_extent, pre_transform));
+        return Ok((swapchain, format, image_extent, pre_transform));
+    }
+
+    /// Pick the extent of the swapchain images
+    ///
+    /// A current extent of u32::MAX means the extent is determined by the swapchain
+    /// in which case the requested extent is used if there is one
+    fn choose_extent(
+        caps: &vk::SurfaceCapabilitiesKHR,
+        requested: Option<vk::Extent2D>,
+    ) -> vk::Extent2D {
+        let mut image_extent = match requested {
+            Some(e) if caps.current_extent.width == u32::MAX => e,
+            _ => caps.current_extent,
+        };
+        image_extent.width = image_extent
+            .width
+            .min(caps.max_image_extent.width)
+            .max(caps.min_image_extent.width);
+        image_extent.height = image_extent
+            .height
+            .min(caps.max_image_extent.height)
+            .max(caps.min_image_extent.height);
+        image_extent
     }
 
     fn create_frames(
@@ -393,6 +459,8 @@ impl Swapchain {
                     mem_ty: crate::MemoryType::Device,
                     mip_levels: 1,
                     initial_layout: crate::TextureLayout::SwapchainPresent,
+                    external: None,
+                    exclusive: false,
                     dimension: crate::TextureDimension::D2(
                         extent.width,
                         extent.height,
@@ -453,6 +521,51 @@ impl Swapchain {
         Ok((semaphores_1, semaphores_2))
     }
 
+    /// Create a swapchain with the same properties as this one on another device
+    /// for example after the device was lost, see [`crate::Device::on_recreate`]
+    ///
+    /// A surface can only have one swapchain so self is destroyed before the new swapchain is created
+    pub fn recreate_on(
+        self,
+        device: &crate::Device,
+        surface: &crate::Surface,
+    ) -> Result<Self, crate::Error> {
+        let desc = SwapchainDesc {
+            format: self.format.format.into(),
+            present_mode: self.present_mode.into(),
+            texture_count: self.image_count,
+            texture_usage: self.usage,
+            frames_in_flight: self.frames_in_flight,
+            extent: Some(self.requested_extent.unwrap_or(self.extent).into()),
+            name: self.name.clone(),
+        };
+
+        // command buffers from the old device may still reference the swapchain
+        // so destroy it explicitly and leave a null handle for them to drop
+        let inner = self.inner.clone();
+        drop(self);
+        unsafe {
+            inner.loader.destroy_swapchain(inner.raw.get(), None);
+        }
+        inner.raw.set(vk::SwapchainKHR::null());
+        drop(inner);
+
+        Self::new(device, surface, &desc)
+    }
+
+    /// Recreate the swapchain with a new extent
+    ///
+    /// Use when the window is resized if the surface doesn't define an extent of its own
+    /// (eg wayland or headless surfaces), otherwise the extent is ignored and this is the same as [`Swapchain::recreate`]
+    pub fn resize(
+        &mut self,
+        device: &crate::Device,
+        extent: crate::Extent2D,
+    ) -> Result<(), crate::Error> {
+        self.requested_extent = Some(extent.into());
+        self.recreate(device)
+    }
+
     pub fn recreate(&mut self, device: &crate::Device) -> Result<(), crate::Error> {
         // destroy previous resources
         for texture in self.textures.drain(..) {
@@ -484,13 +597,16 @@ impl Swapchain {
             Err(e) => return Err(e.into()),
         };
 
+        // surfaces without an extent use the last extent requested, see Swapchain::resize
+        let image_extent = Self::choose_extent(&caps, self.requested_extent);
+
         let create_info = vk::SwapchainCreateInfoKHR {
             s_type: vk::StructureType::SWAPCHAIN_CREATE_INFO_KHR,
             p_next: ptr::null(),
             surface: **self.inner.surface,
             old_swapchain: self.inner.raw.get(),
             min_image_count: self.image_count,
-            image_extent: caps.current_extent,
+            image_extent,
             image_format: self.format.format,
             image_color_space: self.format.color_space,
             image_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT,
@@ -516,7 +632,7 @@ impl Swapchain {
             self.inner.loader.destroy_swapchain(self.inner.raw.get(), None); 
         }        
         
-        self.extent = caps.current_extent;
+        self.extent = image_extent;
 
         let (textures, views) = Self::create_frames(
             device,
@@ -530,6 +646,8 @@ impl Swapchain {
         self.textures = textures;
         self.views = views;
         self.version += 1;
+        // present ids are per swapchain so start again
+        self.present_id.set(0);
 
         device.raw.check_errors()?;
 
@@ -661,9 +779,21 @@ impl Swapchain {
             }
         }
 
+        let present_id = self.present_id.get() + 1;
+        let present_id_info = vk::PresentIdKHR {
+            s_type: vk::StructureType::PRESENT_ID_KHR,
+            p_next: ptr::null(),
+            swapchain_count: 1,
+            p_present_ids: &present_id,
+        };
+
         let present_info = vk::PresentInfoKHR {
             s_type: vk::StructureType::PRESENT_INFO_KHR,
-            p_next: ptr::null(),
+            p_next: if self.present_wait.is_some() {
+                &present_id_info as *const _ as *const _
+            } else {
+                ptr::null()
+            },
             p_image_indices: &view.index as _,
             p_swapchains: self.inner.raw.as_ptr(),
             swapchain_count: 1,
@@ -681,12 +811,57 @@ impl Swapchain {
                 self.inner.device.check_errors()?;
                 let frame = (self.frame.get() + 1) % self.frames_in_flight;
                 self.frame.set(frame);
+                if self.present_wait.is_some() {
+                    self.present_id.set(present_id);
+                }
                 Ok(b)
             }
             Err(e) => Err(e.into()),
         }
     }
 
+    /// The id of the last frame presented, 0 if nothing has been presented since the swapchain was (re)created
+    ///
+    /// Returns None if the device doesn't have [`crate::DeviceFeatures::PRESENT_WAIT`]
+    pub fn last_present_id(&self) -> Option<u64> {
+        self.present_wait.as_ref().map(|_| self.present_id.get())
+    }
+
+    /// Wait until the frame with the present id has been shown on screen or timeout nanoseconds have passed
+    ///
+    /// Returns Ok(true) if the frame was shown and Ok(false) if the wait timed out.
+    /// Will panic if the device doesn't have [`crate::DeviceFeatures::PRESENT_WAIT`]
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkWaitForPresentKHR.html>
+    pub fn wait_for_present(&self, present_id: u64, timeout: u64) -> Result<bool, crate::Error> {
+        let present_wait = match &self.present_wait {
+            Some(p) => p,
+            None => panic!("ERROR: Attempt to wait for present on device without DeviceFeatures::PRESENT_WAIT"),
+        };
+        if present_id == 0 {
+            return Ok(true);
+        }
+
+        let result = unsafe { present_wait.wait_for_present(self.inner.raw.get(), present_id, timeout) };
+
+        match result {
+            Ok(_) => {
+                self.inner.device.check_errors()?;
+                Ok(true)
+            }
+            Err(vk::Result::TIMEOUT) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Wait until no more than max_queued presented frames are waiting to be shown on screen
+    ///
+    /// Calling before acquiring each frame bounds the latency between input and display to max_queued + 1 frames.
+    /// Returns Ok(false) if the wait timed out.
+    /// Will panic if the device doesn't have [`crate::DeviceFeatures::PRESENT_WAIT`]
+    pub fn limit_latency(&self, max_queued: u64, timeout: u64) -> Result<bool, crate::Error> {
+        self.wait_for_present(self.present_id.get().saturating_sub(max_queued), timeout)
+    }
+
     pub fn extent(&self) -> crate::Extent2D {
         self.extent.into()
     }
@@ -694,6 +869,14 @@ impl Swapchain {
     pub fn format(&self) -> crate::Format {
         self.format.format.into()
     }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.inner.device.set_swapchain_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
 }
 
 impl Drop for SwapchainInner {
@@ -205,6 +205,10 @@ pub struct Texture {
     pub(crate) mip_levels: u32,
     pub(crate) initial_layout: crate::TextureLayout,
     pub(crate) dimension: crate::TextureDimension,
+    /// the handle type if the memory can be shared with other apis
+    pub(crate) external: Option<crate::ExternalHandleType>,
+    /// true if the texture is owned by one queue family at a time instead of shared between them
+    pub(crate) exclusive: bool,
 }
 
 impl std::hash::Hash for Texture {
@@ -234,6 +238,8 @@ impl Clone for Texture {
             mip_levels: self.mip_levels,
             initial_layout: self.initial_layout,
             dimension: self.dimension,
+            external: self.external,
+            exclusive: self.exclusive,
         }
     }
 }
@@ -258,16 +264,91 @@ impl Texture {
 impl Texture {
     /// Create a new Texture from the device and description
     pub fn new(device: &crate::Device, desc: &TextureDesc) -> Result<Self, Error> {
+        Self::raw_new(device, desc, None, false)
+    }
+
+    /// Create a new Texture that is owned by one queue family at a time
+    ///
+    /// Textures are normally shared between the queue families of the device, an exclusive texture avoids the
+    /// cost of that but must be moved between queue families with [`crate::CommandBuffer::queue_ownership_barrier`]
+    /// before being used on a queue of another family
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkSharingMode.html>
+    pub fn new_exclusive(device: &crate::Device, desc: &TextureDesc) -> Result<Self, Error> {
+        Self::raw_new(device, desc, None, true)
+    }
+
+    /// Create a new Texture whose memory can be exported with [`Texture::export`]
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkExportMemoryAllocateInfo.html>
+    pub fn new_exportable(
+        device: &crate::Device,
+        desc: &TextureDesc,
+        handle_type: crate::ExternalHandleType,
+    ) -> Result<Self, Error> {
+        Self::raw_new(device, desc, Some(crate::ExternalMemory::Export(handle_type)), false)
+    }
+
+    /// Create a new Texture using memory exported by another api
+    ///
+    /// Safety: the handle must refer to memory created for an image with the same description
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkImportMemoryFdInfoKHR.html>
+    pub unsafe fn import(
+        device: &crate::Device,
+        desc: &TextureDesc,
+        handle: crate::ExternalHandle,
+    ) -> Result<Self, Error> {
+        Self::raw_new(device, desc, Some(crate::ExternalMemory::Import(handle)), false)
+    }
+
+    fn raw_new(
+        device: &crate::Device,
+        desc: &TextureDesc,
+        external: Option<crate::ExternalMemory>,
+        exclusive: bool,
+    ) -> Result<Self, Error> {
         #[cfg(feature = "logging")]
         log::trace!("GPU: Create Texture, name {:?}", desc.name);
 
-        let dimension_flags = desc.dimension.flags();
+        if external.is_some()
+            && !device
+                .raw
+                .features
+                .contains(crate::DeviceFeatures::EXTERNAL_MEMORY)
+        {
+            panic!("ERROR: Attempt to create external Texture without DeviceFeatures::EXTERNAL_MEMORY enabled");
+        }
+
+        let mut dimension_flags = desc.dimension.flags();
+        if let crate::TextureDimension::D3(..) = desc.dimension {
+            // allows create_layer_view to render into single depth slices
+            if device.raw.features.contains(crate::DeviceFeatures::TEXTURE_3D_SLICES)
+                && desc.usage.intersects(crate::TextureUsage::COLOR_OUTPUT | crate::TextureUsage::DEPTH_OUTPUT)
+            {
+                dimension_flags |= vk::ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE;
+            }
+        }
         let usage_flags = desc.usage.flags();
 
+        let external_info = vk::ExternalMemoryImageCreateInfo {
+            s_type: vk::StructureType::EXTERNAL_MEMORY_IMAGE_CREATE_INFO,
+            p_next: ptr::null(),
+            handle_types: external
+                .map(|e| e.handle_type().into())
+                .unwrap_or(vk::ExternalMemoryHandleTypeFlags::empty()),
+        };
+
+        let (sharing_mode, queue_families) = if exclusive {
+            (vk::SharingMode::EXCLUSIVE, &[][..])
+        } else {
+            device.sharing_mode()
+        };
         let create_info = vk::ImageCreateInfo {
             s_type: vk::StructureType::IMAGE_CREATE_INFO,
-            p_next: ptr::null(),
-            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            p_next: if external.is_some() {
+                &external_info as *const _ as *const _
+            } else {
+                ptr::null()
+            },
+            sharing_mode,
             flags: dimension_flags | usage_flags,
             format: desc.format.into(),
             extent: desc.dimension.into(),
@@ -278,8 +359,8 @@ impl Texture {
             initial_layout: vk::ImageLayout::UNDEFINED,
             samples: desc.dimension.samples().into(),
             image_type: desc.dimension.into(),
-            queue_family_index_count: 0,
-            p_queue_family_indices: ptr::null(),
+            queue_family_index_count: queue_families.len() as u32,
+            p_queue_family_indices: queue_families.as_ptr(),
         };
 
         let raw_result = unsafe { device.raw.create_image(&create_info, None) };
@@ -291,9 +372,14 @@ impl Texture {
 
         let mem_req = unsafe { device.raw.get_image_memory_requirements(raw) };
 
+        let external_alloc = external.map(crate::ExternalAllocateInfo::new);
+
         let memory_alloc = vk::MemoryAllocateInfo {
             s_type: vk::StructureType::MEMORY_ALLOCATE_INFO,
-            p_next: ptr::null(),
+            p_next: match (&external_alloc, external) {
+                (Some(info), Some(external)) => info.p_next(external),
+                _ => ptr::null(),
+            },
             allocation_size: mem_req.size,
             memory_type_index: crate::find_memory_type(
                 mem_req,
@@ -327,6 +413,8 @@ impl Texture {
             mem_ty: desc.memory,
             dimension: desc.dimension,
             initial_layout: desc.layout,
+            external: external.map(|e| e.handle_type()),
+            exclusive,
         };
 
         match desc.layout {
@@ -345,6 +433,32 @@ impl Texture {
         Ok(s)
     }
 
+    /// Export a handle to the memory of the texture so that it can be used by another api
+    ///
+    /// Panics if the texture wasn't created with [`Texture::new_exportable`] or [`Texture::import`].
+    /// The caller is responsible for closing the returned handle
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkGetMemoryFdKHR.html>
+    pub fn export(&self) -> Result<crate::ExternalHandle, Error> {
+        let handle_type = self
+            .external
+            .expect("ERROR: Attempt to export Texture that wasn't created as exportable");
+        let memory = self
+            .memory
+            .as_ref()
+            .expect("ERROR: Attempt to export Texture without memory");
+        crate::export_memory(&self.device, **memory, handle_type)
+    }
+
+    /// Returns true if the texture was created with [`Texture::new_exclusive`]
+    pub fn exclusive(&self) -> bool {
+        self.exclusive
+    }
+
+    /// Returns the handle type that the texture memory can be shared with if any
+    pub fn external_handle_type(&self) -> Option<crate::ExternalHandleType> {
+        self.external
+    }
+
     /// Create the default view that encompases the whole image
     pub fn create_default_view(&self) -> Result<TextureView, Error> {
         self.create_view(&TextureViewDesc {
@@ -357,14 +471,113 @@ impl Texture {
         })
     }
 
+    /// Create a 2D (or 1D) view of a single layer and mip level of the texture
+    ///
+    /// Used to render into one layer of an array or cube texture or one depth slice of a 3D texture.
+    /// Rendering into 3D textures requires [`crate::DeviceFeatures::TEXTURE_3D_SLICES`] and the texture
+    /// to be created with [`crate::TextureUsage::COLOR_OUTPUT`] or [`crate::TextureUsage::DEPTH_OUTPUT`],
+    /// for 3D textures the layer is the depth slice within the mip level
+    pub fn create_layer_view(&self, layer: u32, mip: u32) -> Result<TextureView, Error> {
+        use crate::TextureDimension as D;
+
+        if mip >= self.mip_levels {
+            panic!(
+                "ERROR: Attempt to create layer view of mip level {} of texture {:?} with {} mip levels",
+                mip, self.name, self.mip_levels
+            );
+        }
+
+        let extent: crate::Extent3D = self.dimension.into();
+        let width = (extent.width >> mip).max(1);
+        let height = (extent.height >> mip).max(1);
+        let layers = match self.dimension {
+            D::D3(_, _, depth) => (depth >> mip).max(1),
+            d => d.layers(),
+        };
+        if layer >= layers {
+            panic!(
+                "ERROR: Attempt to create view of layer {} of texture {:?} with {} layers at mip level {}",
+                layer, self.name, layers, mip
+            );
+        }
+
+        if let D::D3(..) = self.dimension {
+            if !self.device.features.contains(crate::DeviceFeatures::TEXTURE_3D_SLICES) {
+                panic!("ERROR: Attempt to create layer view of 3D texture without DeviceFeatures::TEXTURE_3D_SLICES enabled");
+            }
+            if !self.usage.intersects(crate::TextureUsage::COLOR_OUTPUT | crate::TextureUsage::DEPTH_OUTPUT) {
+                panic!("ERROR: Attempt to create layer view of 3D texture without TextureUsage::COLOR_OUTPUT or TextureUsage::DEPTH_OUTPUT");
+            }
+        }
+
+        let dimension = match self.dimension {
+            D::D1(_) | D::D1Array(_, _) => D::D1(width),
+            d => D::D2(width, height, d.samples()),
+        };
+
+        self.create_view(&TextureViewDesc {
+            name: self.name.as_ref().map(|n| format!("{}_layer_{}_mip_{}", n, layer, mip)),
+            dimension,
+            base_mip_level: mip,
+            mip_levels: 1,
+            base_array_layer: layer,
+            format_change: None,
+        })
+    }
+
+    /// Create a view of a multi-planar texture to be sampled with a YCbCr sampler
+    ///
+    /// The conversion must be the same as the one the sampler was created with, see [`crate::Sampler::new_ycbcr`]
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkSamplerYcbcrConversionInfo.html>
+    pub fn create_ycbcr_view(
+        &self,
+        conversion: &crate::SamplerYcbcrConversion,
+        name: Option<String>,
+    ) -> Result<TextureView, Error> {
+        if conversion.format != self.format {
+            panic!(
+                "ERROR: Attempt to create YCbCr view of texture with format {:?} using conversion for format {:?}",
+                self.format, conversion.format
+            );
+        }
+        self.raw_create_view(
+            &TextureViewDesc {
+                name,
+                dimension: self.dimension.into(),
+                base_array_layer: 0,
+                base_mip_level: 0,
+                mip_levels: self.mip_levels,
+                format_change: None,
+            },
+            Some(conversion),
+        )
+    }
+
     /// Create a TextureView from description
     pub fn create_view(&self, desc: &TextureViewDesc) -> Result<TextureView, Error> {
+        self.raw_create_view(desc, None)
+    }
+
+    fn raw_create_view(
+        &self,
+        desc: &TextureViewDesc,
+        conversion: Option<&crate::SamplerYcbcrConversion>,
+    ) -> Result<TextureView, Error> {
         #[cfg(feature = "logging")]
         log::trace!("GPU: Create TextureView, name {:?}", desc.name);
 
+        let conversion_info = conversion.map(|c| vk::SamplerYcbcrConversionInfo {
+            s_type: vk::StructureType::SAMPLER_YCBCR_CONVERSION_INFO,
+            p_next: ptr::null(),
+            conversion: **c.raw,
+        });
+
         let create_info = vk::ImageViewCreateInfo {
             s_type: vk::StructureType::IMAGE_VIEW_CREATE_INFO,
-            p_next: ptr::null(),
+            p_next: match &conversion_info {
+                Some(info) => info as *const _ as *const _,
+                None => ptr::null(),
+            },
             flags: vk::ImageViewCreateFlags::empty(),
             image: **self.raw,
             format: if let Some(format) = desc.format_change {
@@ -566,6 +779,19 @@ impl Texture {
     pub fn id(&self) -> u64 {
         unsafe { std::mem::transmute(**self.raw) }
     }
+
+    /// Get the name of self
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|n| &**n)
+    }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_texture_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
 }
 
 impl Drop for Texture {
@@ -732,6 +958,14 @@ impl TextureView {
             mip_levels: self.mip_levels,
         })
     }
+
+    /// Set the name of the object for debugging tools through VK_EXT_debug_utils
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSetDebugUtilsObjectNameEXT.html>
+    pub fn set_object_name(&mut self, name: &str) -> Result<(), crate::Error> {
+        self.device.set_texture_view_name(self, name)?;
+        self.name = Some(name.to_string());
+        Ok(())
+    }
 }
 
 impl Drop for TextureView {
@@ -867,8 +1101,11 @@ impl<'a> TextureSlice<'a> {
     /// If the texture is a swapchain texture then this will panic
     pub fn write(&self, data: &[u8]) -> Result<(), Error> {
         let offset = (self.offset.x * self.offset.y * self.offset.z) as usize;
-        let size = self.texture.format.size()
-            * (self.extent.width * self.extent.height * self.extent.depth) as usize;
+        let size = self.texture.format.data_size(
+            self.extent.width,
+            self.extent.height,
+            self.extent.depth,
+        );
         if self.texture.mem_ty == crate::MemoryType::Device {
             panic!("ERROR: Attempt to write to TextureSlice with memory type not visible to host");
         }
@@ -908,8 +1145,11 @@ impl<'a> TextureSlice<'a> {
     /// If the Texture is a swapchain texture then this will panic
     pub fn read(&self, data: &mut [u8]) -> Result<(), Error> {
         let offset = (self.offset.x * self.offset.y * self.offset.z) as usize;
-        let size = self.texture.format.size()
-            * (self.extent.width * self.extent.height * self.extent.depth) as usize;
+        let size = self.texture.format.data_size(
+            self.extent.width,
+            self.extent.height,
+            self.extent.depth,
+        );
         if self.texture.mem_ty == crate::MemoryType::Device {
             panic!("ERROR: Attempt to read from TextureSlice with memory type not visible to host");
         }
@@ -0,0 +1,131 @@
+//! Structured messages from the validation layers
+//!
+//! By default validation messages are logged and errors are collected and returned as
+//! [`crate::Error::Validation`] from the next call that checks for errors. Setting a handler with
+//! [`crate::Instance::set_validation_handler`] routes messages to user code as they are produced
+
+use ash::vk;
+
+use std::ffi::CStr;
+
+/// How severe a validation message is
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkDebugUtilsMessageSeverityFlagBitsEXT.html>
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValidationSeverity {
+    Verbose,
+    Info,
+    Warning,
+    Error,
+}
+
+impl ValidationSeverity {
+    pub(crate) fn from_raw(severity: vk::DebugUtilsMessageSeverityFlagsEXT) -> Self {
+        if severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+            Self::Error
+        } else if severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING) {
+            Self::Warning
+        } else if severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::INFO) {
+            Self::Info
+        } else {
+            Self::Verbose
+        }
+    }
+}
+
+/// What caused a validation message
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkDebugUtilsMessageTypeFlagBitsEXT.html>
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ValidationMessageType {
+    General,
+    Validation,
+    Performance,
+    Unknown,
+}
+
+impl ValidationMessageType {
+    pub(crate) fn from_raw(ty: vk::DebugUtilsMessageTypeFlagsEXT) -> Self {
+        match ty {
+            vk::DebugUtilsMessageTypeFlagsEXT::GENERAL => Self::General,
+            vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION => Self::Validation,
+            vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE => Self::Performance,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// An object referenced by a validation message
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkDebugUtilsObjectNameInfoEXT.html>
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ValidationObject {
+    pub(crate) ty: vk::ObjectType,
+    /// the raw vulkan handle of the object
+    pub handle: u64,
+    /// the name of the object if one was set with `set_object_name`
+    pub name: Option<String>,
+}
+
+impl ValidationObject {
+    pub unsafe fn raw_object_type(&self) -> vk::ObjectType {
+        self.ty
+    }
+}
+
+/// A message produced by the validation layers
+/// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkDebugUtilsMessengerCallbackDataEXT.html>
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ValidationMessage {
+    pub severity: ValidationSeverity,
+    pub ty: ValidationMessageType,
+    /// the name of the validation check that produced the message, e.g. "VUID-vkCmdDraw-None-02699"
+    pub message_id_name: Option<String>,
+    pub message_id_number: i32,
+    pub message: String,
+    /// objects related to the message
+    pub objects: Vec<ValidationObject>,
+}
+
+impl ValidationMessage {
+    pub(crate) unsafe fn from_raw(
+        severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+        ty: vk::DebugUtilsMessageTypeFlagsEXT,
+        data: &vk::DebugUtilsMessengerCallbackDataEXT,
+    ) -> Self {
+        let string = |p: *const std::os::raw::c_char| {
+            if p.is_null() {
+                None
+            } else {
+                Some(CStr::from_ptr(p).to_string_lossy().into_owned())
+            }
+        };
+        let objects = if data.p_objects.is_null() {
+            Vec::new()
+        } else {
+            std::slice::from_raw_parts(data.p_objects, data.object_count as usize)
+                .iter()
+                .map(|o| ValidationObject {
+                    ty: o.object_type,
+                    handle: o.object_handle,
+                    name: string(o.p_object_name),
+                })
+                .collect()
+        };
+        Self {
+            severity: ValidationSeverity::from_raw(severity),
+            ty: ValidationMessageType::from_raw(ty),
+            message_id_name: string(data.p_message_id_name),
+            message_id_number: data.message_id_number,
+            message: string(data.p_message).unwrap_or_default(),
+            objects,
+        }
+    }
+}
+
+/// A user function that receives validation messages
+///
+/// Called from whatever thread the driver reports the message on
+pub type ValidationCallback = Box<dyn Fn(&ValidationMessage) + Send + Sync>;
+
+pub(crate) struct ValidationHandler {
+    pub min_severity: ValidationSeverity,
+    pub callback: ValidationCallback,
+}
@@ -0,0 +1,459 @@
+//! Skeletal animation
+//!
+//! A [`Skeleton`] is a hierarchy of joints and an [`AnimationClip`] holds keyframes for some of
+//! those joints. Sampling a clip gives a [`Pose`] which [`Skeleton::joint_matrices`] turns into the
+//! matrices that skinned vertices are transformed by, [`BoneBuffer`] uploads them for use in shaders
+
+/// Translation, rotation and scale of a joint relative to its parent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    /// the translation of the joint
+    pub translation: glam::Vec3,
+    /// the rotation of the joint
+    pub rotation: glam::Quat,
+    /// the scale of the joint
+    pub scale: glam::Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    /// The transform that does nothing
+    pub const IDENTITY: Self = Self {
+        translation: glam::Vec3::ZERO,
+        rotation: glam::Quat::IDENTITY,
+        scale: glam::Vec3::ONE,
+    };
+
+    /// Get the transform as a matrix
+    pub fn matrix(&self) -> glam::Mat4 {
+        glam::Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    /// Decompose a matrix into a transform
+    pub fn from_matrix(matrix: glam::Mat4) -> Self {
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    /// Interpolate between self and other, t = 0 is self and t = 1 is other
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            translation: self.translation.lerp(other.translation, t),
+            rotation: self.rotation.slerp(other.rotation, t),
+            scale: self.scale.lerp(other.scale, t),
+        }
+    }
+}
+
+/// A single joint of a [`Skeleton`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Joint {
+    /// the name of the joint if any
+    pub name: Option<String>,
+    /// the index of the parent joint in the skeleton, None for roots
+    pub parent: Option<usize>,
+    /// transforms from model space into the space of the joint when at rest
+    pub inverse_bind: glam::Mat4,
+    /// the transform of the joint relative to its parent when not animated
+    pub rest: Transform,
+}
+
+/// A hierarchy of joints that skinned vertices are attached to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skeleton {
+    pub(crate) joints: Vec<Joint>,
+    /// indices of the joints ordered so that parents come before their children
+    pub(crate) order: Vec<usize>,
+}
+
+impl Skeleton {
+    /// Create a new skeleton
+    ///
+    /// Will panic if a parent index is out of range or the parents form a cycle
+    pub fn new(joints: Vec<Joint>) -> Self {
+        let mut order = Vec::with_capacity(joints.len());
+        let mut placed = vec![false; joints.len()];
+        while order.len() != joints.len() {
+            let before = order.len();
+            for (i, joint) in joints.iter().enumerate() {
+                if placed[i] {
+                    continue;
+                }
+                let ready = match joint.parent {
+                    Some(p) if p >= joints.len() => panic!(
+                        "ERROR: Joint {} has parent {} out of range of skeleton with {} joints",
+                        i,
+                        p,
+                        joints.len()
+                    ),
+                    Some(p) => placed[p],
+                    None => true,
+                };
+                if ready {
+                    placed[i] = true;
+                    order.push(i);
+                }
+            }
+            if order.len() == before {
+                panic!("ERROR: Attempt to create Skeleton with cyclic joint parents");
+            }
+        }
+        Self { joints, order }
+    }
+
+    /// The joints of the skeleton
+    pub fn joints(&self) -> &[Joint] {
+        &self.joints
+    }
+
+    /// The number of joints in the skeleton
+    pub fn len(&self) -> usize {
+        self.joints.len()
+    }
+
+    /// Returns true if the skeleton has no joints
+    pub fn is_empty(&self) -> bool {
+        self.joints.is_empty()
+    }
+
+    /// Find the index of the joint by name
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.joints
+            .iter()
+            .position(|j| j.name.as_deref() == Some(name))
+    }
+
+    /// The pose with every joint at rest
+    pub fn rest_pose(&self) -> Pose {
+        Pose {
+            locals: self.joints.iter().map(|j| j.rest).collect(),
+        }
+    }
+
+    /// The model space transform of each joint in the pose
+    pub fn global_transforms(&self, pose: &Pose) -> Vec<glam::Mat4> {
+        if pose.locals.len() != self.joints.len() {
+            panic!(
+                "ERROR: Attempt to use pose with {} joints on skeleton with {} joints",
+                pose.locals.len(),
+                self.joints.len()
+            );
+        }
+        let mut globals = vec![glam::Mat4::IDENTITY; self.joints.len()];
+        for &i in &self.order {
+            let local = pose.locals[i].matrix();
+            globals[i] = match self.joints[i].parent {
+                Some(p) => globals[p] * local,
+                None => local,
+            };
+        }
+        globals
+    }
+
+    /// The matrices to transform skinned vertices by for each joint in the pose
+    ///
+    /// These are what should be uploaded to the gpu, see [`BoneBuffer`]
+    pub fn joint_matrices(&self, pose: &Pose) -> Vec<glam::Mat4> {
+        self.global_transforms(pose)
+            .into_iter()
+            .zip(&self.joints)
+            .map(|(g, j)| g * j.inverse_bind)
+            .collect()
+    }
+}
+
+/// The local transform of every joint in a [`Skeleton`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pose {
+    /// the transform of each joint relative to its parent
+    pub locals: Vec<Transform>,
+}
+
+impl Pose {
+    /// Interpolate between self and other, t = 0 is self and t = 1 is other
+    pub fn blend(&self, other: &Pose, t: f32) -> Pose {
+        if self.locals.len() != other.locals.len() {
+            panic!(
+                "ERROR: Attempt to blend poses with {} and {} joints",
+                self.locals.len(),
+                other.locals.len()
+            );
+        }
+        Pose {
+            locals: self
+                .locals
+                .iter()
+                .zip(&other.locals)
+                .map(|(a, b)| a.lerp(b, t))
+                .collect(),
+        }
+    }
+}
+
+/// How to interpolate between keyframes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Interpolation {
+    /// Hold the value of the previous keyframe
+    Step,
+    /// Linearly interpolate between keyframes, rotations are spherically interpolated
+    Linear,
+}
+
+/// Values that can be interpolated between keyframes
+pub trait Interpolate: Copy {
+    /// Interpolate between a and b, t = 0 is a and t = 1 is b
+    fn interpolate(a: Self, b: Self, t: f32) -> Self;
+}
+
+impl Interpolate for glam::Vec3 {
+    fn interpolate(a: Self, b: Self, t: f32) -> Self {
+        a.lerp(b, t)
+    }
+}
+
+impl Interpolate for glam::Quat {
+    fn interpolate(a: Self, b: Self, t: f32) -> Self {
+        a.slerp(b, t)
+    }
+}
+
+/// Values of one property at points in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframes<T> {
+    pub(crate) times: Vec<f32>,
+    pub(crate) values: Vec<T>,
+    pub(crate) interpolation: Interpolation,
+}
+
+impl<T: Interpolate> Keyframes<T> {
+    /// Create new keyframes, times must be increasing and the same length as values
+    pub fn new(times: Vec<f32>, values: Vec<T>, interpolation: Interpolation) -> Self {
+        if times.len() != values.len() {
+            panic!(
+                "ERROR: Attempt to create Keyframes with {} times and {} values",
+                times.len(),
+                values.len()
+            );
+        }
+        if times.windows(2).any(|w| w[1] < w[0]) {
+            panic!("ERROR: Attempt to create Keyframes with decreasing times");
+        }
+        Self {
+            times,
+            values,
+            interpolation,
+        }
+    }
+
+    /// Create new keyframes, returns None if times aren't increasing or are a different length to values
+    pub fn try_new(times: Vec<f32>, values: Vec<T>, interpolation: Interpolation) -> Option<Self> {
+        if times.len() != values.len() || times.windows(2).any(|w| w[1] < w[0]) {
+            return None;
+        }
+        Some(Self {
+            times,
+            values,
+            interpolation,
+        })
+    }
+
+    /// The time of each keyframe
+    pub fn times(&self) -> &[f32] {
+        &self.times
+    }
+
+    /// The value of each keyframe
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// The time of the last keyframe
+    pub fn duration(&self) -> f32 {
+        self.times.last().copied().unwrap_or(0.0)
+    }
+
+    /// The value at time, clamped to the first and last keyframes
+    ///
+    /// Returns None if there are no keyframes
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let last = self.times.len().checked_sub(1)?;
+        if time <= self.times[0] {
+            return Some(self.values[0]);
+        }
+        if time >= self.times[last] {
+            return Some(self.values[last]);
+        }
+        let next = self.times.partition_point(|&t| t <= time);
+        let prev = next - 1;
+        match self.interpolation {
+            Interpolation::Step => Some(self.values[prev]),
+            Interpolation::Linear => {
+                let span = self.times[next] - self.times[prev];
+                let t = if span > 0.0 {
+                    (time - self.times[prev]) / span
+                } else {
+                    0.0
+                };
+                Some(T::interpolate(self.values[prev], self.values[next], t))
+            }
+        }
+    }
+}
+
+/// The keyframes that animate one joint
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    /// the index of the joint in the skeleton
+    pub joint: usize,
+    /// translation keyframes if the translation is animated
+    pub translation: Option<Keyframes<glam::Vec3>>,
+    /// rotation keyframes if the rotation is animated
+    pub rotation: Option<Keyframes<glam::Quat>>,
+    /// scale keyframes if the scale is animated
+    pub scale: Option<Keyframes<glam::Vec3>>,
+}
+
+/// An animation of the joints of a [`Skeleton`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationClip {
+    /// the name of the clip if any
+    pub name: Option<String>,
+    pub(crate) channels: Vec<Channel>,
+    pub(crate) duration: f32,
+}
+
+impl AnimationClip {
+    /// Create a new clip, the duration is the time of the last keyframe
+    pub fn new(name: Option<&str>, channels: Vec<Channel>) -> Self {
+        let duration = channels
+            .iter()
+            .flat_map(|c| {
+                [
+                    c.translation.as_ref().map(|k| k.duration()),
+                    c.rotation.as_ref().map(|k| k.duration()),
+                    c.scale.as_ref().map(|k| k.duration()),
+                ]
+            })
+            .flatten()
+            .fold(0.0, f32::max);
+        Self {
+            name: name.map(|n| n.to_string()),
+            channels,
+            duration,
+        }
+    }
+
+    /// The channels of the clip
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    /// The length of the clip in seconds
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    /// Sample the clip at time, joints that aren't animated are left at rest
+    ///
+    /// If looping the time wraps around the duration otherwise it is clamped
+    pub fn sample(&self, skeleton: &Skeleton, time: f32, looping: bool) -> Pose {
+        let mut pose = skeleton.rest_pose();
+        self.sample_into(time, looping, &mut pose);
+        pose
+    }
+
+    /// Sample the clip at time overwriting the animated joints of pose
+    ///
+    /// If looping the time wraps around the duration otherwise it is clamped
+    pub fn sample_into(&self, time: f32, looping: bool, pose: &mut Pose) {
+        let time = if looping && self.duration > 0.0 {
+            time.rem_euclid(self.duration)
+        } else {
+            time.clamp(0.0, self.duration)
+        };
+        for channel in &self.channels {
+            let local = match pose.locals.get_mut(channel.joint) {
+                Some(l) => l,
+                None => panic!(
+                    "ERROR: AnimationClip {:?} animates joint {} out of range of pose with {} joints",
+                    self.name,
+                    channel.joint,
+                    pose.locals.len()
+                ),
+            };
+            if let Some(t) = channel.translation.as_ref().and_then(|k| k.sample(time)) {
+                local.translation = t;
+            }
+            if let Some(r) = channel.rotation.as_ref().and_then(|k| k.sample(time)) {
+                local.rotation = r;
+            }
+            if let Some(s) = channel.scale.as_ref().and_then(|k| k.sample(time)) {
+                local.scale = s;
+            }
+        }
+    }
+}
+
+/// Storage buffer holding the joint matrices of a skeleton for skinning in shaders
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BoneBuffer {
+    /// the storage of the matrices, one per joint
+    pub storage: gfx::Storage<glam::Mat4>,
+}
+
+impl BoneBuffer {
+    /// Create a new bone buffer for the skeleton with every matrix set to identity
+    ///
+    /// The data on the gpu won't be correct until the encoder is submitted
+    pub fn new(
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        skeleton: &Skeleton,
+        name: Option<&str>,
+    ) -> Result<Self, gpu::Error> {
+        let matrices = vec![glam::Mat4::IDENTITY; skeleton.len().max(1)];
+        Ok(Self {
+            storage: gfx::Storage::from_vec(encoder, device, matrices, name)?,
+        })
+    }
+
+    /// Upload joint matrices, usually from [`Skeleton::joint_matrices`]
+    ///
+    /// The update will only be complete when the command encoder is submitted
+    pub fn update(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        matrices: &[glam::Mat4],
+    ) -> Result<(), gpu::Error> {
+        self.storage.update_range(encoder, device, 0, matrices)
+    }
+
+    /// Sample the pose and upload its joint matrices
+    pub fn update_pose(
+        &self,
+        encoder: &mut gfx::CommandEncoder<'_>,
+        device: &gpu::Device,
+        skeleton: &Skeleton,
+        pose: &Pose,
+    ) -> Result<(), gpu::Error> {
+        self.update(encoder, device, &skeleton.joint_matrices(pose))
+    }
+}
+
+impl std::ops::Deref for BoneBuffer {
+    type Target = gpu::Buffer;
+
+    fn deref(&self) -> &Self::Target {
+        &self.storage.buffer
+    }
+}
@@ -1,12 +1,18 @@
+pub mod animation;
 pub mod defaults;
 #[cfg(feature = "loading")]
 pub mod loading;
+pub mod normal;
+pub mod optimize;
 pub mod tangent;
 pub mod cull_lod;
 
+pub use animation::*;
 pub use defaults::*;
 #[cfg(feature = "loading")]
 pub use loading::*;
+pub use normal::*;
+pub use optimize::*;
 pub use tangent::*;
 pub use cull_lod::*;
 
@@ -24,6 +30,11 @@ pub trait Vertex: gfx::Vertex {
     /// Should set the tangent vectors of this vertex if any
     fn set_tangents(&mut self, u: glam::Vec3, v: glam::Vec3);
 
+    /// Should set the normal vector of this vertex if any
+    ///
+    /// Vertices without normals can ignore this
+    fn set_normal(&mut self, _normal: glam::Vec3) {}
+
     /// Get the position of the vertex
     fn pos(&self) -> glam::Vec3;
 
@@ -38,4 +49,19 @@ pub trait Vertex: gfx::Vertex {
 
     /// Get the tangent in the v direction if any
     fn tangent_v(&self) -> Option<glam::Vec3>;
+
+    /// Should set the indices and weights of the joints influencing this vertex if any
+    ///
+    /// Vertices that aren't skinned can ignore this
+    fn set_joints(&mut self, _joints: [u32; 4], _weights: glam::Vec4) {}
+
+    /// Get the indices of the joints influencing this vertex if any
+    fn joints(&self) -> Option<[u32; 4]> {
+        None
+    }
+
+    /// Get the weights of the joints influencing this vertex if any
+    fn weights(&self) -> Option<glam::Vec4> {
+        None
+    }
 }
@@ -2,17 +2,23 @@
 pub enum LoadError {
     Gpu(String, gpu::Error),
     Tobj(tobj::LoadError),
-    MissingNormals(String),
+    Gltf(gltf::Error),
     MissingUvs(String),
+    InvalidAnimation(String),
 }
 
 impl std::fmt::Display for LoadError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             LoadError::Gpu(n, e) => writeln!(f, "Error loading file: {}, {}", n, e),
-            LoadError::MissingNormals(n) => writeln!(f, "Error loading {}, missing normals", n),
             LoadError::MissingUvs(n) => writeln!(f, "Error loading {}, missing uv coordinates", n),
+            LoadError::InvalidAnimation(n) => writeln!(
+                f,
+                "Error loading animation {}, keyframe times must be increasing and match the number of values",
+                n
+            ),
             LoadError::Tobj(e) => writeln!(f, "{}", e),
+            LoadError::Gltf(e) => writeln!(f, "{}", e),
         }
     }
 }
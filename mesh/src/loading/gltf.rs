@@ -0,0 +1,291 @@
+use crate::Vertex;
+
+use super::LoadError;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A mesh loaded from a gltf file
+#[derive(Debug, Clone)]
+pub struct GltfMesh<V: Vertex> {
+    /// the mesh
+    pub mesh: gfx::Mesh<V>,
+    /// the name of the mesh in the file if any
+    pub name: Option<String>,
+    /// the index into [`GltfScene::skeletons`] of the skeleton that the mesh is skinned to if any
+    pub skin: Option<usize>,
+}
+
+/// An animation loaded from a gltf file targeting one skeleton
+#[derive(Debug, Clone)]
+pub struct GltfAnimation {
+    /// the animation
+    pub clip: crate::AnimationClip,
+    /// the index into [`GltfScene::skeletons`] of the skeleton that the clip animates
+    pub skin: usize,
+}
+
+/// The meshes, skeletons and animations loaded from a gltf file
+#[derive(Debug, Clone)]
+pub struct GltfScene<V: Vertex> {
+    /// one mesh for each primitive in the file
+    pub meshes: Vec<GltfMesh<V>>,
+    /// one skeleton for each skin in the file
+    pub skeletons: Vec<crate::Skeleton>,
+    /// animations of the skeletons, an animation in the file that moves joints of several
+    /// skeletons is split into one clip per skeleton
+    pub animations: Vec<GltfAnimation>,
+}
+
+/// Load the meshes of a gltf file along with joint indices and weights, skins and animations
+///
+/// Joint indices of vertices index into the skeleton the mesh is skinned to. Transforms of nodes
+/// above the root joints of a skin aren't applied. Cubic spline animations are sampled linearly
+pub fn load_from_gltf<P: AsRef<Path> + std::fmt::Debug, V: Vertex>(
+    encoder: &mut gfx::CommandEncoder<'_>,
+    device: &gpu::Device,
+    gen_tangents: bool,
+    optimize: bool,
+    path: P,
+    name: Option<&str>,
+) -> Result<GltfScene<V>, LoadError> {
+    let (document, buffers, _) = match gltf::import(path) {
+        Ok(i) => i,
+        Err(e) => return Err(LoadError::Gltf(e)),
+    };
+    let get_buffer = |b: gltf::Buffer<'_>| Some(&*buffers[b.index()]);
+
+    // parent node of each node
+    let mut parents = HashMap::new();
+    for node in document.nodes() {
+        for child in node.children() {
+            parents.insert(child.index(), node.index());
+        }
+    }
+
+    // (skin, joint) of each node that is a joint
+    let mut joints = HashMap::new();
+    let mut skeletons = Vec::new();
+    for skin in document.skins() {
+        let nodes = skin.joints().map(|n| n.index()).collect::<Vec<_>>();
+        let joint_of = nodes
+            .iter()
+            .enumerate()
+            .map(|(j, &n)| (n, j))
+            .collect::<HashMap<_, _>>();
+        let inverse_binds = skin
+            .reader(get_buffer)
+            .read_inverse_bind_matrices()
+            .map(|m| {
+                m.map(|m| glam::Mat4::from_cols_array_2d(&m))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_else(|| vec![glam::Mat4::IDENTITY; nodes.len()]);
+
+        let skeleton_joints = skin
+            .joints()
+            .zip(inverse_binds)
+            .map(|(node, inverse_bind)| {
+                let (translation, rotation, scale) = node.transform().decomposed();
+                crate::Joint {
+                    name: node.name().map(|n| n.to_string()),
+                    parent: parents
+                        .get(&node.index())
+                        .and_then(|p| joint_of.get(p))
+                        .copied(),
+                    inverse_bind,
+                    rest: crate::Transform {
+                        translation: translation.into(),
+                        rotation: glam::Quat::from_array(rotation),
+                        scale: scale.into(),
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for (n, j) in joint_of {
+            joints.entry(n).or_insert((skin.index(), j));
+        }
+        skeletons.push(crate::Skeleton::new(skeleton_joints));
+    }
+
+    // skin of each mesh from the first node that uses the mesh
+    let mut mesh_skins = HashMap::new();
+    for node in document.nodes() {
+        if let Some(mesh) = node.mesh() {
+            mesh_skins
+                .entry(mesh.index())
+                .or_insert(node.skin().map(|s| s.index()));
+        }
+    }
+
+    let mut meshes = Vec::new();
+    for mesh in document.meshes() {
+        let mesh_name = mesh
+            .name()
+            .map(|n| n.to_string())
+            .unwrap_or_else(|| format!("mesh_{}", mesh.index()));
+        for (p, primitive) in mesh.primitives().enumerate() {
+            let reader = primitive.reader(get_buffer);
+
+            let positions = match reader.read_positions() {
+                Some(p) => p.collect::<Vec<_>>(),
+                None => continue,
+            };
+            let normals = reader.read_normals().map(|n| n.collect::<Vec<_>>());
+            let uvs = match reader.read_tex_coords(0) {
+                Some(t) => t.into_f32().collect::<Vec<_>>(),
+                None => return Err(LoadError::MissingUvs(mesh_name)),
+            };
+            let skin_joints = reader
+                .read_joints(0)
+                .map(|j| j.into_u16().collect::<Vec<_>>());
+            let skin_weights = reader
+                .read_weights(0)
+                .map(|w| w.into_f32().collect::<Vec<_>>());
+
+            let mut vertices = positions
+                .iter()
+                .zip(&uvs)
+                .enumerate()
+                .map(|(i, (&position, &uv))| {
+                    let normal = normals
+                        .as_ref()
+                        .map(|n| glam::Vec3::from(n[i]))
+                        .unwrap_or(glam::Vec3::ZERO);
+                    V::new(position.into(), uv.into(), normal, None, None)
+                })
+                .collect::<Vec<_>>();
+
+            if let (Some(skin_joints), Some(skin_weights)) = (&skin_joints, &skin_weights) {
+                for ((vertex, j), &w) in vertices.iter_mut().zip(skin_joints).zip(skin_weights) {
+                    vertex.set_joints(j.map(|j| j as u32), w.into());
+                }
+            }
+
+            let mut indices = match reader.read_indices() {
+                Some(i) => i.into_u32().collect::<Vec<_>>(),
+                None => (0..vertices.len() as u32).collect(),
+            };
+
+            if normals.is_none() {
+                crate::compute_normals(&mut vertices, &mut indices, crate::DEFAULT_CREASE_ANGLE);
+            }
+
+            if gen_tangents {
+                crate::calc_tangent_indexed(&mut vertices, &indices);
+            }
+
+            if optimize {
+                crate::optimize(&mut vertices, &mut indices);
+            }
+
+            let primitive_name = format!("{}_{}", mesh_name, p);
+            let name = name.map(|n| format!("{}_{}", n, primitive_name));
+            let gfx_mesh = match gfx::Mesh::indexed(
+                encoder,
+                device,
+                &vertices,
+                &indices,
+                name.as_ref().map(|n| &**n),
+            ) {
+                Ok(m) => m,
+                Err(e) => return Err(LoadError::Gpu(primitive_name, e)),
+            };
+
+            meshes.push(GltfMesh {
+                mesh: gfx_mesh,
+                name: mesh.name().map(|n| n.to_string()),
+                skin: mesh_skins.get(&mesh.index()).copied().flatten(),
+            });
+        }
+    }
+
+    let mut animations = Vec::new();
+    for animation in document.animations() {
+        // channels of each skin by joint
+        let mut channels: HashMap<usize, HashMap<usize, crate::Channel>> = HashMap::new();
+        for channel in animation.channels() {
+            let (skin, joint) = match joints.get(&channel.target().node().index()) {
+                Some(&j) => j,
+                None => continue,
+            };
+            let reader = channel.reader(get_buffer);
+            let times = match reader.read_inputs() {
+                Some(i) => i.collect::<Vec<_>>(),
+                None => continue,
+            };
+            let (interpolation, cubic) = match channel.sampler().interpolation() {
+                gltf::animation::Interpolation::Step => (crate::Interpolation::Step, false),
+                gltf::animation::Interpolation::Linear => (crate::Interpolation::Linear, false),
+                gltf::animation::Interpolation::CubicSpline => (crate::Interpolation::Linear, true),
+            };
+
+            let entry = channels
+                .entry(skin)
+                .or_default()
+                .entry(joint)
+                .or_insert_with(|| crate::Channel {
+                    joint,
+                    translation: None,
+                    rotation: None,
+                    scale: None,
+                });
+            let invalid = || {
+                LoadError::InvalidAnimation(
+                    animation
+                        .name()
+                        .map(|n| n.to_string())
+                        .unwrap_or_else(|| animation.index().to_string()),
+                )
+            };
+            match reader.read_outputs() {
+                Some(gltf::animation::util::ReadOutputs::Translations(t)) => {
+                    let v = keyframe_values(t.map(glam::Vec3::from), cubic);
+                    entry.translation = Some(
+                        crate::Keyframes::try_new(times, v, interpolation).ok_or_else(invalid)?,
+                    );
+                }
+                Some(gltf::animation::util::ReadOutputs::Rotations(r)) => {
+                    let v = keyframe_values(r.into_f32().map(glam::Quat::from_array), cubic);
+                    entry.rotation = Some(
+                        crate::Keyframes::try_new(times, v, interpolation).ok_or_else(invalid)?,
+                    );
+                }
+                Some(gltf::animation::util::ReadOutputs::Scales(s)) => {
+                    let v = keyframe_values(s.map(glam::Vec3::from), cubic);
+                    entry.scale = Some(
+                        crate::Keyframes::try_new(times, v, interpolation).ok_or_else(invalid)?,
+                    );
+                }
+                _ => (),
+            }
+        }
+
+        let mut skins = channels.into_iter().collect::<Vec<_>>();
+        skins.sort_by_key(|(s, _)| *s);
+        for (skin, channels) in skins {
+            let mut channels = channels.into_values().collect::<Vec<_>>();
+            channels.sort_by_key(|c| c.joint);
+            animations.push(GltfAnimation {
+                clip: crate::AnimationClip::new(animation.name(), channels),
+                skin,
+            });
+        }
+    }
+
+    Ok(GltfScene {
+        meshes,
+        skeletons,
+        animations,
+    })
+}
+
+/// Collect the values of keyframes, cubic spline outputs are (in tangent, value, out tangent)
+/// for each keyframe so only the values are kept
+fn keyframe_values<T>(values: impl Iterator<Item = T>, cubic: bool) -> Vec<T> {
+    if cubic {
+        values.skip(1).step_by(3).collect()
+    } else {
+        values.collect()
+    }
+}
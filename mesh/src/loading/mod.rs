@@ -1,5 +1,7 @@
 pub mod error;
+pub mod gltf;
 pub mod obj;
 
 pub use error::*;
+pub use self::gltf::*;
 pub use obj::*;
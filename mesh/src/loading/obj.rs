@@ -8,6 +8,7 @@ pub fn load_meshes_from_obj<P: AsRef<Path> + std::fmt::Debug, V: Vertex>(
     encoder: &mut gfx::CommandEncoder<'_>,
     device: &gpu::Device,
     gen_tangents: bool,
+    optimize: bool,
     path: P,
     name: Option<&str>,
 ) -> Result<Vec<gfx::Mesh<V>>, LoadError> {
@@ -21,25 +22,30 @@ pub fn load_meshes_from_obj<P: AsRef<Path> + std::fmt::Debug, V: Vertex>(
     let mut meshes = Vec::with_capacity(models.len());
 
     for model in models {
-        if model.mesh.normals.is_empty() {
-            return Err(LoadError::MissingNormals(model.name));
-        }
-
         if model.mesh.texcoords.is_empty() {
             return Err(LoadError::MissingUvs(model.name));
         }
 
-        let vertices = model
+        let has_normals = !model.mesh.normals.is_empty();
+
+        let mut vertices = model
             .mesh
             .positions
             .chunks(3)
-            .zip(model.mesh.normals.chunks(3))
             .zip(model.mesh.texcoords.chunks(2))
-            .map(|((position, normal), uv)| {
+            .enumerate()
+            .map(|(i, (position, uv))| {
+                let normal = if has_normals {
+                    <[f32; 3]>::try_from(&model.mesh.normals[i * 3..i * 3 + 3])
+                        .unwrap()
+                        .into()
+                } else {
+                    glam::Vec3::ZERO
+                };
                 V::new(
                     <[f32; 3]>::try_from(position).unwrap().into(),
                     <[f32; 2]>::try_from(uv).unwrap().into(),
-                    <[f32; 3]>::try_from(normal).unwrap().into(),
+                    normal,
                     None,
                     None,
                 )
@@ -50,7 +56,15 @@ pub fn load_meshes_from_obj<P: AsRef<Path> + std::fmt::Debug, V: Vertex>(
             // crate::utils::gen_tangents(&mut vertices);
         }
 
-        let indices = &*model.mesh.indices;
+        let mut indices = model.mesh.indices;
+
+        if !has_normals {
+            crate::compute_normals(&mut vertices, &mut indices, crate::DEFAULT_CREASE_ANGLE);
+        }
+
+        if optimize {
+            crate::optimize(&mut vertices, &mut indices);
+        }
 
         let name = name.map(|n| format!("{}_{}", n, model.name));
         let mesh = match gfx::Mesh::indexed(
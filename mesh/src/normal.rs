@@ -0,0 +1,85 @@
+use crate::Vertex;
+
+use std::collections::HashMap;
+
+/// Crease angle used by the loaders when generating normals for meshes without them
+pub const DEFAULT_CREASE_ANGLE: f32 = std::f32::consts::FRAC_PI_3;
+
+/// Calculate the normal of each vertex from the triangles of an indexed triangle list
+///
+/// Triangles sharing a position are smoothed together if the angle between their face normals is
+/// less than angle_threshold (radians), otherwise the edge is kept sharp. An angle_threshold of 0
+/// gives faceted normals and anything >= pi smooths the whole mesh.
+/// Vertices used by triangles on both sides of a crease are duplicated so vertices and indices may
+/// grow. Vertices at the same position are smoothed together even if they have different uvs
+pub fn compute_normals<V: Vertex>(
+    vertices: &mut Vec<V>,
+    indices: &mut [u32],
+    angle_threshold: f32,
+) {
+    let face_normals = indices
+        .chunks_exact(3)
+        .map(|tri| {
+            let a = vertices[tri[0] as usize].pos();
+            let b = vertices[tri[1] as usize].pos();
+            let c = vertices[tri[2] as usize].pos();
+            // length is proportional to area so larger triangles contribute more
+            (b - a).cross(c - a)
+        })
+        .collect::<Vec<_>>();
+    let unit_normals = face_normals
+        .iter()
+        .map(|n| n.normalize_or_zero())
+        .collect::<Vec<_>>();
+
+    // triangles touching each position
+    let mut positions: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+    for (t, tri) in indices.chunks_exact(3).enumerate() {
+        for &i in tri {
+            let key = vertices[i as usize].pos().to_array().map(f32::to_bits);
+            let faces = positions.entry(key).or_default();
+            if faces.last() != Some(&t) {
+                faces.push(t);
+            }
+        }
+    }
+
+    let cos_threshold = angle_threshold.cos();
+    // (vertex, normal) to index of vertex with that normal
+    let mut split: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+    let mut normal_set = vec![false; vertices.len()];
+    for (corner, index) in indices.iter_mut().enumerate() {
+        let t = corner / 3;
+        let vertex = vertices[*index as usize];
+        let key = vertex.pos().to_array().map(f32::to_bits);
+        let normal = if angle_threshold <= 0.0 {
+            unit_normals[t]
+        } else {
+            positions[&key]
+                .iter()
+                .filter(|&&f| f == t || unit_normals[t].dot(unit_normals[f]) >= cos_threshold)
+                .fold(glam::Vec3::ZERO, |n, &f| n + face_normals[f])
+                .normalize_or_zero()
+        };
+
+        let normal_key = normal.to_array().map(f32::to_bits);
+        if let Some(&i) = split.get(&(*index, normal_key)) {
+            *index = i;
+            continue;
+        }
+        let new_index = if normal_set[*index as usize] {
+            // another corner gave this vertex a different normal so duplicate it
+            let mut duplicate = vertex;
+            duplicate.set_normal(normal);
+            vertices.push(duplicate);
+            normal_set.push(true);
+            (vertices.len() - 1) as u32
+        } else {
+            vertices[*index as usize].set_normal(normal);
+            normal_set[*index as usize] = true;
+            *index
+        };
+        split.insert((*index, normal_key), new_index);
+        *index = new_index;
+    }
+}
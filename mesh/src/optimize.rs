@@ -0,0 +1,233 @@
+//! Reordering indices and vertices so that meshes render faster
+//!
+//! [`optimize`] runs all the passes in the right order, the passes can also be run separately
+//! - [`optimize_vertex_cache`] reorders triangles so that recently transformed vertices are reused (Forsyth)
+//! - [`optimize_overdraw`] reorders clusters of triangles so that outward facing ones are drawn first (Tipsify)
+//! - [`optimize_vertex_fetch`] reorders vertices into the order they are first used
+
+use crate::Vertex;
+
+/// Size of the simulated post transform cache
+const CACHE_SIZE: usize = 32;
+const CACHE_DECAY_POWER: f32 = 1.5;
+const LAST_TRIANGLE_SCORE: f32 = 0.75;
+const VALENCE_BOOST_SCALE: f32 = 2.0;
+const VALENCE_BOOST_POWER: f32 = 0.5;
+
+/// Size of the fifo cache simulated when splitting triangles into clusters for overdraw
+const OVERDRAW_CACHE_SIZE: usize = 16;
+
+/// Optimize the mesh for the vertex cache, overdraw and vertex fetch
+///
+/// Vertices that aren't referenced by any index are removed
+pub fn optimize<V: Vertex>(vertices: &mut Vec<V>, indices: &mut [u32]) {
+    optimize_vertex_cache(indices, vertices.len());
+    optimize_overdraw(vertices, indices);
+    optimize_vertex_fetch(vertices, indices);
+}
+
+/// Score of a vertex in the cache position with the number of triangles still to be drawn using it
+fn vertex_score(cache_position: Option<usize>, remaining: usize) -> f32 {
+    if remaining == 0 {
+        return -1.0;
+    }
+    let mut score = match cache_position {
+        Some(p) if p < 3 => LAST_TRIANGLE_SCORE,
+        Some(p) => {
+            let scaler = 1.0 / (CACHE_SIZE - 3) as f32;
+            (1.0 - (p - 3) as f32 * scaler).powf(CACHE_DECAY_POWER)
+        }
+        None => 0.0,
+    };
+    score += VALENCE_BOOST_SCALE * (remaining as f32).powf(-VALENCE_BOOST_POWER);
+    score
+}
+
+/// Reorder the triangles of a triangle list to make better use of the post transform vertex cache
+///
+/// Uses Tom Forsyth's linear speed vertex cache optimisation
+/// <https://tomforsyth1000.github.io/papers/fast_vert_cache_opt.html>
+pub fn optimize_vertex_cache(indices: &mut [u32], vertex_count: usize) {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return;
+    }
+
+    // triangles that use each vertex
+    let mut offsets = vec![0usize; vertex_count + 1];
+    for &i in indices.iter() {
+        offsets[i as usize + 1] += 1;
+    }
+    for v in 0..vertex_count {
+        offsets[v + 1] += offsets[v];
+    }
+    let mut adjacency = vec![0usize; indices.len()];
+    let mut filled = offsets.clone();
+    for (t, tri) in indices.chunks_exact(3).enumerate() {
+        for &i in tri {
+            adjacency[filled[i as usize]] = t;
+            filled[i as usize] += 1;
+        }
+    }
+
+    let mut remaining = (0..vertex_count)
+        .map(|v| offsets[v + 1] - offsets[v])
+        .collect::<Vec<_>>();
+    let mut scores = remaining
+        .iter()
+        .map(|&r| vertex_score(None, r))
+        .collect::<Vec<_>>();
+    let triangle_score =
+        |tri: &[u32], scores: &[f32]| -> f32 { tri.iter().map(|&i| scores[i as usize]).sum() };
+    let mut emitted = vec![false; triangle_count];
+    let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE + 3);
+    let mut result = Vec::with_capacity(indices.len());
+
+    let mut best = None;
+    let mut cursor = 0;
+    for _ in 0..triangle_count {
+        let t = match best {
+            Some(t) => t,
+            None => {
+                // nothing live in the cache, fall back to the next triangle not yet emitted
+                while emitted[cursor] {
+                    cursor += 1;
+                }
+                cursor
+            }
+        };
+
+        emitted[t] = true;
+        let tri = [indices[t * 3], indices[t * 3 + 1], indices[t * 3 + 2]];
+        result.extend_from_slice(&tri);
+
+        for &v in &tri {
+            let v = v as usize;
+            remaining[v] -= 1;
+            // remove the emitted triangle from the vertex's live triangles
+            let live = &mut adjacency[offsets[v]..offsets[v] + remaining[v] + 1];
+            if let Some(p) = live.iter().position(|&a| a == t) {
+                live.swap(p, remaining[v]);
+            }
+        }
+
+        // move the triangle's vertices to the front of the cache
+        cache.retain(|v| !tri.contains(v));
+        for &v in tri.iter().rev() {
+            cache.insert(0, v);
+        }
+        for v in cache.drain(CACHE_SIZE.min(cache.len())..) {
+            scores[v as usize] = vertex_score(None, remaining[v as usize]);
+        }
+        for (p, &v) in cache.iter().enumerate() {
+            scores[v as usize] = vertex_score(Some(p), remaining[v as usize]);
+        }
+
+        // the next triangle is the best live triangle using a vertex in the cache
+        best = None;
+        let mut best_score = -1.0;
+        for &v in &cache {
+            let v = v as usize;
+            for &a in &adjacency[offsets[v]..offsets[v] + remaining[v]] {
+                let score = triangle_score(&indices[a * 3..a * 3 + 3], &scores);
+                if score > best_score {
+                    best_score = score;
+                    best = Some(a);
+                }
+            }
+        }
+    }
+
+    indices[..result.len()].copy_from_slice(&result);
+}
+
+/// Reorder clusters of triangles so that triangles facing out from the center of the mesh are drawn first
+///
+/// Should be run after [`optimize_vertex_cache`]. The triangles are split into clusters where the
+/// simulated vertex cache misses on every vertex of a triangle so that cache efficiency is mostly
+/// kept, then the clusters are sorted so that those facing away from the centroid come first
+/// as in Sander et al. "Fast Triangle Reordering for Vertex Locality and Reduced Overdraw"
+pub fn optimize_overdraw<V: Vertex>(vertices: &[V], indices: &mut [u32]) {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return;
+    }
+
+    // start a new cluster whenever a triangle misses the cache on all of its vertices
+    let mut cache: std::collections::VecDeque<u32> = std::collections::VecDeque::new();
+    let mut starts = Vec::new();
+    for (t, tri) in indices.chunks_exact(3).enumerate() {
+        let mut misses = 0;
+        for &v in tri {
+            if !cache.contains(&v) {
+                misses += 1;
+                cache.push_back(v);
+                if cache.len() > OVERDRAW_CACHE_SIZE {
+                    cache.pop_front();
+                }
+            }
+        }
+        if misses == 3 || t == 0 {
+            starts.push(t);
+        }
+    }
+    if starts.len() < 2 {
+        return;
+    }
+
+    let centroid =
+        vertices.iter().fold(glam::Vec3::ZERO, |a, v| a + v.pos()) / vertices.len().max(1) as f32;
+
+    let mut clusters = starts
+        .iter()
+        .enumerate()
+        .map(|(c, &start)| {
+            let end = starts.get(c + 1).copied().unwrap_or(triangle_count);
+            let mut center = glam::Vec3::ZERO;
+            let mut normal = glam::Vec3::ZERO;
+            let mut area = 0.0;
+            for tri in indices[start * 3..end * 3].chunks_exact(3) {
+                let a = vertices[tri[0] as usize].pos();
+                let b = vertices[tri[1] as usize].pos();
+                let c = vertices[tri[2] as usize].pos();
+                let n = (b - a).cross(c - a);
+                let tri_area = n.length();
+                center += (a + b + c) / 3.0 * tri_area;
+                normal += n;
+                area += tri_area;
+            }
+            if area > 0.0 {
+                center /= area;
+            }
+            let sort_key = (center - centroid).dot(normal.normalize_or_zero());
+            (start, end, sort_key)
+        })
+        .collect::<Vec<_>>();
+
+    clusters.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    let mut result = Vec::with_capacity(indices.len());
+    for (start, end, _) in clusters {
+        result.extend_from_slice(&indices[start * 3..end * 3]);
+    }
+    indices[..result.len()].copy_from_slice(&result);
+}
+
+/// Reorder the vertices into the order that they are first referenced by the indices
+///
+/// Vertices that aren't referenced are removed and the indices are remapped
+/// Returns the number of vertices kept
+pub fn optimize_vertex_fetch<V: Vertex>(vertices: &mut Vec<V>, indices: &mut [u32]) -> usize {
+    let mut remap = vec![u32::MAX; vertices.len()];
+    let mut result = Vec::with_capacity(vertices.len());
+    for index in indices.iter_mut() {
+        let old = *index as usize;
+        if remap[old] == u32::MAX {
+            remap[old] = result.len() as u32;
+            result.push(vertices[old]);
+        }
+        *index = remap[old];
+    }
+    *vertices = result;
+    vertices.len()
+}
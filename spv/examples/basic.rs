@@ -189,6 +189,7 @@ fn main() {
             depth_stencil: None,
             viewports: &[viewport],
             cache: None,
+            base: None,
         })
         .unwrap();
 
@@ -230,19 +231,15 @@ fn main() {
                             depth_stencil: None,
                             viewports: &[viewport],
                             cache: None,
+                            base: None,
                         })
                         .unwrap();
                 }
 
                 let view = match swapchain.acquire(!0) {
                     Ok((view, _)) => view,
-                    Err(e) => {
-                        if e.can_continue() {
-                            return;
-                        } else {
-                            panic!("{}", e)
-                        }
-                    }
+                    Err(gpu::Error::OutOfDateSwapchain) => return,
+                    Err(e) => panic!("{}", e),
                 };
 
                 command_buffer.begin(true).unwrap();
@@ -273,13 +270,8 @@ fn main() {
 
                 match swapchain.present(view) {
                     Ok(_) => (),
-                    Err(e) => {
-                        if e.can_continue() {
-                            return;
-                        } else {
-                            panic!("{}", e);
-                        }
-                    }
+                    Err(gpu::Error::OutOfDateSwapchain) => return,
+                    Err(e) => panic!("{}", e),
                 }
             }
             _ => (),
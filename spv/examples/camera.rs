@@ -307,6 +307,7 @@ fn main() {
                 stage: gpu::ShaderStages::VERTEX,
                 count: std::num::NonZeroU32::new(1).unwrap(),
             }],
+            immutable_samplers: &[],
         })
         .unwrap();
 
@@ -438,6 +439,7 @@ fn main() {
             depth_stencil,
             viewports: &[viewport],
             cache: None,
+            base: None,
         })
         .unwrap();
 
@@ -508,6 +510,7 @@ fn main() {
                             depth_stencil,
                             viewports: &[viewport],
                             cache: None,
+                            base: None,
                         })
                         .unwrap();
 
@@ -522,14 +525,11 @@ fn main() {
 
                 let view = match swapchain.acquire(!0) {
                     Ok((view, _)) => view,
-                    Err(e) => {
-                        if e.can_continue() {
-                            resized = true;
-                            return;
-                        } else {
-                            panic!("{}", e)
-                        }
+                    Err(gpu::Error::OutOfDateSwapchain) => {
+                        resized = true;
+                        return;
                     }
+                    Err(e) => panic!("{}", e),
                 };
 
                 command.begin(true).unwrap();
@@ -576,14 +576,11 @@ fn main() {
 
                 match swapchain.present(view) {
                     Ok(_) => (),
-                    Err(e) => {
-                        if e.can_continue() {
-                            resized = true;
-                            return;
-                        } else {
-                            panic!("{}", e);
-                        }
+                    Err(gpu::Error::OutOfDateSwapchain) => {
+                        resized = true;
+                        return;
                     }
+                    Err(e) => panic!("{}", e),
                 }
             }
             _ => (),
@@ -180,6 +180,7 @@ fn main() {
                     count: std::num::NonZeroU32::new(1).unwrap(),
                 },
             ],
+            immutable_samplers: &[],
         })
         .unwrap();
 
@@ -240,6 +241,7 @@ fn main() {
             depth_stencil: None,
             viewports: &[viewport],
             cache: None,
+            base: None,
         })
         .unwrap();
 
@@ -393,19 +395,15 @@ fn main() {
                             depth_stencil: None,
                             viewports: &[viewport],
                             cache: None,
+                            base: None,
                         })
                         .unwrap();
                 }
 
                 let view = match swapchain.acquire(!0) {
                     Ok((view, _)) => view,
-                    Err(e) => {
-                        if e.can_continue() {
-                            return;
-                        } else {
-                            panic!("{}", e)
-                        }
-                    }
+                    Err(gpu::Error::OutOfDateSwapchain) => return,
+                    Err(e) => panic!("{}", e),
                 };
 
                 command_buffer.begin(true).unwrap();
@@ -450,13 +448,8 @@ fn main() {
 
                 match swapchain.present(view) {
                     Ok(_) => (),
-                    Err(e) => {
-                        if e.can_continue() {
-                            return;
-                        } else {
-                            panic!("{}", e);
-                        }
-                    }
+                    Err(gpu::Error::OutOfDateSwapchain) => return,
+                    Err(e) => panic!("{}", e),
                 }
             }
             _ => (),
@@ -162,6 +162,7 @@ fn main() {
                 stage: gpu::ShaderStages::FRAGMENT,
                 count: std::num::NonZeroU32::new(1).unwrap(),
             }],
+            immutable_samplers: &[],
         })
         .unwrap();
 
@@ -226,6 +227,7 @@ fn main() {
             depth_stencil: None,
             viewports: &[viewport],
             cache: None,
+            base: None,
         })
         .unwrap();
 
@@ -270,19 +272,15 @@ fn main() {
                             depth_stencil: None,
                             viewports: &[viewport],
                             cache: None,
+                            base: None,
                         })
                         .unwrap();
                 }
 
                 let view = match swapchain.acquire(!0) {
                     Ok((view, _)) => view,
-                    Err(e) => {
-                        if e.can_continue() {
-                            return;
-                        } else {
-                            panic!("{}", e)
-                        }
-                    }
+                    Err(gpu::Error::OutOfDateSwapchain) => return,
+                    Err(e) => panic!("{}", e),
                 };
 
                 let elapsed = start_time.elapsed().as_secs_f32() / 5.0;
@@ -331,13 +329,8 @@ fn main() {
 
                 match swapchain.present(view) {
                     Ok(_) => (),
-                    Err(e) => {
-                        if e.can_continue() {
-                            return;
-                        } else {
-                            panic!("{}", e);
-                        }
-                    }
+                    Err(gpu::Error::OutOfDateSwapchain) => return,
+                    Err(e) => panic!("{}", e),
                 }
             }
             _ => (),
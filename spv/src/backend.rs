@@ -0,0 +1,218 @@
+//! Targets that the builder's instructions can be lowered to, see [`crate::Builder::compile_to`]
+
+use either::*;
+
+use std::collections::BTreeMap;
+
+use crate::Instruction;
+use crate::OpLoadStoreData;
+use crate::ScalarVal;
+use crate::Type;
+use crate::Val;
+use crate::VectorVal;
+use crate::MatrixVal;
+
+/// A target that the builder's instructions can be lowered to
+pub trait Backend {
+    type Output;
+
+    fn compile(&self, inner: &crate::BuilderInner) -> Self::Output;
+}
+
+/// Assemble a spir-v module, this is what [`crate::Builder::compile`] uses
+pub struct SpirV;
+
+impl Backend for SpirV {
+    type Output = Vec<u32>;
+
+    fn compile(&self, inner: &crate::BuilderInner) -> Vec<u32> {
+        inner.compile()
+    }
+}
+
+/// Write the entry point for the stage as glsl, see [`crate::Builder::to_glsl`]
+pub struct Glsl(pub crate::Stage);
+
+impl Backend for Glsl {
+    type Output = String;
+
+    fn compile(&self, inner: &crate::BuilderInner) -> String {
+        inner.to_glsl(self.0)
+    }
+}
+
+/// Write every entry point into one wgsl module for WebGPU, see [`crate::Builder::to_wgsl`]
+pub struct Wgsl;
+
+impl Backend for Wgsl {
+    type Output = String;
+
+    fn compile(&self, inner: &crate::BuilderInner) -> String {
+        inner.to_wgsl()
+    }
+}
+
+// helpers shared by the text backends
+// ================================================================================
+// ================================================================================
+// ================================================================================
+
+pub(crate) fn either_str<'a>(s: &'a Either<&'static str, String>) -> &'a str {
+    match s {
+        Left(s) => s,
+        Right(s) => s,
+    }
+}
+
+pub(crate) fn struct_member_name(s: &crate::StructType, field: u32) -> String {
+    s.members[field as usize]
+        .name
+        .as_ref()
+        .map(|n| either_str(n).to_owned())
+        .unwrap_or_else(|| format!("m{}", field))
+}
+
+pub(crate) fn member_name(ty: &Type, field: u32) -> String {
+    match ty {
+        Type::Struct(s) => struct_member_name(s, field),
+        _ => format!("m{}", field),
+    }
+}
+
+fn add_var(vars: &mut BTreeMap<usize, Type>, id: usize, ty: &Type) {
+    if !matches!(ty, Type::Void | Type::Texture(_)) {
+        vars.entry(id).or_insert_with(|| ty.clone());
+    }
+}
+
+/// Get the type of every function variable written or read by the instructions
+pub(crate) fn collect_vars(instructions: &[Instruction], vars: &mut BTreeMap<usize, Type>) {
+    for instruction in instructions {
+        match instruction {
+            Instruction::LhsRhs(o) => {
+                for (id, ty) in [&o.lhs, &o.rhs, &o.store] {
+                    add_var(vars, *id, ty);
+                }
+            },
+            Instruction::Lhs(o) => {
+                add_var(vars, o.lhs.0, &o.lhs.1);
+                add_var(vars, o.store.0, &o.store.1);
+            },
+            Instruction::VectorShuffle(o) => {
+                add_var(vars, o.src.0, &Type::Vector(o.src.1));
+                add_var(vars, o.dst.0, &Type::Vector(o.dst.1));
+            },
+            Instruction::LoadStore(o) => {
+                for data in [&o.src, &o.dst] {
+                    match data {
+                        OpLoadStoreData::Variable { id } => add_var(vars, *id, &o.ty),
+                        OpLoadStoreData::StorageElement { element, .. }
+                        | OpLoadStoreData::StorageElementField { element, .. } => add_var(vars, element.0, &element.1),
+                        OpLoadStoreData::Struct { id, struct_ty, .. } => add_var(vars, *id, &Type::Struct(struct_ty.clone())),
+                        OpLoadStoreData::ArrayElement { id, array_ty, index } => {
+                            add_var(vars, *id, &Type::Array(array_ty.clone()));
+                            add_var(vars, index.0, &index.1);
+                        },
+                        _ => (),
+                    }
+                }
+            },
+            Instruction::FuncCall(o) => {
+                for (id, ty) in &o.args {
+                    add_var(vars, *id, ty);
+                }
+                add_var(vars, o.store, &o.store_ty);
+            },
+            Instruction::Argument(o) => add_var(vars, o.store, &o.ty),
+            Instruction::ReturnValue(o) => add_var(vars, o.value.0, &o.value.1),
+            Instruction::SetConst(o) => add_var(vars, o.store, &o.val.ty()),
+            Instruction::Cmp(o) => {
+                add_var(vars, o.lhs.0, &o.lhs.1);
+                add_var(vars, o.rhs.0, &o.rhs.1);
+                add_var(vars, o.store, &Type::BOOL);
+            },
+            Instruction::Select(o) => {
+                add_var(vars, o.condition, &Type::BOOL);
+                for id in [o.a, o.b, o.store] {
+                    add_var(vars, id, &o.ty);
+                }
+            },
+            Instruction::Composite(o) => {
+                for (id, ty) in &o.constituents {
+                    add_var(vars, *id, ty);
+                }
+                add_var(vars, o.id, &o.ty);
+            },
+            Instruction::Extract(o) => {
+                add_var(vars, o.src_id, &o.src_ty);
+                add_var(vars, o.store_id, &o.element_ty);
+            },
+            Instruction::Sample(o) => {
+                add_var(vars, o.coordinate.0, &o.coordinate.1);
+                add_var(vars, o.store.0, &o.store.1);
+            },
+            Instruction::SampledTextureElement(o) => add_var(vars, o.index.0, &o.index.1),
+            Instruction::Convert(o) => {
+                add_var(vars, o.src.0, &o.src.1);
+                add_var(vars, o.dst.0, &o.dst.1);
+            },
+            Instruction::If(o) => collect_if_vars(o, vars),
+            Instruction::Loop(o) => {
+                collect_vars(&o.condition_instructions, vars);
+                add_var(vars, o.condition, &Type::BOOL);
+                collect_vars(&o.instructions, vars);
+            },
+            Instruction::Subgroup(o) => {
+                if let Some((id, ty)) = &o.value {
+                    add_var(vars, *id, ty);
+                }
+                if let Some(Right((id, ty))) = &o.index {
+                    add_var(vars, *id, ty);
+                }
+                add_var(vars, o.store.0, &o.store.1);
+            },
+            _ => (),
+        }
+    }
+}
+
+fn collect_if_vars(op: &crate::OpIf, vars: &mut BTreeMap<usize, Type>) {
+    add_var(vars, op.condition, &Type::BOOL);
+    collect_vars(&op.instructions, vars);
+    if let Some(then) = &*op.then.borrow() {
+        match then {
+            Left(t) => collect_if_vars(t, vars),
+            Right(t) => collect_vars(&t.instructions, vars),
+        }
+    }
+}
+
+/// Get the components of a constant, matrices are in column major order
+pub(crate) fn val_scalars(val: &Val) -> Vec<ScalarVal> {
+    match val {
+        Val::Scalar(s) => vec![*s],
+        Val::Vector(v) => match v {
+            VectorVal::IVec2(v) => v.to_array().iter().map(|x| ScalarVal::Int(*x)).collect::<Vec<_>>(),
+            VectorVal::IVec3(v) => v.to_array().iter().map(|x| ScalarVal::Int(*x)).collect(),
+            VectorVal::IVec4(v) => v.to_array().iter().map(|x| ScalarVal::Int(*x)).collect(),
+            VectorVal::UVec2(v) => v.to_array().iter().map(|x| ScalarVal::UInt(*x)).collect(),
+            VectorVal::UVec3(v) => v.to_array().iter().map(|x| ScalarVal::UInt(*x)).collect(),
+            VectorVal::UVec4(v) => v.to_array().iter().map(|x| ScalarVal::UInt(*x)).collect(),
+            VectorVal::Vec2(v) => v.to_array().iter().map(|x| ScalarVal::Float(*x)).collect(),
+            VectorVal::Vec3(v) => v.to_array().iter().map(|x| ScalarVal::Float(*x)).collect(),
+            VectorVal::Vec4(v) => v.to_array().iter().map(|x| ScalarVal::Float(*x)).collect(),
+            VectorVal::DVec2(v) => v.to_array().iter().map(|x| ScalarVal::Double(*x)).collect(),
+            VectorVal::DVec3(v) => v.to_array().iter().map(|x| ScalarVal::Double(*x)).collect(),
+            VectorVal::DVec4(v) => v.to_array().iter().map(|x| ScalarVal::Double(*x)).collect(),
+        },
+        // matrix constructors take scalars in column major order
+        Val::Matrix(m) => match m {
+            MatrixVal::Mat2(m) => m.to_cols_array().iter().map(|x| ScalarVal::Float(*x)).collect(),
+            MatrixVal::Mat3(m) => m.to_cols_array().iter().map(|x| ScalarVal::Float(*x)).collect(),
+            MatrixVal::Mat4(m) => m.to_cols_array().iter().map(|x| ScalarVal::Float(*x)).collect(),
+            MatrixVal::DMat2(m) => m.to_cols_array().iter().map(|x| ScalarVal::Double(*x)).collect(),
+            MatrixVal::DMat3(m) => m.to_cols_array().iter().map(|x| ScalarVal::Double(*x)).collect(),
+            MatrixVal::DMat4(m) => m.to_cols_array().iter().map(|x| ScalarVal::Double(*x)).collect(),
+        },
+    }
+}
@@ -6,6 +6,27 @@ use std::rc::Rc;
 use std::cell::RefCell;
 use std::marker::PhantomData;
 
+/// Get the index of the member called name on T, checking that it has type R
+fn struct_member<T: crate::IsStructTypeConst, R: crate::AsTypeConst>(name: &str) -> u32 {
+    let struct_ty = T::STRUCT_TY;
+    let (field, member) = struct_ty
+        .members
+        .iter()
+        .enumerate()
+        .find(|(_, m)| if let Some(n) = &m.name {
+            match n {
+                either::Either::Left(s) => *s == name,
+                either::Either::Right(s) => &**s == name,
+            }
+        } else {
+            false
+        }).expect(&format!("No field by name {} on struct", name));
+    if member.ty != R::TY {
+        panic!("Field {} on struct has type {:?} not {:?}", name, member.ty, R::TY);
+    }
+    field as u32
+}
+
 pub struct PushConstants<T: crate::IsTypeConst> {
     pub(crate) b: Rc<RefCell<crate::BuilderInner>>,
     pub(crate) marker: PhantomData<T>,
@@ -37,7 +58,7 @@ impl<T: crate::IsTypeConst + crate::IsStructTypeConst> PushConstants<T> {
             let new_id = scope.get_new_id();
 
             scope.push_instruction(crate::Instruction::LoadStore(crate::OpLoadStore {
-                ty: T::TY,
+                ty: R::TY,
                 src: crate::OpLoadStoreData::PushConstantField { field },
                 dst: crate::OpLoadStoreData::Variable { id: new_id },
             }));
@@ -97,7 +118,7 @@ impl<T: crate::IsTypeConst + crate::IsStructTypeConst> Uniform<T> {
             let new_id = scope.get_new_id();
 
             scope.push_instruction(crate::Instruction::LoadStore(crate::OpLoadStore {
-                ty: T::TY,
+                ty: R::TY,
                 src: crate::OpLoadStoreData::UniformField { field, id: self.id },
                 dst: crate::OpLoadStoreData::Variable { id: new_id },
             }));
@@ -123,6 +144,110 @@ impl<T: crate::IsTypeConst + crate::IsStructTypeConst> Uniform<T> {
             }).expect(&format!("No field by name {} on struct", field)).0;
         self.load_field_by_index::<R>(field as u32)
     }
+
+    /// Get a handle to a single member of the struct, loading it only loads that member
+    /// ```no_run
+    /// u_material.member::<spv::Float>("roughness").load();
+    /// ```
+    /// is equivalent to the glsl
+    /// ```glsl
+    /// u_material.data.roughness;
+    /// ```
+    pub fn member<R: crate::IsTypeConst>(&self, name: &str) -> UniformMember<R> {
+        UniformMember {
+            id: self.id,
+            field: struct_member::<T, R>(name),
+            nested: true,
+            b: Rc::clone(&self.b),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// A uniform block with multiple members, see [`crate::Builder::uniform_block`]
+pub struct UniformBlock {
+    pub(crate) id: usize,
+    pub(crate) b: Rc<RefCell<crate::BuilderInner>>,
+}
+
+impl UniformBlock {
+    /// Declare the next member of the block, it's offset follows std140 rules
+    /// ```no_run
+    /// block.member::<T>(name);
+    /// ```
+    /// is equivalent to the glsl
+    /// ```glsl
+    /// layout(set = s, binding = b) uniform UData {
+    ///     ..
+    ///     T name;
+    /// };
+    /// ```
+    pub fn member<T: crate::IsTypeConst>(&self, name: &'static str) -> UniformMember<T> {
+        let align = T::TY.std140_align()
+            .expect(&format!("Type {:?} can't be a member of a uniform block", T::TY));
+
+        let mut inner = self.b.borrow_mut();
+        let members = match &mut inner.uniforms[self.id].ty {
+            crate::Type::Struct(s) => s.members.to_mut(),
+            _ => unreachable!(),
+        };
+
+        if members.iter().any(|m| m.name.as_ref().map(|n| n.as_ref().either(|n| *n, |n| &**n)) == Some(name)) {
+            panic!("Uniform block already has a member named {}", name);
+        }
+
+        let end = members.last().map(|m| m.offset + m.ty.size().unwrap()).unwrap_or(0);
+        let offset = (end + align - 1) / align * align;
+
+        let field = members.len() as u32;
+        members.push(crate::StructMember {
+            name: Some(either::Left(name)),
+            ty: T::TY,
+            offset,
+        });
+
+        drop(inner);
+        UniformMember {
+            id: self.id,
+            field,
+            nested: false,
+            b: Rc::clone(&self.b),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// A member of a [`UniformBlock`] or of the struct in a [`Uniform`]
+pub struct UniformMember<T: crate::IsTypeConst> {
+    pub(crate) id: usize,
+    pub(crate) field: u32,
+    /// if true the member is in the struct wrapped by the block rather than the block itself
+    pub(crate) nested: bool,
+    pub(crate) b: Rc<RefCell<crate::BuilderInner>>,
+    pub(crate) marker: PhantomData<T>,
+}
+
+impl<T: crate::IsTypeConst> UniformMember<T> {
+    pub fn load<'a>(&'a self) -> T::T<'a> {
+        let mut inner = self.b.borrow_mut();
+        if let Some(scope) = &mut inner.scope {
+            let new_id = scope.get_new_id();
+
+            scope.push_instruction(crate::Instruction::LoadStore(crate::OpLoadStore {
+                ty: T::TY,
+                src: if self.nested {
+                    crate::OpLoadStoreData::UniformField { field: self.field, id: self.id }
+                } else {
+                    crate::OpLoadStoreData::UniformMember { field: self.field, id: self.id }
+                },
+                dst: crate::OpLoadStoreData::Variable { id: new_id },
+            }));
+
+            T::T::from_id(new_id, &self.b)
+        } else {
+            panic!("Cannot load uniform when not in function");
+        }
+    }
 }
 
 pub struct Storage<T: crate::IsTypeConst> {
@@ -151,6 +276,26 @@ impl<T: crate::IsTypeConst> Storage<T> {
             panic!("Cannot load storage element when not in function");
         }
     }
+
+    pub fn store_element<'a>(&'a self, element: impl SpvRustEq<crate::Int<'a>>, value: impl SpvRustEq<T::T<'a>>) {
+        let mut inner = self.b.borrow_mut();
+        if !inner.storages[self.id].write {
+            panic!("Cannot store to readonly storage buffer");
+        }
+        if let Some(scope) = &mut inner.scope {
+            let element_id = element.id(&mut **scope);
+            let element_ty = element.ty();
+            let value_id = value.id(&mut **scope);
+
+            scope.push_instruction(crate::Instruction::LoadStore(crate::OpLoadStore {
+                ty: T::TY,
+                src: crate::OpLoadStoreData::Variable { id: value_id },
+                dst: crate::OpLoadStoreData::StorageElement { id: self.id, element: (element_id, element_ty) },
+            }));
+        } else {
+            panic!("Cannot store storage element when not in function");
+        }
+    }
 }
 
 impl<T: crate::IsTypeConst + crate::IsStructTypeConst> Storage<T> {
@@ -163,7 +308,7 @@ impl<T: crate::IsTypeConst + crate::IsStructTypeConst> Storage<T> {
             let element_ty = element.ty();
 
             scope.push_instruction(crate::Instruction::LoadStore(crate::OpLoadStore {
-                ty: T::TY,
+                ty: R::TY,
                 src: crate::OpLoadStoreData::StorageElementField { id: self.id, element: (element_id, element_ty), field },
                 dst: crate::OpLoadStoreData::Variable { id: new_id },
             }));
@@ -189,4 +334,131 @@ impl<T: crate::IsTypeConst + crate::IsStructTypeConst> Storage<T> {
             }).expect(&format!("No field by name {} on struct", field)).0;
         self.load_field_by_index::<R>(element, field as u32)
     }
+
+    /// Get a handle to a single member of the struct at element, loading or storing it only touches that member
+    /// ```no_run
+    /// let roughness = s_materials.member::<spv::Float>(i, "roughness");
+    /// roughness.store(roughness.load() * 0.5);
+    /// ```
+    /// is equivalent to the glsl
+    /// ```glsl
+    /// s_materials.data[i].roughness = s_materials.data[i].roughness * 0.5;
+    /// ```
+    pub fn member<'a, R: crate::IsTypeConst>(&'a self, element: impl SpvRustEq<crate::Int<'a>>, name: &str) -> StorageMember<'a, R> {
+        let field = struct_member::<T, R>(name);
+
+        let mut inner = self.b.borrow_mut();
+        if let Some(scope) = &mut inner.scope {
+            let element_id = element.id(&mut **scope);
+            let element_ty = element.ty();
+
+            StorageMember {
+                id: self.id,
+                element: (element_id, element_ty),
+                field,
+                b: &self.b,
+                marker: PhantomData,
+            }
+        } else {
+            panic!("Cannot access storage member when not in function");
+        }
+    }
+}
+
+/// A member of the struct at one element of a [`Storage`] buffer
+pub struct StorageMember<'a, T: crate::IsTypeConst> {
+    pub(crate) id: usize,
+    pub(crate) element: (usize, crate::Type),
+    pub(crate) field: u32,
+    pub(crate) b: &'a Rc<RefCell<crate::BuilderInner>>,
+    pub(crate) marker: PhantomData<T>,
+}
+
+impl<'a, T: crate::IsTypeConst> StorageMember<'a, T> {
+    pub fn load(&self) -> T::T<'a> {
+        let mut inner = self.b.borrow_mut();
+        if let Some(scope) = &mut inner.scope {
+            let new_id = scope.get_new_id();
+
+            scope.push_instruction(crate::Instruction::LoadStore(crate::OpLoadStore {
+                ty: T::TY,
+                src: crate::OpLoadStoreData::StorageElementField { id: self.id, element: self.element.clone(), field: self.field },
+                dst: crate::OpLoadStoreData::Variable { id: new_id },
+            }));
+
+            T::T::from_id(new_id, self.b)
+        } else {
+            panic!("Cannot load storage member when not in function");
+        }
+    }
+
+    pub fn store(&self, value: impl SpvRustEq<T::T<'a>>) {
+        let mut inner = self.b.borrow_mut();
+        if !inner.storages[self.id].write {
+            panic!("Cannot store to readonly storage buffer");
+        }
+        if let Some(scope) = &mut inner.scope {
+            let value_id = value.id(&mut **scope);
+
+            scope.push_instruction(crate::Instruction::LoadStore(crate::OpLoadStore {
+                ty: T::TY,
+                src: crate::OpLoadStoreData::Variable { id: value_id },
+                dst: crate::OpLoadStoreData::StorageElementField { id: self.id, element: self.element.clone(), field: self.field },
+            }));
+        } else {
+            panic!("Cannot store storage member when not in function");
+        }
+    }
+}
+
+/// An array of sampled textures, see [`crate::Builder::sampled_texture_array`]
+pub struct SampledTextureArray<D: crate::AsDimension, S: crate::SampledGTexture<D>> {
+    pub(crate) id: usize,
+    pub(crate) count: u32,
+    pub(crate) b: Rc<RefCell<crate::BuilderInner>>,
+    pub(crate) marker: PhantomData<(D, S)>,
+}
+
+impl<D: crate::AsDimension, S: crate::SampledGTexture<D>> SampledTextureArray<D, S> {
+    /// The number of textures in the array
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    fn element(&self, index: &dyn crate::AsType, non_uniform: bool) -> S {
+        let ty = index.ty();
+        assert!(
+            matches!(ty, crate::Type::Scalar(crate::ScalarType::Signed(_)) | crate::Type::Scalar(crate::ScalarType::Unsigned(_))),
+            "Cannot index sampled texture array with {:?}", ty
+        );
+
+        let mut inner = self.b.borrow_mut();
+        if let Some(scope) = &mut inner.scope {
+            let index_id = index.id(&mut **scope);
+            let store = scope.get_new_id();
+            scope.push_instruction(crate::Instruction::SampledTextureElement(crate::OpSampledTextureElement {
+                tex_ty: <S::Texture as crate::GTexture<D>>::TEXTURE_TY,
+                sampled_texture: self.id,
+                index: (index_id, ty),
+                non_uniform,
+                store,
+            }));
+            drop(inner);
+            S::from_combine(store, Rc::clone(&self.b))
+        } else {
+            panic!("Cannot index sampled texture array when not in function");
+        }
+    }
+
+    /// Get the texture at index, the index must be the same for all invocations in a draw or dispatch
+    pub fn index(&self, index: impl crate::AsType) -> S {
+        self.element(&index, false)
+    }
+
+    /// Get the texture at index where the index can differ between invocations, for example a per fragment material id
+    ///
+    /// Requires the device to be created with `gpu::DeviceFeatures::DESCRIPTOR_INDEXING`
+    pub fn index_non_uniform(&self, index: impl crate::AsType) -> S {
+        self.element(&index, true)
+    }
 }
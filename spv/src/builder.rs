@@ -2,12 +2,17 @@
 use either::*;
 use rspirv::binary::Assemble;
 
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 
 pub(crate) struct RSpirvBuilder {
     pub(crate) raw: rspirv::dr::Builder,
     pub(crate) ext: u32,
     pub(crate) struct_map: HashMap<crate::StructType, u32>,
+    /// capabilities required by instructions beyond Shader, emitted after compiling functions
+    pub(crate) capabilities: BTreeSet<rspirv::spirv::Capability>,
+    /// extensions required by instructions, emitted after compiling functions
+    pub(crate) extensions: BTreeSet<&'static str>,
 }
 
 impl std::ops::Deref for RSpirvBuilder {
@@ -29,7 +34,9 @@ pub struct IOData {
     pub ty: crate::IOType,
     pub location: Either<u32, rspirv::spirv::BuiltIn>,
     pub flat: bool,
-    pub name: Option<&'static str>
+    pub name: Option<&'static str>,
+    /// if true the location was allocated by the builder, see [`crate::Builder::input_auto`]
+    pub auto: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -51,6 +58,8 @@ pub struct UniformData {
     pub set: u32,
     pub binding: u32,
     pub name: Option<&'static str>,
+    /// if true ty is a struct whose members are the members of the block
+    pub block: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -71,12 +80,21 @@ pub struct TextureData {
     pub name: Option<&'static str>,
 }
 
+impl TextureData {
+    /// Get the format of the texture if it was declared as a storage image
+    pub fn format(&self) -> Option<crate::TextureFormat> {
+        self.ty.image_format()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct SampledTextureData {
     pub set: u32,
     pub binding: u32,
     pub ty: crate::TextureType,
     pub name: Option<&'static str>,
+    /// Some(n) if declared as an array of n sampled textures
+    pub count: Option<u32>,
 }
 
 #[derive(Clone, Debug)]
@@ -97,6 +115,9 @@ pub struct BuilderInner {
     pub(crate) samplers: Vec<SamplerData>,
     pub(crate) functions: HashMap<usize, FuncData>,
     pub(crate) entry_points: HashMap<crate::Stage, usize>,
+    pub(crate) local_size: [u32; 3],
+    pub(crate) topology: Option<crate::PrimitiveTopology>,
+    pub(crate) ssa: bool,
     pub(crate) scope: Option<Box<dyn crate::Scope>>,
 }
 
@@ -113,10 +134,74 @@ impl BuilderInner {
             samplers: Vec::new(),
             functions: HashMap::new(),
             entry_points: HashMap::new(),
+            local_size: [1, 1, 1],
+            topology: None,
+            ssa: false,
             scope: None,
         }
     }
 
+    /// Get the lowest location where an io of type ty doesn't overlap any io in io
+    pub(crate) fn free_location(io: &[IOData], ty: crate::IOType) -> u32 {
+        let used = io.iter()
+            .filter_map(|i| i.location.left().map(|l| l..l + i.ty.locations()))
+            .collect::<Vec<_>>();
+        (0..)
+            .find(|l| !used.iter().any(|u| u.start < l + ty.locations() && *l < u.end))
+            .unwrap()
+    }
+
+    /// Get the indices of the inputs and outputs used by the function and the functions it calls
+    pub(crate) fn used_io(&self, func: usize) -> (BTreeSet<usize>, BTreeSet<usize>) {
+        let mut inputs = BTreeSet::new();
+        let mut outputs = BTreeSet::new();
+        let mut visited = BTreeSet::new();
+        self.collect_io(func, &mut inputs, &mut outputs, &mut visited);
+        (inputs, outputs)
+    }
+
+    fn collect_io(&self, func: usize, inputs: &mut BTreeSet<usize>, outputs: &mut BTreeSet<usize>, visited: &mut BTreeSet<usize>) {
+        if !visited.insert(func) {
+            return;
+        }
+        if let Some(f) = self.functions.get(&func) {
+            self.collect_instructions_io(&f.instructions, inputs, outputs, visited);
+        }
+    }
+
+    fn collect_instructions_io(&self, instructions: &[crate::Instruction], inputs: &mut BTreeSet<usize>, outputs: &mut BTreeSet<usize>, visited: &mut BTreeSet<usize>) {
+        for instruction in instructions {
+            match instruction {
+                crate::Instruction::LoadStore(o) => {
+                    for data in [&o.src, &o.dst] {
+                        match data {
+                            crate::OpLoadStoreData::Input { location } => { inputs.insert(*location); },
+                            crate::OpLoadStoreData::Output { location } => { outputs.insert(*location); },
+                            _ => (),
+                        }
+                    }
+                },
+                crate::Instruction::FuncCall(o) => self.collect_io(o.func, inputs, outputs, visited),
+                crate::Instruction::If(o) => self.collect_if_io(o, inputs, outputs, visited),
+                crate::Instruction::Loop(o) => {
+                    self.collect_instructions_io(&o.condition_instructions, inputs, outputs, visited);
+                    self.collect_instructions_io(&o.instructions, inputs, outputs, visited);
+                },
+                _ => (),
+            }
+        }
+    }
+
+    fn collect_if_io(&self, op: &crate::OpIf, inputs: &mut BTreeSet<usize>, outputs: &mut BTreeSet<usize>, visited: &mut BTreeSet<usize>) {
+        self.collect_instructions_io(&op.instructions, inputs, outputs, visited);
+        if let Some(then) = &*op.then.borrow() {
+            match then {
+                Left(t) => self.collect_if_io(t, inputs, outputs, visited),
+                Right(t) => self.collect_instructions_io(&t.instructions, inputs, outputs, visited),
+            }
+        }
+    }
+
     pub fn __scope<'a>(&'a mut self) -> Option<&'a mut dyn crate::Scope> {
         if let Some(scope) = &mut self.scope {
             Some(&mut **scope)
@@ -154,6 +239,8 @@ pub(crate) enum BlockInfo {
 
 pub(crate) struct FuncMapInfo {
     pub vars: HashMap<usize, u32>,
+    /// OpFunctionParameter of each argument
+    pub arguments: Vec<u32>,
     pub var_block: usize,
     pub block_info: BlockInfo,
 }
@@ -196,6 +283,8 @@ impl BuilderInner {
             raw: raw_builder,
             ext,
             struct_map: HashMap::new(),
+            capabilities: BTreeSet::new(),
+            extensions: BTreeSet::new(),
         };
 
         b.set_version(1, 0);
@@ -214,9 +303,24 @@ impl BuilderInner {
 
         let shader_info = self.map_info(&mut b);
 
+        if self.inputs.iter().any(|i| matches!(
+            i.location, 
+            Right(rspirv::spirv::BuiltIn::SubgroupSize) | Right(rspirv::spirv::BuiltIn::SubgroupLocalInvocationId)
+        )) {
+            b.capabilities.insert(rspirv::spirv::Capability::GroupNonUniform);
+        }
+
         for (id, func) in self.functions.iter() {
             let (_, fn_idx) = *shader_info.functions.get(id).unwrap();
             b.select_function(Some(fn_idx)).unwrap();
+
+            let arguments = func.arguments
+                .iter()
+                .map(|ty| {
+                    let spv_ty = ty.rspirv(&mut b);
+                    b.function_parameter(spv_ty).unwrap()
+                })
+                .collect();
             
             b.begin_block(None).unwrap();
             let var_block = b.selected_block().unwrap();
@@ -224,6 +328,7 @@ impl BuilderInner {
             let mut func_info = FuncMapInfo {
                 var_block,
                 vars: HashMap::new(),
+                arguments,
                 block_info: BlockInfo::None,
             };
 
@@ -240,19 +345,41 @@ impl BuilderInner {
             }
         }
 
-        let mut interface = shader_info.inputs.clone();
-        interface.extend_from_slice(&shader_info.outputs);
-
         for (stage, fn_id) in &self.entry_points {
             let (spv_fn, _) = *shader_info.functions.get(fn_id).unwrap();
-            stage.specialize(&mut b, spv_fn);
+            stage.specialize(&mut b, spv_fn, self.local_size);
 
             let func = self.functions.get(fn_id).unwrap();
 
+            // only the io used by the entry point, builtins from other stages aren't allowed in the interface
+            let (inputs, outputs) = self.used_io(*fn_id);
+            let interface = inputs.iter()
+                .map(|i| shader_info.inputs[*i])
+                .chain(outputs.iter().map(|o| shader_info.outputs[*o]))
+                .collect::<Vec<_>>();
+
             b.entry_point(stage.rspirv(), spv_fn, func.name.unwrap(), &interface)
         }
 
-        b.raw.module().assemble()
+        // group non uniform operations require spir-v 1.3
+        if b.capabilities.contains(&rspirv::spirv::Capability::GroupNonUniform) {
+            b.set_version(1, 3);
+        }
+        for capability in std::mem::take(&mut b.capabilities) {
+            b.capability(capability);
+        }
+        for extension in std::mem::take(&mut b.extensions) {
+            b.extension(extension);
+        }
+
+        let mut module = b.raw.module();
+        if self.ssa {
+            crate::ssa::promote(&mut module);
+        }
+        let spv = module.assemble();
+        #[cfg(all(feature = "validate", debug_assertions))]
+        crate::validate::validate(&spv);
+        spv
     }
 
     fn map_info(&self, b: &mut RSpirvBuilder) -> ShaderMapInfo {
@@ -355,15 +482,20 @@ impl BuilderInner {
             .iter()
             .map(|u| {
                 let spv_ty = u.ty.rspirv(b);
-                let outer_spv_ty = b.type_struct([spv_ty]);
+                let outer_spv_ty = if u.block {
+                    spv_ty
+                } else {
+                    let outer_spv_ty = b.type_struct([spv_ty]);
+                    b.member_decorate(
+                        outer_spv_ty, 
+                        0, 
+                        rspirv::spirv::Decoration::Offset, 
+                        [rspirv::dr::Operand::LiteralInt32(0)]
+                    );
+                    outer_spv_ty
+                };
 
                 b.decorate(outer_spv_ty, rspirv::spirv::Decoration::Block, None);
-                b.member_decorate(
-                    outer_spv_ty, 
-                    0, 
-                    rspirv::spirv::Decoration::Offset, 
-                    [rspirv::dr::Operand::LiteralInt32(0)]
-                );
 
                 let p_spv_ty = b.type_pointer(None, rspirv::spirv::StorageClass::Uniform, outer_spv_ty);
                 let var = b.variable(p_spv_ty, None, rspirv::spirv::StorageClass::Uniform, None);
@@ -516,7 +648,13 @@ impl BuilderInner {
             .map(|t| {
                 let spv_tex_ty = t.ty.rspirv(b);
 
-                let spv_sampled_tex_ty = b.type_sampled_image(spv_tex_ty);
+                let mut spv_sampled_tex_ty = b.type_sampled_image(spv_tex_ty);
+
+                if let Some(count) = t.count {
+                    let spv_uint_ty = crate::ScalarType::UINT.rspirv(b);
+                    let spv_count = b.constant_u32(spv_uint_ty, count);
+                    spv_sampled_tex_ty = b.type_array(spv_sampled_tex_ty, spv_count);
+                }
 
                 let spv_p_ty = b.type_pointer(None, rspirv::spirv::StorageClass::UniformConstant, spv_sampled_tex_ty);
 
@@ -0,0 +1,223 @@
+//! Common subexpression elimination
+//!
+//! Variables in a scope can be written more than once (for example by `+=`) so values are only reused while
+//! neither the operands nor the variable holding the earlier result have been written since.
+
+use either::*;
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+
+/// Pure instructions that produce the same value given the same operands
+///
+/// operands are replaced by the variable they are a copy of so that values computed from copies can be reused too
+fn key(instruction: &crate::Instruction, values: &Values) -> Option<(String, Vec<usize>, usize, crate::Type)> {
+    let c = |(id, ty): &(usize, crate::Type)| (values.canonical(*id), ty.clone());
+    match instruction {
+        crate::Instruction::LhsRhs(o) => Some((
+            format!("lhs_rhs {:?} {:?} {:?} {:?}", o.ty, c(&o.lhs), c(&o.rhs), o.store.1),
+            vec![values.canonical(o.lhs.0), values.canonical(o.rhs.0)],
+            o.store.0,
+            o.store.1.clone(),
+        )),
+        crate::Instruction::Lhs(o) => Some((
+            format!("lhs {:?} {:?} {:?}", o.ty, c(&o.lhs), o.store.1),
+            vec![values.canonical(o.lhs.0)],
+            o.store.0,
+            o.store.1.clone(),
+        )),
+        crate::Instruction::VectorShuffle(o) => Some((
+            format!("shuffle {:?} {:?} {:?} {:?}", values.canonical(o.src.0), o.src.1, o.dst.1, o.components),
+            vec![values.canonical(o.src.0)],
+            o.dst.0,
+            crate::Type::Vector(o.dst.1),
+        )),
+        crate::Instruction::Cmp(o) => Some((
+            format!("cmp {:?} {:?} {:?}", o.cmp, c(&o.lhs), c(&o.rhs)),
+            vec![values.canonical(o.lhs.0), values.canonical(o.rhs.0)],
+            o.store,
+            crate::Type::BOOL,
+        )),
+        crate::Instruction::Select(o) => Some((
+            format!("select {} {:?} {} {}", values.canonical(o.condition), o.ty, values.canonical(o.a), values.canonical(o.b)),
+            vec![values.canonical(o.condition), values.canonical(o.a), values.canonical(o.b)],
+            o.store,
+            o.ty.clone(),
+        )),
+        crate::Instruction::Composite(o) => Some((
+            format!("composite {:?} {:?}", o.ty, o.constituents.iter().map(c).collect::<Vec<_>>()),
+            o.constituents.iter().map(|c| values.canonical(c.0)).collect(),
+            o.id,
+            o.ty.clone(),
+        )),
+        crate::Instruction::Extract(o) => Some((
+            format!("extract {} {:?} {:?} {}", values.canonical(o.src_id), o.src_ty, o.element_ty, o.element_idx),
+            vec![values.canonical(o.src_id)],
+            o.store_id,
+            o.element_ty.clone(),
+        )),
+        crate::Instruction::Convert(o) => Some((
+            format!("convert {:?} {:?}", c(&o.src), o.dst.1),
+            vec![values.canonical(o.src.0)],
+            o.dst.0,
+            o.dst.1.clone(),
+        )),
+        crate::Instruction::SetConst(o) => Some((
+            format!("const {:?}", o.val),
+            Vec::new(),
+            o.store,
+            o.val.ty(),
+        )),
+        _ => None,
+    }
+}
+
+fn load_store_writes(data: &crate::OpLoadStoreData, writes: &mut BTreeSet<usize>) {
+    match data {
+        crate::OpLoadStoreData::Variable { id }
+        | crate::OpLoadStoreData::Struct { id, .. }
+        | crate::OpLoadStoreData::ArrayElement { id, .. } => { writes.insert(*id); },
+        _ => (),
+    }
+}
+
+/// Collect the variables written by the instruction
+fn writes(instruction: &crate::Instruction, writes: &mut BTreeSet<usize>) {
+    match instruction {
+        crate::Instruction::LhsRhs(o) => { writes.insert(o.store.0); },
+        crate::Instruction::Lhs(o) => { writes.insert(o.store.0); },
+        crate::Instruction::VectorShuffle(o) => { writes.insert(o.dst.0); },
+        crate::Instruction::LoadStore(o) => load_store_writes(&o.dst, writes),
+        crate::Instruction::FuncCall(o) => { writes.insert(o.store); },
+        crate::Instruction::Argument(o) => { writes.insert(o.store); },
+        crate::Instruction::SetConst(o) => { writes.insert(o.store); },
+        crate::Instruction::Cmp(o) => { writes.insert(o.store); },
+        crate::Instruction::Select(o) => { writes.insert(o.store); },
+        crate::Instruction::Composite(o) => { writes.insert(o.id); },
+        crate::Instruction::Extract(o) => { writes.insert(o.store_id); },
+        crate::Instruction::Sample(o) => { writes.insert(o.store.0); },
+        crate::Instruction::Combine(o) => { writes.insert(o.store); },
+        crate::Instruction::SampledTextureElement(o) => { writes.insert(o.store); },
+        crate::Instruction::Convert(o) => { writes.insert(o.dst.0); },
+        crate::Instruction::Subgroup(o) => { writes.insert(o.store.0); },
+        crate::Instruction::If(o) => if_writes(o, writes),
+        crate::Instruction::Loop(o) => o.condition_instructions
+            .iter()
+            .chain(o.instructions.iter())
+            .for_each(|i| self::writes(i, writes)),
+        _ => (),
+    }
+}
+
+fn if_writes(op: &crate::OpIf, w: &mut BTreeSet<usize>) {
+    op.instructions.iter().for_each(|i| writes(i, w));
+    if let Some(then) = &*op.then.borrow() {
+        match then {
+            Left(t) => if_writes(t, w),
+            Right(t) => t.instructions.iter().for_each(|i| writes(i, w)),
+        }
+    }
+}
+
+/// Available values, the variable holding the result and the operands it was computed from
+#[derive(Clone, Default)]
+struct Values {
+    values: HashMap<String, (usize, Vec<usize>)>,
+    /// variables holding a copy of another variable
+    copies: HashMap<usize, usize>,
+}
+
+impl Values {
+    fn canonical(&self, id: usize) -> usize {
+        *self.copies.get(&id).unwrap_or(&id)
+    }
+
+    fn invalidate(&mut self, written: &BTreeSet<usize>) {
+        self.values.retain(|_, (result, operands)| {
+            !written.contains(result) && !operands.iter().any(|o| written.contains(o))
+        });
+        self.copies.retain(|copy, src| !written.contains(copy) && !written.contains(src));
+    }
+}
+
+fn eliminate_if(op: &mut crate::OpIf, values: &Values) -> usize {
+    let mut count = eliminate(&mut op.instructions, &mut values.clone());
+    if let Some(then) = &mut *op.then.borrow_mut() {
+        count += match then {
+            Left(t) => eliminate_if(t, values),
+            Right(t) => eliminate(&mut t.instructions, &mut values.clone()),
+        };
+    }
+    count
+}
+
+fn eliminate_loop(op: &mut crate::OpLoop, values: &Values) -> usize {
+    // values from before the loop can only be used if nothing in the loop overwrites them on a later iteration
+    let mut written = BTreeSet::new();
+    op.condition_instructions
+        .iter()
+        .chain(op.instructions.iter())
+        .for_each(|i| writes(i, &mut written));
+    let mut values = values.clone();
+    values.invalidate(&written);
+    eliminate(&mut op.condition_instructions, &mut values) + eliminate(&mut op.instructions, &mut values)
+}
+
+fn eliminate(instructions: &mut [crate::Instruction], values: &mut Values) -> usize {
+    let mut count = 0;
+    for instruction in instructions {
+        match instruction {
+            // values computed before the if are available in both branches but not the other way round
+            crate::Instruction::If(o) => count += eliminate_if(o, values),
+            crate::Instruction::Loop(o) => count += eliminate_loop(o, values),
+            _ => (),
+        }
+
+        let mut written = BTreeSet::new();
+        writes(instruction, &mut written);
+
+        let key = key(instruction, values);
+
+        if let Some((key, _, store, ty)) = &key {
+            if let Some((result, _)) = values.values.get(key).cloned() {
+                if result != *store {
+                    *instruction = crate::Instruction::LoadStore(crate::OpLoadStore {
+                        ty: ty.clone(),
+                        src: crate::OpLoadStoreData::Variable { id: result },
+                        dst: crate::OpLoadStoreData::Variable { id: *store },
+                    });
+                    count += 1;
+                    values.invalidate(&written);
+                    values.copies.insert(*store, result);
+                } else {
+                    values.invalidate(&written);
+                }
+                continue;
+            }
+        }
+
+        values.invalidate(&written);
+
+        if let Some((key, operands, store, _)) = key {
+            // eg a += b can't be reused as a has changed
+            if !operands.contains(&store) {
+                values.values.insert(key, (store, operands));
+            }
+        }
+    }
+    count
+}
+
+impl crate::Builder {
+    /// Replace pure instructions that recompute a value already computed in the same function with a copy of the earlier result
+    ///
+    /// Returns the number of instructions replaced, this should be called once all functions have been declared and before compiling
+    pub fn eliminate_common_subexpressions(&self) -> usize {
+        let mut inner = self.__inner().borrow_mut();
+        assert!(inner.scope.is_none(), "Cannot eliminate common subexpressions when builder is in a function");
+        inner.functions
+            .values_mut()
+            .map(|f| eliminate(&mut f.instructions, &mut Values::default()))
+            .sum()
+    }
+}
@@ -361,6 +361,10 @@ impl StructType {
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum TextureFormat {
     R8Unorm,
+    R16Float,
+    R32Uint,
+    R32Sint,
+    R32Float,
     Rg8Unorm,
     Rg8Snorm,
     Rg16Unorm,
@@ -407,6 +411,21 @@ pub enum TextureFormat {
     // Bgra8Srgb,
 }
 
+impl TextureFormat {
+    /// Get the scalar type of texels read from an image of this format
+    pub fn scalar_ty(&self) -> ScalarType {
+        match self {
+            TextureFormat::R32Uint
+            | TextureFormat::Rg32Uint
+            | TextureFormat::Rgba32Uint => ScalarType::UINT,
+            TextureFormat::R32Sint
+            | TextureFormat::Rg32Sint
+            | TextureFormat::Rgba32Sint => ScalarType::INT,
+            _ => ScalarType::FLOAT,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum TextureSpvFormat {
     Color(TextureFormat),
@@ -419,6 +438,10 @@ impl TextureSpvFormat {
         match self {
             Self::Color(c) => match c {
                 TextureFormat::R8Unorm => rspirv::spirv::ImageFormat::R8,
+                TextureFormat::R16Float => rspirv::spirv::ImageFormat::R16f,
+                TextureFormat::R32Uint => rspirv::spirv::ImageFormat::R32ui,
+                TextureFormat::R32Sint => rspirv::spirv::ImageFormat::R32i,
+                TextureFormat::R32Float => rspirv::spirv::ImageFormat::R32f,
                 TextureFormat::Rg8Unorm => rspirv::spirv::ImageFormat::Rg8,
                 TextureFormat::Rg8Snorm => rspirv::spirv::ImageFormat::Rg8Snorm,
                 TextureFormat::Rg16Unorm => rspirv::spirv::ImageFormat::Rg16,
@@ -537,16 +560,24 @@ impl TextureType {
             } else {
                 0
             },
-            if let TextureSpvFormat::Sampled = self.format {
-                1
-            } else {
-                0
+            // storage images must be declared as not sampled in vulkan
+            match self.format {
+                TextureSpvFormat::Color(_) => 2,
+                _ => 1,
             },
             self.format.rspirv(),
             None,
         )
     }
 
+    /// Get the format of a storage image, None for sampled and depth textures
+    pub fn image_format(&self) -> Option<TextureFormat> {
+        match self.format {
+            TextureSpvFormat::Color(f) => Some(f),
+            _ => None,
+        }
+    }
+
     pub(crate) fn pointer(&self, b: &mut crate::RSpirvBuilder) -> u32 {
         let spv_tex_ty = self.rspirv(b);
         b.type_pointer(None, rspirv::spirv::StorageClass::UniformConstant, spv_tex_ty)
@@ -621,6 +652,22 @@ impl Type {
         }
     }
 
+    /// Get the base alignment of the type in a std140 uniform block
+    ///
+    /// Returns None if the type can't be a member of a uniform block declared with [`crate::Builder::uniform_block`]
+    pub fn std140_align(&self) -> Option<u32> {
+        match self {
+            Type::Scalar(s) if !s.is_bool() => Some(s.size()),
+            Type::Vector(v) if !v.scalar_ty.is_bool() => match v.n_scalar {
+                2 => Some(2 * v.scalar_ty.size()),
+                _ => Some(4 * v.scalar_ty.size()),
+            },
+            // matrix columns are aligned to vec4 but MatrixStride is the size of a column
+            Type::Matrix(m) if m.stride() % 16 == 0 => Some(m.stride()),
+            _ => None,
+        }
+    }
+
     pub fn size(&self) -> Option<u32> {
         match self {
             Type::Void => Some(0),
@@ -30,6 +30,12 @@ pub trait SpvRustEq<T>: AsType {
     fn as_ty<'a>(&'a self) -> &'a dyn AsType;
 }
 
+/// Values that can be used as components of a vector with scalar type S, see [`crate::Builder::vec4_from`]
+pub trait VectorComponents<S>: AsType {
+    /// the number of scalar components self contributes
+    fn n_components(&self) -> u32;
+}
+
 pub trait AsScalarTypeConst {
     const SCALAR_TY: crate::ScalarType;
 }
@@ -116,7 +122,7 @@ impl IsTypeConst for Void {
 
 impl<'a> FromId<'a> for Void {
     fn from_id(_: usize, _: &'a Rc<RefCell<crate::BuilderInner>>) -> Self {
-        panic!("Cannot construct instance of void");
+        Void { }
     }
 }
 
@@ -405,6 +411,46 @@ impl_vector_ty!(
     DVec4, GlamDVec4, Float(64), 4,
 );
 
+macro_rules! impl_vector_components {
+    ($($scalar:ident, $rust_scalar:ident, $($name:ident, $rust:ident, $n:literal,)*;)*) => {
+        $(
+            impl<'a, 'b> VectorComponents<$scalar<'b>> for $scalar<'a> {
+                fn n_components(&self) -> u32 {
+                    1
+                }
+            }
+
+            impl<'a> VectorComponents<$scalar<'a>> for $rust_scalar {
+                fn n_components(&self) -> u32 {
+                    1
+                }
+            }
+
+            $(
+                impl<'a, 'b> VectorComponents<$scalar<'b>> for $name<'a> {
+                    fn n_components(&self) -> u32 {
+                        $n
+                    }
+                }
+
+                impl<'a> VectorComponents<$scalar<'a>> for $rust {
+                    fn n_components(&self) -> u32 {
+                        $n
+                    }
+                }
+            )*
+        )*
+    };
+}
+
+#[rustfmt::skip]
+impl_vector_components!(
+    Int, i32, IVec2, GlamIVec2, 2, IVec3, GlamIVec3, 3, IVec4, GlamIVec4, 4,;
+    UInt, u32, UVec2, GlamUVec2, 2, UVec3, GlamUVec3, 3, UVec4, GlamUVec4, 4,;
+    Float, f32, Vec2, GlamVec2, 2, Vec3, GlamVec3, 3, Vec4, GlamVec4, 4,;
+    Double, f64, DVec2, GlamDVec2, 2, DVec3, GlamDVec3, 3, DVec4, GlamDVec4, 4,;
+);
+
 // impl matrix types
 // ================================================================================
 // ================================================================================
@@ -570,7 +616,7 @@ macro_rules! impl_convert {
 
                         scope.push_instruction(crate::Instruction::Convert(crate::OpConvert {
                             src: (v.id, v.ty()),
-                            dst: (new_id, <Int as AsTypeConst>::TY),
+                            dst: (new_id, <$dst as AsTypeConst>::TY),
                         }));
 
                         // drop(scope);
@@ -912,6 +958,13 @@ macro_rules! impl_assign_op {
                 $f(&mut b, &*self, &rhs, crate::Type::$store)
             }
         }
+
+        impl<'a> std::ops::$op<$rust> for $name<'a> {
+            fn $f(&mut self, rhs: $rust) {
+                let mut b = self.b.borrow_mut();
+                $f(&mut b, &*self, &rhs, crate::Type::$store)
+            }
+        }
     };
 }
 
@@ -1044,6 +1097,8 @@ macro_rules! impl_scalar_vec_op {
 macro_rules! impl_scalar_vec_ops {
     ($($scalar:ident, $rust_scalar:ident, $vec:ident, $rust_vec:ident, $store:ident,)*) => {
         $(
+            impl_scalar_vec_op!($scalar, $rust_scalar, $vec, $rust_vec, Add, add, $store);
+            impl_scalar_vec_op!($scalar, $rust_scalar, $vec, $rust_vec, Sub, sub, $store);
             impl_scalar_vec_op!($scalar, $rust_scalar, $vec, $rust_vec, Mul, mul, $store);
             impl_scalar_vec_op!($scalar, $rust_scalar, $vec, $rust_vec, Div, div, $store);
         )*
@@ -1087,6 +1142,8 @@ macro_rules! impl_scalar_vec_assign_op {
 macro_rules! impl_scalar_vec_assign_ops {
     ($($scalar:ident, $rust_scalar:ident, $vec:ident, $store:ident,)*) => {
         $(
+            impl_scalar_vec_assign_op!($scalar, $rust_scalar, $vec, AddAssign, add_assign, $store);
+            impl_scalar_vec_assign_op!($scalar, $rust_scalar, $vec, SubAssign, sub_assign, $store);
             impl_scalar_vec_assign_op!($scalar, $rust_scalar, $vec, MulAssign, mul_assign, $store);
             impl_scalar_vec_assign_op!($scalar, $rust_scalar, $vec, DivAssign, div_assign, $store);
         )*
@@ -1254,6 +1311,89 @@ impl_scalar_vec_mat_ops!(
     Double, f64, DVec4, GlamDVec4, DMat4, GlamDMat4, DMAT4, DVEC4,
 );
 
+// literal promotion
+// ================================================================================
+// ================================================================================
+// ================================================================================
+
+// integer literals are converted to the float type on the cpu, matching glsl where `color * 2` is valid
+
+macro_rules! impl_promote_op {
+    ($name:ident, $rust:ident, $op:ident, $f:ident) => {
+        impl<'a> std::ops::$op<i32> for $name<'a> {
+            type Output = $name<'a>;
+
+            fn $f(self, rhs: i32) -> Self::Output {
+                std::ops::$op::$f(self, rhs as $rust)
+            }
+        }
+
+        impl<'a> std::ops::$op<$name<'a>> for i32 {
+            type Output = $name<'a>;
+
+            fn $f(self, rhs: $name<'a>) -> Self::Output {
+                std::ops::$op::$f(self as $rust, rhs)
+            }
+        }
+    };
+}
+
+macro_rules! impl_promote_assign_op {
+    ($name:ident, $rust:ident, $op:ident, $f:ident) => {
+        impl<'a> std::ops::$op<i32> for $name<'a> {
+            fn $f(&mut self, rhs: i32) {
+                std::ops::$op::$f(self, rhs as $rust)
+            }
+        }
+    };
+}
+
+macro_rules! impl_promote_ops {
+    ($($name:ident, $rust:ident,)*) => {
+        $(
+            impl_promote_op!($name, $rust, Add, add);
+            impl_promote_op!($name, $rust, Sub, sub);
+            impl_promote_op!($name, $rust, Mul, mul);
+            impl_promote_op!($name, $rust, Div, div);
+            impl_promote_assign_op!($name, $rust, AddAssign, add_assign);
+            impl_promote_assign_op!($name, $rust, SubAssign, sub_assign);
+            impl_promote_assign_op!($name, $rust, MulAssign, mul_assign);
+            impl_promote_assign_op!($name, $rust, DivAssign, div_assign);
+        )*
+    };
+}
+
+macro_rules! impl_promote_mat_ops {
+    ($($name:ident, $rust:ident,)*) => {
+        $(
+            impl_promote_op!($name, $rust, Mul, mul);
+            impl_promote_op!($name, $rust, Div, div);
+        )*
+    };
+}
+
+#[rustfmt::skip]
+impl_promote_ops!(
+    Float, f32,
+    Double, f64,
+    Vec2, f32,
+    Vec3, f32,
+    Vec4, f32,
+    DVec2, f64,
+    DVec3, f64,
+    DVec4, f64,
+);
+
+#[rustfmt::skip]
+impl_promote_mat_ops!(
+    Mat2, f32,
+    Mat3, f32,
+    Mat4, f32,
+    DMat2, f64,
+    DMat3, f64,
+    DMat4, f64,
+);
+
 // comparisons
 // ================================================================================
 // ================================================================================
@@ -2300,6 +2440,48 @@ impl_mat_col!(
     DMat4, DVec4,
 );
 
+macro_rules! impl_mat_row {
+    ($($mat:ident, $vec:ident, $n:literal,)*) => {
+        $(
+            impl<'a> $mat<'a> {
+                pub fn row(&self, idx: u32) -> $vec<'a> {
+                    let elements = (0..$n)
+                        .map(|i| self.col(i).unit(idx))
+                        .collect::<Vec<_>>();
+
+                    let mut inner = self.b.borrow_mut();
+                    if let Some(scope) = &mut inner.scope {
+                        let new_id = scope.get_new_id();
+
+                        scope.push_instruction(crate::Instruction::Composite(crate::OpComposite {
+                            ty: <$vec as crate::AsTypeConst>::TY,
+                            id: new_id,
+                            constituents: elements.iter().map(|e| (e.id, e.ty())).collect(),
+                        }));
+
+                        $vec {
+                            id: new_id,
+                            b: self.b
+                        }
+                    } else {
+                        panic!("Cannot extract row from matrix when not in function")
+                    }
+                }
+            }
+        )*
+    };
+}
+
+#[rustfmt::skip]
+impl_mat_row!(
+    Mat2, Vec2, 2,
+    Mat3, Vec3, 3,
+    Mat4, Vec4, 4,
+    DMat2, DVec2, 2,
+    DMat3, DVec3, 3,
+    DMat4, DVec4, 4,
+);
+
 // spv struct
 // ================================================================================
 // ================================================================================
@@ -2632,7 +2814,6 @@ impl_g_texture!(
     ITexture, INT, SampledITexture,
     UTexture, UINT, SampledUTexture,
     Texture, FLOAT, SampledTexture,
-    DTexture, DOUBLE, SampledDTexture,
 );
 
 pub type ITexture1D         = ITexture<D1>;
@@ -2662,14 +2843,6 @@ pub type Texture2DMsArray   = Texture<D2MsArray>;
 pub type TextureCube        = Texture<Cube>;
 pub type TextureCubeArray   = Texture<CubeArray>;
 
-pub type DTexture1D         = DTexture<D1>;
-pub type DTexture1DArray    = DTexture<D1Array>;
-pub type DTexture2D         = DTexture<D2>;
-pub type DTexture2DMs       = DTexture<D2Ms>;
-pub type DTexture2DArray    = DTexture<D2Array>;
-pub type DTexture2DMsArray  = DTexture<D2MsArray>;
-pub type DTextureCube       = DTexture<Cube>;
-pub type DTextureCubeArray  = DTexture<CubeArray>;
 
 // spv sampled texture
 // ================================================================================
@@ -2738,7 +2911,6 @@ impl_g_sampler!(
     SampledITexture, ITexture, IVec4,
     SampledUTexture, UTexture, UVec4,
     SampledTexture, Texture, Vec4,
-    SampledDTexture, DTexture, DVec4,
 );
 
 pub type SampledITexture1D         = SampledITexture<D1>;
@@ -2767,12 +2939,3 @@ pub type SampledTexture2DArray     = SampledTexture<D2Array>;
 pub type SampledTexture2DMsArray   = SampledTexture<D2MsArray>;
 pub type SampledTextureCube        = SampledTexture<Cube>;
 pub type SampledTextureCubeArray   = SampledTexture<CubeArray>;
-
-pub type SampledDTexture1D         = SampledDTexture<D1>;
-pub type SampledDTexture1DArray    = SampledDTexture<D1Array>;
-pub type SampledDTexture2D         = SampledDTexture<D2>;
-pub type SampledDTexture2DMs       = SampledDTexture<D2Ms>;
-pub type SampledDTexture2DArray    = SampledDTexture<D2Array>;
-pub type SampledDTexture2DMsArray  = SampledDTexture<D2MsArray>;
-pub type SampledDTextureCube       = SampledDTexture<Cube>;
-pub type SampledDTextureCubeArray  = SampledDTexture<CubeArray>;
\ No newline at end of file
@@ -13,8 +13,10 @@ pub struct Func<T: crate::IsTypeConst> {
 }
 
 impl<T: crate::IsTypeConst> Func<T> {
+    /// Call the function with arguments matching those declared with [`crate::Builder::arg`]
     pub fn call<'a>(&'a self, args: impl IntoIterator<Item=&'a dyn crate::AsType>) -> T::T<'a> {
         let mut inner = self.inner.borrow_mut();
+        let arguments = inner.functions.get(&self.id).unwrap().arguments.clone();
         if let Some(scope) = &mut inner.scope {
             let new_id = scope.get_new_id();
 
@@ -22,6 +24,9 @@ impl<T: crate::IsTypeConst> Func<T> {
                 .map(|t| (t.id(&mut **scope), t.ty()))
                 .collect::<Vec<_>>();
 
+            let types = args.iter().map(|(_, ty)| ty.clone()).collect::<Vec<_>>();
+            assert!(types == arguments, "Cannot call function taking {:?} with {:?}", arguments, types);
+
             scope.push_instruction(crate::Instruction::FuncCall(crate::OpFuncCall {
                 func: self.id,
                 store_ty: T::TY,
@@ -0,0 +1,642 @@
+//! Lowering of the builder's instructions to glsl source
+//!
+//! This is for inspecting what was generated, not for compiling. Function variables are declared at the top of each
+//! function as `v{id}` like in the spir-v and combined samplers are written inline where they're used.
+
+use either::*;
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+
+use crate::backend::*;
+use crate::Instruction;
+use crate::OpLoadStoreData;
+use crate::ScalarType;
+use crate::ScalarVal;
+use crate::Type;
+use crate::Val;
+
+fn scalar_prefix(ty: ScalarType) -> &'static str {
+    match ty {
+        ScalarType::Bool => "b",
+        ScalarType::Signed(_) => "i",
+        ScalarType::Unsigned(_) => "u",
+        ScalarType::Float(64) => "d",
+        ScalarType::Float(_) => "",
+    }
+}
+
+fn texture_ty(ty: &crate::TextureType, combined: bool) -> String {
+    let dim = match ty.dimension {
+        crate::TextureDimension::D1 => "1D",
+        crate::TextureDimension::D1Array => "1DArray",
+        crate::TextureDimension::D2 => "2D",
+        crate::TextureDimension::D2Ms => "2DMS",
+        crate::TextureDimension::D2Array => "2DArray",
+        crate::TextureDimension::D2MsArray => "2DMSArray",
+        crate::TextureDimension::Cube => "Cube",
+        crate::TextureDimension::CubeArray => "CubeArray",
+        crate::TextureDimension::D3 => "3D",
+    };
+    let prefix = match ty.scalar_ty {
+        ScalarType::Signed(_) => "i",
+        ScalarType::Unsigned(_) => "u",
+        _ => "",
+    };
+    match ty.format {
+        crate::TextureSpvFormat::Color(_) => format!("{}image{}", prefix, dim),
+        _ if combined => format!("{}sampler{}", prefix, dim),
+        _ => format!("{}texture{}", prefix, dim),
+    }
+}
+
+fn built_in(built_in: rspirv::spirv::BuiltIn) -> String {
+    use rspirv::spirv::BuiltIn::*;
+
+    match built_in {
+        VertexId => "gl_VertexID".to_owned(),
+        DrawIndex => "gl_DrawID".to_owned(),
+        PatchVertices => "gl_PatchVerticesIn".to_owned(),
+        PrimitiveId => "gl_PrimitiveID".to_owned(),
+        InvocationId => "gl_InvocationID".to_owned(),
+        SampleId => "gl_SampleID".to_owned(),
+        NumWorkgroups => "gl_NumWorkGroups".to_owned(),
+        WorkgroupId => "gl_WorkGroupID".to_owned(),
+        LocalInvocationId => "gl_LocalInvocationID".to_owned(),
+        GlobalInvocationId => "gl_GlobalInvocationID".to_owned(),
+        SubgroupLocalInvocationId => "gl_SubgroupInvocationID".to_owned(),
+        b => format!("gl_{:?}", b),
+    }
+}
+
+fn scalar_literal(val: &ScalarVal) -> String {
+    match val {
+        ScalarVal::Bool(b) => b.to_string(),
+        ScalarVal::Int(i) => i.to_string(),
+        ScalarVal::UInt(u) => format!("{}u", u),
+        ScalarVal::Float(f) => format!("{:?}", f),
+        ScalarVal::Double(d) => format!("{:?}lf", d),
+    }
+}
+
+fn literal(val: &Val) -> String {
+    if let Val::Scalar(s) = val {
+        return scalar_literal(s);
+    }
+    let ty = match val.ty() {
+        Type::Vector(v) => format!("{}vec{}", scalar_prefix(v.scalar_ty), v.n_scalar),
+        Type::Matrix(m) => format!("{}mat{}", scalar_prefix(m.vec_ty.scalar_ty), m.n_vec),
+        _ => unreachable!(),
+    };
+    format!("{}({})", ty, val_scalars(val).iter().map(scalar_literal).collect::<Vec<_>>().join(", "))
+}
+struct Writer<'a> {
+    inner: &'a crate::BuilderInner,
+    entry: usize,
+    src: String,
+    indent: usize,
+    extensions: BTreeSet<&'static str>,
+    /// struct definitions in the order they have to be declared
+    structs: Vec<String>,
+    struct_names: HashMap<crate::StructType, String>,
+    /// combined samplers don't have a variable so they're written where they're used
+    sampled: HashMap<usize, String>,
+}
+
+impl<'a> Writer<'a> {
+    fn line(&mut self, line: impl AsRef<str>) {
+        for _ in 0..self.indent {
+            self.src.push_str("    ");
+        }
+        self.src.push_str(line.as_ref());
+        self.src.push('\n');
+    }
+
+    fn ty(&mut self, ty: &Type) -> String {
+        match ty {
+            Type::Void => "void".to_owned(),
+            Type::Scalar(s) => match s {
+                ScalarType::Bool => "bool",
+                ScalarType::Signed(_) => "int",
+                ScalarType::Unsigned(_) => "uint",
+                ScalarType::Float(64) => "double",
+                ScalarType::Float(_) => "float",
+            }.to_owned(),
+            Type::Vector(v) => format!("{}vec{}", scalar_prefix(v.scalar_ty), v.n_scalar),
+            Type::Matrix(m) => if m.n_vec == m.vec_ty.n_scalar {
+                format!("{}mat{}", scalar_prefix(m.vec_ty.scalar_ty), m.n_vec)
+            } else {
+                format!("{}mat{}x{}", scalar_prefix(m.vec_ty.scalar_ty), m.n_vec, m.vec_ty.n_scalar)
+            },
+            Type::Array(a) => {
+                let element = self.ty(&a.element_ty);
+                match a.length {
+                    Some(length) => format!("{}[{}]", element, length),
+                    None => format!("{}[]", element),
+                }
+            },
+            Type::Struct(s) => self.struct_name(s),
+            Type::Texture(t) => texture_ty(t, false),
+        }
+    }
+
+    fn struct_name(&mut self, s: &crate::StructType) -> String {
+        if let Some(name) = self.struct_names.get(s) {
+            return name.clone();
+        }
+        // declare the member types first
+        let members = s.members
+            .iter()
+            .enumerate()
+            .map(|(i, m)| format!("    {} {};\n", self.ty(&m.ty), struct_member_name(s, i as u32)))
+            .collect::<String>();
+        let name = match &s.name {
+            Some(name) => either_str(name).to_owned(),
+            None => format!("Struct{}", self.structs.len()),
+        };
+        self.structs.push(format!("struct {} {{\n{}}};\n", name, members));
+        self.struct_names.insert(s.clone(), name.clone());
+        name
+    }
+
+    fn var(&self, id: usize) -> String {
+        self.sampled.get(&id).cloned().unwrap_or_else(|| format!("v{}", id))
+    }
+
+    fn assign(&mut self, id: usize, expr: String) {
+        let var = self.var(id);
+        self.line(format!("{} = {};", var, expr));
+    }
+
+    fn io_name(io: &crate::IOData, prefix: &str, idx: usize) -> String {
+        match io.location {
+            Left(_) => io.name.map(str::to_owned).unwrap_or_else(|| format!("{}_{}", prefix, idx)),
+            Right(b) => built_in(b),
+        }
+    }
+
+    fn uniform_name(&self, id: usize) -> String {
+        self.inner.uniforms[id].name.map(str::to_owned).unwrap_or_else(|| format!("uniform_{}", id))
+    }
+
+    fn storage_name(&self, id: usize) -> String {
+        self.inner.storages[id].name.map(str::to_owned).unwrap_or_else(|| format!("storage_{}", id))
+    }
+
+    fn texture_name(&self, id: usize) -> String {
+        self.inner.textures[id].name.map(str::to_owned).unwrap_or_else(|| format!("texture_{}", id))
+    }
+
+    fn sampled_texture_name(&self, id: usize) -> String {
+        self.inner.sampled_textures[id].name.map(str::to_owned).unwrap_or_else(|| format!("sampled_texture_{}", id))
+    }
+
+    fn sampler_name(&self, id: usize) -> String {
+        self.inner.samplers[id].name.map(str::to_owned).unwrap_or_else(|| format!("sampler_{}", id))
+    }
+
+    fn push_constants_name(&self) -> String {
+        self.inner.push_constants
+            .as_ref()
+            .and_then(|p| p.name)
+            .unwrap_or("push_constants")
+            .to_owned()
+    }
+
+    fn func_name(&self, id: usize) -> String {
+        if id == self.entry {
+            "main".to_owned()
+        } else {
+            self.inner.functions[&id].name.map(str::to_owned).unwrap_or_else(|| format!("func_{}", id))
+        }
+    }
+
+    fn data(&self, data: &OpLoadStoreData) -> String {
+        match data {
+            OpLoadStoreData::Input { location } => Self::io_name(&self.inner.inputs[*location], "in", *location),
+            OpLoadStoreData::Output { location } => Self::io_name(&self.inner.outputs[*location], "out", *location),
+            OpLoadStoreData::UniformField { field, id }
+            | OpLoadStoreData::UniformMember { field, id } => {
+                format!("{}.{}", self.uniform_name(*id), member_name(&self.inner.uniforms[*id].ty, *field))
+            },
+            OpLoadStoreData::Uniform { id } => {
+                let uniform = &self.inner.uniforms[*id];
+                if uniform.block {
+                    format!("{}.{}", self.uniform_name(*id), member_name(&uniform.ty, 0))
+                } else {
+                    self.uniform_name(*id)
+                }
+            },
+            OpLoadStoreData::Storage { id } => self.storage_name(*id),
+            OpLoadStoreData::StorageElement { id, element } => format!("{}[{}]", self.storage_name(*id), self.var(element.0)),
+            OpLoadStoreData::StorageElementField { id, element, field } => format!(
+                "{}[{}].{}",
+                self.storage_name(*id),
+                self.var(element.0),
+                member_name(&self.inner.storages[*id].ty, *field),
+            ),
+            OpLoadStoreData::Variable { id } => self.var(*id),
+            OpLoadStoreData::Struct { id, struct_ty, field } => format!(
+                "{}.{}",
+                self.var(*id),
+                struct_member_name(struct_ty, *field),
+            ),
+            OpLoadStoreData::ArrayElement { id, index, .. } => format!("{}[{}]", self.var(*id), self.var(index.0)),
+            OpLoadStoreData::PushConstant => self.push_constants_name(),
+            OpLoadStoreData::PushConstantField { field } => format!(
+                "{}.{}",
+                self.push_constants_name(),
+                member_name(&self.inner.push_constants.as_ref().unwrap().ty, *field),
+            ),
+        }
+    }
+
+    fn declarations(&mut self) {
+        let (inputs, outputs) = self.inner.used_io(self.entry);
+
+        for (idx, io) in inputs.iter().map(|i| (*i, &self.inner.inputs[*i])) {
+            match io.location {
+                Left(location) => {
+                    let ty = self.ty(&io.ty.ty());
+                    let flat = if io.flat { "flat " } else { "" };
+                    self.line(format!("layout(location = {}) {}in {} {};", location, flat, ty, Self::io_name(io, "in", idx)));
+                },
+                Right(rspirv::spirv::BuiltIn::SubgroupSize) | Right(rspirv::spirv::BuiltIn::SubgroupLocalInvocationId) => {
+                    self.extensions.insert("GL_KHR_shader_subgroup_basic");
+                },
+                Right(_) => (),
+            }
+        }
+
+        for (idx, io) in outputs.iter().map(|o| (*o, &self.inner.outputs[*o])) {
+            if let Left(location) = io.location {
+                let ty = self.ty(&io.ty.ty());
+                let flat = if io.flat { "flat " } else { "" };
+                self.line(format!("layout(location = {}) {}out {} {};", location, flat, ty, Self::io_name(io, "out", idx)));
+            }
+        }
+
+        if let Some(p) = &self.inner.push_constants {
+            let ty = self.ty(&p.ty);
+            let name = self.push_constants_name();
+            self.line(format!("layout(push_constant) uniform {}_block {{ {} {}; }};", name, ty, name));
+        }
+
+        for (idx, u) in self.inner.uniforms.iter().enumerate() {
+            let name = self.uniform_name(idx);
+            match (&u.ty, u.block) {
+                (Type::Struct(s), true) => {
+                    self.line(format!("layout(set = {}, binding = {}) uniform {}_block {{", u.set, u.binding, name));
+                    for (i, m) in s.members.iter().enumerate() {
+                        let ty = self.ty(&m.ty);
+                        self.line(format!("    layout(offset = {}) {} {};", m.offset, ty, member_name(&u.ty, i as u32)));
+                    }
+                    self.line(format!("}} {};", name));
+                },
+                _ => {
+                    let ty = self.ty(&u.ty);
+                    self.line(format!("layout(set = {}, binding = {}) uniform {}_block {{ {} {}; }};", u.set, u.binding, name, ty, name));
+                },
+            }
+        }
+
+        for (idx, s) in self.inner.storages.iter().enumerate() {
+            let ty = self.ty(&s.ty);
+            let name = self.storage_name(idx);
+            let access = match (s.read, s.write) {
+                (true, false) => "readonly ",
+                (false, true) => "writeonly ",
+                _ => "",
+            };
+            self.line(format!(
+                "layout(set = {}, binding = {}, std430) {}buffer {}_block {{ {} {}[]; }};",
+                s.set, s.binding, access, name, ty, name,
+            ));
+        }
+
+        for (idx, t) in self.inner.textures.iter().enumerate() {
+            let format = match t.ty.format {
+                crate::TextureSpvFormat::Color(_) => format!(", {}", format!("{:?}", t.ty.format.rspirv()).to_lowercase().replace("snorm", "_snorm")),
+                _ => String::new(),
+            };
+            self.line(format!(
+                "layout(set = {}, binding = {}{}) uniform {} {};",
+                t.set, t.binding, format, texture_ty(&t.ty, false), self.texture_name(idx),
+            ));
+        }
+
+        for (idx, t) in self.inner.sampled_textures.iter().enumerate() {
+            let count = t.count.map(|c| format!("[{}]", c)).unwrap_or_default();
+            self.line(format!(
+                "layout(set = {}, binding = {}) uniform {} {}{};",
+                t.set, t.binding, texture_ty(&t.ty, true), self.sampled_texture_name(idx), count,
+            ));
+        }
+
+        for (idx, s) in self.inner.samplers.iter().enumerate() {
+            self.line(format!("layout(set = {}, binding = {}) uniform sampler {};", s.set, s.binding, self.sampler_name(idx)));
+        }
+    }
+
+    fn function(&mut self, id: usize, func: &crate::FuncData) {
+        self.sampled.clear();
+
+        let mut vars = BTreeMap::new();
+        collect_vars(&func.instructions, &mut vars);
+
+        let ret = self.ty(&func.ret);
+        let arguments = func.arguments
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| format!("{} a{}", self.ty(ty), i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.line(format!("{} {}({}) {{", ret, self.func_name(id), arguments));
+        self.indent += 1;
+        for (id, ty) in &vars {
+            let ty = self.ty(ty);
+            self.line(format!("{} v{};", ty, id));
+        }
+        if !vars.is_empty() {
+            self.src.push('\n');
+        }
+        self.instructions(&func.instructions);
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn instructions(&mut self, instructions: &[Instruction]) {
+        for instruction in instructions {
+            self.instruction(instruction);
+        }
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) {
+        match instruction {
+            Instruction::LhsRhs(o) => {
+                let (l, r) = (self.var(o.lhs.0), self.var(o.rhs.0));
+                let op = match o.ty {
+                    crate::OpLhsRhsType::Add => "+",
+                    crate::OpLhsRhsType::Sub => "-",
+                    crate::OpLhsRhsType::Mul => "*",
+                    crate::OpLhsRhsType::Div => "/",
+                    crate::OpLhsRhsType::BitAnd => "&",
+                    crate::OpLhsRhsType::BitOr => "|",
+                    crate::OpLhsRhsType::BitXor => "^",
+                    crate::OpLhsRhsType::LogicalAnd => "&&",
+                    crate::OpLhsRhsType::LogicalOr => "||",
+                    crate::OpLhsRhsType::LogicalEqual => "==",
+                    crate::OpLhsRhsType::LogicalNotEqual => "!=",
+                    crate::OpLhsRhsType::Cross => return self.assign(o.store.0, format!("cross({}, {})", l, r)),
+                    crate::OpLhsRhsType::Dot => return self.assign(o.store.0, format!("dot({}, {})", l, r)),
+                };
+                self.assign(o.store.0, format!("{} {} {}", l, op, r));
+            },
+            Instruction::Lhs(o) => {
+                let l = self.var(o.lhs.0);
+                let f = match o.ty {
+                    crate::OpLhsType::LogicalNot => if let Type::Vector(_) = o.lhs.1 {
+                        "not"
+                    } else {
+                        return self.assign(o.store.0, format!("!{}", l));
+                    },
+                    crate::OpLhsType::Normalize => "normalize",
+                    crate::OpLhsType::Length => "length",
+                    crate::OpLhsType::Exp => "exp",
+                    crate::OpLhsType::Exp2 => "exp2",
+                    crate::OpLhsType::Sin => "sin",
+                    crate::OpLhsType::Cos => "cos",
+                    crate::OpLhsType::Tan => "tan",
+                    crate::OpLhsType::ASin => "asin",
+                    crate::OpLhsType::ACos => "acos",
+                    crate::OpLhsType::ATan => "atan",
+                };
+                self.assign(o.store.0, format!("{}({})", f, l));
+            },
+            Instruction::VectorShuffle(o) => {
+                let swizzle = o.components
+                    .iter()
+                    .take(o.dst.1.n_scalar as usize)
+                    .map(|c| ['x', 'y', 'z', 'w'][*c as usize])
+                    .collect::<String>();
+                self.assign(o.dst.0, format!("{}.{}", self.var(o.src.0), swizzle));
+            },
+            Instruction::LoadStore(o) => {
+                let (dst, src) = (self.data(&o.dst), self.data(&o.src));
+                self.line(format!("{} = {};", dst, src));
+            },
+            Instruction::FuncCall(o) => {
+                let call = format!(
+                    "{}({})",
+                    self.func_name(o.func),
+                    o.args.iter().map(|(id, _)| self.var(*id)).collect::<Vec<_>>().join(", "),
+                );
+                if let Type::Void = o.store_ty {
+                    self.line(format!("{};", call));
+                } else {
+                    self.assign(o.store, call);
+                }
+            },
+            Instruction::Argument(o) => self.assign(o.store, format!("a{}", o.index)),
+            Instruction::ReturnValue(o) => {
+                let value = self.var(o.value.0);
+                self.line(format!("return {};", value));
+            },
+            Instruction::SetConst(o) => self.assign(o.store, literal(&o.val)),
+            Instruction::Cmp(o) => {
+                let (l, r) = (self.var(o.lhs.0), self.var(o.rhs.0));
+                let vector = matches!(o.lhs.1, Type::Vector(_));
+                let expr = match (&o.cmp, vector) {
+                    (crate::CmpType::Eq, _) => format!("{} == {}", l, r),
+                    (crate::CmpType::NEq, _) => format!("{} != {}", l, r),
+                    (crate::CmpType::Lt, false) => format!("{} < {}", l, r),
+                    (crate::CmpType::Gt, false) => format!("{} > {}", l, r),
+                    (crate::CmpType::Le, false) => format!("{} <= {}", l, r),
+                    (crate::CmpType::Ge, false) => format!("{} >= {}", l, r),
+                    (crate::CmpType::Lt, true) => format!("all(lessThan({}, {}))", l, r),
+                    (crate::CmpType::Gt, true) => format!("all(greaterThan({}, {}))", l, r),
+                    (crate::CmpType::Le, true) => format!("all(lessThanEqual({}, {}))", l, r),
+                    (crate::CmpType::Ge, true) => format!("all(greaterThanEqual({}, {}))", l, r),
+                };
+                self.assign(o.store, expr);
+            },
+            Instruction::Select(o) => {
+                let expr = format!("{} ? {} : {}", self.var(o.condition), self.var(o.a), self.var(o.b));
+                self.assign(o.store, expr);
+            },
+            Instruction::Composite(o) => {
+                let ty = self.ty(&o.ty);
+                let constituents = o.constituents.iter().map(|(id, _)| self.var(*id)).collect::<Vec<_>>().join(", ");
+                self.assign(o.id, format!("{}({})", ty, constituents));
+            },
+            Instruction::Extract(o) => {
+                let src = self.var(o.src_id);
+                let expr = match &o.src_ty {
+                    Type::Struct(_) => format!("{}.{}", src, member_name(&o.src_ty, o.element_idx)),
+                    _ => format!("{}[{}]", src, o.element_idx),
+                };
+                self.assign(o.store_id, expr);
+            },
+            Instruction::Sample(o) => {
+                let texture = match o.sampled_texture {
+                    Left(id) => self.sampled_texture_name(id),
+                    Right(id) => self.var(id),
+                };
+                let coordinate = self.var(o.coordinate.0);
+                let expr = if o.explict_lod {
+                    format!("textureLod({}, {}, 0.0)", texture, coordinate)
+                } else {
+                    format!("texture({}, {})", texture, coordinate)
+                };
+                self.assign(o.store.0, expr);
+            },
+            Instruction::Combine(o) => {
+                let expr = format!("{}({}, {})", texture_ty(&o.tex_ty, true), self.texture_name(o.texture), self.sampler_name(o.sampler));
+                self.sampled.insert(o.store, expr);
+            },
+            Instruction::SampledTextureElement(o) => {
+                let mut index = self.var(o.index.0);
+                if o.non_uniform {
+                    self.extensions.insert("GL_EXT_nonuniform_qualifier");
+                    index = format!("nonuniformEXT({})", index);
+                }
+                let expr = format!("{}[{}]", self.sampled_texture_name(o.sampled_texture), index);
+                self.sampled.insert(o.store, expr);
+            },
+            Instruction::Convert(o) => {
+                let ty = self.ty(&o.dst.1);
+                self.assign(o.dst.0, format!("{}({})", ty, self.var(o.src.0)));
+            },
+            Instruction::If(o) => self.if_chain(o, false),
+            Instruction::Loop(o) => {
+                match o.control {
+                    crate::LoopControl::None => (),
+                    crate::LoopControl::Unroll => {
+                        self.extensions.insert("GL_EXT_control_flow_attributes");
+                        self.line("[[unroll]]");
+                    },
+                    crate::LoopControl::DontUnroll => {
+                        self.extensions.insert("GL_EXT_control_flow_attributes");
+                        self.line("[[dont_unroll]]");
+                    },
+                }
+                self.line("while (true) {");
+                self.indent += 1;
+                self.instructions(&o.condition_instructions);
+                let condition = self.var(o.condition);
+                self.line(format!("if (!{}) break;", condition));
+                self.instructions(&o.instructions);
+                self.indent -= 1;
+                self.line("}");
+            },
+            Instruction::Subgroup(o) => {
+                let value = o.value.as_ref().map(|(id, _)| self.var(*id)).unwrap_or_default();
+                let index = match &o.index {
+                    Some(Left(lane)) => format!("{}u", lane),
+                    Some(Right((id, _))) => self.var(*id),
+                    None => String::new(),
+                };
+                let scan = |op: &rspirv::spirv::GroupOperation| match op {
+                    rspirv::spirv::GroupOperation::InclusiveScan => "Inclusive",
+                    rspirv::spirv::GroupOperation::ExclusiveScan => "Exclusive",
+                    _ => "",
+                };
+                let (extension, expr) = match &o.ty {
+                    crate::OpSubgroupType::Elect => ("GL_KHR_shader_subgroup_basic", "subgroupElect()".to_owned()),
+                    crate::OpSubgroupType::All => ("GL_KHR_shader_subgroup_vote", format!("subgroupAll({})", value)),
+                    crate::OpSubgroupType::Any => ("GL_KHR_shader_subgroup_vote", format!("subgroupAny({})", value)),
+                    crate::OpSubgroupType::Ballot => ("GL_KHR_shader_subgroup_ballot", format!("subgroupBallot({})", value)),
+                    crate::OpSubgroupType::BallotBitCount(op) => ("GL_KHR_shader_subgroup_ballot", format!("subgroupBallot{}BitCount({})", scan(op), value)),
+                    crate::OpSubgroupType::Broadcast => ("GL_KHR_shader_subgroup_ballot", format!("subgroupBroadcast({}, {})", value, index)),
+                    crate::OpSubgroupType::BroadcastFirst => ("GL_KHR_shader_subgroup_ballot", format!("subgroupBroadcastFirst({})", value)),
+                    crate::OpSubgroupType::Shuffle => ("GL_KHR_shader_subgroup_shuffle", format!("subgroupShuffle({}, {})", value, index)),
+                    crate::OpSubgroupType::Add(op) => ("GL_KHR_shader_subgroup_arithmetic", format!("subgroup{}Add({})", scan(op), value)),
+                    crate::OpSubgroupType::Min(op) => ("GL_KHR_shader_subgroup_arithmetic", format!("subgroup{}Min({})", scan(op), value)),
+                    crate::OpSubgroupType::Max(op) => ("GL_KHR_shader_subgroup_arithmetic", format!("subgroup{}Max({})", scan(op), value)),
+                };
+                self.extensions.insert(extension);
+                self.assign(o.store.0, expr);
+            },
+            Instruction::Return => self.line("return;"),
+            Instruction::Discard => self.line("discard;"),
+            Instruction::Continue => self.line("continue;"),
+            Instruction::Break => self.line("break;"),
+        }
+    }
+
+    fn if_chain(&mut self, op: &crate::OpIf, else_if: bool) {
+        let condition = self.var(op.condition);
+        if else_if {
+            self.line(format!("}} else if ({}) {{", condition));
+        } else {
+            self.line(format!("if ({}) {{", condition));
+        }
+        self.indent += 1;
+        self.instructions(&op.instructions);
+        self.indent -= 1;
+        match &*op.then.borrow() {
+            Some(Left(t)) => self.if_chain(t, true),
+            Some(Right(t)) => {
+                self.line("} else {");
+                self.indent += 1;
+                self.instructions(&t.instructions);
+                self.indent -= 1;
+                self.line("}");
+            },
+            None => self.line("}"),
+        }
+    }
+}
+
+impl crate::BuilderInner {
+    /// Write the entry point for stage and everything it could use as glsl
+    pub(crate) fn to_glsl(&self, stage: crate::Stage) -> String {
+        let entry = match self.entry_points.get(&stage) {
+            Some(entry) => *entry,
+            None => panic!("Cannot convert to glsl without an entry point for {:?} stage", stage),
+        };
+
+        let mut w = Writer {
+            inner: self,
+            entry,
+            src: String::new(),
+            indent: 0,
+            extensions: BTreeSet::new(),
+            structs: Vec::new(),
+            struct_names: HashMap::new(),
+            sampled: HashMap::new(),
+        };
+
+        w.declarations();
+        let declarations = std::mem::take(&mut w.src);
+
+        // functions can only call functions declared before them
+        let mut ids = self.functions.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        for id in ids {
+            let other_entry = id != entry && self.entry_points.values().any(|e| *e == id);
+            if !other_entry {
+                w.src.push('\n');
+                w.function(id, &self.functions[&id]);
+            }
+        }
+
+        let mut src = String::from("#version 450\n");
+        for extension in &w.extensions {
+            src.push_str(&format!("#extension {} : require\n", extension));
+        }
+        src.push('\n');
+        if stage == crate::Stage::Compute {
+            let [x, y, z] = self.local_size;
+            src.push_str(&format!("layout(local_size_x = {}, local_size_y = {}, local_size_z = {}) in;\n\n", x, y, z));
+        }
+        for s in &w.structs {
+            src.push_str(s);
+            src.push('\n');
+        }
+        src.push_str(&declarations);
+        src.push_str(&w.src);
+        src
+    }
+}
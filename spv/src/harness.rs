@@ -0,0 +1,119 @@
+//! Execute generated compute modules headlessly
+//!
+//! Used to check the results of instructions rather than only that the module assembles.
+//! The module under test should declare a single storage buffer at set 0 binding 0 which is
+//! filled with the input data before dispatch and read back afterwards.
+//!
+//! ```no_run
+//! let harness = spv::harness::Harness::new(gpu::DeviceFeatures::BASE).unwrap();
+//!
+//! let b = spv::Builder::new();
+//! let data = b.storage::<spv::Float>(0, 0, Some("data"));
+//! let id = b.global_invocation_id();
+//! b.entry(spv::Stage::Compute, "main", || {
+//!     let i = spv::Int::from(id.load().x());
+//!     let x = data.load_element(i);
+//!     data.store_element(i, x * 2.0);
+//! });
+//!
+//! let result = harness.run(&b, &[1.0f32, 2.0, 3.0], [3, 1, 1]).unwrap();
+//! assert_eq!(result, [2.0, 4.0, 6.0]);
+//! ```
+
+use crate::Builder;
+use crate::Stage;
+
+/// Device used to run compute modules produced by a [`Builder`]
+pub struct Harness {
+    pub device: gpu::Device,
+}
+
+impl Harness {
+    /// Create a headless device with the features required by the modules to be run
+    pub fn new(features: gpu::DeviceFeatures) -> Result<Self, gpu::Error> {
+        let instance = gpu::Instance::new(&gpu::InstanceDesc::default())?;
+
+        let device = instance.create_device(&gpu::DeviceDesc {
+            features,
+            ..Default::default()
+        })?;
+
+        Ok(Self { device })
+    }
+
+    /// Compile the compute entry point of the builder, dispatch it with `groups` work groups
+    /// over a storage buffer at set 0 binding 0 initialized with `data` and return the contents
+    /// of the buffer after execution
+    pub fn run<T: bytemuck::Pod>(&self, b: &Builder, data: &[T], groups: [u32; 3]) -> Result<Vec<T>, gpu::Error> {
+        let entry = b.get_entry_name(Stage::Compute)
+            .expect("Cannot run builder in harness without a compute entry point");
+        let spv = b.compile();
+
+        let buffer = self.device.create_buffer(&gpu::BufferDesc {
+            name: None,
+            size: (data.len() * std::mem::size_of::<T>()) as u64,
+            usage: gpu::BufferUsage::STORAGE,
+            memory: gpu::MemoryType::Host,
+        })?;
+        buffer.slice_ref(..).write(bytemuck::cast_slice(data))?;
+
+        let shader = self.device.create_shader_module(&gpu::ShaderModuleDesc {
+            name: None,
+            entries: &[(gpu::ShaderStages::COMPUTE, entry)],
+            spirv: &spv,
+        })?;
+
+        let descriptor_layout = self.device.create_descriptor_layout(&gpu::DescriptorLayoutDesc {
+            name: None,
+            entries: &[gpu::DescriptorLayoutEntry {
+                ty: gpu::DescriptorLayoutEntryType::StorageBuffer { read_only: false },
+                stage: gpu::ShaderStages::COMPUTE,
+                count: std::num::NonZeroU32::new(1).unwrap(),
+            }],
+            immutable_samplers: &[],
+        })?;
+
+        let descriptor_set = self.device.create_descriptor_set(&gpu::DescriptorSetDesc {
+            name: None,
+            layout: &descriptor_layout,
+            entries: &[gpu::DescriptorSetEntry::Buffer(buffer.slice_ref(..))],
+        })?;
+
+        let layout = self.device.create_pipeline_layout(&gpu::PipelineLayoutDesc {
+            name: None,
+            descriptor_sets: &[&descriptor_layout],
+            push_constants: &[],
+        })?;
+
+        let pipeline = self.device.create_compute_pipeline(&gpu::ComputePipelineDesc {
+            name: None,
+            layout: &layout,
+            shader: (&shader, None),
+            cache: None,
+        })?;
+
+        let mut command = self.device.create_command_buffer(None)?;
+        command.begin(true)?;
+        command.begin_compute_pass(&pipeline)?;
+        command.bind_descriptor(0, &descriptor_set, gpu::PipelineBindPoint::Compute, &layout)?;
+        command.dispatch(groups[0], groups[1], groups[2])?;
+        // make the shader writes visible to the host read below
+        command.pipeline_barrier(
+            gpu::PipelineStageFlags::COMPUTE,
+            gpu::PipelineStageFlags::HOST,
+            &[gpu::BufferAccessInfo {
+                buffer: buffer.slice_ref(..),
+                src_access: gpu::AccessFlags::SHADER_WRITE,
+                dst_access: gpu::AccessFlags::HOST_READ,
+            }],
+            &[],
+        )?;
+        command.end()?;
+        command.submit()?;
+        command.wait(!0)?;
+
+        let mut result = data.to_vec();
+        buffer.slice_ref(..).read(bytemuck::cast_slice_mut(&mut result))?;
+        Ok(result)
+    }
+}
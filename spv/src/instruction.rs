@@ -30,6 +30,7 @@ pub enum OpLhsRhsType {
     Dot,
 }
 
+#[derive(Clone)]
 pub struct OpLhsRhs {
     pub ty: OpLhsRhsType,
     pub lhs: (usize, crate::Type),
@@ -61,9 +62,9 @@ impl OpLhsRhs {
 
     fn get_fn_pointer(&self, spv_lhs_obj: &mut u32, spv_rhs_obj: &mut u32, b: &mut crate::RSpirvBuilder) -> Box<dyn FnOnce(&mut rspirv::dr::Builder, u32, Option<u32>, u32, u32) -> Result<u32, rspirv::dr::Error>> {
         let f = match self.ty {
-            OpLhsRhsType::Add => Box::new(self.get_add_fn_pointer()),
-            OpLhsRhsType::Sub => Box::new(self.get_sub_fn_pointer()),
-            OpLhsRhsType::Mul => Box::new(self.get_mul_fn_pointer(spv_lhs_obj, spv_rhs_obj)),
+            OpLhsRhsType::Add => Box::new(self.get_add_fn_pointer(b, spv_lhs_obj, spv_rhs_obj)),
+            OpLhsRhsType::Sub => Box::new(self.get_sub_fn_pointer(b, spv_lhs_obj, spv_rhs_obj)),
+            OpLhsRhsType::Mul => Box::new(self.get_mul_fn_pointer(b, spv_lhs_obj, spv_rhs_obj)),
             OpLhsRhsType::Div => Box::new(self.get_div_fn_pointer(b, spv_lhs_obj, spv_rhs_obj)),
             OpLhsRhsType::BitOr => todo!(),
             OpLhsRhsType::BitXor => todo!(),
@@ -78,7 +79,7 @@ impl OpLhsRhs {
         f
     }
 
-    fn get_add_fn_pointer(&self) -> fn(&mut rspirv::dr::Builder, u32, Option<u32>, u32, u32) -> Result<u32, rspirv::dr::Error> {
+    fn get_add_fn_pointer(&self, b: &mut crate::RSpirvBuilder, spv_lhs_obj: &mut u32, spv_rhs_obj: &mut u32) -> fn(&mut rspirv::dr::Builder, u32, Option<u32>, u32, u32) -> Result<u32, rspirv::dr::Error> {
         match self.lhs.1 {
             crate::Type::Scalar(s0) => match self.rhs.1 {
                 crate::Type::Scalar(_) => {
@@ -91,9 +92,31 @@ impl OpLhsRhs {
                         unreachable!()
                     }
                 },
+                crate::Type::Vector(v1) => {
+                    // add lhs (scalar) rhs (vector)
+                    *spv_lhs_obj = splat(b, v1, *spv_lhs_obj);
+                    if s0.is_int() || s0.is_uint() {
+                        Builder::i_add
+                    } else if s0.is_float() {
+                        Builder::f_add
+                    } else {
+                        unreachable!()
+                    }
+                },
                 _ => unreachable!()
             },
             crate::Type::Vector(v0) => match self.rhs.1 {
+                crate::Type::Scalar(_) => {
+                    // add lhs (vector) rhs (scalar)
+                    *spv_rhs_obj = splat(b, v0, *spv_rhs_obj);
+                    if v0.is_int() || v0.is_uint() {
+                        Builder::i_add
+                    } else if v0.is_float() {
+                        Builder::f_add
+                    } else {
+                        unreachable!()
+                    }
+                },
                 crate::Type::Vector(_) => {
                     // add lhs (vector) rhs (vector)
                     if v0.is_int() || v0.is_uint() {
@@ -123,7 +146,7 @@ impl OpLhsRhs {
         }
     }
 
-    fn get_sub_fn_pointer(&self) -> fn(&mut Builder, u32, Option<u32>, u32, u32) -> Result<u32, rspirv::dr::Error> {
+    fn get_sub_fn_pointer(&self, b: &mut crate::RSpirvBuilder, spv_lhs_obj: &mut u32, spv_rhs_obj: &mut u32) -> fn(&mut Builder, u32, Option<u32>, u32, u32) -> Result<u32, rspirv::dr::Error> {
         match self.lhs.1 {
             crate::Type::Scalar(s0) => match self.rhs.1 {
                 crate::Type::Scalar(_) => {
@@ -136,9 +159,31 @@ impl OpLhsRhs {
                         unreachable!()
                     }
                 },
+                crate::Type::Vector(v1) => {
+                    // sub lhs (scalar) rhs (vector)
+                    *spv_lhs_obj = splat(b, v1, *spv_lhs_obj);
+                    if s0.is_int() || s0.is_uint() {
+                        Builder::i_sub
+                    } else if s0.is_float() {
+                        Builder::f_sub
+                    } else {
+                        unreachable!()
+                    }
+                },
                 _ => unreachable!()
             },
             crate::Type::Vector(v0) => match self.rhs.1 {
+                crate::Type::Scalar(_) => {
+                    // sub lhs (vector) rhs (scalar)
+                    *spv_rhs_obj = splat(b, v0, *spv_rhs_obj);
+                    if v0.is_int() || v0.is_uint() {
+                        Builder::i_sub
+                    } else if v0.is_float() {
+                        Builder::f_sub
+                    } else {
+                        unreachable!()
+                    }
+                },
                 crate::Type::Vector(_) => {
                     // sub lhs (vector) rhs (vector)
                     if v0.is_int() || v0.is_uint() {
@@ -168,7 +213,7 @@ impl OpLhsRhs {
         }
     }
 
-    fn get_mul_fn_pointer(&self, spv_lhs_obj: &mut u32, spv_rhs_obj: &mut u32) -> fn(&mut Builder, u32, Option<u32>, u32, u32) -> Result<u32, rspirv::dr::Error> {
+    fn get_mul_fn_pointer(&self, b: &mut crate::RSpirvBuilder, spv_lhs_obj: &mut u32, spv_rhs_obj: &mut u32) -> fn(&mut Builder, u32, Option<u32>, u32, u32) -> Result<u32, rspirv::dr::Error> {
         match self.lhs.1 {
             crate::Type::Scalar(s0) => match self.rhs.1 {
                 crate::Type::Scalar(_) => {
@@ -181,10 +226,16 @@ impl OpLhsRhs {
                         unreachable!()
                     }
                 },
-                crate::Type::Vector(_) => {
+                crate::Type::Vector(v1) => {
                     // mul lhs (scalar) rhs (vector)
-                    std::mem::swap(spv_lhs_obj, spv_rhs_obj);
-                    Builder::vector_times_scalar
+                    if s0.is_int() || s0.is_uint() {
+                        // vector times scalar is only defined for floats
+                        *spv_lhs_obj = splat(b, v1, *spv_lhs_obj);
+                        Builder::i_mul
+                    } else {
+                        std::mem::swap(spv_lhs_obj, spv_rhs_obj);
+                        Builder::vector_times_scalar
+                    }
                 },
                 crate::Type::Matrix(_) => {
                     // mul lhs (scalar) rhs (matrix)
@@ -196,7 +247,13 @@ impl OpLhsRhs {
             crate::Type::Vector(v0) => match self.rhs.1 {
                 crate::Type::Scalar(_) => {
                     // mul lhs (vector) rhs (scalar)
-                    Builder::vector_times_scalar
+                    if v0.is_int() || v0.is_uint() {
+                        // vector times scalar is only defined for floats
+                        *spv_rhs_obj = splat(b, v0, *spv_rhs_obj);
+                        Builder::i_mul
+                    } else {
+                        Builder::vector_times_scalar
+                    }
                 },
                 crate::Type::Vector(_) => {
                     // mul lhs (vector) rhs (vector)
@@ -353,11 +410,18 @@ impl OpLhsRhs {
     }
 }
 
+/// Construct a vector of type v with every component set to the scalar obj
+fn splat(b: &mut crate::RSpirvBuilder, v: crate::VectorType, obj: u32) -> u32 {
+    let spv_vec_ty = v.rspirv(b);
+    b.composite_construct(spv_vec_ty, None, (0..v.n_scalar).map(|_| obj)).unwrap()
+}
+
 // op lhs
 // ================================================================================
 // ================================================================================
 // ================================================================================
 
+#[derive(Clone, Debug)]
 pub enum OpLhsType {
     LogicalNot,
     Normalize,
@@ -373,6 +437,7 @@ pub enum OpLhsType {
     
 }
 
+#[derive(Clone)]
 pub struct OpLhs {
     pub ty: OpLhsType,
     pub lhs: (usize, crate::Type),
@@ -437,6 +502,7 @@ impl OpLhs {
 // ================================================================================
 // ================================================================================
 
+#[derive(Clone)]
 pub struct OpVectorShuffle {
     pub src: (usize, crate::VectorType),
     pub dst: (usize, crate::VectorType),
@@ -471,6 +537,7 @@ impl OpVectorShuffle {
 // ================================================================================
 // ================================================================================
 
+#[derive(Clone)]
 pub enum OpLoadStoreData {
     Input { 
         location: usize,
@@ -485,6 +552,10 @@ pub enum OpLoadStoreData {
     Uniform {
         id: usize,
     },
+    UniformMember {
+        field: u32,
+        id: usize,
+    },
     Storage {
         id: usize,
     },
@@ -535,6 +606,12 @@ impl OpLoadStoreData {
                 let idx = crate::ScalarVal::UInt(0).set_rspirv(b);
                 b.access_chain(spv_p_ty, None, outer_spv_var, Some(idx)).unwrap()
             },
+            OpLoadStoreData::UniformMember { field, id } => {
+                let spv_var = shader_info.uniforms[*id];
+                let spv_p_ty = b.type_pointer(None, rspirv::spirv::StorageClass::Uniform, spv_obj_ty);
+                let idx = crate::ScalarVal::UInt(*field).set_rspirv(b);
+                b.access_chain(spv_p_ty, None, spv_var, Some(idx)).unwrap()
+            },
             OpLoadStoreData::Storage { id } => {
                 let outer_spv_var = shader_info.storages[*id];
                 let spv_array_ty = b.type_runtime_array(spv_obj_ty);
@@ -601,6 +678,7 @@ impl OpLoadStoreData {
     }
 }
 
+#[derive(Clone)]
 pub struct OpLoadStore {
     pub ty: crate::Type,
     pub src: OpLoadStoreData,
@@ -623,6 +701,7 @@ impl OpLoadStore {
 // ================================================================================
 // ================================================================================
 
+#[derive(Clone)]
 pub struct OpFuncCall {
     pub func: usize,
     pub store_ty: crate::Type,
@@ -631,8 +710,69 @@ pub struct OpFuncCall {
 }
 
 impl OpFuncCall {
-    fn compile(&self, _: &mut crate::RSpirvBuilder, _: &crate::ShaderMapInfo, _: &mut crate::FuncMapInfo) -> bool {
-        todo!()
+    fn compile(&self, b: &mut crate::RSpirvBuilder, shader_info: &crate::ShaderMapInfo, func_info: &mut crate::FuncMapInfo) -> bool {
+        let (spv_func, _) = *shader_info.functions.get(&self.func).unwrap();
+
+        let spv_args = self.args
+            .iter()
+            .map(|(id, ty)| {
+                let spv_ty = ty.rspirv(b);
+                let spv_var = func_info.var(b, *id, ty);
+                b.load(spv_ty, None, spv_var, None, None).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let spv_ret_ty = self.store_ty.rspirv(b);
+        let spv_obj = b.function_call(spv_ret_ty, None, spv_func, spv_args).unwrap();
+
+        if self.store_ty != crate::Type::Void {
+            let spv_var = func_info.var(b, self.store, &self.store_ty);
+            b.store(spv_var, spv_obj, None, None).unwrap();
+        }
+        false
+    }
+}
+
+// op argument
+// ================================================================================
+// ================================================================================
+// ================================================================================
+
+#[derive(Clone)]
+pub struct OpArgument {
+    pub index: usize,
+    pub ty: crate::Type,
+    pub store: usize,
+}
+
+impl OpArgument {
+    fn compile(&self, b: &mut crate::RSpirvBuilder, _: &crate::ShaderMapInfo, func_info: &mut crate::FuncMapInfo) -> bool {
+        let spv_obj = func_info.arguments[self.index];
+        let spv_var = func_info.var(b, self.store, &self.ty);
+
+        b.store(spv_var, spv_obj, None, None).unwrap();
+        false
+    }
+}
+
+// op return value
+// ================================================================================
+// ================================================================================
+// ================================================================================
+
+#[derive(Clone)]
+pub struct OpReturnValue {
+    pub value: (usize, crate::Type),
+}
+
+impl OpReturnValue {
+    fn compile(&self, b: &mut crate::RSpirvBuilder, _: &crate::ShaderMapInfo, func_info: &mut crate::FuncMapInfo) -> bool {
+        let spv_ty = self.value.1.rspirv(b);
+        let spv_var = func_info.var(b, self.value.0, &self.value.1);
+        let spv_obj = b.load(spv_ty, None, spv_var, None, None).unwrap();
+
+        b.ret_value(spv_obj).unwrap();
+        true
     }
 }
 
@@ -641,6 +781,7 @@ impl OpFuncCall {
 // ================================================================================
 // ================================================================================
 
+#[derive(Clone)]
 pub struct OpSetConst {
     pub val: crate::Val,
     pub store: usize,
@@ -661,6 +802,7 @@ impl OpSetConst {
 // ================================================================================
 // ================================================================================
 
+#[derive(Clone, Debug)]
 pub enum CmpType {
     Eq,
     NEq,
@@ -670,6 +812,7 @@ pub enum CmpType {
     Ge,
 }
 
+#[derive(Clone)]
 pub struct OpCmp {
     pub cmp: CmpType,
     pub lhs: (usize, crate::Type),
@@ -849,11 +992,58 @@ impl OpCmp {
     }
 }
 
+// op select
+// ================================================================================
+// ================================================================================
+// ================================================================================
+
+/// Note only scalar and vector types can be selected between
+#[derive(Clone)]
+pub struct OpSelect {
+    pub condition: usize,
+    pub ty: crate::Type,
+    pub a: usize,
+    pub b: usize,
+    pub store: usize,
+}
+
+impl OpSelect {
+    fn compile(&self, b: &mut crate::RSpirvBuilder, _: &crate::ShaderMapInfo, func_info: &mut crate::FuncMapInfo) -> bool {
+        let spv_condition_var = func_info.var(b, self.condition, &crate::Type::BOOL);
+        let spv_condition_ty = crate::Type::BOOL.rspirv(b);
+        let mut spv_condition_obj = b.load(spv_condition_ty, None, spv_condition_var, None, None).unwrap();
+
+        // before spir-v 1.4 selecting vectors requires a condition per component
+        if let crate::Type::Vector(v) = self.ty {
+            let spv_bvec_ty = crate::VectorType { scalar_ty: crate::ScalarType::Bool, n_scalar: v.n_scalar }.rspirv(b);
+            spv_condition_obj = b.composite_construct(
+                spv_bvec_ty,
+                None,
+                (0..v.n_scalar).map(|_| spv_condition_obj).collect::<Vec<_>>()
+            ).unwrap();
+        }
+
+        let spv_ty = self.ty.rspirv(b);
+
+        let spv_a_var = func_info.var(b, self.a, &self.ty);
+        let spv_a_obj = b.load(spv_ty, None, spv_a_var, None, None).unwrap();
+
+        let spv_b_var = func_info.var(b, self.b, &self.ty);
+        let spv_b_obj = b.load(spv_ty, None, spv_b_var, None, None).unwrap();
+
+        let spv_res_obj = b.select(spv_ty, None, spv_condition_obj, spv_a_obj, spv_b_obj).unwrap();
+        let spv_res_var = func_info.var(b, self.store, &self.ty);
+        b.store(spv_res_var, spv_res_obj, None, None).unwrap();
+        false
+    }
+}
+
 // op composite
 // ================================================================================
 // ================================================================================
 // ================================================================================
 
+#[derive(Clone)]
 pub struct OpComposite {
     pub ty: crate::Type,
     pub id: usize,
@@ -886,6 +1076,7 @@ impl OpComposite {
 // ================================================================================
 // ================================================================================
 
+#[derive(Clone)]
 pub struct OpExtract {
     pub src_id: usize,
     pub src_ty: crate::Type,
@@ -915,6 +1106,7 @@ impl OpExtract {
 // ================================================================================
 // ================================================================================
 
+#[derive(Clone)]
 pub struct OpCombine {
     pub tex_ty: crate::TextureType,
     pub texture: usize,
@@ -941,11 +1133,62 @@ impl OpCombine {
     }
 }
 
+// op sampled texture element
+// ================================================================================
+// ================================================================================
+// ================================================================================
+
+#[derive(Clone)]
+pub struct OpSampledTextureElement {
+    pub tex_ty: crate::TextureType,
+    pub sampled_texture: usize,
+    pub index: (usize, crate::Type),
+    /// the index can differ between invocations
+    pub non_uniform: bool,
+    pub store: usize,
+}
+
+impl OpSampledTextureElement {
+    fn compile(&self, b: &mut crate::RSpirvBuilder, shader_info: &crate::ShaderMapInfo, func_info: &mut crate::FuncMapInfo) -> bool {
+        let spv_array = shader_info.sampled_textures[self.sampled_texture];
+
+        let spv_tex_ty = self.tex_ty.rspirv(b);
+        let spv_sampled_tex_ty = b.type_sampled_image(spv_tex_ty);
+        let spv_p_ty = b.type_pointer(None, rspirv::spirv::StorageClass::UniformConstant, spv_sampled_tex_ty);
+
+        let spv_index_var = func_info.var(b, self.index.0, &self.index.1);
+        let spv_index_ty = self.index.1.rspirv(b);
+        let mut spv_index_obj = b.load(spv_index_ty, None, spv_index_var, None, None).unwrap();
+        if self.non_uniform {
+            // the load is removed when variables are promoted to ssa values so decorate a copy
+            // that the access chain uses instead
+            spv_index_obj = b.copy_object(spv_index_ty, None, spv_index_obj).unwrap();
+        }
+
+        let spv_element = b.access_chain(spv_p_ty, None, spv_array, [spv_index_obj]).unwrap();
+        let spv_sampled_texture = b.load(spv_sampled_tex_ty, None, spv_element, None, None).unwrap();
+
+        if self.non_uniform {
+            b.capabilities.insert(rspirv::spirv::Capability::ShaderNonUniform);
+            b.capabilities.insert(rspirv::spirv::Capability::SampledImageArrayNonUniformIndexing);
+            b.extensions.insert("SPV_EXT_descriptor_indexing");
+            for id in [spv_index_obj, spv_element, spv_sampled_texture] {
+                b.decorate(id, rspirv::spirv::Decoration::NonUniform, None);
+            }
+        }
+
+        // like combine the sampled texture is used directly by sample instructions
+        func_info.vars.insert(self.store, spv_sampled_texture);
+        false
+    }
+}
+
 // op convert
 // ================================================================================
 // ================================================================================
 // ================================================================================
 
+#[derive(Clone)]
 pub struct OpConvert {
     pub src: (usize, crate::Type),
     pub dst: (usize, crate::Type),
@@ -959,15 +1202,18 @@ impl OpConvert {
 
         let f = if let crate::Type::Scalar(s1) = &self.src.1 {
             if let crate::Type::Scalar(s2) = &self.dst.1 {
+                // the sat conversions require the kernel capability, changing sign is a bitcast in shaders
                 match *s1 {
-                    ScalarType::Signed(_) => match *s2 {
+                    ScalarType::Signed(w1) => match *s2 {
                         ScalarType::Signed(_) => Builder::s_convert,
-                        ScalarType::Unsigned(_) => Builder::sat_convert_s_to_u,
+                        ScalarType::Unsigned(w2) if w1 == w2 => Builder::bitcast,
+                        ScalarType::Unsigned(_) => Builder::s_convert,
                         ScalarType::Float(_) => Builder::convert_s_to_f,
                         _ => unimplemented!()
                     },
-                    ScalarType::Unsigned(_) => match *s2 {
-                        ScalarType::Signed(_) => Builder::sat_convert_u_to_s,
+                    ScalarType::Unsigned(w1) => match *s2 {
+                        ScalarType::Signed(w2) if w1 == w2 => Builder::bitcast,
+                        ScalarType::Signed(_) => Builder::u_convert,
                         ScalarType::Unsigned(_) => Builder::u_convert,
                         ScalarType::Float(_) => Builder::convert_u_to_f,
                         _ => unimplemented!()
@@ -1002,6 +1248,7 @@ impl OpConvert {
 // ================================================================================
 // ================================================================================
 
+#[derive(Clone)]
 pub struct OpSample {
     // Left(uniform) Right(combined)
     pub tex_ty: crate::TextureType,
@@ -1014,7 +1261,11 @@ pub struct OpSample {
 impl OpSample {
     fn compile(&self, b: &mut crate::RSpirvBuilder, shader_info: &crate::ShaderMapInfo, func_info: &mut crate::FuncMapInfo) -> bool {
         let spv_sampled_texture = match self.sampled_texture {
-            Left(id) => shader_info.sampled_textures[id],
+            Left(id) => {
+                let spv_tex_ty = self.tex_ty.rspirv(b);
+                let spv_sampled_tex_ty = b.type_sampled_image(spv_tex_ty);
+                b.load(spv_sampled_tex_ty, None, shader_info.sampled_textures[id], None, None).unwrap()
+            },
             Right(id) => func_info.var(b, id, &crate::Type::Texture(self.tex_ty)),
         };
 
@@ -1061,6 +1312,17 @@ pub struct OpIf {
     pub then: Rc<RefCell<Option<Either<Box<OpIf>, OpElse>>>>,
 }
 
+impl Clone for OpIf {
+    fn clone(&self) -> Self {
+        // the else branch is behind an Rc so that it can be set after the if, give the clone its own
+        Self {
+            condition: self.condition,
+            instructions: self.instructions.clone(),
+            then: Rc::new(RefCell::new(self.then.borrow().clone())),
+        }
+    }
+}
+
 impl OpIf {
     fn compile(&self, b: &mut crate::RSpirvBuilder, shader_info: &crate::ShaderMapInfo, func_info: &mut crate::FuncMapInfo) -> bool {
         let prev_block = func_info.block_info;
@@ -1120,6 +1382,7 @@ impl OpIf {
     }
 }
 
+#[derive(Clone)]
 pub struct OpElse {
     pub instructions: Vec<Instruction>,
 }
@@ -1137,25 +1400,259 @@ impl OpElse {
     }
 }
 
+// op loop
+// ================================================================================
+// ================================================================================
+// ================================================================================
+
+/// Hint to the driver about whether a loop should be unrolled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoopControl {
+    /// Let the driver decide
+    None,
+    /// Request the loop is unrolled, best for loops with small constant trip counts
+    Unroll,
+    /// Request the loop is kept as a loop
+    DontUnroll,
+}
+
+impl LoopControl {
+    fn rspirv(&self) -> rspirv::spirv::LoopControl {
+        match self {
+            LoopControl::None => rspirv::spirv::LoopControl::NONE,
+            LoopControl::Unroll => rspirv::spirv::LoopControl::UNROLL,
+            LoopControl::DontUnroll => rspirv::spirv::LoopControl::DONT_UNROLL,
+        }
+    }
+}
+
+/// Note the condition instructions are evaluated before every iteration
+#[derive(Clone)]
+pub struct OpLoop {
+    pub condition_instructions: Vec<Instruction>,
+    pub condition: usize,
+    pub instructions: Vec<Instruction>,
+    pub control: LoopControl,
+}
+
+impl OpLoop {
+    fn compile(&self, b: &mut crate::RSpirvBuilder, shader_info: &crate::ShaderMapInfo, func_info: &mut crate::FuncMapInfo) -> bool {
+        let prev_block = func_info.block_info;
+
+        let header_label = b.id();
+        let condition_label = b.id();
+        let body_label = b.id();
+        let continue_label = b.id();
+        let end_label = b.id();
+
+        b.branch(header_label).unwrap();
+
+        b.begin_block(Some(header_label)).unwrap();
+        let block = b.selected_block().unwrap();
+        b.loop_merge(end_label, continue_label, self.control.rspirv(), None).unwrap();
+        b.select_block(Some(block)).unwrap();
+        b.branch(condition_label).unwrap();
+
+        b.begin_block(Some(condition_label)).unwrap();
+        for instruction in &self.condition_instructions {
+            instruction.compile(b, shader_info, func_info);
+        }
+
+        let spv_condition_var = func_info.var(b, self.condition, &crate::Type::BOOL);
+        let spv_condition_ty = crate::Type::BOOL.rspirv(b);
+        let spv_condition_obj = b.load(spv_condition_ty, None, spv_condition_var, None, None).unwrap();
+        b.branch_conditional(spv_condition_obj, body_label, end_label, None).unwrap();
+
+        b.begin_block(Some(body_label)).unwrap();
+
+        func_info.block_info = crate::BlockInfo::Loop {
+            condition_label,
+            continue_label,
+            end_label,
+        };
+
+        let mut bl = false;
+        for instruction in &self.instructions {
+            bl |= instruction.compile(b, shader_info, func_info);
+            if bl {
+                break;
+            }
+        }
+
+        if !bl {
+            b.branch(continue_label).unwrap();
+        }
+
+        b.begin_block(Some(continue_label)).unwrap();
+        b.branch(header_label).unwrap();
+
+        b.begin_block(Some(end_label)).unwrap();
+
+        func_info.block_info = prev_block;
+
+        false
+    }
+}
+
+// op subgroup
+// ================================================================================
+// ================================================================================
+// ================================================================================
+
+#[derive(Clone)]
+pub enum OpSubgroupType {
+    Elect,
+    All,
+    Any,
+    Ballot,
+    BallotBitCount(rspirv::spirv::GroupOperation),
+    Broadcast,
+    BroadcastFirst,
+    Shuffle,
+    Add(rspirv::spirv::GroupOperation),
+    Min(rspirv::spirv::GroupOperation),
+    Max(rspirv::spirv::GroupOperation),
+}
+
+impl OpSubgroupType {
+    /// the class of subgroup operation that has to be supported by the device to use this op
+    pub fn operations(&self) -> crate::SubgroupOperations {
+        match self {
+            OpSubgroupType::Elect => crate::SubgroupOperations::BASIC,
+            OpSubgroupType::All | OpSubgroupType::Any => crate::SubgroupOperations::VOTE,
+            OpSubgroupType::Ballot
+            | OpSubgroupType::BallotBitCount(_)
+            | OpSubgroupType::Broadcast
+            | OpSubgroupType::BroadcastFirst => crate::SubgroupOperations::BALLOT,
+            OpSubgroupType::Shuffle => crate::SubgroupOperations::SHUFFLE,
+            OpSubgroupType::Add(_) | OpSubgroupType::Min(_) | OpSubgroupType::Max(_) => crate::SubgroupOperations::ARITHMETIC,
+        }
+    }
+
+    fn capability(&self) -> rspirv::spirv::Capability {
+        match self {
+            OpSubgroupType::Elect => rspirv::spirv::Capability::GroupNonUniform,
+            OpSubgroupType::All | OpSubgroupType::Any => rspirv::spirv::Capability::GroupNonUniformVote,
+            OpSubgroupType::Ballot
+            | OpSubgroupType::BallotBitCount(_)
+            | OpSubgroupType::Broadcast
+            | OpSubgroupType::BroadcastFirst => rspirv::spirv::Capability::GroupNonUniformBallot,
+            OpSubgroupType::Shuffle => rspirv::spirv::Capability::GroupNonUniformShuffle,
+            OpSubgroupType::Add(_) | OpSubgroupType::Min(_) | OpSubgroupType::Max(_) => rspirv::spirv::Capability::GroupNonUniformArithmetic,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct OpSubgroup {
+    pub ty: OpSubgroupType,
+    pub value: Option<(usize, crate::Type)>,
+    /// Left(constant lane) for broadcast, Right(dynamic lane) for shuffle
+    pub index: Option<Either<u32, (usize, crate::Type)>>,
+    pub store: (usize, crate::Type),
+}
+
+impl OpSubgroup {
+    fn compile(&self, b: &mut crate::RSpirvBuilder, _: &crate::ShaderMapInfo, func_info: &mut crate::FuncMapInfo) -> bool {
+        b.capabilities.insert(rspirv::spirv::Capability::GroupNonUniform);
+        b.capabilities.insert(self.ty.capability());
+
+        let spv_value_obj = self.value.as_ref().map(|(id, ty)| {
+            let spv_value_ty = ty.rspirv(b);
+            let spv_value_var = func_info.var(b, *id, ty);
+            b.load(spv_value_ty, None, spv_value_var, None, None).unwrap()
+        });
+
+        let spv_index_obj = self.index.as_ref().map(|index| match index {
+            // broadcast lanes have to be constant before spir-v 1.5
+            Left(lane) => crate::ScalarVal::UInt(*lane).set_rspirv(b),
+            Right((id, ty)) => {
+                let spv_index_ty = ty.rspirv(b);
+                let spv_index_var = func_info.var(b, *id, ty);
+                b.load(spv_index_ty, None, spv_index_var, None, None).unwrap()
+            },
+        });
+
+        let spv_scope = crate::ScalarVal::UInt(rspirv::spirv::Scope::Subgroup as u32).set_rspirv(b);
+        let spv_res_ty = self.store.1.rspirv(b);
+
+        let (is_float, is_int) = match &self.store.1 {
+            crate::Type::Scalar(s) => (s.is_float(), s.is_int()),
+            crate::Type::Vector(v) => (v.is_float(), v.is_int()),
+            _ => unreachable!(),
+        };
+
+        let value = || spv_value_obj.unwrap();
+        let index = || spv_index_obj.unwrap();
+
+        let spv_res_obj = match self.ty {
+            OpSubgroupType::Elect => b.group_non_uniform_elect(spv_res_ty, None, spv_scope),
+            OpSubgroupType::All => b.group_non_uniform_all(spv_res_ty, None, spv_scope, value()),
+            OpSubgroupType::Any => b.group_non_uniform_any(spv_res_ty, None, spv_scope, value()),
+            OpSubgroupType::Ballot => b.group_non_uniform_ballot(spv_res_ty, None, spv_scope, value()),
+            OpSubgroupType::BallotBitCount(op) => b.group_non_uniform_ballot_bit_count(spv_res_ty, None, spv_scope, op, value()),
+            OpSubgroupType::Broadcast => b.group_non_uniform_broadcast(spv_res_ty, None, spv_scope, value(), index()),
+            OpSubgroupType::BroadcastFirst => b.group_non_uniform_broadcast_first(spv_res_ty, None, spv_scope, value()),
+            OpSubgroupType::Shuffle => b.group_non_uniform_shuffle(spv_res_ty, None, spv_scope, value(), index()),
+            OpSubgroupType::Add(op) => {
+                if is_float {
+                    b.group_non_uniform_f_add(spv_res_ty, None, spv_scope, op, value(), None)
+                } else {
+                    b.group_non_uniform_i_add(spv_res_ty, None, spv_scope, op, value(), None)
+                }
+            },
+            OpSubgroupType::Min(op) => {
+                if is_float {
+                    b.group_non_uniform_f_min(spv_res_ty, None, spv_scope, op, value(), None)
+                } else if is_int {
+                    b.group_non_uniform_s_min(spv_res_ty, None, spv_scope, op, value(), None)
+                } else {
+                    b.group_non_uniform_u_min(spv_res_ty, None, spv_scope, op, value(), None)
+                }
+            },
+            OpSubgroupType::Max(op) => {
+                if is_float {
+                    b.group_non_uniform_f_max(spv_res_ty, None, spv_scope, op, value(), None)
+                } else if is_int {
+                    b.group_non_uniform_s_max(spv_res_ty, None, spv_scope, op, value(), None)
+                } else {
+                    b.group_non_uniform_u_max(spv_res_ty, None, spv_scope, op, value(), None)
+                }
+            },
+        }.unwrap();
+
+        let spv_res_var = func_info.var(b, self.store.0, &self.store.1);
+        b.store(spv_res_var, spv_res_obj, None, None).unwrap();
+        false
+    }
+}
+
 // instruction
 // ================================================================================
 // ================================================================================
 // ================================================================================
 
+#[derive(Clone)]
 pub enum Instruction {
     LhsRhs(OpLhsRhs),
     Lhs(OpLhs),
     VectorShuffle(OpVectorShuffle),
     LoadStore(OpLoadStore),
     FuncCall(OpFuncCall),
+    Argument(OpArgument),
+    ReturnValue(OpReturnValue),
     SetConst(OpSetConst),
     Cmp(OpCmp),
+    Select(OpSelect),
     Composite(OpComposite),
     Extract(OpExtract),
     Sample(OpSample),
     Combine(OpCombine),
+    SampledTextureElement(OpSampledTextureElement),
     Convert(OpConvert),
     If(OpIf),
+    Loop(OpLoop),
+    Subgroup(OpSubgroup),
     Return,
     Discard,
     Continue,
@@ -1170,15 +1667,24 @@ impl Instruction {
             Instruction::VectorShuffle(o) => o.compile(b, shader_info, func_info),
             Instruction::LoadStore(o) => o.compile(b, shader_info, func_info),
             Instruction::FuncCall(o) => o.compile(b, shader_info, func_info),
+            Instruction::Argument(o) => o.compile(b, shader_info, func_info),
+            Instruction::ReturnValue(o) => o.compile(b, shader_info, func_info),
             Instruction::SetConst(o) => o.compile(b, shader_info, func_info),
             Instruction::Cmp(o) => o.compile(b, shader_info, func_info),
+            Instruction::Select(o) => o.compile(b, shader_info, func_info),
             Instruction::Composite(o) => o.compile(b, shader_info, func_info),
             Instruction::Extract(o) => o.compile(b, shader_info, func_info),
             Instruction::Sample(o) => o.compile(b, shader_info, func_info),
             Instruction::Combine(o) => o.compile(b, shader_info, func_info),
+            Instruction::SampledTextureElement(o) => o.compile(b, shader_info, func_info),
             Instruction::Convert(o) => o.compile(b, shader_info, func_info),
             Instruction::If(o) => o.compile(b, shader_info, func_info),
-            Instruction::Return => todo!(),
+            Instruction::Loop(o) => o.compile(b, shader_info, func_info),
+            Instruction::Subgroup(o) => o.compile(b, shader_info, func_info),
+            Instruction::Return => {
+                b.ret().unwrap();
+                true
+            },
             Instruction::Discard => {
                 b.kill().unwrap();
                 true
@@ -25,6 +25,14 @@ pub enum IOType {
 }
 
 impl IOType {
+    /// The number of locations taken up by an io of this type
+    pub fn locations(&self) -> u32 {
+        match self {
+            IOType::DVec3 | IOType::DVec4 => 2,
+            _ => 1,
+        }
+    }
+
     pub fn ty(&self) -> crate::Type {
         match self {
             IOType::Int => crate::Type::Scalar(crate::ScalarType::Signed(32)),
@@ -188,6 +196,23 @@ pub struct Output<T: AsIOTypeConst> {
 }
 
 impl<T: AsIOTypeConst> Output<T> {
+    fn raw_load(&self) -> usize {
+        let mut inner = self.inner.borrow_mut();
+        if let Some(scope) = &mut inner.scope {
+            let store = scope.get_new_id();
+
+            scope.push_instruction(crate::Instruction::LoadStore(crate::OpLoadStore {
+                ty: T::IO_TY.ty(),
+                src: crate::OpLoadStoreData::Output { location: self.id },
+                dst: crate::OpLoadStoreData::Variable { id: store },
+            }));
+
+            store
+        } else {
+            panic!("Error cannot load output when not in function");
+        }
+    }
+
     fn raw_store(&self, id: usize) {
         let mut inner = self.inner.borrow_mut();
         if let Some(scope) = &mut inner.scope {
@@ -216,6 +241,15 @@ macro_rules! impl_io {
             }
 
             impl Output<$io> {
+                /// Load the value last stored to the output
+                pub fn load<'a>(&'a self) -> crate::$ty<'a> {
+                    let id = self.raw_load();
+                    crate::$ty {
+                        id,
+                        b: &self.inner,
+                    }
+                }
+
                 pub fn store(&self, data: crate::$ty<'_>) {
                     self.raw_store(data.id);//, <$io as AsIOTypeConst>::IO_TY);
                 }
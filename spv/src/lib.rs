@@ -118,6 +118,7 @@
 //! - comparisons are peformed with the methods eq, neq, lt, gt, le, ge not the rust traits in [`std::cmp`]
 //! - boolean operations of && and || are implemented on the bit operations & and | instead due to requirements of the rust std library traits
 //! - Conditions to be evaulated in shader use the function [`spv_if`] using normal if statements will obviously be evaluated on the cpu
+//! - Loops evaluated in shader use [`spv_while`], rust loops (or [`spv_for_unrolled`]) are unrolled into straight line code
 //! - Storage buffers are all runtime arrays but this is subject to change
 //! 
 //! This library is not at all usable in it's current state, while it does basically work, everything is subject to change and it is far too untested for me 
@@ -136,6 +137,17 @@ pub mod builder;
 pub mod func;
 pub mod scope;
 pub mod bindings;
+pub mod subgroup;
+pub mod link;
+pub mod backend;
+#[cfg(feature = "harness")]
+pub mod harness;
+mod cse;
+mod ssa;
+mod glsl;
+mod wgsl;
+#[cfg(feature = "validate")]
+mod validate;
 
 pub use data::*;
 pub use instruction::*;
@@ -144,6 +156,9 @@ pub use builder::*;
 pub use func::*;
 pub use scope::*;
 pub use bindings::*;
+pub use subgroup::*;
+pub use link::*;
+pub use backend::*;
 
 pub use glam::IVec2 as GlamIVec2;
 pub use glam::IVec3 as GlamIVec3;
@@ -177,15 +192,53 @@ pub enum Stage {
 }
 
 impl Stage {
-    pub(crate) fn specialize(&self, b: &mut RSpirvBuilder, spv_fn: u32) {
+    pub(crate) fn specialize(&self, b: &mut RSpirvBuilder, spv_fn: u32, local_size: [u32; 3]) {
         match self {
             Stage::Fragment => {
                 b.execution_mode(spv_fn, rspirv::spirv::ExecutionMode::OriginUpperLeft, &[]);
             },
+            Stage::Compute => {
+                b.execution_mode(spv_fn, rspirv::spirv::ExecutionMode::LocalSize, &local_size);
+            },
             _ => (),
         }
     }
 
+    /// Returns true if the built in can be used as an input (or output) by entry points of self
+    pub fn supports_built_in(&self, built_in: rspirv::spirv::BuiltIn, output: bool) -> bool {
+        use rspirv::spirv::BuiltIn::*;
+
+        match (built_in, output) {
+            (SubgroupSize, false) | (SubgroupLocalInvocationId, false) => true,
+            (VertexId, false)
+            | (VertexIndex, false)
+            | (InstanceIndex, false)
+            | (DrawIndex, false)
+            | (BaseVertex, false)
+            | (BaseInstance, false) => *self == Stage::Vertex,
+            (PatchVertices, false) => matches!(self, Stage::TessellationControl | Stage::TessellationEval),
+            (PrimitiveId, false) => matches!(self, Stage::TessellationControl | Stage::TessellationEval | Stage::Geometry | Stage::Fragment),
+            (InvocationId, false) => matches!(self, Stage::TessellationControl | Stage::Geometry),
+            (TessCoord, false) => *self == Stage::TessellationEval,
+            (FragCoord, false)
+            | (PointCoord, false)
+            | (FrontFacing, false)
+            | (Layer, false)
+            | (SampleId, false)
+            | (SamplePosition, false)
+            | (HelperInvocation, false) => *self == Stage::Fragment,
+            (NumWorkgroups, false)
+            | (WorkgroupId, false)
+            | (LocalInvocationId, false)
+            | (GlobalInvocationId, false)
+            | (LocalInvocationIndex, false) => *self == Stage::Compute,
+            (Position, true) | (PointSize, true) => matches!(self, Stage::Vertex | Stage::TessellationControl | Stage::TessellationEval | Stage::Geometry),
+            (Layer, true) | (PrimitiveId, true) => *self == Stage::Geometry,
+            (FragDepth, true) | (SampleMask, true) => *self == Stage::Fragment,
+            _ => false,
+        }
+    }
+
     pub(crate) fn rspirv(&self) -> rspirv::spirv::ExecutionModel {
         match self {
             Stage::Vertex => rspirv::spirv::ExecutionModel::Vertex,
@@ -198,6 +251,17 @@ impl Stage {
     }
 }
 
+/// The primitive topology a vertex shader is intended to be drawn with, see [`Builder::topology`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrimitiveTopology {
+    PointList,
+    LineList,
+    LineStrip,
+    TriangleList,
+    TriangleStrip,
+    TriangleFan,
+}
+
 pub struct Builder {
     inner: Rc<RefCell<BuilderInner>>,
 }
@@ -208,7 +272,31 @@ impl Builder {
     }
 
     pub fn compile(&self) -> Vec<u32> {
-        self.inner.borrow_mut().compile()
+        self.compile_to(SpirV)
+    }
+
+    /// Lower the functions and entry points with the backend
+    /// ```no_run
+    /// let spv: Vec<u32> = b.compile_to(spv::SpirV);
+    /// let wgsl: String = b.compile_to(spv::Wgsl);
+    /// ```
+    pub fn compile_to<B: Backend>(&self, backend: B) -> B::Output {
+        backend.compile(&self.inner.borrow())
+    }
+
+    /// Write the entry point for stage as glsl source
+    ///
+    /// This is intended for inspecting what was generated and isn't guaranteed to compile, use [`Builder::compile`] for the spir-v
+    pub fn to_glsl(&self, stage: Stage) -> String {
+        self.compile_to(Glsl(stage))
+    }
+
+    /// Write the functions and entry points as a wgsl module
+    ///
+    /// Only vertex, fragment and compute entry points can be written and sampled textures have to be declared
+    /// as a separate texture and sampler as wgsl has no combined image samplers
+    pub fn to_wgsl(&self) -> String {
+        self.compile_to(Wgsl)
     }
 
     pub fn __inner<'a>(&'a self) -> &'a Rc<RefCell<BuilderInner>> {
@@ -260,6 +348,11 @@ impl Builder {
         let inner = self.inner.borrow_mut();
         inner.push_constants.clone()
     }
+
+    pub fn get_topology(&self) -> Option<PrimitiveTopology> {
+        let inner = self.inner.borrow_mut();
+        inner.topology
+    }
 }
 
 // io
@@ -285,6 +378,7 @@ impl Builder {
             location: Left(location),
             flat,
             name,
+            auto: false,
         });
         drop(inner);
         Input { 
@@ -311,6 +405,7 @@ impl Builder {
             location: Left(location),
             flat,
             name,
+            auto: false,
         });
         drop(inner);
         Output {
@@ -329,6 +424,7 @@ impl Builder {
             location: Right(built_in),
             flat: false,
             name: Some(name),
+            auto: false,
         });
         drop(inner);
         Input { 
@@ -338,6 +434,100 @@ impl Builder {
         }
     }
 
+    /// declare an input to this shader at the lowest location not used by other inputs
+    ///
+    /// Inputs with explicit locations should be declared first, the location assigned can be found with
+    /// [`Builder::get_inputs`] and can be changed to match the outputs of the previous stage with [`Builder::link_inputs`]
+    pub fn input_auto<T: AsIOTypeConst>(&self, flat: bool, name: &'static str) -> Input<T> {
+        let mut inner = self.inner.borrow_mut();
+        assert!(inner.scope.is_none(), "Error cannot declare input: {{ flat: {}, name: {:?} }} when builder is in a function", flat, name);
+        let id = inner.inputs.len();
+        let location = BuilderInner::free_location(&inner.inputs, T::IO_TY);
+        inner.inputs.push(IOData {
+            ty: T::IO_TY,
+            location: Left(location),
+            flat,
+            name: Some(name),
+            auto: true,
+        });
+        drop(inner);
+        Input { 
+            id, 
+            inner: Rc::clone(&self.inner), 
+            marker: std::marker::PhantomData,
+        }
+    }
+
+    /// declare an output to this shader at the lowest location not used by other outputs, see [`Builder::input_auto`]
+    pub fn output_auto<T: AsIOTypeConst>(&self, flat: bool, name: &'static str) -> Output<T> {
+        let mut inner = self.inner.borrow_mut();
+        assert!(inner.scope.is_none(), "Error cannot declare output: {{ flat: {}, name: {:?} }} when builder is in a function", flat, name);
+        let id = inner.outputs.len();
+        let location = BuilderInner::free_location(&inner.outputs, T::IO_TY);
+        inner.outputs.push(IOData {
+            ty: T::IO_TY,
+            location: Left(location),
+            flat,
+            name: Some(name),
+            auto: true,
+        });
+        drop(inner);
+        Output {
+            id,
+            inner: Rc::clone(&self.inner),
+            marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Move the inputs of self with automatic locations to the location of the output with the same name in src
+    ///
+    /// Automatic inputs without a matching output are moved to the lowest location not used by other inputs
+    /// or the outputs of src.
+    /// Panics if a linked input would overlap an input with an explicit location or another linked input
+    pub fn link_inputs(&self, src: &Builder) {
+        let src_outputs = src.get_outputs();
+        let mut inner = self.inner.borrow_mut();
+
+        let mut unmatched = Vec::new();
+        for (idx, input) in inner.inputs.iter_mut().enumerate().filter(|(_, i)| i.auto) {
+            match src_outputs.iter().find(|o| o.name.is_some() && o.name == input.name) {
+                Some(output) => input.location = output.location,
+                None => unmatched.push(idx),
+            }
+        }
+
+        let placed = inner.inputs
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !unmatched.contains(idx))
+            .filter_map(|(_, i)| i.location.left().map(|l| (i, l..l + i.ty.locations())))
+            .collect::<Vec<_>>();
+        for (a, (input, range)) in placed.iter().enumerate() {
+            if let Some((other, other_range)) = placed[..a]
+                .iter()
+                .find(|(_, r)| r.start < range.end && range.start < r.end)
+            {
+                panic!(
+                    "Error cannot link input {:?} at location {} as it overlaps input {:?} at location {}",
+                    input.name, range.start, other.name, other_range.start
+                );
+            }
+        }
+
+        let mut taken = inner.inputs
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !unmatched.contains(idx))
+            .map(|(_, i)| i.clone())
+            .chain(src_outputs.iter().cloned())
+            .collect::<Vec<_>>();
+        for idx in unmatched {
+            let location = BuilderInner::free_location(&taken, inner.inputs[idx].ty);
+            inner.inputs[idx].location = Left(location);
+            taken.push(inner.inputs[idx].clone());
+        }
+    }
+
     fn built_in_output<T: AsIOTypeConst>(&self, built_in: rspirv::spirv::BuiltIn, name: &'static str) -> Output<T> {
         let mut inner = self.inner.borrow_mut();
         assert!(inner.scope.is_none(), "Error cannot declare built in output: {:?} when builder is in a function", built_in);
@@ -347,6 +537,7 @@ impl Builder {
             location: Right(built_in),
             flat: false,
             name: Some(name),
+            auto: false,
         });
         drop(inner);
         Output {
@@ -383,6 +574,32 @@ macro_rules! impl_io {
     };
 }
 
+macro_rules! impl_io_auto {
+    ($($name:ident, $f_in:ident, $f_flat_in:ident, $f_out:ident, $f_flat_out:ident,)*) => {
+        $(
+            /// see [`Builder::input_auto`]
+            pub fn $f_in(&self, name: &'static str) -> Input<$name> {
+                self.input_auto(false, name)
+            }
+    
+            /// see [`Builder::input_auto`]
+            pub fn $f_flat_in(&self, name: &'static str) -> Input<$name> {
+                self.input_auto(true, name)
+            }
+    
+            /// see [`Builder::output_auto`]
+            pub fn $f_out(&self, name: &'static str) -> Output<$name> {
+                self.output_auto(false, name)
+            }
+    
+            /// see [`Builder::output_auto`]
+            pub fn $f_flat_out(&self, name: &'static str) -> Output<$name> {
+                self.output_auto(true, name)
+            }
+        )*
+    };
+}
+
 macro_rules! impl_built_in_input {
     ($($f:ident, $ty:ident, $built_in:ident,)*) => {
         $(
@@ -412,9 +629,18 @@ impl Builder {
         IOVec4, in_vec4, in_flat_vec4, out_vec4, out_flat_vec4,
     );
 
+    #[rustfmt::skip]
+    impl_io_auto!(
+        IOFloat, in_float_auto, in_flat_float_auto, out_float_auto, out_flat_float_auto,
+        IOVec2, in_vec2_auto, in_flat_vec2_auto, out_vec2_auto, out_flat_vec2_auto,
+        IOVec3, in_vec3_auto, in_flat_vec3_auto, out_vec3_auto, out_flat_vec3_auto,
+        IOVec4, in_vec4_auto, in_flat_vec4_auto, out_vec4_auto, out_flat_vec4_auto,
+    );
+
     #[rustfmt::skip]
     impl_built_in_input!(
         vertex_id, IOInt, VertexId,
+        vertex_index, IOInt, VertexIndex,
         instance_index, IOInt, InstanceIndex,
         draw_index, IOInt, DrawIndex,
         base_vertex, IOInt, BaseVertex,
@@ -434,6 +660,9 @@ impl Builder {
         local_invocation_id, IOUVec3, LocalInvocationId,
         global_invocation_id, IOUVec3, GlobalInvocationId,
         local_invocation_index, IOUInt, LocalInvocationIndex,
+
+        subgroup_size, IOUInt, SubgroupSize,
+        subgroup_invocation_id, IOUInt, SubgroupLocalInvocationId,
     );
 
     #[rustfmt::skip]
@@ -472,13 +701,19 @@ impl Builder {
 
         let mut inner = self.inner.borrow_mut();
 
-        let instructions = match inner.scope.take().unwrap().downcast::<FuncScope>() {
-            Ok(scope) => scope.instructions,
+        let (instructions, arguments) = match inner.scope.take().unwrap().downcast::<FuncScope>() {
+            Ok(scope) => (scope.instructions, scope.arguments),
             Err(_) => unreachable!(),
         };
+
+        if T::TY != Type::Void {
+            let returns = matches!(instructions.last(), Some(Instruction::ReturnValue(o)) if o.value.1 == T::TY);
+            assert!(returns, "Error cannot declare function: {{ name: {:?} }} that doesn't end by returning {:?}", name, T::TY);
+        }
         
         let func_data = inner.functions.get_mut(&func_id).unwrap();
         func_data.instructions = instructions;
+        func_data.arguments = arguments;
 
         drop(inner);
 
@@ -489,12 +724,112 @@ impl Builder {
         }
     }
 
+    /// Declare the next argument of the function being built
+    ///
+    /// Arguments must be declared at the top level of the function, the value passed
+    /// to [`Func::call`] at the same position is returned
+    pub fn arg<T: IsTypeConst>(&self) -> T::T<'_> {
+        let mut inner = self.inner.borrow_mut();
+        let scope = match inner.scope.as_mut().and_then(|s| s.downcast_mut::<FuncScope>()) {
+            Some(s) => s,
+            None => panic!("Cannot declare argument when not at the top level of a function"),
+        };
+        let store = scope.get_new_id();
+        scope.instructions.push(Instruction::Argument(OpArgument {
+            index: scope.arguments.len(),
+            ty: T::TY,
+            store,
+        }));
+        scope.arguments.push(T::TY);
+        drop(inner);
+        T::T::from_id(store, &self.inner)
+    }
+
+    /// Return from the function being built
+    pub fn ret(&self) {
+        let mut inner = self.inner.borrow_mut();
+        if let Some(scope) = &mut inner.scope {
+            scope.push_instruction(Instruction::Return);
+        } else {
+            panic!("Cannot return when not in function");
+        }
+    }
+
+    /// Return value from the function being built, the type must match the function's return type
+    pub fn ret_value(&self, value: impl AsType) {
+        let mut inner = self.inner.borrow_mut();
+        if let Some(scope) = &mut inner.scope {
+            let value = (value.id(&mut **scope), value.ty());
+            scope.push_instruction(Instruction::ReturnValue(OpReturnValue { value }));
+        } else {
+            panic!("Cannot return value when not in function");
+        }
+    }
+
+    /// Set the workgroup size of compute entry points, defaults to (1, 1, 1)
+    /// ```no_run
+    /// b.local_size(x, y, z);
+    /// ```
+    /// is equivalent to the glsl
+    /// ```glsl
+    /// layout(local_size_x = x, local_size_y = y, local_size_z = z) in;
+    /// ```
+    pub fn local_size(&self, x: u32, y: u32, z: u32) {
+        self.inner.borrow_mut().local_size = [x, y, z];
+    }
+
+    /// Declare the primitive topology the vertex shader is intended to be drawn with
+    ///
+    /// This doesn't change the spir-v, it's recorded so that pipelines can be created with the right input assembly
+    pub fn topology(&self, topology: PrimitiveTopology) {
+        self.inner.borrow_mut().topology = Some(topology);
+    }
+
+    /// Compile functions to ssa values with OpPhi where control flow merges instead of function variables, defaults to false
+    ///
+    /// Only variables that are loaded from and stored to directly are promoted so the module is still valid for anything else
+    pub fn ssa(&self, ssa: bool) {
+        self.inner.borrow_mut().ssa = ssa;
+    }
+
+    /// Declare that the vertex shader draws points and get the point size output
+    ///
+    /// Vulkan leaves the size of points undefined unless it's written so the output should be stored to in the entry point
+    /// ```no_run
+    /// let point_size = b.points();
+    /// b.entry(spv::Stage::Vertex, "main", || {
+    ///     point_size.store(b.const_float(4.0));
+    /// });
+    /// ```
+    pub fn points(&self) -> Output<IOFloat> {
+        self.topology(PrimitiveTopology::PointList);
+        self.point_size()
+    }
+
     pub fn entry<F: FnOnce()>(&self, stage: Stage, name: &'static str, f: F) {
         let main = self.func::<Void, _>(Some(name), f);
 
         let mut inner = self.inner.borrow_mut();
 
         inner.entry_points.insert(stage, main.id);
+
+        let (inputs, outputs) = inner.used_io(main.id);
+        for (io, output) in inputs.iter()
+            .map(|i| (&inner.inputs[*i], false))
+            .chain(outputs.iter().map(|o| (&inner.outputs[*o], true)))
+        {
+            if let Right(built_in) = io.location {
+                if !stage.supports_built_in(built_in, output) {
+                    panic!(
+                        "Cannot use built in {} {:?} in entry point {} for {:?} stage",
+                        if output { "output" } else { "input" },
+                        built_in,
+                        name,
+                        stage,
+                    );
+                }
+            }
+        }
     }
 }
 
@@ -659,6 +994,8 @@ impl Builder {
         DVec2, dvec2, DVEC2, Double,
         Mat2, mat2, MAT2, Vec2,
         DMat2, dmat2, DMAT2, DVec2,
+        Mat2, mat2_cols, MAT2, Vec2,
+        DMat2, dmat2_cols, DMAT2, DVec2,
     );
 
     #[rustfmt::skip]
@@ -669,6 +1006,8 @@ impl Builder {
         DVec3, dvec3, DVEC3, Double,
         Mat3, mat3, MAT3, Vec3,
         DMat3, dmat3, DMAT3, DVec3,
+        Mat3, mat3_cols, MAT3, Vec3,
+        DMat3, dmat3_cols, DMAT3, DVec3,
     );
 
     #[rustfmt::skip]
@@ -679,6 +1018,160 @@ impl Builder {
         DVec4, dvec4, DVEC4, Double,
         Mat4, mat4, MAT4, Vec4,
         DMat4, dmat4, DMAT4, DVec4,
+        Mat4, mat4_cols, MAT4, Vec4,
+        DMat4, dmat4_cols, DMAT4, DVec4,
+    );
+}
+
+impl Builder {
+    fn diagonal(&self, ty: MatrixType, s: &dyn AsType, zero: &dyn AsType) -> usize {
+        let mut inner = self.inner.borrow_mut();
+        if let Some(scope) = &mut inner.scope {
+            let s_id = s.id(&mut **scope);
+            let zero_id = zero.id(&mut **scope);
+            let scalar_ty = Type::Scalar(ty.vec_ty.scalar_ty);
+
+            let cols = (0..ty.n_vec)
+                .map(|col| {
+                    let col_id = scope.get_new_id();
+                    scope.push_instruction(Instruction::Composite(OpComposite {
+                        ty: Type::Vector(ty.vec_ty),
+                        id: col_id,
+                        constituents: (0..ty.vec_ty.n_scalar)
+                            .map(|row| (if row == col { s_id } else { zero_id }, scalar_ty.clone()))
+                            .collect(),
+                    }));
+                    (col_id, Type::Vector(ty.vec_ty))
+                })
+                .collect();
+
+            let new_id = scope.get_new_id();
+            scope.push_instruction(Instruction::Composite(OpComposite {
+                ty: Type::Matrix(ty),
+                id: new_id,
+                constituents: cols,
+            }));
+
+            new_id
+        } else {
+            panic!("Cannot construct matrix when not in function");
+        }
+    }
+}
+
+macro_rules! make_diagonal {
+    ($($name:ident, $f:ident, $identity:ident, $c:ident, $elem:ident, $zero:literal,)*) => {
+        $(
+            /// Construct a matrix with s along the diagonal and zero elsewhere
+            /// ```no_run
+            /// b.mat4_diagonal(s);
+            /// ```
+            /// is equivalent to the glsl
+            /// ```glsl
+            /// mat4(s);
+            /// ```
+            pub fn $f<'a>(&'a self, s: impl SpvRustEq<$elem<'a>>) -> $name<'a> {
+                let id = self.diagonal(MatrixType::$c, s.as_ty_ref(), &$zero);
+                $name {
+                    id,
+                    b: &self.inner
+                }
+            }
+
+            /// Construct the identity matrix
+            pub fn $identity<'a>(&'a self) -> $name<'a> {
+                self.$f(1.0)
+            }
+        )*
+    };
+}
+
+impl Builder {
+    #[rustfmt::skip]
+    make_diagonal!(
+        Mat2, mat2_diagonal, mat2_identity, MAT2, Float, 0.0f32,
+        Mat3, mat3_diagonal, mat3_identity, MAT3, Float, 0.0f32,
+        Mat4, mat4_diagonal, mat4_identity, MAT4, Float, 0.0f32,
+        DMat2, dmat2_diagonal, dmat2_identity, DMAT2, Double, 0.0f64,
+        DMat3, dmat3_diagonal, dmat3_identity, DMAT3, Double, 0.0f64,
+        DMat4, dmat4_diagonal, dmat4_identity, DMAT4, Double, 0.0f64,
+    );
+}
+
+macro_rules! make_from_mat3 {
+    ($($name:ident, $f:ident, $mat3:ident, $vec3:ident, $from_vec:ident, $cols:ident, $zero:literal, $one:literal,)*) => {
+        $(
+            /// Construct an affine transform from a rotation/scale matrix and a translation
+            /// ```no_run
+            /// b.mat4_from_mat3(m, t);
+            /// ```
+            /// is equivalent to the glsl
+            /// ```glsl
+            /// mat4(vec4(m[0], 0.0), vec4(m[1], 0.0), vec4(m[2], 0.0), vec4(t, 1.0));
+            /// ```
+            pub fn $f<'a>(&'a self, m: $mat3<'a>, translation: impl VectorComponents<$vec3<'a>>) -> $name<'a> {
+                let c0 = m.col(0);
+                let c1 = m.col(1);
+                let c2 = m.col(2);
+                self.$cols(
+                    self.$from_vec(&[&c0, &$zero]),
+                    self.$from_vec(&[&c1, &$zero]),
+                    self.$from_vec(&[&c2, &$zero]),
+                    self.$from_vec(&[&translation, &$one]),
+                )
+            }
+        )*
+    };
+}
+
+impl Builder {
+    #[rustfmt::skip]
+    make_from_mat3!(
+        Mat4, mat4_from_mat3, Mat3, Float, vec4_from, mat4_cols, 0.0f32, 1.0f32,
+        DMat4, dmat4_from_mat3, DMat3, Double, dvec4_from, dmat4_cols, 0.0f64, 1.0f64,
+    );
+}
+
+macro_rules! make_from {
+    ($($name:ident, $f:ident, $c:ident, $elem:ident, $n:literal,)*) => {
+        $(
+            /// Construct a vector from any combination of scalars and smaller vectors
+            /// ```no_run
+            /// b.vec4_from(&[&v.xyz(), &1.0]);
+            /// ```
+            /// is equivalent to the glsl
+            /// ```glsl
+            /// vec4(v.xyz, 1.0);
+            /// ```
+            /// Will panic if the number of components doesn't match the size of the vector
+            pub fn $f<'a>(&'a self, components: &[&dyn VectorComponents<$elem<'a>>]) -> $name<'a> {
+                let n = components.iter().map(|c| c.n_components()).sum::<u32>();
+                assert!(n == $n, "Cannot construct {} from {} components", stringify!($name), n);
+                let id = self.composite(Type::$c, components.iter().map(|c| c.as_ty_ref()));
+                $name {
+                    id,
+                    b: &self.inner
+                }
+            }
+        )*
+    };
+}
+
+impl Builder {
+    #[rustfmt::skip]
+    make_from!(
+        IVec2, ivec2_from, IVEC2, Int, 2,
+        IVec3, ivec3_from, IVEC3, Int, 3,
+        IVec4, ivec4_from, IVEC4, Int, 4,
+        UVec2, uvec2_from, UVEC2, UInt, 2,
+        UVec3, uvec3_from, UVEC3, UInt, 3,
+        UVec4, uvec4_from, UVEC4, UInt, 4,
+        Vec2, vec2_from, VEC2, Float, 2,
+        Vec3, vec3_from, VEC3, Float, 3,
+        Vec4, vec4_from, VEC4, Float, 4,
+        DVec2, dvec2_from, DVEC2, Double, 2,
+        DVec3, dvec3_from, DVEC3, Double, 3,
+        DVec4, dvec4_from, DVEC4, Double, 4,
     );
 }
 
@@ -732,6 +1225,7 @@ impl Builder {
             set,
             binding,
             name,
+            block: false,
         });
 
         drop(inner);
@@ -742,6 +1236,41 @@ impl Builder {
         }
     }
 
+    /// Declare a uniform buffer with multiple members for this shader, members are declared with [`UniformBlock::member`]
+    /// ```no_run
+    /// let block = b.uniform_block(s, b, Some(name));
+    /// let model = block.member::<spv::Mat4>("model");
+    /// let color = block.member::<spv::Vec4>("color");
+    /// ```
+    /// is equivalent to the glsl
+    /// ```glsl
+    /// layout(set = s, binding = b) uniform UData {
+    ///     mat4 model;
+    ///     vec4 color;
+    /// } name;
+    /// ```
+    pub fn uniform_block(&self, set: u32, binding: u32, name: Option<&'static str>) -> UniformBlock {
+        let mut inner = self.inner.borrow_mut();
+
+        let id = inner.uniforms.len();
+        inner.uniforms.push(UniformData {
+            ty: Type::Struct(StructType {
+                name: None,
+                members: std::borrow::Cow::Owned(Vec::new()),
+            }),
+            set,
+            binding,
+            name,
+            block: true,
+        });
+
+        drop(inner);
+        UniformBlock { 
+            id, 
+            b: Rc::clone(&self.inner), 
+        }
+    }
+
     fn raw_storage<T: IsTypeConst>(&self, set: u32, binding: u32, read: bool, write: bool, name: Option<&'static str>) -> Storage<T> {
         let mut inner = self.inner.borrow_mut();
 
@@ -886,16 +1415,66 @@ impl Builder {
         self.raw_texture(set, binding, name)
     }
 
-    /// Declare a dtextureD for the shader
+    fn raw_storage_texture<D: AsDimension, T: GTexture<D>>(&self, set: u32, binding: u32, format: TextureFormat, name: Option<&'static str>) -> T {
+        assert!(
+            format.scalar_ty() == T::TEXTURE_TY.scalar_ty,
+            "Cannot declare storage texture {:?} with format {:?} as its texels aren't {:?}", 
+            name, 
+            format, 
+            T::TEXTURE_TY.scalar_ty
+        );
+
+        let mut inner = self.inner.borrow_mut();
+
+        let id = inner.textures.len();
+        inner.textures.push(TextureData {
+            ty: TextureType {
+                format: TextureSpvFormat::Color(format),
+                ..T::TEXTURE_TY
+            },
+            set,
+            binding,
+            name,
+        });
+
+        drop(inner);
+        T::new(id, Rc::clone(&self.inner))
+    }
+
+    /// Declare a storage image with an integer format for the shader
     /// ```no_run
-    /// b.dtexture<D>(s, b, Some(name));
+    /// b.storage_itexture<D>(s, b, TextureFormat::R32Sint, Some(name));
     /// ```
     /// is equivalent to the glsl
     /// ```glsl
-    /// layout(set = s, binding = b) uniform dtextureD name;
+    /// layout(set = s, binding = b, r32i) uniform iimageD name;
     /// ```
-    pub fn dtexture<D: AsDimension>(&self, set: u32, binding: u32, name: Option<&'static str>) -> DTexture<D> {
-        self.raw_texture(set, binding, name)
+    pub fn storage_itexture<D: AsDimension>(&self, set: u32, binding: u32, format: TextureFormat, name: Option<&'static str>) -> ITexture<D> {
+        self.raw_storage_texture(set, binding, format, name)
+    }
+
+    /// Declare a storage image with an unsigned integer format for the shader
+    /// ```no_run
+    /// b.storage_utexture<D>(s, b, TextureFormat::R32Uint, Some(name));
+    /// ```
+    /// is equivalent to the glsl
+    /// ```glsl
+    /// layout(set = s, binding = b, r32ui) uniform uimageD name;
+    /// ```
+    pub fn storage_utexture<D: AsDimension>(&self, set: u32, binding: u32, format: TextureFormat, name: Option<&'static str>) -> UTexture<D> {
+        self.raw_storage_texture(set, binding, format, name)
+    }
+
+    /// Declare a storage image with a float or normalized format for the shader
+    /// ```no_run
+    /// b.storage_texture<D>(s, b, TextureFormat::Rgba16Float, Some(name));
+    /// ```
+    /// is equivalent to the glsl
+    /// ```glsl
+    /// layout(set = s, binding = b, rgba16f) uniform imageD name;
+    /// ```
+    pub fn storage_texture<D: AsDimension>(&self, set: u32, binding: u32, format: TextureFormat, name: Option<&'static str>) -> Texture<D> {
+        self.raw_storage_texture(set, binding, format, name)
     }
 
     fn raw_sampled_texture<D: AsDimension, T: SampledGTexture<D>>(&self, set: u32, binding: u32, name: Option<&'static str>) -> T {
@@ -907,12 +1486,72 @@ impl Builder {
             set,
             binding,
             name,
+            count: None,
         });
 
         drop(inner);
         T::from_uniform(id, Rc::clone(&self.inner))
     }
 
+    fn raw_sampled_texture_array<D: AsDimension, T: SampledGTexture<D>>(&self, set: u32, binding: u32, count: u32, name: Option<&'static str>) -> SampledTextureArray<D, T> {
+        assert!(count != 0, "Cannot declare sampled texture array with count 0");
+
+        let mut inner = self.inner.borrow_mut();
+
+        let id = inner.sampled_textures.len();
+        inner.sampled_textures.push(SampledTextureData {
+            ty: T::Texture::TEXTURE_TY,
+            set,
+            binding,
+            name,
+            count: Some(count),
+        });
+
+        drop(inner);
+        SampledTextureArray {
+            id,
+            count,
+            b: Rc::clone(&self.inner),
+            marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Declare an array of itextureD for the shader
+    /// ```no_run
+    /// b.sampled_itexture_array<D>(s, b, n, Some(name));
+    /// ```
+    /// is equivalent to the glsl
+    /// ```glsl
+    /// layout(set = s, binding = b) uniform isamplerD name[n];
+    /// ```
+    pub fn sampled_itexture_array<D: AsDimension>(&self, set: u32, binding: u32, count: u32, name: Option<&'static str>) -> SampledTextureArray<D, SampledITexture<D>> {
+        self.raw_sampled_texture_array(set, binding, count, name)
+    }
+
+    /// Declare an array of utextureD for the shader
+    /// ```no_run
+    /// b.sampled_utexture_array<D>(s, b, n, Some(name));
+    /// ```
+    /// is equivalent to the glsl
+    /// ```glsl
+    /// layout(set = s, binding = b) uniform usamplerD name[n];
+    /// ```
+    pub fn sampled_utexture_array<D: AsDimension>(&self, set: u32, binding: u32, count: u32, name: Option<&'static str>) -> SampledTextureArray<D, SampledUTexture<D>> {
+        self.raw_sampled_texture_array(set, binding, count, name)
+    }
+
+    /// Declare an array of textureD for the shader
+    /// ```no_run
+    /// b.sampled_texture_array<D>(s, b, n, Some(name));
+    /// ```
+    /// is equivalent to the glsl
+    /// ```glsl
+    /// layout(set = s, binding = b) uniform samplerD name[n];
+    /// ```
+    pub fn sampled_texture_array<D: AsDimension>(&self, set: u32, binding: u32, count: u32, name: Option<&'static str>) -> SampledTextureArray<D, SampledTexture<D>> {
+        self.raw_sampled_texture_array(set, binding, count, name)
+    }
+
     /// Declare an itextureD for the shader
     /// ```no_run
     /// b.sampled_itexture<D>(s, b, Some(name));
@@ -949,23 +1588,12 @@ impl Builder {
         self.raw_sampled_texture(set, binding, name)
     }
 
-    /// Declare a dtextureD for the shader
-    /// ```no_run
-    /// b.sampled_dtexture<D>(s, b, Some(name));
-    /// ```
-    /// is equivalent to the glsl
-    /// ```glsl
-    /// layout(set = s, binding = b) uniform dsamplereD name;
-    /// ```
-    pub fn sampled_dtexture<D: AsDimension>(&self, set: u32, binding: u32, name: Option<&'static str>) -> SampledDTexture<D> {
-        self.raw_sampled_texture(set, binding, name)
-    }
 }
 
 macro_rules! impl_texture {
     ($($name:ident, $f:ident,)*) => {
         $(
-            /// see one of [`Builder::itexture`] [`Builder::utexture`] [`Builder::texture`] [`Builder::dtexture`]
+            /// see one of [`Builder::itexture`] [`Builder::utexture`] [`Builder::texture`]
             pub fn $f(&self, set: u32, binding: u32, name: Option<&'static str>) -> $name {
                 self.raw_texture(set, binding, name)
             }
@@ -1002,21 +1630,13 @@ impl Builder {
         TextureCube, texture_cube,
         TextureCubeArray, texture_cube_array,
 
-        DTexture1D, dtexture1d,
-        DTexture1DArray, dtexture1d_array,
-        DTexture2D, dtexture2d,
-        DTexture2DMs, dtexture2d_ms,
-        DTexture2DArray, dtexture2d_array,
-        DTexture2DMsArray, dtexture2d_ms_array,
-        DTextureCube, dtexture_cube,
-        DTextureCubeArray, dtexture_cube_array,
     );
 }
 
 macro_rules! impl_sampled_texture {
     ($($name:ident, $f:ident,)*) => {
         $(
-            /// see one of [`Builder::sampled_itexture`] [`Builder::sampled_utexture`] [`Builder::sampled_texture`] [`Builder::sampled_dtexture`]
+            /// see one of [`Builder::sampled_itexture`] [`Builder::sampled_utexture`] [`Builder::sampled_texture`]
             pub fn $f(&self, set: u32, binding: u32, name: Option<&'static str>) -> $name {
                 self.raw_sampled_texture(set, binding, name)
             }
@@ -1053,14 +1673,6 @@ impl Builder {
         SampledTextureCube, sampled_texture_cube,
         SampledTextureCubeArray, sampled_texture_cube_array,
 
-        SampledDTexture1D, sampled_dtexture1d,
-        SampledDTexture1DArray, sampled_dtexture1d_array,
-        SampledDTexture2D, sampled_dtexture2d,
-        SampledDTexture2DMs, sampled_dtexture2d_ms,
-        SampledDTexture2DArray, sampled_dtexture2d_array,
-        SampledDTexture2DMsArray, sampled_dtexture2d_ms_array,
-        SampledDTextureCube, sampled_dtexture_cube,
-        SampledDTextureCubeArray, sampled_dtexture_cube_array,
     );
 }
 
@@ -1114,9 +1726,74 @@ pub fn sample<'a, 'b, D: AsDimension, S: SampledGTexture<D>>(sampled_texture: &'
 // ================================================================================
 // ================================================================================
 
-// pub fn loop_while<F: FnOnce()>(b: Builder, f: F) {
+/// Inserts a loop block in the spir-v module that runs f while the condition is true
+///
+/// The condition closure is evaluated before every iteration so values it depends on should be updated with store in f
+/// ```ignore
+/// let mut i = b.const_int(0);
+/// spv::spv_while(&b, spv::LoopControl::None, move || i.lt(4), || {
+///     i += 1;
+/// });
+/// ```
+pub fn spv_while<'a, C: FnOnce() -> Bool<'a>, F: FnOnce()>(b: &'a Builder, control: LoopControl, condition: C, f: F) {
+    let mut inner = b.inner.borrow_mut();
 
-// }
+    if let Some(scope) = inner.scope.take() {
+        inner.scope = Some(Box::new(IfScope {
+            instructions: Vec::new(),
+            outer: scope,
+        }));
+
+        drop(inner);
+
+        let condition = condition();
+
+        let mut inner = b.inner.borrow_mut();
+
+        let condition_scope = if let Ok(t) = inner.scope.take().unwrap().downcast::<IfScope>() {
+            t
+        } else {
+            unreachable!()
+        };
+
+        inner.scope = Some(Box::new(IfScope {
+            instructions: Vec::new(),
+            outer: condition_scope.outer,
+        }));
+
+        drop(inner);
+
+        f();
+
+        let mut inner = b.inner.borrow_mut();
+
+        let mut loop_scope = if let Ok(t) = inner.scope.take().unwrap().downcast::<IfScope>() {
+            t
+        } else {
+            unreachable!()
+        };
+
+        loop_scope.outer.push_instruction(crate::Instruction::Loop(OpLoop {
+            condition_instructions: condition_scope.instructions,
+            condition: condition.id,
+            instructions: loop_scope.instructions,
+            control,
+        }));
+
+        inner.scope = Some(loop_scope.outer);
+    } else {
+        panic!("Cannot loop if not in function");
+    }
+}
+
+/// Emits f once for each i in 0..n as straight line code
+///
+/// Useful for small fixed kernels where the trip count is known on the cpu, larger counts should use [`spv_while`] with [`LoopControl::Unroll`]
+pub fn spv_for_unrolled<F: FnMut(usize)>(n: usize, mut f: F) {
+    for i in 0..n {
+        f(i);
+    }
+}
 
 pub struct IfChain<'a> {
     builder: &'a Rc<RefCell<BuilderInner>>,
@@ -1247,3 +1924,64 @@ impl<'a> IfChain<'a> {
         }
     }
 }
+
+/// Selects a if the condition is true otherwise b without branching
+///
+/// Both a and b are always evaluated, only scalar and vector types can be selected between, see [`spv_if_else`] for other types
+pub fn spv_select<'a, T: FromId<'a> + AsType>(condition: Bool<'a>, a: T, b: T) -> T {
+    let ty = <T as AsTypeConst>::TY;
+    assert!(
+        matches!(ty, Type::Scalar(_) | Type::Vector(_)),
+        "Cannot select between values of type {:?}, use spv_if_else instead", ty
+    );
+
+    let mut inner = condition.b.borrow_mut();
+
+    if let Some(scope) = &mut inner.scope {
+        let a_id = a.id(&mut **scope);
+        let b_id = b.id(&mut **scope);
+        let store = scope.get_new_id();
+
+        scope.push_instruction(Instruction::Select(OpSelect {
+            condition: condition.id,
+            ty,
+            a: a_id,
+            b: b_id,
+            store,
+        }));
+
+        drop(inner);
+        T::from_id(store, condition.b)
+    } else {
+        panic!("Cannot select if not in function");
+    }
+}
+
+/// Evaluates a if the condition is true otherwise b and returns the result
+///
+/// Unlike [`spv_select`] only the taken branch is evaluated and any type can be returned
+pub fn spv_if_else<'a, T: FromId<'a> + AsType, A: FnOnce() -> T, B: FnOnce() -> T>(condition: Bool<'a>, a: A, b: B) -> T {
+    let ty = <T as AsTypeConst>::TY;
+    let builder = condition.b;
+
+    let store = if let Some(scope) = &mut builder.borrow_mut().scope {
+        scope.get_new_id()
+    } else {
+        panic!("Cannot branch if not in function");
+    };
+
+    let assign = |v: T| {
+        let mut inner = builder.borrow_mut();
+        let scope = inner.scope.as_mut().unwrap();
+        let id = v.id(&mut **scope);
+        scope.push_instruction(Instruction::LoadStore(OpLoadStore {
+            ty: ty.clone(),
+            src: OpLoadStoreData::Variable { id },
+            dst: OpLoadStoreData::Variable { id: store },
+        }));
+    };
+
+    spv_if(condition, || assign(a())).spv_else(|| assign(b()));
+
+    T::from_id(store, builder)
+}
@@ -0,0 +1,239 @@
+//! Linking functions from one builder into another
+//!
+//! A library of utility functions can be declared once on its own builder and imported into every builder that uses it.
+//! ```no_run
+//! let lib = spv::Builder::new();
+//! let luma = lib.func::<spv::Float, _>(Some("luma"), || {
+//!     let color = lib.arg::<spv::Vec3>();
+//!     ..
+//!     lib.ret_value(y);
+//! });
+//!
+//! let b = spv::Builder::new();
+//! let import = b.import(&lib);
+//! let luma = import.func(&luma);
+//! b.entry(spv::Stage::Fragment, "main", || {
+//!     ..
+//!     let y = luma.call([&color as &dyn spv::AsType]);
+//! });
+//! ```
+//!
+//! Struct types and constants are stored by value in the instructions so don't need remapping, inputs, outputs and
+//! descriptors used by the imported functions are merged with those already declared on the builder.
+
+use either::*;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+/// Functions imported from another builder, see [`crate::Builder::import`]
+pub struct Import {
+    pub(crate) library: Rc<RefCell<crate::BuilderInner>>,
+    pub(crate) functions: HashMap<usize, usize>,
+    pub(crate) inner: Rc<RefCell<crate::BuilderInner>>,
+}
+
+impl Import {
+    /// Get the imported copy of a function declared on the library builder
+    pub fn func<T: crate::IsTypeConst>(&self, func: &crate::Func<T>) -> crate::Func<T> {
+        assert!(Rc::ptr_eq(&func.inner, &self.library), "Cannot get function from import that wasn't declared on the imported builder");
+        crate::Func {
+            id: *self.functions.get(&func.id).unwrap(),
+            inner: Rc::clone(&self.inner),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Maps indices into the library builder to indices into the importing builder
+struct ImportMap {
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+    uniforms: Vec<usize>,
+    storages: Vec<usize>,
+    textures: Vec<usize>,
+    sampled_textures: Vec<usize>,
+    samplers: Vec<usize>,
+    functions: HashMap<usize, usize>,
+}
+
+/// Append the elements of src not already in dst, returning where each element of src ended up
+fn merge<T: Clone>(dst: &mut Vec<T>, src: &[T], same: impl Fn(&T, &T) -> bool, check: impl Fn(&T, &T)) -> Vec<usize> {
+    src.iter()
+        .map(|s| {
+            if let Some(idx) = dst.iter().position(|d| same(d, s)) {
+                check(&dst[idx], s);
+                idx
+            } else {
+                dst.push(s.clone());
+                dst.len() - 1
+            }
+        })
+        .collect()
+}
+
+impl ImportMap {
+    fn load_store_data(&self, data: &mut crate::OpLoadStoreData) {
+        use crate::OpLoadStoreData::*;
+
+        match data {
+            Input { location } => *location = self.inputs[*location],
+            Output { location } => *location = self.outputs[*location],
+            UniformField { id, .. }
+            | Uniform { id }
+            | UniformMember { id, .. } => *id = self.uniforms[*id],
+            Storage { id }
+            | StorageElement { id, .. }
+            | StorageElementField { id, .. } => *id = self.storages[*id],
+            _ => (),
+        }
+    }
+
+    fn op_if(&self, op: &mut crate::OpIf) {
+        self.instructions(&mut op.instructions);
+        if let Some(then) = &mut *op.then.borrow_mut() {
+            match then {
+                Left(t) => self.op_if(t),
+                Right(t) => self.instructions(&mut t.instructions),
+            }
+        }
+    }
+
+    fn instructions(&self, instructions: &mut [crate::Instruction]) {
+        for instruction in instructions {
+            match instruction {
+                crate::Instruction::LoadStore(o) => {
+                    self.load_store_data(&mut o.src);
+                    self.load_store_data(&mut o.dst);
+                },
+                crate::Instruction::FuncCall(o) => o.func = *self.functions.get(&o.func).unwrap(),
+                crate::Instruction::Sample(o) => if let Left(id) = &mut o.sampled_texture {
+                    *id = self.sampled_textures[*id];
+                },
+                crate::Instruction::SampledTextureElement(o) => o.sampled_texture = self.sampled_textures[o.sampled_texture],
+                crate::Instruction::Combine(o) => {
+                    o.texture = self.textures[o.texture];
+                    o.sampler = self.samplers[o.sampler];
+                },
+                crate::Instruction::If(o) => self.op_if(o),
+                crate::Instruction::Loop(o) => {
+                    self.instructions(&mut o.condition_instructions);
+                    self.instructions(&mut o.instructions);
+                },
+                _ => (),
+            }
+        }
+    }
+}
+
+impl crate::Builder {
+    /// Import the functions declared on library into self
+    ///
+    /// Entry points of library aren't imported. Inputs and outputs at the same location and descriptors at the same set and binding
+    /// are shared with self and must have the same type.
+    pub fn import(&self, library: &crate::Builder) -> Import {
+        assert!(!Rc::ptr_eq(self.__inner(), library.__inner()), "Cannot import builder into itself");
+
+        let lib = library.__inner().borrow();
+        let mut inner = self.__inner().borrow_mut();
+        assert!(lib.scope.is_none() && inner.scope.is_none(), "Cannot import builder when either builder is in a function");
+
+        let inputs = merge(
+            &mut inner.inputs,
+            &lib.inputs,
+            |a, b| a.location == b.location,
+            |a, b| assert!(a.ty == b.ty, "Imported input at location {:?} has type {:?} but builder has {:?}", b.location, b.ty, a.ty),
+        );
+        let outputs = merge(
+            &mut inner.outputs,
+            &lib.outputs,
+            |a, b| a.location == b.location,
+            |a, b| assert!(a.ty == b.ty, "Imported output at location {:?} has type {:?} but builder has {:?}", b.location, b.ty, a.ty),
+        );
+        let uniforms = merge(
+            &mut inner.uniforms,
+            &lib.uniforms,
+            |a, b| a.set == b.set && a.binding == b.binding,
+            |a, b| assert!(a.ty == b.ty && a.block == b.block, "Imported uniform at set {} binding {} has a different type to the builder", b.set, b.binding),
+        );
+        let storages = merge(
+            &mut inner.storages,
+            &lib.storages,
+            |a, b| a.set == b.set && a.binding == b.binding,
+            |a, b| assert!(a.ty == b.ty, "Imported storage at set {} binding {} has type {:?} but builder has {:?}", b.set, b.binding, b.ty, a.ty),
+        );
+        let textures = merge(
+            &mut inner.textures,
+            &lib.textures,
+            |a, b| a.set == b.set && a.binding == b.binding,
+            |a, b| assert!(a.ty == b.ty, "Imported texture at set {} binding {} has type {:?} but builder has {:?}", b.set, b.binding, b.ty, a.ty),
+        );
+        let sampled_textures = merge(
+            &mut inner.sampled_textures,
+            &lib.sampled_textures,
+            |a, b| a.set == b.set && a.binding == b.binding,
+            |a, b| assert!(
+                a.ty == b.ty && a.count == b.count,
+                "Imported sampled texture at set {} binding {} has type {:?} (count {:?}) but builder has {:?} (count {:?})",
+                b.set, b.binding, b.ty, b.count, a.ty, a.count,
+            ),
+        );
+        let samplers = merge(
+            &mut inner.samplers,
+            &lib.samplers,
+            |a, b| a.set == b.set && a.binding == b.binding,
+            |_, _| (),
+        );
+
+        match (&inner.push_constants, &lib.push_constants) {
+            (Some(a), Some(b)) => assert!(a.ty == b.ty, "Imported push constants have type {:?} but builder has {:?}", b.ty, a.ty),
+            (None, Some(b)) => inner.push_constants = Some(b.clone()),
+            _ => (),
+        }
+
+        let mut ids = lib.functions
+            .keys()
+            .filter(|id| !lib.entry_points.values().any(|e| e == *id))
+            .cloned()
+            .collect::<Vec<_>>();
+        ids.sort();
+
+        let start = inner.functions.len();
+        let functions = ids.iter()
+            .enumerate()
+            .map(|(i, id)| (*id, start + i))
+            .collect::<HashMap<_, _>>();
+
+        let map = ImportMap {
+            inputs,
+            outputs,
+            uniforms,
+            storages,
+            textures,
+            sampled_textures,
+            samplers,
+            functions,
+        };
+
+        for id in &ids {
+            let func = lib.functions.get(id).unwrap();
+            let mut instructions = func.instructions.clone();
+            map.instructions(&mut instructions);
+            inner.functions.insert(*map.functions.get(id).unwrap(), crate::FuncData {
+                ret: func.ret.clone(),
+                arguments: func.arguments.clone(),
+                instructions,
+                name: func.name,
+            });
+        }
+
+        drop(inner);
+        Import {
+            library: Rc::clone(library.__inner()),
+            functions: map.functions,
+            inner: Rc::clone(self.__inner()),
+        }
+    }
+}
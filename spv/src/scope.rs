@@ -58,6 +58,8 @@ impl dyn Scope {
 pub struct FuncScope {
     pub(crate) instructions: Vec<crate::Instruction>,
     pub(crate) variables: Slab<Option<String>>,
+    /// types of the arguments declared with [`crate::Builder::arg`]
+    pub(crate) arguments: Vec<crate::Type>,
 } 
 
 impl Scope for FuncScope {
@@ -83,6 +85,7 @@ impl FuncScope {
         Self {
             variables: Slab::new(),
             instructions: Vec::new(),
+            arguments: Vec::new(),
         }
     }
 }
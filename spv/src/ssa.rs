@@ -0,0 +1,355 @@
+//! Promotion of function variables to ssa values
+//!
+//! Instructions compile to loads and stores of function variables which leaves removing them to the driver's optimizer.
+//! This pass replaces variables that are only loaded from and stored to with the values stored and inserts OpPhi where
+//! control flow merges, following Braun et al. "Simple and Efficient Construction of Static Single Assignment Form".
+
+use rspirv::dr;
+use rspirv::spirv::Op;
+use rspirv::spirv::Word;
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+
+struct Phi {
+    block: usize,
+    var: Word,
+    /// (value, parent block label)
+    operands: Vec<(Word, Word)>,
+}
+
+/// Undefined values shared between all functions in the module
+struct Undefs<'a> {
+    bound: &'a mut Word,
+    values: HashMap<Word, Word>,
+    instructions: Vec<dr::Instruction>,
+}
+
+impl<'a> Undefs<'a> {
+    fn get(&mut self, ty: Word) -> Word {
+        if let Some(id) = self.values.get(&ty) {
+            return *id;
+        }
+        let id = *self.bound;
+        *self.bound += 1;
+        self.instructions.push(dr::Instruction::new(Op::Undef, Some(ty), Some(id), Vec::new()));
+        self.values.insert(ty, id);
+        id
+    }
+}
+
+struct Promoter<'a, 'b> {
+    undefs: &'b mut Undefs<'a>,
+    /// the type pointed to by each promoted variable
+    types: HashMap<Word, Word>,
+    labels: Vec<Word>,
+    preds: Vec<Vec<usize>>,
+    sealed: Vec<bool>,
+    defs: HashMap<(Word, usize), Word>,
+    incomplete: HashMap<usize, Vec<Word>>,
+    phis: BTreeMap<Word, Phi>,
+    replace: HashMap<Word, Word>,
+}
+
+impl<'a, 'b> Promoter<'a, 'b> {
+    fn resolve(&self, mut id: Word) -> Word {
+        while let Some(r) = self.replace.get(&id) {
+            id = *r;
+        }
+        id
+    }
+
+    fn new_phi(&mut self, var: Word, block: usize) -> Word {
+        let id = *self.undefs.bound;
+        *self.undefs.bound += 1;
+        self.phis.insert(id, Phi {
+            block,
+            var,
+            operands: Vec::new(),
+        });
+        id
+    }
+
+    fn write(&mut self, var: Word, block: usize, value: Word) {
+        self.defs.insert((var, block), value);
+    }
+
+    fn read(&mut self, var: Word, block: usize) -> Word {
+        if let Some(value) = self.defs.get(&(var, block)) {
+            return self.resolve(*value);
+        }
+
+        let value = if !self.sealed[block] {
+            // not all predecessors are known yet, the operands are filled in when the block is sealed
+            let phi = self.new_phi(var, block);
+            self.incomplete.entry(block).or_default().push(phi);
+            phi
+        } else if self.preds[block].is_empty() {
+            let ty = *self.types.get(&var).unwrap();
+            self.undefs.get(ty)
+        } else if self.preds[block].len() == 1 {
+            self.read(var, self.preds[block][0])
+        } else {
+            let phi = self.new_phi(var, block);
+            // break cycles through loops before reading the predecessors
+            self.write(var, block, phi);
+            self.add_phi_operands(phi);
+            phi
+        };
+
+        self.write(var, block, value);
+        value
+    }
+
+    fn add_phi_operands(&mut self, phi: Word) {
+        let (var, block) = {
+            let p = self.phis.get(&phi).unwrap();
+            (p.var, p.block)
+        };
+        let operands = self.preds[block]
+            .clone()
+            .into_iter()
+            .map(|pred| (self.read(var, pred), self.labels[pred]))
+            .collect();
+        self.phis.get_mut(&phi).unwrap().operands = operands;
+    }
+
+    fn seal(&mut self, block: usize) {
+        for phi in self.incomplete.remove(&block).unwrap_or_default() {
+            self.add_phi_operands(phi);
+        }
+        self.sealed[block] = true;
+    }
+
+    /// Remove phis that only ever merge one value (or themselves) until none are left
+    fn remove_trivial_phis(&mut self) {
+        loop {
+            let mut changed = false;
+            let ids = self.phis.keys().cloned().collect::<Vec<_>>();
+            for id in ids {
+                let values = self.phis.get(&id).unwrap().operands
+                    .iter()
+                    .map(|(v, _)| self.resolve(*v))
+                    .filter(|v| *v != id)
+                    .collect::<BTreeSet<_>>();
+                if values.len() <= 1 {
+                    let phi = self.phis.remove(&id).unwrap();
+                    let same = match values.into_iter().next() {
+                        Some(v) => v,
+                        None => {
+                            let ty = *self.types.get(&phi.var).unwrap();
+                            self.undefs.get(ty)
+                        },
+                    };
+                    self.replace.insert(id, same);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+}
+
+fn id_ref(operand: &dr::Operand) -> Option<Word> {
+    if let dr::Operand::IdRef(id) = operand {
+        Some(*id)
+    } else {
+        None
+    }
+}
+
+/// Function variables that are only used as the pointer of loads and stores
+fn promotable(func: &dr::Function) -> BTreeSet<Word> {
+    let mut vars = func.blocks
+        .first()
+        .map(|b| b.instructions.iter())
+        .into_iter()
+        .flatten()
+        .filter(|i| {
+            i.class.opcode == Op::Variable
+                && i.operands.len() == 1
+                && i.operands[0] == dr::Operand::StorageClass(rspirv::spirv::StorageClass::Function)
+        })
+        .map(|i| i.result_id.unwrap())
+        .collect::<BTreeSet<_>>();
+
+    for instruction in func.blocks.iter().flat_map(|b| b.instructions.iter()) {
+        for (idx, operand) in instruction.operands.iter().enumerate() {
+            if let Some(id) = id_ref(operand) {
+                let pointer = idx == 0 && matches!(instruction.class.opcode, Op::Load | Op::Store);
+                if !pointer {
+                    vars.remove(&id);
+                }
+            }
+        }
+    }
+
+    vars
+}
+
+fn successors(block: &dr::Block) -> Vec<Word> {
+    match block.instructions.last() {
+        Some(i) if i.class.opcode == Op::Branch => vec![id_ref(&i.operands[0]).unwrap()],
+        Some(i) if i.class.opcode == Op::BranchConditional => vec![
+            id_ref(&i.operands[1]).unwrap(),
+            id_ref(&i.operands[2]).unwrap(),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the promoted variables and the values that replaced removed loads
+fn promote_function(func: &mut dr::Function, pointers: &HashMap<Word, Word>, undefs: &mut Undefs) -> (BTreeSet<Word>, HashMap<Word, Word>) {
+    let vars = promotable(func);
+    if vars.is_empty() {
+        return (vars, HashMap::new());
+    }
+
+    let types = func.blocks[0].instructions
+        .iter()
+        .filter(|i| vars.contains(&i.result_id.unwrap_or(0)))
+        .map(|i| (i.result_id.unwrap(), *pointers.get(&i.result_type.unwrap()).unwrap()))
+        .collect::<HashMap<_, _>>();
+
+    let labels = func.blocks
+        .iter()
+        .map(|b| b.label.as_ref().unwrap().result_id.unwrap())
+        .collect::<Vec<_>>();
+    let indices = labels.iter()
+        .enumerate()
+        .map(|(i, l)| (*l, i))
+        .collect::<HashMap<_, _>>();
+
+    let mut preds = vec![Vec::new(); func.blocks.len()];
+    for (i, block) in func.blocks.iter().enumerate() {
+        for succ in successors(block) {
+            let succ = *indices.get(&succ).unwrap();
+            if !preds[succ].contains(&i) {
+                preds[succ].push(i);
+            }
+        }
+    }
+
+    let n_blocks = func.blocks.len();
+    let mut p = Promoter {
+        undefs,
+        types,
+        labels,
+        preds,
+        sealed: vec![false; n_blocks],
+        defs: HashMap::new(),
+        incomplete: HashMap::new(),
+        phis: BTreeMap::new(),
+        replace: HashMap::new(),
+    };
+
+    let mut filled = vec![false; n_blocks];
+    let seal_ready = |p: &mut Promoter, filled: &[bool]| {
+        for block in 0..n_blocks {
+            if !p.sealed[block] && p.preds[block].iter().all(|pred| filled[*pred]) {
+                p.seal(block);
+            }
+        }
+    };
+
+    seal_ready(&mut p, &filled);
+    for (idx, block) in func.blocks.iter_mut().enumerate() {
+        let mut instructions = Vec::with_capacity(block.instructions.len());
+        for instruction in block.instructions.drain(..) {
+            let pointer = instruction.operands.first().and_then(id_ref).filter(|id| vars.contains(id));
+            match (instruction.class.opcode, pointer) {
+                (Op::Load, Some(var)) => {
+                    let value = p.read(var, idx);
+                    p.replace.insert(instruction.result_id.unwrap(), value);
+                },
+                (Op::Store, Some(var)) => {
+                    let value = p.resolve(id_ref(&instruction.operands[1]).unwrap());
+                    p.write(var, idx, value);
+                },
+                (Op::Variable, _) if vars.contains(&instruction.result_id.unwrap()) => (),
+                _ => instructions.push(instruction),
+            }
+        }
+        block.instructions = instructions;
+        filled[idx] = true;
+        seal_ready(&mut p, &filled);
+    }
+
+    p.remove_trivial_phis();
+
+    for (id, phi) in std::mem::take(&mut p.phis).into_iter().rev() {
+        let ty = *p.types.get(&phi.var).unwrap();
+        let operands = phi.operands
+            .iter()
+            .flat_map(|(v, l)| [dr::Operand::IdRef(p.resolve(*v)), dr::Operand::IdRef(*l)])
+            .collect();
+        func.blocks[phi.block].instructions.insert(0, dr::Instruction::new(Op::Phi, Some(ty), Some(id), operands));
+    }
+
+    for instruction in func.blocks.iter_mut().flat_map(|b| b.instructions.iter_mut()) {
+        for operand in &mut instruction.operands {
+            if let dr::Operand::IdRef(id) = operand {
+                *id = p.resolve(*id);
+            }
+        }
+    }
+
+    let replaced = p.replace
+        .keys()
+        .map(|id| (*id, p.resolve(*id)))
+        .collect();
+
+    (vars, replaced)
+}
+
+/// Promote the function variables in every function of the module to ssa values
+pub(crate) fn promote(module: &mut dr::Module) {
+    let pointers = module.types_global_values
+        .iter()
+        .filter(|i| i.class.opcode == Op::TypePointer)
+        .map(|i| (i.result_id.unwrap(), id_ref(&i.operands[1]).unwrap()))
+        .collect::<HashMap<_, _>>();
+
+    let mut undefs = Undefs {
+        bound: &mut module.header.as_mut().unwrap().bound,
+        values: HashMap::new(),
+        instructions: Vec::new(),
+    };
+
+    let mut promoted = BTreeSet::new();
+    let mut replaced = HashMap::new();
+    for func in &mut module.functions {
+        let (vars, values) = promote_function(func, &pointers, &mut undefs);
+        promoted.extend(vars);
+        replaced.extend(values);
+    }
+
+    let undefs = undefs.instructions;
+    module.types_global_values.extend(undefs);
+
+    // names of removed variables would reference ids that no longer exist
+    module.debug_names.retain(|i| !i.operands.first().and_then(id_ref).map_or(false, |id| promoted.contains(&id)));
+
+    // the same goes for decorations, decorations on removed loads move to the value that replaced them
+    module.annotations.retain(|i| !i.operands.first().and_then(id_ref).map_or(false, |id| promoted.contains(&id)));
+    for instruction in &mut module.annotations {
+        for operand in &mut instruction.operands {
+            if let dr::Operand::IdRef(id) = operand {
+                if let Some(r) = replaced.get(id) {
+                    *id = *r;
+                }
+            }
+        }
+    }
+    // several loads may have been replaced by the same value
+    let mut annotations = Vec::with_capacity(module.annotations.len());
+    for instruction in module.annotations.drain(..) {
+        if !annotations.contains(&instruction) {
+            annotations.push(instruction);
+        }
+    }
+    module.annotations = annotations;
+}
@@ -0,0 +1,355 @@
+//! Subgroup (wave) operations
+//!
+//! Subgroup operations let invocations of the same subgroup share data without going through shared memory,
+//! for example for prefix sums and stream compaction in compute shaders.
+//!
+//! Vulkan only guarantees subgroup operations in compute shaders, and which classes of operation are supported
+//! depends on the device. Use [`crate::Builder::validate_subgroups`] with the stages and operations reported by the device
+//! before creating the shader module.
+
+use either::*;
+
+use std::rc::Rc;
+use std::cell::RefCell;
+
+#[rustfmt::skip]
+use crate::{
+    AsType,
+    AsTypeConst,
+    SpvRustEq,
+    Bool,
+    Int,
+    UInt,
+    Float,
+    Double,
+    IVec2,
+    IVec3,
+    IVec4,
+    UVec2,
+    UVec3,
+    UVec4,
+    Vec2,
+    Vec3,
+    Vec4,
+    DVec2,
+    DVec3,
+    DVec4,
+};
+
+bitflags::bitflags!(
+    /// Classes of subgroup operation, equivalent to VkSubgroupFeatureFlags
+    pub struct SubgroupOperations: u32 {
+        /// elect, subgroup_size and subgroup_invocation_id
+        const BASIC = 0x1;
+        /// all and any
+        const VOTE = 0x2;
+        /// add, min and max reductions and scans
+        const ARITHMETIC = 0x4;
+        /// ballot, broadcast and ballot bit counts
+        const BALLOT = 0x8;
+        /// shuffle
+        const SHUFFLE = 0x10;
+    }
+);
+
+/// Error returned by [`crate::Builder::validate_subgroups`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubgroupError {
+    /// The entry point for the stage uses subgroup operations but the stage doesn't support them
+    UnsupportedStage(crate::Stage),
+    /// The entry point for the stage uses subgroup operations that aren't supported
+    UnsupportedOperations {
+        stage: crate::Stage,
+        missing: SubgroupOperations,
+    },
+}
+
+impl std::fmt::Display for SubgroupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubgroupError::UnsupportedStage(stage) => {
+                write!(f, "Entry point {:?} uses subgroup operations but the stage doesn't support them", stage)
+            },
+            SubgroupError::UnsupportedOperations { stage, missing } => {
+                write!(f, "Entry point {:?} uses unsupported subgroup operations {:?}", stage, missing)
+            },
+        }
+    }
+}
+
+impl std::error::Error for SubgroupError {}
+
+fn instruction_operations(instruction: &crate::Instruction) -> SubgroupOperations {
+    match instruction {
+        crate::Instruction::Subgroup(o) => o.ty.operations(),
+        crate::Instruction::If(o) => if_operations(o),
+        crate::Instruction::Loop(o) => o.condition_instructions
+            .iter()
+            .chain(o.instructions.iter())
+            .fold(SubgroupOperations::empty(), |acc, i| acc | instruction_operations(i)),
+        _ => SubgroupOperations::empty(),
+    }
+}
+
+fn if_operations(op: &crate::OpIf) -> SubgroupOperations {
+    let mut operations = op.instructions
+        .iter()
+        .fold(SubgroupOperations::empty(), |acc, i| acc | instruction_operations(i));
+    if let Some(then) = &*op.then.borrow() {
+        operations |= match then {
+            Left(t) => if_operations(t),
+            Right(t) => t.instructions
+                .iter()
+                .fold(SubgroupOperations::empty(), |acc, i| acc | instruction_operations(i)),
+        };
+    }
+    operations
+}
+
+impl crate::Builder {
+    /// Get the classes of subgroup operation used by the entry point for stage
+    pub fn get_subgroup_operations(&self, stage: crate::Stage) -> SubgroupOperations {
+        let inner = self.__inner().borrow();
+        let mut operations = match inner.entry_points.get(&stage).and_then(|f| inner.functions.get(f)) {
+            Some(func) => func.instructions
+                .iter()
+                .fold(SubgroupOperations::empty(), |acc, i| acc | instruction_operations(i)),
+            None => return SubgroupOperations::empty(),
+        };
+        if inner.inputs.iter().any(|i| matches!(
+            i.location,
+            Right(rspirv::spirv::BuiltIn::SubgroupSize) | Right(rspirv::spirv::BuiltIn::SubgroupLocalInvocationId)
+        )) {
+            operations |= SubgroupOperations::BASIC;
+        }
+        operations
+    }
+
+    /// Check that every entry point only uses subgroup operations in supported stages and of supported classes
+    ///
+    /// stages and operations should come from the devices subgroup properties (supportedStages and supportedOperations)
+    pub fn validate_subgroups(&self, stages: &[crate::Stage], operations: SubgroupOperations) -> Result<(), SubgroupError> {
+        let entry_points = self.__inner().borrow().entry_points.keys().cloned().collect::<Vec<_>>();
+        for stage in entry_points {
+            let used = self.get_subgroup_operations(stage);
+            if used.is_empty() {
+                continue;
+            }
+            if !stages.contains(&stage) {
+                return Err(SubgroupError::UnsupportedStage(stage));
+            }
+            if !operations.contains(used) {
+                return Err(SubgroupError::UnsupportedOperations {
+                    stage,
+                    missing: used - operations,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true for only one active invocation in the subgroup
+    /// equivalent to the glsl function `subgroupElect`
+    pub fn subgroup_elect<'a>(&'a self) -> Bool<'a> {
+        let id = subgroup_op(self.__inner(), crate::OpSubgroupType::Elect, None, None, crate::Type::BOOL);
+        Bool {
+            id,
+            b: self.__inner(),
+        }
+    }
+}
+
+fn subgroup_op(
+    b: &Rc<RefCell<crate::BuilderInner>>,
+    ty: crate::OpSubgroupType,
+    value: Option<(usize, crate::Type)>,
+    index: Option<Either<u32, &dyn AsType>>,
+    store: crate::Type,
+) -> usize {
+    let mut inner = b.borrow_mut();
+    if let Some(scope) = &mut inner.scope {
+        let new_id = scope.get_new_id();
+
+        let index = index.map(|i| i.map_right(|i| (i.id(&mut **scope), i.ty())));
+
+        scope.push_instruction(crate::Instruction::Subgroup(crate::OpSubgroup {
+            ty,
+            value,
+            index,
+            store: (new_id, store),
+        }));
+
+        new_id
+    } else {
+        panic!("Cannot call subgroup op when not in function")
+    }
+}
+
+macro_rules! impl_subgroup_vote {
+    ($($name:ident,)*) => {
+        $(
+            impl<'a> $name<'a> {
+                /// Returns true if self is true for all active invocations in the subgroup
+                /// equivalent to the glsl function `subgroupAll`
+                pub fn subgroup_all(&self) -> Bool<'a> {
+                    let id = subgroup_op(self.b, crate::OpSubgroupType::All, Some((self.id, <Self as AsTypeConst>::TY)), None, crate::Type::BOOL);
+                    Bool {
+                        id,
+                        b: self.b,
+                    }
+                }
+
+                /// Returns true if self is true for any active invocation in the subgroup
+                /// equivalent to the glsl function `subgroupAny`
+                pub fn subgroup_any(&self) -> Bool<'a> {
+                    let id = subgroup_op(self.b, crate::OpSubgroupType::Any, Some((self.id, <Self as AsTypeConst>::TY)), None, crate::Type::BOOL);
+                    Bool {
+                        id,
+                        b: self.b,
+                    }
+                }
+
+                /// Returns a bitfield with a bit set for each active invocation in the subgroup where self is true
+                /// equivalent to the glsl function `subgroupBallot`
+                pub fn subgroup_ballot(&self) -> UVec4<'a> {
+                    let id = subgroup_op(self.b, crate::OpSubgroupType::Ballot, Some((self.id, <Self as AsTypeConst>::TY)), None, crate::Type::UVEC4);
+                    UVec4 {
+                        id,
+                        b: self.b,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+#[rustfmt::skip]
+impl_subgroup_vote!(
+    Bool,
+);
+
+macro_rules! impl_subgroup_ballot_count {
+    ($($name:ident, $f:ident, $op:ident,)*) => {
+        $(
+            impl<'a> $name<'a> {
+                /// Count the bits set in a ballot, see [`Bool::subgroup_ballot`]
+                /// equivalent to the glsl functions `subgroupBallotBitCount`, `subgroupBallotInclusiveBitCount` and `subgroupBallotExclusiveBitCount`
+                pub fn $f(&self) -> UInt<'a> {
+                    let id = subgroup_op(
+                        self.b,
+                        crate::OpSubgroupType::BallotBitCount(rspirv::spirv::GroupOperation::$op),
+                        Some((self.id, <Self as AsTypeConst>::TY)),
+                        None,
+                        crate::Type::UINT,
+                    );
+                    UInt {
+                        id,
+                        b: self.b,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+#[rustfmt::skip]
+impl_subgroup_ballot_count!(
+    UVec4, subgroup_ballot_bit_count, Reduce,
+    UVec4, subgroup_ballot_inclusive_bit_count, InclusiveScan,
+    UVec4, subgroup_ballot_exclusive_bit_count, ExclusiveScan,
+);
+
+macro_rules! impl_subgroup_share {
+    ($($name:ident,)*) => {
+        $(
+            impl<'a> $name<'a> {
+                /// Get the value of self from the invocation with index lane in the subgroup
+                /// equivalent to the glsl function `subgroupBroadcast`
+                pub fn subgroup_broadcast(&self, lane: u32) -> $name<'a> {
+                    let id = subgroup_op(self.b, crate::OpSubgroupType::Broadcast, Some((self.id, <Self as AsTypeConst>::TY)), Some(Left(lane)), <Self as AsTypeConst>::TY);
+                    $name {
+                        id,
+                        b: self.b,
+                    }
+                }
+
+                /// Get the value of self from the active invocation with the lowest index in the subgroup
+                /// equivalent to the glsl function `subgroupBroadcastFirst`
+                pub fn subgroup_broadcast_first(&self) -> $name<'a> {
+                    let id = subgroup_op(self.b, crate::OpSubgroupType::BroadcastFirst, Some((self.id, <Self as AsTypeConst>::TY)), None, <Self as AsTypeConst>::TY);
+                    $name {
+                        id,
+                        b: self.b,
+                    }
+                }
+
+                /// Get the value of self from the invocation with index lane in the subgroup, lane doesn't have to be uniform
+                /// equivalent to the glsl function `subgroupShuffle`
+                pub fn subgroup_shuffle(&self, lane: impl SpvRustEq<UInt<'a>>) -> $name<'a> {
+                    let id = subgroup_op(self.b, crate::OpSubgroupType::Shuffle, Some((self.id, <Self as AsTypeConst>::TY)), Some(Right(lane.as_ty())), <Self as AsTypeConst>::TY);
+                    $name {
+                        id,
+                        b: self.b,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+#[rustfmt::skip]
+impl_subgroup_share!(
+    Int, UInt, Float, Double,
+    IVec2, IVec3, IVec4,
+    UVec2, UVec3, UVec4,
+    Vec2, Vec3, Vec4,
+    DVec2, DVec3, DVec4,
+);
+
+macro_rules! impl_subgroup_arithmetic {
+    ($($name:ident, $f:ident, $op:ident, $group_op:ident,)*) => {
+        $(
+            impl<'a> $name<'a> {
+                /// see [`crate::subgroup`], equivalent to the glsl functions `subgroupAdd`, `subgroupInclusiveAdd`,
+                /// `subgroupExclusiveAdd`, `subgroupMin` and `subgroupMax`
+                pub fn $f(&self) -> $name<'a> {
+                    let id = subgroup_op(
+                        self.b,
+                        crate::OpSubgroupType::$op(rspirv::spirv::GroupOperation::$group_op),
+                        Some((self.id, <Self as AsTypeConst>::TY)),
+                        None,
+                        <Self as AsTypeConst>::TY,
+                    );
+                    $name {
+                        id,
+                        b: self.b,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_subgroup_arithmetics {
+    ($($name:ident,)*) => {
+        $(
+            #[rustfmt::skip]
+            impl_subgroup_arithmetic!(
+                $name, subgroup_add, Add, Reduce,
+                $name, subgroup_inclusive_add, Add, InclusiveScan,
+                $name, subgroup_exclusive_add, Add, ExclusiveScan,
+                $name, subgroup_min, Min, Reduce,
+                $name, subgroup_max, Max, Reduce,
+            );
+        )*
+    };
+}
+
+#[rustfmt::skip]
+impl_subgroup_arithmetics!(
+    Int, UInt, Float, Double,
+    IVec2, IVec3, IVec4,
+    UVec2, UVec3, UVec4,
+    Vec2, Vec3, Vec4,
+    DVec2, DVec3, DVec4,
+);
@@ -0,0 +1,102 @@
+//! Validation of compiled modules with spirv-tools
+//!
+//! Enabled with the `validate` feature, every module assembled in debug builds is passed to spirv-val and
+//! failures panic with the instruction the validator pointed at so that malformed modules are caught at
+//! compile time rather than when the driver consumes them.
+
+use rspirv::grammar::CoreInstructionTable;
+use rspirv::grammar::OperandKind;
+
+use spirv_tools::val::Validator;
+
+use std::collections::HashMap;
+
+/// Length of the spir-v header in words
+const HEADER_LEN: usize = 5;
+
+/// Description of the instruction at a word offset in an assembled module
+struct Located {
+    /// index of the instruction within the module
+    index: usize,
+    opname: &'static str,
+    result: Option<u32>,
+}
+
+/// Find the instruction that contains the word at `offset`
+fn locate(spv: &[u32], offset: usize) -> Option<Located> {
+    let mut word = HEADER_LEN;
+    let mut index = 0;
+    while word < spv.len() {
+        let count = (spv[word] >> 16) as usize;
+        let opcode = (spv[word] & 0xffff) as u16;
+        if count == 0 {
+            return None;
+        }
+        if offset < word + count {
+            let instruction = CoreInstructionTable::lookup_opcode(opcode)?;
+            let result = instruction.operands
+                .iter()
+                .take(2)
+                .position(|o| o.kind == OperandKind::IdResult)
+                .and_then(|i| spv.get(word + 1 + i).cloned());
+            return Some(Located {
+                index,
+                opname: instruction.opname,
+                result,
+            });
+        }
+        word += count;
+        index += 1;
+    }
+    None
+}
+
+/// Collect the names given to ids by OpName
+fn names(spv: &[u32]) -> HashMap<u32, String> {
+    let mut names = HashMap::new();
+    let mut word = HEADER_LEN;
+    while word < spv.len() {
+        let count = (spv[word] >> 16) as usize;
+        if count == 0 {
+            break;
+        }
+        if spv[word] & 0xffff == rspirv::spirv::Op::Name as u32 && count > 2 {
+            let bytes = spv[word + 2..word + count]
+                .iter()
+                .flat_map(|w| w.to_le_bytes())
+                .take_while(|b| *b != 0)
+                .collect::<Vec<_>>();
+            names.insert(spv[word + 1], String::from_utf8_lossy(&bytes).into_owned());
+        }
+        word += count;
+    }
+    names
+}
+
+/// Run spirv-val on the module and panic describing the failure if it is invalid
+pub(crate) fn validate(spv: &[u32]) {
+    let validator = spirv_tools::val::create(None);
+    let err = match validator.validate(spv, None) {
+        Ok(_) => return,
+        Err(e) => e,
+    };
+
+    let located = err.diagnostic
+        .as_ref()
+        .filter(|d| d.index != 0)
+        .and_then(|d| locate(spv, d.index));
+
+    match located {
+        Some(l) => {
+            let name = l.result
+                .and_then(|id| names(spv).remove(&id))
+                .map(|n| format!(" \"{}\"", n))
+                .unwrap_or_default();
+            let result = l.result
+                .map(|id| format!(" %{}{}", id, name))
+                .unwrap_or_default();
+            panic!("Compiled module failed validation at instruction {} (Op{}{}): {}", l.index, l.opname, result, err)
+        },
+        None => panic!("Compiled module failed validation: {}", err),
+    }
+}
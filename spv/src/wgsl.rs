@@ -0,0 +1,641 @@
+//! Lowering of the builder's instructions to wgsl source
+//!
+//! Inputs and outputs are module scope private variables, each entry point is wrapped in a function that copies the
+//! stage inputs into them before calling it and returns the outputs after. Wgsl has no combined image samplers so
+//! only separate textures and samplers can be written.
+
+use either::*;
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+
+use crate::backend::*;
+use crate::Instruction;
+use crate::OpLoadStoreData;
+use crate::ScalarType;
+use crate::ScalarVal;
+use crate::Type;
+use crate::Val;
+
+fn scalar_ty(ty: ScalarType) -> &'static str {
+    match ty {
+        ScalarType::Bool => "bool",
+        ScalarType::Signed(_) => "i32",
+        ScalarType::Unsigned(_) => "u32",
+        ScalarType::Float(64) => "f64",
+        ScalarType::Float(_) => "f32",
+    }
+}
+
+fn texture_ty(ty: &crate::TextureType) -> String {
+    let dim = match ty.dimension {
+        crate::TextureDimension::D1 => "1d",
+        crate::TextureDimension::D1Array => "1d_array",
+        crate::TextureDimension::D2 => "2d",
+        crate::TextureDimension::D2Ms => "2d",
+        crate::TextureDimension::D2Array => "2d_array",
+        crate::TextureDimension::D2MsArray => "2d_array",
+        crate::TextureDimension::Cube => "cube",
+        crate::TextureDimension::CubeArray => "cube_array",
+        crate::TextureDimension::D3 => "3d",
+    };
+    let ms = if ty.dimension.ms() { "multisampled_" } else { "" };
+    match ty.format {
+        crate::TextureSpvFormat::Color(f) => format!("texture_storage_{}<{}, read_write>", dim, format!("{:?}", f).to_lowercase()),
+        crate::TextureSpvFormat::Depth => format!("texture_depth_{}{}", ms, dim),
+        crate::TextureSpvFormat::Sampled => format!("texture_{}{}<{}>", ms, dim, scalar_ty(ty.scalar_ty)),
+    }
+}
+
+/// The name and type of the wgsl built in value
+fn built_in(built_in: rspirv::spirv::BuiltIn) -> (&'static str, &'static str) {
+    use rspirv::spirv::BuiltIn::*;
+
+    match built_in {
+        VertexIndex => ("vertex_index", "u32"),
+        InstanceIndex => ("instance_index", "u32"),
+        Position | FragCoord => ("position", "vec4<f32>"),
+        FrontFacing => ("front_facing", "bool"),
+        FragDepth => ("frag_depth", "f32"),
+        SampleId => ("sample_index", "u32"),
+        SampleMask => ("sample_mask", "u32"),
+        PrimitiveId => ("primitive_index", "u32"),
+        LocalInvocationId => ("local_invocation_id", "vec3<u32>"),
+        LocalInvocationIndex => ("local_invocation_index", "u32"),
+        GlobalInvocationId => ("global_invocation_id", "vec3<u32>"),
+        WorkgroupId => ("workgroup_id", "vec3<u32>"),
+        NumWorkgroups => ("num_workgroups", "vec3<u32>"),
+        SubgroupSize => ("subgroup_size", "u32"),
+        SubgroupLocalInvocationId => ("subgroup_invocation_id", "u32"),
+        b => panic!("Cannot write built in {:?} as wgsl", b),
+    }
+}
+
+fn scalar_literal(val: &ScalarVal) -> String {
+    match val {
+        ScalarVal::Bool(b) => b.to_string(),
+        ScalarVal::Int(i) => format!("{}i", i),
+        ScalarVal::UInt(u) => format!("{}u", u),
+        ScalarVal::Float(f) => format!("{:?}f", f),
+        ScalarVal::Double(d) => format!("{:?}", d),
+    }
+}
+
+struct Writer<'a> {
+    inner: &'a crate::BuilderInner,
+    src: String,
+    indent: usize,
+    extensions: BTreeSet<&'static str>,
+    /// struct definitions in the order they have to be declared
+    structs: Vec<String>,
+    struct_names: HashMap<crate::StructType, String>,
+    /// combined samplers are written as the texture and sampler arguments where they're used
+    sampled: HashMap<usize, String>,
+}
+
+impl<'a> Writer<'a> {
+    fn line(&mut self, line: impl AsRef<str>) {
+        for _ in 0..self.indent {
+            self.src.push_str("    ");
+        }
+        self.src.push_str(line.as_ref());
+        self.src.push('\n');
+    }
+
+    fn ty(&mut self, ty: &Type) -> String {
+        match ty {
+            Type::Void => unreachable!(),
+            Type::Scalar(s) => scalar_ty(*s).to_owned(),
+            Type::Vector(v) => format!("vec{}<{}>", v.n_scalar, scalar_ty(v.scalar_ty)),
+            Type::Matrix(m) => format!("mat{}x{}<{}>", m.n_vec, m.vec_ty.n_scalar, scalar_ty(m.vec_ty.scalar_ty)),
+            Type::Array(a) => {
+                let element = self.ty(&a.element_ty);
+                match a.length {
+                    Some(length) => format!("array<{}, {}>", element, length),
+                    None => format!("array<{}>", element),
+                }
+            },
+            Type::Struct(s) => self.struct_name(s),
+            Type::Texture(t) => texture_ty(t),
+        }
+    }
+
+    fn struct_name(&mut self, s: &crate::StructType) -> String {
+        if let Some(name) = self.struct_names.get(s) {
+            return name.clone();
+        }
+        // declare the member types first
+        let members = s.members
+            .iter()
+            .enumerate()
+            .map(|(i, m)| format!("    {}: {},\n", struct_member_name(s, i as u32), self.ty(&m.ty)))
+            .collect::<String>();
+        let name = match &s.name {
+            Some(name) => either_str(name).to_owned(),
+            None => format!("Struct{}", self.structs.len()),
+        };
+        self.structs.push(format!("struct {} {{\n{}}}\n", name, members));
+        self.struct_names.insert(s.clone(), name.clone());
+        name
+    }
+
+    fn literal(&mut self, val: &Val) -> String {
+        if let Val::Scalar(s) = val {
+            return scalar_literal(s);
+        }
+        let ty = self.ty(&val.ty());
+        format!("{}({})", ty, val_scalars(val).iter().map(scalar_literal).collect::<Vec<_>>().join(", "))
+    }
+
+    fn var(&self, id: usize) -> String {
+        self.sampled.get(&id).cloned().unwrap_or_else(|| format!("v{}", id))
+    }
+
+    fn assign(&mut self, id: usize, expr: String) {
+        let var = self.var(id);
+        self.line(format!("{} = {};", var, expr));
+    }
+
+    /// the name of the private variable that holds the io
+    fn io_name(io: &crate::IOData, prefix: &str, idx: usize) -> String {
+        io.name.map(str::to_owned).unwrap_or_else(|| format!("{}_{}", prefix, idx))
+    }
+
+    fn uniform_name(&self, id: usize) -> String {
+        self.inner.uniforms[id].name.map(str::to_owned).unwrap_or_else(|| format!("uniform_{}", id))
+    }
+
+    fn storage_name(&self, id: usize) -> String {
+        self.inner.storages[id].name.map(str::to_owned).unwrap_or_else(|| format!("storage_{}", id))
+    }
+
+    fn texture_name(&self, id: usize) -> String {
+        self.inner.textures[id].name.map(str::to_owned).unwrap_or_else(|| format!("texture_{}", id))
+    }
+
+    fn sampler_name(&self, id: usize) -> String {
+        self.inner.samplers[id].name.map(str::to_owned).unwrap_or_else(|| format!("sampler_{}", id))
+    }
+
+    fn push_constants_name(&self) -> String {
+        self.inner.push_constants
+            .as_ref()
+            .and_then(|p| p.name)
+            .unwrap_or("push_constants")
+            .to_owned()
+    }
+
+    fn func_name(&self, id: usize) -> String {
+        let name = self.inner.functions[&id].name.map(str::to_owned).unwrap_or_else(|| format!("func_{}", id));
+        // the entry point name is taken by the wrapper
+        if self.inner.entry_points.values().any(|e| *e == id) {
+            format!("{}_impl", name)
+        } else {
+            name
+        }
+    }
+
+    fn data(&self, data: &OpLoadStoreData) -> String {
+        match data {
+            OpLoadStoreData::Input { location } => Self::io_name(&self.inner.inputs[*location], "in", *location),
+            OpLoadStoreData::Output { location } => Self::io_name(&self.inner.outputs[*location], "out", *location),
+            OpLoadStoreData::UniformField { field, id }
+            | OpLoadStoreData::UniformMember { field, id } => {
+                format!("{}.{}", self.uniform_name(*id), member_name(&self.inner.uniforms[*id].ty, *field))
+            },
+            OpLoadStoreData::Uniform { id } => {
+                let uniform = &self.inner.uniforms[*id];
+                if uniform.block {
+                    format!("{}.{}", self.uniform_name(*id), member_name(&uniform.ty, 0))
+                } else {
+                    self.uniform_name(*id)
+                }
+            },
+            OpLoadStoreData::Storage { id } => self.storage_name(*id),
+            OpLoadStoreData::StorageElement { id, element } => format!("{}[{}]", self.storage_name(*id), self.var(element.0)),
+            OpLoadStoreData::StorageElementField { id, element, field } => format!(
+                "{}[{}].{}",
+                self.storage_name(*id),
+                self.var(element.0),
+                member_name(&self.inner.storages[*id].ty, *field),
+            ),
+            OpLoadStoreData::Variable { id } => self.var(*id),
+            OpLoadStoreData::Struct { id, struct_ty, field } => format!("{}.{}", self.var(*id), struct_member_name(struct_ty, *field)),
+            OpLoadStoreData::ArrayElement { id, index, .. } => format!("{}[{}]", self.var(*id), self.var(index.0)),
+            OpLoadStoreData::PushConstant => self.push_constants_name(),
+            OpLoadStoreData::PushConstantField { field } => format!(
+                "{}.{}",
+                self.push_constants_name(),
+                member_name(&self.inner.push_constants.as_ref().unwrap().ty, *field),
+            ),
+        }
+    }
+
+    fn declarations(&mut self) {
+        for (idx, io) in self.inner.inputs.iter().enumerate() {
+            let ty = self.ty(&io.ty.ty());
+            self.line(format!("var<private> {}: {};", Self::io_name(io, "in", idx), ty));
+        }
+
+        for (idx, io) in self.inner.outputs.iter().enumerate() {
+            let ty = self.ty(&io.ty.ty());
+            self.line(format!("var<private> {}: {};", Self::io_name(io, "out", idx), ty));
+        }
+
+        if let Some(p) = &self.inner.push_constants {
+            let ty = self.ty(&p.ty);
+            self.line(format!("var<push_constant> {}: {};", self.push_constants_name(), ty));
+        }
+
+        for (idx, u) in self.inner.uniforms.iter().enumerate() {
+            let ty = self.ty(&u.ty);
+            self.line(format!("@group({}) @binding({}) var<uniform> {}: {};", u.set, u.binding, self.uniform_name(idx), ty));
+        }
+
+        for (idx, s) in self.inner.storages.iter().enumerate() {
+            let ty = self.ty(&s.ty);
+            // wgsl doesn't have write only storage buffers
+            let access = if s.write { "read_write" } else { "read" };
+            self.line(format!("@group({}) @binding({}) var<storage, {}> {}: array<{}>;", s.set, s.binding, access, self.storage_name(idx), ty));
+        }
+
+        for (idx, t) in self.inner.textures.iter().enumerate() {
+            self.line(format!("@group({}) @binding({}) var {}: {};", t.set, t.binding, self.texture_name(idx), texture_ty(&t.ty)));
+        }
+
+        if let Some(t) = self.inner.sampled_textures.first() {
+            panic!("Cannot write sampled texture {:?} as wgsl, use a separate texture and sampler", t.name);
+        }
+
+        for (idx, s) in self.inner.samplers.iter().enumerate() {
+            self.line(format!("@group({}) @binding({}) var {}: sampler;", s.set, s.binding, self.sampler_name(idx)));
+        }
+    }
+
+    fn function(&mut self, id: usize, func: &crate::FuncData) {
+        self.sampled.clear();
+
+        let mut vars = BTreeMap::new();
+        collect_vars(&func.instructions, &mut vars);
+
+        let ret = match func.ret {
+            Type::Void => String::new(),
+            _ => format!(" -> {}", self.ty(&func.ret)),
+        };
+        let arguments = func.arguments
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| format!("a{}: {}", i, self.ty(ty)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.line(format!("fn {}({}){} {{", self.func_name(id), arguments, ret));
+        self.indent += 1;
+        for (id, ty) in &vars {
+            let ty = self.ty(ty);
+            self.line(format!("var v{}: {};", id, ty));
+        }
+        if !vars.is_empty() {
+            self.src.push('\n');
+        }
+        self.instructions(&func.instructions);
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    /// Write the function called by the pipeline that copies the stage io to and from the private variables
+    fn entry_point(&mut self, stage: crate::Stage, id: usize) {
+        let name = self.inner.functions[&id].name.unwrap();
+        let (inputs, outputs) = self.inner.used_io(id);
+
+        let mut input_members = Vec::new();
+        let mut copy_inputs = Vec::new();
+        for idx in inputs {
+            let io = &self.inner.inputs[idx];
+            let var = Self::io_name(io, "in", idx);
+            let (attribute, ty) = match io.location {
+                Left(location) => {
+                    let flat = if io.flat { " @interpolate(flat)" } else { "" };
+                    (format!("@location({}){}", location, flat), self.ty(&io.ty.ty()))
+                },
+                Right(b) => {
+                    let (b, ty) = built_in(b);
+                    if b.starts_with("subgroup") {
+                        self.extensions.insert("subgroups");
+                    }
+                    (format!("@builtin({})", b), ty.to_owned())
+                },
+            };
+            input_members.push(format!("    {} {}: {},\n", attribute, var, ty));
+            let io_ty = self.ty(&io.ty.ty());
+            copy_inputs.push(format!("{} = {}(input.{});", var, io_ty, var));
+        }
+
+        let mut output_members = Vec::new();
+        let mut copy_outputs = Vec::new();
+        for idx in outputs {
+            let io = &self.inner.outputs[idx];
+            let var = Self::io_name(io, "out", idx);
+            let (attribute, ty) = match io.location {
+                Left(location) => {
+                    let flat = if io.flat { " @interpolate(flat)" } else { "" };
+                    (format!("@location({}){}", location, flat), self.ty(&io.ty.ty()))
+                },
+                Right(b) => {
+                    let (b, ty) = built_in(b);
+                    (format!("@builtin({})", b), ty.to_owned())
+                },
+            };
+            output_members.push(format!("    {} {}: {},\n", attribute, var, ty));
+            copy_outputs.push(format!("{}({})", ty, var));
+        }
+
+        let mut arguments = String::new();
+        if !input_members.is_empty() {
+            self.structs.push(format!("struct {}_Input {{\n{}}}\n", name, input_members.concat()));
+            arguments = format!("input: {}_Input", name);
+        }
+        let mut ret = String::new();
+        if !output_members.is_empty() {
+            self.structs.push(format!("struct {}_Output {{\n{}}}\n", name, output_members.concat()));
+            ret = format!(" -> {}_Output", name);
+        }
+
+        match stage {
+            crate::Stage::Vertex => self.line("@vertex"),
+            crate::Stage::Fragment => self.line("@fragment"),
+            crate::Stage::Compute => {
+                let [x, y, z] = self.inner.local_size;
+                self.line(format!("@compute @workgroup_size({}, {}, {})", x, y, z));
+            },
+            _ => panic!("Cannot write {:?} entry point as wgsl", stage),
+        }
+        self.line(format!("fn {}({}){} {{", name, arguments, ret));
+        self.indent += 1;
+        for copy in copy_inputs {
+            self.line(copy);
+        }
+        self.line(format!("{}();", self.func_name(id)));
+        if !copy_outputs.is_empty() {
+            self.line(format!("return {}_Output({});", name, copy_outputs.join(", ")));
+        }
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn instructions(&mut self, instructions: &[Instruction]) {
+        for instruction in instructions {
+            self.instruction(instruction);
+        }
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) {
+        match instruction {
+            Instruction::LhsRhs(o) => {
+                let (l, r) = (self.var(o.lhs.0), self.var(o.rhs.0));
+                let op = match o.ty {
+                    crate::OpLhsRhsType::Add => "+",
+                    crate::OpLhsRhsType::Sub => "-",
+                    crate::OpLhsRhsType::Mul => "*",
+                    crate::OpLhsRhsType::Div => "/",
+                    crate::OpLhsRhsType::BitAnd => "&",
+                    crate::OpLhsRhsType::BitOr => "|",
+                    crate::OpLhsRhsType::BitXor => "^",
+                    crate::OpLhsRhsType::LogicalAnd => "&&",
+                    crate::OpLhsRhsType::LogicalOr => "||",
+                    crate::OpLhsRhsType::LogicalEqual => "==",
+                    crate::OpLhsRhsType::LogicalNotEqual => "!=",
+                    crate::OpLhsRhsType::Cross => return self.assign(o.store.0, format!("cross({}, {})", l, r)),
+                    crate::OpLhsRhsType::Dot => return self.assign(o.store.0, format!("dot({}, {})", l, r)),
+                };
+                self.assign(o.store.0, format!("{} {} {}", l, op, r));
+            },
+            Instruction::Lhs(o) => {
+                let l = self.var(o.lhs.0);
+                let f = match o.ty {
+                    crate::OpLhsType::LogicalNot => return self.assign(o.store.0, format!("!{}", l)),
+                    crate::OpLhsType::Normalize => "normalize",
+                    crate::OpLhsType::Length => "length",
+                    crate::OpLhsType::Exp => "exp",
+                    crate::OpLhsType::Exp2 => "exp2",
+                    crate::OpLhsType::Sin => "sin",
+                    crate::OpLhsType::Cos => "cos",
+                    crate::OpLhsType::Tan => "tan",
+                    crate::OpLhsType::ASin => "asin",
+                    crate::OpLhsType::ACos => "acos",
+                    crate::OpLhsType::ATan => "atan",
+                };
+                self.assign(o.store.0, format!("{}({})", f, l));
+            },
+            Instruction::VectorShuffle(o) => {
+                let swizzle = o.components
+                    .iter()
+                    .take(o.dst.1.n_scalar as usize)
+                    .map(|c| ['x', 'y', 'z', 'w'][*c as usize])
+                    .collect::<String>();
+                self.assign(o.dst.0, format!("{}.{}", self.var(o.src.0), swizzle));
+            },
+            Instruction::LoadStore(o) => {
+                let (dst, src) = (self.data(&o.dst), self.data(&o.src));
+                self.line(format!("{} = {};", dst, src));
+            },
+            Instruction::FuncCall(o) => {
+                let call = format!(
+                    "{}({})",
+                    self.func_name(o.func),
+                    o.args.iter().map(|(id, _)| self.var(*id)).collect::<Vec<_>>().join(", "),
+                );
+                if let Type::Void = o.store_ty {
+                    self.line(format!("{};", call));
+                } else {
+                    self.assign(o.store, call);
+                }
+            },
+            Instruction::Argument(o) => self.assign(o.store, format!("a{}", o.index)),
+            Instruction::ReturnValue(o) => {
+                let value = self.var(o.value.0);
+                self.line(format!("return {};", value));
+            },
+            Instruction::SetConst(o) => {
+                let literal = self.literal(&o.val);
+                self.assign(o.store, literal);
+            },
+            Instruction::Cmp(o) => {
+                let (l, r) = (self.var(o.lhs.0), self.var(o.rhs.0));
+                let op = match o.cmp {
+                    crate::CmpType::Eq => "==",
+                    crate::CmpType::NEq => "!=",
+                    crate::CmpType::Lt => "<",
+                    crate::CmpType::Gt => ">",
+                    crate::CmpType::Le => "<=",
+                    crate::CmpType::Ge => ">=",
+                };
+                // vector comparisons are component wise
+                let expr = match (&o.lhs.1, &o.cmp) {
+                    (Type::Vector(_), crate::CmpType::NEq) => format!("any({} != {})", l, r),
+                    (Type::Vector(_), _) => format!("all({} {} {})", l, op, r),
+                    _ => format!("{} {} {}", l, op, r),
+                };
+                self.assign(o.store, expr);
+            },
+            Instruction::Select(o) => {
+                let expr = format!("select({}, {}, {})", self.var(o.b), self.var(o.a), self.var(o.condition));
+                self.assign(o.store, expr);
+            },
+            Instruction::Composite(o) => {
+                let ty = self.ty(&o.ty);
+                let constituents = o.constituents.iter().map(|(id, _)| self.var(*id)).collect::<Vec<_>>().join(", ");
+                self.assign(o.id, format!("{}({})", ty, constituents));
+            },
+            Instruction::Extract(o) => {
+                let src = self.var(o.src_id);
+                let expr = match &o.src_ty {
+                    Type::Struct(_) => format!("{}.{}", src, member_name(&o.src_ty, o.element_idx)),
+                    _ => format!("{}[{}]", src, o.element_idx),
+                };
+                self.assign(o.store_id, expr);
+            },
+            Instruction::Sample(o) => {
+                let texture = match o.sampled_texture {
+                    Left(_) => unreachable!(),
+                    Right(id) => self.var(id),
+                };
+                let coordinate = self.var(o.coordinate.0);
+                let expr = if o.explict_lod {
+                    format!("textureSampleLevel({}, {}, 0.0)", texture, coordinate)
+                } else {
+                    format!("textureSample({}, {})", texture, coordinate)
+                };
+                self.assign(o.store.0, expr);
+            },
+            Instruction::Combine(o) => {
+                let expr = format!("{}, {}", self.texture_name(o.texture), self.sampler_name(o.sampler));
+                self.sampled.insert(o.store, expr);
+            },
+            // sampled textures are rejected when declaring them
+            Instruction::SampledTextureElement(_) => unreachable!(),
+            Instruction::Convert(o) => {
+                let ty = self.ty(&o.dst.1);
+                self.assign(o.dst.0, format!("{}({})", ty, self.var(o.src.0)));
+            },
+            Instruction::If(o) => self.if_chain(o, false),
+            // wgsl doesn't have loop control attributes
+            Instruction::Loop(o) => {
+                self.line("loop {");
+                self.indent += 1;
+                self.instructions(&o.condition_instructions);
+                let condition = self.var(o.condition);
+                self.line(format!("if !{} {{ break; }}", condition));
+                self.instructions(&o.instructions);
+                self.indent -= 1;
+                self.line("}");
+            },
+            Instruction::Subgroup(o) => {
+                let value = o.value.as_ref().map(|(id, _)| self.var(*id)).unwrap_or_default();
+                let index = match &o.index {
+                    Some(Left(lane)) => format!("{}u", lane),
+                    Some(Right((id, _))) => self.var(*id),
+                    None => String::new(),
+                };
+                let scan = |op: &rspirv::spirv::GroupOperation| match op {
+                    rspirv::spirv::GroupOperation::Reduce => "",
+                    rspirv::spirv::GroupOperation::InclusiveScan => "Inclusive",
+                    rspirv::spirv::GroupOperation::ExclusiveScan => "Exclusive",
+                    op => panic!("Cannot write subgroup operation {:?} as wgsl", op),
+                };
+                let expr = match &o.ty {
+                    crate::OpSubgroupType::Elect => "subgroupElect()".to_owned(),
+                    crate::OpSubgroupType::All => format!("subgroupAll({})", value),
+                    crate::OpSubgroupType::Any => format!("subgroupAny({})", value),
+                    crate::OpSubgroupType::Ballot => format!("subgroupBallot({})", value),
+                    crate::OpSubgroupType::BallotBitCount(rspirv::spirv::GroupOperation::Reduce) => {
+                        format!("dot(countOneBits({}), vec4<u32>(1u))", value)
+                    },
+                    crate::OpSubgroupType::BallotBitCount(op) => panic!("Cannot write subgroup ballot bit count {:?} as wgsl", op),
+                    crate::OpSubgroupType::Broadcast => format!("subgroupBroadcast({}, {})", value, index),
+                    crate::OpSubgroupType::BroadcastFirst => format!("subgroupBroadcastFirst({})", value),
+                    crate::OpSubgroupType::Shuffle => format!("subgroupShuffle({}, {})", value, index),
+                    crate::OpSubgroupType::Add(op) => format!("subgroup{}Add({})", scan(op), value),
+                    crate::OpSubgroupType::Min(op) => format!("subgroup{}Min({})", scan(op), value),
+                    crate::OpSubgroupType::Max(op) => format!("subgroup{}Max({})", scan(op), value),
+                };
+                self.extensions.insert("subgroups");
+                self.assign(o.store.0, expr);
+            },
+            Instruction::Return => self.line("return;"),
+            Instruction::Discard => self.line("discard;"),
+            Instruction::Continue => self.line("continue;"),
+            Instruction::Break => self.line("break;"),
+        }
+    }
+
+    fn if_chain(&mut self, op: &crate::OpIf, else_if: bool) {
+        let condition = self.var(op.condition);
+        if else_if {
+            self.line(format!("}} else if {} {{", condition));
+        } else {
+            self.line(format!("if {} {{", condition));
+        }
+        self.indent += 1;
+        self.instructions(&op.instructions);
+        self.indent -= 1;
+        match &*op.then.borrow() {
+            Some(Left(t)) => self.if_chain(t, true),
+            Some(Right(t)) => {
+                self.line("} else {");
+                self.indent += 1;
+                self.instructions(&t.instructions);
+                self.indent -= 1;
+                self.line("}");
+            },
+            None => self.line("}"),
+        }
+    }
+}
+
+impl crate::BuilderInner {
+    /// Write every function and entry point as wgsl
+    pub(crate) fn to_wgsl(&self) -> String {
+        let mut w = Writer {
+            inner: self,
+            src: String::new(),
+            indent: 0,
+            extensions: BTreeSet::new(),
+            structs: Vec::new(),
+            struct_names: HashMap::new(),
+            sampled: HashMap::new(),
+        };
+
+        w.declarations();
+        let declarations = std::mem::take(&mut w.src);
+
+        let mut ids = self.functions.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        for id in ids {
+            w.src.push('\n');
+            w.function(id, &self.functions[&id]);
+        }
+
+        let mut entry_points = self.entry_points.iter().map(|(s, f)| (*f, *s)).collect::<Vec<_>>();
+        entry_points.sort_by_key(|(f, _)| *f);
+        for (id, stage) in entry_points {
+            w.src.push('\n');
+            w.entry_point(stage, id);
+        }
+
+        let mut src = String::new();
+        for extension in &w.extensions {
+            src.push_str(&format!("enable {};\n", extension));
+        }
+        if !w.extensions.is_empty() {
+            src.push('\n');
+        }
+        for s in &w.structs {
+            src.push_str(s);
+            src.push('\n');
+        }
+        src.push_str(&declarations);
+        src.push_str(&w.src);
+        src
+    }
+}
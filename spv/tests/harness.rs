@@ -0,0 +1,57 @@
+//! Modules executed on the device to check their results, needs a vulkan device to run
+#![cfg(feature = "harness")]
+
+#[test]
+fn double_storage() {
+    let harness = spv::harness::Harness::new(gpu::DeviceFeatures::BASE).unwrap();
+
+    for ssa in [false, true] {
+        let b = spv::Builder::new();
+        b.ssa(ssa);
+
+        let data = b.storage::<spv::Float>(0, 0, Some("data"));
+        let id = b.global_invocation_id();
+
+        b.entry(spv::Stage::Compute, "main", || {
+            let i = spv::Int::from(id.load().x());
+            let x = data.load_element(i);
+            data.store_element(i, x * 2.0);
+        });
+
+        let result = harness.run(&b, &[1.0f32, 2.0, 3.0, 4.0], [4, 1, 1]).unwrap();
+        assert_eq!(result, [2.0, 4.0, 6.0, 8.0]);
+    }
+}
+
+#[test]
+fn import_call() {
+    let harness = spv::harness::Harness::new(gpu::DeviceFeatures::BASE).unwrap();
+
+    for ssa in [false, true] {
+        let lib = spv::Builder::new();
+        let scale = lib.func::<spv::Float, _>(Some("scale"), || {
+            let x = lib.arg::<spv::Float>();
+            let factor = lib.arg::<spv::Float>();
+            lib.ret_value(x * factor);
+        });
+
+        let b = spv::Builder::new();
+        b.ssa(ssa);
+        let import = b.import(&lib);
+        let scale = import.func(&scale);
+
+        let data = b.storage::<spv::Float>(0, 0, Some("data"));
+        let id = b.global_invocation_id();
+
+        b.entry(spv::Stage::Compute, "main", || {
+            let i = spv::Int::from(id.load().x());
+            let x = data.load_element(i);
+            let factor = b.const_float(3.0);
+            let y = scale.call([&x as &dyn spv::AsType, &factor]);
+            data.store_element(i, y);
+        });
+
+        let result = harness.run(&b, &[1.0f32, -2.0, 0.5], [3, 1, 1]).unwrap();
+        assert_eq!(result, [3.0, -6.0, 1.5]);
+    }
+}
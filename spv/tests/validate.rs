@@ -0,0 +1,58 @@
+//! Modules that have to pass spirv-val, compiling panics if validation fails
+#![cfg(feature = "validate")]
+
+#[test]
+fn non_uniform_ssa() {
+    for ssa in [false, true] {
+        let b = spv::Builder::new();
+        b.ssa(ssa);
+
+        let in_uv = b.input::<spv::IOVec2>(0, false, Some("in_uv"));
+        let in_material = b.input::<spv::IOUInt>(1, true, Some("in_material"));
+        let out_color = b.output::<spv::IOVec4>(0, false, Some("out_color"));
+
+        let textures = b.sampled_texture_array::<spv::D2>(0, 0, 4, Some("u_textures"));
+
+        b.entry(spv::Stage::Fragment, "main", || {
+            let texture = textures.index_non_uniform(in_material.load());
+            out_color.store(spv::sample(&texture, in_uv.load()));
+        });
+
+        b.compile();
+    }
+}
+
+#[test]
+fn import_call() {
+    for ssa in [false, true] {
+        let lib = spv::Builder::new();
+        let scale = lib.func::<spv::Float, _>(Some("scale"), || {
+            let x = lib.arg::<spv::Float>();
+            let factor = lib.arg::<spv::Float>();
+            lib.ret_value(x * factor);
+        });
+        let noop = lib.func::<spv::Void, _>(Some("noop"), || {
+            lib.ret();
+        });
+
+        let b = spv::Builder::new();
+        b.ssa(ssa);
+        let import = b.import(&lib);
+        let scale = import.func(&scale);
+        let noop = import.func(&noop);
+
+        let data = b.storage::<spv::Float>(0, 0, Some("data"));
+        let id = b.global_invocation_id();
+
+        b.entry(spv::Stage::Compute, "main", || {
+            let i = spv::Int::from(id.load().x());
+            let x = data.load_element(i);
+            let factor = b.const_float(2.0);
+            let y = scale.call([&x as &dyn spv::AsType, &factor]);
+            noop.call([]);
+            data.store_element(i, y);
+        });
+
+        b.compile();
+    }
+}